@@ -345,6 +345,27 @@ WRAP
   echo "$wrapper"
 }
 
+# 可选：把 server/python 打成 runtime/python.zip，首次启动时由桌面壳解压到数据目录，
+# 避免杀毒软件每次启动都扫描数千个小文件。通过 PTNEXUS_PYTHON_ZIP=1 启用。
+pack_python_zip() {
+  local py_root="$RUNTIME_DIR/server/python"
+  local zip_path="$RUNTIME_DIR/python.zip"
+
+  rm -f "$zip_path" "$zip_path.sha256"
+  python3 - <<PY
+import zipfile
+from pathlib import Path
+root = Path(r"$py_root")
+with zipfile.ZipFile(r"$zip_path", "w", zipfile.ZIP_DEFLATED) as z:
+    for path in sorted(root.rglob("*")):
+        if path.is_file():
+            z.write(path, path.relative_to(root).as_posix())
+PY
+
+  (cd "$RUNTIME_DIR" && sha256sum python.zip > python.zip.sha256)
+  rm -rf "$py_root"
+}
+
 build_runtime() {
   log "清理并重建 runtime"
   rm -rf "$RUNTIME_DIR"
@@ -388,6 +409,11 @@ build_runtime() {
   log "准备 Windows 媒体工具 (mpv/ffmpeg)"
   prepare_windows_media_tools
 
+  if [[ "${PTNEXUS_PYTHON_ZIP:-0}" == "1" ]]; then
+    log "打包 Python 运行时为 python.zip"
+    pack_python_zip
+  fi
+
   log "准备版本文件"
   cp "$ROOT_DIR/CHANGELOG.json" "$DESKTOP_DIR/CHANGELOG.json"
 }
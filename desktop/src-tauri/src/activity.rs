@@ -0,0 +1,201 @@
+//! 等待服务就绪超时时，判断进程是仍在初始化还是已经卡住。
+//!
+//! 超时前一段时间与超时时各采样一次进程的 CPU 时间和 stderr 日志大小：CPU 时间在增加或日志在增长，
+//! 说明服务仍在工作，只是需要更长时间（如首次启动时迁移大数据库），提示调大超时；两者都没有变化则很可能
+//! 卡在了某处（等待网络、死锁），错误信息附上 stderr 末尾帮助定位。读不到 CPU 时间且日志没有变化时无法判断。
+
+use std::path::Path;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::process::Command;
+use std::time::Duration;
+
+/// 超时前多久开始观察。
+const WINDOW: Duration = Duration::from_secs(10);
+/// CPU 时间增加不超过该值视为空闲（调度与 GC 的零星开销）。
+const CPU_IDLE_THRESHOLD: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// 进程累计的 CPU 时间，无法读取时为 None。
+    pub cpu: Option<Duration>,
+    pub stderr_len: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Activity {
+    /// CPU 时间或日志在增长。
+    Busy {
+        cpu: Option<Duration>,
+        log_grew: bool,
+    },
+    /// 没有任何活动。
+    Idle,
+    Unknown,
+}
+
+/// 观察的时长：超时前 10 秒，超时较短时取其一半，避开进程刚启动时的开销。
+pub fn window(timeout: Duration) -> Duration {
+    WINDOW.min(timeout / 2)
+}
+
+pub fn sample(pid: u32, stderr_log: &Path) -> Sample {
+    Sample {
+        cpu: cpu_time(pid),
+        stderr_len: std::fs::metadata(stderr_log)
+            .map(|meta| meta.len())
+            .unwrap_or(0),
+    }
+}
+
+pub fn classify(earlier: Sample, latest: Sample) -> Activity {
+    let cpu = match (earlier.cpu, latest.cpu) {
+        (Some(before), Some(after)) => Some(after.saturating_sub(before)),
+        _ => None,
+    };
+    let cpu_busy = cpu.is_some_and(|spent| spent > CPU_IDLE_THRESHOLD);
+    let log_grew = latest.stderr_len > earlier.stderr_len;
+    if cpu_busy || log_grew {
+        Activity::Busy { cpu, log_grew }
+    } else if cpu.is_some() {
+        Activity::Idle
+    } else {
+        Activity::Unknown
+    }
+}
+
+impl Activity {
+    /// 用于错误信息的活动描述，如“CPU 时间增加 3.2 秒，stderr 日志在增长”。
+    pub fn describe(self) -> String {
+        match self {
+            Self::Busy { cpu, log_grew } => {
+                let mut parts = Vec::new();
+                if let Some(cpu) = cpu.filter(|spent| *spent > CPU_IDLE_THRESHOLD) {
+                    parts.push(format!("CPU 时间增加 {:.1} 秒", cpu.as_secs_f64()));
+                }
+                if log_grew {
+                    parts.push("stderr 日志在增长".to_string());
+                }
+                parts.join("，")
+            }
+            Self::Idle => "CPU 时间与日志都没有变化".to_string(),
+            Self::Unknown => "无法读取进程的 CPU 时间".to_string(),
+        }
+    }
+}
+
+/// Linux：/proc/<pid>/stat 的 utime + stime，单位为时钟滴答（USER_HZ，固定为 100）。
+#[cfg(target_os = "linux")]
+fn cpu_time(pid: u32) -> Option<Duration> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    parse_proc_stat(&stat)
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn parse_proc_stat(stat: &str) -> Option<Duration> {
+    // 进程名可能含空格和括号，从最后一个右括号之后开始按空格切分；utime、stime 是第 14、15 个字段
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(Duration::from_millis((utime + stime) * 10))
+}
+
+/// macOS：`ps -o cputime=` 输出形如 `1:02.35` 或 `1:00:02.35`。
+#[cfg(target_os = "macos")]
+fn cpu_time(pid: u32) -> Option<Duration> {
+    let output = Command::new("ps")
+        .args(["-o", "cputime=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    parse_clock(String::from_utf8_lossy(&output.stdout).trim())
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn parse_clock(value: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    for part in value.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+/// Windows：通过 PowerShell 读取 TotalProcessorTime（毫秒）。只在超时前后各调用一次。
+#[cfg(target_os = "windows")]
+fn cpu_time(pid: u32) -> Option<Duration> {
+    use std::os::windows::process::CommandExt;
+
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command"])
+        .arg(format!(
+            "[int64](Get-Process -Id {pid}).TotalProcessorTime.TotalMilliseconds"
+        ))
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    let millis = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(Duration::from_millis(millis))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn cpu_time(_pid: u32) -> Option<Duration> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(cpu_ms: Option<u64>, stderr_len: u64) -> Sample {
+        Sample {
+            cpu: cpu_ms.map(Duration::from_millis),
+            stderr_len,
+        }
+    }
+
+    #[test]
+    fn samples_are_classified_by_cpu_and_log_growth() {
+        // 迁移数据库：CPU 在跑，日志没动
+        let busy = classify(at(Some(1_000), 500), at(Some(4_200), 500));
+        assert_eq!(
+            busy,
+            Activity::Busy {
+                cpu: Some(Duration::from_millis(3_200)),
+                log_grew: false
+            }
+        );
+        assert_eq!(busy.describe(), "CPU 时间增加 3.2 秒");
+        // 等待网络但在打印重试日志
+        assert!(matches!(
+            classify(at(Some(1_000), 500), at(Some(1_020), 800)),
+            Activity::Busy { log_grew: true, .. }
+        ));
+        // 零星开销不算活动
+        assert_eq!(
+            classify(at(Some(1_000), 500), at(Some(1_050), 500)),
+            Activity::Idle
+        );
+        // 读不到 CPU 时间时只看日志
+        assert!(matches!(
+            classify(at(None, 0), at(None, 10)),
+            Activity::Busy { cpu: None, .. }
+        ));
+        assert_eq!(classify(at(None, 10), at(Some(900), 10)), Activity::Unknown);
+        // 日志被轮转变小也不算增长
+        assert_eq!(classify(at(Some(0), 900), at(Some(0), 100)), Activity::Idle);
+    }
+
+    #[test]
+    fn cpu_time_formats_are_parsed() {
+        let stat =
+            "1234 (python (server)) S 1 1234 1234 0 -1 4194560 2000 0 0 0 250 130 0 0 20 0 4 0 100";
+        assert_eq!(parse_proc_stat(stat), Some(Duration::from_millis(3_800)));
+        assert_eq!(parse_proc_stat("garbage"), None);
+
+        assert_eq!(parse_clock("0:02.50"), Some(Duration::from_millis(2_500)));
+        assert_eq!(parse_clock("1:00:02.00"), Some(Duration::from_secs(3_602)));
+        assert_eq!(parse_clock(""), None);
+    }
+}
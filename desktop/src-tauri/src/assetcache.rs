@@ -0,0 +1,379 @@
+//! 经桌面壳下载并缓存站点图标等外部小图片。
+//!
+//! WebUI 在 WebView 中直接加载站点的图标时，请求从本机 IP 直接发往站点：即使后端访问站点走了代理，
+//! 站点仍能看到用户的真实地址，离线时图片也全部加载失败。`fetch_external_asset(url)` 改由桌面壳下载，
+//! 返回 `data:` URL，WebUI 直接用作 `<img>` 的 src。
+//!
+//! 下载使用系统的 curl（Windows 10 起自带），代理与根证书取自服务的运行环境：runtime.env 中的
+//! `HTTPS_PROXY` / `HTTP_PROXY` / `ALL_PROXY` / `NO_PROXY`，以及企业根证书合并后的 `REQUESTS_CA_BUNDLE`
+//! （见 cabundle.rs）。只接受不超过 512 KB 的常见图片格式，同时最多 4 个下载。结果按 URL 的哈希缓存在
+//! `<data_dir>/asset-cache/`，总大小超过上限时删除最久未使用的文件；命中缓存时不访问网络。
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Condvar, Mutex};
+use std::time::SystemTime;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::datadir;
+use crate::runtime::RuntimeManager;
+
+const CACHE_DIR: &str = "asset-cache";
+const MAX_ASSET_BYTES: u64 = 512 * 1024;
+const CACHE_CAP_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_CONCURRENT_FETCHES: usize = 4;
+const FETCH_TIMEOUT_SECS: &str = "15";
+/// 运行环境中传给 curl 的代理设置。
+const PROXY_KEYS: [&str; 8] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+    "NO_PROXY",
+    "no_proxy",
+];
+/// 接受的图片类型与缓存文件的扩展名；同一扩展名以第一条的类型返回。
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+    ("image/avif", "avif"),
+    ("image/bmp", "bmp"),
+    ("image/x-icon", "ico"),
+    ("image/vnd.microsoft.icon", "ico"),
+    ("image/svg+xml", "svg"),
+];
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+static FETCH_SLOTS: Semaphore = Semaphore::new(MAX_CONCURRENT_FETCHES);
+
+struct Semaphore {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+struct Permit<'a>(&'a Semaphore);
+
+impl Semaphore {
+    const fn new(permits: usize) -> Self {
+        Self {
+            available: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+
+    fn acquire(&self) -> Permit<'_> {
+        let mut available = self.available.lock().unwrap_or_else(|e| e.into_inner());
+        while *available == 0 {
+            available = self
+                .released
+                .wait(available)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        *available -= 1;
+        Permit(self)
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.0.available.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+        self.0.released.notify_one();
+    }
+}
+
+/// 返回图片的 `data:` URL；缓存中有时直接读取，否则下载后写入缓存。
+pub fn fetch(app: &AppHandle, url: &str) -> Result<String, String> {
+    let url = validate_url(url)?;
+    let dir = datadir::active_profile(app)?.root.join(CACHE_DIR);
+    let key = cache_key(url);
+    if let Some((mime, bytes)) = lookup(&dir, &key) {
+        return Ok(data_url(mime, &bytes));
+    }
+
+    let _permit = FETCH_SLOTS.acquire();
+    // 排队期间同一地址可能已被其他请求下载
+    if let Some((mime, bytes)) = lookup(&dir, &key) {
+        return Ok(data_url(mime, &bytes));
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("创建 {} 失败: {e}", dir.display()))?;
+    let (mime, bytes) = download(url, &dir, &key, &network_env(app))?;
+    evict(&dir, CACHE_CAP_BYTES);
+    Ok(data_url(mime, &bytes))
+}
+
+fn validate_url(url: &str) -> Result<&str, String> {
+    let url = url.trim();
+    let scheme_ok = ["http://", "https://"]
+        .iter()
+        .any(|scheme| url.len() > scheme.len() && url[..scheme.len()].eq_ignore_ascii_case(scheme));
+    if !scheme_ok || url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("不支持的地址: {url}"));
+    }
+    Ok(url)
+}
+
+fn cache_key(url: &str) -> String {
+    format!("{:x}", Sha256::digest(url.as_bytes()))
+}
+
+/// 读取缓存并刷新修改时间，淘汰时按修改时间判断最近使用。
+fn lookup(dir: &Path, key: &str) -> Option<(&'static str, Vec<u8>)> {
+    IMAGE_TYPES.iter().find_map(|(mime, ext)| {
+        let path = dir.join(format!("{key}.{ext}"));
+        let bytes = fs::read(&path).ok()?;
+        if let Ok(file) = File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some((*mime, bytes))
+    })
+}
+
+/// 代理与根证书取自服务的运行环境；服务尚未启动时 curl 使用桌面壳自己的环境变量。
+pub fn network_env(app: &AppHandle) -> HashMap<String, String> {
+    let Some(common_env) = app
+        .try_state::<RuntimeManager>()
+        .and_then(|rt| rt.context())
+        .map(|ctx| ctx.common_env)
+    else {
+        return HashMap::new();
+    };
+    let mut env: HashMap<String, String> = PROXY_KEYS
+        .iter()
+        .filter_map(|key| Some((key.to_string(), common_env.get(*key)?.clone())))
+        .collect();
+    if let Some(bundle) = common_env
+        .get("REQUESTS_CA_BUNDLE")
+        .or_else(|| common_env.get("SSL_CERT_FILE"))
+        .filter(|value| !value.trim().is_empty())
+    {
+        env.insert("CURL_CA_BUNDLE".to_string(), bundle.clone());
+    }
+    env
+}
+
+/// 下载到临时文件，检查状态码、类型与大小后改名为缓存文件。
+fn download(
+    url: &str,
+    dir: &Path,
+    key: &str,
+    env: &HashMap<String, String>,
+) -> Result<(&'static str, Vec<u8>), String> {
+    let part = dir.join(format!("{key}.part-{}", std::process::id()));
+    let result = run_curl(url, &part, env).and_then(|(status, content_type)| {
+        if status != 200 {
+            return Err(format!("下载 {url} 失败: HTTP {status}"));
+        }
+        let (mime, ext) = image_type(&content_type)
+            .ok_or_else(|| format!("{url} 不是支持的图片类型（{content_type}）"))?;
+        let size = fs::metadata(&part).map(|meta| meta.len()).unwrap_or(0);
+        if size > MAX_ASSET_BYTES {
+            return Err(format!("{url} 超过 {} KB", MAX_ASSET_BYTES / 1024));
+        }
+        let bytes = fs::read(&part).map_err(|e| format!("读取下载的文件失败: {e}"))?;
+        let target = cache_path(dir, key, ext);
+        fs::rename(&part, &target).map_err(|e| format!("写入 {} 失败: {e}", target.display()))?;
+        Ok((mime, bytes))
+    });
+    let _ = fs::remove_file(&part);
+    result
+}
+
+/// 运行 curl，返回状态码与 Content-Type。
+fn run_curl(
+    url: &str,
+    output: &Path,
+    env: &HashMap<String, String>,
+) -> Result<(u16, String), String> {
+    let mut command = Command::new("curl");
+    command
+        .args([
+            "--silent",
+            "--show-error",
+            "--location",
+            "--max-redirs",
+            "3",
+        ])
+        .args(["--proto", "=http,https", "--proto-redir", "=http,https"])
+        .args(["--max-filesize", &MAX_ASSET_BYTES.to_string()])
+        .args(["--max-time", FETCH_TIMEOUT_SECS])
+        .args([
+            "--user-agent",
+            concat!("PT-Nexus-Desktop/", env!("CARGO_PKG_VERSION")),
+        ])
+        .args(["--write-out", "%{http_code} %{content_type}"])
+        .arg("--output")
+        .arg(output)
+        .arg("--")
+        .arg(url)
+        .envs(env);
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW);
+    let result = command.output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => "未找到 curl，无法下载外部图片".to_string(),
+        _ => format!("启动 curl 失败: {e}"),
+    })?;
+    if !result.status.success() {
+        // 63：文件大小超过 --max-filesize
+        if result.status.code() == Some(63) {
+            return Err(format!("{url} 超过 {} KB", MAX_ASSET_BYTES / 1024));
+        }
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("下载 {url} 失败: {}", stderr.trim()));
+    }
+    let written = String::from_utf8_lossy(&result.stdout);
+    let (status, content_type) = written
+        .trim()
+        .split_once(' ')
+        .unwrap_or((written.trim(), ""));
+    let status = status
+        .parse()
+        .map_err(|_| format!("下载 {url} 失败: 无法识别的响应"))?;
+    Ok((status, content_type.to_string()))
+}
+
+fn image_type(content_type: &str) -> Option<(&'static str, &'static str)> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    let ext = IMAGE_TYPES.iter().find(|(known, _)| *known == mime)?.1;
+    // 同一扩展名统一以第一条的类型返回，与读取缓存时一致
+    IMAGE_TYPES.iter().find(|(_, known)| *known == ext).copied()
+}
+
+fn cache_path(dir: &Path, key: &str, ext: &str) -> PathBuf {
+    dir.join(format!("{key}.{ext}"))
+}
+
+/// 总大小超过上限时，从最久未使用的文件开始删除。
+fn evict(dir: &Path, cap: u64) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_name().to_string_lossy().contains(".part-"))
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok().filter(|meta| meta.is_file())?;
+            Some((meta.modified().ok()?, meta.len(), entry.path()))
+        })
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    files.sort();
+    for (_, size, path) in files {
+        if total <= cap {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= size;
+        }
+    }
+}
+
+fn data_url(mime: &str, bytes: &[u8]) -> String {
+    format!("data:{mime};base64,{}", base64(bytes))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn only_whitelisted_images_are_accepted() {
+        assert_eq!(
+            image_type("image/PNG; charset=binary"),
+            Some(("image/png", "png"))
+        );
+        assert_eq!(
+            image_type("image/vnd.microsoft.icon"),
+            Some(("image/x-icon", "ico"))
+        );
+        assert_eq!(image_type("text/html"), None);
+        assert_eq!(image_type(""), None);
+        assert!(validate_url("https://tracker.example/favicon.ico").is_ok());
+        assert!(validate_url("file:///etc/passwd").is_err());
+        assert!(validate_url("https://a.example/x y").is_err());
+        assert_eq!(base64(b"PT Nexus"), "UFQgTmV4dXM=");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b""), "");
+    }
+
+    #[test]
+    fn cache_hits_are_served_and_least_recently_used_are_evicted() {
+        let dir = temp_dir("lru");
+        let old = cache_key("https://a.example/old.png");
+        let used = cache_key("https://a.example/used.png");
+        fs::write(cache_path(&dir, &old, "png"), vec![1u8; 60]).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(cache_path(&dir, &used, "ico"), vec![2u8; 60]).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+
+        // 读取旧文件使其成为最近使用的一个
+        assert_eq!(
+            lookup(&dir, &old).map(|(mime, bytes)| (mime, bytes.len())),
+            Some(("image/png", 60))
+        );
+        assert!(lookup(&dir, &cache_key("https://a.example/missing.png")).is_none());
+
+        evict(&dir, 100);
+        assert!(cache_path(&dir, &old, "png").exists());
+        assert!(!cache_path(&dir, &used, "ico").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn semaphore_bounds_concurrency() {
+        let semaphore = Arc::new(Semaphore::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let workers: Vec<_> = (0..6)
+            .map(|_| {
+                let (semaphore, running, peak) = (semaphore.clone(), running.clone(), peak.clone());
+                std::thread::spawn(move || {
+                    let _permit = semaphore.acquire();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+}
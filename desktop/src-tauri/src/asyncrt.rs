@@ -0,0 +1,105 @@
+//! 桌面壳共用的异步运行时。
+//!
+//! 健康检查、更新检查、Webhook 等访问 HTTP 的功能原本各自启动线程，大部分时间阻塞在 sleep 或 socket 上。
+//! setup 中创建一个 tokio 运行时（[`WORKER_THREADS`] 个工作线程）托管在 Tauri state 中，[`spawn_tracked`]
+//! 在其上运行异步任务，并像线程一样登记到任务表（见 tasks.rs）：同名任务再次启动时取消旧的，重启服务、
+//! 退出应用时按 Scope 取消并等待退出。任务在等待中用 `CancelToken::sleep_async` 检查取消标记，
+//! 不要在任务中长时间阻塞，否则会占住工作线程。
+//!
+//! 拿不到 AppHandle 的模块通过 [`spawn`] 使用同一个运行时：setup 中 [`install`] 的 [`Spawner`] 转交给
+//! [`spawn_tracked`]；未安装时（测试、启动早期）改用按需创建的备用运行时，不登记。
+//! 目前服务启动的就绪等待已在这里运行（见 services.rs），其余功能逐步迁移。
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::OnceLock;
+
+use tauri::{AppHandle, Manager};
+use tokio::runtime::{Builder, Handle, Runtime};
+
+use crate::tasks::{CancelToken, Scope, TaskRegistry};
+
+pub const WORKER_THREADS: usize = 2;
+
+pub type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+/// 启动异步任务：任务名和生成任务的函数。
+pub type Spawner =
+    Box<dyn Fn(String, Box<dyn FnOnce(CancelToken) -> BoxFuture + Send>) + Send + Sync>;
+
+pub struct AsyncRuntime(Runtime);
+
+impl AsyncRuntime {
+    pub fn handle(&self) -> &Handle {
+        self.0.handle()
+    }
+}
+
+static SPAWNER: OnceLock<Spawner> = OnceLock::new();
+static FALLBACK: OnceLock<Runtime> = OnceLock::new();
+
+fn build(thread_name: &str, workers: usize) -> std::io::Result<Runtime> {
+    Builder::new_multi_thread()
+        .worker_threads(workers)
+        .thread_name(thread_name)
+        .enable_all()
+        .build()
+}
+
+/// 在 setup 中任务表之后调用。
+pub fn init(app: &tauri::App) -> Result<(), String> {
+    let runtime =
+        build("ptnexus-async", WORKER_THREADS).map_err(|e| format!("创建异步运行时失败: {e}"))?;
+    app.manage(AsyncRuntime(runtime));
+    Ok(())
+}
+
+pub fn install(spawner: Spawner) {
+    let _ = SPAWNER.set(spawner);
+}
+
+/// 在共用运行时上启动并登记异步任务，任务拿到一份 AppHandle；与 `tasks::spawn` 对应。
+/// 运行时或任务表尚未创建时在备用运行时上运行，不登记。
+pub fn spawn_tracked<F, Fut>(app: &AppHandle, name: &str, scope: Scope, run: F) -> CancelToken
+where
+    F: FnOnce(AppHandle, CancelToken) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let handle = app.clone();
+    let run = move |token| run(handle, token);
+    match (
+        app.try_state::<AsyncRuntime>(),
+        app.try_state::<TaskRegistry>(),
+    ) {
+        (Some(runtime), Some(registry)) => registry.spawn_async(runtime.handle(), name, scope, run),
+        _ => spawn_detached(run),
+    }
+}
+
+/// 通过安装的 [`Spawner`] 启动异步任务；未安装时在备用运行时上运行。
+pub fn spawn<F, Fut>(name: &str, run: F)
+where
+    F: FnOnce(CancelToken) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    match SPAWNER.get() {
+        Some(spawner) => spawner(
+            name.to_string(),
+            Box::new(move |token| Box::pin(run(token))),
+        ),
+        None => {
+            spawn_detached(run);
+        }
+    }
+}
+
+fn spawn_detached<F, Fut>(run: F) -> CancelToken
+where
+    F: FnOnce(CancelToken) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let token = CancelToken::default();
+    let runtime = FALLBACK
+        .get_or_init(|| build("ptnexus-async-fallback", 1).expect("创建备用异步运行时失败"));
+    runtime.spawn(run(token.clone()));
+    token
+}
@@ -0,0 +1,104 @@
+//! 任务栏 / Dock 图标角标。
+//!
+//! 角标数字 = 不可用的服务数 + 未查看的后台任务失败数。服务数由健康监测维护，恢复后自动清除；
+//! 任务失败数由前端通过 `set_badge` 上报，窗口获得焦点即视为已查看。
+//! Windows 使用任务栏叠加图标（只能显示错误标记），macOS 使用 Dock 数字角标，Linux 不处理。
+
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager};
+
+#[derive(Default)]
+pub struct BadgeState {
+    counts: Mutex<BadgeCounts>,
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+struct BadgeCounts {
+    down_services: u32,
+    failed_tasks: u32,
+}
+
+impl BadgeCounts {
+    fn total(self) -> Option<u32> {
+        Some(self.down_services.saturating_add(self.failed_tasks)).filter(|count| *count > 0)
+    }
+}
+
+/// 健康监测调用：更新不可用服务数。
+pub fn set_down_services(app: &AppHandle, count: u32) {
+    update(app, |counts| counts.down_services = count);
+}
+
+/// 前端调用：设置未查看的任务失败数，None 表示清除。
+pub fn set_failed_tasks(app: &AppHandle, count: Option<u32>) {
+    if count.unwrap_or(0) > 0 {
+        crate::power::wake(app);
+    }
+    update(app, |counts| counts.failed_tasks = count.unwrap_or(0));
+}
+
+/// 主窗口获得焦点：任务失败视为已查看，服务故障仍保留到恢复为止。
+pub fn on_focus(app: &AppHandle) {
+    update(app, |counts| counts.failed_tasks = 0);
+}
+
+fn update(app: &AppHandle, change: impl FnOnce(&mut BadgeCounts)) {
+    let Some(state) = app.try_state::<BadgeState>() else {
+        return;
+    };
+    let total = {
+        let Ok(mut counts) = state.counts.lock() else {
+            return;
+        };
+        let before = *counts;
+        change(&mut counts);
+        if *counts == before {
+            return;
+        }
+        counts.total()
+    };
+    apply(app, total);
+}
+
+#[cfg(target_os = "windows")]
+fn apply(app: &AppHandle, count: Option<u32>) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let icon = count.map(|_| error_glyph());
+    let _ = window.set_overlay_icon(icon);
+}
+
+#[cfg(target_os = "macos")]
+fn apply(app: &AppHandle, count: Option<u32>) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let _ = window.set_badge_count(count.map(i64::from));
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn apply(_app: &AppHandle, _count: Option<u32>) {}
+
+/// 16x16 红色圆点，作为 Windows 任务栏叠加图标。
+#[cfg(target_os = "windows")]
+fn error_glyph() -> tauri::image::Image<'static> {
+    const SIZE: u32 = 16;
+    let center = (SIZE as f32 - 1.0) / 2.0;
+    let radius = SIZE as f32 / 2.0;
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let dx = x as f32 - center;
+            let dy = y as f32 - center;
+            let inside = dx * dx + dy * dy <= radius * radius;
+            rgba.extend_from_slice(if inside {
+                &[0xE0, 0x2E, 0x2E, 0xFF]
+            } else {
+                &[0, 0, 0, 0]
+            });
+        }
+    }
+    tauri::image::Image::new_owned(rgba, SIZE, SIZE)
+}
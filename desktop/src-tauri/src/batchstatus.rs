@@ -0,0 +1,376 @@
+//! batch 服务任务队列的摘要。
+//!
+//! 休眠、内存回收、更新后重启等功能都要知道 batch 是否有任务在执行、执行的是什么，统一由这里向 batch 的
+//! `GET /status` 查询，不再各自请求。batch 返回的 JSON 中读取以下字段，缺少的按 0 / 空处理：
+//! `active_jobs`、`queue_length`、`current_item`、`progress`（0–100），以及旧版就有的
+//! `running` / `active` / `busy` 标记和 `status` 文本。
+//!
+//! 有订阅者（托盘提示、界面调用 `watch_batch_summary`）时在共用异步运行时上每 10 秒查询一次，摘要变化时
+//! 发出 `batch-summary` 事件；没有订阅者时不查询。内部调用 [`summary`] / [`is_busy`] 时缓存超过 5 秒
+//! 则当场查询。旧版 batch 没有 `/status`（返回 404）时摘要的 `capability` 为 `unsupported`，视为空闲，
+//! 10 分钟后再探测一次。
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::events::{self, Replay};
+use crate::localhttp::{self, Response};
+use crate::tasks::Scope;
+use crate::{asyncrt, health, status};
+
+const STATUS_PATH: &str = "/status";
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// 内部调用时，缓存在这段时间内直接使用。
+const FRESH_FOR: Duration = Duration::from_secs(5);
+/// batch 不支持 `/status` 时，隔多久再探测（更新后可能已支持）。
+const UNSUPPORTED_RECHECK: Duration = Duration::from_secs(10 * 60);
+const STATUS_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// 尚未查询成功过。
+    #[default]
+    Unknown,
+    Supported,
+    /// 旧版 batch 没有 `/status`。
+    Unsupported,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct BatchSummary {
+    pub capability: Capability,
+    /// 最近一次查询是否连上了 batch。
+    pub reachable: bool,
+    pub busy: bool,
+    pub active_jobs: u64,
+    pub queue_length: u64,
+    pub current_item: Option<String>,
+    /// 当前任务的进度（0–100），batch 未报告时为 None。
+    pub progress: Option<f64>,
+}
+
+impl BatchSummary {
+    /// 托盘提示中附加的说明，如 “batch 1 个任务 45%，排队 3”；空闲时为 None。
+    pub fn tooltip_suffix(&self) -> Option<String> {
+        if !self.busy {
+            return None;
+        }
+        let mut text = format!("batch {} 个任务", self.active_jobs.max(1));
+        if let Some(progress) = self.progress {
+            text.push_str(&format!(" {progress:.0}%"));
+        }
+        if self.queue_length > 0 {
+            text.push_str(&format!("，排队 {}", self.queue_length));
+        }
+        Some(text)
+    }
+}
+
+#[derive(Default)]
+pub struct BatchStatusCache {
+    cache: Mutex<Cache>,
+    subscribers: Mutex<HashSet<u64>>,
+    next_id: AtomicU64,
+}
+
+#[derive(Clone, Default)]
+struct Cache {
+    summary: BatchSummary,
+    fetched_at: Option<Instant>,
+}
+
+impl Cache {
+    fn is_fresh(&self, now: Instant) -> bool {
+        let max_age = if self.summary.capability == Capability::Unsupported {
+            UNSUPPORTED_RECHECK
+        } else {
+            FRESH_FOR
+        };
+        self.fetched_at
+            .is_some_and(|at| now.duration_since(at) < max_age)
+    }
+}
+
+/// 订阅期间保持轮询，drop 时取消订阅。
+pub struct Subscription {
+    app: AppHandle,
+    id: u64,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        unwatch(&self.app, self.id);
+    }
+}
+
+pub fn subscribe(app: &AppHandle) -> Subscription {
+    Subscription {
+        app: app.clone(),
+        id: watch(app),
+    }
+}
+
+/// 登记一个订阅者，返回订阅 ID；第一个订阅者出现时开始轮询。
+pub fn watch(app: &AppHandle) -> u64 {
+    let Some(state) = app.try_state::<BatchStatusCache>() else {
+        return 0;
+    };
+    let id = state.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+    let first = match state.subscribers.lock() {
+        Ok(mut subscribers) => {
+            subscribers.insert(id);
+            subscribers.len() == 1
+        }
+        Err(_) => false,
+    };
+    if first {
+        start_polling(app);
+    }
+    id
+}
+
+/// 取消订阅；最后一个订阅者取消后，轮询在下一轮结束。
+pub fn unwatch(app: &AppHandle, id: u64) {
+    if let Some(state) = app.try_state::<BatchStatusCache>() {
+        if let Ok(mut subscribers) = state.subscribers.lock() {
+            subscribers.remove(&id);
+        }
+    }
+}
+
+fn has_subscribers(app: &AppHandle) -> bool {
+    app.try_state::<BatchStatusCache>()
+        .and_then(|state| state.subscribers.lock().ok().map(|s| !s.is_empty()))
+        .unwrap_or(false)
+}
+
+fn start_polling(app: &AppHandle) {
+    asyncrt::spawn_tracked(app, "batch-status", Scope::Shell, |app, token| async move {
+        while has_subscribers(&app) {
+            if status::is_running(&app) && !cached(&app).is_some_and(|c| c.is_fresh(Instant::now()))
+            {
+                if let Some(port) = batch_port(&app) {
+                    let probe =
+                        interpret(localhttp::fetch_async("127.0.0.1", port, STATUS_PATH).await);
+                    store(&app, probe);
+                }
+            }
+            if !token.sleep_async(POLL_INTERVAL).await {
+                return;
+            }
+        }
+    });
+}
+
+/// 当前摘要；缓存过期时当场查询（阻塞，最多约 3 秒）。
+pub fn summary(app: &AppHandle) -> BatchSummary {
+    if let Some(cache) = cached(app).filter(|c| c.is_fresh(Instant::now())) {
+        return cache.summary;
+    }
+    let Some(port) = batch_port(app) else {
+        return BatchSummary::default();
+    };
+    let probe = interpret(localhttp::fetch(
+        "127.0.0.1",
+        port,
+        STATUS_PATH,
+        STATUS_TIMEOUT,
+    ));
+    store(app, probe)
+}
+
+/// 最近一次查询的结果，不发起查询；供订阅者读取。
+pub fn latest(app: &AppHandle) -> BatchSummary {
+    cached(app).map(|cache| cache.summary).unwrap_or_default()
+}
+
+/// batch 报告有任务在执行。查询失败或不支持时视为空闲，服务已不可用时无需保护。
+pub fn is_busy(app: &AppHandle) -> bool {
+    summary(app).busy
+}
+
+fn batch_port(app: &AppHandle) -> Option<u16> {
+    health::service_ports(app)
+        .into_iter()
+        .find(|(name, _)| *name == "batch")
+        .map(|(_, port)| port)
+}
+
+fn cached(app: &AppHandle) -> Option<Cache> {
+    let state = app.try_state::<BatchStatusCache>()?;
+    let cache = state.cache.lock().ok()?;
+    Some(cache.clone())
+}
+
+enum Probe {
+    Status(Value),
+    Unsupported,
+    Unreachable,
+}
+
+fn interpret(response: Result<Response, String>) -> Probe {
+    match response {
+        Ok(response) if response.status == 200 => serde_json::from_slice(&response.body)
+            .map(Probe::Status)
+            .unwrap_or(Probe::Unreachable),
+        Ok(response) if response.status == 404 => Probe::Unsupported,
+        _ => Probe::Unreachable,
+    }
+}
+
+/// 写入缓存，摘要变化时发出 `batch-summary` 事件。
+fn store(app: &AppHandle, probe: Probe) -> BatchSummary {
+    let Some(state) = app.try_state::<BatchStatusCache>() else {
+        return next_summary(&BatchSummary::default(), probe);
+    };
+    let (summary, changed) = match state.cache.lock() {
+        Ok(mut cache) => {
+            let summary = next_summary(&cache.summary, probe);
+            let changed = summary != cache.summary;
+            cache.summary = summary.clone();
+            cache.fetched_at = Some(Instant::now());
+            (summary, changed)
+        }
+        Err(_) => return BatchSummary::default(),
+    };
+    if changed {
+        events::emit(app, "batch-summary", Replay::Latest, &summary);
+    }
+    summary
+}
+
+fn next_summary(previous: &BatchSummary, probe: Probe) -> BatchSummary {
+    match probe {
+        Probe::Status(status) => summarize(&status),
+        Probe::Unsupported => BatchSummary {
+            capability: Capability::Unsupported,
+            reachable: true,
+            ..BatchSummary::default()
+        },
+        // 连不上时保留已知的能力，任务信息清空
+        Probe::Unreachable => BatchSummary {
+            capability: previous.capability,
+            ..BatchSummary::default()
+        },
+    }
+}
+
+fn summarize(status: &Value) -> BatchSummary {
+    let count = |key: &str| status.get(key).and_then(Value::as_u64).unwrap_or(0);
+    let active_jobs = count("active_jobs");
+    let current_item = status
+        .get("current_item")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string);
+    BatchSummary {
+        capability: Capability::Supported,
+        reachable: true,
+        busy: active_jobs > 0 || reports_active_job(status),
+        active_jobs,
+        queue_length: count("queue_length"),
+        current_item,
+        progress: status
+            .get("progress")
+            .and_then(Value::as_f64)
+            .map(|progress| progress.clamp(0.0, 100.0)),
+    }
+}
+
+fn reports_active_job(status: &Value) -> bool {
+    let flag = |key: &str| status.get(key).and_then(Value::as_bool).unwrap_or(false);
+    let state = status
+        .get("status")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    flag("running")
+        || flag("active")
+        || flag("busy")
+        || matches!(state.as_str(), "running" | "processing" | "busy")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, body: &str) -> Result<Response, String> {
+        Ok(Response {
+            status,
+            status_line: format!("HTTP/1.0 {status}"),
+            content_type: "application/json".to_string(),
+            body: body.as_bytes().to_vec(),
+        })
+    }
+
+    #[test]
+    fn batch_status_reports_active_job() {
+        assert!(reports_active_job(&serde_json::json!({"running": true})));
+        assert!(reports_active_job(
+            &serde_json::json!({"status": "Processing"})
+        ));
+        assert!(!reports_active_job(
+            &serde_json::json!({"running": false, "status": "idle"})
+        ));
+        assert!(!reports_active_job(&serde_json::json!([])));
+    }
+
+    #[test]
+    fn summary_reads_queue_fields() {
+        let summary = summarize(&serde_json::json!({
+            "active_jobs": 2,
+            "queue_length": 5,
+            "current_item": " 转种 #12 ",
+            "progress": 145.5
+        }));
+        assert!(summary.busy);
+        assert_eq!(summary.capability, Capability::Supported);
+        assert_eq!(summary.current_item.as_deref(), Some("转种 #12"));
+        assert_eq!(summary.progress, Some(100.0));
+        assert_eq!(
+            summary.tooltip_suffix().as_deref(),
+            Some("batch 2 个任务 100%，排队 5")
+        );
+
+        let legacy = summarize(&serde_json::json!({"running": true}));
+        assert!(legacy.busy);
+        assert_eq!(legacy.tooltip_suffix().as_deref(), Some("batch 1 个任务"));
+        assert_eq!(
+            summarize(&serde_json::json!({"status": "idle"})).tooltip_suffix(),
+            None
+        );
+    }
+
+    #[test]
+    fn missing_endpoint_marks_capability_absent() {
+        let supported = next_summary(
+            &BatchSummary::default(),
+            interpret(response(200, r#"{"active_jobs": 1}"#)),
+        );
+        assert!(supported.busy);
+
+        let down = next_summary(&supported, interpret(Err("连接失败".to_string())));
+        assert_eq!(down.capability, Capability::Supported);
+        assert!(!down.reachable && !down.busy);
+
+        let old = next_summary(&down, interpret(response(404, "not found")));
+        assert_eq!(old.capability, Capability::Unsupported);
+        assert!(!old.busy);
+
+        let now = Instant::now();
+        let cache = Cache {
+            summary: old,
+            fetched_at: Some(now),
+        };
+        assert!(cache.is_fresh(now + FRESH_FOR * 2));
+        assert!(!cache.is_fresh(now + UNSUPPORTED_RECHECK));
+    }
+}
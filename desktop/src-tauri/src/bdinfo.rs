@@ -0,0 +1,262 @@
+//! BDInfo 可用性检查与路径覆盖。
+//!
+//! 默认使用 server/bdinfo 下打包的 BDInfo；Linux/macOS 上通常没有打包或需要 mono，
+//! 可以在 runtime.env 中用 `PTNEXUS_BDINFO_PATH` 指向自行安装的版本。
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::runtime::{self, RuntimeManager};
+
+pub const BDINFO_PATH_KEY: &str = "PTNEXUS_BDINFO_PATH";
+const BDINFO_DIR_KEY: &str = "PTNEXUS_BDINFO_DIR";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Serialize)]
+pub struct BdinfoStatus {
+    pub path: String,
+    pub overridden: bool,
+    pub exists: bool,
+    pub runnable: bool,
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 用户覆盖了 BDInfo 路径但没有同时改目录时，让 PTNEXUS_BDINFO_DIR 跟随可执行文件所在目录。
+pub fn align_bdinfo_dir(envs: &mut HashMap<String, String>, bundled_dir: &Path) {
+    let Some(path) = envs.get(BDINFO_PATH_KEY).map(PathBuf::from) else {
+        return;
+    };
+    let Some(parent) = path.parent() else {
+        return;
+    };
+
+    let dir_is_bundled = envs
+        .get(BDINFO_DIR_KEY)
+        .map(|dir| Path::new(dir) == bundled_dir)
+        .unwrap_or(true);
+    if dir_is_bundled && parent != bundled_dir {
+        envs.insert(
+            BDINFO_DIR_KEY.to_string(),
+            parent.to_string_lossy().to_string(),
+        );
+    }
+}
+
+/// 当前生效的 BDInfo 路径：已启动时取运行环境，否则按 runtime.env 与默认布局推导。
+fn configured_path(app: &AppHandle) -> Result<(PathBuf, bool), String> {
+    if let Some(value) = runtime::read_runtime_setting(app, BDINFO_PATH_KEY) {
+        return Ok((PathBuf::from(value), true));
+    }
+
+    if let Some(context) = app
+        .try_state::<RuntimeManager>()
+        .and_then(|rt| rt.context())
+    {
+        if let Some(value) = context.common_env.get(BDINFO_PATH_KEY) {
+            return Ok((PathBuf::from(value), false));
+        }
+    }
+
+    let server_dir = runtime::resolve_server_dir(app)?;
+    Ok((
+        server_dir.join("bdinfo").join(runtime::exe_name("BDInfo")),
+        false,
+    ))
+}
+
+pub fn status(app: &AppHandle) -> BdinfoStatus {
+    match configured_path(app) {
+        Ok((path, overridden)) => probe(&path, overridden),
+        Err(err) => BdinfoStatus {
+            path: String::new(),
+            overridden: false,
+            exists: false,
+            runnable: false,
+            output: None,
+            error: Some(err),
+        },
+    }
+}
+
+/// 校验并把 BDInfo 路径写入 runtime.env，重启后生效。
+pub fn set_path(app: &AppHandle, path: &str) -> Result<BdinfoStatus, String> {
+    let path = PathBuf::from(path.trim());
+    if !path.is_file() {
+        return Err(format!("BDInfo 路径不存在或不是文件: {}", path.display()));
+    }
+
+    let status = probe(&path, true);
+    if !status.runnable {
+        return Err(format!(
+            "无法运行 BDInfo（{}）: {}",
+            path.display(),
+            status.error.clone().unwrap_or_default()
+        ));
+    }
+
+    let env_file = runtime::ensure_runtime_env_file(app)?;
+    let value = path.to_string_lossy().to_string();
+    runtime::update_env_file(&env_file, &[(BDINFO_PATH_KEY, Some(value.as_str()))])?;
+    Ok(status)
+}
+
+/// 只检查文件是否存在，不试运行，用于每次启动时的自检。
+pub fn located(app: &AppHandle) -> BdinfoStatus {
+    match configured_path(app) {
+        Ok((path, overridden)) => BdinfoStatus {
+            path: path.to_string_lossy().to_string(),
+            overridden,
+            exists: path.is_file(),
+            runnable: false,
+            output: None,
+            error: (!path.is_file()).then(|| "文件不存在".to_string()),
+        },
+        Err(err) => BdinfoStatus {
+            path: String::new(),
+            overridden: false,
+            exists: false,
+            runnable: false,
+            output: None,
+            error: Some(err),
+        },
+    }
+}
+
+/// 以 `--version` 试运行，按退出状态与输出判断是否可用（见 [`judge`]）。
+fn probe(path: &Path, overridden: bool) -> BdinfoStatus {
+    let mut status = BdinfoStatus {
+        path: path.to_string_lossy().to_string(),
+        overridden,
+        exists: path.is_file(),
+        runnable: false,
+        output: None,
+        error: None,
+    };
+    if !status.exists {
+        status.error = Some("文件不存在".to_string());
+        return status;
+    }
+
+    let mut cmd = Command::new(path);
+    cmd.arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            status.error = Some(if cfg!(target_os = "windows") {
+                format!("启动失败: {e}")
+            } else {
+                format!("启动失败: {e}（非 Windows 平台可能需要安装 mono）")
+            });
+            return status;
+        }
+    };
+
+    let begin = Instant::now();
+    let mut code = None;
+    let mut timed_out = false;
+    loop {
+        match child.try_wait() {
+            Ok(Some(exit)) => {
+                code = exit.code();
+                break;
+            }
+            Ok(None) if begin.elapsed() < PROBE_TIMEOUT => {
+                thread::sleep(Duration::from_millis(100))
+            }
+            _ => {
+                timed_out = true;
+                let _ = child.kill();
+                let _ = child.wait();
+                break;
+            }
+        }
+    }
+
+    let mut output = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        let _ = stdout.read_to_string(&mut output);
+    }
+    if let Some(mut stderr) = child.stderr.take() {
+        let _ = stderr.read_to_string(&mut output);
+    }
+    let summary: Vec<&str> = output
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(5)
+        .collect();
+    if !summary.is_empty() {
+        status.output = Some(summary.join("\n"));
+    }
+
+    match judge(code, timed_out, &output) {
+        Ok(()) => status.runnable = true,
+        Err(err) => status.error = Some(err),
+    }
+    status
+}
+
+/// 试运行的结论。正常退出即可用；部分版本不认识 `--version`，以非 0 退出码打印用法，有输出也视为可用。
+/// 126/127 是包装脚本找不到解释器（如 mono）时 shell 的退出码，此时的输出只是错误信息。
+fn judge(code: Option<i32>, timed_out: bool, output: &str) -> Result<(), String> {
+    let has_output = !output.trim().is_empty();
+    if timed_out {
+        return if has_output {
+            Ok(())
+        } else {
+            Err(format!("{} 秒内没有任何输出", PROBE_TIMEOUT.as_secs()))
+        };
+    }
+    match code {
+        Some(0) => Ok(()),
+        Some(code @ (126 | 127)) => {
+            Err(format!("无法执行（退出码 {code}）: {}", first_line(output)))
+        }
+        Some(_) if has_output => Ok(()),
+        Some(code) => Err(format!("退出码 {code}，没有任何输出")),
+        None => Err("进程被信号终止".to_string()),
+    }
+}
+
+fn first_line(output: &str) -> &str {
+    output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_status_and_output_decide_runnable() {
+        assert!(judge(Some(0), false, "").is_ok());
+        // 不认识 --version 的版本打印用法后以非 0 退出
+        assert!(judge(Some(1), false, "Usage: BDInfo <path>").is_ok());
+        assert!(judge(Some(1), false, "  \n").is_err());
+        let missing_mono = judge(Some(127), false, "\nmono: command not found\n").unwrap_err();
+        assert!(missing_mono.contains("mono: command not found"));
+        assert!(judge(None, false, "").is_err());
+        assert!(judge(None, true, "BDInfo 0.7.5").is_ok());
+        assert!(judge(None, true, "").is_err());
+    }
+}
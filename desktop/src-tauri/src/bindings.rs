@@ -0,0 +1,387 @@
+//! 检查服务实际监听的地址。
+//!
+//! 后端 API 没有鉴权，默认只监听 127.0.0.1。若 runtime.env 或后端自身的问题导致监听了 0.0.0.0 等
+//! 更宽的地址，而用户并未把监听地址配置为对外开放，就记录安全警告、发出 `binding-warning` 事件
+//! 并弹出一次系统通知。检查失败（权限不足、系统命令缺失）时只记日志，不影响启动。
+
+use std::net::IpAddr;
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+use crate::events::{self, Replay};
+use crate::runtime;
+
+static NOTIFIED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ServiceBinding {
+    pub service: String,
+    pub port: u16,
+    /// 实际监听的地址，如 `127.0.0.1`、`0.0.0.0`、`::`。
+    pub addresses: Vec<String>,
+    /// 配置的监听地址。
+    pub expected_host: String,
+    /// 实际监听范围比配置更宽。
+    pub exposed: bool,
+}
+
+/// 检查子进程在 `port` 上的监听地址，必要时发出警告。
+pub fn check(
+    app: &AppHandle,
+    service: &str,
+    pid: u32,
+    port: u16,
+    expected_host: &str,
+) -> Option<ServiceBinding> {
+    // 启动器可能再派生出真正监听端口的子进程，按 PID 找不到时退回只按端口匹配
+    // （启动前已确认端口空闲，此时占用它的只可能是我们的服务）
+    let found = listening_addresses(Some(pid), port).and_then(|addresses| {
+        if addresses.is_empty() {
+            listening_addresses(None, port)
+        } else {
+            Ok(addresses)
+        }
+    });
+    let addresses = match found {
+        Ok(addresses) if !addresses.is_empty() => addresses,
+        Ok(_) => return None,
+        Err(err) => {
+            runtime::shell_log(app, &format!("[WARN] 无法检查 {service} 的监听地址: {err}"));
+            return None;
+        }
+    };
+
+    let exposed = is_loopback_host(expected_host)
+        && addresses.iter().any(|address| !is_loopback_host(address));
+    let binding = ServiceBinding {
+        service: service.to_string(),
+        port,
+        addresses,
+        expected_host: expected_host.to_string(),
+        exposed,
+    };
+
+    if exposed {
+        let message = format!(
+            "{service} 监听在 {}:{port}，超出配置的 {expected_host}，未鉴权的接口可能暴露到局域网",
+            binding.addresses.join(", ")
+        );
+        runtime::shell_log(app, &format!("[SECURITY] {message}"));
+        events::emit(app, "binding-warning", Replay::All, &binding);
+        if !NOTIFIED.swap(true, Ordering::SeqCst) {
+            let _ = app
+                .notification()
+                .builder()
+                .title("PT Nexus 安全提示")
+                .body(format!("{message}。请检查 runtime.env 中的监听地址设置。"))
+                .show();
+        }
+    }
+    Some(binding)
+}
+
+pub fn is_loopback_host(host: &str) -> bool {
+    let host = host.trim().trim_start_matches('[').trim_end_matches(']');
+    if host.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    host.parse::<IpAddr>()
+        .map(|ip| ip.is_loopback())
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+#[cfg(target_os = "windows")]
+fn listening_addresses(pid: Option<u32>, port: u16) -> Result<Vec<String>, String> {
+    Ok(parse_netstat(&netstat_output()?, pid, port))
+}
+
+#[cfg(target_os = "windows")]
+fn netstat_output() -> Result<String, String> {
+    let mut output = String::new();
+    for protocol in ["TCP", "TCPv6"] {
+        let result = Command::new("netstat")
+            .args(["-ano", "-p", protocol])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .map_err(|e| format!("执行 netstat 失败: {e}"))?;
+        output.push_str(&String::from_utf8_lossy(&result.stdout));
+    }
+    Ok(output)
+}
+
+#[cfg(target_os = "macos")]
+fn listening_addresses(pid: Option<u32>, port: u16) -> Result<Vec<String>, String> {
+    Ok(parse_lsof(&lsof_output(pid, port)?, port))
+}
+
+#[cfg(target_os = "macos")]
+fn lsof_output(pid: Option<u32>, port: u16) -> Result<String, String> {
+    let mut cmd = Command::new("lsof");
+    cmd.arg("-nP");
+    if let Some(pid) = pid {
+        cmd.args(["-a", "-p", &pid.to_string()]);
+    }
+    let output = cmd
+        .arg(format!("-iTCP:{port}"))
+        .arg("-sTCP:LISTEN")
+        .output()
+        .map_err(|e| format!("执行 lsof 失败: {e}"))?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn listening_addresses(pid: Option<u32>, port: u16) -> Result<Vec<String>, String> {
+    Ok(parse_ss(&ss_output(port)?, pid, port))
+}
+
+#[cfg(target_os = "linux")]
+fn ss_output(port: u16) -> Result<String, String> {
+    let output = Command::new("ss")
+        .args(["-ltnpH", "sport", "=", &format!(":{port}")])
+        .output()
+        .map_err(|e| format!("执行 ss 失败: {e}"))?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn listening_addresses(_pid: Option<u32>, _port: u16) -> Result<Vec<String>, String> {
+    Err("当前平台不支持".to_string())
+}
+
+/// 正在监听 `port` 的进程，如 `python (PID 1234)`；查不到（权限不足、系统命令缺失）时返回 None。
+#[cfg(target_os = "windows")]
+pub fn port_owner(port: u16) -> Option<String> {
+    let pid = parse_netstat_owner(&netstat_output().ok()?, port)?;
+    let output = Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/FO", "CSV", "/NH"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok();
+    // "python.exe","1234",...
+    let name = output.and_then(|output| {
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()?
+            .split("\",\"")
+            .next()
+            .map(|name| name.trim_matches('"').to_string())
+            .filter(|name| !name.is_empty() && !name.starts_with("INFO:"))
+    });
+    Some(match name {
+        Some(name) => format!("{name} (PID {pid})"),
+        None => format!("PID {pid}"),
+    })
+}
+
+#[cfg(target_os = "macos")]
+pub fn port_owner(port: u16) -> Option<String> {
+    parse_lsof_owner(&lsof_output(None, port).ok()?, port)
+}
+
+#[cfg(target_os = "linux")]
+pub fn port_owner(port: u16) -> Option<String> {
+    parse_ss_owner(&ss_output(port).ok()?, port)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub fn port_owner(_port: u16) -> Option<String> {
+    None
+}
+
+/// 拆分 `host:port`，IPv6 地址可能带方括号。
+fn split_host_port(value: &str) -> Option<(String, u16)> {
+    let (host, port) = value.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let host = host.split('%').next().unwrap_or(host);
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+/// `TCP    0.0.0.0:5275    0.0.0.0:0    LISTENING    1234`
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+fn parse_netstat(output: &str, pid: Option<u32>, port: u16) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 5 || !fields[0].starts_with("TCP") || fields[3] != "LISTENING" {
+                return None;
+            }
+            if pid.is_some_and(|pid| fields[4].parse::<u32>().ok() != Some(pid)) {
+                return None;
+            }
+            let (host, local_port) = split_host_port(fields[1])?;
+            (local_port == port).then_some(host)
+        })
+        .collect()
+}
+
+/// `python  1234 user  5u  IPv4 0x...  0t0  TCP 127.0.0.1:5275 (LISTEN)`
+#[cfg_attr(not(any(target_os = "macos", test)), allow(dead_code))]
+fn parse_lsof(output: &str, port: u16) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| line.contains("(LISTEN)"))
+        .filter_map(|line| {
+            let name = line.split_whitespace().rev().nth(1)?;
+            let (host, local_port) = split_host_port(name)?;
+            let host = if host == "*" {
+                "0.0.0.0".to_string()
+            } else {
+                host
+            };
+            (local_port == port).then_some(host)
+        })
+        .collect()
+}
+
+/// `LISTEN 0 2048 0.0.0.0:5275 0.0.0.0:* users:(("python",pid=1234,fd=5))`
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn parse_ss(output: &str, pid: Option<u32>, port: u16) -> Vec<String> {
+    let pid_marker = pid.map(|pid| format!("pid={pid},"));
+    output
+        .lines()
+        .filter(|line| {
+            pid_marker
+                .as_ref()
+                .is_none_or(|marker| line.contains(marker))
+        })
+        .filter_map(|line| {
+            let local = line.split_whitespace().nth(3)?;
+            let (host, local_port) = split_host_port(local)?;
+            let host = if host == "*" {
+                "0.0.0.0".to_string()
+            } else {
+                host
+            };
+            (local_port == port).then_some(host)
+        })
+        .collect()
+}
+
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+fn parse_netstat_owner(output: &str, port: u16) -> Option<u32> {
+    output.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 5 || !fields[0].starts_with("TCP") || fields[3] != "LISTENING" {
+            return None;
+        }
+        let (_, local_port) = split_host_port(fields[1])?;
+        (local_port == port)
+            .then(|| fields[4].parse().ok())
+            .flatten()
+    })
+}
+
+#[cfg_attr(not(any(target_os = "macos", test)), allow(dead_code))]
+fn parse_lsof_owner(output: &str, port: u16) -> Option<String> {
+    output
+        .lines()
+        .filter(|line| line.contains("(LISTEN)"))
+        .find_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (_, local_port) = split_host_port(fields.iter().rev().nth(1)?)?;
+            (local_port == port && fields.len() > 1)
+                .then(|| format!("{} (PID {})", fields[0], fields[1]))
+        })
+}
+
+/// 从 `users:(("python",pid=1234,fd=5))` 中取出进程名与 PID；没有权限查看其他用户的进程时 ss 不输出 users 字段。
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn parse_ss_owner(output: &str, port: u16) -> Option<String> {
+    output.lines().find_map(|line| {
+        let local = line.split_whitespace().nth(3)?;
+        let (_, local_port) = split_host_port(local)?;
+        if local_port != port {
+            return None;
+        }
+        let users = line.split_once("users:((\"")?.1;
+        let (name, rest) = users.split_once('"')?;
+        let pid = rest.split_once("pid=")?.1.split([',', ')']).next()?;
+        Some(format!("{name} (PID {pid})"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback_hosts_are_recognized() {
+        assert!(is_loopback_host("127.0.0.1"));
+        assert!(is_loopback_host("::1"));
+        assert!(is_loopback_host("[::1]"));
+        assert!(is_loopback_host("localhost"));
+        assert!(!is_loopback_host("0.0.0.0"));
+        assert!(!is_loopback_host("::"));
+        assert!(!is_loopback_host("192.168.1.10"));
+    }
+
+    #[test]
+    fn netstat_output_is_filtered_by_pid_and_port() {
+        let output = "\
+  Proto  Local Address          Foreign Address        State           PID
+  TCP    0.0.0.0:5275           0.0.0.0:0              LISTENING       1234
+  TCP    127.0.0.1:5276         0.0.0.0:0              LISTENING       1234
+  TCP    0.0.0.0:5275           0.0.0.0:0              LISTENING       999
+  TCP    [::]:5275              [::]:0                 LISTENING       1234
+  TCP    127.0.0.1:5275         127.0.0.1:50000        ESTABLISHED     1234
+";
+        assert_eq!(
+            parse_netstat(output, Some(1234), 5275),
+            vec!["0.0.0.0", "::"]
+        );
+        assert_eq!(
+            parse_netstat(output, None, 5275),
+            vec!["0.0.0.0", "0.0.0.0", "::"]
+        );
+    }
+
+    #[test]
+    fn lsof_output_is_parsed() {
+        let output = "\
+COMMAND   PID USER   FD   TYPE DEVICE SIZE/OFF NODE NAME
+python  1234 me    5u  IPv4 0x1      0t0  TCP 127.0.0.1:5275 (LISTEN)
+python  1234 me    6u  IPv6 0x2      0t0  TCP *:5275 (LISTEN)
+";
+        assert_eq!(parse_lsof(output, 5275), vec!["127.0.0.1", "0.0.0.0"]);
+    }
+
+    #[test]
+    fn ss_output_is_filtered_by_pid() {
+        let output = "\
+LISTEN 0 2048 127.0.0.1:5275 0.0.0.0:* users:((\"python\",pid=1234,fd=5))
+LISTEN 0 2048 [::]:5275 [::]:* users:((\"python\",pid=4321,fd=5))
+";
+        assert_eq!(parse_ss(output, Some(1234), 5275), vec!["127.0.0.1"]);
+        assert_eq!(parse_ss(output, None, 5275), vec!["127.0.0.1", "::"]);
+    }
+
+    #[test]
+    fn port_owners_are_parsed() {
+        let netstat =
+            "  TCP    0.0.0.0:5275           0.0.0.0:0              LISTENING       4321\n";
+        assert_eq!(parse_netstat_owner(netstat, 5275), Some(4321));
+        assert_eq!(parse_netstat_owner(netstat, 5276), None);
+
+        let lsof = "COMMAND   PID USER   FD   TYPE DEVICE SIZE/OFF NODE NAME\n\
+node    777 me    5u  IPv4 0x1      0t0  TCP *:5275 (LISTEN)\n";
+        assert_eq!(
+            parse_lsof_owner(lsof, 5275).as_deref(),
+            Some("node (PID 777)")
+        );
+
+        let ss = "LISTEN 0 2048 127.0.0.1:5275 0.0.0.0:* users:((\"nginx\",pid=88,fd=6))\n\
+LISTEN 0 2048 127.0.0.1:5276 0.0.0.0:*\n";
+        assert_eq!(parse_ss_owner(ss, 5275).as_deref(), Some("nginx (PID 88)"));
+        assert_eq!(parse_ss_owner(ss, 5276), None);
+    }
+}
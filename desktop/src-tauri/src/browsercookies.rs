@@ -0,0 +1,556 @@
+//! 从本机浏览器导入站点的 Cookie。
+//!
+//! 配置站点时需要手动复制 Cookie，很多用户找不到。`import_cookies_from_browser(browser, domain)`
+//! 在用户确认后读取所选浏览器的 Cookie 数据库，取出适用于该域名的 Cookie 返回给 WebUI，由 WebUI
+//! 填入站点设置并交给后端保存；桌面壳不把 Cookie 写入磁盘，也不写日志。
+//!
+//! - Firefox：`cookies.sqlite` 未加密，各平台都可读取。
+//! - Chrome / Edge：Cookie 由系统加密，目前只支持 Windows（DPAPI 解密 Local State 中的密钥，再以
+//!   AES-256-GCM 解密）。Chrome 127 起的“应用绑定加密”（v20）只有浏览器自己能解开，此时返回说明，
+//!   请用户手动复制。浏览器运行时独占 Cookie 文件，需要先完全关闭浏览器。
+//!
+//! 同一浏览器有多个配置档时，使用 Cookie 文件最近修改的那个。不支持的组合返回说明而不是报错退出。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{Connection, ErrorCode, OpenFlags};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::commanderror::{self, CommandError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Browser {
+    Chrome,
+    Edge,
+    Firefox,
+}
+
+impl Browser {
+    fn label(self) -> &'static str {
+        match self {
+            Browser::Chrome => "Chrome",
+            Browser::Edge => "Edge",
+            Browser::Firefox => "Firefox",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BrowserCookie {
+    pub name: String,
+    pub value: String,
+    /// Cookie 所属的域名，以 `.` 开头的对子域名同样有效。
+    pub host: String,
+    pub path: String,
+}
+
+/// 弹窗征得用户同意后读取 Cookie；用户取消时返回 None。
+/// 浏览器占用 Cookie 文件时错误码为 `browser_locked`，不支持的浏览器或加密方式为 `unsupported`。
+pub fn import(
+    app: &AppHandle,
+    browser: Browser,
+    domain: &str,
+) -> Result<Option<Vec<BrowserCookie>>, CommandError> {
+    let domain = normalize_domain(domain).map_err(CommandError::invalid)?;
+    let confirmed = app
+        .dialog()
+        .message(format!(
+            "PT Nexus 将从 {} 读取 {domain} 的 Cookie，用于登录该站点。\n\n\
+             Cookie 只会填入站点设置，由你确认保存到 PT Nexus 后端；桌面壳不会另外保存或记录。",
+            browser.label()
+        ))
+        .title("从浏览器导入 Cookie")
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "允许读取".to_string(),
+            "取消".to_string(),
+        ))
+        .blocking_show();
+    if !confirmed {
+        return Ok(None);
+    }
+
+    let cookies = match browser {
+        Browser::Firefox => read_firefox(&domain)?,
+        Browser::Chrome | Browser::Edge => read_chromium(browser, &domain)?,
+    };
+    if cookies.is_empty() {
+        return Err(CommandError::new(
+            commanderror::NOT_FOUND,
+            format!(
+                "{} 中没有 {domain} 的有效 Cookie，请先在该浏览器中登录站点",
+                browser.label()
+            ),
+        ));
+    }
+    Ok(Some(cookies))
+}
+
+/// 接受 `pt.example.com`、`https://pt.example.com/index.php` 等写法，只保留主机名。
+fn normalize_domain(input: &str) -> Result<String, String> {
+    let input = input.trim();
+    let without_scheme = input.split_once("://").map_or(input, |(_, rest)| rest);
+    let host = without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default()
+        .rsplit('@')
+        .next()
+        .unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default();
+    let host = host.trim_matches('.').to_ascii_lowercase();
+    let valid = host.contains('.')
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    if !valid {
+        return Err(format!("无效的站点域名: {input}"));
+    }
+    Ok(host)
+}
+
+/// 站点 `domain` 的请求会带上的 Cookie：主机相同，或 Cookie 属于上级域名（`.example.com`）。
+fn applies_to(host: &str, domain: &str) -> bool {
+    let bare = host.trim_start_matches('.').to_ascii_lowercase();
+    domain == bare || (host.starts_with('.') && domain.ends_with(&format!(".{bare}")))
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// 只读打开浏览器的数据库；被浏览器锁住时提示关闭浏览器。
+fn open_store(browser: Browser, path: &Path) -> Result<Connection, CommandError> {
+    Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| store_error(browser, path, e))
+}
+
+fn store_error(browser: Browser, path: &Path, err: rusqlite::Error) -> CommandError {
+    let locked = matches!(
+        err.sqlite_error_code(),
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+    );
+    if locked {
+        locked_error(browser)
+    } else {
+        CommandError::new(
+            commanderror::IO,
+            format!("读取 {} 失败: {err}", path.display()),
+        )
+    }
+}
+
+fn locked_error(browser: Browser) -> CommandError {
+    CommandError::new(
+        commanderror::BROWSER_LOCKED,
+        format!(
+            "{0} 正在使用 Cookie 文件，请完全关闭 {0}（包括托盘中的后台进程）后重试",
+            browser.label()
+        ),
+    )
+}
+
+fn store_not_found(browser: Browser, dir: &Path) -> CommandError {
+    CommandError::new(
+        commanderror::NOT_FOUND,
+        format!(
+            "未找到 {} 的 Cookie 文件（{}）",
+            browser.label(),
+            dir.display()
+        ),
+    )
+}
+
+/// 多个配置档中 Cookie 文件最近修改的一个。
+fn most_recent(candidates: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+    candidates
+        .into_iter()
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+            Some((modified, path))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+fn firefox_profiles_dir() -> Option<PathBuf> {
+    let env_dir = |key: &str| std::env::var_os(key).map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        Some(
+            env_dir("APPDATA")?
+                .join("Mozilla")
+                .join("Firefox")
+                .join("Profiles"),
+        )
+    } else if cfg!(target_os = "macos") {
+        Some(env_dir("HOME")?.join("Library/Application Support/Firefox/Profiles"))
+    } else {
+        Some(env_dir("HOME")?.join(".mozilla").join("firefox"))
+    }
+}
+
+fn read_firefox(domain: &str) -> Result<Vec<BrowserCookie>, CommandError> {
+    let profiles = firefox_profiles_dir()
+        .ok_or_else(|| CommandError::new(commanderror::NOT_FOUND, "无法确定 Firefox 配置档目录"))?;
+    let store = fs::read_dir(&profiles)
+        .ok()
+        .and_then(|entries| {
+            most_recent(
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path().join("cookies.sqlite")),
+            )
+        })
+        .ok_or_else(|| store_not_found(Browser::Firefox, &profiles))?;
+    read_firefox_store(&store, domain, now_secs())
+}
+
+fn read_firefox_store(
+    store: &Path,
+    domain: &str,
+    now: i64,
+) -> Result<Vec<BrowserCookie>, CommandError> {
+    let browser = Browser::Firefox;
+    let conn = open_store(browser, store)?;
+    let mut stmt = conn
+        .prepare("SELECT host, name, value, path, expiry FROM moz_cookies")
+        .map_err(|e| store_error(browser, store, e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                BrowserCookie {
+                    name: row.get(1)?,
+                    value: row.get(2)?,
+                    host: row.get(0)?,
+                    path: row.get(3)?,
+                },
+                row.get::<_, i64>(4)?,
+            ))
+        })
+        .map_err(|e| store_error(browser, store, e))?;
+
+    let mut cookies = Vec::new();
+    for row in rows {
+        let (host, cookie, expiry) = row.map_err(|e| store_error(browser, store, e))?;
+        // 新版 Firefox 以毫秒记录过期时间
+        let expiry = if expiry > 100_000_000_000 {
+            expiry / 1000
+        } else {
+            expiry
+        };
+        if applies_to(&host, domain) && expiry > now {
+            cookies.push(cookie);
+        }
+    }
+    Ok(cookies)
+}
+
+/// Chromium 加密 Cookie 的格式。
+#[derive(Debug, PartialEq)]
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+enum Sealed<'a> {
+    /// `v10` / `v11`：AES-256-GCM，12 字节 nonce，密文末尾带 16 字节校验。
+    Gcm {
+        nonce: &'a [u8],
+        ciphertext: &'a [u8],
+    },
+    /// `v20`：应用绑定加密，只有浏览器能解开。
+    AppBound,
+    /// Chrome 80 之前：整个值直接用 DPAPI 加密。
+    Legacy(&'a [u8]),
+}
+
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+fn classify(encrypted: &[u8]) -> Sealed<'_> {
+    match encrypted.get(..3) {
+        Some(b"v10" | b"v11") if encrypted.len() > 15 => Sealed::Gcm {
+            nonce: &encrypted[3..15],
+            ciphertext: &encrypted[15..],
+        },
+        Some(b"v20") => Sealed::AppBound,
+        _ => Sealed::Legacy(encrypted),
+    }
+}
+
+/// 数据库版本 24 起，解密后的值前面带有域名的 SHA-256（32 字节）。
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+fn strip_host_digest(plain: Vec<u8>, db_version: i64) -> Vec<u8> {
+    if db_version >= 24 && plain.len() >= 32 {
+        plain[32..].to_vec()
+    } else {
+        plain
+    }
+}
+
+/// Chromium 的过期时间是自 1601-01-01 起的微秒数，0 表示会话 Cookie。
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+fn chromium_expired(expires_utc: i64, now: i64) -> bool {
+    const EPOCH_DELTA_SECS: i64 = 11_644_473_600;
+    expires_utc != 0 && expires_utc / 1_000_000 - EPOCH_DELTA_SECS <= now
+}
+
+#[cfg(target_os = "windows")]
+fn read_chromium(browser: Browser, domain: &str) -> Result<Vec<BrowserCookie>, CommandError> {
+    let local_app_data = std::env::var_os("LOCALAPPDATA")
+        .ok_or_else(|| CommandError::new(commanderror::NOT_FOUND, "无法确定 LOCALAPPDATA 目录"))?;
+    let user_data = match browser {
+        Browser::Edge => Path::new(&local_app_data).join("Microsoft").join("Edge"),
+        _ => Path::new(&local_app_data).join("Google").join("Chrome"),
+    }
+    .join("User Data");
+    let store = fs::read_dir(&user_data)
+        .ok()
+        .and_then(|entries| {
+            most_recent(entries.filter_map(|entry| entry.ok()).flat_map(|entry| {
+                let profile = entry.path();
+                [
+                    profile.join("Network").join("Cookies"),
+                    profile.join("Cookies"),
+                ]
+            }))
+        })
+        .ok_or_else(|| store_not_found(browser, &user_data))?;
+    // 浏览器运行时以独占方式打开 Cookie 文件，先试着打开以给出明确的提示
+    if let Err(err) = fs::File::open(&store) {
+        const ERROR_SHARING_VIOLATION: i32 = 32;
+        if err.raw_os_error() == Some(ERROR_SHARING_VIOLATION) {
+            return Err(locked_error(browser));
+        }
+        return Err(CommandError::io(
+            format_args!("读取 {} 失败", store.display()),
+            err,
+        ));
+    }
+    let key = chromium_key(&user_data.join("Local State"))?;
+
+    let conn = open_store(browser, &store)?;
+    let db_version: i64 = conn
+        .query_row("SELECT value FROM meta WHERE key = 'version'", [], |row| {
+            row.get::<_, String>(0)
+        })
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default();
+    let mut stmt = conn
+        .prepare("SELECT host_key, name, value, encrypted_value, path, expires_utc FROM cookies")
+        .map_err(|e| store_error(browser, &store, e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Vec<u8>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })
+        .map_err(|e| store_error(browser, &store, e))?;
+
+    let now = now_secs();
+    let mut cookies = Vec::new();
+    for row in rows {
+        let (host, name, value, encrypted, path, expires_utc) =
+            row.map_err(|e| store_error(browser, &store, e))?;
+        if !applies_to(&host, domain) || chromium_expired(expires_utc, now) {
+            continue;
+        }
+        let value = if encrypted.is_empty() {
+            value
+        } else {
+            let plain = match classify(&encrypted) {
+                Sealed::Gcm { nonce, ciphertext } => {
+                    strip_host_digest(aes_gcm_open(&key, nonce, ciphertext)?, db_version)
+                }
+                Sealed::AppBound => {
+                    return Err(CommandError::new(
+                        commanderror::UNSUPPORTED,
+                        format!(
+                            "{} 使用了应用绑定加密，其他程序无法读取其 Cookie；请在浏览器开发者工具中手动复制",
+                            browser.label()
+                        ),
+                    ))
+                }
+                Sealed::Legacy(data) => dpapi_unprotect(data)?,
+            };
+            String::from_utf8(plain).map_err(|_| format!("{name} 解密后不是有效的文本"))?
+        };
+        cookies.push(BrowserCookie {
+            name,
+            value,
+            host,
+            path,
+        });
+    }
+    Ok(cookies)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn read_chromium(browser: Browser, _domain: &str) -> Result<Vec<BrowserCookie>, CommandError> {
+    Err(CommandError::new(
+        commanderror::UNSUPPORTED,
+        format!(
+            "暂不支持在此系统上从 {} 导入 Cookie（Cookie 由系统钥匙串加密），请改用 Firefox 或手动复制",
+            browser.label()
+        ),
+    ))
+}
+
+/// Local State 中 `os_crypt.encrypted_key` 为 base64，去掉 `DPAPI` 前缀后用 DPAPI 解密得到 AES 密钥。
+#[cfg(target_os = "windows")]
+fn chromium_key(local_state: &Path) -> Result<Vec<u8>, String> {
+    use base64::Engine;
+
+    let content = fs::read_to_string(local_state)
+        .map_err(|e| format!("读取 {} 失败: {e}", local_state.display()))?;
+    let state: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("解析 {} 失败: {e}", local_state.display()))?;
+    let encoded = state["os_crypt"]["encrypted_key"]
+        .as_str()
+        .ok_or("Local State 中没有 Cookie 加密密钥")?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| "Cookie 加密密钥格式无效")?;
+    let wrapped = decoded
+        .strip_prefix(b"DPAPI")
+        .ok_or("不支持的 Cookie 加密密钥格式")?;
+    dpapi_unprotect(wrapped)
+}
+
+#[cfg(target_os = "windows")]
+fn aes_gcm_open(key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Nonce};
+
+    let cipher =
+        Aes256Gcm::new_from_slice(key).map_err(|_| "Cookie 加密密钥长度无效".to_string())?;
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Cookie 解密失败，可能已被其他 Windows 用户加密".to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn dpapi_unprotect(data: &[u8]) -> Result<Vec<u8>, String> {
+    use windows::Win32::Foundation::{LocalFree, HLOCAL};
+    use windows::Win32::Security::Cryptography::{
+        CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptUnprotectData(
+            &input,
+            None,
+            None,
+            None,
+            None,
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )
+    }
+    .map_err(|e| format!("DPAPI 解密失败: {e}"))?;
+    if output.pbData.is_null() {
+        return Err("DPAPI 解密失败: 没有返回数据".to_string());
+    }
+    let plain =
+        unsafe { std::slice::from_raw_parts(output.pbData, output.cbData as usize) }.to_vec();
+    unsafe { LocalFree(Some(HLOCAL(output.pbData.cast()))) };
+    Ok(plain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn domains_are_normalized_and_matched_like_a_browser() {
+        assert_eq!(
+            normalize_domain("https://PT.Example.com:8443/index.php?x=1").unwrap(),
+            "pt.example.com"
+        );
+        assert_eq!(
+            normalize_domain("pt.example.com").unwrap(),
+            "pt.example.com"
+        );
+        assert!(normalize_domain("localhost").is_err());
+        assert!(normalize_domain("bad host.com").is_err());
+
+        assert!(applies_to("pt.example.com", "pt.example.com"));
+        assert!(applies_to(".example.com", "pt.example.com"));
+        assert!(applies_to(".pt.example.com", "pt.example.com"));
+        // 只属于其他子域名或上级主机本身的 Cookie 不会发给该站点
+        assert!(!applies_to("www.example.com", "pt.example.com"));
+        assert!(!applies_to("example.com", "pt.example.com"));
+        assert!(!applies_to(".ample.com", "pt.example.com"));
+    }
+
+    #[test]
+    fn chromium_values_are_classified_by_prefix() {
+        let mut sealed = b"v10".to_vec();
+        sealed.extend_from_slice(&[7u8; 12]);
+        sealed.extend_from_slice(b"ciphertext-and-tag");
+        assert_eq!(
+            classify(&sealed),
+            Sealed::Gcm {
+                nonce: &[7u8; 12],
+                ciphertext: b"ciphertext-and-tag"
+            }
+        );
+        assert_eq!(classify(b"v20whatever-long-enough"), Sealed::AppBound);
+        assert_eq!(
+            classify(b"\x01\x00\x00\x00"),
+            Sealed::Legacy(b"\x01\x00\x00\x00")
+        );
+
+        let mut plain = vec![0u8; 32];
+        plain.extend_from_slice(b"uid=1");
+        assert_eq!(strip_host_digest(plain.clone(), 24), b"uid=1");
+        assert_eq!(strip_host_digest(b"uid=1".to_vec(), 23), b"uid=1");
+
+        let now = 1_700_000_000;
+        let chromium_time = |unix: i64| (unix + 11_644_473_600) * 1_000_000;
+        assert!(!chromium_expired(0, now));
+        assert!(!chromium_expired(chromium_time(now + 60), now));
+        assert!(chromium_expired(chromium_time(now - 60), now));
+    }
+
+    #[test]
+    fn firefox_store_returns_unexpired_cookies_for_the_site() {
+        let dir = temp_dir("firefox");
+        let store = dir.join("cookies.sqlite");
+        let conn = Connection::open(&store).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE moz_cookies (host TEXT, name TEXT, value TEXT, path TEXT, expiry INTEGER);
+             INSERT INTO moz_cookies VALUES ('.example.com', 'c_secure_uid', '42', '/', 1800000000);
+             INSERT INTO moz_cookies VALUES ('pt.example.com', 'c_secure_pass', 'abc', '/', 1800000000000);
+             INSERT INTO moz_cookies VALUES ('pt.example.com', 'old', 'x', '/', 1600000000);
+             INSERT INTO moz_cookies VALUES ('other.com', 'uid', '1', '/', 1800000000);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let cookies = read_firefox_store(&store, "pt.example.com", 1_700_000_000).unwrap();
+        let names: Vec<&str> = cookies.iter().map(|cookie| cookie.name.as_str()).collect();
+        assert_eq!(names, vec!["c_secure_uid", "c_secure_pass"]);
+        assert_eq!(cookies[1].value, "abc");
+        assert!(read_firefox_store(&dir.join("missing.sqlite"), "pt.example.com", 0).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,227 @@
+//! 企业代理的根证书。
+//!
+//! 在会解密 HTTPS 的企业代理后面，后端访问站点时会因为证书不受信任而失败。
+//! runtime.env 中设置 `PTNEXUS_EXTRA_CA_BUNDLE=<PEM 文件>` 后，启动时先校验该文件，
+//! 再把它与内置 Python 的 certifi 根证书合并为 `<data_dir>/tmp/ca-bundle.pem`，
+//! 通过 REQUESTS_CA_BUNDLE / SSL_CERT_FILE 交给子进程，既信任企业根证书也不丢失公共根证书。
+//! 用户在 runtime.env 中显式设置了这两个变量时不覆盖。
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::fsutil;
+
+pub const EXTRA_CA_BUNDLE_KEY: &str = "PTNEXUS_EXTRA_CA_BUNDLE";
+const COMBINED_FILE_NAME: &str = "ca-bundle.pem";
+const PROPAGATED_KEYS: [&str; 2] = ["REQUESTS_CA_BUNDLE", "SSL_CERT_FILE"];
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+/// 配置了额外根证书时校验并生成合并后的证书文件，写入子进程环境变量；返回需要记录的日志。
+pub fn apply(
+    envs: &mut HashMap<String, String>,
+    data_dir: &Path,
+    python_home: &Path,
+) -> Result<Vec<String>, String> {
+    let Some(extra) = configured_path(envs) else {
+        return Ok(Vec::new());
+    };
+    let extra_pem = load(&extra)?;
+
+    let mut messages = Vec::new();
+    let bundle = match find_certifi_bundle(python_home) {
+        Some(certifi) => {
+            let base = fs::read_to_string(&certifi)
+                .map_err(|e| format!("读取 {} 失败: {e}", certifi.display()))?;
+            let combined = data_dir.join("tmp").join(COMBINED_FILE_NAME);
+            write_combined(&combined, &base, &extra_pem)?;
+            messages.push(format!(
+                "[INFO] 已合并 {} 与 {} 的根证书: {}",
+                extra.display(),
+                certifi.display(),
+                combined.display()
+            ));
+            combined
+        }
+        None => {
+            messages.push(format!(
+                "[WARN] 未找到内置 Python 的 certifi 根证书，子进程将只信任 {} 中的证书",
+                extra.display()
+            ));
+            extra.clone()
+        }
+    };
+
+    for key in PROPAGATED_KEYS {
+        if envs.get(key).is_some_and(|value| !value.trim().is_empty()) {
+            messages.push(format!("[INFO] runtime.env 已设置 {key}，保持不变"));
+        } else {
+            envs.insert(key.to_string(), bundle.to_string_lossy().to_string());
+        }
+    }
+    Ok(messages)
+}
+
+/// runtime.env 中配置的额外根证书路径，供诊断信息显示（只显示路径）。
+pub fn configured_path(envs: &HashMap<String, String>) -> Option<PathBuf> {
+    envs.get(EXTRA_CA_BUNDLE_KEY)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+fn load(path: &Path) -> Result<String, String> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        format!(
+            "{EXTRA_CA_BUNDLE_KEY} 指定的证书文件 {} 无法读取: {e}",
+            path.display()
+        )
+    })?;
+    validate_pem(&content).map_err(|e| {
+        format!(
+            "{EXTRA_CA_BUNDLE_KEY} 指定的证书文件 {} 无效: {e}",
+            path.display()
+        )
+    })?;
+    Ok(content)
+}
+
+/// 检查 PEM 中至少有一个证书块，且每个块的 Base64 内容完整；返回证书数量。
+fn validate_pem(content: &str) -> Result<usize, String> {
+    let mut count = 0;
+    let mut body: Option<String> = None;
+    for line in content.lines().map(str::trim) {
+        match (&mut body, line) {
+            (None, PEM_BEGIN) => body = Some(String::new()),
+            (None, _) => {}
+            (Some(_), PEM_BEGIN) => return Err(format!("第 {} 个证书缺少结束行", count + 1)),
+            (Some(data), PEM_END) => {
+                if !is_valid_base64(data) {
+                    return Err(format!("第 {} 个证书的内容不是有效的 Base64", count + 1));
+                }
+                count += 1;
+                body = None;
+            }
+            (Some(data), _) => data.push_str(line),
+        }
+    }
+    if body.is_some() {
+        return Err(format!("第 {} 个证书缺少结束行", count + 1));
+    }
+    if count == 0 {
+        return Err("未找到 PEM 格式的证书（-----BEGIN CERTIFICATE-----）".to_string());
+    }
+    Ok(count)
+}
+
+fn is_valid_base64(data: &str) -> bool {
+    let trimmed = data.trim_end_matches('=');
+    !data.is_empty()
+        && data.len().is_multiple_of(4)
+        && data.len() - trimmed.len() <= 2
+        && trimmed
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+}
+
+/// 在内置 Python 的 site-packages 中查找 certifi 的 cacert.pem。
+fn find_certifi_bundle(python_home: &Path) -> Option<PathBuf> {
+    let relative = Path::new("certifi").join("cacert.pem");
+    // Windows 嵌入式布局
+    let mut candidates = vec![python_home
+        .join("Lib")
+        .join("site-packages")
+        .join(&relative)];
+    // Unix 布局：lib/python3.x/site-packages
+    if let Ok(entries) = fs::read_dir(python_home.join("lib")) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with("python3") {
+                candidates.push(entry.path().join("site-packages").join(&relative));
+            }
+        }
+    }
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+fn write_combined(target: &Path, base: &str, extra: &str) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("创建目录失败 ({}): {e}", parent.display()))?;
+    }
+    let mut content = base.trim_end().to_string();
+    content.push_str("\n\n# PTNEXUS_EXTRA_CA_BUNDLE\n");
+    content.push_str(extra.trim_end());
+    content.push('\n');
+
+    fsutil::atomic_write(target, content)
+        .map_err(|e| format!("写入合并证书失败 ({}): {e}", target.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    const CERT: &str =
+        "-----BEGIN CERTIFICATE-----\nMIIBszCCAVmgAwIBAgIUQ2Fm\nZQ==\n-----END CERTIFICATE-----\n";
+
+    #[test]
+    fn pem_files_are_validated() {
+        assert_eq!(validate_pem(CERT), Ok(1));
+        assert_eq!(validate_pem(&format!("# corp\n{CERT}\n{CERT}")), Ok(2));
+        assert!(validate_pem("").is_err());
+        assert!(validate_pem("-----BEGIN CERTIFICATE-----\nMIIB\n").is_err());
+        assert!(validate_pem(
+            "-----BEGIN CERTIFICATE-----\nnot base64!\n-----END CERTIFICATE-----\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn bundle_is_combined_with_certifi_and_propagated() {
+        let dir = temp_dir("combine");
+        let python_home = dir.join("python");
+        let certifi = python_home
+            .join("lib")
+            .join("python3.11")
+            .join("site-packages")
+            .join("certifi");
+        fs::create_dir_all(&certifi).unwrap();
+        fs::write(certifi.join("cacert.pem"), "# public roots\n").unwrap();
+        let extra = dir.join("corp.pem");
+        fs::write(&extra, CERT).unwrap();
+
+        let mut envs = HashMap::from([
+            (
+                EXTRA_CA_BUNDLE_KEY.to_string(),
+                extra.to_string_lossy().to_string(),
+            ),
+            ("SSL_CERT_FILE".to_string(), "/custom.pem".to_string()),
+        ]);
+        apply(&mut envs, &dir, &python_home).unwrap();
+
+        let combined = dir.join("tmp").join(COMBINED_FILE_NAME);
+        assert_eq!(PathBuf::from(&envs["REQUESTS_CA_BUNDLE"]), combined);
+        assert_eq!(envs["SSL_CERT_FILE"], "/custom.pem");
+        let content = fs::read_to_string(&combined).unwrap();
+        assert!(content.starts_with("# public roots"));
+        assert!(content.contains(PEM_BEGIN));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn invalid_bundle_error_names_the_file() {
+        let dir = temp_dir("invalid");
+        let extra = dir.join("corp.crt");
+        fs::write(&extra, "not a certificate").unwrap();
+        let mut envs = HashMap::from([(
+            EXTRA_CA_BUNDLE_KEY.to_string(),
+            extra.to_string_lossy().to_string(),
+        )]);
+        let err = apply(&mut envs, &dir, &dir.join("python")).unwrap_err();
+        assert!(err.contains("corp.crt"));
+        assert!(!envs.contains_key("REQUESTS_CA_BUNDLE"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
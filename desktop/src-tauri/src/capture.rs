@@ -0,0 +1,317 @@
+//! 后端服务 stdout/stderr 的捕获。
+//!
+//! 子进程的两个输出流接到桌面壳持有的管道上，每个流一个读取线程：按行读出后先检查就绪标记，再放进有界
+//! 队列，由写入线程给每行加上时间和服务名前缀，以追加方式写入原有的 `<服务>.stdout.log` /
+//! `<服务>.stderr.log`，同时交给日志查看器实时显示。写文件或发事件变慢、队列写满时直接丢弃新的行并计数，
+//! 读取不会停下来，子进程也就不会因管道写满而阻塞；丢弃的行数随后写成一行提示。
+//!
+//! 行内容按原始字节写入文件，非 UTF-8 的输出不会被替换；只有发给查看器的内容按 UTF-8 宽松解码。
+//! 单行超过 `MAX_LINE_BYTES` 时按该长度切成多行，一直不换行的输出（进度条、二进制）不会占满内存。
+//! 每批写入时重新打开日志文件，文件被移走或清空后会重新创建，与之前由子进程直接写文件时一致。
+
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::runtime;
+use crate::tasks::CancelToken;
+
+/// 读取线程与写入线程之间最多排队的行数。
+const QUEUE_LINES: usize = 4096;
+/// 写入线程一次最多取出的行数，同一批共用一次文件打开和一个事件。
+const BATCH_LINES: usize = 256;
+/// 单行最多读取的字节数，超出部分作为下一行。
+const MAX_LINE_BYTES: u64 = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+/// 启动读取线程：任务名和线程主体。
+pub type Spawner = Box<dyn Fn(String, Box<dyn FnOnce(CancelToken) + Send>) + Send + Sync>;
+/// 一批新写入的行（服务名、流、不含换行的内容）。
+pub type LineSink = Box<dyn Fn(&str, Stream, &[String]) + Send + Sync>;
+
+/// 桌面壳提供的线程登记与实时日志；未安装时（测试、启动早期）直接启动线程，不发实时日志。
+pub struct Hooks {
+    pub spawn: Spawner,
+    pub sink: LineSink,
+}
+
+static HOOKS: OnceLock<Hooks> = OnceLock::new();
+
+pub fn install(hooks: Hooks) {
+    let _ = HOOKS.set(hooks);
+}
+
+#[derive(Default)]
+struct State {
+    finished: AtomicBool,
+}
+
+/// 一个子进程两个输出流的捕获状态。
+pub struct Output {
+    stdout: Arc<State>,
+    stderr: Arc<State>,
+    logs_dir: PathBuf,
+    service: String,
+}
+
+impl Output {
+    /// 开始捕获。
+    pub fn attach(
+        service: &str,
+        stdout: impl Read + Send + 'static,
+        stderr: impl Read + Send + 'static,
+        logs_dir: &Path,
+    ) -> Self {
+        Self {
+            stdout: capture(service, Stream::Stdout, stdout, logs_dir),
+            stderr: capture(service, Stream::Stderr, stderr, logs_dir),
+            logs_dir: logs_dir.to_path_buf(),
+            service: service.to_string(),
+        }
+    }
+
+    pub fn log(&self, stream: Stream) -> PathBuf {
+        log_path(&self.logs_dir, &self.service, stream)
+    }
+
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// 进程退出后等待剩余输出写入日志文件，最多等待 `timeout`（子进程留下的孙进程可能一直占着管道）。
+    /// 在就绪等待的异步任务中调用（见 services.rs）。
+    pub async fn wait_flushed(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline
+            && !(self.stdout.finished.load(Ordering::SeqCst)
+                && self.stderr.finished.load(Ordering::SeqCst))
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+}
+
+fn capture(
+    service: &str,
+    stream: Stream,
+    pipe: impl Read + Send + 'static,
+    logs_dir: &Path,
+) -> Arc<State> {
+    let state = Arc::new(State::default());
+    let thread_state = state.clone();
+    let service = service.to_string();
+    let log = log_path(logs_dir, &service, stream);
+    let name = format!("output:{service}.{}", stream.name());
+    let run = move |token: CancelToken| {
+        pump(&service, stream, pipe, &log, &token);
+        thread_state.finished.store(true, Ordering::SeqCst);
+    };
+    match HOOKS.get() {
+        Some(hooks) => (hooks.spawn)(name, Box::new(run)),
+        None => {
+            thread::spawn(move || run(CancelToken::default()));
+        }
+    }
+    state
+}
+
+fn pump(service: &str, stream: Stream, pipe: impl Read, log: &Path, token: &CancelToken) {
+    let (sender, receiver) = mpsc::sync_channel(QUEUE_LINES);
+    let dropped = AtomicU64::new(0);
+    thread::scope(|scope| {
+        scope.spawn(|| write_lines(service, stream, log, receiver, &dropped));
+        read_lines(pipe, sender, &dropped, token);
+    });
+}
+
+/// 读到管道关闭（进程退出）或被取消为止；队列满时丢弃该行并计数，超长的行按 `MAX_LINE_BYTES` 切开。
+fn read_lines(
+    pipe: impl Read,
+    sender: SyncSender<Vec<u8>>,
+    dropped: &AtomicU64,
+    token: &CancelToken,
+) {
+    let mut reader = BufReader::new(pipe);
+    loop {
+        let mut line = Vec::new();
+        match (&mut reader)
+            .take(MAX_LINE_BYTES)
+            .read_until(b'\n', &mut line)
+        {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        if let Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) = sender.try_send(line) {
+            dropped.fetch_add(1, Ordering::SeqCst);
+        }
+        if token.is_cancelled() {
+            return;
+        }
+    }
+}
+
+fn write_lines(
+    service: &str,
+    stream: Stream,
+    log: &Path,
+    receiver: Receiver<Vec<u8>>,
+    dropped: &AtomicU64,
+) {
+    let sink = HOOKS.get().map(|hooks| &hooks.sink);
+    let flush = |lines: &[Vec<u8>]| {
+        let lost = dropped.swap(0, Ordering::SeqCst);
+        if lines.is_empty() && lost == 0 {
+            return;
+        }
+        let stamp = runtime::format_utc_timestamp(SystemTime::now());
+        let (bytes, text) = format_batch(&stamp, service, lost, lines);
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(log) {
+            let _ = file.write_all(&bytes);
+        }
+        if let Some(sink) = sink {
+            sink(service, stream, &text);
+        }
+    };
+
+    while let Ok(first) = receiver.recv() {
+        let mut batch = vec![first];
+        batch.extend(receiver.try_iter().take(BATCH_LINES - 1));
+        flush(&batch);
+    }
+    // 最后一批之后丢弃的行也要留下记录
+    flush(&[]);
+}
+
+/// 给每行加上 `时间 [服务] ` 前缀：返回写入文件的原始字节和发给查看器的文本。
+fn format_batch(
+    stamp: &str,
+    service: &str,
+    lost: u64,
+    lines: &[Vec<u8>],
+) -> (Vec<u8>, Vec<String>) {
+    let prefix = format!("{stamp} [{service}] ");
+    let mut bytes = Vec::new();
+    let mut text = Vec::with_capacity(lines.len() + 1);
+    if lost > 0 {
+        let notice = format!("[桌面壳] 输出过快，丢弃了 {lost} 行");
+        bytes.extend_from_slice(format!("{prefix}{notice}\n").as_bytes());
+        text.push(format!("{prefix}{notice}"));
+    }
+    for line in lines {
+        bytes.extend_from_slice(prefix.as_bytes());
+        bytes.extend_from_slice(line);
+        // 进程退出前最后一行可能没有换行
+        if !line.ends_with(b"\n") {
+            bytes.push(b'\n');
+        }
+        let content = line.strip_suffix(b"\n").unwrap_or(line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        text.push(format!("{prefix}{}", String::from_utf8_lossy(content)));
+    }
+    (bytes, text)
+}
+
+/// 服务输出的日志文件，与子进程直接写文件时的文件名相同。
+pub fn log_path(logs_dir: &Path, service: &str, stream: Stream) -> PathBuf {
+    logs_dir.join(format!("{service}.{}.log", stream.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+    use std::fs;
+    use std::io::Cursor;
+
+    #[test]
+    fn lines_are_prefixed_and_kept_byte_for_byte() {
+        let lines = vec![b"ok\r\n".to_vec(), b"caf\xe9\n".to_vec(), b"tail".to_vec()];
+        let (bytes, text) = format_batch("2024-01-01T00:00:00Z", "server", 2, &lines);
+        let mut expected = "2024-01-01T00:00:00Z [server] [桌面壳] 输出过快，丢弃了 2 行\n"
+            .as_bytes()
+            .to_vec();
+        expected.extend_from_slice(b"2024-01-01T00:00:00Z [server] ok\r\n");
+        expected.extend_from_slice(b"2024-01-01T00:00:00Z [server] caf\xe9\n");
+        expected.extend_from_slice(b"2024-01-01T00:00:00Z [server] tail\n");
+        assert_eq!(bytes, expected);
+        assert_eq!(text[1], "2024-01-01T00:00:00Z [server] ok");
+        assert_eq!(text[2], "2024-01-01T00:00:00Z [server] caf\u{fffd}");
+    }
+
+    #[test]
+    fn full_queue_drops_and_counts_without_blocking() {
+        let (sender, receiver) = mpsc::sync_channel(2);
+        let dropped = AtomicU64::new(0);
+        let input = (0..10).map(|i| format!("line {i}\n")).collect::<String>() + "ready\n";
+        read_lines(
+            Cursor::new(input),
+            sender,
+            &dropped,
+            &CancelToken::default(),
+        );
+        assert_eq!(receiver.try_iter().count(), 2);
+        assert_eq!(dropped.load(Ordering::SeqCst), 9);
+    }
+
+    #[test]
+    fn long_lines_are_split_at_the_cap() {
+        let (sender, receiver) = mpsc::sync_channel(8);
+        let cap = MAX_LINE_BYTES as usize;
+        let mut input = vec![b'x'; cap * 2 + 10];
+        input.extend_from_slice(b"\nnext\n");
+        read_lines(
+            Cursor::new(input),
+            sender,
+            &AtomicU64::new(0),
+            &CancelToken::default(),
+        );
+        let lines: Vec<Vec<u8>> = receiver.try_iter().collect();
+        let lens: Vec<usize> = lines.iter().map(Vec::len).collect();
+        assert_eq!(lens, [cap, cap, 11, 5]);
+        assert_eq!(lines[3], b"next\n");
+    }
+
+    #[test]
+    fn output_is_appended_to_existing_log() {
+        let dir = temp_dir("append");
+        let path = log_path(&dir, "batch", Stream::Stderr);
+        fs::write(&path, "earlier\n").unwrap();
+        let output = Output::attach(
+            "batch",
+            Cursor::new(b"fresh ready\n".to_vec()),
+            Cursor::new(b"oops \xff\n".to_vec()),
+            &dir,
+        );
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(output.wait_flushed(Duration::from_secs(5)));
+
+        let stderr = fs::read(&path).unwrap();
+        assert!(stderr.starts_with(b"earlier\n"));
+        assert!(stderr.ends_with(b" [batch] oops \xff\n"));
+        let stdout = fs::read_to_string(log_path(&dir, "batch", Stream::Stdout)).unwrap();
+        assert!(stdout.ends_with(" [batch] fresh ready\n"), "{stdout}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
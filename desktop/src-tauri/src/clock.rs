@@ -0,0 +1,305 @@
+//! 本机时钟偏差检测。
+//!
+//! 本机时间偏差几分钟就会让站点的签名、Cookie 校验失败，表现为“所有站点都返回签名错误”。
+//! 开启桌面设置 `clock_check` 后，启动时及之后每天向 NTP 服务器（默认 pool.ntp.org）查询一次；
+//! UDP 不通时退回读取 HTTP 响应的 Date 头。偏差超过 30 秒时发出 `clock-skew-detected` 事件与系统通知。
+//! 每次查询最多等待 2 秒，离线时静默跳过。
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::tasks::{self, Scope};
+use crate::{runtime, settings};
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// 检查设置是否开启、是否到了下一次查询时间的间隔。
+const SETTINGS_POLL: Duration = Duration::from_secs(10 * 60);
+pub const SKEW_THRESHOLD_MS: i64 = 30_000;
+/// NTP 时间从 1900-01-01 起算，与 Unix 时间相差的秒数。
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+const HTTP_FALLBACK_HOST: &str = "www.baidu.com";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ClockSkew {
+    /// 标准时间减去本机时间（毫秒），正数表示本机时钟偏慢。
+    pub offset_ms: i64,
+    pub source: String,
+    pub checked_at: String,
+}
+
+impl ClockSkew {
+    pub fn exceeds_threshold(&self) -> bool {
+        self.offset_ms.abs() > SKEW_THRESHOLD_MS
+    }
+
+    pub fn describe(&self) -> String {
+        let seconds = self.offset_ms.abs() as f64 / 1000.0;
+        let direction = if self.offset_ms > 0 { "慢" } else { "快" };
+        format!(
+            "本机时钟比标准时间{direction} {seconds:.1} 秒（{}）",
+            self.source
+        )
+    }
+}
+
+/// 最近一次测得的偏差，托管在 Tauri state 中。
+#[derive(Default)]
+pub struct ClockState {
+    last: Mutex<Option<ClockSkew>>,
+}
+
+pub fn last_skew(app: &AppHandle) -> Option<ClockSkew> {
+    app.try_state::<ClockState>()
+        .and_then(|state| state.last.lock().ok().and_then(|last| last.clone()))
+}
+
+pub fn start(app: &AppHandle) {
+    app.manage(ClockState::default());
+    tasks::spawn(app, "clock-check", Scope::Shell, move |app, token| {
+        let mut last_check: Option<Instant> = None;
+        loop {
+            let current = settings::current(&app);
+            let due = last_check.is_none_or(|at| at.elapsed() >= CHECK_INTERVAL);
+            if current.clock_check && due {
+                last_check = Some(Instant::now());
+                if let Some(skew) = measure(&current.clock_check_server) {
+                    report(&app, skew);
+                }
+            }
+            if !token.sleep(SETTINGS_POLL) {
+                return;
+            }
+        }
+    });
+}
+
+fn report(app: &AppHandle, skew: ClockSkew) {
+    if let Some(state) = app.try_state::<ClockState>() {
+        if let Ok(mut last) = state.last.lock() {
+            *last = Some(skew.clone());
+        }
+    }
+    if !skew.exceeds_threshold() {
+        runtime::shell_log(app, &format!("[INFO] 时钟校验: {}", skew.describe()));
+        return;
+    }
+
+    runtime::shell_log(app, &format!("[WARN] 时钟校验: {}", skew.describe()));
+    journal::record(app, Severity::Warn, None, skew.describe());
+    events::emit(app, "clock-skew-detected", Replay::Latest, &skew);
+    let _ = app
+        .notification()
+        .builder()
+        .title("PT Nexus 检测到系统时间不准")
+        .body(format!(
+            "{}。时间偏差会导致站点返回签名错误、登录失效、种子下载失败等问题，请在系统设置中开启自动同步时间。",
+            skew.describe()
+        ))
+        .show();
+}
+
+/// 依次尝试 NTP 与 HTTP Date 头，都失败（通常是离线）时返回 None。
+fn measure(server: &str) -> Option<ClockSkew> {
+    let server = server.trim();
+    let (offset_ms, source) = match sntp_offset(server) {
+        Ok(offset) => (offset, format!("NTP {server}")),
+        Err(_) => (
+            http_date_offset(HTTP_FALLBACK_HOST).ok()?,
+            format!("HTTP {HTTP_FALLBACK_HOST}"),
+        ),
+    };
+    Some(ClockSkew {
+        offset_ms,
+        source,
+        checked_at: runtime::format_utc_timestamp(SystemTime::now()),
+    })
+}
+
+fn resolve(host: &str, port: u16) -> Result<SocketAddr, String> {
+    (host, port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| format!("无法解析地址 {host}:{port}"))
+}
+
+fn unix_ms(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// SNTP（RFC 4330）客户端请求，返回 ((T2 - T1) + (T3 - T4)) / 2。
+fn sntp_offset(server: &str) -> Result<i64, String> {
+    let addr = resolve(server, 123)?;
+    let bind: SocketAddr = if addr.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind).map_err(|e| format!("创建 UDP 套接字失败: {e}"))?;
+    let _ = socket.set_read_timeout(Some(QUERY_TIMEOUT));
+    let _ = socket.set_write_timeout(Some(QUERY_TIMEOUT));
+
+    // LI = 0，版本 3，模式 3（客户端）
+    let mut request = [0u8; 48];
+    request[0] = 0x1B;
+    let t1 = unix_ms(SystemTime::now());
+    socket
+        .send_to(&request, addr)
+        .map_err(|e| format!("发送 NTP 请求失败: {e}"))?;
+    let mut response = [0u8; 48];
+    let (len, _) = socket
+        .recv_from(&mut response)
+        .map_err(|e| format!("等待 NTP 响应失败: {e}"))?;
+    let t4 = unix_ms(SystemTime::now());
+    if len < 48 {
+        return Err("NTP 响应过短".to_string());
+    }
+
+    let t2 = ntp_timestamp_ms(&response[32..40]).ok_or("NTP 响应缺少接收时间")?;
+    let t3 = ntp_timestamp_ms(&response[40..48]).ok_or("NTP 响应缺少发送时间")?;
+    Ok(((t2 - t1) + (t3 - t4)) / 2)
+}
+
+/// 64 位 NTP 时间戳（32 位秒 + 32 位小数）转为 Unix 毫秒；全零表示服务器未填写。
+fn ntp_timestamp_ms(bytes: &[u8]) -> Option<i64> {
+    let seconds = u64::from(u32::from_be_bytes(bytes.get(0..4)?.try_into().ok()?));
+    let fraction = u64::from(u32::from_be_bytes(bytes.get(4..8)?.try_into().ok()?));
+    if seconds == 0 && fraction == 0 {
+        return None;
+    }
+    let unix_seconds = seconds.checked_sub(NTP_UNIX_OFFSET)? as i64;
+    Some(unix_seconds * 1000 + ((fraction * 1000) >> 32) as i64)
+}
+
+/// 读取 HTTP 响应的 Date 头估算偏差，精度为秒级，足够判断 30 秒阈值。
+fn http_date_offset(host: &str) -> Result<i64, String> {
+    let addr = resolve(host, 80)?;
+    let mut stream = TcpStream::connect_timeout(&addr, QUERY_TIMEOUT)
+        .map_err(|e| format!("连接 {host} 失败: {e}"))?;
+    let _ = stream.set_read_timeout(Some(QUERY_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(QUERY_TIMEOUT));
+
+    let sent = unix_ms(SystemTime::now());
+    write!(stream, "HEAD / HTTP/1.0\r\nHost: {host}\r\n\r\n")
+        .map_err(|e| format!("发送请求失败: {e}"))?;
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < 16 * 1024 {
+        let n = stream
+            .read(&mut buf)
+            .map_err(|e| format!("读取响应失败: {e}"))?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    let received = unix_ms(SystemTime::now());
+
+    let head = String::from_utf8_lossy(&head);
+    let date = head
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("date")
+                .then(|| value.trim())
+        })
+        .ok_or("响应中没有 Date 头")?;
+    let server_seconds =
+        parse_http_date(date).ok_or_else(|| format!("无法解析 Date 头: {date}"))?;
+    // Date 只精确到秒，取其中点，并以请求往返的中点作为本机时间
+    Ok(server_seconds * 1000 + 500 - (sent + received) / 2)
+}
+
+/// 解析 IMF-fixdate，如 `Sun, 06 Nov 1994 08:49:37 GMT`，返回 Unix 秒。
+fn parse_http_date(value: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let mut parts = value.split_whitespace().skip(1);
+    let day: i64 = parts.next()?.parse().ok()?;
+    let month_name = parts.next()?;
+    let month = MONTHS.iter().position(|m| *m == month_name)? as i64 + 1;
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut time = parts
+        .next()?
+        .split(':')
+        .map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if parts.next()? != "GMT" {
+        return None;
+    }
+
+    // Howard Hinnant 的 days_from_civil 算法
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ntp_timestamps_are_converted_to_unix_ms() {
+        // 2023-11-14T22:13:20.5Z
+        let seconds = (1_700_000_000 + NTP_UNIX_OFFSET) as u32;
+        let mut bytes = seconds.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&0x8000_0000u32.to_be_bytes());
+        assert_eq!(ntp_timestamp_ms(&bytes), Some(1_700_000_000_500));
+        assert_eq!(ntp_timestamp_ms(&[0; 8]), None);
+    }
+
+    #[test]
+    fn http_dates_are_parsed() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(784_111_777)
+        );
+        assert_eq!(
+            parse_http_date("Tue, 14 Nov 2023 22:13:20 GMT"),
+            Some(1_700_000_000)
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"),
+            Some(1_709_164_800)
+        );
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 PST"), None);
+    }
+
+    #[test]
+    fn skew_is_described_with_direction() {
+        let skew = ClockSkew {
+            offset_ms: -95_400,
+            source: "NTP pool.ntp.org".to_string(),
+            checked_at: String::new(),
+        };
+        assert!(skew.exceeds_threshold());
+        assert_eq!(
+            skew.describe(),
+            "本机时钟比标准时间快 95.4 秒（NTP pool.ntp.org）"
+        );
+        assert!(!ClockSkew {
+            offset_ms: 29_000,
+            ..skew
+        }
+        .exceeds_threshold());
+    }
+}
@@ -0,0 +1,299 @@
+//! 命令返回给 WebUI 的错误。
+//!
+//! 命令失败时前端收到 `{ code, message, details? }`：`code` 是稳定的英文错误码（见 [`CODES`]），
+//! 供 WebUI 区分“没有权限”“文件不存在”“后端未启动”等情况、决定是否重试或显示本地化文案；
+//! `message` 是桌面壳给出的中文说明，可直接显示；`details` 只在部分错误码下出现，结构见各错误码的说明。
+//! 错误码一经发布不再改名或复用，新增的错误码追加到表末尾。
+
+use std::fmt;
+use std::io;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::portconfig::PortProblems;
+use crate::runtime::BootstrapError;
+
+pub const FAILED: &str = "failed";
+pub const INVALID_ARGUMENT: &str = "invalid_argument";
+pub const NOT_FOUND: &str = "not_found";
+pub const PERMISSION_DENIED: &str = "permission_denied";
+pub const ALREADY_EXISTS: &str = "already_exists";
+pub const TIMED_OUT: &str = "timed_out";
+pub const IO: &str = "io";
+pub const NOT_READY: &str = "not_ready";
+pub const ALREADY_RUNNING: &str = "already_running";
+pub const BACKEND_UNAVAILABLE: &str = "backend_unavailable";
+pub const INVALID_PORT_CONFIG: &str = "invalid_port_config";
+pub const BOOTSTRAP_FAILED: &str = "bootstrap_failed";
+pub const SERVICE_FAILED: &str = "service_failed";
+pub const DATABASE_CORRUPT: &str = "database_corrupt";
+pub const DATABASE_UNREACHABLE: &str = "database_unreachable";
+pub const RUNTIME_FILES_MISSING: &str = "runtime_files_missing";
+pub const BROWSER_LOCKED: &str = "browser_locked";
+pub const UNSUPPORTED: &str = "unsupported";
+
+/// 全部错误码及其含义，WebUI 通过 `list_error_codes` 读取。服务启动失败时从 stderr 识别出的错误码见
+/// pyfailure.rs，同样附带 details { service }。
+pub const CODES: &[(&str, &str)] = &[
+    (FAILED, "其他失败，原因见 message"),
+    (INVALID_ARGUMENT, "参数不合法，修改后重试"),
+    (NOT_FOUND, "文件或目录不存在"),
+    (PERMISSION_DENIED, "没有读写权限"),
+    (ALREADY_EXISTS, "目标已存在"),
+    (TIMED_OUT, "操作超时，可以重试"),
+    (IO, "其他文件或系统调用错误"),
+    (NOT_READY, "运行时或主窗口尚未就绪，稍后重试"),
+    (ALREADY_RUNNING, "后端服务已在运行"),
+    (BACKEND_UNAVAILABLE, "后端服务无法访问"),
+    (
+        INVALID_PORT_CONFIG,
+        "端口设置有误；details 为 { message, errors, suggestions }，同 validate_port_config 的返回值",
+    ),
+    (BOOTSTRAP_FAILED, "启动失败"),
+    (SERVICE_FAILED, "某个服务启动失败；details 为 { service }"),
+    (
+        DATABASE_CORRUPT,
+        "SQLite 数据库完整性检查失败；details 为 { path, latest_backup }",
+    ),
+    (
+        DATABASE_UNREACHABLE,
+        "无法连接 MySQL / PostgreSQL；details 为 { db_type, address }",
+    ),
+    (
+        RUNTIME_FILES_MISSING,
+        "运行目录不完整；details 为 { runtime_root, missing: [{ component, path, alternative }] }",
+    ),
+    ("python_bytecode_corrupt", "服务启动失败：Python 字节码文件损坏；details 为 { service }"),
+    ("python_runtime_broken", "服务启动失败：内置 Python 运行时不完整；details 为 { service }"),
+    ("python_dll_load_failed", "服务启动失败：Python 扩展模块无法加载；details 为 { service }"),
+    ("database_open_failed", "服务启动失败：无法打开 SQLite 数据库文件；details 为 { service }"),
+    ("python_module_missing", "服务启动失败：Python 模块缺失；details 为 { service }"),
+    ("python_import_failed", "服务启动失败：Python 模块导入失败；details 为 { service }"),
+    (BROWSER_LOCKED, "浏览器正在使用 Cookie 文件，完全关闭浏览器后重试"),
+    (UNSUPPORTED, "当前系统或浏览器不支持该操作"),
+];
+
+/// `list_error_codes` 返回的一项。
+#[derive(Debug, Serialize)]
+pub struct CodeInfo {
+    pub code: &'static str,
+    pub description: &'static str,
+}
+
+pub fn codes() -> Vec<CodeInfo> {
+    CODES
+        .iter()
+        .map(|&(code, description)| CodeInfo { code, description })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommandError {
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl CommandError {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// 参数校验失败。
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::new(INVALID_ARGUMENT, message)
+    }
+
+    /// 运行时或窗口尚未就绪。
+    pub fn not_ready(message: impl Into<String>) -> Self {
+        Self::new(NOT_READY, message)
+    }
+
+    /// 文件操作失败，错误码取自 `err.kind()`，`context` 说明在做什么。
+    pub fn io(context: impl fmt::Display, err: io::Error) -> Self {
+        Self::new(io_code(err.kind()), format!("{context}: {err}"))
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// 尚未细分的错误（各模块返回的中文说明）。
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::new(FAILED, message)
+    }
+}
+
+impl From<io::Error> for CommandError {
+    fn from(err: io::Error) -> Self {
+        Self::new(io_code(err.kind()), err.to_string())
+    }
+}
+
+impl From<BootstrapError> for CommandError {
+    fn from(err: BootstrapError) -> Self {
+        let message = err.to_string();
+        match err {
+            BootstrapError::Failed(_) => Self::new(BOOTSTRAP_FAILED, message),
+            BootstrapError::ServiceFailed {
+                service, diagnosis, ..
+            } => {
+                let code = diagnosis.map_or(SERVICE_FAILED, |signature| signature.code);
+                Self::new(code, message).with_details(json!({ "service": service }))
+            }
+            BootstrapError::DatabaseCorrupt {
+                path,
+                latest_backup,
+                ..
+            } => Self::new(DATABASE_CORRUPT, message).with_details(json!({
+                "path": path,
+                "latest_backup": latest_backup,
+            })),
+            BootstrapError::DatabaseUnreachable { db_type, address } => {
+                Self::new(DATABASE_UNREACHABLE, message)
+                    .with_details(json!({ "db_type": db_type, "address": address }))
+            }
+            BootstrapError::MissingFiles {
+                runtime_root,
+                missing,
+            } => {
+                let missing: Vec<Value> = missing
+                    .into_iter()
+                    .map(|file| {
+                        json!({
+                            "component": file.component,
+                            "path": file.path,
+                            "alternative": file.alternative,
+                        })
+                    })
+                    .collect();
+                Self::new(RUNTIME_FILES_MISSING, message).with_details(json!({
+                    "runtime_root": runtime_root,
+                    "missing": missing,
+                }))
+            }
+        }
+    }
+}
+
+impl From<PortProblems> for CommandError {
+    fn from(problems: PortProblems) -> Self {
+        let details = serde_json::to_value(&problems).unwrap_or(Value::Null);
+        Self::new(INVALID_PORT_CONFIG, problems.message).with_details(details)
+    }
+}
+
+fn io_code(kind: io::ErrorKind) -> &'static str {
+    match kind {
+        io::ErrorKind::NotFound => NOT_FOUND,
+        io::ErrorKind::PermissionDenied => PERMISSION_DENIED,
+        io::ErrorKind::AlreadyExists => ALREADY_EXISTS,
+        io::ErrorKind::TimedOut => TIMED_OUT,
+        io::ErrorKind::InvalidInput => INVALID_ARGUMENT,
+        _ => IO,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn known(code: &str) -> bool {
+        CODES.iter().any(|(known, _)| *known == code)
+    }
+
+    #[test]
+    fn codes_are_unique_and_stable_identifiers() {
+        for (index, (code, description)) in CODES.iter().enumerate() {
+            assert!(
+                CODES[index + 1..].iter().all(|(other, _)| other != code),
+                "重复的错误码 {code}"
+            );
+            assert!(code.chars().all(|c| c.is_ascii_lowercase() || c == '_'));
+            assert!(!description.is_empty());
+        }
+        let listed = serde_json::to_value(codes()).unwrap();
+        assert_eq!(listed.as_array().unwrap().len(), CODES.len());
+        assert_eq!(
+            listed[0],
+            json!({ "code": "failed", "description": CODES[0].1 })
+        );
+    }
+
+    #[test]
+    fn conversions_use_listed_codes() {
+        let denied = CommandError::io(
+            "打开日志目录失败",
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        assert_eq!(denied.code, PERMISSION_DENIED);
+        assert!(denied.message.starts_with("打开日志目录失败: "));
+        assert_eq!(
+            CommandError::from(io::Error::from(io::ErrorKind::NotFound)).code,
+            NOT_FOUND
+        );
+        assert_eq!(
+            CommandError::from(io::Error::from(io::ErrorKind::BrokenPipe)).code,
+            IO
+        );
+
+        let unreachable = CommandError::from(BootstrapError::DatabaseUnreachable {
+            db_type: "mysql".to_string(),
+            address: "db:3306".to_string(),
+        });
+        assert_eq!(unreachable.code, DATABASE_UNREACHABLE);
+        assert_eq!(unreachable.details.as_ref().unwrap()["address"], "db:3306");
+        let corrupt = CommandError::from(BootstrapError::DatabaseCorrupt {
+            path: PathBuf::from("ptnexus.db"),
+            detail: "malformed".to_string(),
+            latest_backup: None,
+        });
+        assert_eq!(corrupt.code, DATABASE_CORRUPT);
+        for signature in crate::pyfailure::SIGNATURES {
+            let failed = CommandError::from(BootstrapError::ServiceFailed {
+                service: "server".to_string(),
+                message: "x".to_string(),
+                diagnosis: Some(signature),
+            });
+            assert!(known(failed.code), "{} 未列入 CODES", failed.code);
+            assert_eq!(failed.details.as_ref().unwrap()["service"], "server");
+        }
+        for code in [
+            unreachable.code,
+            corrupt.code,
+            CommandError::from(BootstrapError::Failed("x".to_string())).code,
+            CommandError::from("x".to_string()).code,
+            CommandError::invalid("x").code,
+            CommandError::not_ready("x").code,
+        ] {
+            assert!(known(code), "{code} 未列入 CODES");
+        }
+    }
+
+    #[test]
+    fn serializes_without_empty_details() {
+        let value = serde_json::to_value(CommandError::invalid("名称不能为空")).unwrap();
+        assert_eq!(
+            value,
+            json!({ "code": "invalid_argument", "message": "名称不能为空" })
+        );
+    }
+}
@@ -0,0 +1,325 @@
+//! server 的 config.json 损坏时自动恢复。
+//!
+//! 断电等情况可能让 config.json 只写了一半，server 启动时 JSON 解析失败会反复退出。
+//! 启动 server 之前先校验该文件：无法解析时改名为 `config.json.corrupt-<时间戳>` 保留现场，
+//! 再从 `backups/config/` 中最近一份可用的备份恢复；没有备份时由 server 重新生成默认配置。
+//! 备份在每次正常退出时写入。所有改名与复制都先写临时文件再 rename，并记入 shell.log。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::{fsutil, runtime};
+
+const BACKUP_PREFIX: &str = "config-";
+const BACKUP_SUFFIX: &str = ".json";
+const BACKUP_KEEP: usize = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigRecovery {
+    /// 损坏文件改名后的位置。
+    pub corrupt_path: String,
+    /// 用于恢复的备份；为 None 表示没有可用备份，设置已重置为默认值。
+    pub restored_from: Option<String>,
+}
+
+pub fn backups_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("backups").join("config")
+}
+
+/// bootstrap 中、启动 server 之前调用。恢复失败时返回错误阻止启动。
+pub fn preflight(app: &AppHandle, config_path: &Path, logs_dir: &Path) -> Result<(), String> {
+    let Some(data_dir) = config_path.parent() else {
+        return Ok(());
+    };
+    let log = |message: &str| runtime::append_shell_log(logs_dir, message);
+    let Some(recovery) = recover(config_path, &backups_dir(data_dir), &log)? else {
+        return Ok(());
+    };
+
+    let (summary, body) = match &recovery.restored_from {
+        Some(backup) => (
+            format!("config.json 已损坏，已从备份恢复: {backup}"),
+            "配置文件 config.json 已损坏，已从最近一次正常退出时的备份恢复。损坏的文件已另存以便排查。"
+                .to_string(),
+        ),
+        None => (
+            "config.json 已损坏且没有可用备份，设置已重置".to_string(),
+            "配置文件 config.json 已损坏且没有可用备份，所有设置已重置为默认值，请重新配置。损坏的文件已另存以便排查。"
+                .to_string(),
+        ),
+    };
+    journal::record(app, Severity::Warn, Some("server"), summary);
+    crate::power::wake(app);
+    events::emit(app, "config-recovered", Replay::All, &recovery);
+    let _ = app
+        .notification()
+        .builder()
+        .title("PT Nexus 配置文件已恢复")
+        .body(body)
+        .show();
+    Ok(())
+}
+
+/// 正常退出时调用：config.json 可以解析时复制一份到备份目录。
+pub fn backup_on_shutdown(app: &AppHandle) {
+    let Some(context) = app
+        .try_state::<runtime::RuntimeManager>()
+        .and_then(|rt| rt.context())
+    else {
+        return;
+    };
+    let Some(config_path) = context.common_env.get("CONFIG_FILE").map(PathBuf::from) else {
+        return;
+    };
+    let Some(data_dir) = config_path.parent() else {
+        return;
+    };
+
+    match backup(&config_path, &backups_dir(data_dir), BACKUP_KEEP) {
+        Ok(Some(path)) => runtime::shell_log(
+            app,
+            &format!("[INFO] 已备份 config.json: {}", path.display()),
+        ),
+        Ok(None) => {}
+        Err(err) => runtime::shell_log(app, &format!("[WARN] {err}")),
+    }
+}
+
+/// config.json 不存在或可以解析时返回 None；否则改名保留并尝试从备份恢复。
+fn recover(
+    config_path: &Path,
+    backups_dir: &Path,
+    log: &dyn Fn(&str),
+) -> Result<Option<ConfigRecovery>, String> {
+    let content = match fs::read(config_path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("读取 {} 失败: {err}", config_path.display())),
+    };
+    let Err(reason) = validate(&content) else {
+        return Ok(None);
+    };
+    log(&format!(
+        "[WARN] {} 无法解析: {reason}",
+        config_path.display()
+    ));
+
+    let stamp = runtime::format_utc_timestamp(SystemTime::now()).replace(':', "-");
+    let corrupt_path = sibling(config_path, &format!(".corrupt-{stamp}"));
+    fs::rename(config_path, &corrupt_path).map_err(|e| {
+        format!(
+            "重命名损坏的配置文件失败 ({} -> {}): {e}",
+            config_path.display(),
+            corrupt_path.display()
+        )
+    })?;
+    log(&format!(
+        "[INFO] 已将损坏的配置文件重命名为 {}",
+        corrupt_path.display()
+    ));
+
+    // 备份本身也可能有问题，从新到旧找第一份能解析的
+    let usable = list_backups(backups_dir).into_iter().rev().find(|backup| {
+        fs::read(backup)
+            .map(|content| validate(&content).is_ok())
+            .unwrap_or(false)
+    });
+    let restored_from = match usable {
+        Some(backup) => {
+            copy_atomic(&backup, config_path)?;
+            log(&format!(
+                "[INFO] 已从备份 {} 恢复 {}",
+                backup.display(),
+                config_path.display()
+            ));
+            Some(backup.to_string_lossy().to_string())
+        }
+        None => {
+            log("[WARN] 没有可用的 config.json 备份，server 将生成默认配置");
+            None
+        }
+    };
+
+    Ok(Some(ConfigRecovery {
+        corrupt_path: corrupt_path.to_string_lossy().to_string(),
+        restored_from,
+    }))
+}
+
+/// server 要求顶层是 JSON 对象。
+fn validate(content: &[u8]) -> Result<(), String> {
+    match serde_json::from_slice::<serde_json::Value>(content) {
+        Ok(serde_json::Value::Object(_)) => Ok(()),
+        Ok(_) => Err("顶层不是 JSON 对象".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn backup(config_path: &Path, backups_dir: &Path, keep: usize) -> Result<Option<PathBuf>, String> {
+    let Ok(content) = fs::read(config_path) else {
+        return Ok(None);
+    };
+    // 不把已经损坏的文件备份进去，否则会挤掉可用的备份
+    if validate(&content).is_err() {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(backups_dir)
+        .map_err(|e| format!("创建配置备份目录失败 ({}): {e}", backups_dir.display()))?;
+    let stamp = runtime::format_utc_timestamp(SystemTime::now()).replace(':', "-");
+    let target = backups_dir.join(format!("{BACKUP_PREFIX}{stamp}{BACKUP_SUFFIX}"));
+    copy_atomic(config_path, &target)?;
+
+    let backups = list_backups(backups_dir);
+    let excess = backups.len().saturating_sub(keep);
+    for old in backups.into_iter().take(excess) {
+        let _ = fs::remove_file(old);
+    }
+    Ok(Some(target))
+}
+
+fn copy_atomic(from: &Path, to: &Path) -> Result<(), String> {
+    fsutil::atomic_copy(from, to)
+        .map_err(|e| format!("复制 {} 到 {} 失败: {e}", from.display(), to.display()))
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// 备份文件名自带 UTC 时间戳，按文件名排序即按时间排序。
+fn list_backups(backups_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(backups_dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with(BACKUP_PREFIX) && n.ends_with(BACKUP_SUFFIX))
+                .unwrap_or(false)
+        })
+        .collect();
+    backups.sort();
+    backups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+    use std::cell::RefCell;
+
+    fn run_recover(config: &Path, backups: &Path) -> (Option<ConfigRecovery>, Vec<String>) {
+        let lines = RefCell::new(Vec::new());
+        let log = |line: &str| lines.borrow_mut().push(line.to_string());
+        let recovery = recover(config, backups, &log).unwrap();
+        (recovery, lines.into_inner())
+    }
+
+    #[test]
+    fn valid_or_missing_config_is_left_alone() {
+        let dir = temp_dir("valid");
+        let config = dir.join("config.json");
+        let backups = backups_dir(&dir);
+
+        assert!(run_recover(&config, &backups).0.is_none());
+        fs::write(&config, r#"{"cookiecloud": {}}"#).unwrap();
+        let (recovery, lines) = run_recover(&config, &backups);
+        assert!(recovery.is_none());
+        assert!(lines.is_empty());
+        assert!(config.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn truncated_config_is_restored_from_newest_usable_backup() {
+        let dir = temp_dir("restore");
+        let config = dir.join("config.json");
+        let backups = backups_dir(&dir);
+        fs::create_dir_all(&backups).unwrap();
+        fs::write(
+            backups.join("config-2024-01-01T00-00-00Z.json"),
+            r#"{"v": 1}"#,
+        )
+        .unwrap();
+        fs::write(
+            backups.join("config-2024-02-01T00-00-00Z.json"),
+            r#"{"v": 2}"#,
+        )
+        .unwrap();
+        fs::write(
+            backups.join("config-2024-03-01T00-00-00Z.json"),
+            r#"{"v": "#,
+        )
+        .unwrap();
+        fs::write(&config, r#"{"cross_seed": {"seedvault_"#).unwrap();
+
+        let (recovery, lines) = run_recover(&config, &backups);
+        let recovery = recovery.unwrap();
+        assert_eq!(fs::read_to_string(&config).unwrap(), r#"{"v": 2}"#);
+        assert!(recovery
+            .restored_from
+            .unwrap()
+            .ends_with("config-2024-02-01T00-00-00Z.json"));
+        assert!(recovery.corrupt_path.contains("config.json.corrupt-"));
+        assert_eq!(
+            fs::read_to_string(&recovery.corrupt_path).unwrap(),
+            r#"{"cross_seed": {"seedvault_"#
+        );
+        assert_eq!(lines.len(), 3);
+        assert!(!fsutil::tmp_path(&config).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn without_backup_the_corrupt_file_is_moved_aside() {
+        let dir = temp_dir("reset");
+        let config = dir.join("config.json");
+        fs::write(&config, "[1, 2]").unwrap();
+
+        let (recovery, _) = run_recover(&config, &backups_dir(&dir));
+        let recovery = recovery.unwrap();
+        assert!(recovery.restored_from.is_none());
+        assert!(!config.exists());
+        assert!(Path::new(&recovery.corrupt_path).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn backups_skip_corrupt_files_and_keep_the_newest() {
+        let dir = temp_dir("backup");
+        let config = dir.join("config.json");
+        let backups = backups_dir(&dir);
+        fs::create_dir_all(&backups).unwrap();
+        for month in 1..=3 {
+            fs::write(
+                backups.join(format!("config-2024-0{month}-01T00-00-00Z.json")),
+                "{}",
+            )
+            .unwrap();
+        }
+
+        fs::write(&config, "{").unwrap();
+        assert!(backup(&config, &backups, 2).unwrap().is_none());
+        assert_eq!(list_backups(&backups).len(), 3);
+
+        fs::write(&config, r#"{"ok": true}"#).unwrap();
+        let written = backup(&config, &backups, 2).unwrap().unwrap();
+        let remaining = list_backups(&backups);
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(remaining.last(), Some(&written));
+        assert_eq!(fs::read_to_string(&written).unwrap(), r#"{"ok": true}"#);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,233 @@
+//! 后端进程崩溃时的转储文件。
+//!
+//! PyInstaller 打包的 server.exe 发生访问冲突时直接退出，stderr 中什么也不会留下。
+//! Windows 上启动时在当前用户的注册表中为各服务程序配置 Windows 错误报告（WER）的 LocalDumps，
+//! 崩溃时把小型转储写到 `<data_dir>/logs/dumps`，每个程序最多保留几份；停止服务时删除这些配置。
+//! 通用的 python.exe 不做配置，以免收集到其他程序的转储。
+//! Linux 上把 core 文件大小的软限制提高到硬限制，子进程继承该限制；core 文件的位置由
+//! `/proc/sys/kernel/core_pattern` 决定，启动时记录在 shell.log 中（由 systemd-coredump 接管时用
+//! `coredumpctl list` 查看）。
+//! 超过保留时间的转储在启动时清理，诊断信息只列出文件名，不读取内容。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// 转储保留两周，足够回溯最近的问题。
+const DUMP_RETENTION: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+/// 配置 LocalDumps 的服务程序（不含扩展名）。
+#[cfg(target_os = "windows")]
+const DUMPED_PROGRAMS: &[&str] = &["server", "batch", "updater"];
+/// WER 对每个程序保留的转储数量。
+#[cfg(target_os = "windows")]
+const DUMP_COUNT: u32 = 3;
+
+pub fn dumps_dir(logs_dir: &Path) -> PathBuf {
+    logs_dir.join("dumps")
+}
+
+/// 启动服务前调用：清理过期转储并配置崩溃转储，返回需要写入 shell.log 的消息。
+pub fn prepare(logs_dir: &Path) -> Vec<String> {
+    let dir = dumps_dir(logs_dir);
+    let mut messages = Vec::new();
+    let removed = remove_older_than(&dir, DUMP_RETENTION);
+    if removed > 0 {
+        messages.push(format!("[INFO] 已清理 {removed} 个过期的崩溃转储"));
+    }
+    messages.extend(platform::enable(&dir));
+    messages
+}
+
+/// 停止服务时调用，撤销启动时写入的系统配置。
+pub fn disable() {
+    platform::disable();
+}
+
+/// 转储目录中的文件名，新的在前。
+pub fn list(logs_dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dumps_dir(logs_dir)) else {
+        return Vec::new();
+    };
+    let mut dumps: Vec<(SystemTime, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .map(|entry| {
+            let modified = entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (modified, entry.file_name().to_string_lossy().to_string())
+        })
+        .collect();
+    dumps.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    dumps.into_iter().map(|(_, name)| name).collect()
+}
+
+fn remove_older_than(dir: &Path, age: Duration) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let now = SystemTime::now();
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|elapsed| elapsed >= age)
+        })
+        .filter(|entry| fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::os::windows::process::CommandExt;
+    use std::path::Path;
+    use std::process::Command;
+
+    use super::{DUMPED_PROGRAMS, DUMP_COUNT};
+    use crate::runtime::exe_name;
+
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    const LOCAL_DUMPS_KEY: &str =
+        r"HKCU\Software\Microsoft\Windows\Windows Error Reporting\LocalDumps";
+    /// 1 = 小型转储，只含线程栈与模块列表，体积通常只有几百 KB。
+    const DUMP_TYPE_MINI: u32 = 1;
+
+    pub fn enable(dir: &Path) -> Vec<String> {
+        if let Err(err) = std::fs::create_dir_all(dir) {
+            return vec![format!(
+                "[WARN] 创建崩溃转储目录失败 ({}): {err}",
+                dir.display()
+            )];
+        }
+        let folder = dir.to_string_lossy().to_string();
+        let count = DUMP_COUNT.to_string();
+        let dump_type = DUMP_TYPE_MINI.to_string();
+        let mut failed = Vec::new();
+        for program in DUMPED_PROGRAMS {
+            let key = format!(r"{LOCAL_DUMPS_KEY}\{}", exe_name(program));
+            let values = [
+                ("DumpFolder", "REG_EXPAND_SZ", folder.as_str()),
+                ("DumpCount", "REG_DWORD", count.as_str()),
+                ("DumpType", "REG_DWORD", dump_type.as_str()),
+            ];
+            if !values.iter().all(|&(name, kind, data)| {
+                reg(&["add", &key, "/v", name, "/t", kind, "/d", data, "/f"])
+            }) {
+                failed.push(exe_name(program));
+            }
+        }
+        if failed.is_empty() {
+            vec![format!("[INFO] 已开启崩溃转储，位置: {}", dir.display())]
+        } else {
+            vec![format!("[WARN] 为 {} 配置崩溃转储失败", failed.join(", "))]
+        }
+    }
+
+    pub fn disable() {
+        for program in DUMPED_PROGRAMS {
+            let key = format!(r"{LOCAL_DUMPS_KEY}\{}", exe_name(program));
+            let _ = reg(&["delete", &key, "/f"]);
+        }
+    }
+
+    fn reg(args: &[&str]) -> bool {
+        Command::new("reg")
+            .args(args)
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::ffi::{c_int, c_ulong};
+    use std::path::Path;
+
+    const RLIMIT_CORE: c_int = 4;
+
+    #[repr(C)]
+    struct Rlimit {
+        rlim_cur: c_ulong,
+        rlim_max: c_ulong,
+    }
+
+    extern "C" {
+        fn getrlimit(resource: c_int, rlim: *mut Rlimit) -> c_int;
+        fn setrlimit(resource: c_int, rlim: *const Rlimit) -> c_int;
+    }
+
+    /// 提高 core 文件大小的软限制；子进程启动时继承。
+    pub fn enable(_dir: &Path) -> Vec<String> {
+        let mut limit = Rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: 传入的指针指向栈上有效的 Rlimit，布局与 glibc/musl 的 struct rlimit 一致。
+        let raised = unsafe {
+            getrlimit(RLIMIT_CORE, &mut limit) == 0 && {
+                limit.rlim_cur = limit.rlim_max;
+                setrlimit(RLIMIT_CORE, &limit) == 0
+            }
+        };
+        if !raised {
+            return vec![
+                "[WARN] 无法调整 core 文件大小限制，后端崩溃时不会生成 core 文件".to_string(),
+            ];
+        }
+        if limit.rlim_max == 0 {
+            return vec!["[INFO] 系统禁止生成 core 文件（硬限制为 0）".to_string()];
+        }
+        let pattern = std::fs::read_to_string("/proc/sys/kernel/core_pattern")
+            .map(|pattern| pattern.trim().to_string())
+            .unwrap_or_default();
+        vec![format!(
+            "[INFO] 已开启 core 文件，位置由 core_pattern 决定: {pattern}"
+        )]
+    }
+
+    pub fn disable() {}
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+mod platform {
+    use std::path::Path;
+
+    pub fn enable(_dir: &Path) -> Vec<String> {
+        Vec::new()
+    }
+
+    pub fn disable() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn dumps_are_listed_by_name_only() {
+        let logs_dir = temp_dir("list");
+        assert!(list(&logs_dir).is_empty());
+        let dir = dumps_dir(&logs_dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("server.exe.1234.dmp"), b"MDMP").unwrap();
+        assert_eq!(list(&logs_dir), vec!["server.exe.1234.dmp".to_string()]);
+        let _ = fs::remove_dir_all(&logs_dir);
+    }
+
+    #[test]
+    fn expired_dumps_are_removed() {
+        let dir = temp_dir("expired");
+        fs::write(dir.join("batch.exe.42.dmp"), b"MDMP").unwrap();
+        assert_eq!(remove_older_than(&dir, DUMP_RETENTION), 0);
+        assert_eq!(remove_older_than(&dir, Duration::ZERO), 1);
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,439 @@
+//! 启动前的数据库检查。
+//!
+//! DB_TYPE 为 sqlite 时，启动 server 之前先做 `PRAGMA integrity_check`，
+//! 通过后再用 `VACUUM INTO` 生成一份快照到 `backups/auto/`，保证随时能找回最近一次可用的数据库。
+//! 数据库很大时两项都可以通过 runtime.env 关闭。
+//!
+//! DB_TYPE 为 mysql / postgresql 时先检查 host:port 能否建立 TCP 连接（常见情况是 NAS 上的数据库没开机），
+//! 连不上时不启动服务，由界面让用户选择重试、编辑配置或暂时使用 SQLite 启动。
+//! 选择 SQLite 后只在本次运行中覆盖 DB_TYPE（记录在 [`SqliteFallback`] 中，重启服务时沿用），不改写 runtime.env。
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use rusqlite::{Connection, OpenFlags};
+use tauri::{AppHandle, Manager};
+
+use crate::runtime::{self, BootstrapError};
+
+const SQLITE_FILE_NAME: &str = "pt_stats.db";
+/// SQLite 数据库所在目录，未设置时与 `PTNEXUS_DATA_DIR` 相同；由 datamove.rs 写入 runtime.env。
+pub const SQLITE_DIR_KEY: &str = "PTNEXUS_DB_DIR";
+const SKIP_INTEGRITY_CHECK_KEY: &str = "PTNEXUS_SKIP_DB_INTEGRITY_CHECK";
+const SKIP_BACKUP_KEY: &str = "PTNEXUS_SKIP_DB_BACKUP";
+const BACKUP_MAX_MB_KEY: &str = "PTNEXUS_DB_BACKUP_MAX_MB";
+const BACKUP_KEEP_KEY: &str = "PTNEXUS_DB_BACKUP_KEEP";
+const DEFAULT_BACKUP_MAX_MB: u64 = 512;
+const DEFAULT_BACKUP_KEEP: usize = 5;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 本次运行中因数据库不可用而临时改用 SQLite 时，记录原数据库的地址。
+#[derive(Default)]
+pub struct SqliteFallback {
+    offline: Mutex<Option<String>>,
+}
+
+/// 本次运行改用 SQLite 启动，之后的启动与重启都沿用，直到应用退出。
+pub fn enable_sqlite_fallback(app: &AppHandle, offline_address: String) {
+    if let Some(state) = app.try_state::<SqliteFallback>() {
+        if let Ok(mut offline) = state.offline.lock() {
+            *offline = Some(offline_address);
+        }
+    }
+}
+
+/// 临时使用 SQLite 时，原数据库的地址。
+pub fn sqlite_fallback(app: &AppHandle) -> Option<String> {
+    app.try_state::<SqliteFallback>().and_then(|state| {
+        state
+            .offline
+            .lock()
+            .ok()
+            .and_then(|offline| offline.clone())
+    })
+}
+
+/// 已选择临时使用 SQLite 时覆盖子进程环境中的 DB_TYPE。
+pub fn apply_sqlite_fallback(app: &AppHandle, envs: &mut HashMap<String, String>, logs_dir: &Path) {
+    if let Some(offline) = sqlite_fallback(app) {
+        envs.insert("DB_TYPE".to_string(), "sqlite".to_string());
+        runtime::append_shell_log(
+            logs_dir,
+            &format!(
+                "[WARN] 数据库 {offline} 不可用，本次运行临时使用 SQLite（runtime.env 未修改）"
+            ),
+        );
+    }
+}
+
+/// DB_TYPE 为 mysql / postgresql 时配置的数据库类型与 host:port；端口未设置时使用默认端口。
+pub fn remote_endpoint(envs: &HashMap<String, String>) -> Option<(String, String, u16)> {
+    let db_type = envs.get("DB_TYPE")?.trim().to_ascii_lowercase();
+    let (prefix, default_port) = match db_type.as_str() {
+        "mysql" => ("MYSQL", 3306),
+        "postgresql" | "postgres" => ("POSTGRES", 5432),
+        _ => return None,
+    };
+    let host = envs
+        .get(&format!("{prefix}_HOST"))
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "127.0.0.1".to_string());
+    let port = envs
+        .get(&format!("{prefix}_PORT"))
+        .and_then(|port| port.trim().parse::<u16>().ok())
+        .unwrap_or(default_port);
+    Some((db_type, host, port))
+}
+
+fn accepts_connections(host: &str, port: u16) -> bool {
+    (host, port)
+        .to_socket_addrs()
+        .map(|mut addrs| {
+            addrs.any(|addr| TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).is_ok())
+        })
+        .unwrap_or(false)
+}
+
+/// 按 server 的 config.py 规则推导 sqlite 文件位置；非 sqlite 后端返回 None。
+pub fn sqlite_path(envs: &HashMap<String, String>) -> Option<PathBuf> {
+    let db_type = envs
+        .get("DB_TYPE")
+        .map(|v| v.trim().to_ascii_lowercase())
+        .unwrap_or_else(|| "sqlite".to_string());
+    if db_type != "sqlite" {
+        return None;
+    }
+
+    let dir = envs
+        .get(SQLITE_DIR_KEY)
+        .filter(|dir| !dir.trim().is_empty())
+        .or_else(|| envs.get("PTNEXUS_DATA_DIR"))?;
+    Some(Path::new(dir.trim()).join(SQLITE_FILE_NAME))
+}
+
+pub fn auto_backups_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("backups").join("auto")
+}
+
+/// 启动前检查：远程数据库无法连接、SQLite 完整性校验失败时阻止启动，校验通过后做一次自动快照。
+pub fn preflight(
+    envs: &HashMap<String, String>,
+    data_dir: &Path,
+    logs_dir: &Path,
+) -> Result<(), BootstrapError> {
+    if let Some((db_type, host, port)) = remote_endpoint(envs) {
+        if !accepts_connections(&host, port) {
+            return Err(BootstrapError::DatabaseUnreachable {
+                db_type,
+                address: format!("{host}:{port}"),
+            });
+        }
+        return Ok(());
+    }
+
+    let Some(db_path) = sqlite_path(envs) else {
+        return Ok(());
+    };
+    if !db_path.exists() {
+        return Ok(());
+    }
+
+    let backups_dir = auto_backups_dir(data_dir);
+    let flag = |key: &str| {
+        envs.get(key)
+            .map(|v| runtime::is_truthy(v))
+            .unwrap_or(false)
+    };
+
+    if flag(SKIP_INTEGRITY_CHECK_KEY) {
+        runtime::append_shell_log(logs_dir, "[INFO] 已按配置跳过 SQLite 完整性检查");
+    } else if let Err(detail) = check_integrity(&db_path) {
+        return Err(BootstrapError::DatabaseCorrupt {
+            path: db_path,
+            detail,
+            latest_backup: latest_backup(&backups_dir),
+        });
+    }
+
+    if flag(SKIP_BACKUP_KEY) {
+        runtime::append_shell_log(logs_dir, "[INFO] 已按配置跳过 SQLite 启动前备份");
+        return Ok(());
+    }
+
+    let max_bytes = envs
+        .get(BACKUP_MAX_MB_KEY)
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_BACKUP_MAX_MB)
+        * 1024
+        * 1024;
+    let keep = envs
+        .get(BACKUP_KEEP_KEY)
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_BACKUP_KEEP)
+        .max(1);
+
+    // 备份失败不影响启动，只记录日志
+    match take_snapshot(&db_path, &backups_dir, max_bytes, keep) {
+        Ok(Some(path)) => runtime::append_shell_log(
+            logs_dir,
+            &format!("[INFO] 已创建 SQLite 启动前备份: {}", path.display()),
+        ),
+        Ok(None) => runtime::append_shell_log(
+            logs_dir,
+            &format!(
+                "[INFO] 数据库超过 {} MB，跳过启动前备份（可通过 {BACKUP_MAX_MB_KEY} 调整）",
+                max_bytes / 1024 / 1024
+            ),
+        ),
+        Err(err) => runtime::append_shell_log(logs_dir, &format!("[WARN] {err}")),
+    }
+
+    Ok(())
+}
+
+fn check_integrity(db_path: &Path) -> Result<(), String> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("无法打开数据库: {e}"))?;
+
+    let mut stmt = conn
+        .prepare("PRAGMA integrity_check")
+        .map_err(|e| format!("执行完整性检查失败: {e}"))?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("执行完整性检查失败: {e}"))?;
+
+    let mut problems = Vec::new();
+    for row in rows.take(10) {
+        let line = row.map_err(|e| format!("读取完整性检查结果失败: {e}"))?;
+        if line != "ok" {
+            problems.push(line);
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("\n"))
+    }
+}
+
+/// 生成一份数据库快照并按保留数量清理旧快照；超过大小阈值时跳过并返回 None。
+/// 不直接复制文件：上次被强制结束时未写回主文件的 -wal / -journal 内容会丢失或不一致。
+/// 经 SQLite 以只读连接 `VACUUM INTO` 导出，快照是一个独立、一致的数据库文件。
+fn take_snapshot(
+    db_path: &Path,
+    backups_dir: &Path,
+    max_bytes: u64,
+    keep: usize,
+) -> Result<Option<PathBuf>, String> {
+    let size = fs::metadata(db_path)
+        .map_err(|e| format!("读取数据库信息失败 ({}): {e}", db_path.display()))?
+        .len();
+    let wal_size = fs::metadata(companion(db_path, "-wal"))
+        .map(|m| m.len())
+        .unwrap_or(0);
+    if size + wal_size > max_bytes {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(backups_dir)
+        .map_err(|e| format!("创建备份目录失败 ({}): {e}", backups_dir.display()))?;
+
+    let stamp = runtime::format_utc_timestamp(SystemTime::now()).replace(':', "-");
+    let target = backups_dir.join(format!("pt_stats-{stamp}.db"));
+    // 先导出到临时文件，完成后再改名，中途失败不会留下不完整的快照
+    let partial = backups_dir.join(format!("pt_stats-{stamp}.db.partial"));
+    let _ = fs::remove_file(&partial);
+    if let Err(err) = vacuum_into(db_path, &partial)
+        .and_then(|()| fs::rename(&partial, &target).map_err(|e| e.to_string()))
+    {
+        let _ = fs::remove_file(&partial);
+        return Err(format!("备份数据库失败 ({}): {err}", target.display()));
+    }
+
+    prune_backups(backups_dir, keep);
+    Ok(Some(target))
+}
+
+fn vacuum_into(db_path: &Path, target: &Path) -> Result<(), String> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("无法打开数据库: {e}"))?;
+    conn.execute("VACUUM INTO ?1", [target.to_string_lossy()])
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn companion(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = db_path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// 快照文件名自带 UTC 时间戳，按文件名排序即按时间排序。
+fn list_backups(backups_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(backups_dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with("pt_stats-") && n.ends_with(".db"))
+                .unwrap_or(false)
+        })
+        .collect();
+    backups.sort();
+    backups
+}
+
+pub fn latest_backup(backups_dir: &Path) -> Option<PathBuf> {
+    list_backups(backups_dir).pop()
+}
+
+fn prune_backups(backups_dir: &Path, keep: usize) {
+    let backups = list_backups(backups_dir);
+    let excess = backups.len().saturating_sub(keep);
+    for old in backups.into_iter().take(excess) {
+        let _ = fs::remove_file(old);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    fn envs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn remote_endpoint_follows_db_type() {
+        assert_eq!(remote_endpoint(&envs(&[])), None);
+        assert_eq!(remote_endpoint(&envs(&[("DB_TYPE", "sqlite")])), None);
+        assert_eq!(
+            remote_endpoint(&envs(&[
+                ("DB_TYPE", "MySQL"),
+                ("MYSQL_HOST", " 192.168.1.10 "),
+                ("MYSQL_PORT", "3307"),
+            ])),
+            Some(("mysql".to_string(), "192.168.1.10".to_string(), 3307))
+        );
+        assert_eq!(
+            remote_endpoint(&envs(&[("DB_TYPE", "postgresql"), ("POSTGRES_PORT", "")])),
+            Some(("postgresql".to_string(), "127.0.0.1".to_string(), 5432))
+        );
+    }
+
+    fn backup_names(backups_dir: &Path) -> Vec<String> {
+        list_backups(backups_dir)
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_includes_uncheckpointed_wal_content() {
+        let dir = temp_dir("snapshot-wal");
+        let db_path = dir.join(SQLITE_FILE_NAME);
+        let writer = Connection::open(&db_path).unwrap();
+        writer
+            .execute_batch(
+                "PRAGMA journal_mode=WAL; PRAGMA wal_autocheckpoint=0;
+                 CREATE TABLE t (v TEXT); INSERT INTO t VALUES ('in-wal');",
+            )
+            .unwrap();
+        // 写入连接保持打开，内容还在 -wal 中，直接复制主文件会丢失
+        assert!(companion(&db_path, "-wal").exists());
+
+        let backups_dir = dir.join("backups");
+        let snapshot = take_snapshot(&db_path, &backups_dir, u64::MAX, 5)
+            .unwrap()
+            .unwrap();
+        let copy = Connection::open(&snapshot).unwrap();
+        let value: String = copy
+            .query_row("SELECT v FROM t", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(value, "in-wal");
+        assert!(!companion(&snapshot, "-wal").exists());
+        assert_eq!(list_backups(&backups_dir), vec![snapshot]);
+        drop(writer);
+
+        assert_eq!(take_snapshot(&db_path, &backups_dir, 1, 5).unwrap(), None);
+    }
+
+    #[test]
+    fn list_backups_ignores_other_files_and_sorts_by_time() {
+        let dir = temp_dir("list-backups");
+        assert!(list_backups(&dir.join("missing")).is_empty());
+        for name in [
+            "pt_stats-2024-05-02T00-00-00Z.db",
+            "pt_stats-2024-05-01T00-00-00Z.db",
+            "pt_stats-2024-05-03T00-00-00Z.db.partial",
+            "other.db",
+            "pt_stats-notes.txt",
+        ] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        assert_eq!(
+            backup_names(&dir),
+            [
+                "pt_stats-2024-05-01T00-00-00Z.db",
+                "pt_stats-2024-05-02T00-00-00Z.db"
+            ]
+        );
+        assert_eq!(
+            latest_backup(&dir).unwrap(),
+            dir.join("pt_stats-2024-05-02T00-00-00Z.db")
+        );
+    }
+
+    #[test]
+    fn prune_backups_keeps_the_newest() {
+        let dir = temp_dir("prune-backups");
+        for day in 1..=4 {
+            fs::write(
+                dir.join(format!("pt_stats-2024-05-0{day}T00-00-00Z.db")),
+                b"",
+            )
+            .unwrap();
+        }
+        fs::write(dir.join("other.db"), b"").unwrap();
+        prune_backups(&dir, 2);
+        assert_eq!(
+            backup_names(&dir),
+            [
+                "pt_stats-2024-05-03T00-00-00Z.db",
+                "pt_stats-2024-05-04T00-00-00Z.db"
+            ]
+        );
+        assert!(dir.join("other.db").exists());
+        prune_backups(&dir, 5);
+        assert_eq!(backup_names(&dir).len(), 2);
+    }
+
+    #[test]
+    fn unreachable_database_is_detected() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(accepts_connections("127.0.0.1", port));
+        drop(listener);
+        assert!(!accepts_connections("127.0.0.1", port));
+    }
+}
@@ -0,0 +1,701 @@
+//! 数据目录的解析与位置检查。
+//!
+//! 配置档（profile）使用的目录统一由 [`ProfilePaths`] 给出：临时文件、inbox、config.json、更新与仓库目录
+//! 都位于配置档自己的根目录下，不同配置档之间互不重叠，避免共用 `<data_dir>/tmp` 时互相覆盖中间文件。
+//! 默认配置档 `default` 的根目录就是应用数据目录，其他配置档位于 `<应用数据目录>/profiles/<名称>/`，
+//! 各自有独立的 runtime.env（端口、数据库）与日志；当前配置档的名称记录在 `<应用数据目录>/active-profile`。
+//! 桌面设置、运行记录与 WebView 数据不随配置档切换。`open_dir(kind)` 等命令打开的目录同样由这里解析
+//! （见 [`DirKind`]），不会打开其他配置档的目录。
+//!
+//! 数据目录位于 OneDrive / Dropbox 等同步盘或网络共享上时，同步程序会在写入过程中锁住 SQLite 文件，
+//! 是数据库损坏的常见原因。启动时检测一次并提示用户，但从不阻止启动；可在桌面设置中关闭提示。
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::events::{self, Replay};
+use crate::{fsutil, runtime, settings};
+
+/// 与 tauri.conf.json 的 identifier 一致，没有 AppHandle 时据此定位应用数据目录。
+const APP_IDENTIFIER: &str = "com.ptnexus.desktop";
+
+/// 每次进程只提示一次，服务重启时不重复打扰。
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// 路径中出现即视为位于同步盘的目录名（不区分大小写，OneDrive 企业版形如 “OneDrive - 公司名”）。
+const SYNC_DIR_MARKERS: &[(&str, &str)] = &[
+    ("onedrive", "OneDrive"),
+    ("dropbox", "Dropbox"),
+    ("google drive", "Google Drive"),
+    ("googledrive", "Google Drive"),
+    ("my drive", "Google Drive"),
+    ("icloud drive", "iCloud"),
+    ("iclouddrive", "iCloud"),
+    ("mobile documents", "iCloud"),
+];
+
+/// 一个配置档使用的目录与文件，全部由根目录推导。
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfilePaths {
+    pub root: PathBuf,
+    /// 后端的 TEMP_DIR。
+    pub temp_dir: PathBuf,
+    /// 拖入文件的中转目录，位于 temp_dir 中。
+    pub inbox_dir: PathBuf,
+    pub config_file: PathBuf,
+    pub update_dir: PathBuf,
+    pub repo_dir: PathBuf,
+    /// 各服务上一个版本的程序，见 rollback.rs。
+    pub rollback_dir: PathBuf,
+    pub logs_dir: PathBuf,
+    /// config.json 等的备份，见 configguard.rs。
+    pub backups_dir: PathBuf,
+    /// 定时导出的数据，见 dataexport.rs。
+    pub exports_dir: PathBuf,
+}
+
+impl ProfilePaths {
+    pub fn for_root(root: &Path) -> Self {
+        let temp_dir = root.join("tmp");
+        let update_dir = root.join("updates");
+        Self {
+            root: root.to_path_buf(),
+            inbox_dir: temp_dir.join("inbox"),
+            temp_dir,
+            config_file: root.join("config.json"),
+            repo_dir: update_dir.join("repo"),
+            rollback_dir: update_dir.join("rollback"),
+            update_dir,
+            logs_dir: root.join("logs"),
+            backups_dir: root.join("backups"),
+            exports_dir: root.join("exports"),
+        }
+    }
+
+    pub fn dir(&self, kind: DirKind) -> &Path {
+        match kind {
+            DirKind::Data => &self.root,
+            DirKind::Logs => &self.logs_dir,
+            DirKind::Backups => &self.backups_dir,
+            DirKind::Exports => &self.exports_dir,
+            DirKind::Tmp => &self.temp_dir,
+            DirKind::Updates => &self.update_dir,
+        }
+    }
+}
+
+/// 可以在文件管理器中打开的目录，`open_dir(kind)` 的参数。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DirKind {
+    Data,
+    Logs,
+    Backups,
+    Exports,
+    Tmp,
+    Updates,
+}
+
+impl DirKind {
+    pub fn parse(kind: &str) -> Result<Self, String> {
+        match kind.trim() {
+            "data" => Ok(Self::Data),
+            "logs" => Ok(Self::Logs),
+            "backups" => Ok(Self::Backups),
+            "exports" => Ok(Self::Exports),
+            "tmp" => Ok(Self::Tmp),
+            "updates" => Ok(Self::Updates),
+            other => Err(format!(
+                "未知的目录类型: {other}（可选 data / logs / backups / exports / tmp / updates）"
+            )),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Data => "数据目录",
+            Self::Logs => "日志目录",
+            Self::Backups => "备份目录",
+            Self::Exports => "导出目录",
+            Self::Tmp => "临时目录",
+            Self::Updates => "更新目录",
+        }
+    }
+}
+
+pub const DEFAULT_PROFILE: &str = "default";
+const PROFILES_DIR: &str = "profiles";
+const ACTIVE_PROFILE_FILE: &str = "active-profile";
+
+/// 配置档列表中的一项。
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub root: String,
+    pub active: bool,
+}
+
+/// 当前使用的配置档。
+pub fn active_profile(app: &AppHandle) -> Result<ProfilePaths, String> {
+    app_data_dir(app).map(|base| active_profile_in(&base))
+}
+
+/// 应用数据目录 `base` 中当前使用的配置档，供没有 AppHandle 的命令行调用使用。
+pub fn active_profile_in(base: &Path) -> ProfilePaths {
+    ProfilePaths::for_root(&profile_root(base, &active_name(base)))
+}
+
+/// 当前配置档的名称。
+pub fn active_profile_name(app: &AppHandle) -> Result<String, String> {
+    app_data_dir(app).map(|base| active_name(&base))
+}
+
+/// 名为 `name` 的配置档，不存在时报错并给出查找的路径。
+pub fn profile(app: &AppHandle, name: &str) -> Result<ProfilePaths, String> {
+    profile_in(&app_data_dir(app)?, name)
+}
+
+fn profile_in(base: &Path, name: &str) -> Result<ProfilePaths, String> {
+    validate_profile_name(name)?;
+    let root = profile_root(base, name);
+    if !root.is_dir() {
+        return Err(format!("配置档“{name}”不存在 ({})", root.display()));
+    }
+    Ok(ProfilePaths::for_root(&root))
+}
+
+/// 全部配置档，默认配置档在前，其余按名称排列。
+pub fn list_profiles(app: &AppHandle) -> Result<Vec<ProfileInfo>, String> {
+    let base = app_data_dir(app)?;
+    Ok(profiles_in(&base))
+}
+
+/// 把当前配置档记录为 `name`，配置档目录不存在时创建。只修改记录，服务由调用方重新启动。
+pub fn set_active_profile(app: &AppHandle, name: &str) -> Result<(), String> {
+    set_active(&app_data_dir(app)?, name)
+}
+
+/// 按 Tauri 的规则推算应用数据目录，供命令行调用与创建 Tauri 运行时之前的代码使用。
+pub fn standalone_base_dir() -> Option<PathBuf> {
+    let env_dir = |key: &str| std::env::var_os(key).map(PathBuf::from);
+    let base = if cfg!(target_os = "windows") {
+        env_dir("APPDATA")?
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME")?.join("Library").join("Application Support")
+    } else {
+        env_dir("XDG_DATA_HOME").or_else(|| Some(env_dir("HOME")?.join(".local").join("share")))?
+    };
+    Some(base.join(APP_IDENTIFIER))
+}
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("解析应用数据目录失败: {e}"))
+}
+
+fn profile_root(base: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        base.to_path_buf()
+    } else {
+        base.join(PROFILES_DIR).join(name)
+    }
+}
+
+/// 记录缺失、名称无效或目录已被删除时回到默认配置档。
+fn active_name(base: &Path) -> String {
+    fs::read_to_string(base.join(ACTIVE_PROFILE_FILE))
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| validate_profile_name(name).is_ok() && profile_root(base, name).is_dir())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+fn set_active(base: &Path, name: &str) -> Result<(), String> {
+    validate_profile_name(name)?;
+    let root = profile_root(base, name);
+    fs::create_dir_all(&root)
+        .map_err(|e| format!("创建配置档目录失败 ({}): {e}", root.display()))?;
+    fsutil::atomic_write(&base.join(ACTIVE_PROFILE_FILE), name)
+        .map_err(|e| format!("保存当前配置档失败: {e}"))
+}
+
+fn profiles_in(base: &Path) -> Vec<ProfileInfo> {
+    let active = active_name(base);
+    let mut names: Vec<String> = fs::read_dir(base.join(PROFILES_DIR))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| name != DEFAULT_PROFILE && validate_profile_name(name).is_ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+        .into_iter()
+        .map(|name| ProfileInfo {
+            root: profile_root(base, &name).to_string_lossy().to_string(),
+            active: name == active,
+            name,
+        })
+        .collect()
+}
+
+/// 配置档名称用作目录名与菜单 id：1 到 32 个字母、数字、`-` 或 `_`。
+pub fn validate_profile_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "配置档名称“{name}”无效：只能包含字母、数字、- 和 _，最长 32 个字符"
+        ))
+    }
+}
+
+/// 数据目录占用明细中的一项。
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DirUsage {
+    /// 相对数据目录的路径，如 `logs`、`updates/rollback`。
+    pub name: String,
+    pub bytes: u64,
+}
+
+/// 数据目录下各项的占用，回滚副本从 updates 中单独列出；按占用从大到小排列。
+pub fn usage(profile: &ProfilePaths) -> Vec<DirUsage> {
+    let Ok(entries) = fs::read_dir(&profile.root) else {
+        return Vec::new();
+    };
+    let rollback = dir_size(&profile.rollback_dir);
+    let mut items: Vec<DirUsage> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let path = entry.path();
+            let mut bytes = dir_size(&path);
+            if path == profile.update_dir {
+                bytes = bytes.saturating_sub(rollback);
+            }
+            DirUsage {
+                name: entry.file_name().to_string_lossy().to_string(),
+                bytes,
+            }
+        })
+        .collect();
+    if rollback > 0 {
+        items.push(DirUsage {
+            name: "updates/rollback".to_string(),
+            bytes: rollback,
+        });
+    }
+    items.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    items
+}
+
+/// 文件或目录（递归，不跟随符号链接）的总大小。
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| dir_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// `path` 所在磁盘对当前用户可用的剩余空间（字节），无法获取时返回 None。
+#[cfg(target_os = "windows")]
+pub fn free_space(path: &Path) -> Option<u64> {
+    use windows::core::HSTRING;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let mut available = 0u64;
+    unsafe { GetDiskFreeSpaceExW(&HSTRING::from(path), Some(&mut available), None, None) }
+        .ok()
+        .map(|()| available)
+}
+
+/// `path` 所在磁盘对当前用户可用的剩余空间（字节），取自 `df -Pk`；无法获取时返回 None。
+#[cfg(not(target_os = "windows"))]
+pub fn free_space(path: &Path) -> Option<u64> {
+    let output = std::process::Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    parse_df_available(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// POSIX 格式的 df 输出：第二行第 4 列为可用的 1024 字节块数。
+#[cfg(not(target_os = "windows"))]
+fn parse_df_available(output: &str) -> Option<u64> {
+    let blocks: u64 = output
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()?;
+    Some(blocks * 1024)
+}
+
+#[derive(Clone, Serialize)]
+pub struct DataDirRisk {
+    /// "sync"：位于同步盘；"network"：位于网络共享。
+    pub kind: &'static str,
+    pub path: String,
+    pub detail: String,
+}
+
+/// 检查数据目录是否位于同步盘或网络共享。
+pub fn inspect(data_dir: &Path) -> Option<DataDirRisk> {
+    let (kind, detail) = if let Some(service) = sync_service(data_dir) {
+        ("sync", format!("位于 {service} 同步目录"))
+    } else if let Some(detail) = network_location(data_dir) {
+        ("network", detail)
+    } else {
+        return None;
+    };
+
+    Some(DataDirRisk {
+        kind,
+        path: data_dir.to_string_lossy().to_string(),
+        detail,
+    })
+}
+
+/// bootstrap 中调用：发现风险时发出 `data-dir-warning` 事件与系统通知。
+pub fn warn_if_risky(app: &AppHandle, data_dir: &Path) {
+    if settings::current(app).suppress_data_dir_warning {
+        return;
+    }
+    let Some(risk) = inspect(data_dir) else {
+        return;
+    };
+    if WARNED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    runtime::shell_log(
+        app,
+        &format!("[WARN] 数据目录{}: {}", risk.detail, risk.path),
+    );
+    crate::power::wake(app);
+    events::emit(app, "data-dir-warning", Replay::Latest, &risk);
+    let _ = app
+        .notification()
+        .builder()
+        .title("PT Nexus 数据目录位置存在风险")
+        .body(format!(
+            "数据目录{}，同步或网络中断可能损坏数据库，建议将数据目录移到本地磁盘。",
+            risk.detail
+        ))
+        .show();
+}
+
+fn sync_service(path: &Path) -> Option<&'static str> {
+    if let Some(service) = sync_marker_in_path(path) {
+        return Some(service);
+    }
+
+    // OneDrive 可能把“文档”等目录重定向到任意名称的文件夹，按其环境变量再确认一次
+    for var in ["OneDrive", "OneDriveConsumer", "OneDriveCommercial"] {
+        if let Some(root) = std::env::var_os(var).filter(|v| !v.is_empty()) {
+            if path.starts_with(Path::new(&root)) {
+                return Some("OneDrive");
+            }
+        }
+    }
+
+    // Dropbox 在同步根目录放置 .dropbox 文件
+    if path
+        .ancestors()
+        .any(|dir| dir.join(".dropbox").exists() || dir.join(".dropbox.cache").exists())
+    {
+        return Some("Dropbox");
+    }
+
+    placeholder_ancestor(path)
+}
+
+fn sync_marker_in_path(path: &Path) -> Option<&'static str> {
+    path.components().find_map(|component| {
+        let Component::Normal(name) = component else {
+            return None;
+        };
+        let name = name.to_string_lossy().to_lowercase();
+        SYNC_DIR_MARKERS
+            .iter()
+            .find(|(marker, _)| name == *marker || name.starts_with(&format!("{marker} - ")))
+            .map(|(_, service)| *service)
+    })
+}
+
+/// Windows：按需下载（云占位符）目录带有 RECALL_ON_* 属性或重解析点。
+#[cfg(target_os = "windows")]
+fn placeholder_ancestor(path: &Path) -> Option<&'static str> {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x0004_0000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x0040_0000;
+
+    path.ancestors()
+        .filter_map(|dir| std::fs::metadata(dir).ok())
+        .any(|meta| {
+            meta.file_attributes()
+                & (FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)
+                != 0
+        })
+        .then_some("云同步")
+}
+
+#[cfg(not(target_os = "windows"))]
+fn placeholder_ancestor(_path: &Path) -> Option<&'static str> {
+    None
+}
+
+#[cfg(target_os = "windows")]
+fn network_location(path: &Path) -> Option<String> {
+    use std::path::Prefix;
+
+    use windows::core::HSTRING;
+    use windows::Win32::Storage::FileSystem::GetDriveTypeW;
+    use windows::Win32::System::WindowsProgramming::DRIVE_REMOTE;
+
+    let Some(Component::Prefix(prefix)) = path.components().next() else {
+        return None;
+    };
+    match prefix.kind() {
+        Prefix::UNC(..) | Prefix::VerbatimUNC(..) => Some("位于网络共享（UNC 路径）".to_string()),
+        Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+            let root = HSTRING::from(format!("{}:\\", letter as char));
+            let drive_type = unsafe { GetDriveTypeW(&root) };
+            (drive_type == DRIVE_REMOTE)
+                .then(|| format!("位于映射的网络驱动器 {}:", letter as char))
+        }
+        _ => None,
+    }
+}
+
+/// Linux：按 /proc/mounts 找到路径所在的挂载点，判断是否为网络文件系统。
+#[cfg(target_os = "linux")]
+fn network_location(path: &Path) -> Option<String> {
+    const NETWORK_FS: &[&str] = &[
+        "nfs",
+        "nfs4",
+        "cifs",
+        "smbfs",
+        "smb3",
+        "sshfs",
+        "fuse.sshfs",
+        "9p",
+    ];
+
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+    let (mount_point, fs_type) = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = fields.next()?;
+            let fs_type = fields.next()?;
+            Some((mount_point.replace("\\040", " "), fs_type.to_string()))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.len())?;
+
+    NETWORK_FS
+        .contains(&fs_type.as_str())
+        .then(|| format!("位于网络文件系统 {mount_point}（{fs_type}）"))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn network_location(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn sync_markers_match_whole_components() {
+        assert_eq!(
+            sync_marker_in_path(Path::new("/Users/a/OneDrive/Documents/PT Nexus")),
+            Some("OneDrive")
+        );
+        assert_eq!(
+            sync_marker_in_path(Path::new("/home/a/OneDrive - Contoso/PT Nexus")),
+            Some("OneDrive")
+        );
+        assert_eq!(
+            sync_marker_in_path(Path::new("/home/a/Dropbox/apps")),
+            Some("Dropbox")
+        );
+        assert_eq!(
+            sync_marker_in_path(Path::new("/home/a/onedrive-backup")),
+            None
+        );
+        assert_eq!(sync_marker_in_path(Path::new("/var/lib/ptnexus")), None);
+    }
+
+    #[test]
+    fn active_profile_pointer_falls_back_to_default() {
+        let base = temp_dir("profiles");
+
+        assert_eq!(active_name(&base), DEFAULT_PROFILE);
+        set_active(&base, "work").unwrap();
+        assert_eq!(active_name(&base), "work");
+        assert!(base.join("profiles").join("work").is_dir());
+        assert!(set_active(&base, "../escape").is_err());
+        assert_eq!(active_name(&base), "work");
+
+        fs::create_dir_all(base.join("profiles").join("home")).unwrap();
+        fs::create_dir_all(base.join("profiles").join("bad name")).unwrap();
+        let listed: Vec<(String, bool)> = profiles_in(&base)
+            .into_iter()
+            .map(|p| (p.name, p.active))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("default".to_string(), false),
+                ("home".to_string(), false),
+                ("work".to_string(), true)
+            ]
+        );
+
+        // 当前配置档的目录被删除后回到默认配置档
+        fs::remove_dir_all(base.join("profiles").join("work")).unwrap();
+        assert_eq!(active_name(&base), DEFAULT_PROFILE);
+        set_active(&base, DEFAULT_PROFILE).unwrap();
+        assert_eq!(profile_root(&base, &active_name(&base)), base);
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn profiles_do_not_share_paths() {
+        let base = Path::new("/data/ptnexus/profiles");
+        let home = ProfilePaths::for_root(&base.join("home"));
+        let work = ProfilePaths::for_root(&base.join("work"));
+        assert_eq!(home.inbox_dir, base.join("home").join("tmp").join("inbox"));
+        assert!(home.repo_dir.starts_with(&home.update_dir));
+
+        let owned = |paths: &ProfilePaths| {
+            [
+                paths.temp_dir.clone(),
+                paths.inbox_dir.clone(),
+                paths.config_file.clone(),
+                paths.update_dir.clone(),
+                paths.repo_dir.clone(),
+                paths.rollback_dir.clone(),
+                paths.logs_dir.clone(),
+                paths.backups_dir.clone(),
+                paths.exports_dir.clone(),
+            ]
+        };
+        for path in owned(&home) {
+            assert!(path.starts_with(&home.root));
+            assert!(!path.starts_with(&work.root), "{}", path.display());
+        }
+        for path in owned(&work) {
+            assert!(!path.starts_with(&home.root), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn every_dir_kind_resolves_inside_the_active_profile() {
+        let base = temp_dir("kinds");
+        set_active(&base, "work").unwrap();
+        let work = active_profile_in(&base);
+        assert_eq!(work.root, base.join("profiles").join("work"));
+
+        let kinds = [
+            ("data", DirKind::Data),
+            ("logs", DirKind::Logs),
+            ("backups", DirKind::Backups),
+            ("exports", DirKind::Exports),
+            ("tmp", DirKind::Tmp),
+            ("updates", DirKind::Updates),
+        ];
+        for (name, kind) in kinds {
+            assert_eq!(DirKind::parse(name), Ok(kind));
+            let dir = work.dir(kind);
+            assert!(dir.starts_with(&work.root), "{}", dir.display());
+            assert_eq!(
+                ProfilePaths::for_root(&base).dir(kind).strip_prefix(&base),
+                dir.strip_prefix(&work.root)
+            );
+        }
+        assert_eq!(work.dir(DirKind::Logs), work.root.join("logs"));
+        assert_eq!(work.dir(DirKind::Tmp), work.temp_dir);
+        assert!(DirKind::parse("../profiles")
+            .unwrap_err()
+            .contains("../profiles"));
+
+        assert_eq!(profile_in(&base, "work").unwrap(), work);
+        assert_eq!(profile_in(&base, DEFAULT_PROFILE).unwrap().root, base);
+        assert!(profile_in(&base, "home")
+            .unwrap_err()
+            .contains(&base.join("profiles").join("home").display().to_string()));
+        assert!(profile_in(&base, "../work").is_err());
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn usage_lists_rollback_copies_separately() {
+        let root = temp_dir("usage");
+        let profile = ProfilePaths::for_root(&root);
+        fs::create_dir_all(profile.rollback_dir.join("batch")).unwrap();
+        fs::create_dir_all(&profile.repo_dir).unwrap();
+        fs::write(
+            profile.rollback_dir.join("batch").join("0-batch"),
+            vec![0u8; 300],
+        )
+        .unwrap();
+        fs::write(profile.repo_dir.join("CHANGELOG.json"), vec![0u8; 100]).unwrap();
+        fs::write(&profile.config_file, b"{}").unwrap();
+
+        let usage = usage(&profile);
+        let bytes = |name: &str| {
+            usage
+                .iter()
+                .find(|item| item.name == name)
+                .map(|item| item.bytes)
+        };
+        assert_eq!(bytes("updates/rollback"), Some(300));
+        assert_eq!(bytes("updates"), Some(100));
+        assert_eq!(bytes("config.json"), Some(2));
+        assert_eq!(usage[0].name, "updates/rollback");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn df_output_gives_available_bytes() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                      /dev/sda1        102400000  51200000  46080000      53% /\n";
+        assert_eq!(parse_df_available(output), Some(46_080_000 * 1024));
+        assert_eq!(parse_df_available("df: /missing: No such file"), None);
+        assert!(free_space(&std::env::temp_dir()).is_some());
+    }
+}
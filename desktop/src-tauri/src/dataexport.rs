@@ -0,0 +1,460 @@
+//! 关键数据的定期导出。
+//!
+//! 完整备份之外，桌面壳每天调用 server 的导出接口一次，把种子与转种映射等关键数据以 gzip 压缩的 JSON
+//! 保存到数据目录的 `exports/` 下（`seed-mappings-<UTC 时间>.json.gz`），只保留最近若干份，
+//! 数据库意外损坏时最多损失一天的数据。与时钟校验一样由后台线程定期检查是否到期：距最新一份导出超过
+//! 间隔时执行，重启应用不会重复导出。
+//!
+//! 默认关闭，在 runtime.env 中配置：
+//!
+//! - `PTNEXUS_DATA_EXPORT=1`：开启
+//! - `PTNEXUS_DATA_EXPORT_INTERVAL_HOURS`：间隔（小时），默认 24
+//! - `PTNEXUS_DATA_EXPORT_KEEP`：保留份数，默认 7
+//! - `PTNEXUS_DATA_EXPORT_PATH`：导出接口，默认 `/api/export/seed-mappings`
+//!
+//! 旧版本后端没有该接口（返回 404/405）时视为不支持：只记录一次，不计为失败，到下一个间隔再探测。
+//! 每次成功或失败都记入运行记录并发出 `data-export` 事件；连续失败 3 次时发送系统通知。
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::tasks::{self, Scope};
+use crate::{datadir, fsutil, localhttp, runtime, traystats};
+
+pub const ENABLE_KEY: &str = "PTNEXUS_DATA_EXPORT";
+pub const INTERVAL_KEY: &str = "PTNEXUS_DATA_EXPORT_INTERVAL_HOURS";
+pub const KEEP_KEY: &str = "PTNEXUS_DATA_EXPORT_KEEP";
+pub const PATH_KEY: &str = "PTNEXUS_DATA_EXPORT_PATH";
+const DEFAULT_PATH: &str = "/api/export/seed-mappings";
+const DEFAULT_INTERVAL_HOURS: u64 = 24;
+const DEFAULT_KEEP: usize = 7;
+
+const FILE_PREFIX: &str = "seed-mappings-";
+const FILE_SUFFIX: &str = ".json.gz";
+/// 检查配置、是否到期的间隔。
+const POLL_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// 失败后重试的间隔。
+const RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// 导出数据量可能较大，给后端足够的时间。
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(120);
+const NOTIFY_AFTER_FAILURES: u32 = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Config {
+    enabled: bool,
+    interval: Duration,
+    keep: usize,
+    path: String,
+}
+
+impl Config {
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let number = |key: &str| {
+            lookup(key)
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .filter(|n| *n > 0)
+        };
+        let path = lookup(PATH_KEY)
+            .map(|value| value.trim().to_string())
+            .filter(|value| value.starts_with('/'))
+            .unwrap_or_else(|| DEFAULT_PATH.to_string());
+        Self {
+            enabled: lookup(ENABLE_KEY).is_some_and(|value| runtime::is_truthy(&value)),
+            interval: Duration::from_secs(
+                number(INTERVAL_KEY).unwrap_or(DEFAULT_INTERVAL_HOURS) * 60 * 60,
+            ),
+            keep: number(KEEP_KEY).map_or(DEFAULT_KEEP, |keep| keep as usize),
+            path,
+        }
+    }
+
+    fn read(app: &AppHandle) -> Self {
+        Self::from_lookup(|key| runtime::read_runtime_setting(app, key))
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DataExportFile {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DataExportStatus {
+    pub enabled: bool,
+    pub endpoint: String,
+    pub interval_hours: u64,
+    pub keep: usize,
+    pub dir: Option<String>,
+    /// 后端是否提供导出接口，尚未探测时为 None。
+    pub supported: Option<bool>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    pub exports: Vec<DataExportFile>,
+}
+
+/// `data-export` 事件的内容。
+#[derive(Clone, Debug, Serialize)]
+struct DataExportEvent {
+    ok: bool,
+    file: Option<DataExportFile>,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+struct Progress {
+    supported: Option<bool>,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+    last_attempt: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct DataExportState {
+    progress: Mutex<Progress>,
+    /// 定时导出与手动导出不能同时进行。
+    running: Mutex<()>,
+}
+
+enum Outcome {
+    Exported(DataExportFile),
+    Unsupported,
+}
+
+pub fn start(app: &AppHandle) {
+    app.manage(DataExportState::default());
+    tasks::spawn(app, "data-export", Scope::Shell, move |app, token| {
+        while token.sleep(POLL_INTERVAL) {
+            let config = Config::read(&app);
+            if config.enabled && is_due(&app, &config) {
+                let _ = run(&app, &config);
+            }
+        }
+    });
+}
+
+/// 手动导出一次，不受开关与间隔限制。
+pub fn run_now(app: &AppHandle) -> Result<DataExportFile, String> {
+    let config = Config::read(app);
+    match run(app, &config)? {
+        Outcome::Exported(file) => Ok(file),
+        Outcome::Unsupported => Err(format!(
+            "当前后端版本不支持数据导出（{} 不存在）",
+            config.path
+        )),
+    }
+}
+
+pub fn status(app: &AppHandle) -> DataExportStatus {
+    let config = Config::read(app);
+    let dir = exports_dir(app).ok();
+    let (supported, last_error, consecutive_failures) = app
+        .try_state::<DataExportState>()
+        .and_then(|state| {
+            state.progress.lock().ok().map(|progress| {
+                (
+                    progress.supported,
+                    progress.last_error.clone(),
+                    progress.consecutive_failures,
+                )
+            })
+        })
+        .unwrap_or_default();
+    DataExportStatus {
+        enabled: config.enabled,
+        endpoint: config.path,
+        interval_hours: config.interval.as_secs() / 3600,
+        keep: config.keep,
+        exports: dir.as_deref().map(list).unwrap_or_default(),
+        dir: dir.map(|dir| dir.to_string_lossy().to_string()),
+        supported,
+        last_error,
+        consecutive_failures,
+    }
+}
+
+fn is_due(app: &AppHandle, config: &Config) -> bool {
+    let Some(state) = app.try_state::<DataExportState>() else {
+        return false;
+    };
+    let Ok(progress) = state.progress.lock() else {
+        return false;
+    };
+    // 不支持时等一个完整间隔再探测，失败后按较短的间隔重试
+    let wait = match progress.supported {
+        Some(false) => config.interval,
+        _ if progress.last_error.is_some() => RETRY_INTERVAL.min(config.interval),
+        _ => Duration::ZERO,
+    };
+    if progress.last_attempt.is_some_and(|at| at.elapsed() < wait) {
+        return false;
+    }
+    let newest = exports_dir(app)
+        .ok()
+        .and_then(|dir| list(&dir).into_iter().next())
+        .and_then(|file| {
+            fs::metadata(&file.path)
+                .and_then(|meta| meta.modified())
+                .ok()
+        });
+    newest.is_none_or(|at| at.elapsed().map_or(true, |age| age >= config.interval))
+}
+
+fn run(app: &AppHandle, config: &Config) -> Result<Outcome, String> {
+    let state = app
+        .try_state::<DataExportState>()
+        .ok_or_else(|| "数据导出尚未初始化".to_string())?;
+    let Ok(_running) = state.running.try_lock() else {
+        return Err("已有数据导出正在进行".to_string());
+    };
+
+    let result = export(app, config);
+    let mut progress = state
+        .progress
+        .lock()
+        .map_err(|_| "数据导出状态不可用".to_string())?;
+    progress.last_attempt = Some(Instant::now());
+    match &result {
+        Ok(Outcome::Exported(file)) => {
+            progress.supported = Some(true);
+            progress.last_error = None;
+            progress.consecutive_failures = 0;
+            drop(progress);
+            journal::record(
+                app,
+                Severity::Info,
+                Some("server"),
+                format!("数据导出完成: {}", file.name),
+            );
+            events::emit(
+                app,
+                "data-export",
+                Replay::Latest,
+                &DataExportEvent {
+                    ok: true,
+                    file: Some(file.clone()),
+                    error: None,
+                },
+            );
+        }
+        Ok(Outcome::Unsupported) => {
+            let first = progress.supported != Some(false);
+            progress.supported = Some(false);
+            progress.last_error = None;
+            drop(progress);
+            if first {
+                let message = format!("当前后端版本不提供数据导出接口 {}，暂不导出", config.path);
+                runtime::shell_log(app, &format!("[INFO] {message}"));
+                journal::record(app, Severity::Info, Some("server"), message);
+            }
+        }
+        Err(err) => {
+            progress.last_error = Some(err.clone());
+            progress.consecutive_failures += 1;
+            let failures = progress.consecutive_failures;
+            drop(progress);
+            runtime::shell_log(app, &format!("[WARN] 数据导出失败: {err}"));
+            journal::record(
+                app,
+                Severity::Warn,
+                Some("server"),
+                format!("数据导出失败: {err}"),
+            );
+            events::emit(
+                app,
+                "data-export",
+                Replay::Latest,
+                &DataExportEvent {
+                    ok: false,
+                    file: None,
+                    error: Some(err.clone()),
+                },
+            );
+            if failures == NOTIFY_AFTER_FAILURES {
+                let _ = app
+                    .notification()
+                    .builder()
+                    .title("PT Nexus 数据导出连续失败")
+                    .body(format!("已连续 {failures} 次导出失败，最近一次: {err}"))
+                    .show();
+            }
+        }
+    }
+    result
+}
+
+fn export(app: &AppHandle, config: &Config) -> Result<Outcome, String> {
+    let (host, port) = traystats::server_address(app);
+    let response = localhttp::fetch(&host, port, &config.path, EXPORT_TIMEOUT)?;
+    match response.status {
+        200 => {}
+        404 | 405 => return Ok(Outcome::Unsupported),
+        _ => {
+            return Err(format!(
+                "请求 {} 失败: {}",
+                config.path, response.status_line
+            ))
+        }
+    }
+
+    let data = encode(&response.body)?;
+    let dir = exports_dir(app)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("创建导出目录失败 ({}): {e}", dir.display()))?;
+    let now = SystemTime::now();
+    let path = dir.join(file_name(now));
+    fsutil::atomic_write(&path, &data)
+        .map_err(|e| format!("写入导出文件失败 ({}): {e}", path.display()))?;
+    prune(&dir, config.keep);
+
+    Ok(Outcome::Exported(DataExportFile {
+        name: file_name(now),
+        path: path.to_string_lossy().to_string(),
+        size: data.len() as u64,
+        created_at: runtime::format_utc_timestamp(now),
+    }))
+}
+
+/// 后端已压缩时原样保存，否则确认是 JSON（而不是错误页）后压缩。
+fn encode(body: &[u8]) -> Result<Vec<u8>, String> {
+    if body.starts_with(&[0x1f, 0x8b]) {
+        return Ok(body.to_vec());
+    }
+    serde_json::from_slice::<serde_json::Value>(body)
+        .map_err(|e| format!("导出内容不是有效的 JSON: {e}"))?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(body)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| format!("压缩导出内容失败: {e}"))
+}
+
+/// `seed-mappings-20231114-221320.json.gz`，按名称排序即按时间排序。
+fn file_name(time: SystemTime) -> String {
+    let stamp: String = runtime::format_utc_timestamp(time)
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == 'T')
+        .map(|c| if c == 'T' { '-' } else { c })
+        .collect();
+    format!("{FILE_PREFIX}{stamp}{FILE_SUFFIX}")
+}
+
+/// 导出目录中的文件，新的在前。
+fn list(dir: &Path) -> Vec<DataExportFile> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<DataExportFile> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(FILE_PREFIX) || !name.ends_with(FILE_SUFFIX) {
+                return None;
+            }
+            let meta = entry.metadata().ok().filter(|meta| meta.is_file())?;
+            Some(DataExportFile {
+                created_at: runtime::format_utc_timestamp(
+                    meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                ),
+                path: entry.path().to_string_lossy().to_string(),
+                size: meta.len(),
+                name,
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| b.name.cmp(&a.name));
+    files
+}
+
+/// 只保留最新的 `keep` 份，返回删除的数量。
+fn prune(dir: &Path, keep: usize) -> usize {
+    list(dir)
+        .into_iter()
+        .skip(keep.max(1))
+        .filter(|file| fs::remove_file(&file.path).is_ok())
+        .count()
+}
+
+fn exports_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    datadir::active_profile(app).map(|profile| profile.exports_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::time::UNIX_EPOCH;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn config_defaults_to_disabled_and_ignores_invalid_values() {
+        let config = Config::from_lookup(|_| None);
+        assert!(!config.enabled);
+        assert_eq!(config.interval, Duration::from_secs(24 * 60 * 60));
+        assert_eq!(config.keep, 7);
+        assert_eq!(config.path, DEFAULT_PATH);
+
+        let config = Config::from_lookup(|key| {
+            match key {
+                ENABLE_KEY => Some("yes"),
+                INTERVAL_KEY => Some("6"),
+                KEEP_KEY => Some("0"),
+                PATH_KEY => Some("api/no-slash"),
+                _ => None,
+            }
+            .map(str::to_string)
+        });
+        assert!(config.enabled);
+        assert_eq!(config.interval, Duration::from_secs(6 * 60 * 60));
+        assert_eq!(config.keep, 7);
+        assert_eq!(config.path, DEFAULT_PATH);
+    }
+
+    #[test]
+    fn exports_are_compressed_and_pruned_oldest_first() {
+        let data = encode(br#"{"seeds": []}"#).unwrap();
+        let mut json = String::new();
+        GzDecoder::new(&data[..]).read_to_string(&mut json).unwrap();
+        assert_eq!(json, r#"{"seeds": []}"#);
+        assert_eq!(encode(&data).unwrap(), data);
+        assert!(encode(b"<html>502</html>").is_err());
+
+        let dir = temp_dir("prune");
+        for hour in 0..4 {
+            let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000 + hour * 3600);
+            fs::write(dir.join(file_name(time)), &data).unwrap();
+        }
+        fs::write(dir.join("notes.txt"), "keep me").unwrap();
+        assert_eq!(
+            file_name(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            "seed-mappings-20231114-221320.json.gz"
+        );
+
+        assert_eq!(prune(&dir, 2), 2);
+        let names: Vec<String> = list(&dir).into_iter().map(|file| file.name).collect();
+        assert_eq!(
+            names,
+            [
+                "seed-mappings-20231115-011320.json.gz",
+                "seed-mappings-20231115-001320.json.gz"
+            ]
+        );
+        assert!(dir.join("notes.txt").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
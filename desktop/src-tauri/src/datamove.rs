@@ -0,0 +1,683 @@
+//! 把 SQLite 数据库移动到其他目录（runtime.env 中的 `PTNEXUS_DB_DIR`）。
+//!
+//! 只移动数据库及其 `-wal` / `-shm` / `-journal` 文件；config.json、临时目录等仍留在配置档根目录
+//! （`PTNEXUS_DATA_DIR` 不变），配置保护（configguard.rs）照常生效。
+//! 数据库可能有几十 GB，移动期间停止全部服务，主窗口显示桌面壳自带的启动页。新目录与原目录在同一文件系统时直接 rename；
+//! 否则按 8 MB 分块复制到 `<新目录>/<文件名>.partial`，每块落盘后把已完成的偏移与滚动哈希
+//! 写入 `<文件名>.partial.json`，复制完重新读取 `.partial` 核对哈希，一致才改为正式文件名、
+//! 写入 runtime.env 并删除原文件。移动计划保存在配置档根目录的 `data-move.json` 中：中途退出或断电后，
+//! 下次启动时先从记录的偏移继续，完成后才启动服务；原文件在此期间被改动过时该文件从头复制。
+//! 取消或失败时撤销已做的改动，按原目录重启服务。进度作为启动阶段（`bootstrap-stage`）由启动页显示，
+//! 带字节数的 `migration-progress` 事件保留给需要进度条的页面。
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::runtime::{self, RuntimeManager};
+use crate::{database, datadir, fsutil, status};
+
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+const PLAN_FILE: &str = "data-move.json";
+const PARTIAL_SUFFIX: &str = ".partial";
+const CHECKPOINT_SUFFIX: &str = ".partial.json";
+const PROBE_FILE: &str = ".ptnexus-move-probe";
+/// SQLite 主文件之外可能存在的 WAL、共享内存与回滚日志文件；进程被强制结束后留下的
+/// `-journal` 必须与数据库放在一起，下次打开时才能回滚未完成的事务。
+const COMPANION_SUFFIXES: [&str; 3] = ["-wal", "-shm", "-journal"];
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+static MOVING: AtomicBool = AtomicBool::new(false);
+static CANCEL: AtomicBool = AtomicBool::new(false);
+
+/// 持久化的移动计划，下次启动据此继续。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct MovePlan {
+    source_dir: PathBuf,
+    target_dir: PathBuf,
+    /// 两个目录在同一文件系统，逐个 rename 即可。
+    rename: bool,
+    files: Vec<String>,
+    /// 全部文件的字节数。
+    total: u64,
+}
+
+/// `.partial` 已写入并落盘的位置。
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Checkpoint {
+    offset: u64,
+    /// 前 `offset` 个字节的 FNV-1a 哈希。
+    hash: u64,
+    source_len: u64,
+    source_modified_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct MoveProgress {
+    stage: &'static str,
+    message: String,
+    copied: u64,
+    total: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    eta_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+}
+
+/// 是否正在移动数据目录，此时不允许重启服务。
+pub fn in_progress() -> bool {
+    MOVING.load(Ordering::SeqCst)
+}
+
+/// 移动期间拒绝启动服务。
+pub fn ensure_idle() -> Result<(), String> {
+    if in_progress() {
+        Err("正在移动数据目录，完成或取消后会自动启动服务".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// 请求取消正在进行的移动，在当前分块写完后生效；没有进行中的移动时返回 false。
+pub fn cancel() -> bool {
+    if in_progress() {
+        CANCEL.store(true, Ordering::SeqCst);
+    }
+    in_progress()
+}
+
+/// 停止服务，把当前 SQLite 数据库移动到 `target` 并写入 runtime.env，然后重启服务。
+/// 服务停止期间 WebUI 不可用，主窗口切换到启动页，服务重启后由启动流程导航回运行时页面。
+pub fn start(app: &AppHandle, runtime: &RuntimeManager, target: &str) -> Result<(), String> {
+    let target_dir = PathBuf::from(target.trim());
+    if !target_dir.is_absolute() {
+        return Err("请选择一个完整路径作为新的数据目录".to_string());
+    }
+    let context = runtime
+        .context()
+        .ok_or_else(|| "运行时状态不可用".to_string())?;
+    let database = database::sqlite_path(&context.common_env)
+        .ok_or_else(|| "当前数据库不是 SQLite，没有需要移动的数据文件".to_string())?;
+    let profile = datadir::active_profile(app)?;
+    let plan_path = profile.root.join(PLAN_FILE);
+    if plan_path.exists() {
+        return Err("上次的数据目录移动尚未完成，请重启应用继续".to_string());
+    }
+    if MOVING.swap(true, Ordering::SeqCst) {
+        return Err("数据目录正在移动中".to_string());
+    }
+
+    let plan = match create_plan(&database, &target_dir)
+        .and_then(|plan| write_plan(&plan_path, &plan).map(|()| plan))
+    {
+        Ok(plan) => plan,
+        Err(err) => {
+            MOVING.store(false, Ordering::SeqCst);
+            return Err(err);
+        }
+    };
+
+    runtime::show_startup_page(app);
+    emit(app, "stopping", "正在停止后端服务", &plan, 0);
+    runtime.shutdown_all();
+    let result = run(app, &plan_path, &plan, &profile.root.join("runtime.env"));
+    MOVING.store(false, Ordering::SeqCst);
+
+    if let Err(err) = runtime.restart(app) {
+        return Err(match result {
+            Ok(()) => format!("数据目录已移动，但重启服务失败：{err}"),
+            Err(move_err) => format!("{move_err}\n\n且按原目录重启服务失败：{err}"),
+        });
+    }
+    result
+}
+
+/// 当前配置档是否有上次中途退出、尚未完成的移动。
+pub fn has_pending(app: &AppHandle) -> bool {
+    datadir::active_profile(app)
+        .is_ok_and(|profile| read_plan(&profile.root.join(PLAN_FILE)).is_some())
+}
+
+/// 在启动服务之前调用：上次移动中途退出时从断点继续，结束（完成或撤销）后才返回。
+pub fn resume_pending(app: &AppHandle) {
+    let Ok(profile) = datadir::active_profile(app) else {
+        return;
+    };
+    let plan_path = profile.root.join(PLAN_FILE);
+    let Some(plan) = read_plan(&plan_path) else {
+        return;
+    };
+    if MOVING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    journal::record(app, Severity::Info, None, "继续上次未完成的数据目录移动");
+    let _ = run(app, &plan_path, &plan, &profile.root.join("runtime.env"));
+    MOVING.store(false, Ordering::SeqCst);
+    status::mark_starting(app);
+}
+
+/// 执行计划并切换 runtime.env；失败或取消时撤销改动。无论结果如何都删除计划。
+fn run(app: &AppHandle, plan_path: &Path, plan: &MovePlan, env_file: &Path) -> Result<(), String> {
+    CANCEL.store(false, Ordering::SeqCst);
+    status::mark_stopped(app);
+    runtime::shell_log(
+        app,
+        &format!(
+            "[INFO] 开始移动数据库 {} → {}（{}）",
+            plan.source_dir.display(),
+            plan.target_dir.display(),
+            if plan.rename {
+                "同一磁盘，直接移动"
+            } else {
+                "跨磁盘复制"
+            }
+        ),
+    );
+
+    let mut eta = Eta::default();
+    let mut last_emit: Option<Instant> = None;
+    let mut report = |file: &str, copied: u64| {
+        let eta_secs = eta.update(copied, plan.total);
+        if last_emit.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) && copied < plan.total {
+            return;
+        }
+        last_emit = Some(Instant::now());
+        let percent = (copied * 100).checked_div(plan.total).unwrap_or(100);
+        runtime::emit_stage(
+            app,
+            "data-move",
+            format!("正在移动数据库 {file}（{percent}%）"),
+        );
+        events::emit(
+            app,
+            "migration-progress",
+            Replay::Latest,
+            MoveProgress {
+                stage: if plan.rename { "moving" } else { "copying" },
+                message: format!("正在移动 {file}"),
+                copied,
+                total: plan.total,
+                eta_secs,
+                file: Some(file.to_string()),
+            },
+        );
+    };
+
+    let result = transfer(plan, &CANCEL, CHUNK_SIZE, &mut report).and_then(|()| {
+        emit(app, "switching", "正在切换到新的数据目录", plan, plan.total);
+        switch(plan, env_file)
+    });
+    match &result {
+        Ok(leftovers) => {
+            for path in leftovers {
+                runtime::shell_log(
+                    app,
+                    &format!("[WARN] 未能删除原数据文件 {}，可手动删除", path.display()),
+                );
+            }
+            runtime::shell_log(
+                app,
+                "[INFO] 数据库移动完成，已写入 runtime.env 的 PTNEXUS_DB_DIR",
+            );
+            emit(
+                app,
+                "done",
+                "数据目录已移动，正在重启服务",
+                plan,
+                plan.total,
+            );
+        }
+        Err(err) => {
+            roll_back(plan);
+            let cancelled = CANCEL.load(Ordering::SeqCst);
+            let (stage, message) = if cancelled {
+                ("cancelled", "已取消移动，正在按原目录重启服务".to_string())
+            } else {
+                ("failed", format!("移动失败，已恢复原目录：{err}"))
+            };
+            runtime::shell_log(app, &format!("[WARN] {message}"));
+            emit(app, stage, &message, plan, 0);
+        }
+    }
+    let _ = fs::remove_file(plan_path);
+    result.map(|_| ())
+}
+
+/// 同时作为启动阶段发出，由启动页显示。
+fn emit(app: &AppHandle, stage: &'static str, message: &str, plan: &MovePlan, copied: u64) {
+    runtime::emit_stage(app, "data-move", message.to_string());
+    events::emit(
+        app,
+        "migration-progress",
+        Replay::Latest,
+        MoveProgress {
+            stage,
+            message: message.to_string(),
+            copied,
+            total: plan.total,
+            eta_secs: None,
+            file: None,
+        },
+    );
+}
+
+/// 按本次运行的平均速度估算剩余秒数；刚开始或刚续传时尚无估计。
+#[derive(Default)]
+struct Eta {
+    started: Option<(Instant, u64)>,
+}
+
+impl Eta {
+    fn update(&mut self, copied: u64, total: u64) -> Option<u64> {
+        let (at, from) = *self.started.get_or_insert((Instant::now(), copied));
+        let elapsed = at.elapsed().as_secs_f64();
+        let done = copied.saturating_sub(from);
+        if done == 0 || elapsed < 1.0 {
+            return None;
+        }
+        let rate = done as f64 / elapsed;
+        Some((total.saturating_sub(copied) as f64 / rate).ceil() as u64)
+    }
+}
+
+/// 列出要移动的文件并判断能否直接 rename；新目录中已有同名文件时拒绝，以免覆盖。
+fn create_plan(database: &Path, target_dir: &Path) -> Result<MovePlan, String> {
+    let source_dir = database
+        .parent()
+        .ok_or_else(|| format!("无法确定数据库所在目录: {}", database.display()))?;
+    let name = database
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| format!("无法确定数据库文件名: {}", database.display()))?;
+    if !database.is_file() {
+        return Err(format!("未找到 SQLite 数据库: {}", database.display()));
+    }
+    fs::create_dir_all(target_dir)
+        .map_err(|e| format!("创建新数据目录失败 ({}): {e}", target_dir.display()))?;
+    if same_dir(source_dir, target_dir) {
+        return Err("新目录与当前数据目录相同".to_string());
+    }
+
+    let mut files = vec![name.clone()];
+    files.extend(
+        COMPANION_SUFFIXES
+            .iter()
+            .map(|suffix| format!("{name}{suffix}"))
+            .filter(|companion| source_dir.join(companion).is_file()),
+    );
+    let mut total = 0;
+    for file in &files {
+        if target_dir.join(file).exists() {
+            return Err(format!("新目录中已有 {file}，请换一个目录或先移走该文件"));
+        }
+        total += fs::metadata(source_dir.join(file))
+            .map_err(|e| format!("读取 {file} 失败: {e}"))?
+            .len();
+    }
+    Ok(MovePlan {
+        source_dir: source_dir.to_path_buf(),
+        target_dir: target_dir.to_path_buf(),
+        rename: same_filesystem(source_dir, target_dir),
+        files,
+        total,
+    })
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// 试着把一个探测文件从原目录 rename 到新目录，成功即说明两者在同一文件系统。
+fn same_filesystem(source_dir: &Path, target_dir: &Path) -> bool {
+    let probe = source_dir.join(PROBE_FILE);
+    let moved = target_dir.join(PROBE_FILE);
+    if fs::write(&probe, b"").is_err() {
+        return false;
+    }
+    let renamed = fs::rename(&probe, &moved).is_ok();
+    let _ = fs::remove_file(&probe);
+    let _ = fs::remove_file(&moved);
+    renamed
+}
+
+/// 移动或复制计划中的全部文件，已完成的部分直接跳过。`progress` 收到文件名与累计字节数。
+fn transfer(
+    plan: &MovePlan,
+    cancel: &AtomicBool,
+    chunk_size: usize,
+    progress: &mut dyn FnMut(&str, u64),
+) -> Result<(), String> {
+    let mut done = 0;
+    for name in &plan.files {
+        let (source, target) = (plan.source_dir.join(name), plan.target_dir.join(name));
+        if plan.rename {
+            if source.exists() || !target.exists() {
+                fs::rename(&source, &target).map_err(|e| format!("移动 {name} 失败: {e}"))?;
+            }
+        } else {
+            copy_resumable(&source, &target, chunk_size, cancel, &mut |offset| {
+                progress(name, done + offset)
+            })?;
+        }
+        done += fs::metadata(&target).map(|meta| meta.len()).unwrap_or(0);
+        progress(name, done);
+    }
+    Ok(())
+}
+
+/// 把 `source` 断点续传地复制为 `target`。`target` 已存在且没有 `.partial` 时视为上次已完成。
+fn copy_resumable(
+    source: &Path,
+    target: &Path,
+    chunk_size: usize,
+    cancel: &AtomicBool,
+    progress: &mut dyn FnMut(u64),
+) -> Result<(), String> {
+    let partial = with_suffix(target, PARTIAL_SUFFIX);
+    let checkpoint_path = with_suffix(target, CHECKPOINT_SUFFIX);
+    if target.exists() && !partial.exists() {
+        return Ok(());
+    }
+    let name = source.display().to_string();
+    let meta = fs::metadata(source).map_err(|e| format!("读取 {name} 失败: {e}"))?;
+    let (source_len, source_modified_ms) = (meta.len(), modified_ms(&meta));
+    let partial_len = fs::metadata(&partial).map(|meta| meta.len()).unwrap_or(0);
+    let mut checkpoint = read_checkpoint(&checkpoint_path)
+        .filter(|saved| {
+            saved.source_len == source_len
+                && saved.source_modified_ms == source_modified_ms
+                && saved.offset <= source_len
+                && saved.offset <= partial_len
+        })
+        .unwrap_or(Checkpoint {
+            offset: 0,
+            hash: FNV_OFFSET,
+            source_len,
+            source_modified_ms,
+        });
+
+    let io_err = |e: io::Error| format!("复制 {name} 失败: {e}");
+    let mut input = File::open(source).map_err(io_err)?;
+    let mut output = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&partial)
+        .map_err(io_err)?;
+    // 丢弃最后一次记录之后写入的内容
+    output.set_len(checkpoint.offset).map_err(io_err)?;
+    output
+        .seek(SeekFrom::Start(checkpoint.offset))
+        .map_err(io_err)?;
+    input
+        .seek(SeekFrom::Start(checkpoint.offset))
+        .map_err(io_err)?;
+    progress(checkpoint.offset);
+
+    let mut buffer = vec![0; chunk_size];
+    while checkpoint.offset < source_len {
+        if cancel.load(Ordering::SeqCst) {
+            return Err("已取消".to_string());
+        }
+        let len = (source_len - checkpoint.offset).min(chunk_size as u64) as usize;
+        input.read_exact(&mut buffer[..len]).map_err(io_err)?;
+        output.write_all(&buffer[..len]).map_err(io_err)?;
+        output.sync_data().map_err(io_err)?;
+        checkpoint.hash = fnv1a(checkpoint.hash, &buffer[..len]);
+        checkpoint.offset += len as u64;
+        write_checkpoint(&checkpoint_path, &checkpoint)?;
+        progress(checkpoint.offset);
+    }
+    drop(output);
+
+    if hash_file(&partial).map_err(io_err)? != checkpoint.hash {
+        let _ = fs::remove_file(&partial);
+        let _ = fs::remove_file(&checkpoint_path);
+        return Err(format!("{name} 复制后校验不一致，新目录所在磁盘可能有问题"));
+    }
+    fs::rename(&partial, target).map_err(io_err)?;
+    let _ = fs::remove_file(&checkpoint_path);
+    Ok(())
+}
+
+/// 写入新的 PTNEXUS_DB_DIR 并删除原文件，返回未能删除的原文件。
+fn switch(plan: &MovePlan, env_file: &Path) -> Result<Vec<PathBuf>, String> {
+    let target = plan.target_dir.to_string_lossy();
+    runtime::update_env_file(env_file, &[(database::SQLITE_DIR_KEY, Some(&target))])?;
+    if plan.rename {
+        return Ok(Vec::new());
+    }
+    Ok(plan
+        .files
+        .iter()
+        .map(|name| plan.source_dir.join(name))
+        .filter(|source| match fs::remove_file(source) {
+            Ok(()) => false,
+            Err(err) => err.kind() != io::ErrorKind::NotFound,
+        })
+        .collect())
+}
+
+/// 撤销未切换完成的移动：rename 过去的文件移回原处，复制出的文件与 `.partial` 删除。
+fn roll_back(plan: &MovePlan) {
+    for name in &plan.files {
+        let (source, target) = (plan.source_dir.join(name), plan.target_dir.join(name));
+        if plan.rename {
+            if !source.exists() && target.exists() {
+                let _ = fs::rename(&target, &source);
+            }
+            continue;
+        }
+        let _ = fs::remove_file(with_suffix(&target, PARTIAL_SUFFIX));
+        let _ = fs::remove_file(with_suffix(&target, CHECKPOINT_SUFFIX));
+        // 原文件还在才删除复制出的文件，否则它就是仅剩的一份
+        if source.exists() {
+            let _ = fs::remove_file(&target);
+        }
+    }
+}
+
+fn read_plan(path: &Path) -> Option<MovePlan> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn write_plan(path: &Path, plan: &MovePlan) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(plan).map_err(|e| e.to_string())?;
+    fsutil::atomic_write(path, content).map_err(|e| format!("保存移动计划失败: {e}"))
+}
+
+fn read_checkpoint(path: &Path) -> Option<Checkpoint> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn write_checkpoint(path: &Path, checkpoint: &Checkpoint) -> Result<(), String> {
+    let content = serde_json::to_string(checkpoint).map_err(|e| e.to_string())?;
+    fsutil::atomic_write(path, content).map_err(|e| format!("记录复制进度失败: {e}"))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn modified_ms(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut hash = FNV_OFFSET;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hash);
+        }
+        hash = fnv1a(hash, &buffer[..read]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    fn copy_plan(dir: &Path, content: &[u8]) -> MovePlan {
+        let (source_dir, target_dir) = (dir.join("old"), dir.join("new"));
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::write(source_dir.join("pt_stats.db"), content).unwrap();
+        fs::write(source_dir.join("pt_stats.db-wal"), b"wal").unwrap();
+        let mut plan = create_plan(&source_dir.join("pt_stats.db"), &target_dir).unwrap();
+        plan.rename = false;
+        plan
+    }
+
+    #[test]
+    fn interrupted_copy_resumes_from_the_checkpoint() {
+        let dir = temp_dir("resume");
+        let content = sample(10_000);
+        let plan = copy_plan(&dir, &content);
+        assert_eq!(plan.files, ["pt_stats.db", "pt_stats.db-wal"]);
+        assert_eq!(plan.total, 10_003);
+
+        // 第三块写完后取消，模拟中途退出
+        let cancel = AtomicBool::new(false);
+        let mut chunks = 0;
+        let err = transfer(&plan, &cancel, 1024, &mut |_, _| {
+            chunks += 1;
+            if chunks == 4 {
+                cancel.store(true, Ordering::SeqCst);
+            }
+        })
+        .unwrap_err();
+        assert_eq!(err, "已取消");
+        let target = plan.target_dir.join("pt_stats.db");
+        assert!(!target.exists());
+        let saved = read_checkpoint(&with_suffix(&target, CHECKPOINT_SUFFIX)).unwrap();
+        assert_eq!(saved.offset, 3 * 1024);
+
+        let mut reported = Vec::new();
+        let cancel = AtomicBool::new(false);
+        transfer(&plan, &cancel, 1024, &mut |_, copied| reported.push(copied)).unwrap();
+        assert_eq!(reported.first(), Some(&(3 * 1024)));
+        assert_eq!(reported.last(), Some(&plan.total));
+        assert_eq!(fs::read(&target).unwrap(), content);
+        assert!(!with_suffix(&target, PARTIAL_SUFFIX).exists());
+
+        let env_file = dir.join("runtime.env");
+        assert!(switch(&plan, &env_file).unwrap().is_empty());
+        assert!(!plan.source_dir.join("pt_stats.db").exists());
+        let env = fs::read_to_string(&env_file).unwrap();
+        assert!(env.contains(&format!("PTNEXUS_DB_DIR={}", plan.target_dir.display())));
+        assert!(!env.contains("PTNEXUS_DATA_DIR"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn changed_source_or_corrupt_partial_restarts_the_file() {
+        let dir = temp_dir("changed");
+        let plan = copy_plan(&dir, &sample(4096));
+        let source = plan.source_dir.join("pt_stats.db");
+        let target = plan.target_dir.join("pt_stats.db");
+        let cancel = AtomicBool::new(false);
+        let mut calls = 0;
+        let _ = copy_resumable(&source, &target, 1024, &cancel, &mut |_| {
+            calls += 1;
+            if calls == 2 {
+                cancel.store(true, Ordering::SeqCst);
+            }
+        });
+        assert!(with_suffix(&target, PARTIAL_SUFFIX).exists());
+
+        let changed = sample(5000);
+        fs::write(&source, &changed).unwrap();
+        let cancel = AtomicBool::new(false);
+        let mut first = None;
+        copy_resumable(&source, &target, 1024, &cancel, &mut |offset| {
+            first.get_or_insert(offset);
+        })
+        .unwrap();
+        assert_eq!(first, Some(0));
+        assert_eq!(fs::read(&target).unwrap(), changed);
+
+        // 已写入的内容与记录的哈希不一致时不会改名为正式文件
+        fs::remove_file(&target).unwrap();
+        let partial = with_suffix(&target, PARTIAL_SUFFIX);
+        fs::write(&partial, vec![0; 5000]).unwrap();
+        write_checkpoint(
+            &with_suffix(&target, CHECKPOINT_SUFFIX),
+            &Checkpoint {
+                offset: 5000,
+                hash: fnv1a(FNV_OFFSET, &changed),
+                source_len: 5000,
+                source_modified_ms: modified_ms(&fs::metadata(&source).unwrap()),
+            },
+        )
+        .unwrap();
+        assert!(copy_resumable(&source, &target, 1024, &cancel, &mut |_| {}).is_err());
+        assert!(!target.exists() && !partial.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rename_moves_and_roll_back_restores() {
+        let dir = temp_dir("rename");
+        let source_dir = dir.join("old");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::write(source_dir.join("pt_stats.db"), b"db").unwrap();
+        fs::write(source_dir.join("pt_stats.db-journal"), b"journal").unwrap();
+        fs::write(source_dir.join("config.json"), b"{}").unwrap();
+        let plan = create_plan(&source_dir.join("pt_stats.db"), &dir.join("new")).unwrap();
+        assert!(plan.rename);
+        assert_eq!(plan.files, ["pt_stats.db", "pt_stats.db-journal"]);
+        assert!(!source_dir.join(PROBE_FILE).exists());
+
+        let cancel = AtomicBool::new(false);
+        transfer(&plan, &cancel, CHUNK_SIZE, &mut |_, _| {}).unwrap();
+        assert!(!source_dir.join("pt_stats.db").exists());
+        assert_eq!(
+            fs::read(dir.join("new").join("pt_stats.db")).unwrap(),
+            b"db"
+        );
+        assert!(dir.join("new").join("pt_stats.db-journal").exists());
+        assert!(source_dir.join("config.json").exists());
+        roll_back(&plan);
+        assert_eq!(fs::read(source_dir.join("pt_stats.db")).unwrap(), b"db");
+        assert!(!dir.join("new").join("pt_stats.db").exists());
+
+        // 新目录中已有同名文件时拒绝
+        fs::write(dir.join("new").join("pt_stats.db"), b"other").unwrap();
+        assert!(create_plan(&source_dir.join("pt_stats.db"), &dir.join("new")).is_err());
+        assert!(create_plan(&source_dir.join("pt_stats.db"), &source_dir).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
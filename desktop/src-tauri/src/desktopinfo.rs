@@ -0,0 +1,147 @@
+//! 告诉 WebUI 它运行在桌面壳中，以及桌面壳提供了哪些能力。
+//!
+//! 以初始化脚本注入 `window.__PTNEXUS_DESKTOP__`：每次导航后、页面脚本执行前即已存在，
+//! WebUI 据此判断是否显示桌面端功能，不必探测 `__TAURI_INTERNALS__` 或试探调用命令。
+//! 对象及其 features 数组都被冻结，属性不可改写、不可删除。字段：
+//!
+//! - `version`：string，桌面壳版本号，如 "0.1.0"
+//! - `platform`："windows" | "macos" | "linux"
+//! - `features`：string[]，取值见 [`KNOWN_FEATURES`]，未列出的能力不可用
+//! - `injections`：string[]，桌面壳仍在注入的页面脚本，取值见 injections.rs，未列出的由 WebUI 自行实现
+//! - `coldStart`：boolean，当前页面是桌面壳缓存的 WebUI（见 webuicache.rs），后端尚未就绪，接口不可用
+//!
+//! injections 是只读访问器：原地更新 WebUI 后窗口不会重建，每次注入时由 runtime.rs 刷新其取值。
+//! coldStart 按当前页面地址计算，同一窗口先后加载缓存页面与运行时页面时取值不同。
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::{injections, runtime, script, webuicache};
+
+/// features 可能包含的取值及含义，按此顺序列出。
+pub const KNOWN_FEATURES: &[(&str, &str)] = &[
+    ("notifications", "系统通知"),
+    ("file-dialogs", "原生的打开/保存文件对话框"),
+    ("lan-mode", "WebUI 监听在通配地址上，可从局域网访问"),
+];
+
+#[derive(Debug, Serialize)]
+pub struct DesktopInfo {
+    pub version: String,
+    pub platform: &'static str,
+    pub features: Vec<&'static str>,
+    pub injections: Vec<&'static str>,
+}
+
+const DEFINE_JS: &str = r#"
+  if (Object.prototype.hasOwnProperty.call(window, '__PTNEXUS_DESKTOP__')) return;
+  Object.freeze(info.features);
+  const injections = Object.freeze(info.injections);
+  delete info.injections;
+  Object.defineProperty(info, 'injections', {
+    get: () => window.__PTNEXUS_ACTIVE_INJECTIONS__ || injections,
+    enumerable: true,
+    configurable: false,
+  });
+  Object.defineProperty(info, 'coldStart', {
+    get: () => location.protocol === cacheScheme + ':' || location.hostname === cacheScheme + '.localhost',
+    enumerable: true,
+    configurable: false,
+  });
+  Object.defineProperty(window, '__PTNEXUS_DESKTOP__', {
+    value: Object.freeze(info),
+    writable: false,
+    enumerable: false,
+    configurable: false,
+  });
+"#;
+
+pub fn current(app: &AppHandle) -> DesktopInfo {
+    let lan_mode = runtime::read_runtime_setting(app, "UPDATER_HOST")
+        .is_some_and(|host| is_wildcard(host.trim()));
+    DesktopInfo {
+        version: app.package_info().version.to_string(),
+        platform: std::env::consts::OS,
+        features: features(lan_mode),
+        injections: injections::active(app),
+    }
+}
+
+/// 创建窗口时传给 `initialization_script` 的脚本。
+pub fn init_script(info: &DesktopInfo) -> String {
+    let value = serde_json::to_value(info).unwrap_or_default();
+    script::call_with_args(
+        DEFINE_JS,
+        &[
+            ("info", value),
+            ("cacheScheme", serde_json::Value::from(webuicache::SCHEME)),
+        ],
+    )
+}
+
+fn features(lan_mode: bool) -> Vec<&'static str> {
+    KNOWN_FEATURES
+        .iter()
+        .map(|(feature, _)| *feature)
+        .filter(|feature| lan_mode || *feature != "lan-mode")
+        .collect()
+}
+
+fn is_wildcard(host: &str) -> bool {
+    matches!(host, "0.0.0.0" | "::" | "[::]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模块文档中列出的字段。
+    const FIELDS: &[&str] = &["version", "platform", "features", "injections", "coldStart"];
+
+    #[test]
+    fn serialization_matches_schema() {
+        let info = DesktopInfo {
+            version: "0.1.0".to_string(),
+            platform: "linux",
+            features: features(true),
+            injections: injections::known(),
+        };
+        let value = serde_json::to_value(&info).unwrap();
+        let keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        // coldStart 由脚本定义
+        let mut documented: Vec<&str> = FIELDS
+            .iter()
+            .copied()
+            .filter(|key| *key != "coldStart")
+            .collect();
+        documented.sort_unstable();
+        assert_eq!(keys, documented);
+        assert_eq!(
+            value["features"],
+            serde_json::json!(["notifications", "file-dialogs", "lan-mode"])
+        );
+        assert!(!features(false).contains(&"lan-mode"));
+        assert_eq!(
+            value["injections"],
+            serde_json::json!(["db-config-button", "startup-overlay"])
+        );
+    }
+
+    #[test]
+    fn script_defines_a_frozen_object() {
+        let script = init_script(&DesktopInfo {
+            version: "0.1.0".to_string(),
+            platform: "windows",
+            features: features(false),
+            injections: Vec::new(),
+        });
+        assert!(script.contains("Object.freeze(info)"));
+        assert!(script.contains(r#""platform":"windows""#));
+        assert!(script.contains(r#""ptnexus-cache""#));
+    }
+}
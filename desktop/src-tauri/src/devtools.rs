@@ -0,0 +1,195 @@
+//! 开发者工具：排查用户那边的 WebUI 问题时打开 WebView 的开发者工具。
+//!
+//! 默认不可用，满足以下任一条件时才允许打开：调试构建；runtime.env 中设置 `PTNEXUS_DEVTOOLS=1`；
+//! 在托盘菜单中 10 秒内连续点击版本号 5 次（只在本次运行有效）。`toggle_devtools` 与窗口中的
+//! Ctrl+Shift+I（macOS 上也可以用 Cmd+Option+I）打开或关闭当前窗口的开发者工具，未启用时返回错误。
+//! 启用状态显示在 `get_system_info` 中。
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, WebviewWindow};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::journal::{self, Severity};
+use crate::runtime;
+
+pub const ENV_KEY: &str = "PTNEXUS_DEVTOOLS";
+pub const VERSION_MENU_ID: &str = "version";
+const UNLOCK_CLICKS: usize = 5;
+const UNLOCK_WINDOW: Duration = Duration::from_secs(10);
+/// 桌面平台的 WebView（WebView2、WKWebView、WebKitGTK）都带开发者工具。
+const SUPPORTED: bool = cfg!(any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "linux"
+));
+
+/// 作为初始化脚本注入主窗口，在捕获阶段处理，页面自己的快捷键不会抢先拦截。
+pub const SHORTCUT_JS: &str = r#"
+(function() {
+  if (window.__PTNEXUS_DEVTOOLS_KEY__) return;
+  window.__PTNEXUS_DEVTOOLS_KEY__ = true;
+  window.addEventListener('keydown', function(event) {
+    // macOS 上 Option 会改变 event.key，按物理按键判断
+    if (event.code !== 'KeyI') return;
+    if (!((event.ctrlKey && event.shiftKey) || (event.metaKey && event.altKey))) return;
+    event.preventDefault();
+    try {
+      var pending = window.__TAURI_INTERNALS__.invoke('toggle_devtools', {});
+      if (pending && typeof pending.catch === 'function') {
+        pending.catch(function(err) { console.warn((err && err.message) || String(err)); });
+      }
+    } catch (e) {}
+  }, true);
+})();
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DevtoolsSource {
+    DebugBuild,
+    RuntimeEnv,
+    TraySequence,
+}
+
+#[derive(Serialize)]
+pub struct DevtoolsStatus {
+    pub enabled: bool,
+    /// 因何启用；未启用时为 None。
+    pub source: Option<DevtoolsSource>,
+    /// 当前平台的 WebView 是否支持开发者工具。
+    pub supported: bool,
+}
+
+#[derive(Default)]
+pub struct DevtoolsState {
+    unlocked: AtomicBool,
+    clicks: Mutex<ClickCounter>,
+}
+
+#[derive(Default)]
+struct ClickCounter(VecDeque<Instant>);
+
+impl ClickCounter {
+    /// 记录一次点击，UNLOCK_WINDOW 内累计 UNLOCK_CLICKS 次时返回 true 并清零。
+    fn register(&mut self, now: Instant) -> bool {
+        while self
+            .0
+            .front()
+            .is_some_and(|first| now.duration_since(*first) > UNLOCK_WINDOW)
+        {
+            self.0.pop_front();
+        }
+        self.0.push_back(now);
+        if self.0.len() < UNLOCK_CLICKS {
+            return false;
+        }
+        self.0.clear();
+        true
+    }
+}
+
+pub fn status(app: &AppHandle) -> DevtoolsStatus {
+    let source = if cfg!(debug_assertions) {
+        Some(DevtoolsSource::DebugBuild)
+    } else if runtime::read_runtime_setting(app, ENV_KEY)
+        .is_some_and(|value| runtime::is_truthy(&value))
+    {
+        Some(DevtoolsSource::RuntimeEnv)
+    } else if app
+        .try_state::<DevtoolsState>()
+        .is_some_and(|state| state.unlocked.load(Ordering::SeqCst))
+    {
+        Some(DevtoolsSource::TraySequence)
+    } else {
+        None
+    };
+    DevtoolsStatus {
+        enabled: source.is_some(),
+        source,
+        supported: SUPPORTED,
+    }
+}
+
+/// 托盘菜单中的版本号被点击；连续点击够次数时在本次运行中启用开发者工具。
+pub fn register_version_click(app: &AppHandle) {
+    let Some(state) = app.try_state::<DevtoolsState>() else {
+        return;
+    };
+    let unlocked = state
+        .clicks
+        .lock()
+        .map(|mut clicks| clicks.register(Instant::now()))
+        .unwrap_or(false);
+    if !unlocked || state.unlocked.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    journal::record(
+        app,
+        Severity::Info,
+        None,
+        "已通过托盘菜单启用开发者工具（本次运行有效）",
+    );
+    let _ = app
+        .notification()
+        .builder()
+        .title("PT Nexus 已启用开发者工具")
+        .body("在窗口中按 Ctrl+Shift+I 打开或关闭开发者工具，退出应用后恢复关闭。")
+        .show();
+}
+
+/// 打开或关闭窗口的开发者工具，返回操作后是否处于打开状态。
+pub fn toggle(app: &AppHandle, window: &WebviewWindow) -> Result<bool, String> {
+    if !SUPPORTED {
+        return Err("当前平台的 WebView 不支持开发者工具".to_string());
+    }
+    if !status(app).enabled {
+        return Err(format!(
+            "开发者工具未启用：在 runtime.env 中设置 {ENV_KEY}=1 后重启应用，或在托盘菜单中连续点击版本号 {UNLOCK_CLICKS} 次"
+        ));
+    }
+    Ok(toggle_window(window))
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+fn toggle_window(window: &WebviewWindow) -> bool {
+    if window.is_devtools_open() {
+        window.close_devtools();
+        false
+    } else {
+        window.open_devtools();
+        true
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn toggle_window(_window: &WebviewWindow) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn five_quick_clicks_unlock() {
+        let mut counter = ClickCounter::default();
+        let start = Instant::now();
+        for i in 0..4 {
+            assert!(!counter.register(start + Duration::from_secs(i)));
+        }
+        assert!(counter.register(start + Duration::from_secs(4)));
+        // 解锁后重新计数
+        assert!(!counter.register(start + Duration::from_secs(5)));
+
+        // 间隔太久的点击不累计
+        let mut counter = ClickCounter::default();
+        for i in 0..6 {
+            assert!(!counter.register(start + Duration::from_secs(i * 3)));
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! 诊断页：运行记录时间线、启动自检结果与环境信息；以及打包上述内容的诊断包。
+//!
+//! 与日志查看器一样只依赖桌面壳自身，后端启动失败时也能打开：窗口只允许停留在桌面壳的本地页面，
+//! 不会加载运行时 WebUI。诊断包只读取能读到的文件，数据目录只初始化了一部分时照样生成。
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde_json::json;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::{datadir, journal, runtime, selftest, status, tasks, webviewprofile};
+
+pub const DIAGNOSTICS_LABEL: &str = "diagnostics";
+/// 诊断包保存在应用数据目录下的该子目录中。
+const BUNDLE_DIR: &str = "diagnostics";
+/// 每个日志文件只打包末尾这么多字节。
+const LOG_TAIL_BYTES: u64 = 1024 * 1024;
+const EVENT_LIMIT: usize = 500;
+
+pub fn open_window(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(DIAGNOSTICS_LABEL) {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    let builder = WebviewWindowBuilder::new(
+        app,
+        DIAGNOSTICS_LABEL,
+        WebviewUrl::App("diagnostics.html".into()),
+    )
+    .title("PT Nexus 诊断信息")
+    .inner_size(860.0, 640.0)
+    .min_inner_size(560.0, 400.0)
+    .on_navigation(is_local_page);
+    webviewprofile::apply_shared(app, builder)
+        .build()
+        .map_err(|e| format!("打开诊断页失败: {e}"))?;
+    Ok(())
+}
+
+/// 桌面壳打包的本地页面；诊断页与日志查看器不离开这些页面。
+pub fn is_local_page(url: &tauri::Url) -> bool {
+    url.scheme() == "tauri" || (url.scheme() == "http" && url.host_str() == Some("tauri.localhost"))
+}
+
+/// 生成诊断包，返回保存路径。
+pub fn export_bundle(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("无法解析应用数据目录: {e}"))?;
+    let profile_dir = datadir::active_profile(app)
+        .ok()
+        .map(|profile| profile.root);
+
+    let self_test = selftest::run(app);
+    let summary = json!({
+        "generated_at": runtime::format_utc_timestamp(SystemTime::now()),
+        "app_version": app.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "app_data_dir": app_data_dir,
+        "profile_dir": profile_dir,
+        "status": status::snapshot(app),
+        "self_test": self_test,
+        "background_tasks": tasks::list(app),
+    });
+    let mut entries = vec![
+        (
+            "summary.json".to_string(),
+            serde_json::to_vec_pretty(&summary).unwrap_or_default(),
+        ),
+        (
+            "runtime-events.json".to_string(),
+            serde_json::to_vec_pretty(&journal::query(app, EVENT_LIMIT, None)).unwrap_or_default(),
+        ),
+    ];
+    entries.extend(collect_files(&app_data_dir, profile_dir.as_deref()));
+
+    let stamp = runtime::format_utc_timestamp(SystemTime::now()).replace(':', "-");
+    let path = app_data_dir
+        .join(BUNDLE_DIR)
+        .join(format!("ptnexus-diagnostics-{stamp}.zip"));
+    write_bundle(&path, &entries)?;
+    runtime::shell_log(app, &format!("[INFO] 已导出诊断包: {}", path.display()));
+    Ok(path)
+}
+
+/// 收集日志与配置，缺失的目录和文件直接跳过；runtime.env 中的密码等值被替换。
+fn collect_files(app_data_dir: &Path, profile_dir: Option<&Path>) -> Vec<(String, Vec<u8>)> {
+    let mut entries = Vec::new();
+    let mut dirs = vec![app_data_dir];
+    if let Some(profile_dir) = profile_dir.filter(|dir| *dir != app_data_dir) {
+        dirs.push(profile_dir);
+    }
+    for (index, dir) in dirs.into_iter().enumerate() {
+        let prefix = if index == 0 { "" } else { "profile/" };
+        if let Ok(bytes) = read_tail(&dir.join("bootstrap-error.log"), LOG_TAIL_BYTES) {
+            entries.push((format!("{prefix}bootstrap-error.log"), bytes));
+        }
+        if let Ok(content) = fs::read_to_string(dir.join("runtime.env")) {
+            entries.push((
+                format!("{prefix}runtime.env"),
+                redact_env(&content).into_bytes(),
+            ));
+        }
+        let Ok(logs) = fs::read_dir(dir.join("logs")) else {
+            continue;
+        };
+        let mut logs: Vec<PathBuf> = logs
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        logs.sort();
+        for path in logs {
+            let name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            if let Ok(bytes) = read_tail(&path, LOG_TAIL_BYTES) {
+                entries.push((format!("{prefix}logs/{name}"), bytes));
+            }
+        }
+    }
+    entries
+}
+
+fn read_tail(path: &Path, max_bytes: u64) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max_bytes)))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn redact_env(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| match line.split_once('=') {
+            Some((key, value))
+                if !line.trim_start().starts_with('#')
+                    && !value.trim().is_empty()
+                    && runtime::is_secret_key(key.trim()) =>
+            {
+                format!("{key}=<已隐藏>")
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn write_bundle(path: &Path, entries: &[(String, Vec<u8>)]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建 {} 失败: {e}", parent.display()))?;
+    }
+    let write = || -> zip::result::ZipResult<()> {
+        let mut zip = zip::ZipWriter::new(File::create(path)?);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        for (name, bytes) in entries {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(bytes)?;
+        }
+        zip.finish()?;
+        Ok(())
+    };
+    write().map_err(|e| {
+        let _ = fs::remove_file(path);
+        format!("写入诊断包 {} 失败: {e}", path.display())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    /// 启动在创建日志目录之前就失败：只有 bootstrap-error.log 与 runtime.env，配置档目录不存在。
+    #[test]
+    fn bundle_is_written_after_early_bootstrap_failure() {
+        let root = temp_dir("partial");
+        fs::write(root.join("bootstrap-error.log"), "运行目录不完整").unwrap();
+        fs::write(
+            root.join("runtime.env"),
+            "DB_TYPE=mysql\nMYSQL_PASSWORD=hunter2\n# MYSQL_PASSWORD=example\nAPI_TOKEN=\n",
+        )
+        .unwrap();
+
+        let entries = collect_files(&root, Some(&root.join("profiles/missing")));
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["bootstrap-error.log", "runtime.env"]);
+        let env = String::from_utf8(entries[1].1.clone()).unwrap();
+        assert!(env.contains("MYSQL_PASSWORD=<已隐藏>") && !env.contains("hunter2"));
+        assert!(env.contains("# MYSQL_PASSWORD=example") && env.contains("API_TOKEN="));
+
+        let path = root.join(BUNDLE_DIR).join("bundle.zip");
+        write_bundle(&path, &entries).unwrap();
+        assert!(fs::metadata(&path).unwrap().len() > 0);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn logs_are_truncated_to_their_tail() {
+        let root = temp_dir("tail");
+        fs::create_dir_all(root.join("logs")).unwrap();
+        fs::write(root.join("logs/server.stderr.log"), "0123456789").unwrap();
+        assert_eq!(
+            read_tail(&root.join("logs/server.stderr.log"), 4).unwrap(),
+            b"6789"
+        );
+        let entries = collect_files(&root, None);
+        assert_eq!(entries[0].0, "logs/server.stderr.log");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn only_bundled_pages_are_local() {
+        for url in [
+            "tauri://localhost/log-viewer.html",
+            "http://tauri.localhost/diagnostics.html",
+        ] {
+            assert!(is_local_page(&tauri::Url::parse(url).unwrap()), "{url}");
+        }
+        for url in ["http://127.0.0.1:5274/", "https://example.com/"] {
+            assert!(!is_local_page(&tauri::Url::parse(url).unwrap()), "{url}");
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! 用系统的文件夹选择框为 WebUI 的目录设置选择目录。
+//!
+//! WebUI 的下载目录、视频文件路径等设置原本只能手动输入绝对路径，Windows 上尤其容易写错。
+//! `pick_directory_for(setting_key, new_subfolder)` 弹出系统文件夹选择框，传入 `new_subfolder` 时在所选
+//! 目录下新建（或沿用已有的）同名子文件夹。返回解析后的绝对路径与检查结果：目录不可写、位于同步盘或
+//! 网络共享（与数据目录相同的检测，见 datadir.rs）时给出提示，由 WebUI 显示在输入框旁，保存与否由用户
+//! 决定。用户取消时返回 None，不视为错误。
+
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::{datadir, pathgrant};
+
+/// Windows 文件名中不允许出现的字符，其他平台一并拒绝，保证设置在各平台通用。
+const INVALID_NAME_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PickedDirectory {
+    /// 原样返回调用时的设置项，便于 WebUI 把结果填回对应的输入框。
+    pub setting_key: String,
+    pub path: String,
+    pub writable: bool,
+    pub warnings: Vec<String>,
+}
+
+/// 弹出文件夹选择框；用户取消时返回 None。
+pub fn pick(
+    app: &AppHandle,
+    setting_key: &str,
+    new_subfolder: Option<&str>,
+) -> Result<Option<PickedDirectory>, String> {
+    let subfolder = match new_subfolder.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => Some(validate_subfolder_name(name)?),
+        None => None,
+    };
+    let title = match subfolder {
+        Some(name) => format!("选择在其中新建「{name}」的文件夹"),
+        None => "选择文件夹".to_string(),
+    };
+    let Some(picked) = app.dialog().file().set_title(title).blocking_pick_folder() else {
+        return Ok(None);
+    };
+    let mut dir = picked
+        .into_path()
+        .map_err(|e| format!("无法使用所选文件夹: {e}"))?;
+    if let Some(name) = subfolder {
+        dir = create_subfolder(&dir, name)?;
+    }
+    inspect(setting_key, &dir).map(Some)
+}
+
+/// 子文件夹只能是单层名称，不能借 `..` 或分隔符跳到别处。
+fn validate_subfolder_name(name: &str) -> Result<&str, String> {
+    if name == "." || name == ".." || name.ends_with('.') || name.ends_with(' ') {
+        return Err(format!("无效的文件夹名称: {name}"));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| INVALID_NAME_CHARS.contains(c) || c.is_control())
+    {
+        return Err(format!("文件夹名称不能包含字符 {c:?}"));
+    }
+    Ok(name)
+}
+
+fn create_subfolder(parent: &Path, name: &str) -> Result<PathBuf, String> {
+    let dir = parent.join(name);
+    match fs::create_dir(&dir) {
+        Ok(()) => Ok(dir),
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists && dir.is_dir() => Ok(dir),
+        Err(err) => Err(format!("新建文件夹 {} 失败: {err}", dir.display())),
+    }
+}
+
+/// 检查所选目录：必须存在；不可写、位于同步盘或网络共享时给出提示。
+fn inspect(setting_key: &str, dir: &Path) -> Result<PickedDirectory, String> {
+    let canonical = dir
+        .canonicalize()
+        .map_err(|e| format!("无法访问 {}: {e}", dir.display()))?;
+    if !canonical.is_dir() {
+        return Err(format!("{} 不是文件夹", dir.display()));
+    }
+    let path = pathgrant::strip_verbatim(canonical);
+
+    let mut warnings = Vec::new();
+    let writable = probe_writable(&path);
+    if !writable {
+        warnings.push("当前用户无法写入该文件夹".to_string());
+    }
+    if let Some(risk) = datadir::inspect(&path) {
+        warnings.push(format!(
+            "该文件夹{}，同步或网络中断时读写可能失败或变慢",
+            risk.detail
+        ));
+    }
+    Ok(PickedDirectory {
+        setting_key: setting_key.to_string(),
+        path: path.to_string_lossy().to_string(),
+        writable,
+        warnings,
+    })
+}
+
+/// 只读属性与 ACL 在各平台表现不同，直接试写一个临时文件最可靠。
+pub(crate) fn probe_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".ptnexus-write-test-{}", std::process::id()));
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(err) => err.kind() == std::io::ErrorKind::AlreadyExists,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn subfolder_names_stay_in_the_picked_directory() {
+        assert_eq!(validate_subfolder_name("电影"), Ok("电影"));
+        assert!(validate_subfolder_name("..").is_err());
+        assert!(validate_subfolder_name("a/b").is_err());
+        assert!(validate_subfolder_name(r"a\b").is_err());
+        assert!(validate_subfolder_name("name.").is_err());
+        assert!(validate_subfolder_name("tab\there").is_err());
+    }
+
+    #[test]
+    fn picked_directory_is_created_and_inspected() {
+        let parent = temp_dir("picked");
+
+        let dir = create_subfolder(&parent, "downloads").unwrap();
+        // 已存在的同名文件夹直接沿用
+        assert_eq!(create_subfolder(&parent, "downloads").unwrap(), dir);
+        fs::write(parent.join("file"), b"x").unwrap();
+        assert!(create_subfolder(&parent, "file").is_err());
+
+        let picked = inspect("download_dir", &dir).unwrap();
+        assert_eq!(picked.setting_key, "download_dir");
+        assert!(Path::new(&picked.path).is_absolute());
+        assert!(picked.writable);
+        assert!(picked.warnings.is_empty());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        assert!(inspect("download_dir", &parent.join("missing")).is_err());
+        assert!(inspect("download_dir", &parent.join("file")).is_err());
+        let _ = fs::remove_dir_all(&parent);
+    }
+}
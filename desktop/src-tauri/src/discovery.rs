@@ -0,0 +1,267 @@
+//! 供浏览器用户脚本发现实际地址的 `/discovery`。
+//!
+//! 与 PT 站点集成的用户脚本过去写死 `http://127.0.0.1:5275`，端口修改或自动选择后就失效。健康检查监听
+//! （见 healthz.rs）额外提供 `GET /discovery`，返回 `{webui_url, api_url, batch_url, version}`。
+//!
+//! 带 Origin 头的跨域请求只对 runtime.env 中 `PTNEXUS_DISCOVERY_ORIGINS` 列出的站点放行：逗号分隔的域名，
+//! 同时匹配其子域名；为空时一律返回 403。没有 Origin 头的请求（curl 等本机工具）不受此限制。
+//! 每个来源每分钟最多 [`RATE_LIMIT`] 次请求，超出返回 429。`PTNEXUS_DISCOVERY=0` 关闭该接口。
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::healthz::Reply;
+use crate::runtime::{self, RuntimeManager};
+
+pub const ENABLED_KEY: &str = "PTNEXUS_DISCOVERY";
+pub const ORIGINS_KEY: &str = "PTNEXUS_DISCOVERY_ORIGINS";
+const PATH: &str = "/discovery";
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const RATE_LIMIT: u32 = 30;
+/// 记录的来源超过该数量时清理已过期的窗口。
+const MAX_TRACKED: usize = 256;
+
+#[derive(Serialize)]
+struct Discovery {
+    webui_url: String,
+    api_url: Option<String>,
+    batch_url: Option<String>,
+    version: String,
+}
+
+/// 按来源（没有 Origin 头时为空字符串）计数的固定窗口限流。
+#[derive(Default)]
+pub struct RateLimiter(Mutex<HashMap<String, (Instant, u32)>>);
+
+impl RateLimiter {
+    fn admit(&self, key: &str, now: Instant) -> bool {
+        let Ok(mut windows) = self.0.lock() else {
+            return false;
+        };
+        if windows.len() >= MAX_TRACKED {
+            windows.retain(|_, (started, _)| now.duration_since(*started) < RATE_WINDOW);
+        }
+        let window = windows.entry(key.to_string()).or_insert((now, 0));
+        if now.duration_since(window.0) >= RATE_WINDOW {
+            *window = (now, 0);
+        }
+        window.1 += 1;
+        window.1 <= RATE_LIMIT
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Method {
+    Get,
+    Preflight,
+}
+
+/// 处理 `/discovery` 请求；其他路径或接口已关闭时返回 None，由调用方返回 404。
+pub fn handle(app: &AppHandle, request: &str) -> Option<Reply> {
+    let method = route(request)?;
+    if runtime::read_runtime_setting(app, ENABLED_KEY).is_some_and(|value| is_disabled(&value)) {
+        return None;
+    }
+
+    let origin = header(request, "origin");
+    let mut headers = Vec::new();
+    if let Some(origin) = origin {
+        let allowlist = runtime::read_runtime_setting(app, ORIGINS_KEY).unwrap_or_default();
+        if !origin_allowed(origin, &parse_allowlist(&allowlist)) {
+            return Some(error(
+                "403 Forbidden",
+                format!("来源 {origin} 不在 {ORIGINS_KEY} 中"),
+            ));
+        }
+        headers = cors_headers(origin, request);
+    }
+
+    let allowed = app
+        .try_state::<RateLimiter>()
+        .is_none_or(|limiter| limiter.admit(origin.unwrap_or_default(), Instant::now()));
+    if !allowed {
+        let mut reply = error("429 Too Many Requests", "请求过于频繁".to_string());
+        reply.headers.extend(headers);
+        reply
+            .headers
+            .push(("Retry-After", RATE_WINDOW.as_secs().to_string()));
+        return Some(reply);
+    }
+
+    if method == Method::Preflight {
+        return Some(Reply {
+            status: "204 No Content",
+            headers,
+            body: String::new(),
+        });
+    }
+    let mut reply = match document(app) {
+        Some(document) => Reply {
+            status: "200 OK",
+            headers: Vec::new(),
+            body: serde_json::to_string_pretty(&document).unwrap_or_default(),
+        },
+        None => error("503 Service Unavailable", "服务尚未启动".to_string()),
+    };
+    reply.headers.extend(headers);
+    Some(reply)
+}
+
+fn document(app: &AppHandle) -> Option<Discovery> {
+    let context = app.try_state::<RuntimeManager>()?.context()?;
+    Some(Discovery {
+        webui_url: context.runtime_url.to_string(),
+        api_url: context.common_env.get("CORE_API_URL").cloned(),
+        batch_url: context.common_env.get("GO_SERVICE_URL").cloned(),
+        version: app.package_info().version.to_string(),
+    })
+}
+
+fn error(status: &'static str, message: String) -> Reply {
+    Reply {
+        status,
+        headers: Vec::new(),
+        body: serde_json::json!({ "error": message }).to_string(),
+    }
+}
+
+fn route(request: &str) -> Option<Method> {
+    let mut parts = request.lines().next()?.split_whitespace();
+    let method = parts.next()?;
+    let path = parts.next()?.split('?').next()?;
+    match (method, path) {
+        ("GET", PATH) => Some(Method::Get),
+        ("OPTIONS", PATH) => Some(Method::Preflight),
+        _ => None,
+    }
+}
+
+fn is_disabled(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "0" | "false" | "no" | "off"
+    )
+}
+
+/// 读取请求头（名称不区分大小写），空值视为没有该头。
+fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+    request
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty())
+}
+
+/// 允许写成 `m-team.cc`、`*.m-team.cc` 或 `https://kp.m-team.cc/`。
+fn parse_allowlist(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|entry| {
+            let entry = entry.trim().to_ascii_lowercase();
+            let entry = entry
+                .split_once("://")
+                .map_or(entry.as_str(), |(_, rest)| rest);
+            let entry = entry.split('/').next().unwrap_or_default();
+            entry.trim_start_matches("*.").to_string()
+        })
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+fn origin_allowed(origin: &str, allowlist: &[String]) -> bool {
+    let Ok(url) = tauri::Url::parse(origin) else {
+        return false;
+    };
+    let Some(host) = url.host_str().map(|host| host.to_ascii_lowercase()) else {
+        return false;
+    };
+    matches!(url.scheme(), "http" | "https")
+        && allowlist.iter().any(|domain| {
+            host == *domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+}
+
+fn cors_headers(origin: &str, request: &str) -> Vec<(&'static str, String)> {
+    let mut headers = vec![
+        ("Access-Control-Allow-Origin", origin.to_string()),
+        ("Access-Control-Allow-Methods", "GET, OPTIONS".to_string()),
+        ("Access-Control-Max-Age", "600".to_string()),
+        ("Vary", "Origin".to_string()),
+    ];
+    // Chrome 从公网页面访问本机地址前的预检（Private Network Access）
+    if header(request, "access-control-request-private-network") == Some("true") {
+        headers.push(("Access-Control-Allow-Private-Network", "true".to_string()));
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn origins_match_listed_domains_and_subdomains() {
+        let allowlist = parse_allowlist(" M-Team.cc, *.hdsky.me ,https://pterclub.com/, ");
+        assert_eq!(allowlist, ["m-team.cc", "hdsky.me", "pterclub.com"]);
+        for origin in [
+            "https://m-team.cc",
+            "https://kp.m-team.cc",
+            "https://hdsky.me",
+            "http://pterclub.com:8080",
+        ] {
+            assert!(origin_allowed(origin, &allowlist), "{origin}");
+        }
+        for origin in [
+            "https://evilm-team.cc",
+            "https://m-team.cc.example.com",
+            "null",
+            "chrome-extension://abc",
+        ] {
+            assert!(!origin_allowed(origin, &allowlist), "{origin}");
+        }
+        assert!(!origin_allowed("https://m-team.cc", &parse_allowlist("")));
+    }
+
+    #[test]
+    fn requests_are_routed_and_headers_read() {
+        let preflight = "OPTIONS /discovery HTTP/1.1\r\nOrigin: https://kp.m-team.cc\r\n\
+                         Access-Control-Request-Private-Network: true\r\n\r\n";
+        assert_eq!(route(preflight), Some(Method::Preflight));
+        assert_eq!(
+            route("GET /discovery?x=1 HTTP/1.1\r\n\r\n"),
+            Some(Method::Get)
+        );
+        assert_eq!(route("POST /discovery HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(route("GET /healthz HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(header(preflight, "ORIGIN"), Some("https://kp.m-team.cc"));
+        assert_eq!(
+            header("GET /discovery HTTP/1.1\r\nOrigin: \r\n\r\n", "origin"),
+            None
+        );
+        let headers = cors_headers("https://kp.m-team.cc", preflight);
+        assert!(headers.contains(&("Access-Control-Allow-Private-Network", "true".to_string())));
+        assert!(is_disabled(" OFF ") && !is_disabled("1"));
+    }
+
+    #[test]
+    fn rate_limit_is_per_origin_and_window() {
+        let limiter = RateLimiter::default();
+        let now = Instant::now();
+        for _ in 0..RATE_LIMIT {
+            assert!(limiter.admit("https://a.example", now));
+        }
+        assert!(!limiter.admit("https://a.example", now));
+        assert!(limiter.admit("https://b.example", now));
+        assert!(limiter.admit("https://a.example", now + RATE_WINDOW));
+    }
+}
@@ -0,0 +1,477 @@
+//! 运行期间的磁盘空间监测。
+//!
+//! 启动时的检查帮不了深夜大批量转种途中磁盘被写满的情况：SQLite 开始写入失败，任务半途而废。健康监测
+//! 每轮探测后调用 [`tick`]，每分钟检查一次数据目录和已授权目录（见 pathgrant.rs）所在磁盘的剩余空间，
+//! Unix 上同时检查剩余 inode：
+//!
+//! - 低于警告阈值：发出 `disk-space` 事件和一次系统通知，恢复正常后才会再次提醒；
+//! - 低于临界阈值：调用 batch 的暂停接口暂停正在进行的任务，`shell_status` 的 `disk_space` 常驻显示，
+//!   直到剩余空间回到临界阈值的两倍以上，再调用恢复接口继续任务。
+//!
+//! 阈值与接口路径在 runtime.env 中配置（见 [`WARN_MB_KEY`]、[`PAUSE_PATH_KEY`] 等）。旧版 batch 没有暂停接口
+//! （返回 404/405）或无法连接时记录到 shell.log 后跳过，磁盘告警照常显示；暂停是否生效见
+//! `DiskStatus::batch_pause`。
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::webhook::{self, Trigger};
+use crate::{datadir, health, localhttp, pathgrant, runtime};
+
+pub const WARN_MB_KEY: &str = "PTNEXUS_DISK_WARN_MB";
+pub const CRITICAL_MB_KEY: &str = "PTNEXUS_DISK_CRITICAL_MB";
+pub const WARN_INODES_KEY: &str = "PTNEXUS_DISK_WARN_INODES";
+pub const CRITICAL_INODES_KEY: &str = "PTNEXUS_DISK_CRITICAL_INODES";
+pub const PAUSE_PATH_KEY: &str = "PTNEXUS_BATCH_PAUSE_PATH";
+pub const RESUME_PATH_KEY: &str = "PTNEXUS_BATCH_RESUME_PATH";
+const DEFAULT_WARN_MB: u64 = 2048;
+const DEFAULT_CRITICAL_MB: u64 = 500;
+const DEFAULT_WARN_INODES: u64 = 50_000;
+const DEFAULT_CRITICAL_INODES: u64 = 5_000;
+const DEFAULT_PAUSE_PATH: &str = "/api/jobs/pause";
+const DEFAULT_RESUME_PATH: &str = "/api/jobs/resume";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// 进入临界状态后，剩余量回到临界阈值的这么多倍才恢复任务，避免在阈值附近反复暂停、恢复。
+const RESUME_FACTOR: u64 = 2;
+const BATCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Ok,
+    Warning,
+    Critical,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Thresholds {
+    warn_bytes: u64,
+    critical_bytes: u64,
+    warn_inodes: u64,
+    critical_inodes: u64,
+}
+
+impl Thresholds {
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let number = |key: &str, default: u64| {
+            lookup(key)
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        let critical_bytes = number(CRITICAL_MB_KEY, DEFAULT_CRITICAL_MB) * 1024 * 1024;
+        let critical_inodes = number(CRITICAL_INODES_KEY, DEFAULT_CRITICAL_INODES);
+        Self {
+            // 警告阈值不低于临界阈值
+            warn_bytes: (number(WARN_MB_KEY, DEFAULT_WARN_MB) * 1024 * 1024).max(critical_bytes),
+            critical_bytes,
+            warn_inodes: number(WARN_INODES_KEY, DEFAULT_WARN_INODES).max(critical_inodes),
+            critical_inodes,
+        }
+    }
+}
+
+/// 一个磁盘的采样；取不到的项为 None，不参与判断。
+#[derive(Clone, Debug, Serialize)]
+pub struct Volume {
+    pub path: String,
+    pub free_bytes: Option<u64>,
+    pub free_inodes: Option<u64>,
+    pub level: Level,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DiskStatus {
+    pub level: Level,
+    /// 低于警告阈值的磁盘。
+    pub volumes: Vec<Volume>,
+    /// 临界状态下请求暂停 batch 任务的结果，`paused` 表示暂停确实生效；未到临界时为 None。
+    pub batch_pause: Option<BatchPause>,
+    pub critical_bytes: u64,
+}
+
+#[derive(Default)]
+pub struct DiskWatch(Mutex<Watch>);
+
+#[derive(Default)]
+struct Watch {
+    checked_at: Option<Instant>,
+    level: Option<Level>,
+    flags: Flags,
+    status: Option<DiskStatus>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Flags {
+    /// 本次空间不足已经提醒过。
+    warned: bool,
+    /// 本次临界状态请求暂停的结果；为 `Paused` 时由我们暂停，恢复时需要继续。
+    pause: Option<BatchPause>,
+}
+
+/// 由健康监测线程每轮调用，距上次检查不足一分钟时直接返回。
+pub fn tick(app: &AppHandle) {
+    let Some(watch) = app.try_state::<DiskWatch>() else {
+        return;
+    };
+    // 采样和调用 batch 接口可能要几秒，期间不持有锁，shell_status 读取状态不受影响
+    let (previous, mut flags) = {
+        let Ok(mut watch) = watch.0.lock() else {
+            return;
+        };
+        if watch
+            .checked_at
+            .is_some_and(|checked| checked.elapsed() < CHECK_INTERVAL)
+        {
+            return;
+        }
+        watch.checked_at = Some(Instant::now());
+        (watch.level.unwrap_or(Level::Ok), watch.flags)
+    };
+
+    let thresholds = Thresholds::from_lookup(|key| runtime::read_runtime_setting(app, key));
+    let volumes: Vec<Volume> = watched_paths(app)
+        .into_iter()
+        .map(|path| {
+            let free_bytes = datadir::free_space(&path);
+            let free_inodes = free_inodes(&path);
+            Volume {
+                path: path.to_string_lossy().to_string(),
+                free_bytes,
+                free_inodes,
+                level: assess(previous, free_bytes, free_inodes, &thresholds),
+            }
+        })
+        .collect();
+    let level = volumes
+        .iter()
+        .map(|volume| volume.level)
+        .fold(
+            Level::Ok,
+            |worst, level| if level > worst { level } else { worst },
+        );
+
+    if level != previous {
+        transition(app, &mut flags, previous, level, &volumes);
+    }
+    let Ok(mut watch) = watch.0.lock() else {
+        return;
+    };
+    watch.level = Some(level);
+    watch.flags = flags;
+    watch.status = (level != Level::Ok).then(|| DiskStatus {
+        level,
+        volumes: volumes
+            .into_iter()
+            .filter(|volume| volume.level != Level::Ok)
+            .collect(),
+        batch_pause: flags.pause,
+        critical_bytes: thresholds.critical_bytes,
+    });
+}
+
+/// 当前的磁盘告警；空间充足时为 None。
+pub fn status(app: &AppHandle) -> Option<DiskStatus> {
+    app.try_state::<DiskWatch>()?.0.lock().ok()?.status.clone()
+}
+
+fn transition(
+    app: &AppHandle,
+    flags: &mut Flags,
+    previous: Level,
+    level: Level,
+    volumes: &[Volume],
+) {
+    let summary = describe(volumes.iter().filter(|volume| volume.level == level));
+    events::emit(
+        app,
+        "disk-space",
+        Replay::Latest,
+        serde_json::json!({ "level": level, "summary": summary }),
+    );
+    match level {
+        Level::Ok => {
+            flags.warned = false;
+            journal::record(app, Severity::Info, None, "磁盘空间已恢复");
+        }
+        Level::Warning if previous == Level::Ok && !flags.warned => {
+            flags.warned = true;
+            journal::record(
+                app,
+                Severity::Warn,
+                None,
+                format!("磁盘空间不足：{summary}"),
+            );
+            notify(
+                app,
+                "PT Nexus 磁盘空间不足",
+                &format!("{summary}。请尽快清理，空间耗尽时会暂停批量任务。"),
+            );
+        }
+        Level::Warning => {}
+        Level::Critical => {
+            flags.warned = true;
+            let message = format!("磁盘空间即将耗尽：{summary}");
+            journal::record(app, Severity::Error, None, message.clone());
+            webhook::notify(app, Trigger::Failure, message);
+            let pause = call_batch(app, PAUSE_PATH_KEY, DEFAULT_PAUSE_PATH, "暂停");
+            flags.pause = Some(pause);
+            let advice = if pause == BatchPause::Paused {
+                "已暂停批量任务，释放空间后自动继续。"
+            } else {
+                "请立即清理磁盘，否则数据库写入可能失败。"
+            };
+            notify(
+                app,
+                "PT Nexus 磁盘空间即将耗尽",
+                &format!("{summary}。{advice}"),
+            );
+        }
+    }
+    if previous == Level::Critical && level != Level::Critical {
+        if flags.pause == Some(BatchPause::Paused) {
+            call_batch(app, RESUME_PATH_KEY, DEFAULT_RESUME_PATH, "恢复");
+        }
+        flags.pause = None;
+    }
+}
+
+fn notify(app: &AppHandle, title: &str, body: &str) {
+    crate::power::wake(app);
+    let _ = app.notification().builder().title(title).body(body).show();
+}
+
+/// 调用 batch 的暂停/恢复接口的结果。
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchPause {
+    /// 接口返回 2xx，任务已暂停。
+    Paused,
+    /// batch 未运行、无法连接，或是没有该接口的旧版本（404/405）；只记录到 shell.log。
+    Unsupported,
+    /// 接口存在但返回了其他错误。
+    Failed,
+}
+
+/// 调用 batch 的暂停/恢复接口；接口不存在或无法连接时记录后跳过，磁盘告警照常显示。
+fn call_batch(app: &AppHandle, key: &str, default_path: &str, action: &str) -> BatchPause {
+    let path = runtime::read_runtime_setting(app, key)
+        .map(|value| value.trim().to_string())
+        .filter(|value| value.starts_with('/'))
+        .unwrap_or_else(|| default_path.to_string());
+    let Some(port) = health::service_ports(app)
+        .into_iter()
+        .find(|(name, _)| *name == "batch")
+        .map(|(_, port)| port)
+    else {
+        runtime::shell_log(app, &format!("[INFO] batch 未运行，跳过{action}任务"));
+        return BatchPause::Unsupported;
+    };
+    let body = serde_json::json!({ "reason": "disk_space" }).to_string();
+    let result = localhttp::post_json("127.0.0.1", port, &path, &body, BATCH_TIMEOUT)
+        .map(|response| (response.status, response.status_line));
+    let outcome = classify(&result);
+    let detail = match &result {
+        Ok((_, status_line)) => status_line.as_str(),
+        Err(err) => err.as_str(),
+    };
+    let line = match outcome {
+        BatchPause::Paused => format!("[INFO] 已{action} batch 任务（磁盘空间）"),
+        BatchPause::Unsupported => {
+            format!("[INFO] batch 不支持或无法连接 {path}（{detail}），跳过{action}任务")
+        }
+        BatchPause::Failed => format!("[WARN] {action} batch 任务失败: {detail}"),
+    };
+    runtime::shell_log(app, &line);
+    outcome
+}
+
+/// 404/405（旧版 batch 没有该接口）与连接失败都按不支持处理。
+fn classify(result: &Result<(u16, String), String>) -> BatchPause {
+    match result {
+        Ok((status, _)) if status / 100 == 2 => BatchPause::Paused,
+        Ok((404 | 405, _)) | Err(_) => BatchPause::Unsupported,
+        Ok(_) => BatchPause::Failed,
+    }
+}
+
+/// 数据目录与已授权的目录（不存在的跳过）。
+fn watched_paths(app: &AppHandle) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = datadir::active_profile(app)
+        .map(|profile| profile.root)
+        .into_iter()
+        .collect();
+    for grant in pathgrant::list(app) {
+        if grant.path.exists() && !paths.contains(&grant.path) {
+            paths.push(grant.path);
+        }
+    }
+    paths
+}
+
+/// 一个磁盘的级别；已处于临界状态时，剩余量回到临界阈值的 [`RESUME_FACTOR`] 倍以上才解除。
+fn assess(
+    previous: Level,
+    free_bytes: Option<u64>,
+    free_inodes: Option<u64>,
+    thresholds: &Thresholds,
+) -> Level {
+    let level = |free: Option<u64>, warn: u64, critical: u64| match free {
+        None => Level::Ok,
+        Some(free) if free < critical => Level::Critical,
+        Some(free)
+            if previous == Level::Critical && free < critical.saturating_mul(RESUME_FACTOR) =>
+        {
+            Level::Critical
+        }
+        Some(free) if free < warn => Level::Warning,
+        Some(_) => Level::Ok,
+    };
+    let bytes = level(free_bytes, thresholds.warn_bytes, thresholds.critical_bytes);
+    let inodes = level(
+        free_inodes,
+        thresholds.warn_inodes,
+        thresholds.critical_inodes,
+    );
+    if inodes > bytes {
+        inodes
+    } else {
+        bytes
+    }
+}
+
+fn describe<'a>(volumes: impl Iterator<Item = &'a Volume>) -> String {
+    volumes
+        .map(|volume| {
+            let mut parts = Vec::new();
+            if let Some(bytes) = volume.free_bytes {
+                parts.push(format!("剩余 {} MB", bytes / 1024 / 1024));
+            }
+            if let Some(inodes) = volume.free_inodes {
+                parts.push(format!("剩余 inode {inodes}"));
+            }
+            format!("{}（{}）", volume.path, parts.join("，"))
+        })
+        .collect::<Vec<_>>()
+        .join("；")
+}
+
+/// `path` 所在文件系统剩余的 inode 数，取自 `df -Pi`；没有 inode 限制的文件系统返回 None。
+#[cfg(not(target_os = "windows"))]
+fn free_inodes(path: &std::path::Path) -> Option<u64> {
+    let output = std::process::Command::new("df")
+        .arg("-Pi")
+        .arg(path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    parse_df_inodes(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(target_os = "windows")]
+fn free_inodes(_path: &std::path::Path) -> Option<u64> {
+    None
+}
+
+/// `df -Pi` 的第二行：第 2 列为 inode 总数（btrfs 等为 0，表示不限制），第 4 列为剩余数。
+#[cfg(not(target_os = "windows"))]
+fn parse_df_inodes(output: &str) -> Option<u64> {
+    let mut columns = output.lines().nth(1)?.split_whitespace().skip(1);
+    let total: u64 = columns.next()?.parse().ok()?;
+    let free: u64 = columns.nth(1)?.parse().ok()?;
+    (total > 0).then_some(free)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn thresholds() -> Thresholds {
+        Thresholds::from_lookup(|key| match key {
+            WARN_MB_KEY => Some("1000".to_string()),
+            CRITICAL_MB_KEY => Some("100".to_string()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn levels_follow_thresholds_with_hysteresis_on_recovery() {
+        let t = thresholds();
+        assert_eq!(assess(Level::Ok, Some(2000 * MB), None, &t), Level::Ok);
+        assert_eq!(assess(Level::Ok, Some(500 * MB), None, &t), Level::Warning);
+        assert_eq!(
+            assess(Level::Warning, Some(50 * MB), None, &t),
+            Level::Critical
+        );
+        // 临界状态下刚好回到阈值以上不算恢复
+        assert_eq!(
+            assess(Level::Critical, Some(150 * MB), None, &t),
+            Level::Critical
+        );
+        assert_eq!(
+            assess(Level::Critical, Some(250 * MB), None, &t),
+            Level::Warning
+        );
+        assert_eq!(assess(Level::Critical, None, None, &t), Level::Ok);
+        assert_eq!(
+            assess(Level::Ok, Some(2000 * MB), Some(1_000), &t),
+            Level::Critical
+        );
+        assert_eq!(
+            assess(Level::Ok, Some(2000 * MB), Some(20_000), &t),
+            Level::Warning
+        );
+    }
+
+    #[test]
+    fn thresholds_are_read_and_kept_ordered() {
+        let t = Thresholds::from_lookup(|key| match key {
+            WARN_MB_KEY => Some("10".to_string()),
+            CRITICAL_MB_KEY => Some(" 200 ".to_string()),
+            CRITICAL_INODES_KEY => Some("oops".to_string()),
+            _ => None,
+        });
+        assert_eq!(t.critical_bytes, 200 * MB);
+        assert_eq!(t.warn_bytes, 200 * MB);
+        assert_eq!(t.critical_inodes, DEFAULT_CRITICAL_INODES);
+        assert_eq!(t.warn_inodes, DEFAULT_WARN_INODES);
+        assert_eq!(
+            Thresholds::from_lookup(|_| None).critical_bytes,
+            DEFAULT_CRITICAL_MB * MB
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn df_inode_output_is_parsed() {
+        let ext4 = "Filesystem Inodes IUsed IFree IUse% Mounted on\n/dev/sda1 655360 120000 535360 19% /\n";
+        assert_eq!(parse_df_inodes(ext4), Some(535_360));
+        let btrfs = "Filesystem Inodes IUsed IFree IUse% Mounted on\n/dev/sdb1 0 0 0 - /data\n";
+        assert_eq!(parse_df_inodes(btrfs), None);
+        assert_eq!(parse_df_inodes(""), None);
+    }
+
+    #[test]
+    fn missing_or_unreachable_batch_api_is_unsupported() {
+        assert_eq!(
+            classify(&Ok((200, "HTTP/1.1 200 OK".into()))),
+            BatchPause::Paused
+        );
+        assert_eq!(classify(&Ok((204, String::new()))), BatchPause::Paused);
+        assert_eq!(classify(&Ok((404, String::new()))), BatchPause::Unsupported);
+        assert_eq!(classify(&Ok((405, String::new()))), BatchPause::Unsupported);
+        assert_eq!(
+            classify(&Err("connection refused".into())),
+            BatchPause::Unsupported
+        );
+        assert_eq!(classify(&Ok((500, String::new()))), BatchPause::Failed);
+    }
+}
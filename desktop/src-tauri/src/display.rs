@@ -0,0 +1,87 @@
+//! 多显示器缩放。
+//!
+//! Windows 上 tao 在创建窗口前已把进程设为 Per-Monitor V2 DPI 感知，WebView2 会跟随所在显示器的
+//! DPI 自动换算，所以这里的缩放系数是相对系统 DPI 的“界面缩放”，跨屏后按目标显示器的偏好重新应用即可，
+//! 不需要再乘以 DPI 比例，否则会被放大两次。
+//! 偏好保存在桌面设置中：`ui_scale` 为全局默认，`monitor_scales` 按显示器名称单独覆盖。
+
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::settings;
+
+pub const MIN_UI_SCALE: f64 = 0.5;
+pub const MAX_UI_SCALE: f64 = 3.0;
+
+/// 记录主窗口上一次所在的显示器，移动窗口时只在跨屏后重新应用缩放。
+#[derive(Default)]
+pub struct DisplayState {
+    last_monitor: Mutex<Option<String>>,
+}
+
+pub fn is_valid_scale(factor: f64) -> bool {
+    factor.is_finite() && (MIN_UI_SCALE..=MAX_UI_SCALE).contains(&factor)
+}
+
+/// 设置界面缩放；`per_monitor` 为 true 时只对主窗口当前所在的显示器生效。
+pub fn set_ui_scale(app: &AppHandle, factor: f64, per_monitor: bool) -> Result<(), String> {
+    if !is_valid_scale(factor) {
+        return Err(format!(
+            "缩放比例需在 {MIN_UI_SCALE} 到 {MAX_UI_SCALE} 之间，当前为 {factor}"
+        ));
+    }
+
+    let window = app.get_webview_window("main");
+    let monitor = window.as_ref().and_then(monitor_name);
+    settings::update(app, |settings| match (per_monitor, &monitor) {
+        (true, Some(name)) => {
+            settings.monitor_scales.insert(name.clone(), factor);
+        }
+        _ => settings.ui_scale = factor,
+    })?;
+    if per_monitor && monitor.is_none() {
+        return Err("无法识别当前显示器，已改为全局缩放".to_string());
+    }
+
+    if let Some(window) = window {
+        apply(&window);
+    }
+    Ok(())
+}
+
+/// 按主窗口当前所在显示器应用缩放。
+pub fn apply(window: &WebviewWindow) {
+    let monitor = monitor_name(window);
+    if let Some(state) = window.try_state::<DisplayState>() {
+        if let Ok(mut last) = state.last_monitor.lock() {
+            *last = monitor.clone();
+        }
+    }
+
+    let current = settings::current(window.app_handle());
+    let factor = monitor
+        .and_then(|name| current.monitor_scales.get(&name).copied())
+        .unwrap_or(current.ui_scale);
+    let _ = window.set_zoom(factor);
+}
+
+/// DPI 变化或窗口移动后调用；只有换了显示器才重新应用。
+pub fn on_window_moved(window: &WebviewWindow, dpi_changed: bool) {
+    let monitor = monitor_name(window);
+    let changed = window
+        .try_state::<DisplayState>()
+        .and_then(|state| state.last_monitor.lock().ok().map(|last| *last != monitor))
+        .unwrap_or(true);
+    if dpi_changed || changed {
+        apply(window);
+    }
+}
+
+fn monitor_name(window: &WebviewWindow) -> Option<String> {
+    window
+        .current_monitor()
+        .ok()
+        .flatten()
+        .and_then(|monitor| monitor.name().cloned())
+}
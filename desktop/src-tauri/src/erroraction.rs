@@ -0,0 +1,172 @@
+//! 错误提示上的快捷操作。
+//!
+//! 启动失败、服务掉线等错误随附可以直接执行的操作，启动页、诊断页、WebUI 与原生对话框都渲染为按钮，
+//! 点击后调用 `invoke_error_action(id, context)`，不必再照着提示中的路径手动查找日志。
+//! 序列化为 `{"id": "open_log", "label": "查看 server 日志", "context": "server"}`，`id` 是稳定的接口：
+//!
+//! - `open_log`：用文本编辑器打开服务的 stderr 日志，`context` 为服务名
+//! - `open_logs_dir`：打开日志目录
+//! - `retry`：重新执行启动流程
+//! - `copy_details`：把错误详情复制到剪贴板，`context` 为详情文本
+//! - `remove_quarantine`：确认后清除运行文件的系统隔离标记并重试（见 quarantine.rs）
+//! - `verify_runtime`：执行启动自检并显示结果
+//! - `open_data_dir`：打开当前配置档的数据目录，检查权限
+//! - `reinstall_python`：下次启动时重新解压内置 Python 运行时并重试（见 pyfailure.rs）
+
+use serde::{Deserialize, Serialize};
+
+use crate::journal::Severity;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(into = "ActionPayload", try_from = "ActionPayload")]
+pub enum ErrorAction {
+    OpenLog(String),
+    OpenLogsDir,
+    Retry,
+    CopyDetails(String),
+    RemoveQuarantine,
+    VerifyRuntime,
+    OpenDataDir,
+    ReinstallPython,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ActionPayload {
+    id: String,
+    #[serde(default)]
+    label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    context: Option<String>,
+}
+
+impl ErrorAction {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::OpenLog(_) => "open_log",
+            Self::OpenLogsDir => "open_logs_dir",
+            Self::Retry => "retry",
+            Self::CopyDetails(_) => "copy_details",
+            Self::RemoveQuarantine => "remove_quarantine",
+            Self::VerifyRuntime => "verify_runtime",
+            Self::OpenDataDir => "open_data_dir",
+            Self::ReinstallPython => "reinstall_python",
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::OpenLog(service) => format!("查看 {service} 日志"),
+            Self::OpenLogsDir => "打开日志目录".to_string(),
+            Self::Retry => "重试".to_string(),
+            Self::CopyDetails(_) => "复制错误详情".to_string(),
+            Self::RemoveQuarantine => "解除系统拦截".to_string(),
+            Self::VerifyRuntime => "检查运行文件".to_string(),
+            Self::OpenDataDir => "打开数据目录".to_string(),
+            Self::ReinstallPython => "重新安装 Python 运行时".to_string(),
+        }
+    }
+
+    fn context(&self) -> Option<&str> {
+        match self {
+            Self::OpenLog(value) | Self::CopyDetails(value) => Some(value),
+            Self::OpenLogsDir
+            | Self::Retry
+            | Self::RemoveQuarantine
+            | Self::VerifyRuntime
+            | Self::OpenDataDir
+            | Self::ReinstallPython => None,
+        }
+    }
+
+    /// 按 `invoke_error_action` 收到的参数还原操作。
+    pub fn parse(id: &str, context: Option<String>) -> Result<Self, String> {
+        let context = context.filter(|value| !value.trim().is_empty());
+        let required =
+            |context: Option<String>| context.ok_or_else(|| format!("操作 {id} 缺少参数"));
+        match id {
+            "open_log" => Ok(Self::OpenLog(required(context)?)),
+            "open_logs_dir" => Ok(Self::OpenLogsDir),
+            "retry" => Ok(Self::Retry),
+            "copy_details" => Ok(Self::CopyDetails(required(context)?)),
+            "remove_quarantine" => Ok(Self::RemoveQuarantine),
+            "verify_runtime" => Ok(Self::VerifyRuntime),
+            "open_data_dir" => Ok(Self::OpenDataDir),
+            "reinstall_python" => Ok(Self::ReinstallPython),
+            _ => Err(format!("未知的操作: {id}")),
+        }
+    }
+}
+
+impl From<ErrorAction> for ActionPayload {
+    fn from(action: ErrorAction) -> Self {
+        Self {
+            id: action.id().to_string(),
+            label: action.label(),
+            context: action.context().map(str::to_string),
+        }
+    }
+}
+
+impl TryFrom<ActionPayload> for ErrorAction {
+    type Error = String;
+
+    fn try_from(payload: ActionPayload) -> Result<Self, String> {
+        Self::parse(&payload.id, payload.context)
+    }
+}
+
+/// 运行记录中的错误事件附带的操作：涉及某个服务时打开它的日志，否则打开日志目录。
+pub fn for_event(severity: Severity, service: Option<&str>) -> Vec<ErrorAction> {
+    match (severity, service) {
+        (Severity::Error, Some(service)) => vec![ErrorAction::OpenLog(service.to_string())],
+        (Severity::Error, None) => vec![ErrorAction::OpenLogsDir],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模块文档中列出的全部操作 id，新增操作时同步添加。
+    const ACTION_IDS: &[&str] = &[
+        "open_log",
+        "open_logs_dir",
+        "retry",
+        "copy_details",
+        "remove_quarantine",
+        "verify_runtime",
+        "open_data_dir",
+        "reinstall_python",
+    ];
+
+    #[test]
+    fn every_declared_id_parses_and_round_trips() {
+        for id in ACTION_IDS {
+            let action = ErrorAction::parse(id, Some("server".to_string())).unwrap();
+            assert_eq!(action.id(), *id);
+            let json = serde_json::to_value(&action).unwrap();
+            assert_eq!(json["id"], *id);
+            assert!(!json["label"].as_str().unwrap().is_empty());
+            assert_eq!(serde_json::from_value::<ErrorAction>(json).unwrap(), action);
+        }
+        assert!(ErrorAction::parse("delete_logs", None)
+            .unwrap_err()
+            .contains("delete_logs"));
+        assert!(ErrorAction::parse("open_log", None).is_err());
+        assert!(ErrorAction::parse("copy_details", Some(" ".to_string())).is_err());
+    }
+
+    #[test]
+    fn error_events_carry_log_actions() {
+        assert_eq!(
+            for_event(Severity::Error, Some("batch")),
+            vec![ErrorAction::OpenLog("batch".to_string())]
+        );
+        assert_eq!(
+            for_event(Severity::Error, None),
+            vec![ErrorAction::OpenLogsDir]
+        );
+        assert!(for_event(Severity::Info, Some("batch")).is_empty());
+    }
+}
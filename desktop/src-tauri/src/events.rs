@@ -0,0 +1,212 @@
+//! 桌面壳发给界面的事件：统一编号，状态类事件可以补发。
+//!
+//! 界面重新加载（强制刷新、渲染进程崩溃后恢复）后，监听注册之前发出的事件都会错过，例如启动时只发一次的
+//! `runtime-ready`，横幅便一直停在“服务启动中”。所有广播事件都经 `emit` 发出并带上递增的序号 `seq`：
+//! 对象类型的 payload 增加 `seq` 字段（原有字段不变），其他类型包装为 `{"value": …, "seq": …}`。
+//!
+//! 事件按补发方式保留在内存中：`Replay::All` 的每一条都保留，`Replay::Latest` 只保留同名事件的最后一条
+//! （启动阶段等只关心最新状态的事件），`Replay::Never` 不保留（一次性的确认请求等）。界面注册监听后调用
+//! `sync_runtime_events(last_seen_seq)`，按序号顺序取回错过的事件；较早的事件因容量上限被丢弃时
+//! `truncated` 为 true，界面应重新读取完整状态（如 `shell_status`）。
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 最多保留的事件条数。
+const CAPACITY: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Replay {
+    All,
+    Latest,
+    Never,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ShellEvent {
+    pub seq: u64,
+    pub event: String,
+    /// 与实时事件相同的 payload（已带 `seq`）。
+    pub payload: Value,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct EventSync {
+    /// `last_seen_seq` 之后仍保留的事件，按序号排列。
+    pub events: Vec<ShellEvent>,
+    /// 目前为止最大的序号，界面下次同步时传回。
+    pub latest_seq: u64,
+    /// 有错过的事件已被丢弃，或 `last_seen_seq` 来自之前运行的应用。
+    pub truncated: bool,
+}
+
+#[derive(Default)]
+pub struct EventLog {
+    inner: Mutex<Retained>,
+}
+
+#[derive(Default)]
+struct Retained {
+    latest_seq: u64,
+    events: VecDeque<ShellEvent>,
+    /// 因容量上限丢弃的最大序号。
+    dropped_up_to: u64,
+}
+
+impl EventLog {
+    /// 分配序号并按补发方式保留，返回带 `seq` 的 payload。
+    fn push(&self, event: &str, replay: Replay, payload: Value) -> Value {
+        let Ok(mut retained) = self.inner.lock() else {
+            return payload;
+        };
+        retained.latest_seq += 1;
+        let seq = retained.latest_seq;
+        let payload = with_seq(payload, seq);
+        match replay {
+            Replay::Never => return payload,
+            Replay::Latest => retained.events.retain(|existing| existing.event != event),
+            Replay::All => {}
+        }
+        if retained.events.len() >= CAPACITY {
+            if let Some(dropped) = retained.events.pop_front() {
+                retained.dropped_up_to = dropped.seq;
+            }
+        }
+        retained.events.push_back(ShellEvent {
+            seq,
+            event: event.to_string(),
+            payload: payload.clone(),
+        });
+        payload
+    }
+
+    fn since(&self, last_seen_seq: u64) -> EventSync {
+        let Ok(retained) = self.inner.lock() else {
+            return EventSync {
+                events: Vec::new(),
+                latest_seq: 0,
+                truncated: true,
+            };
+        };
+        // 序号比当前还大，说明界面记录的是重启前的应用，全部重新补发
+        let restarted = last_seen_seq > retained.latest_seq;
+        let after = if restarted { 0 } else { last_seen_seq };
+        EventSync {
+            events: retained
+                .events
+                .iter()
+                .filter(|event| event.seq > after)
+                .cloned()
+                .collect(),
+            latest_seq: retained.latest_seq,
+            truncated: restarted || after < retained.dropped_up_to,
+        }
+    }
+}
+
+/// 带序号广播事件；EventLog 尚未初始化时照常发出，只是没有序号。
+pub fn emit(app: &AppHandle, event: &str, replay: Replay, payload: impl Serialize) {
+    let payload = serde_json::to_value(payload).unwrap_or(Value::Null);
+    let payload = match app.try_state::<EventLog>() {
+        Some(log) => log.push(event, replay, payload),
+        None => payload,
+    };
+    let _ = app.emit(event, payload);
+}
+
+pub fn sync(app: &AppHandle, last_seen_seq: u64) -> EventSync {
+    match app.try_state::<EventLog>() {
+        Some(log) => log.since(last_seen_seq),
+        None => EventLog::default().since(last_seen_seq),
+    }
+}
+
+fn with_seq(payload: Value, seq: u64) -> Value {
+    match payload {
+        Value::Object(mut object) => {
+            object.insert("seq".to_string(), Value::from(seq));
+            Value::Object(object)
+        }
+        value => serde_json::json!({ "value": value, "seq": seq }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn seqs(sync: &EventSync) -> Vec<u64> {
+        sync.events.iter().map(|event| event.seq).collect()
+    }
+
+    #[test]
+    fn payloads_carry_increasing_sequence_numbers() {
+        let log = EventLog::default();
+        let stage = log.push(
+            "bootstrap-stage",
+            Replay::Latest,
+            json!({ "stage": "server" }),
+        );
+        assert_eq!(stage, json!({ "stage": "server", "seq": 1 }));
+        assert_eq!(
+            log.push("runtime-ready", Replay::Latest, json!(true)),
+            json!({ "value": true, "seq": 2 })
+        );
+    }
+
+    #[test]
+    fn missed_events_are_replayed_in_order_and_coalesced() {
+        let log = EventLog::default();
+        log.push(
+            "bootstrap-stage",
+            Replay::Latest,
+            json!({ "stage": "server" }),
+        );
+        log.push("runtime-event", Replay::All, json!({ "message": "a" }));
+        log.push("relaunch-requested", Replay::Never, json!({ "token": "t" }));
+        log.push(
+            "bootstrap-stage",
+            Replay::Latest,
+            json!({ "stage": "batch" }),
+        );
+        log.push("runtime-ready", Replay::Latest, json!(true));
+
+        // 不保留的事件与被合并的旧阶段留下的序号空缺不算丢失
+        let all = log.since(0);
+        assert_eq!(seqs(&all), vec![2, 4, 5]);
+        assert_eq!(all.events[1].payload["stage"], "batch");
+        assert_eq!(all.latest_seq, 5);
+        assert!(!all.truncated);
+
+        let tail = log.since(4);
+        assert_eq!(seqs(&tail), vec![5]);
+        assert_eq!(tail.events[0].event, "runtime-ready");
+        assert!(log.since(5).events.is_empty());
+    }
+
+    #[test]
+    fn dropped_events_and_stale_sequence_numbers_are_reported() {
+        let log = EventLog::default();
+        for i in 0..(CAPACITY + 3) {
+            log.push("runtime-event", Replay::All, json!({ "message": i }));
+        }
+        let latest = (CAPACITY + 3) as u64;
+
+        let behind = log.since(1);
+        assert!(behind.truncated);
+        assert_eq!(behind.events.len(), CAPACITY);
+        assert_eq!(behind.events[0].seq, 4);
+        // 丢弃的最后一条之后开始看的界面没有错过任何事件
+        assert!(!log.since(3).truncated);
+
+        let restarted = log.since(latest + 10);
+        assert!(restarted.truncated);
+        assert_eq!(restarted.events.len(), CAPACITY);
+        assert_eq!(restarted.latest_seq, latest);
+    }
+}
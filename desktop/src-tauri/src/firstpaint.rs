@@ -0,0 +1,90 @@
+//! 主窗口在 WebUI 首次渲染后再显示。
+//!
+//! 主窗口以隐藏状态创建（tauri.conf.json 中 `visible: false`），启动与导航期间都不显示，避免从打包的
+//! 启动页切换到运行时页面时闪一下。初始化脚本在每个页面上判断登录页或主界面是否已渲染（与启动遮罩
+//! 共用同一套判断），满足时调用 `webui_first_paint`，桌面壳随即显示并聚焦主窗口。
+//! 判断失误时不能让应用一直不可见：创建窗口 10 秒后无论是否收到通知都显示窗口。
+//! 只显示一次；之后的导航（强制刷新、崩溃恢复）不会把隐藏到托盘的窗口重新弹出来。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::runtime;
+use crate::tasks::{self, Scope};
+
+const SHOW_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 作为初始化脚本注入主窗口。`window.__PTNEXUS_APP_READY__` 同时供启动遮罩使用。
+pub const FIRST_PAINT_JS: &str = r#"
+(function() {
+  if (window.__PTNEXUS_APP_READY__) return;
+
+  function appReady() {
+    var app = document.getElementById('app');
+    if (!app) return false;
+    // 登录页已渲染
+    if (app.querySelector('.login-page, .login-card')) return true;
+    // 主应用页已渲染
+    if (app.querySelector('.app-container, .layout-container, .main-container, .home-container, .main-nav')) return true;
+    return false;
+  }
+  window.__PTNEXUS_APP_READY__ = appReady;
+
+  // 打包的启动页没有这些元素，轮询一段时间后停止
+  var ticks = 0;
+  var timer = setInterval(function() {
+    if (++ticks > 300) clearInterval(timer);
+    if (!appReady()) return;
+    clearInterval(timer);
+    try {
+      var pending = window.__TAURI_INTERNALS__.invoke('webui_first_paint', {});
+      if (pending && typeof pending.catch === 'function') pending.catch(function() {});
+    } catch (e) {}
+  }, 100);
+})();
+"#;
+
+#[derive(Default)]
+pub struct FirstPaint {
+    shown: AtomicBool,
+}
+
+/// 创建主窗口后调用，开始显示窗口的兜底计时。
+pub fn arm(app: &AppHandle) {
+    app.manage(FirstPaint::default());
+    tasks::spawn(app, "first-paint", Scope::Shell, move |app, token| {
+        if token.sleep(SHOW_TIMEOUT) && reveal(&app) {
+            runtime::shell_log(
+                &app,
+                &format!(
+                    "[WARN] {} 秒内未检测到 WebUI 首次渲染，直接显示主窗口",
+                    SHOW_TIMEOUT.as_secs()
+                ),
+            );
+        }
+    });
+}
+
+/// 显示并聚焦启动时隐藏的主窗口；已显示过时不做任何事，返回是否由本次调用显示。
+pub fn reveal(app: &AppHandle) -> bool {
+    if !take(app) {
+        return false;
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    true
+}
+
+/// 窗口已通过其他途径显示（托盘、启动失败提示等），之后不再自动显示。
+pub fn mark_shown(app: &AppHandle) {
+    take(app);
+}
+
+fn take(app: &AppHandle) -> bool {
+    app.try_state::<FirstPaint>()
+        .is_some_and(|state| !state.shown.swap(true, Ordering::SeqCst))
+}
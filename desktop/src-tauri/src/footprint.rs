@@ -0,0 +1,434 @@
+//! 运行目录占用分析：列出各组件占用的空间，找出可以删除的可选组件。
+//!
+//! `analyze_runtime_footprint` 统计运行目录中 server、Python 运行时、BDInfo、WebUI 静态文件（dist）、
+//! batch、updater 的大小（Python 运行时包括解压到数据目录的部分），并结合 runtime.env 判断可选组件
+//! 是否在使用，给出停用方式。目前只有打包的 BDInfo 是可选的：updater 提供 WebUI 与反向代理，
+//! 其余组件都是启动所必需的。`remove_optional_component` 弹窗确认后删除可选组件并记入
+//! 应用数据目录的 `removed-components.json`，启动自检据此把缺失视为主动删除而不是安装损坏；
+//! 必需组件一律拒绝删除。应用更新重新带回该组件后，删除记录随之失效。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::journal::{self, Severity};
+use crate::{bdinfo, datadir, fsutil, paths, runtime};
+
+const RECORD_FILE: &str = "removed-components.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Component {
+    Server,
+    Python,
+    Bdinfo,
+    Dist,
+    Batch,
+    Updater,
+}
+
+const COMPONENTS: [Component; 6] = [
+    Component::Server,
+    Component::Python,
+    Component::Bdinfo,
+    Component::Dist,
+    Component::Batch,
+    Component::Updater,
+];
+
+impl Component {
+    fn name(self) -> &'static str {
+        match self {
+            Component::Server => "server",
+            Component::Python => "python",
+            Component::Bdinfo => "bdinfo",
+            Component::Dist => "dist",
+            Component::Batch => "batch",
+            Component::Updater => "updater",
+        }
+    }
+
+    fn parse(name: &str) -> Result<Self, String> {
+        COMPONENTS
+            .into_iter()
+            .find(|component| component.name() == name)
+            .ok_or_else(|| {
+                let known: Vec<&str> = COMPONENTS.iter().map(|c| c.name()).collect();
+                format!("未知的组件 {name}，可选: {}", known.join(", "))
+            })
+    }
+
+    fn optional(self) -> bool {
+        self == Component::Bdinfo
+    }
+
+    /// 组件占用的路径；server 目录中单独统计的子目录由 `measure` 扣除。
+    fn paths(self, runtime_root: &Path, data_dir: &Path) -> Vec<PathBuf> {
+        let server_dir = runtime_root.join("server");
+        match self {
+            Component::Server => vec![server_dir],
+            Component::Python => vec![
+                server_dir.join("python"),
+                runtime_root.join("python.zip"),
+                runtime_root.join("python.zip.sha256"),
+                data_dir.join("python-runtime"),
+            ],
+            Component::Bdinfo => vec![server_dir.join("bdinfo")],
+            Component::Dist => vec![server_dir.join("dist")],
+            Component::Batch => vec![runtime_root.join("batch")],
+            Component::Updater => vec![runtime_root.join("updater")],
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ComponentUsage {
+    pub component: Component,
+    /// 实际存在的路径。
+    pub paths: Vec<String>,
+    pub bytes: u64,
+    pub required: bool,
+    /// 按当前配置是否会被用到。
+    pub in_use: bool,
+    /// 已通过 `remove_optional_component` 删除。
+    pub removed: bool,
+    /// 用途及停用方式。
+    pub note: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FootprintReport {
+    pub runtime_root: String,
+    /// 运行目录加上解压到数据目录的 Python 运行时。
+    pub total_bytes: u64,
+    /// 按占用从大到小排列。
+    pub components: Vec<ComponentUsage>,
+    /// 可以删除的组件，未在使用的排在前面。
+    pub candidates: Vec<Component>,
+    pub reclaimable_bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct RemovedComponent {
+    name: String,
+    path: String,
+    bytes: u64,
+    removed_at: String,
+}
+
+pub fn analyze(app: &AppHandle) -> Result<FootprintReport, String> {
+    let runtime_root = runtime::resolve_runtime_root(app)?;
+    let profile = datadir::active_profile(app)?;
+    let bdinfo_override =
+        runtime::read_runtime_setting(app, bdinfo::BDINFO_PATH_KEY).map(PathBuf::from);
+    let removed = read_records(&record_path(app)?);
+    Ok(measure(
+        &runtime_root,
+        &profile.root,
+        bdinfo_override.as_deref(),
+        &removed,
+    ))
+}
+
+/// 确认后删除可选组件，返回是否已删除（用户取消时为 false）。
+pub fn remove_optional(app: &AppHandle, name: &str) -> Result<bool, String> {
+    let component = Component::parse(name)?;
+    if !component.optional() {
+        return Err(format!("{name} 是运行所必需的组件，不能删除"));
+    }
+    let runtime_root = runtime::resolve_runtime_root(app)?;
+    let profile = datadir::active_profile(app)?;
+    let targets: Vec<(PathBuf, u64)> = component
+        .paths(&runtime_root, &profile.root)
+        .into_iter()
+        .filter(|path| paths::extended(path).exists())
+        .map(|path| {
+            let bytes = datadir::dir_size(&paths::extended(&path));
+            (path, bytes)
+        })
+        .collect();
+    if targets.is_empty() {
+        return Err(format!("{name} 不存在，无需删除"));
+    }
+    let bytes: u64 = targets.iter().map(|(_, bytes)| bytes).sum();
+    let listed: Vec<String> = targets
+        .iter()
+        .map(|(path, _)| path.display().to_string())
+        .collect();
+
+    let confirmed = app
+        .dialog()
+        .message(format!(
+            "将删除以下目录（{:.1} MB）：\n\n{}\n\n{}\n\n删除后只能通过重新安装应用恢复。",
+            mb(bytes),
+            listed.join("\n"),
+            removal_effect(component)
+        ))
+        .title("PT Nexus 删除可选组件")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "删除".to_string(),
+            "取消".to_string(),
+        ))
+        .blocking_show();
+    if !confirmed {
+        return Ok(false);
+    }
+
+    for (target, _) in &targets {
+        fs::remove_dir_all(paths::extended(target)).map_err(|e| {
+            format!(
+                "删除 {} 失败（可能有文件正在使用，可停止服务后重试）: {e}",
+                target.display()
+            )
+        })?;
+    }
+    let record_file = record_path(app)?;
+    let mut records = read_records(&record_file);
+    records.retain(|record| record.name != name);
+    records.extend(targets.iter().map(|(target, bytes)| RemovedComponent {
+        name: name.to_string(),
+        path: target.to_string_lossy().to_string(),
+        bytes: *bytes,
+        removed_at: runtime::format_utc_timestamp(SystemTime::now()),
+    }));
+    if let Err(err) = write_records(&record_file, &records) {
+        runtime::shell_log(app, &format!("[WARN] 记录已删除的组件失败: {err}"));
+    }
+    journal::record(
+        app,
+        Severity::Info,
+        None,
+        format!("已删除可选组件 {name}，释放 {:.1} MB", mb(bytes)),
+    );
+    Ok(true)
+}
+
+/// 组件是否已被用户主动删除（记录过删除且至今仍不存在）。
+pub fn is_removed(app: &AppHandle, name: &str) -> bool {
+    record_path(app)
+        .map(|file| active_removals(&read_records(&file)).any(|record| record.name == name))
+        .unwrap_or(false)
+}
+
+fn measure(
+    runtime_root: &Path,
+    data_dir: &Path,
+    bdinfo_override: Option<&Path>,
+    removed: &[RemovedComponent],
+) -> FootprintReport {
+    let size = |targets: &[PathBuf]| -> u64 {
+        targets
+            .iter()
+            .map(|path| datadir::dir_size(&paths::extended(path)))
+            .sum()
+    };
+    let mut components: Vec<ComponentUsage> = COMPONENTS
+        .into_iter()
+        .map(|component| {
+            let targets = component.paths(runtime_root, data_dir);
+            let mut bytes = size(&targets);
+            if component == Component::Server {
+                // python、bdinfo、dist 位于 server 目录中，单独列出
+                let nested: Vec<PathBuf> = [Component::Bdinfo, Component::Dist]
+                    .into_iter()
+                    .flat_map(|c| c.paths(runtime_root, data_dir))
+                    .chain([runtime_root.join("server").join("python")])
+                    .collect();
+                bytes = bytes.saturating_sub(size(&nested));
+            }
+            let in_use = match component {
+                Component::Bdinfo => bdinfo_override.is_none_or(|path| {
+                    path.starts_with(runtime_root.join("server").join("bdinfo"))
+                }),
+                _ => true,
+            };
+            let was_removed =
+                active_removals(removed).any(|record| record.name == component.name());
+            ComponentUsage {
+                component,
+                paths: targets
+                    .iter()
+                    .filter(|path| paths::extended(path).exists())
+                    .map(|path| path.to_string_lossy().to_string())
+                    .collect(),
+                bytes,
+                required: !component.optional(),
+                in_use,
+                removed: was_removed,
+                note: note(component, in_use, was_removed),
+            }
+        })
+        .collect();
+    components.sort_by_key(|usage| std::cmp::Reverse(usage.bytes));
+
+    let mut candidates: Vec<&ComponentUsage> = components
+        .iter()
+        .filter(|usage| !usage.required && usage.bytes > 0)
+        .collect();
+    candidates.sort_by_key(|usage| usage.in_use);
+    FootprintReport {
+        runtime_root: runtime_root.to_string_lossy().to_string(),
+        total_bytes: datadir::dir_size(&paths::extended(runtime_root))
+            + datadir::dir_size(&paths::extended(&data_dir.join("python-runtime"))),
+        reclaimable_bytes: candidates.iter().map(|usage| usage.bytes).sum(),
+        candidates: candidates.iter().map(|usage| usage.component).collect(),
+        components,
+    }
+}
+
+fn note(component: Component, in_use: bool, removed: bool) -> String {
+    match component {
+        Component::Bdinfo if removed => format!(
+            "已删除；原盘（BDMV）的 BDInfo 扫描不可用，需要时可在 runtime.env 中设置 {} 指向自行安装的 BDInfo",
+            bdinfo::BDINFO_PATH_KEY
+        ),
+        Component::Bdinfo if !in_use => format!(
+            "runtime.env 中的 {} 指向其他 BDInfo，打包的版本未被使用，可以删除",
+            bdinfo::BDINFO_PATH_KEY
+        ),
+        Component::Bdinfo => format!(
+            "用于原盘（BDMV）的 BDInfo 扫描；不需要该功能时可以通过 remove_optional_component(\"bdinfo\") 删除，或在 runtime.env 中设置 {} 改用自行安装的版本",
+            bdinfo::BDINFO_PATH_KEY
+        ),
+        Component::Server => "后端服务（server、background_runner），必需".to_string(),
+        Component::Python => "后端服务使用的 Python 运行时，必需".to_string(),
+        Component::Dist => "WebUI 页面，必需".to_string(),
+        Component::Batch => "批量任务服务，必需".to_string(),
+        Component::Updater => {
+            "提供 WebUI 与反向代理，不能删除；更新源检出（数据目录 updates/repo）可通过 clean_update_cache 清理".to_string()
+        }
+    }
+}
+
+fn removal_effect(component: Component) -> String {
+    match component {
+        Component::Bdinfo => format!(
+            "删除后原盘（BDMV）的 BDInfo 扫描不可用，除非在 runtime.env 中设置 {} 指向自行安装的版本。",
+            bdinfo::BDINFO_PATH_KEY
+        ),
+        _ => String::new(),
+    }
+}
+
+/// 仍然有效的删除记录：应用更新可能重新带回组件，此时不再视为已删除。
+fn active_removals(records: &[RemovedComponent]) -> impl Iterator<Item = &RemovedComponent> {
+    records
+        .iter()
+        .filter(|record| !paths::extended(Path::new(&record.path)).exists())
+}
+
+fn record_path(app: &AppHandle) -> Result<PathBuf, String> {
+    // 运行目录为各配置档共用，删除记录放在应用数据目录而不是配置档中
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(RECORD_FILE))
+        .map_err(|e| format!("解析应用数据目录失败: {e}"))
+}
+
+fn read_records(path: &Path) -> Vec<RemovedComponent> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_records(path: &Path, records: &[RemovedComponent]) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(records).map_err(|e| e.to_string())?;
+    fsutil::atomic_write(path, json).map_err(|e| e.to_string())
+}
+
+fn mb(bytes: u64) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    fn write(path: &Path, bytes: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; bytes]).unwrap();
+    }
+
+    #[test]
+    fn report_separates_nested_components_and_lists_removable_ones() {
+        let dir = temp_dir("report");
+        let root = dir.join("runtime");
+        let data_dir = dir.join("data");
+        write(&root.join("server").join("app.py"), 100);
+        write(&root.join("server").join("bdinfo").join("BDInfo"), 3000);
+        write(&root.join("server").join("dist").join("index.html"), 200);
+        write(&root.join("python.zip"), 1000);
+        write(&data_dir.join("python-runtime").join("python"), 4000);
+        write(&root.join("batch").join("batch"), 500);
+        write(&root.join("updater").join("updater"), 600);
+
+        let bytes_of = |report: &FootprintReport, component: Component| {
+            report
+                .components
+                .iter()
+                .find(|usage| usage.component == component)
+                .unwrap()
+                .bytes
+        };
+        let report = measure(&root, &data_dir, None, &[]);
+        assert_eq!(bytes_of(&report, Component::Server), 100);
+        assert_eq!(bytes_of(&report, Component::Python), 5000);
+        assert_eq!(bytes_of(&report, Component::Bdinfo), 3000);
+        assert_eq!(report.total_bytes, 9400);
+        assert_eq!(report.components[0].component, Component::Python);
+        assert_eq!(report.candidates, vec![Component::Bdinfo]);
+        assert_eq!(report.reclaimable_bytes, 3000);
+        assert!(report.components.iter().all(|usage| usage.in_use));
+
+        let external = dir.join("tools").join("BDInfo");
+        let report = measure(&root, &data_dir, Some(&external), &[]);
+        let bdinfo = report
+            .components
+            .iter()
+            .find(|u| u.component == Component::Bdinfo)
+            .unwrap();
+        assert!(!bdinfo.in_use);
+
+        // 删除记录只在组件仍不存在时有效
+        let record = RemovedComponent {
+            name: "bdinfo".to_string(),
+            path: root
+                .join("server")
+                .join("bdinfo")
+                .to_string_lossy()
+                .to_string(),
+            bytes: 3000,
+            removed_at: "2024-01-01T00:00:00Z".to_string(),
+        };
+        let report = measure(&root, &data_dir, None, std::slice::from_ref(&record));
+        assert!(!report.components.iter().any(|usage| usage.removed));
+        fs::remove_dir_all(root.join("server").join("bdinfo")).unwrap();
+        let report = measure(&root, &data_dir, None, &[record]);
+        let bdinfo = report
+            .components
+            .iter()
+            .find(|u| u.component == Component::Bdinfo)
+            .unwrap();
+        assert!(bdinfo.removed);
+        assert!(report.candidates.is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn only_optional_components_are_known_as_removable() {
+        assert_eq!(Component::parse("bdinfo"), Ok(Component::Bdinfo));
+        assert!(Component::parse("webview").is_err());
+        assert!(COMPONENTS
+            .iter()
+            .filter(|component| component.optional())
+            .eq([Component::Bdinfo].iter()));
+    }
+}
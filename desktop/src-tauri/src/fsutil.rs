@@ -0,0 +1,158 @@
+//! 防止断电、崩溃时留下写了一半的文件。
+//!
+//! 桌面壳写入的文件（设置、runtime.env、启动错误日志等）都先写到同目录的 `<文件名>.tmp`，
+//! 落盘后再 rename 到目标位置；rename 在同一文件系统内是原子的，读取方只会看到旧内容或新内容。
+//! Unix 上还会同步目录，保证 rename 本身也已落盘。
+//! 写到一半中断时只会留下 `.tmp` 文件，启动时由 [`remove_stale_tmp`] 清理。
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const TMP_SUFFIX: &str = ".tmp";
+
+/// `path` 对应的临时文件：同目录、文件名追加 `.tmp`。
+pub fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(TMP_SUFFIX);
+    path.with_file_name(name)
+}
+
+/// 原子地写入整个文件。
+pub fn atomic_write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let tmp = tmp_path(path);
+    let written = File::create(&tmp).and_then(|mut file| {
+        file.write_all(contents.as_ref())?;
+        file.sync_all()
+    });
+    commit(&tmp, path, written)
+}
+
+/// 原子地复制文件，目标位置不会出现复制了一半的文件。
+pub fn atomic_copy(from: &Path, to: &Path) -> io::Result<()> {
+    let tmp = tmp_path(to);
+    let copied = fs::copy(from, &tmp).and_then(|_| File::open(&tmp)?.sync_all());
+    commit(&tmp, to, copied)
+}
+
+fn commit(tmp: &Path, path: &Path, written: io::Result<()>) -> io::Result<()> {
+    if let Err(err) = written.and_then(|_| fs::rename(tmp, path)) {
+        let _ = fs::remove_file(tmp);
+        return Err(err);
+    }
+    sync_parent(path);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn sync_parent(path: &Path) {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        let _ = File::open(parent).and_then(|dir| dir.sync_all());
+    }
+}
+
+/// Windows 上无法打开目录句柄做同步，MoveFileEx 完成后即已持久化元数据。
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) {}
+
+/// 删除目录（不含子目录）中上次写入中断遗留的 `.tmp` 文件，返回删除的数量。
+pub fn remove_stale_tmp(dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(TMP_SUFFIX))
+        .filter(|entry| fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+/// 测试用的空目录 `<系统临时目录>/ptnexus-<模块>-test-<name>-<pid>`，模块取自调用处的源文件名，
+/// 各模块的测试并行运行时互不干扰。目录已存在时先清空。
+#[cfg(test)]
+#[track_caller]
+pub fn temp_dir(name: &str) -> PathBuf {
+    let module = Path::new(std::panic::Location::caller().file())
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dir = std::env::temp_dir().join(format!(
+        "ptnexus-{module}-test-{name}-{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn write_replaces_content_without_leaving_tmp() {
+        let dir = temp_dir("write");
+        let path = dir.join("settings.json");
+        atomic_write(&path, r#"{"v": 1}"#).unwrap();
+        atomic_write(&path, r#"{"v": 2}"#).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"v": 2}"#);
+        assert!(!tmp_path(&path).exists());
+
+        let copy = dir.join("copy.json");
+        atomic_copy(&path, &copy).unwrap();
+        assert_eq!(fs::read_to_string(&copy).unwrap(), r#"{"v": 2}"#);
+        assert!(atomic_copy(&dir.join("missing"), &copy).is_err());
+        assert!(!tmp_path(&copy).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn interrupted_write_leaves_target_intact() {
+        let dir = temp_dir("partial");
+        let path = dir.join("settings.json");
+        atomic_write(&path, r#"{"complete": true}"#).unwrap();
+        // 模拟写入临时文件途中断电：只有 .tmp 是半截内容
+        fs::write(tmp_path(&path), r#"{"compl"#).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"complete": true}"#);
+
+        fs::create_dir_all(dir.join("nested.tmp")).unwrap();
+        assert_eq!(remove_stale_tmp(&dir), 1);
+        assert!(!tmp_path(&path).exists());
+        assert!(path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn readers_never_observe_partial_content() {
+        let dir = temp_dir("readers");
+        let path = dir.join("state.json");
+        let small = format!("[{}]", ["1"; 10].join(","));
+        let large = format!("[{}]", vec!["2"; 200_000].join(","));
+        atomic_write(&path, &small).unwrap();
+
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let (path, done) = (path.clone(), done.clone());
+            let (small, large) = (small.clone(), large.clone());
+            thread::spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    let content = fs::read_to_string(&path).unwrap();
+                    assert!(content == small || content == large, "读到了不完整的内容");
+                }
+            })
+        };
+        for round in 0..20 {
+            atomic_write(&path, if round % 2 == 0 { &large } else { &small }).unwrap();
+        }
+        done.store(true, Ordering::SeqCst);
+        reader.join().unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
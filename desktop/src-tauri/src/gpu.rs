@@ -0,0 +1,167 @@
+//! WebView 硬件加速开关。
+//!
+//! 部分老旧核显会出现黑屏/闪烁，可以关闭 WebView 的 GPU 加速。开关保存在桌面设置中，
+//! runtime.env 或环境变量中的 `PTNEXUS_DISABLE_GPU` 优先。该参数只能在启动时生效，修改后需要重启应用。
+//!
+//! WebKitGTK 通过环境变量关闭合成模式。`set_var` 在其他线程运行时并不安全，所以由 [`apply_process_env`]
+//! 在 `run()` 最开始、Tauri 运行时与任何线程创建之前设置，此时还没有 AppHandle，直接按应用数据目录读取开关。
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use tauri::{AppHandle, Manager, Runtime, WebviewWindowBuilder};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::{datadir, runtime, settings};
+
+pub const DISABLE_GPU_KEY: &str = "PTNEXUS_DISABLE_GPU";
+
+/// WebKitGTK 关闭硬件加速使用的环境变量。
+#[cfg(target_os = "linux")]
+const WEBKIT_ENV: [&str; 2] = [
+    "WEBKIT_DISABLE_COMPOSITING_MODE",
+    "WEBKIT_DISABLE_DMABUF_RENDERER",
+];
+/// 标记 [`WEBKIT_ENV`] 由本应用设置：重启应用时子进程会继承环境变量，重新开启加速后需要清除。
+#[cfg(target_os = "linux")]
+const WEBKIT_ENV_MARKER: &str = "PTNEXUS_GPU_ENV_APPLIED";
+
+/// Tauri 在 Windows 上默认传给 WebView2 的参数；自定义参数会覆盖默认值，所以需要一并带上。
+#[cfg(target_os = "windows")]
+const WEBVIEW2_DEFAULT_ARGS: &str =
+    "--disable-features=msWebOOUI,msPdfOOUI,msSmartScreenProtection";
+
+/// 本次运行中创建窗口使用的开关：第一次创建窗口时确定，之后修改设置要重启才生效，
+/// 各窗口的 WebView2 启动参数保持一致（见 webviewprofile.rs）。
+pub fn disabled_at_startup(app: &AppHandle) -> bool {
+    static DISABLED: OnceLock<bool> = OnceLock::new();
+    *DISABLED.get_or_init(|| is_gpu_disabled(app))
+}
+
+pub fn is_gpu_disabled(app: &AppHandle) -> bool {
+    runtime::read_runtime_setting(app, DISABLE_GPU_KEY)
+        .map(|value| runtime::is_truthy(&value))
+        .unwrap_or_else(|| !settings::current(app).gpu_acceleration)
+}
+
+/// 与 [`is_gpu_disabled`] 相同的判断，`base` 为应用数据目录。
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn disabled_in(base: &Path) -> bool {
+    let mut values = HashMap::new();
+    let env_file = datadir::active_profile_in(base).root.join("runtime.env");
+    let _ = runtime::merge_env_file(&mut values, &env_file);
+    values
+        .remove(DISABLE_GPU_KEY)
+        .or_else(|| {
+            std::env::var(DISABLE_GPU_KEY)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        })
+        .map(|value| runtime::is_truthy(&value))
+        .unwrap_or_else(|| !settings::read_in(base).gpu_acceleration)
+}
+
+/// 在 `run()` 最开始调用，按开关设置或清除 WebKitGTK 的环境变量；其他平台通过窗口参数关闭，见 [`apply_to_builder`]。
+pub fn apply_process_env() {
+    #[cfg(target_os = "linux")]
+    {
+        let disabled = datadir::standalone_base_dir().is_some_and(|base| disabled_in(&base));
+        if disabled {
+            for key in WEBKIT_ENV {
+                std::env::set_var(key, "1");
+            }
+            std::env::set_var(WEBKIT_ENV_MARKER, "1");
+        } else if std::env::var_os(WEBKIT_ENV_MARKER).is_some() {
+            // 用户自己设置的变量不动，只清除上一个进程替我们设置的
+            for key in WEBKIT_ENV {
+                std::env::remove_var(key);
+            }
+            std::env::remove_var(WEBKIT_ENV_MARKER);
+        }
+    }
+}
+
+/// 按设置为主窗口的 WebView 附加关闭 GPU 的启动参数（Linux 已在启动时设置环境变量）。
+/// macOS 的 WKWebView 没有公开的开关，设置在该平台上不生效。
+pub fn apply_to_builder<'a, R: Runtime, M: Manager<R>>(
+    builder: WebviewWindowBuilder<'a, R, M>,
+    gpu_disabled: bool,
+) -> WebviewWindowBuilder<'a, R, M> {
+    if !gpu_disabled {
+        return builder;
+    }
+
+    #[cfg(target_os = "windows")]
+    let builder =
+        builder.additional_browser_args(&format!("{WEBVIEW2_DEFAULT_ARGS} --disable-gpu"));
+
+    builder
+}
+
+/// 把硬件加速开关写入桌面设置，并移除 runtime.env 中旧的覆盖项，避免新设置不生效。
+pub fn write_gpu_setting(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    settings::update(app, |settings| settings.gpu_acceleration = enabled)?;
+    let env_file = runtime::ensure_runtime_env_file(app)?;
+    runtime::update_env_file(&env_file, &[(DISABLE_GPU_KEY, None)])
+}
+
+/// 写入设置后询问用户是否立即重启应用。
+pub fn set_gpu_acceleration(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    write_gpu_setting(app, enabled)?;
+
+    let message = if enabled {
+        "已开启 GPU 硬件加速，需要重启应用后生效。"
+    } else {
+        "已关闭 GPU 硬件加速，需要重启应用后生效。"
+    };
+    let app = app.clone();
+    app.dialog()
+        .message(message)
+        .title("PT Nexus")
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "立即重启".to_string(),
+            "稍后".to_string(),
+        ))
+        .show({
+            let app = app.clone();
+            move |confirmed| {
+                if confirmed {
+                    crate::restart_app(&app);
+                }
+            }
+        });
+
+    Ok(())
+}
+
+/// 关闭 GPU 加速并直接重启，用于渲染进程反复崩溃后的一键处理。
+pub fn disable_and_restart(app: &AppHandle) -> Result<(), String> {
+    write_gpu_setting(app, false)?;
+    crate::restart_app(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn launch_check_reads_runtime_env_before_settings() {
+        let base = temp_dir("launch-check");
+        assert!(!disabled_in(&base));
+
+        fs::write(
+            base.join("desktop-settings.json"),
+            r#"{"version": 1, "gpu_acceleration": false}"#,
+        )
+        .unwrap();
+        assert!(disabled_in(&base));
+
+        fs::write(base.join("runtime.env"), "PTNEXUS_DISABLE_GPU=false\n").unwrap();
+        assert!(!disabled_in(&base));
+    }
+}
@@ -0,0 +1,235 @@
+//! 进程仍在运行、端口却不再响应的服务。
+//!
+//! updater 在分发大的更新后偶尔会停止接受连接（上游的套接字泄漏），进程本身并没有退出，
+//! WebView 只能显示连接被拒绝。健康监测每轮完整检查后调用 [`observe`]：服务端口不可用而进程仍在运行，
+//! 连续 3 次即视为卡死（与进程退出区分开），自动平滑重启该服务一次；重启后端口恢复则只写入运行记录，
+//! 仍然不可用才发系统通知请用户处理。端口恢复或进程退出后重新计数。
+//! 自动重启的次数记为 `hang_recoveries`（见 status.rs），诊断包中可以看出上游问题出现的频率。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::tasks::{self, Scope};
+use crate::webhook::{self, Trigger};
+use crate::{power, runtime};
+
+/// 进程存活而端口不可用的连续完整检查次数达到该值才重启。
+const CHECKS_BEFORE_RESTART: u32 = 3;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HangRecovered {
+    pub service: String,
+    /// 重启后端口是否恢复。
+    pub recovered: bool,
+    pub message: String,
+}
+
+/// 卡死检测的计数与各服务自动重启的次数，托管在 Tauri state 中。
+#[derive(Default)]
+pub struct HangWatch {
+    tracker: Mutex<Tracker>,
+    recoveries: Mutex<HashMap<String, u32>>,
+}
+
+pub fn recovery_count(app: &AppHandle, service: &str) -> u32 {
+    app.try_state::<HangWatch>()
+        .and_then(|state| {
+            state
+                .recoveries
+                .lock()
+                .ok()
+                .and_then(|counts| counts.get(service).copied())
+        })
+        .unwrap_or(0)
+}
+
+/// 服务是否处于卡死状态（进程在运行，端口连续不可用）。
+pub fn is_hung(app: &AppHandle, service: &str) -> bool {
+    app.try_state::<HangWatch>()
+        .and_then(|state| {
+            state
+                .tracker
+                .lock()
+                .ok()
+                .map(|tracker| tracker.is_hung(service))
+        })
+        .unwrap_or(false)
+}
+
+/// 健康监测每轮完整检查后调用，`down` 为端口不可用的服务。
+pub fn observe(app: &AppHandle, down: &HashSet<&'static str>) {
+    let Some(state) = app.try_state::<HangWatch>() else {
+        return;
+    };
+    let Some(manager) = app.try_state::<runtime::RuntimeManager>() else {
+        return;
+    };
+    let mut to_restart = Vec::new();
+    if let Ok(mut tracker) = state.tracker.lock() {
+        tracker.forget_recovered(down);
+        for service in down {
+            if tracker.observe(service, manager.is_service_alive(service)) {
+                to_restart.push(*service);
+            }
+        }
+    }
+    for service in to_restart {
+        if let Ok(mut counts) = state.recoveries.lock() {
+            *counts.entry(service.to_string()).or_default() += 1;
+        }
+        let message = format!("{service} 进程仍在运行，但端口连续 {CHECKS_BEFORE_RESTART} 次检查无响应，正在重启该服务");
+        runtime::shell_log(app, &format!("[WARN] {message}"));
+        journal::record(app, Severity::Warn, Some(service), message);
+        // 重启期间健康监测照常运行，离线提示页等不受影响
+        tasks::spawn(
+            app,
+            &format!("hang-recovery:{service}"),
+            Scope::Shell,
+            move |app, _| {
+                recover(&app, service);
+            },
+        );
+    }
+}
+
+fn recover(app: &AppHandle, service: &'static str) {
+    let Some(manager) = app.try_state::<runtime::RuntimeManager>() else {
+        return;
+    };
+    let restarted = manager.restart_service(app, service);
+    let reachable = restarted.is_ok() && is_reachable(app, service);
+    let message = match (&restarted, reachable) {
+        (Ok(()), true) => format!("{service} 端口无响应，已自动重启并恢复"),
+        (Ok(()), false) => format!("{service} 端口无响应，自动重启后仍无法连接"),
+        (Err(err), _) => format!(
+            "{service} 端口无响应，自动重启失败: {}",
+            journal::first_line(err)
+        ),
+    };
+    if reachable {
+        runtime::shell_log(app, &format!("[INFO] {message}"));
+        journal::record(app, Severity::Info, Some(service), message.clone());
+    } else {
+        runtime::shell_log(app, &format!("[ERROR] {message}"));
+        journal::record(app, Severity::Error, Some(service), message.clone());
+        webhook::notify(app, Trigger::Failure, message.clone());
+        power::wake(app);
+        let _ = app
+            .notification()
+            .builder()
+            .title("PT Nexus 服务无响应")
+            .body(format!(
+                "{message}。可以在托盘菜单中重启服务，或查看日志了解原因。"
+            ))
+            .show();
+    }
+    events::emit(
+        app,
+        "service-hang-recovered",
+        Replay::All,
+        &HangRecovered {
+            service: service.to_string(),
+            recovered: reachable,
+            message,
+        },
+    );
+}
+
+fn is_reachable(app: &AppHandle, service: &str) -> bool {
+    if service == "updater" {
+        return runtime::is_runtime_reachable(app);
+    }
+    crate::health::service_ports(app)
+        .into_iter()
+        .find(|(name, _)| *name == service)
+        .is_some_and(|(_, port)| runtime::is_port_open(port))
+}
+
+/// 各服务进程存活而端口不可用的连续次数，以及本次卡死是否已经重启过。
+#[derive(Default)]
+struct Tracker {
+    streak: HashMap<String, u32>,
+    restarted: HashSet<String>,
+}
+
+impl Tracker {
+    /// 记录一次端口不可用，`alive` 为进程是否仍在运行（None 表示暂时无法判断，如服务正在重启）。
+    /// 返回是否应当重启该服务。
+    fn observe(&mut self, service: &str, alive: Option<bool>) -> bool {
+        match alive {
+            Some(true) => {}
+            // 进程已退出不算卡死，按服务掉线处理
+            Some(false) => {
+                self.forget(service);
+                return false;
+            }
+            None => return false,
+        }
+        let streak = self.streak.entry(service.to_string()).or_default();
+        *streak += 1;
+        *streak >= CHECKS_BEFORE_RESTART && self.restarted.insert(service.to_string())
+    }
+
+    /// 端口已恢复的服务重新计数。
+    fn forget_recovered(&mut self, down: &HashSet<&'static str>) {
+        self.streak
+            .retain(|service, _| down.contains(service.as_str()));
+        self.restarted
+            .retain(|service| down.contains(service.as_str()));
+    }
+
+    fn forget(&mut self, service: &str) {
+        self.streak.remove(service);
+        self.restarted.remove(service);
+    }
+
+    fn is_hung(&self, service: &str) -> bool {
+        self.streak
+            .get(service)
+            .is_some_and(|streak| *streak >= CHECKS_BEFORE_RESTART)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restarts_once_after_consecutive_checks_with_a_live_process() {
+        let mut tracker = Tracker::default();
+        assert!(!tracker.observe("updater", Some(true)));
+        assert!(!tracker.observe("updater", Some(true)));
+        assert!(tracker.observe("updater", Some(true)));
+        assert!(tracker.is_hung("updater"));
+        // 同一次卡死只重启一次
+        assert!(!tracker.observe("updater", Some(true)));
+        assert!(!tracker.observe("updater", None));
+
+        // 端口恢复后重新计数
+        tracker.forget_recovered(&HashSet::new());
+        assert!(!tracker.is_hung("updater"));
+        assert!(!tracker.observe("updater", Some(true)));
+        assert!(!tracker.observe("updater", Some(true)));
+        assert!(tracker.observe("updater", Some(true)));
+    }
+
+    #[test]
+    fn exited_processes_are_not_hangs() {
+        let mut tracker = Tracker::default();
+        assert!(!tracker.observe("server", Some(true)));
+        assert!(!tracker.observe("server", Some(true)));
+        assert!(!tracker.observe("server", Some(false)));
+        assert!(!tracker.observe("server", Some(true)));
+        assert!(!tracker.is_hung("server"));
+        // 无法判断进程状态的检查不计数
+        assert!(!tracker.observe("server", None));
+        assert!(!tracker.observe("server", Some(true)));
+        assert!(tracker.observe("server", Some(true)));
+    }
+}
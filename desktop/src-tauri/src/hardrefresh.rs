@@ -0,0 +1,169 @@
+//! 强制刷新界面：清理 WebView 的 HTTP 缓存与 Service Worker 后重新加载运行时页面。
+//!
+//! 更新运行时后，WebView 可能仍在使用旧的 JS 资源，与新接口混用导致界面异常，Ctrl+F5 也不一定能解决。
+//! 这里通过各平台 WebView 的接口清理缓存（WebView2 DevTools 协议、WKWebsiteDataStore、
+//! WebKitGTK WebsiteDataManager），再导航回运行时地址并重新注入脚本。
+//! localStorage 保存着登录状态，不做清理。某一步失败时仍会重新加载，但在返回的错误中说明，
+//! 并给出 WebView 数据目录（Windows 上位于数据目录的 webview-profile 中），便于退出应用后手动删除缓存。
+//!
+//! 清理接口需要在主线程回调中完成，调用方不能在主线程上等待本函数。
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use tauri::{Manager, WebviewWindow};
+
+use crate::{renderwatch, runtime, webviewprofile};
+
+const CLEAR_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn hard_refresh(window: &WebviewWindow) -> Result<(), String> {
+    let app = window.app_handle();
+    let origin = runtime::runtime_url(app).origin().ascii_serialization();
+
+    let failures: Vec<String> = clear_steps(window, &origin)
+        .into_iter()
+        .filter_map(|(step, outcome)| outcome.err().map(|err| format!("{step}: {err}")))
+        .collect();
+    for failure in &failures {
+        runtime::shell_log(app, &format!("[WARN] 强制刷新时清理失败 - {failure}"));
+    }
+
+    renderwatch::force_reload(window)?;
+    let profile = webviewprofile::profile_path(app)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "未知".to_string());
+    if failures.is_empty() {
+        runtime::shell_log(
+            app,
+            &format!("[INFO] 已清理 WebView 缓存并重新加载界面（数据目录: {profile}）"),
+        );
+        Ok(())
+    } else {
+        Err(format!(
+            "界面已重新加载，但部分缓存未能清理：\n{}\n\n可退出应用后手动删除 {profile} 中的 Cache 目录。",
+            failures.join("\n")
+        ))
+    }
+}
+
+/// 在主线程回调中执行 `start`，等待其通过 sender 回报结果。
+fn run_on_webview<F>(window: &WebviewWindow, start: F) -> Result<(), String>
+where
+    F: FnOnce(tauri::webview::PlatformWebview, mpsc::Sender<Result<(), String>>) + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    window
+        .with_webview(move |webview| start(webview, tx))
+        .map_err(|e| format!("访问 WebView 失败: {e}"))?;
+    rx.recv_timeout(CLEAR_TIMEOUT)
+        .map_err(|_| "等待清理完成超时".to_string())?
+}
+
+#[cfg(target_os = "windows")]
+fn clear_steps(window: &WebviewWindow, origin: &str) -> Vec<(&'static str, Result<(), String>)> {
+    let storage_params = serde_json::json!({
+        "origin": origin,
+        "storageTypes": "service_workers,cache_storage",
+    })
+    .to_string();
+    vec![
+        (
+            "HTTP 缓存",
+            webview2::call_devtools(window, "Network.clearBrowserCache", "{}".to_string()),
+        ),
+        (
+            "Service Worker 与 Cache Storage",
+            webview2::call_devtools(window, "Storage.clearDataForOrigin", storage_params),
+        ),
+    ]
+}
+
+#[cfg(target_os = "windows")]
+mod webview2 {
+    use tauri::WebviewWindow;
+    use webview2_com::CallDevToolsProtocolMethodCompletedHandler;
+    use windows::core::HSTRING;
+
+    use super::run_on_webview;
+
+    pub fn call_devtools(
+        window: &WebviewWindow,
+        method: &'static str,
+        params: String,
+    ) -> Result<(), String> {
+        run_on_webview(window, move |webview, tx| {
+            let started = unsafe {
+                (|| -> windows::core::Result<()> {
+                    let core = webview.controller().CoreWebView2()?;
+                    let done = tx.clone();
+                    let handler = CallDevToolsProtocolMethodCompletedHandler::create(Box::new(
+                        move |result: windows::core::Result<()>, _response: String| {
+                            let _ = done.send(result.map_err(|e| e.to_string()));
+                            Ok(())
+                        },
+                    ));
+                    core.CallDevToolsProtocolMethod(
+                        &HSTRING::from(method),
+                        &HSTRING::from(params.as_str()),
+                        &handler,
+                    )
+                })()
+            };
+            if let Err(err) = started {
+                let _ = tx.send(Err(format!("调用 {method} 失败: {err}")));
+            }
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn clear_steps(window: &WebviewWindow, _origin: &str) -> Vec<(&'static str, Result<(), String>)> {
+    use block2::RcBlock;
+    use objc2_foundation::{NSDate, NSSet};
+    use objc2_web_kit::{
+        WKWebView, WKWebsiteDataTypeDiskCache, WKWebsiteDataTypeFetchCache,
+        WKWebsiteDataTypeMemoryCache, WKWebsiteDataTypeServiceWorkerRegistrations,
+    };
+
+    // WKWebsiteDataStore 只能按数据类型清理，应用内只有运行时一个站点，按类型清理即可
+    let outcome = run_on_webview(window, |webview, tx| unsafe {
+        let view = &*(webview.inner() as *const WKWebView);
+        let store = view.configuration().websiteDataStore();
+        let types = NSSet::from_slice(&[
+            WKWebsiteDataTypeDiskCache,
+            WKWebsiteDataTypeMemoryCache,
+            WKWebsiteDataTypeFetchCache,
+            WKWebsiteDataTypeServiceWorkerRegistrations,
+        ]);
+        let since = NSDate::distantPast();
+        let done = RcBlock::new(move || {
+            let _ = tx.send(Ok(()));
+        });
+        store.removeDataOfTypes_modifiedSince_completionHandler(&types, &since, &done);
+    });
+    vec![("WebKit 缓存与 Service Worker", outcome)]
+}
+
+#[cfg(target_os = "linux")]
+fn clear_steps(window: &WebviewWindow, _origin: &str) -> Vec<(&'static str, Result<(), String>)> {
+    use webkit2gtk::{gio, glib, WebViewExt, WebsiteDataManagerExtManual, WebsiteDataTypes};
+
+    let outcome = run_on_webview(window, |webview, tx| {
+        let Some(manager) = webview.inner().website_data_manager() else {
+            let _ = tx.send(Err("WebKitGTK 未提供 WebsiteDataManager".to_string()));
+            return;
+        };
+        manager.clear(
+            WebsiteDataTypes::DISK_CACHE
+                | WebsiteDataTypes::MEMORY_CACHE
+                | WebsiteDataTypes::SERVICE_WORKER_REGISTRATIONS,
+            glib::TimeSpan::from_seconds(0),
+            None::<&gio::Cancellable>,
+            move |result| {
+                let _ = tx.send(result.map_err(|e| e.to_string()));
+            },
+        );
+    });
+    vec![("WebKitGTK 缓存与 Service Worker", outcome)]
+}
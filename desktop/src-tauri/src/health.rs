@@ -0,0 +1,178 @@
+//! 后端健康监测。
+//!
+//! 定期探测 updater 端口：主窗口仍停留在运行时页面而端口不再响应时（进程被手动关闭或崩溃），
+//! 切换到内置的离线提示页，避免 WebView 显示浏览器自带的“无法访问此网站”；服务自行恢复、首页可以打开后
+//! 自动返回。启动后首页迟迟不可用时，启动流程同样停在这个提示页（见 runtime.rs）。
+//! 同时探测各服务端口，把不可用的服务数同步到任务栏角标，并在服务掉线/恢复时写入运行记录；
+//! 刚更新过的服务连续多次不可用时询问是否回滚（见 rollback.rs）；进程仍在运行而端口连续不可用时自动重启该服务
+//! （见 hangwatch.rs）。每轮探测后顺带检查磁盘空间（见 diskwatch.rs）。
+//! 服务掉线或恢复后短时间内加快探测，以便尽快发现恢复；连续失败的次数仍按完整的检查间隔计算。
+//! 空闲节能时降低探测频率。
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::journal::{self, Severity};
+use crate::services::Backoff;
+use crate::tasks::{self, Scope};
+use crate::webhook::{self, Trigger};
+use crate::{badge, diskwatch, hangwatch, power, rollback, runtime, status};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// 状态变化后的探测间隔下限。
+const RECHECK_INTERVAL: Duration = Duration::from_millis(500);
+const FAILURES_BEFORE_FALLBACK: u32 = 2;
+const OFFLINE_PAGE: &str = "offline.html";
+
+pub fn start(app: &AppHandle) {
+    tasks::spawn(app, "health", Scope::Shell, move |app, token| {
+        let mut failures = 0u32;
+        let mut down: HashSet<&'static str> = HashSet::new();
+        let mut down_streak: HashMap<&'static str, u32> = HashMap::new();
+        let mut poll = Backoff::new(RECHECK_INTERVAL, CHECK_INTERVAL);
+        let mut counted_at = Instant::now();
+        loop {
+            if !power::sleep(
+                &app,
+                &token,
+                poll.next_interval(),
+                power::SAVER_HEALTH_INTERVAL,
+            ) {
+                return;
+            }
+            // 按需启动休眠期间服务本就没有运行，不算掉线
+            if status::is_dormant(&app) {
+                down.clear();
+                down_streak.clear();
+                failures = 0;
+                badge::set_down_services(&app, 0);
+                status::set_down_services(&app, &down);
+                continue;
+            }
+            diskwatch::tick(&app);
+
+            let healthy = runtime::is_runtime_reachable(&app);
+            let mut now_down: HashSet<&'static str> = service_ports(&app)
+                .into_iter()
+                .filter(|(_, port)| !runtime::is_port_open(*port))
+                .map(|(service, _)| service)
+                .collect();
+            if !healthy {
+                now_down.insert("updater");
+            }
+            badge::set_down_services(&app, now_down.len() as u32);
+            status::set_down_services(&app, &now_down);
+            record_transitions(&app, &down, &now_down);
+            if now_down != down {
+                poll.reset();
+            }
+            // 加快的探测只用于发现状态变化，连续失败仍按完整的检查间隔计数
+            let full_check = counted_at.elapsed() >= CHECK_INTERVAL;
+            if full_check {
+                counted_at = Instant::now();
+            }
+            down_streak.retain(|service, _| now_down.contains(service));
+            if full_check && status::is_running(&app) {
+                hangwatch::observe(&app, &now_down);
+            }
+            if full_check {
+                for service in &now_down {
+                    let streak = down_streak.entry(*service).or_insert(0);
+                    *streak += 1;
+                    if *streak == rollback::FAILURES_BEFORE_PROMPT {
+                        rollback::offer(&app, service);
+                    }
+                }
+            }
+            down = now_down;
+            failures = match (healthy, full_check) {
+                (true, _) => 0,
+                (false, true) => failures.saturating_add(1),
+                (false, false) => failures,
+            };
+
+            let Some(window) = app.get_webview_window("main") else {
+                continue;
+            };
+            let Ok(current) = window.url() else {
+                continue;
+            };
+
+            if is_offline_page(&app, &current) {
+                // 端口可连接但首页仍返回错误时留在提示页，避免导航到 404 页面
+                if healthy && runtime::probe_runtime_page(&runtime::runtime_url(&app)).is_ok() {
+                    runtime::shell_log(&app, "[INFO] updater 服务已恢复，返回运行时页面");
+                    runtime::reload_runtime_page(&window);
+                }
+            } else if failures >= FAILURES_BEFORE_FALLBACK
+                && runtime::is_runtime_url(&app, &current)
+            {
+                let reason = format!(
+                    "updater 服务（{}）无法连接，可能已被关闭或崩溃。\n服务恢复后会自动返回。",
+                    runtime::runtime_url(&app)
+                );
+                runtime::shell_log(&app, &format!("[WARN] {reason}"));
+                show_offline_page(&window, &reason);
+            }
+        }
+    });
+}
+
+fn record_transitions(
+    app: &AppHandle,
+    before: &HashSet<&'static str>,
+    after: &HashSet<&'static str>,
+) {
+    for service in after.difference(before) {
+        journal::record(
+            app,
+            Severity::Error,
+            Some(service),
+            format!("{service} 服务不可用"),
+        );
+        webhook::notify(app, Trigger::Failure, format!("{service} 服务不可用"));
+    }
+    for service in before.difference(after) {
+        journal::record(
+            app,
+            Severity::Info,
+            Some(service),
+            format!("{service} 服务已恢复"),
+        );
+    }
+}
+
+/// server、batch 的端口（updater 由 is_runtime_reachable 单独探测）。
+pub fn service_ports(app: &AppHandle) -> Vec<(&'static str, u16)> {
+    let context = app
+        .try_state::<runtime::RuntimeManager>()
+        .and_then(|rt| rt.context());
+    [
+        ("server", "SERVER_PORT", 5275),
+        ("batch", "BATCH_PORT", 5276),
+    ]
+    .into_iter()
+    .map(|(service, key, default)| {
+        let port = context
+            .as_ref()
+            .and_then(|ctx| ctx.common_env.get(key))
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(default);
+        (service, port)
+    })
+    .collect()
+}
+
+pub fn is_offline_page(app: &AppHandle, url: &tauri::Url) -> bool {
+    !runtime::is_runtime_url(app, url) && url.path().trim_start_matches('/') == OFFLINE_PAGE
+}
+
+pub fn show_offline_page(window: &WebviewWindow, reason: &str) {
+    let Some(mut url) = runtime::local_page_url(OFFLINE_PAGE) else {
+        return;
+    };
+    url.query_pairs_mut().append_pair("reason", reason);
+    let _ = window.navigate(url);
+}
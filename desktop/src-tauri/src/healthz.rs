@@ -0,0 +1,257 @@
+//! 供外部监控脚本使用的健康检查。
+//!
+//! 桌面壳在 `127.0.0.1:<PTNEXUS_HEALTHZ_PORT>`（默认 5277，runtime.env 中设为 0 关闭）监听 `/healthz`：
+//! 全部服务健康时返回 200，否则返回 503，响应体为 JSON，列出各服务的状态，可直接用
+//! `curl -f 127.0.0.1:5277/healthz` 放进 cron。监听在应用启动时开启，与主窗口无关，服务模式下同样可用。
+//! 同一监听还提供供用户脚本发现实际地址的 `/discovery`（见 discovery.rs）。
+//!
+//! 命令行 `pt-nexus-desktop healthz [--port N]` 请求同一地址并打印 JSON：健康时退出码为 0，
+//! 有服务异常时为 1，应用未运行时为 2。未指定端口时依次读取环境变量与当前配置档的 runtime.env。
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::status::{self, RuntimeState, ServiceStatus};
+use crate::{datadir, discovery, localhttp, runtime};
+
+pub const PORT_KEY: &str = "PTNEXUS_HEALTHZ_PORT";
+pub const DEFAULT_PORT: u16 = 5277;
+const CLI_COMMAND: &str = "healthz";
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// 监听上各接口的响应；响应头固定带 JSON 类型与 `no-store`，`headers` 为额外的头。
+pub struct Reply {
+    pub status: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+#[derive(Serialize)]
+struct Health {
+    healthy: bool,
+    runtime_state: RuntimeState,
+    services: Vec<ServiceStatus>,
+    /// 启动失败时的原因（第一行）。
+    error: Option<String>,
+}
+
+/// 在 setup 中调用；端口被占用时只记录日志。
+pub fn start(app: &AppHandle) {
+    let port = match runtime::read_runtime_setting(app, PORT_KEY) {
+        Some(value) => match value.trim().parse::<u16>() {
+            Ok(port) => port,
+            Err(_) => {
+                runtime::shell_log(app, &format!("[WARN] {PORT_KEY} 不是有效的端口: {value}"));
+                return;
+            }
+        },
+        None => DEFAULT_PORT,
+    };
+    if port == 0 {
+        return;
+    }
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            runtime::shell_log(
+                app,
+                &format!("[WARN] 健康检查端口 127.0.0.1:{port} 监听失败: {err}"),
+            );
+            return;
+        }
+    };
+    let app = app.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+            let _ = respond(&app, stream);
+        }
+    });
+}
+
+fn respond(app: &AppHandle, mut stream: TcpStream) -> std::io::Result<()> {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(READ_TIMEOUT));
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n")
+        && request.len() < MAX_REQUEST_BYTES
+    {
+        let read = stream.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let route = route(&request);
+    let reply = match route {
+        Some(_) => {
+            let health = health(app);
+            let status = if health.healthy {
+                "200 OK"
+            } else {
+                "503 Service Unavailable"
+            };
+            Reply {
+                status,
+                headers: Vec::new(),
+                body: serde_json::to_string_pretty(&health).unwrap_or_default(),
+            }
+        }
+        None => discovery::handle(app, &request).unwrap_or_else(|| Reply {
+            status: "404 Not Found",
+            headers: Vec::new(),
+            body: r#"{"error": "not found"}"#.to_string(),
+        }),
+    };
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n",
+        reply.status,
+        reply.body.len()
+    );
+    for (name, value) in &reply.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    if route != Some(true) {
+        stream.write_all(reply.body.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// 请求 `/healthz` 时返回是否只需响应头（HEAD），其余请求返回 None。
+fn route(request: &str) -> Option<bool> {
+    let mut parts = request.lines().next()?.split_whitespace();
+    let method = parts.next()?;
+    let path = parts.next()?.split('?').next()?;
+    match (method, path) {
+        ("GET", "/healthz") => Some(false),
+        ("HEAD", "/healthz") => Some(true),
+        _ => None,
+    }
+}
+
+fn health(app: &AppHandle) -> Health {
+    let snapshot = status::snapshot(app);
+    Health {
+        healthy: snapshot.runtime_state == RuntimeState::Running,
+        runtime_state: snapshot.runtime_state,
+        services: snapshot.services,
+        error: snapshot.error,
+    }
+}
+
+/// 以 `healthz` 参数启动时执行命令行检查并返回退出码，否则返回 None 继续正常启动。
+pub fn run_cli() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) != Some(CLI_COMMAND) {
+        return None;
+    }
+    attach_console();
+    let port = match cli_port(&args[1..]) {
+        Ok(port) => port,
+        Err(err) => {
+            eprintln!("{err}");
+            return Some(2);
+        }
+    };
+    Some(
+        match localhttp::fetch("127.0.0.1", port, "/healthz", READ_TIMEOUT) {
+            Ok(response) => {
+                println!("{}", String::from_utf8_lossy(&response.body).trim_end());
+                if response.status == 200 {
+                    0
+                } else {
+                    1
+                }
+            }
+            Err(err) => {
+                eprintln!("PT Nexus 未在运行（127.0.0.1:{port}）: {err}");
+                2
+            }
+        },
+    )
+}
+
+/// `--port N` 优先，其次是环境变量、当前配置档的 runtime.env，最后为默认端口。
+fn cli_port(args: &[String]) -> Result<u16, String> {
+    let configured = match args {
+        [] => None,
+        [flag, value] if flag == "--port" => Some(value.clone()),
+        _ => return Err(format!("用法: {CLI_COMMAND} [--port <端口>]")),
+    };
+    let configured = configured
+        .or_else(|| std::env::var(PORT_KEY).ok())
+        .or_else(|| {
+            let env_file = datadir::active_profile_in(&datadir::standalone_base_dir()?)
+                .root
+                .join("runtime.env");
+            let mut values = HashMap::new();
+            runtime::merge_env_file(&mut values, &env_file).ok()?;
+            values.remove(PORT_KEY)
+        });
+    match configured.filter(|value| !value.trim().is_empty()) {
+        None => Ok(DEFAULT_PORT),
+        Some(value) => match value.trim().parse::<u16>() {
+            Ok(0) => Err(format!("健康检查已关闭（{PORT_KEY}=0）")),
+            Ok(port) => Ok(port),
+            Err(_) => Err(format!("无效的端口: {value}")),
+        },
+    }
+}
+
+/// 发布版为 Windows 子系统程序，没有控制台；附加到启动它的命令行窗口以便输出结果。
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // 失败（如从资源管理器双击启动）时没有输出，不影响退出码
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_console() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn only_healthz_is_routed() {
+        assert_eq!(
+            route("GET /healthz HTTP/1.1\r\nHost: x\r\n\r\n"),
+            Some(false)
+        );
+        assert_eq!(
+            route("GET /healthz?verbose=1 HTTP/1.0\r\n\r\n"),
+            Some(false)
+        );
+        assert_eq!(route("HEAD /healthz HTTP/1.1\r\n\r\n"), Some(true));
+        assert_eq!(route("POST /healthz HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(route("GET /healthz/x HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(route(""), None);
+    }
+
+    #[test]
+    fn cli_port_is_taken_from_the_flag() {
+        assert_eq!(cli_port(&args(&["--port", "6000"])), Ok(6000));
+        assert!(cli_port(&args(&["--port", "0"]))
+            .unwrap_err()
+            .contains("已关闭"));
+        assert!(cli_port(&args(&["--port", "x"])).is_err());
+        assert!(cli_port(&args(&["--verbose"])).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! 外部文件交给后端前的中转目录 `<data_dir>/tmp/inbox/`。
+//!
+//! 浏览器下载的 .torrent 等文件可能位于后端无权读取的位置（snap 沙盒浏览器、Linux 上的其他用户）。
+//! 数据目录之外的文件先由桌面壳复制到 inbox（限制大小、文件名唯一），再把 inbox 中的路径交给后端；
+//! 后端确认导入后删除副本，遗留的副本在下次启动时按时间清理。
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime};
+
+use tauri::AppHandle;
+
+use crate::commanderror::CommandError;
+use crate::{datadir, fsutil, runtime};
+
+/// 中转文件大小上限；.torrent 文件通常不超过几 MB。
+const MAX_FILE_BYTES: u64 = 32 * 1024 * 1024;
+/// 启动时清理超过该时长的遗留副本。
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+pub fn inbox_dir(data_dir: &Path) -> PathBuf {
+    datadir::ProfilePaths::for_root(data_dir).inbox_dir
+}
+
+/// 返回可以交给后端的路径：数据目录内的文件原样返回，其他位置的文件复制到 inbox。
+pub fn stage(app: &AppHandle, path: &Path) -> Result<PathBuf, String> {
+    let data_dir = data_dir(app)?;
+    let staged = stage_into(&data_dir, path)?;
+    if staged != path {
+        runtime::shell_log(
+            app,
+            &format!("[INFO] 已将 {} 复制到 {}", path.display(), staged.display()),
+        );
+    }
+    Ok(staged)
+}
+
+/// 后端确认导入后删除 inbox 中的副本；不在 inbox 中的路径不做处理。
+pub fn release(app: &AppHandle, path: &Path) -> Result<(), CommandError> {
+    let inbox = inbox_dir(&data_dir(app)?);
+    if !is_inside(&inbox, path) {
+        return Ok(());
+    }
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(CommandError::io(
+            format_args!("删除中转文件失败 ({})", path.display()),
+            err,
+        )),
+    }
+}
+
+/// 启动时清理遗留的中转副本。
+pub fn clean_stale(app: &AppHandle) {
+    let Ok(data_dir) = data_dir(app) else {
+        return;
+    };
+    let removed = remove_older_than(&inbox_dir(&data_dir), STALE_AFTER);
+    if removed > 0 {
+        runtime::shell_log(app, &format!("[INFO] 已清理 {removed} 个遗留的中转文件"));
+    }
+}
+
+fn stage_into(data_dir: &Path, path: &Path) -> Result<PathBuf, String> {
+    let metadata = fs::metadata(path).map_err(|e| read_error(path, &e))?;
+    if !metadata.is_file() {
+        return Err(format!("{} 不是文件", path.display()));
+    }
+    if is_inside(data_dir, path) {
+        return Ok(path.to_path_buf());
+    }
+    if metadata.len() > MAX_FILE_BYTES {
+        return Err(format!(
+            "{} 超过 {} MB，未导入",
+            path.display(),
+            MAX_FILE_BYTES / 1024 / 1024
+        ));
+    }
+
+    // 先确认可读，给出带路径的权限提示，而不是让后端报一个含糊的错误
+    let content = fs::read(path).map_err(|e| read_error(path, &e))?;
+    let inbox = inbox_dir(data_dir);
+    fs::create_dir_all(&inbox)
+        .map_err(|e| format!("创建中转目录失败 ({}): {e}", inbox.display()))?;
+    let target = inbox.join(unique_name(path));
+    fsutil::atomic_write(&target, content)
+        .map_err(|e| format!("写入中转文件失败 ({}): {e}", target.display()))?;
+    Ok(target)
+}
+
+fn read_error(path: &Path, err: &std::io::Error) -> String {
+    match err.kind() {
+        ErrorKind::PermissionDenied => format!(
+            "没有权限读取 {}。文件可能属于其他用户，或位于沙盒应用（如 snap 版浏览器）的私有目录，请将其移到主目录或下载目录后重试",
+            path.display()
+        ),
+        ErrorKind::NotFound => format!("文件不存在: {}", path.display()),
+        _ => format!("读取 {} 失败: {err}", path.display()),
+    }
+}
+
+/// 时间戳 + 进程内序号 + 原文件名，避免同名文件互相覆盖。
+fn unique_name(path: &Path) -> String {
+    let stamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let original: String = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_control() || "\\/:*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("{stamp}-{sequence}-{original}")
+}
+
+fn is_inside(dir: &Path, path: &Path) -> bool {
+    match (dir.canonicalize(), path.canonicalize()) {
+        (Ok(dir), Ok(path)) => path.starts_with(dir),
+        _ => path.starts_with(dir),
+    }
+}
+
+fn remove_older_than(dir: &Path, age: Duration) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let now = SystemTime::now();
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|elapsed| elapsed >= age)
+        })
+        .filter(|entry| fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    datadir::active_profile(app).map(|profile| profile.root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn outside_files_are_copied_with_unique_names() {
+        let root = temp_dir("copy");
+        let data_dir = root.join("data");
+        let downloads = root.join("Downloads");
+        fs::create_dir_all(&downloads).unwrap();
+        let source = downloads.join("[站点] Movie.torrent");
+        fs::write(&source, b"d8:announce0:e").unwrap();
+
+        let first = stage_into(&data_dir, &source).unwrap();
+        let second = stage_into(&data_dir, &source).unwrap();
+        assert_ne!(first, second);
+        assert!(first.starts_with(inbox_dir(&data_dir)));
+        assert!(first.to_string_lossy().ends_with("[站点] Movie.torrent"));
+        assert_eq!(fs::read(&first).unwrap(), b"d8:announce0:e");
+        assert!(source.exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn files_inside_data_dir_are_passed_through() {
+        let data_dir = temp_dir("inside");
+        let file = data_dir.join("torrents").join("a.torrent");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, b"x").unwrap();
+        assert_eq!(stage_into(&data_dir, &file).unwrap(), file);
+        assert!(!inbox_dir(&data_dir).exists());
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn unreadable_or_missing_files_name_the_path() {
+        let root = temp_dir("missing");
+        let missing = root.join("gone.torrent");
+        let err = stage_into(&root.join("data"), &missing).unwrap_err();
+        assert!(err.contains("gone.torrent"));
+
+        let dir = root.join("folder.torrent");
+        fs::create_dir_all(&dir).unwrap();
+        assert!(stage_into(&root.join("data"), &dir)
+            .unwrap_err()
+            .contains("不是文件"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn stale_copies_are_removed() {
+        let dir = temp_dir("stale");
+        fs::write(dir.join("old.torrent"), b"x").unwrap();
+        assert_eq!(remove_older_than(&dir, Duration::from_secs(3600)), 0);
+        assert_eq!(remove_older_than(&dir, Duration::ZERO), 1);
+        assert!(!dir.join("old.torrent").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
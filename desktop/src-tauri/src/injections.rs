@@ -0,0 +1,164 @@
+//! 依赖 WebUI 页面结构的注入脚本与 WebUI 版本的对应关系。
+//!
+//! 数据库配置按钮按设置页的 DOM 结构插入，启动遮罩靠首屏判断（firstpaint.rs）的选择器决定何时移除；
+//! WebUI 更新改了页面结构后，这些选择器会悄悄失效甚至误判。每个脚本记录最后一次核对选择器时的
+//! WebUI 版本，经配置对象的 versionGate 传给脚本（见 pagescripts.rs），由脚本记到
+//! `window.__PTNEXUS_INJECTIONS__`。每次向运行时页面注入前读取正在运行的 WebUI 版本
+//! （updater 维护的本地 CHANGELOG.json，即更新检查返回的 local_version），
+//! 比核对时的版本新就不再注入，并在运行记录中告警（每个版本一次）；读不到版本时照常注入。
+//! 当前生效的注入见 `window.__PTNEXUS_DESKTOP__.injections`，未列出的功能可以由 WebUI 自行实现。
+
+use std::fs;
+use std::sync::Mutex;
+
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+
+use crate::journal::{self, Severity};
+use crate::{runtime, script};
+
+pub struct Injection {
+    pub id: &'static str,
+    /// 最后一次核对选择器时的 WebUI 版本。
+    pub verified_with: &'static str,
+}
+
+/// 设置页中的“打开数据库配置目录”按钮。
+pub const DB_CONFIG_BUTTON: Injection = Injection {
+    id: "db-config-button",
+    verified_with: "v3.6.1",
+};
+/// 启动遮罩及其渲染超时提示。
+pub const STARTUP_OVERLAY: Injection = Injection {
+    id: "startup-overlay",
+    verified_with: "v3.6.1",
+};
+const DOM_DEPENDENT: [&Injection; 2] = [&DB_CONFIG_BUTTON, &STARTUP_OVERLAY];
+
+#[derive(Default)]
+pub struct InjectionState {
+    /// 已告警过的 WebUI 版本。
+    warned_for: Mutex<Option<String>>,
+}
+
+/// 按正在运行的 WebUI 版本筛选出仍然注入的脚本。
+pub fn active(app: &AppHandle) -> Vec<&'static str> {
+    let version = webui_version(app);
+    let (active, stale) = partition(version.as_deref());
+    if let (Some(version), false) = (version, stale.is_empty()) {
+        warn_once(app, version, &stale);
+    }
+    active
+}
+
+/// 所有可能注入的脚本 id，供测试核对 `active` 与 desktopinfo.rs 的结果。
+#[cfg(test)]
+pub fn known() -> Vec<&'static str> {
+    DOM_DEPENDENT.iter().map(|injection| injection.id).collect()
+}
+
+/// 更新 `window.__PTNEXUS_DESKTOP__.injections` 返回的列表（见 desktopinfo.rs）。
+pub fn publish_script(active: &[&str]) -> String {
+    script::call_with_args(
+        "window.__PTNEXUS_ACTIVE_INJECTIONS__ = Object.freeze(active);",
+        &[("active", serde_json::Value::from(active.to_vec()))],
+    )
+}
+
+fn partition(version: Option<&str>) -> (Vec<&'static str>, Vec<&'static Injection>) {
+    let (stale, active): (Vec<&'static Injection>, Vec<&'static Injection>) =
+        DOM_DEPENDENT.into_iter().partition(|injection| {
+            version.is_some_and(|version| is_newer(version, injection.verified_with))
+        });
+    (active.iter().map(|injection| injection.id).collect(), stale)
+}
+
+fn warn_once(app: &AppHandle, version: String, stale: &[&Injection]) {
+    let Some(state) = app.try_state::<InjectionState>() else {
+        return;
+    };
+    let Ok(mut warned_for) = state.warned_for.lock() else {
+        return;
+    };
+    if warned_for.as_deref() == Some(version.as_str()) {
+        return;
+    }
+    let ids: Vec<&str> = stale.iter().map(|injection| injection.id).collect();
+    journal::record(
+        app,
+        Severity::Warn,
+        None,
+        format!(
+            "WebUI 已更新到 {version}，{} 的页面选择器只核对到 {}，为避免误判已停止注入",
+            ids.join("、"),
+            stale[0].verified_with
+        ),
+    );
+    *warned_for = Some(version);
+}
+
+/// 正在运行的 WebUI 版本，读不到 CHANGELOG.json 时为 None。
+pub fn webui_version(app: &AppHandle) -> Option<String> {
+    let content = fs::read_to_string(runtime::local_changelog_path(app)?).ok()?;
+    changelog_version(&content)
+}
+
+#[derive(Deserialize)]
+struct Changelog {
+    history: Vec<ChangelogEntry>,
+}
+
+#[derive(Deserialize)]
+struct ChangelogEntry {
+    version: String,
+}
+
+fn changelog_version(content: &str) -> Option<String> {
+    let changelog: Changelog = serde_json::from_str(content).ok()?;
+    changelog
+        .history
+        .into_iter()
+        .next()
+        .map(|entry| entry.version)
+}
+
+/// 形如 v3.6.1 的版本号比较；任一方无法解析时不视为更新。
+pub(crate) fn is_newer(running: &str, verified: &str) -> bool {
+    match (parse_version(running), parse_version(verified)) {
+        (Some(running), Some(verified)) => running > verified,
+        _ => false,
+    }
+}
+
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let core = version.split(['-', '+']).next()?;
+    core.split('.').map(|part| part.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newer_webui_versions_disable_dom_injections() {
+        assert_eq!(partition(None).0, known());
+        assert_eq!(partition(Some("v3.6.1")).0, known());
+        assert_eq!(partition(Some("v3.5.9")).0, known());
+        assert_eq!(partition(Some("unknown")).0, known());
+        let (active, stale) = partition(Some("v3.7.0"));
+        assert!(active.is_empty());
+        assert_eq!(stale.len(), 2);
+        assert!(is_newer("v3.6.10", "v3.6.9"));
+        assert!(!is_newer("3.6.1-beta", "v3.6.1"));
+    }
+
+    #[test]
+    fn version_comes_from_the_first_changelog_entry() {
+        let content =
+            r#"{"history": [{"version": "v3.6.1", "changes": []}, {"version": "v3.6.0"}]}"#;
+        assert_eq!(changelog_version(content).as_deref(), Some("v3.6.1"));
+        assert_eq!(changelog_version(r#"{"history": []}"#), None);
+        assert_eq!(changelog_version("not json"), None);
+    }
+}
@@ -0,0 +1,237 @@
+//! 运行记录：启动、崩溃、重启、停止等生命周期事件的时间线，比原始日志更易读。
+//!
+//! 内存中保留最近的若干条供界面查询，同时由后台线程追加写入 `<data_dir>/runtime-events.jsonl`，
+//! 文件超过上限后轮转为 `runtime-events.1.jsonl`。记录事件只是向通道发送消息，不阻塞调用方。
+//! 启动失败时 RuntimeManager 并不存在，所以记录单独托管在 Tauri state 中。
+
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::erroraction::{self, ErrorAction};
+use crate::events::{self, Replay};
+use crate::runtime::format_utc_timestamp;
+
+const EVENTS_FILE: &str = "runtime-events.jsonl";
+const ROTATED_FILE: &str = "runtime-events.1.jsonl";
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const MEMORY_CAPACITY: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RuntimeEvent {
+    /// UTC 时间，如 2024-01-01T09:02:00Z。
+    pub timestamp: String,
+    /// Unix 毫秒时间戳，便于按时间过滤。
+    pub unix_ms: u64,
+    pub severity: Severity,
+    pub service: Option<String>,
+    pub message: String,
+    /// 错误事件可以直接执行的操作（见 erroraction.rs）。
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<ErrorAction>,
+}
+
+pub struct EventJournal {
+    entries: Mutex<VecDeque<RuntimeEvent>>,
+    writer: Mutex<Sender<RuntimeEvent>>,
+}
+
+impl EventJournal {
+    /// 载入上次保留的记录，并启动写入线程。
+    pub fn open(data_dir: &Path) -> Self {
+        let path = data_dir.join(EVENTS_FILE);
+        let entries = load_recent(&path);
+
+        let (tx, rx) = mpsc::channel::<RuntimeEvent>();
+        let dir = data_dir.to_path_buf();
+        thread::spawn(move || {
+            for event in rx {
+                append(&dir, &event);
+            }
+        });
+
+        Self {
+            entries: Mutex::new(entries),
+            writer: Mutex::new(tx),
+        }
+    }
+
+    pub fn record(
+        &self,
+        severity: Severity,
+        service: Option<&str>,
+        message: impl Into<String>,
+    ) -> RuntimeEvent {
+        let now = SystemTime::now();
+        let event = RuntimeEvent {
+            timestamp: format_utc_timestamp(now),
+            unix_ms: now
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            severity,
+            service: service.map(str::to_string),
+            message: message.into(),
+            actions: erroraction::for_event(severity, service),
+        };
+
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() >= MEMORY_CAPACITY {
+                entries.pop_front();
+            }
+            entries.push_back(event.clone());
+        }
+        if let Ok(writer) = self.writer.lock() {
+            let _ = writer.send(event.clone());
+        }
+        event
+    }
+
+    /// 按时间先后返回最近的 `limit` 条，`since` 为 Unix 毫秒时间戳（不含）。
+    pub fn query(&self, limit: usize, since: Option<u64>) -> Vec<RuntimeEvent> {
+        let Ok(entries) = self.entries.lock() else {
+            return Vec::new();
+        };
+        let matched: Vec<&RuntimeEvent> = entries
+            .iter()
+            .filter(|event| since.is_none_or(|since| event.unix_ms > since))
+            .collect();
+        let skip = matched.len().saturating_sub(limit);
+        matched.into_iter().skip(skip).cloned().collect()
+    }
+}
+
+/// 记录一条事件并以 `runtime-event` 推送给诊断页；journal 尚未初始化时忽略。
+pub fn record(
+    app: &AppHandle,
+    severity: Severity,
+    service: Option<&str>,
+    message: impl Into<String>,
+) {
+    if let Some(journal) = app.try_state::<EventJournal>() {
+        let event = journal.record(severity, service, message);
+        events::emit(app, "runtime-event", Replay::All, &event);
+    }
+}
+
+pub fn query(app: &AppHandle, limit: usize, since: Option<u64>) -> Vec<RuntimeEvent> {
+    app.try_state::<EventJournal>()
+        .map(|journal| journal.query(limit, since))
+        .unwrap_or_default()
+}
+
+/// 多行错误只取第一行放进时间线，详情仍以日志为准。
+pub fn first_line(text: &str) -> &str {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("")
+}
+
+fn load_recent(path: &Path) -> VecDeque<RuntimeEvent> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return VecDeque::new();
+    };
+    let mut entries: VecDeque<RuntimeEvent> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    while entries.len() > MEMORY_CAPACITY {
+        entries.pop_front();
+    }
+    entries
+}
+
+fn append(dir: &Path, event: &RuntimeEvent) {
+    let path = dir.join(EVENTS_FILE);
+    if fs::metadata(&path)
+        .map(|m| m.len() >= MAX_FILE_BYTES)
+        .unwrap_or(false)
+    {
+        let _ = fs::rename(&path, rotated_path(dir));
+    }
+    let Ok(line) = serde_json::to_string(event) else {
+        return;
+    };
+    let _ = fs::create_dir_all(dir);
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "{line}");
+    }
+}
+
+fn rotated_path(dir: &Path) -> PathBuf {
+    dir.join(ROTATED_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn query_returns_latest_entries_in_order() {
+        let dir = temp_dir("query");
+        let journal = EventJournal::open(&dir);
+        for i in 0..5 {
+            journal.record(Severity::Info, None, format!("event {i}"));
+        }
+        let latest: Vec<String> = journal
+            .query(2, None)
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+        assert_eq!(latest, vec!["event 3", "event 4"]);
+    }
+
+    #[test]
+    fn memory_is_bounded() {
+        let dir = temp_dir("bounded");
+        let journal = EventJournal::open(&dir);
+        for i in 0..(MEMORY_CAPACITY + 10) {
+            journal.record(Severity::Warn, Some("batch"), format!("event {i}"));
+        }
+        let all = journal.query(usize::MAX, None);
+        assert_eq!(all.len(), MEMORY_CAPACITY);
+        assert_eq!(all[0].message, "event 10");
+    }
+
+    #[test]
+    fn persisted_events_are_reloaded() {
+        let dir = temp_dir("reload");
+        let event = RuntimeEvent {
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            unix_ms: 1_704_067_200_000,
+            severity: Severity::Error,
+            service: Some("server".to_string()),
+            message: "崩溃".to_string(),
+            actions: erroraction::for_event(Severity::Error, Some("server")),
+        };
+        append(&dir, &event);
+        append(&dir, &event);
+
+        let journal = EventJournal::open(&dir);
+        let reloaded = journal.query(10, None);
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!(
+            reloaded[0].actions,
+            vec![ErrorAction::OpenLog("server".to_string())]
+        );
+        assert!(journal.query(10, Some(1_704_067_200_000)).is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+//! 开发调试用的服务启动参数。
+//!
+//! runtime.env 中的 `PTNEXUS_EXTRA_ARGS_<服务>` 追加到该服务的启动参数之后（如 server 的 `-X dev`），
+//! `PTNEXUS_WRAPPER_<服务>` 作为前缀包装整条命令（如 `gdb --args`、`dlv exec --`）。服务名取大写：
+//! SERVER、BATCH、UPDATER、BACKGROUND_RUNNER。
+//!
+//! 取值按 shell 的习惯拆分：空白分隔，单引号内原样保留，双引号内可以用 `\"` 和 `\\`。引号外的反斜杠只转义
+//! 空白、引号和反斜杠本身，Windows 路径中的 `\` 不必写两遍。配置了包装命令时就绪等待的超时延长为
+//! [`WRAPPER_TIMEOUT_FACTOR`] 倍，调试器下启动较慢不会被当作启动失败。每次启动的完整命令写入 shell.log，
+//! 启动干跑计划中也会列出，引号写错时可以对照排查。
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::services::ServiceSpec;
+
+pub const EXTRA_ARGS_PREFIX: &str = "PTNEXUS_EXTRA_ARGS_";
+pub const WRAPPER_PREFIX: &str = "PTNEXUS_WRAPPER_";
+pub const WRAPPER_TIMEOUT_FACTOR: u32 = 10;
+
+/// 启动干跑计划中的一条启动命令。
+#[derive(Serialize)]
+pub struct LaunchCommand {
+    pub service: String,
+    pub argv: Vec<String>,
+    pub wrapped: bool,
+}
+
+/// 按 runtime.env 追加参数、套上包装命令。
+pub fn apply(spec: &mut ServiceSpec, envs: &HashMap<String, String>) -> Result<(), String> {
+    let extra = setting(envs, EXTRA_ARGS_PREFIX, &spec.name)?;
+    spec.args.extend(extra);
+
+    let wrapper = setting(envs, WRAPPER_PREFIX, &spec.name)?;
+    if let Some((program, rest)) = wrapper.split_first() {
+        let mut args = rest.to_vec();
+        args.push(spec.program.to_string_lossy().to_string());
+        args.append(&mut spec.args);
+        spec.program = PathBuf::from(program);
+        spec.args = args;
+        spec.readiness = spec.readiness.clone().stretched(WRAPPER_TIMEOUT_FACTOR);
+    }
+    Ok(())
+}
+
+fn is_wrapped(name: &str, envs: &HashMap<String, String>) -> bool {
+    envs.get(&key(WRAPPER_PREFIX, name))
+        .is_some_and(|value| !value.trim().is_empty())
+}
+
+pub fn describe(spec: &ServiceSpec, envs: &HashMap<String, String>) -> LaunchCommand {
+    LaunchCommand {
+        service: spec.name.clone(),
+        argv: argv(spec),
+        wrapped: is_wrapped(&spec.name, envs),
+    }
+}
+
+fn argv(spec: &ServiceSpec) -> Vec<String> {
+    std::iter::once(spec.program.to_string_lossy().to_string())
+        .chain(spec.args.iter().cloned())
+        .collect()
+}
+
+/// 完整的启动命令，必要时给参数加上引号，按 [`split`] 拆分后与实际参数一致。
+pub fn render(spec: &ServiceSpec) -> String {
+    argv(spec)
+        .iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn key(prefix: &str, name: &str) -> String {
+    format!("{prefix}{}", name.to_ascii_uppercase())
+}
+
+fn setting(
+    envs: &HashMap<String, String>,
+    prefix: &str,
+    name: &str,
+) -> Result<Vec<String>, String> {
+    let key = key(prefix, name);
+    match envs.get(&key) {
+        Some(value) => split(value).map_err(|e| format!("runtime.env 中 {key} 无法解析：{e}")),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// 按 shell 的习惯把一行拆成参数，规则见模块说明。
+pub fn split(input: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    // 用 `""` 写出的空参数也要保留
+    let mut in_word = false;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err("单引号没有闭合".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\')) => {
+                            current.extend(chars.next());
+                        }
+                        Some(c) => current.push(c),
+                        None => return Err("双引号没有闭合".to_string()),
+                    }
+                }
+            }
+            '\\' if chars
+                .peek()
+                .is_some_and(|next| next.is_whitespace() || matches!(next, '\'' | '"' | '\\')) =>
+            {
+                in_word = true;
+                current.extend(chars.next());
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    Ok(words)
+}
+
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && !arg
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '\'' | '"'));
+    if plain {
+        return arg.to_string();
+    }
+    if !arg.contains('\'') {
+        return format!("'{arg}'");
+    }
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::Readiness;
+    use std::time::Duration;
+
+    #[test]
+    fn splits_like_a_shell() {
+        assert_eq!(split("  -X   dev ").unwrap(), ["-X", "dev"]);
+        assert_eq!(
+            split(r#"--name "a b" 'c "d"' e\ f """#).unwrap(),
+            ["--name", "a b", r#"c "d""#, "e f", ""]
+        );
+        assert_eq!(
+            split(r#"C:\tools\gdb.exe "say \"hi\"" x\\y"#).unwrap(),
+            [r"C:\tools\gdb.exe", r#"say "hi""#, r"x\y"]
+        );
+        assert_eq!(split("pre'fix'post").unwrap(), ["prefixpost"]);
+        assert!(split("'open").is_err());
+        assert!(split("\"open").is_err());
+        assert!(split("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rendered_commands_split_back_to_the_same_argv() {
+        for arg in ["plain", "two words", "it's", r#"say "hi""#, r"C:\x y\z", ""] {
+            assert_eq!(split(&quote(arg)).unwrap(), [arg], "{arg}");
+        }
+    }
+
+    #[test]
+    fn wrapper_and_extra_args_build_the_final_command() {
+        let mut spec = ServiceSpec {
+            name: "server".to_string(),
+            program: PathBuf::from("/opt/python"),
+            args: vec!["-u".to_string(), "app.py".to_string()],
+            workdir: PathBuf::from("/opt"),
+            readiness: Readiness::Http {
+                host: "127.0.0.1".to_string(),
+                port: 5275,
+                timeout: Duration::from_secs(60),
+            },
+            depends_on: Vec::new(),
+            required: true,
+        };
+        let envs = HashMap::from([
+            (
+                "PTNEXUS_EXTRA_ARGS_SERVER".to_string(),
+                "-X dev".to_string(),
+            ),
+            (
+                "PTNEXUS_WRAPPER_SERVER".to_string(),
+                "gdb --args".to_string(),
+            ),
+            ("PTNEXUS_EXTRA_ARGS_BATCH".to_string(), "'bad".to_string()),
+        ]);
+        apply(&mut spec, &envs).unwrap();
+        assert_eq!(render(&spec), "gdb --args /opt/python -u app.py -X dev");
+        assert!(matches!(
+            spec.readiness,
+            Readiness::Http { timeout, .. } if timeout == Duration::from_secs(600)
+        ));
+        assert!(describe(&spec, &envs).wrapped);
+
+        spec.name = "batch".to_string();
+        let err = apply(&mut spec, &envs).unwrap_err();
+        assert!(err.contains("PTNEXUS_EXTRA_ARGS_BATCH"), "{err}");
+    }
+}
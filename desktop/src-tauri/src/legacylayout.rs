@@ -0,0 +1,249 @@
+//! 旧版 `_up_/runtime` 安装布局的提醒与迁移。
+//!
+//! 早期安装包把服务放在 `<安装目录>/_up_/runtime/{server,batch,updater}`，现在直接放在
+//! `<安装目录>/{server,batch,updater}`。启动成功后解析到旧布局时，每个应用版本最多提醒一次：写入
+//! shell.log，发出 `legacy-runtime-layout` 事件与系统通知，提醒过的版本记在应用数据目录的
+//! `legacy-layout-notified` 中。
+//!
+//! `migrate_runtime_layout()` 停止全部服务后把 `_up_/runtime` 中的内容逐项移到安装目录下，按启动所需文件
+//! 与各组件入口文件的哈希校验，任何一步失败都移回原处；随后运行目录的解析直接使用新位置，重新启动服务。
+//! 安装目录不可写（如安装在 Program Files 且没有管理员权限）时不做迁移，提示重新安装最新版本。
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::{dirpicker, fsutil, runtime, snapshot};
+
+const NOTICE_FILE: &str = "legacy-layout-notified";
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LegacyLayout {
+    pub runtime_root: String,
+    /// 迁移后的运行目录（安装目录）。
+    pub target: String,
+    /// 安装目录可写，可以直接迁移；否则需要重新安装。
+    pub writable: bool,
+}
+
+/// `<安装目录>/_up_/runtime` 应迁往的安装目录；不是旧布局时为 None。
+pub fn flat_target(runtime_root: &Path) -> Option<PathBuf> {
+    let up = runtime_root.parent()?;
+    let is_legacy = runtime_root
+        .file_name()
+        .is_some_and(|name| name == "runtime")
+        && up.file_name().is_some_and(|name| name == "_up_");
+    if is_legacy {
+        up.parent().map(Path::to_path_buf)
+    } else {
+        None
+    }
+}
+
+/// 启动成功后调用：运行目录仍是旧布局时提醒（每个版本一次）。
+pub fn check(app: &AppHandle) {
+    let Ok(runtime_root) = runtime::resolve_runtime_root(app) else {
+        return;
+    };
+    let Some(target) = flat_target(&runtime_root) else {
+        return;
+    };
+    let Ok(data_dir) = app.path().app_data_dir() else {
+        return;
+    };
+    let version = app.package_info().version.to_string();
+    if !should_remind(&data_dir, &version) {
+        return;
+    }
+
+    let layout = LegacyLayout {
+        runtime_root: runtime_root.to_string_lossy().to_string(),
+        target: target.to_string_lossy().to_string(),
+        writable: dirpicker::probe_writable(&target),
+    };
+    runtime::shell_log(
+        app,
+        &format!(
+            "[WARN] 运行目录使用旧的 _up_/runtime 布局: {}，可迁移到 {}",
+            layout.runtime_root, layout.target
+        ),
+    );
+    events::emit(app, "legacy-runtime-layout", Replay::Latest, &layout);
+    let body = if layout.writable {
+        "安装目录仍使用旧的目录结构，后续版本将不再支持。可以在设置中一键迁移，迁移期间服务会短暂停止。"
+    } else {
+        "安装目录仍使用旧的目录结构，后续版本将不再支持。安装目录不可写，请下载最新安装包重新安装（数据不受影响）。"
+    };
+    let _ = app
+        .notification()
+        .builder()
+        .title("PT Nexus 安装目录需要迁移")
+        .body(body)
+        .show();
+    let _ = fsutil::atomic_write(&data_dir.join(NOTICE_FILE), &version);
+}
+
+fn should_remind(data_dir: &Path, version: &str) -> bool {
+    fs::read_to_string(data_dir.join(NOTICE_FILE))
+        .map(|notified| notified.trim() != version)
+        .unwrap_or(true)
+}
+
+/// 迁移并让之后的解析使用新位置；由 `migrate_runtime_layout` 在服务停止后调用。
+pub fn apply(app: &AppHandle, runtime_root: &Path, target: &Path) -> Result<(), String> {
+    match migrate(runtime_root, target) {
+        Ok(()) => {
+            runtime::remember_runtime_root(target);
+            let message = format!(
+                "运行目录已从 {} 迁移到 {}",
+                runtime_root.display(),
+                target.display()
+            );
+            runtime::shell_log(app, &format!("[INFO] {message}"));
+            journal::record(app, Severity::Info, None, message);
+            Ok(())
+        }
+        Err(err) => {
+            runtime::shell_log(app, &format!("[ERROR] 迁移运行目录失败: {err}"));
+            journal::record(
+                app,
+                Severity::Error,
+                None,
+                format!("迁移运行目录失败: {}", journal::first_line(&err)),
+            );
+            Err(err)
+        }
+    }
+}
+
+/// 把 `legacy_root` 中的全部内容移到 `target`，校验后删除空的旧目录；任何一步失败都把已移动的移回。
+fn migrate(legacy_root: &Path, target: &Path) -> Result<(), String> {
+    let names: Vec<_> = fs::read_dir(legacy_root)
+        .map_err(|e| format!("读取 {} 失败: {e}", legacy_root.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.file_name()))
+        .collect();
+    if let Some(name) = names.iter().find(|name| target.join(name).exists()) {
+        return Err(format!("{} 已存在，无法迁移", target.join(name).display()));
+    }
+
+    let before = snapshot::hash_components(legacy_root);
+    let mut moved = Vec::new();
+    for name in &names {
+        let (from, to) = (legacy_root.join(name), target.join(name));
+        if let Err(err) = fs::rename(&from, &to) {
+            restore(legacy_root, target, &moved);
+            return Err(format!("移动 {} 失败: {err}", from.display()));
+        }
+        moved.push(name);
+    }
+
+    let verified = runtime::check_required_files(target, None)
+        .map_err(|err| err.to_string())
+        .and_then(|_| {
+            (snapshot::hash_components(target) == before)
+                .then_some(())
+                .ok_or_else(|| "组件文件的哈希与迁移前不一致".to_string())
+        });
+    if let Err(err) = verified {
+        restore(legacy_root, target, &moved);
+        return Err(format!("迁移后校验失败，已移回原处: {err}"));
+    }
+
+    // 只删除空目录：`_up_` 中可能还有 CHANGELOG.json 等文件
+    let _ = fs::remove_dir(legacy_root);
+    if let Some(up) = legacy_root.parent() {
+        let _ = fs::remove_dir(up);
+    }
+    Ok(())
+}
+
+fn restore(legacy_root: &Path, target: &Path, moved: &[&OsString]) {
+    for name in moved {
+        let _ = fs::rename(target.join(name), legacy_root.join(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    fn write_runtime(root: &Path) {
+        for (relative, content) in [
+            (
+                format!("updater/{}", runtime::exe_name("updater")),
+                "updater",
+            ),
+            (format!("batch/{}", runtime::exe_name("batch")), "batch"),
+            ("server/dist/index.html".to_string(), "<html>"),
+            ("server/configs/global_mappings.yaml".to_string(), "{}"),
+            ("server/sites_data.json".to_string(), "[]"),
+            ("server/app.py".to_string(), "print()"),
+            ("server/background_runner.py".to_string(), "print()"),
+        ] {
+            let path = root.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    #[test]
+    fn only_up_runtime_is_legacy() {
+        let install = Path::new("/opt/pt-nexus");
+        assert_eq!(
+            flat_target(&install.join("_up_").join("runtime")),
+            Some(install.to_path_buf())
+        );
+        assert_eq!(flat_target(install), None);
+        assert_eq!(flat_target(&install.join("runtime")), None);
+        assert_eq!(flat_target(&install.join("_up_")), None);
+    }
+
+    #[test]
+    fn legacy_runtime_is_moved_next_to_the_exe() {
+        let install = temp_dir("migrate");
+        let legacy = install.join("_up_").join("runtime");
+        write_runtime(&legacy);
+        fs::write(install.join("_up_").join("CHANGELOG.json"), "[]").unwrap();
+
+        migrate(&legacy, &install).unwrap();
+        assert!(runtime::check_required_files(&install, None).is_ok());
+        assert!(!legacy.exists());
+        // 旧目录中剩下的文件保留原处
+        assert!(install.join("_up_").join("CHANGELOG.json").is_file());
+        let _ = fs::remove_dir_all(&install);
+    }
+
+    #[test]
+    fn conflicting_install_dir_is_left_untouched() {
+        let install = temp_dir("conflict");
+        let legacy = install.join("_up_").join("runtime");
+        write_runtime(&legacy);
+        fs::create_dir_all(install.join("server")).unwrap();
+
+        let err = migrate(&legacy, &install).unwrap_err();
+        assert!(
+            err.contains(&install.join("server").display().to_string()),
+            "{err}"
+        );
+        assert!(runtime::check_required_files(&legacy, None).is_ok());
+        assert!(!install.join("batch").exists());
+        let _ = fs::remove_dir_all(&install);
+    }
+
+    #[test]
+    fn reminder_is_shown_once_per_version() {
+        let dir = temp_dir("notice");
+        assert!(should_remind(&dir, "1.2.0"));
+        fs::write(dir.join(NOTICE_FILE), "1.2.0\n").unwrap();
+        assert!(!should_remind(&dir, "1.2.0"));
+        assert!(should_remind(&dir, "1.3.0"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
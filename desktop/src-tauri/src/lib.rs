@@ -1,100 +1,1204 @@
+mod activity;
+mod assetcache;
+mod asyncrt;
+mod badge;
+mod batchstatus;
+mod bdinfo;
+mod bindings;
+mod browsercookies;
+mod cabundle;
+mod capture;
+mod clock;
+mod commanderror;
+mod configguard;
+mod crashdumps;
+mod database;
+mod datadir;
+mod dataexport;
+mod datamove;
+mod desktopinfo;
+mod devtools;
+mod diagnostics;
+mod dirpicker;
+mod discovery;
+mod diskwatch;
+mod display;
+mod erroraction;
+mod events;
+mod firstpaint;
+mod footprint;
+mod fsutil;
+mod gpu;
+mod hangwatch;
+mod hardrefresh;
+mod health;
+mod healthz;
+mod inbox;
+mod injections;
+mod journal;
+mod launchargs;
+mod legacylayout;
+mod localhttp;
+mod loglevel;
+mod logs;
+mod memwatch;
+mod migration;
+mod onboarding;
+mod ondemand;
+mod pagescripts;
+mod pathgrant;
+mod paths;
+mod pdfexport;
+mod portconfig;
+mod power;
+mod pyfailure;
+mod pyruntime;
+mod quarantine;
+mod recycle;
+mod relaunch;
+mod renderwatch;
+mod rollback;
 mod runtime;
+mod safemode;
+mod screenshot;
+mod script;
+mod selftest;
+mod servicemode;
+mod services;
+mod settings;
+mod settingsexport;
+mod snapshot;
+mod status;
+mod tasks;
+mod timings;
+mod trayhost;
+mod trayicon;
+mod traystats;
+mod updatecache;
+mod updatepage;
+mod watchdog;
+mod webhook;
+mod webuicache;
+mod webuierrors;
+mod webviewprofile;
 
+use commanderror::CommandError;
+use erroraction::ErrorAction;
+use renderwatch::RenderWatch;
 use runtime::RuntimeManager;
+use serde::Serialize;
 use tauri::{
-    menu::{Menu, MenuItem},
+    menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, Submenu},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Manager, RunEvent,
+    AppHandle, Manager, RunEvent, WebviewWindowBuilder, Wry,
 };
+use watchdog::RendererWatchdog;
 
+/// 托盘“切换配置”子菜单中配置档菜单项 id 的前缀。
+const PROFILE_MENU_PREFIX: &str = "profile:";
+
+/// 托盘中的“切换配置”子菜单，切换后据此更新勾选状态。
+struct ProfileMenu(Submenu<Wry>);
+
+/// 旧版 WebUI 的握手：服务启动失败或已停止时返回 `failed` / `stopped`，其余状态返回 `pong`。
+/// 旧版界面只认 `pong`，其他返回值会显示异常横幅；新代码应使用 `shell_status`（见 status.rs）。
+#[tauri::command]
+fn ping(app_handle: AppHandle) -> &'static str {
+    match status::snapshot(&app_handle).runtime_state {
+        status::RuntimeState::Failed => "failed",
+        status::RuntimeState::Stopped => "stopped",
+        _ => "pong",
+    }
+}
+
+/// WebUI 判断是否显示“桌面端后台服务异常”横幅所用的状态握手。
+#[tauri::command]
+fn shell_status(app_handle: AppHandle) -> status::ShellStatus {
+    status::snapshot(&app_handle)
+}
+
+/// 界面注册事件监听后调用，取回 `last_seen_seq` 之后错过的状态事件（见 events.rs）。
+#[tauri::command]
+fn sync_runtime_events(app_handle: AppHandle, last_seen_seq: u64) -> events::EventSync {
+    events::sync(&app_handle, last_seen_seq)
+}
+
+/// 全部错误码及其含义（见 commanderror.rs），WebUI 遇到不认识的错误码时可据此显示说明。
 #[tauri::command]
-fn ping() -> &'static str {
-    "pong"
+fn list_error_codes() -> Vec<commanderror::CodeInfo> {
+    commanderror::codes()
 }
 
 /// 供前端 JS 调用，用系统默认浏览器打开外部链接
 #[tauri::command]
-fn open_external(url: String) {
-    let _ = open_url_in_browser(&url);
+fn open_external(url: String) -> Result<(), CommandError> {
+    open_url_in_browser(&url).map_err(|e| CommandError::io("打开链接失败", e))
+}
+
+/// 打开当前配置档的数据目录，返回实际打开的路径。
+#[tauri::command]
+fn open_app_data_dir(app_handle: AppHandle) -> Result<String, CommandError> {
+    open_dir(app_handle, "data".to_string())
+}
+
+/// 打开当前配置档下的目录：data、logs、backups、exports、tmp 或 updates，不存在时先创建，返回实际打开的路径。
+#[tauri::command]
+fn open_dir(app_handle: AppHandle, kind: String) -> Result<String, CommandError> {
+    let kind = datadir::DirKind::parse(&kind).map_err(CommandError::invalid)?;
+    let profile = datadir::active_profile(&app_handle)?;
+    open_existing_dir(profile.dir(kind), kind.label())
+}
+
+/// 打开指定配置档的根目录（不必是当前配置档），返回实际打开的路径。
+#[tauri::command]
+fn open_profile_dir(app_handle: AppHandle, name: String) -> Result<String, CommandError> {
+    datadir::validate_profile_name(&name).map_err(CommandError::invalid)?;
+    let profile = datadir::profile(&app_handle, &name)
+        .map_err(|message| CommandError::new(commanderror::NOT_FOUND, message))?;
+    open_existing_dir(&profile.root, "配置档目录")
+}
+
+fn open_existing_dir(dir: &std::path::Path, label: &str) -> Result<String, CommandError> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| CommandError::io(format_args!("创建{label}失败 ({})", dir.display()), e))?;
+    open_path_in_file_manager(dir)
+        .map_err(|e| CommandError::io(format_args!("打开{label}失败 ({})", dir.display()), e))?;
+    Ok(dir.to_string_lossy().to_string())
+}
+
+/// 注入脚本定时上报的页面心跳，供渲染进程看门狗判断 WebView 是否存活。
+#[tauri::command]
+fn webview_heartbeat(watchdog: tauri::State<'_, RendererWatchdog>) {
+    watchdog.heartbeat();
+}
+
+/// 启动遮罩确认登录页或主界面已渲染。
+#[tauri::command]
+fn webui_render_ready(watch: tauri::State<'_, RenderWatch>) {
+    watch.mark_ready();
+}
+
+/// 登录页或主界面首次渲染完成，显示启动时隐藏的主窗口。
+#[tauri::command]
+fn webui_first_paint(app_handle: AppHandle) {
+    firstpaint::reveal(&app_handle);
+}
+
+/// 启动页、诊断页与 WebUI 中错误提示上的操作按钮。
+#[tauri::command(async)]
+fn invoke_error_action(
+    app_handle: AppHandle,
+    id: String,
+    context: Option<String>,
+) -> Result<(), CommandError> {
+    let action = ErrorAction::parse(&id, context).map_err(CommandError::invalid)?;
+    run_error_action(&app_handle, action)
+}
+
+/// 清理 WebView 缓存后重新加载 WebUI；需要等待主线程回调，不能在主线程上执行。
+#[tauri::command(async)]
+fn hard_refresh(app_handle: AppHandle) -> Result<(), CommandError> {
+    let window = app_handle
+        .get_webview_window("main")
+        .ok_or_else(|| CommandError::not_ready("主窗口不存在"))?;
+    hardrefresh::hard_refresh(&window).map_err(CommandError::from)
+}
+
+/// 主窗口 WebView 数据（localStorage、缓存）所在的目录，供诊断信息显示。
+#[tauri::command]
+fn get_webview_profile_path(app_handle: AppHandle) -> Option<String> {
+    webviewprofile::profile_path(&app_handle).map(|path| path.to_string_lossy().to_string())
+}
+
+/// 用系统默认文本编辑器打开 runtime.env；文件不存在时先按启动流程从模板生成。
+#[tauri::command(async)]
+fn open_runtime_env_in_editor(app_handle: AppHandle) -> Result<(), CommandError> {
+    let env_path = runtime::ensure_runtime_env_file(&app_handle)?;
+
+    open_file_in_editor(&env_path).map_err(|e| {
+        CommandError::io(
+            format_args!("打开 runtime.env 失败 ({})", env_path.display()),
+            e,
+        )
+    })
+}
+
+/// 离线提示页的“重试”：WebUI 首页可以打开时返回运行时页面，否则把原因返回给页面显示。
+#[tauri::command(async)]
+fn reconnect(app_handle: AppHandle) -> Result<(), CommandError> {
+    if let Err(err) = runtime::probe_runtime_page(&runtime::runtime_url(&app_handle)) {
+        return Err(CommandError::new(
+            commanderror::BACKEND_UNAVAILABLE,
+            format!("仍无法打开 WebUI：{err}\n请查看日志或重启应用。"),
+        ));
+    }
+
+    if let Some(window) = app_handle.get_webview_window("main") {
+        runtime::reload_runtime_page(&window);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn open_logs_dir(app_handle: AppHandle) -> Result<String, CommandError> {
+    open_dir(app_handle, "logs".to_string())
+}
+
+/// 检查目标数据库是否可连接并登录（迁移向导与数据库设置共用）。
+#[tauri::command(async)]
+fn test_database_connection(
+    app_handle: AppHandle,
+    target: migration::DatabaseTarget,
+) -> Result<(), CommandError> {
+    migration::test_connection(&app_handle, &target)
+        .map(|_| ())
+        .map_err(CommandError::from)
+}
+
+/// 立即向 Webhook 发送一次测试报告；`url` 为空时使用 runtime.env 中的 PTNEXUS_WEBHOOK_URL。
+#[tauri::command(async)]
+fn test_webhook(app_handle: AppHandle, url: Option<String>) -> Result<(), CommandError> {
+    webhook::test(&app_handle, url.as_deref()).map_err(CommandError::from)
+}
+
+/// 把 SQLite 数据库移动到新的目录（runtime.env 中的 PTNEXUS_DB_DIR），期间停止全部服务，完成后重启。
+/// 期间主窗口显示启动页，进度同时通过 `migration-progress` 事件推送；中途退出时下次启动会先继续移动。
+#[tauri::command(async)]
+fn move_data_dir(
+    app_handle: AppHandle,
+    runtime: tauri::State<'_, RuntimeManager>,
+    target: String,
+) -> Result<(), CommandError> {
+    datamove::start(&app_handle, &runtime, &target).map_err(CommandError::from)
+}
+
+/// 取消正在进行的数据目录移动，撤销已做的改动并按原目录重启服务；没有进行中的移动时返回 false。
+#[tauri::command]
+fn cancel_data_dir_move() -> bool {
+    datamove::cancel()
+}
+
+/// 把当前 SQLite 数据迁移到 MySQL/PostgreSQL，成功后切换 runtime.env 并重启服务。
+/// 目标库校验通过后主窗口切换到启动页显示进度（同时发出 `database-migration-progress` 事件），结果用原生对话框告知。
+#[tauri::command(async)]
+fn migrate_database(
+    app_handle: AppHandle,
+    runtime: tauri::State<'_, RuntimeManager>,
+    target: migration::DatabaseTarget,
+) -> Result<(), CommandError> {
+    migration::migrate(&app_handle, &runtime, &target).map_err(CommandError::from)
+}
+
+/// BDInfo 是否存在且能运行，供“为什么 BDInfo 扫描失败”自查。
+#[tauri::command(async)]
+fn get_bdinfo_status(app_handle: AppHandle) -> bdinfo::BdinfoStatus {
+    bdinfo::status(&app_handle)
+}
+
+/// 校验并保存自定义 BDInfo 路径（写入 runtime.env，重启后生效）。
+#[tauri::command(async)]
+fn set_bdinfo_path(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<bdinfo::BdinfoStatus, CommandError> {
+    bdinfo::set_path(&app_handle, &path).map_err(CommandError::from)
+}
+
+#[tauri::command(async)]
+fn run_self_test(app_handle: AppHandle) -> Vec<selftest::SelfTestItem> {
+    selftest::run(&app_handle)
+}
+
+#[derive(Serialize)]
+struct EffectiveTimezone {
+    timezone: Option<String>,
+    system_timezone: Option<String>,
+    overridden: bool,
+}
+
+/// 后端定时任务实际使用的时区（子进程的 PTNEXUS_TZ，IANA 名称），以及系统检测到的时区。
+#[tauri::command]
+fn get_effective_timezone(app_handle: AppHandle) -> EffectiveTimezone {
+    let system_timezone = runtime::detect_system_timezone();
+    let timezone = app_handle
+        .try_state::<RuntimeManager>()
+        .and_then(|rt| rt.context())
+        .and_then(|ctx| ctx.common_env.get("PTNEXUS_TZ").cloned())
+        .or_else(|| runtime::read_runtime_setting(&app_handle, "PTNEXUS_TZ"))
+        .or_else(|| runtime::read_runtime_setting(&app_handle, "TZ").filter(|tz| tz.contains('/')))
+        .or_else(|| system_timezone.clone());
+
+    EffectiveTimezone {
+        overridden: timezone != system_timezone,
+        timezone,
+        system_timezone,
+    }
+}
+
+/// 设置后台任务失败数角标（None 清除），窗口获得焦点后自动清除。
+#[tauri::command]
+fn set_badge(app_handle: AppHandle, count: Option<u32>) {
+    badge::set_failed_tasks(&app_handle, count);
+}
+
+/// 调整界面缩放，`per_monitor` 为 true 时只记录到当前显示器。
+#[tauri::command]
+fn set_ui_scale(
+    app_handle: AppHandle,
+    factor: f64,
+    per_monitor: Option<bool>,
+) -> Result<(), CommandError> {
+    display::set_ui_scale(&app_handle, factor, per_monitor.unwrap_or(false))
+        .map_err(CommandError::from)
+}
+
+/// 截取主窗口并复制到剪贴板，返回截图文件路径。
+#[tauri::command(async)]
+fn capture_window_screenshot(app_handle: AppHandle) -> Result<String, CommandError> {
+    screenshot::capture_main_window(&app_handle)
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(CommandError::from)
+}
+
+/// 导出页面为 PDF：`route` 为空时导出当前页面，否则在后台窗口中加载该路由后导出。
+/// `orientation` 默认 portrait，`paper_size` 默认 A4。
+#[tauri::command(async)]
+fn print_to_pdf(
+    app_handle: AppHandle,
+    route: Option<String>,
+    orientation: Option<String>,
+    paper_size: Option<String>,
+) -> Result<pdfexport::PrintResult, CommandError> {
+    let orientation =
+        pdfexport::Orientation::parse(orientation.as_deref()).map_err(CommandError::invalid)?;
+    let paper = pdfexport::paper_size(paper_size.as_deref()).map_err(CommandError::invalid)?;
+    pdfexport::print_to_pdf(&app_handle, route, orientation, paper).map_err(CommandError::from)
+}
+
+/// 把外部文件交给后端前先放入中转目录，返回后端可读取的路径。
+#[tauri::command(async)]
+fn stage_inbox_file(app_handle: AppHandle, path: String) -> Result<String, CommandError> {
+    inbox::stage(&app_handle, std::path::Path::new(&path))
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(CommandError::from)
+}
+
+/// 后端确认导入后删除中转副本。
+#[tauri::command]
+fn release_inbox_file(app_handle: AppHandle, path: String) -> Result<(), CommandError> {
+    inbox::release(&app_handle, std::path::Path::new(&path))
+}
+
+#[tauri::command]
+fn open_log_viewer(app_handle: AppHandle) -> Result<(), CommandError> {
+    logs::open_viewer(&app_handle).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn list_log_sources(app_handle: AppHandle) -> Result<Vec<logs::LogSource>, CommandError> {
+    logs::list_sources(&app_handle).map_err(CommandError::from)
+}
+
+#[tauri::command(async)]
+fn read_log_tail(
+    app_handle: AppHandle,
+    source: String,
+    lines: Option<usize>,
+) -> Result<Vec<String>, CommandError> {
+    logs::tail(&app_handle, &source, lines.unwrap_or(500)).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn start_log_stream(app_handle: AppHandle, source: String) -> Result<u64, CommandError> {
+    logs::start_stream(&app_handle, &source).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn stop_log_stream(app_handle: AppHandle, stream_id: u64) {
+    logs::stop_stream(&app_handle, stream_id);
+}
+
+/// WebUI 请求重启整个应用：只发出 `relaunch-requested` 事件，用户在 WebUI 中确认后才会重启。
+#[tauri::command]
+fn relaunch_app(app_handle: AppHandle, reason: String) -> Result<(), CommandError> {
+    relaunch::request(&app_handle, &reason).map_err(CommandError::from)
+}
+
+/// 回应 `relaunch-requested`：确认时停止服务并以相同的命令行参数启动新进程。
+#[tauri::command(async)]
+fn confirm_relaunch(
+    app_handle: AppHandle,
+    token: String,
+    confirmed: bool,
+) -> Result<(), CommandError> {
+    relaunch::confirm(&app_handle, &token, confirmed).map_err(CommandError::from)
+}
+
+/// 全部配置档及当前使用的配置档。
+#[tauri::command]
+fn list_profiles(app_handle: AppHandle) -> Result<Vec<datadir::ProfileInfo>, CommandError> {
+    datadir::list_profiles(&app_handle).map_err(CommandError::from)
+}
+
+/// 不重启应用切换到另一个配置档（不存在时创建），进度通过 `bootstrap-stage` 事件推送。
+#[tauri::command(async)]
+fn switch_profile(app_handle: AppHandle, name: String) -> Result<(), CommandError> {
+    datadir::validate_profile_name(&name).map_err(CommandError::invalid)?;
+    let switched = match app_handle.try_state::<RuntimeManager>() {
+        Some(manager) => manager
+            .switch_profile(&app_handle, &name)
+            .map_err(CommandError::from),
+        // 启动失败、运行时尚未托管：切换后重新执行启动流程，结果通过启动页展示
+        None => datadir::set_active_profile(&app_handle, &name)
+            .map_err(CommandError::from)
+            .and_then(|_| retry_bootstrap(app_handle.clone())),
+    };
+    sync_profile_menu(&app_handle);
+    switched
+}
+
+/// 确认后清除运行文件的隔离标记（macOS quarantine 属性、Windows Zone.Identifier），返回是否已清除。
+#[tauri::command(async)]
+fn remove_quarantine(app_handle: AppHandle) -> Result<bool, CommandError> {
+    quarantine::remove_with_confirmation(&app_handle).map_err(CommandError::from)
+}
+
+/// 数据目录 exports/ 中的关键数据导出及定时导出的配置与状态。
+#[tauri::command]
+fn list_data_exports(app_handle: AppHandle) -> dataexport::DataExportStatus {
+    dataexport::status(&app_handle)
+}
+
+/// 立即导出一次关键数据，不受开关与间隔限制。
+#[tauri::command(async)]
+fn run_data_export_now(app_handle: AppHandle) -> Result<dataexport::DataExportFile, CommandError> {
+    dataexport::run_now(&app_handle).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn copy_text_to_clipboard(text: String) -> Result<(), CommandError> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(|e| CommandError::from(format!("复制到剪贴板失败: {e}")))
+}
+
+/// 通过保存对话框把文本写入文件，返回保存路径；用户取消时返回 None。
+#[tauri::command(async)]
+fn export_text_file(
+    app_handle: AppHandle,
+    content: String,
+    file_name: Option<String>,
+) -> Result<Option<String>, CommandError> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let Some(path) = app_handle
+        .dialog()
+        .file()
+        .set_file_name(file_name.unwrap_or_else(|| "ptnexus.log".to_string()))
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = path
+        .into_path()
+        .map_err(|e| CommandError::invalid(format!("无法使用所选路径: {e}")))?;
+    fsutil::atomic_write(&path, content)
+        .map_err(|e| CommandError::io(format_args!("导出失败 ({})", path.display()), e))?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// 经桌面壳下载并缓存外部小图片（如站点图标），返回 `data:` URL。
+#[tauri::command(async)]
+fn fetch_external_asset(app_handle: AppHandle, url: String) -> Result<String, CommandError> {
+    assetcache::fetch(&app_handle, &url).map_err(CommandError::from)
+}
+
+#[derive(Serialize)]
+struct RuntimeStatus {
+    running: bool,
+    runtime_url: String,
+    bindings: Vec<bindings::ServiceBinding>,
+}
+
+/// 后端运行状态：WebUI 地址与启动时观察到的各服务监听地址。
+#[tauri::command]
+fn runtime_status(app_handle: AppHandle) -> RuntimeStatus {
+    let context = app_handle
+        .try_state::<RuntimeManager>()
+        .and_then(|rt| rt.context());
+    RuntimeStatus {
+        running: context.is_some(),
+        runtime_url: runtime::runtime_url(&app_handle).to_string(),
+        bindings: context.map(|ctx| ctx.bindings).unwrap_or_default(),
+    }
+}
+
+/// 最近的运行记录，按时间先后排列；`since` 为 Unix 毫秒时间戳，只返回其后的记录。
+#[tauri::command]
+fn get_runtime_events(
+    app_handle: AppHandle,
+    limit: Option<usize>,
+    since: Option<u64>,
+) -> Vec<journal::RuntimeEvent> {
+    journal::query(&app_handle, limit.unwrap_or(200), since)
+}
+
+/// 打开或关闭调用方窗口的开发者工具，返回操作后是否打开；未启用或平台不支持时返回错误。
+#[tauri::command]
+fn toggle_devtools(
+    app_handle: AppHandle,
+    window: tauri::WebviewWindow,
+) -> Result<bool, CommandError> {
+    devtools::toggle(&app_handle, &window).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn report_webui_error(
+    app_handle: AppHandle,
+    error: webuierrors::WebuiError,
+) -> Result<(), CommandError> {
+    webuierrors::report(&app_handle, error).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_webui_errors(
+    app_handle: AppHandle,
+) -> Result<Vec<webuierrors::WebuiErrorEntry>, CommandError> {
+    webuierrors::recent(&app_handle).map_err(CommandError::from)
+}
+
+/// 临时调整 Python 服务的日志级别，`minutes`（默认 60）分钟后恢复为 runtime.env 中配置的级别。
+#[tauri::command]
+fn set_backend_log_level(
+    app_handle: AppHandle,
+    service: String,
+    level: String,
+    minutes: Option<u32>,
+) -> Result<loglevel::BackendLogLevel, CommandError> {
+    loglevel::set(&app_handle, &service, &level, minutes).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_backend_log_levels(app_handle: AppHandle) -> Vec<loglevel::BackendLogLevel> {
+    loglevel::list(&app_handle)
+}
+
+#[tauri::command]
+fn open_diagnostics(app_handle: AppHandle) -> Result<(), CommandError> {
+    diagnostics::open_window(&app_handle).map_err(CommandError::from)
+}
+
+/// 把日志、运行记录、自检结果与隐去密码的 runtime.env 打包为 zip，返回保存路径。
+#[tauri::command(async)]
+fn export_diagnostics(app_handle: AppHandle) -> Result<String, CommandError> {
+    let path = diagnostics::export_bundle(&app_handle)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// 桌面壳当前仍在运行的后台任务（监测循环、延时注入等），供诊断查看。
+#[tauri::command]
+fn get_background_tasks(app_handle: AppHandle) -> Vec<tasks::TaskInfo> {
+    tasks::list(&app_handle)
+}
+
+/// batch 的任务队列摘要；缓存超过 5 秒时当场查询。
+#[tauri::command(async)]
+fn get_batch_summary(app_handle: AppHandle) -> batchstatus::BatchSummary {
+    batchstatus::summary(&app_handle)
+}
+
+/// 开始接收 `batch-summary` 事件，返回订阅 ID；不再需要时调用 `unwatch_batch_summary`。
+#[tauri::command]
+fn watch_batch_summary(app_handle: AppHandle) -> u64 {
+    batchstatus::watch(&app_handle)
+}
+
+#[tauri::command]
+fn unwatch_batch_summary(app_handle: AppHandle, watch_id: u64) {
+    batchstatus::unwatch(&app_handle, watch_id);
+}
+
+/// 重新打开初始设置向导，预填当前取值。
+#[tauri::command]
+fn run_setup_wizard(app_handle: AppHandle) -> Result<(), CommandError> {
+    onboarding::open_window(&app_handle, false).map_err(CommandError::from)
+}
+
+/// 设置向导的步骤状态与当前取值（不含数据库密码）。
+#[tauri::command(async)]
+fn get_setup_state(app_handle: AppHandle) -> onboarding::SetupState {
+    onboarding::state(&app_handle)
+}
+
+/// 列出保存设置后会发生的改动及是否需要重启服务。
+#[tauri::command(async)]
+fn preview_setup(
+    app_handle: AppHandle,
+    submission: onboarding::SetupSubmission,
+) -> Vec<onboarding::SetupChange> {
+    onboarding::preview(&app_handle, &submission)
+}
+
+/// 保存设置：检查数据库连接，写入 runtime.env，必要时移动数据目录或重启服务。
+#[tauri::command(async)]
+fn apply_setup(
+    app_handle: AppHandle,
+    submission: onboarding::SetupSubmission,
+) -> Result<Vec<onboarding::SetupChange>, CommandError> {
+    onboarding::apply(&app_handle, &submission).map_err(CommandError::from)
+}
+
+/// 跳过设置向导中的步骤，之后可从托盘重新运行。
+#[tauri::command(async)]
+fn skip_setup(app_handle: AppHandle, steps: Vec<String>) -> Result<(), CommandError> {
+    onboarding::skip(&app_handle, &steps).map_err(CommandError::from)
+}
+
+/// 当前节能档位与空闲时长。
+#[tauri::command]
+fn get_power_profile(app_handle: AppHandle) -> power::PowerStatus {
+    power::status(&app_handle)
+}
+
+/// 在文件管理器中定位文件（截图、日志等）。
+#[tauri::command]
+fn reveal_in_folder(path: String) -> Result<(), CommandError> {
+    reveal_path_in_file_manager(std::path::Path::new(&path))
+        .map_err(|e| CommandError::io("打开文件所在目录失败", e))
 }
 
 #[tauri::command]
-fn open_app_data_dir(app_handle: AppHandle) -> Result<(), String> {
-    let data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("解析应用数据目录失败: {e}"))?;
+fn get_setting(
+    store: tauri::State<'_, settings::SettingsStore>,
+    key: String,
+) -> Result<serde_json::Value, CommandError> {
+    store.get_value(&key).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn set_setting(
+    store: tauri::State<'_, settings::SettingsStore>,
+    key: String,
+    value: serde_json::Value,
+) -> Result<(), CommandError> {
+    store.set_value(&key, value).map_err(CommandError::from)
+}
+
+/// 导出桌面壳配置（设置、窗口状态、runtime.env 非敏感项），返回保存路径；取消时返回 None。
+#[tauri::command(async)]
+fn export_desktop_settings(app_handle: AppHandle) -> Result<Option<String>, CommandError> {
+    settingsexport::export(&app_handle)
+        .map(|path| path.map(|p| p.to_string_lossy().to_string()))
+        .map_err(CommandError::from)
+}
+
+/// 从导出文件导入桌面壳配置，返回已应用与已跳过的键；runtime.env 的修改需重启后生效。
+#[tauri::command(async)]
+fn import_desktop_settings(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<settingsexport::ImportReport, CommandError> {
+    settingsexport::import(&app_handle, std::path::Path::new(&path))
+}
+
+/// 最近几次启动的各阶段耗时，供关于页面展示。
+#[tauri::command]
+fn get_last_startup_timings(
+    app_handle: AppHandle,
+) -> Result<Vec<timings::StartupRecord>, CommandError> {
+    timings::last_startup_timings(&app_handle).map_err(CommandError::from)
+}
+
+/// 干跑启动流程：返回运行目录与最终环境变量（敏感值已隐藏），不启动任何进程。
+#[tauri::command(async)]
+fn get_bootstrap_plan(app_handle: AppHandle) -> Result<runtime::BootstrapPlan, CommandError> {
+    runtime::bootstrap_plan(&app_handle).map_err(CommandError::from)
+}
+
+/// 开关 WebView 硬件加速：写入 runtime.env 的 PTNEXUS_DISABLE_GPU，并询问是否立即重启。
+#[tauri::command]
+fn set_gpu_acceleration(app_handle: AppHandle, enabled: bool) -> Result<(), CommandError> {
+    gpu::set_gpu_acceleration(&app_handle, enabled).map_err(CommandError::from)
+}
 
-    std::fs::create_dir_all(&data_dir).map_err(|e| format!("创建应用数据目录失败: {e}"))?;
+/// 下次启动使用的 server / batch / updater 端口。
+#[tauri::command]
+fn get_port_config(app_handle: AppHandle) -> portconfig::PortConfig {
+    portconfig::current(&app_handle)
+}
+
+/// 逐项校验端口设置（范围、重复、是否被占用），供设置界面在输入时显示提示。
+#[tauri::command(async)]
+fn validate_port_config(
+    app_handle: AppHandle,
+    config: portconfig::PortConfig,
+) -> portconfig::PortProblems {
+    portconfig::validate(&app_handle, &config)
+}
+
+/// 保存端口设置并同步跨服务地址；返回是否需要重启服务。
+#[tauri::command(async)]
+fn set_port_config(
+    app_handle: AppHandle,
+    config: portconfig::PortConfig,
+) -> Result<bool, CommandError> {
+    portconfig::save(&app_handle, &config).map_err(CommandError::from)
+}
+
+/// 按当前 runtime.env 重启全部后端服务，主窗口随后加载新的 WebUI 地址。
+#[tauri::command(async)]
+fn restart_services(app_handle: AppHandle) -> Result<(), CommandError> {
+    let runtime = app_handle
+        .try_state::<RuntimeManager>()
+        .ok_or_else(|| CommandError::not_ready("运行时尚未启动"))?;
+    runtime.restart(&app_handle).map_err(CommandError::from)
+}
+
+/// 退出安全模式：停止服务后去掉 `--safe-mode` 重新启动应用（见 safemode.rs）。
+#[tauri::command(async)]
+fn exit_safe_mode(app_handle: AppHandle) -> Result<(), CommandError> {
+    if !safemode::is_active(&app_handle) {
+        return Err(CommandError::new(
+            commanderror::INVALID_ARGUMENT,
+            "当前不在安全模式",
+        ));
+    }
+    relaunch_without_safe_mode(&app_handle).map_err(CommandError::from)
+}
+
+/// 把旧版 `_up_/runtime` 布局的运行目录迁移到安装目录下（见 legacylayout.rs），期间停止全部服务，
+/// 完成后重新启动；返回新的运行目录。安装目录不可写时拒绝，提示重新安装。
+#[tauri::command(async)]
+fn migrate_runtime_layout(app_handle: AppHandle) -> Result<String, CommandError> {
+    let runtime_root = runtime::resolve_runtime_root(&app_handle)?;
+    let Some(target) = legacylayout::flat_target(&runtime_root) else {
+        return Err(CommandError::invalid("运行目录已经是新的布局，无需迁移"));
+    };
+    if !dirpicker::probe_writable(&target) {
+        return Err(CommandError::new(
+            commanderror::PERMISSION_DENIED,
+            format!(
+                "安装目录 {} 不可写，无法迁移。请下载最新安装包重新安装，数据目录不受影响",
+                target.display()
+            ),
+        ));
+    }
+    datamove::ensure_idle()?;
+    let migrate = || legacylayout::apply(&app_handle, &runtime_root, &target);
+    match app_handle.try_state::<RuntimeManager>() {
+        Some(manager) => manager.restart_with(&app_handle, migrate)?,
+        // 启动失败、服务未运行：迁移后重新执行启动流程
+        None => {
+            migrate()?;
+            retry_bootstrap(app_handle.clone())?;
+        }
+    }
+    Ok(target.to_string_lossy().to_string())
+}
 
-    open_path_in_file_manager(&data_dir)
-        .map_err(|e| format!("打开应用数据目录失败: {e}"))
+/// 停止服务并换回更新前保存的版本，重新启动并等待就绪；没有回滚副本时拒绝。
+#[tauri::command(async)]
+fn rollback_component(app_handle: AppHandle, name: String) -> Result<(), CommandError> {
+    rollback::rollback_component(&app_handle, &name).map_err(CommandError::from)
+}
+
+/// 数据目录各项的占用（字节），回滚副本单独列出。
+#[tauri::command(async)]
+fn get_data_dir_usage(app_handle: AppHandle) -> Result<Vec<datadir::DirUsage>, CommandError> {
+    Ok(datadir::usage(&datadir::active_profile(&app_handle)?))
+}
+
+/// 申请读取数据目录以外的目录：弹窗询问用户，返回是否允许；已授权的目录直接返回 true。
+#[tauri::command(async)]
+fn request_path_access(
+    app_handle: AppHandle,
+    path: String,
+    purpose: String,
+) -> Result<bool, CommandError> {
+    pathgrant::request(&app_handle, &path, &purpose).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn list_path_grants(app_handle: AppHandle) -> Vec<pathgrant::PathGrant> {
+    pathgrant::list(&app_handle)
+}
+
+/// 撤销目录授权，发出 `path-access-revoked` 事件。
+#[tauri::command]
+fn revoke_path_access(app_handle: AppHandle, path: String) -> Result<(), CommandError> {
+    pathgrant::revoke(&app_handle, &path).map_err(CommandError::from)
+}
+
+/// 为 WebUI 的目录设置弹出系统文件夹选择框，可在所选目录下新建子文件夹；用户取消时返回 None。
+#[tauri::command(async)]
+fn pick_directory_for(
+    app_handle: AppHandle,
+    setting_key: String,
+    new_subfolder: Option<String>,
+) -> Result<Option<dirpicker::PickedDirectory>, CommandError> {
+    dirpicker::pick(&app_handle, &setting_key, new_subfolder.as_deref()).map_err(CommandError::from)
+}
+
+/// 征得用户同意后从本机浏览器读取站点的 Cookie，交给 WebUI 填入站点设置；用户取消时返回 None。
+#[tauri::command(async)]
+fn import_cookies_from_browser(
+    app_handle: AppHandle,
+    browser: browsercookies::Browser,
+    domain: String,
+) -> Result<Option<Vec<browsercookies::BrowserCookie>>, CommandError> {
+    browsercookies::import(&app_handle, browser, &domain)
+}
+
+/// 删除全部回滚副本，返回释放的字节数。
+#[tauri::command(async)]
+fn clear_rollback_copies(app_handle: AppHandle) -> Result<u64, CommandError> {
+    rollback::clear(&datadir::active_profile(&app_handle)?.rollback_dir)
+}
+
+/// 运行目录各组件的占用，以及可以删除的可选组件与停用方式。
+#[tauri::command(async)]
+fn analyze_runtime_footprint(
+    app_handle: AppHandle,
+) -> Result<footprint::FootprintReport, CommandError> {
+    footprint::analyze(&app_handle).map_err(CommandError::from)
+}
+
+/// 确认后删除可选组件（目前只有打包的 BDInfo），返回是否已删除；必需组件拒绝删除。
+#[tauri::command(async)]
+fn remove_optional_component(app_handle: AppHandle, name: String) -> Result<bool, CommandError> {
+    footprint::remove_optional(&app_handle, &name).map_err(CommandError::from)
+}
+
+/// 清理更新器留下的旧版本文件，保留当前检出与回滚副本，返回释放的字节数；更新器正在工作时拒绝。
+#[tauri::command(async)]
+fn clean_update_cache(app_handle: AppHandle) -> Result<u64, CommandError> {
+    updatecache::clean(&app_handle).map_err(CommandError::from)
+}
+
+#[derive(Serialize)]
+struct SystemInfo {
+    app_version: String,
+    os: &'static str,
+    arch: &'static str,
+    data_dir: Option<String>,
+    gpu_acceleration: bool,
+    /// 最近一次测得的时钟偏差（毫秒），未开启或尚未测得时为 None。
+    clock_skew_ms: Option<i64>,
+    /// runtime.env 中配置的企业根证书路径（不含内容）。
+    extra_ca_bundle: Option<String>,
+    /// 崩溃转储目录中的文件名（不含内容），新的在前。
+    crash_dumps: Vec<String>,
+    /// 主窗口 WebView 数据所在的目录。
+    webview_profile: Option<String>,
+    /// 系统托盘是否可用；不可用时关闭主窗口会退出应用。
+    tray: trayhost::TrayAvailability,
+    /// 开发者工具是否可用及其原因。
+    devtools: devtools::DevtoolsStatus,
+}
+
+/// 桌面壳自身的环境信息，供关于页与问题反馈使用。
+#[tauri::command]
+fn get_system_info(app_handle: AppHandle) -> SystemInfo {
+    SystemInfo {
+        app_version: app_handle.package_info().version.to_string(),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        data_dir: datadir::active_profile(&app_handle)
+            .ok()
+            .map(|profile| profile.root.to_string_lossy().to_string()),
+        gpu_acceleration: !gpu::is_gpu_disabled(&app_handle),
+        clock_skew_ms: clock::last_skew(&app_handle).map(|skew| skew.offset_ms),
+        extra_ca_bundle: app_handle
+            .try_state::<RuntimeManager>()
+            .and_then(|rt| rt.context())
+            .and_then(|ctx| cabundle::configured_path(&ctx.common_env))
+            .map(|path| path.to_string_lossy().to_string()),
+        crash_dumps: datadir::active_profile(&app_handle)
+            .map(|profile| crashdumps::list(&profile.logs_dir))
+            .unwrap_or_default(),
+        webview_profile: get_webview_profile_path(app_handle.clone()),
+        tray: trayhost::availability(&app_handle),
+        devtools: devtools::status(&app_handle),
+    }
 }
 
 pub fn run() {
+    if let Some(code) = healthz::run_cli() {
+        std::process::exit(code);
+    }
+    // 须在创建 Tauri 运行时与任何线程之前设置环境变量
+    gpu::apply_process_env();
+
     tauri::Builder::default()
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .register_uri_scheme_protocol(webuicache::SCHEME, webuicache::handle)
         .setup(|app| {
             let handle = app.handle().clone();
+            // 最先托管，之后发出的事件才有序号、可以补发
+            app.manage(events::EventLog::default());
+            app.manage(tasks::TaskRegistry::default());
+            if let Err(err) = asyncrt::init(app) {
+                runtime::shell_log(&handle, &format!("[WARN] {err}"));
+            }
+            let async_app = handle.clone();
+            asyncrt::install(Box::new(move |name, run| {
+                asyncrt::spawn_tracked(
+                    &async_app,
+                    &name,
+                    tasks::Scope::Runtime,
+                    move |_, token| run(token),
+                );
+            }));
+            // 须在读取桌面设置之前确定，按住 Shift 的检测也要尽早
+            safemode::init(app);
+            let (spawn_app, sink_app) = (handle.clone(), handle.clone());
+            capture::install(capture::Hooks {
+                spawn: Box::new(move |name, run| {
+                    tasks::spawn(&spawn_app, &name, tasks::Scope::Output, move |_, token| {
+                        run(token)
+                    });
+                }),
+                sink: Box::new(move |service, stream, lines| {
+                    logs::publish(&sink_app, service, stream, lines)
+                }),
+            });
 
-            // ── 系统托盘 ──
-            let show_i = MenuItem::with_id(app, "show", "显示主界面", true, None::<&str>)?;
-            let quit_i = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show_i, &quit_i])?;
-
-            TrayIconBuilder::new()
-                .icon(app.default_window_icon().unwrap().clone())
-                .tooltip("PT Nexus")
-                .menu(&menu)
-                .show_menu_on_left_click(false)
-                .on_menu_event(|app, event| match event.id.as_ref() {
-                    "show" => {
-                        if let Some(w) = app.get_webview_window("main") {
-                            let _ = w.show();
-                            let _ = w.unminimize();
-                            let _ = w.set_focus();
-                        }
-                    }
-                    "quit" => {
+            // ── 清理上次写入中断遗留的临时文件 ──
+            let mut tmp_dirs = Vec::new();
+            if let Ok(data_dir) = app.path().app_data_dir() {
+                tmp_dirs.push(data_dir);
+            }
+            if let Ok(profile) = datadir::active_profile(&handle) {
+                // 备份与回滚副本按类别、服务分子目录存放
+                for parent in [&profile.backups_dir, &profile.rollback_dir] {
+                    let children = std::fs::read_dir(parent)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(Result::ok);
+                    tmp_dirs.extend(
+                        children
+                            .map(|entry| entry.path())
+                            .filter(|path| path.is_dir()),
+                    );
+                }
+                tmp_dirs.extend([
+                    profile.root.join(screenshot::SCREENSHOT_DIR),
+                    profile.root,
+                    profile.logs_dir,
+                    profile.temp_dir,
+                    profile.inbox_dir,
+                    profile.update_dir,
+                    profile.exports_dir,
+                ]);
+            }
+            let removed: usize = tmp_dirs
+                .iter()
+                .map(|dir| fsutil::remove_stale_tmp(dir))
+                .sum();
+            if removed > 0 {
+                runtime::shell_log(
+                    &handle,
+                    &format!("[INFO] 已清理 {removed} 个未写完的临时文件"),
+                );
+            }
+
+            // ── 桌面设置 ──
+            if let Err(err) = settings::init(app) {
+                runtime::shell_log(&handle, &format!("[WARN] {err}"));
+            }
+
+            // ── 运行记录 ──
+            if let Ok(data_dir) = app.path().app_data_dir() {
+                app.manage(journal::EventJournal::open(&data_dir));
+            }
+            safemode::announce(&handle);
+
+            // ── 主窗口 ──
+            // tauri.conf.json 中 main 窗口 create=false，在这里创建以便按运行参数附加 WebView 启动参数。
+            app.manage(display::DisplayState::default());
+            app.manage(logs::LogStreams::default());
+            app.manage(power::PowerState::default());
+            app.manage(devtools::DevtoolsState::default());
+            app.manage(injections::InjectionState::default());
+
+            // 服务模式下不创建窗口，WebUI 通过浏览器访问。
+            let service_mode = servicemode::is_enabled(&handle);
+            if !service_mode {
+                create_main_window(&handle)?;
+                firstpaint::arm(&handle);
+                // 安全模式下没有首屏判断脚本，直接显示
+                if safemode::is_active(&handle) {
+                    firstpaint::reveal(&handle);
+                }
+            }
+
+            // ── macOS 应用菜单 ──
+            // 提供关于/隐藏/退出以及编辑、窗口菜单，使 Cmd+Q、Cmd+W、Cmd+C 等快捷键按系统习惯工作。
+            #[cfg(target_os = "macos")]
+            {
+                app.set_menu(build_macos_menu(&handle)?)?;
+                app.on_menu_event(|app, event| {
+                    if event.id.as_ref() == "macos_quit" {
                         stop_runtime(app);
                         app.exit(0);
                     }
-                    _ => {}
-                })
-                .on_tray_icon_event(|tray, event| {
-                    if let TrayIconEvent::Click {
-                        button: MouseButton::Left,
-                        button_state: MouseButtonState::Up,
-                        ..
-                    } = event
-                    {
-                        let app = tray.app_handle();
-                        if let Some(w) = app.get_webview_window("main") {
-                            let _ = w.show();
-                            let _ = w.unminimize();
-                            let _ = w.set_focus();
-                        }
-                    }
-                })
-                .build(app)?;
+                });
+            }
+
+            // ── 系统托盘 ──
+            // 没有托盘宿主（Wayland 未装 AppIndicator 扩展）或被 PTNEXUS_DISABLE_TRAY 关闭时不创建，
+            // 关闭主窗口改为退出应用（见 trayhost.rs）
+            let tray = trayhost::detect(&handle);
+            app.manage(trayhost::TrayState(tray));
+            if tray == trayhost::TrayAvailability::Available {
+                create_tray(&handle, service_mode)?;
+            } else {
+                runtime::shell_log(&handle, &format!("[WARN] {}", tray.describe()));
+                if service_mode {
+                    runtime::shell_log(
+                        &handle,
+                        "[WARN] 服务模式下没有托盘，只能通过浏览器访问 WebUI",
+                    );
+                }
+            }
 
             // ── 外部链接拦截 ──
             // 通过 runtime.rs 在页面加载后注入 JS 脚本来处理
             // （拦截 window.open / <a target="_blank"> / <a href> 等所有外部链接）
 
+            // ── 渲染进程看门狗 ──
+            app.manage(RendererWatchdog::default());
+            app.manage(RenderWatch::default());
+            app.manage(status::ShellState::default());
+            app.manage(badge::BadgeState::default());
+            app.manage(database::SqliteFallback::default());
+            app.manage(relaunch::RelaunchState::default());
+            app.manage(rollback::RecentUpdates::default());
+            app.manage(webuierrors::ErrorLimiter::default());
+            app.manage(loglevel::LogLevels::default());
+            app.manage(webhook::WebhookState::default());
+            app.manage(diskwatch::DiskWatch::default());
+            app.manage(hangwatch::HangWatch::default());
+            app.manage(batchstatus::BatchStatusCache::default());
+            watchdog::start(&handle);
+            if !safemode::is_active(&handle) {
+                webhook::start(&handle);
+            }
+            app.manage(discovery::RateLimiter::default());
+            healthz::start(&handle);
+            // 须在启动流程生成 runtime.env 之前判断是否首次启动
+            app.manage(onboarding::detect(&handle));
+
             // ── 启动后端服务 ──
-            let runtime = match RuntimeManager::bootstrap(&handle) {
-                Ok(runtime) => runtime,
-                Err(err) => {
-                    write_bootstrap_error_log(&handle, &err);
-                    show_bootstrap_error_dialog(&handle, &err);
-                    return Ok(());
+            // 按需启动时等用户打开窗口再启动（见 ondemand.rs）
+            if ondemand::is_enabled(&handle) {
+                ondemand::enter_at_launch(&handle);
+                safemode::finish_startup(&handle);
+            } else {
+                if settings::current(&handle).start_on_demand {
+                    runtime::shell_log(&handle, "[WARN] 没有托盘，无法按需启动，直接启动服务");
                 }
-            };
-
-            app.manage(runtime);
+                start_runtime(&handle);
+            }
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![ping, open_external, open_app_data_dir])
+        .invoke_handler(tauri::generate_handler![
+            ping,
+            shell_status,
+            exit_safe_mode,
+            sync_runtime_events,
+            list_error_codes,
+            open_external,
+            open_app_data_dir,
+            open_dir,
+            open_profile_dir,
+            open_runtime_env_in_editor,
+            webview_heartbeat,
+            webui_render_ready,
+            webui_first_paint,
+            invoke_error_action,
+            hard_refresh,
+            get_webview_profile_path,
+            retry_bootstrap,
+            start_with_sqlite_fallback,
+            set_gpu_acceleration,
+            get_port_config,
+            validate_port_config,
+            set_port_config,
+            restart_services,
+            rollback_component,
+            migrate_runtime_layout,
+            get_data_dir_usage,
+            clear_rollback_copies,
+            clean_update_cache,
+            analyze_runtime_footprint,
+            remove_optional_component,
+            request_path_access,
+            list_path_grants,
+            revoke_path_access,
+            pick_directory_for,
+            import_cookies_from_browser,
+            get_system_info,
+            reconnect,
+            open_logs_dir,
+            test_database_connection,
+            migrate_database,
+            move_data_dir,
+            test_webhook,
+            cancel_data_dir_move,
+            get_bdinfo_status,
+            set_bdinfo_path,
+            run_self_test,
+            get_effective_timezone,
+            get_bootstrap_plan,
+            get_last_startup_timings,
+            set_badge,
+            get_setting,
+            set_setting,
+            set_ui_scale,
+            capture_window_screenshot,
+            reveal_in_folder,
+            print_to_pdf,
+            open_log_viewer,
+            stage_inbox_file,
+            release_inbox_file,
+            list_log_sources,
+            read_log_tail,
+            start_log_stream,
+            stop_log_stream,
+            copy_text_to_clipboard,
+            list_data_exports,
+            run_data_export_now,
+            remove_quarantine,
+            list_profiles,
+            switch_profile,
+            relaunch_app,
+            confirm_relaunch,
+            export_text_file,
+            fetch_external_asset,
+            get_power_profile,
+            runtime_status,
+            get_runtime_events,
+            report_webui_error,
+            toggle_devtools,
+            get_webui_errors,
+            set_backend_log_level,
+            get_backend_log_levels,
+            open_diagnostics,
+            export_diagnostics,
+            get_background_tasks,
+            get_batch_summary,
+            watch_batch_summary,
+            unwatch_batch_summary,
+            run_setup_wizard,
+            get_setup_state,
+            preview_setup,
+            apply_setup,
+            skip_setup,
+            export_desktop_settings,
+            import_desktop_settings
+        ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
         .run(|app_handle, event| match event {
@@ -102,14 +1206,60 @@ pub fn run() {
                 event: tauri::WindowEvent::CloseRequested { api, .. },
                 label,
                 ..
-            } => {
-                if label == "main" {
-                    api.prevent_close();
+            } if label == "main" => {
+                api.prevent_close();
+                if trayhost::close_to_tray(app_handle) {
                     if let Some(w) = app_handle.get_webview_window("main") {
                         let _ = w.hide();
                     }
+                } else {
+                    stop_runtime(app_handle);
+                    app_handle.exit(0);
                 }
             }
+            RunEvent::WindowEvent {
+                event: tauri::WindowEvent::Focused(true),
+                label,
+                ..
+            } if label == "main" => {
+                power::wake(app_handle);
+                badge::on_focus(app_handle);
+            }
+            RunEvent::WindowEvent {
+                event:
+                    event @ (tauri::WindowEvent::ScaleFactorChanged { .. }
+                    | tauri::WindowEvent::Moved(_)),
+                label,
+                ..
+            } if label == "main" => {
+                if let Some(window) = app_handle.get_webview_window("main") {
+                    let dpi_changed =
+                        matches!(event, tauri::WindowEvent::ScaleFactorChanged { .. });
+                    display::on_window_moved(&window, dpi_changed);
+                }
+            }
+            RunEvent::WindowEvent {
+                event: tauri::WindowEvent::Destroyed,
+                label,
+                ..
+            } if label == logs::LOG_VIEWER_LABEL => {
+                logs::stop_all(app_handle);
+            }
+            // 点击 Dock 图标：窗口隐藏或已销毁时重新显示
+            #[cfg(target_os = "macos")]
+            RunEvent::Reopen { .. } => {
+                if servicemode::is_enabled(app_handle) {
+                    open_runtime_in_browser(app_handle);
+                } else {
+                    show_main_window(app_handle);
+                }
+            }
+            // 最后一个窗口关闭（如服务模式下关掉日志查看器）时不退出，托盘仍在运行
+            RunEvent::ExitRequested {
+                code: None, api, ..
+            } => {
+                api.prevent_exit();
+            }
             RunEvent::ExitRequested { .. } => {
                 stop_runtime(app_handle);
             }
@@ -117,6 +1267,389 @@ pub fn run() {
         });
 }
 
+/// 创建托盘图标与菜单；服务模式下菜单中没有“显示主界面”，左键直接在浏览器中打开 WebUI。
+fn create_tray(app: &AppHandle, service_mode: bool) -> tauri::Result<()> {
+    let show_i = MenuItem::with_id(app, "show", "显示主界面", true, None::<&str>)?;
+    let open_browser_i =
+        MenuItem::with_id(app, "open_in_browser", "在浏览器中打开", true, None::<&str>)?;
+    let hard_refresh_i =
+        MenuItem::with_id(app, "hard_refresh", "强制刷新界面", true, None::<&str>)?;
+    // 诊断工具只依赖桌面壳自身，启动失败、没有服务在运行时同样可用
+    let diagnostics_i = Submenu::with_items(
+        app,
+        "诊断工具",
+        true,
+        &[
+            &MenuItem::with_id(app, "log_viewer", "日志查看器", true, None::<&str>)?,
+            &MenuItem::with_id(app, "diagnostics", "诊断信息", true, None::<&str>)?,
+            &MenuItem::with_id(app, "self_test", "运行自检", true, None::<&str>)?,
+            &MenuItem::with_id(app, "export_diagnostics", "导出诊断包", true, None::<&str>)?,
+            &MenuItem::with_id(app, "retry_bootstrap", "重试启动", true, None::<&str>)?,
+        ],
+    )?;
+    let setup_i = MenuItem::with_id(app, "run_setup", "重新运行初始设置", true, None::<&str>)?;
+    let edit_env_i = MenuItem::with_id(
+        app,
+        "edit_runtime_env",
+        "编辑 runtime.env",
+        true,
+        None::<&str>,
+    )?;
+    let service_mode_i = CheckMenuItem::with_id(
+        app,
+        "toggle_service_mode",
+        "服务模式（不打开窗口）",
+        !servicemode::forced_by_flag(),
+        service_mode,
+        None::<&str>,
+    )?;
+    let profiles_i = profile_submenu(app)?;
+    let start_services_i = MenuItem::with_id(
+        app,
+        ondemand::START_MENU_ID,
+        "启动服务",
+        false,
+        None::<&str>,
+    )?;
+    // 连续点击 5 次在本次运行中启用开发者工具（见 devtools.rs）
+    let version_i = MenuItem::with_id(
+        app,
+        devtools::VERSION_MENU_ID,
+        format!("版本 {}", app.package_info().version),
+        true,
+        None::<&str>,
+    )?;
+    let exit_safe_mode_i = MenuItem::with_id(
+        app,
+        safemode::EXIT_MENU_ID,
+        "退出安全模式并重启",
+        true,
+        None::<&str>,
+    )?;
+    let quit_i = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
+    let mut items: Vec<&dyn IsMenuItem<Wry>> = if service_mode {
+        vec![&open_browser_i as &dyn IsMenuItem<Wry>]
+    } else {
+        vec![
+            &show_i as &dyn IsMenuItem<Wry>,
+            &open_browser_i,
+            &hard_refresh_i,
+        ]
+    };
+    // 按需启动时服务可能处于休眠，提供不打开窗口的启动入口
+    if ondemand::is_enabled(app) {
+        items.push(&start_services_i);
+        app.manage(ondemand::StartMenuItem(start_services_i.clone()));
+    }
+    items.extend([
+        &diagnostics_i as &dyn IsMenuItem<Wry>,
+        &setup_i,
+        &edit_env_i,
+        &profiles_i,
+        &service_mode_i,
+        &version_i,
+    ]);
+    if safemode::is_active(app) {
+        items.push(&exit_safe_mode_i);
+    }
+    items.push(&quit_i);
+    let menu = Menu::with_items(app, &items)?;
+
+    let mut tray = TrayIconBuilder::with_id(traystats::TRAY_ID);
+    if let Some(icon) = trayicon::resolve(app) {
+        tray = tray.icon(icon);
+    }
+    tray.tooltip("PT Nexus")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            "show" => show_main_window(app),
+            devtools::VERSION_MENU_ID => devtools::register_version_click(app),
+            ondemand::START_MENU_ID => {
+                ondemand::wake(app, "托盘菜单", |_| {});
+            }
+            "open_in_browser" => open_runtime_in_browser(app),
+            "hard_refresh" => {
+                tasks::spawn(app, "tray:hard-refresh", tasks::Scope::Shell, |app, _| {
+                    // 页面刚重新导航，错误用原生对话框提示
+                    if let Err(err) = hard_refresh(app.clone()) {
+                        show_native_error(&app, "PT Nexus", &err.message);
+                    }
+                });
+            }
+            "log_viewer" => {
+                if let Err(err) = logs::open_viewer(app) {
+                    show_native_error(app, "PT Nexus", &err);
+                }
+            }
+            "diagnostics" => {
+                if let Err(err) = diagnostics::open_window(app) {
+                    show_native_error(app, "PT Nexus", &err);
+                }
+            }
+            "self_test" => {
+                tasks::spawn(app, "tray:self-test", tasks::Scope::Shell, |app, _| {
+                    show_self_test_results(&app)
+                });
+            }
+            "export_diagnostics" => {
+                tasks::spawn(
+                    app,
+                    "tray:export-diagnostics",
+                    tasks::Scope::Shell,
+                    |app, _| match diagnostics::export_bundle(&app) {
+                        Ok(path) => {
+                            let dir = path.parent().unwrap_or(&path);
+                            if let Err(err) = open_path_in_file_manager(dir) {
+                                let message = format!(
+                                    "诊断包已保存到 {}\n打开目录失败: {err}",
+                                    path.display()
+                                );
+                                show_native_error(&app, "PT Nexus", &message);
+                            }
+                        }
+                        Err(err) => show_native_error(&app, "PT Nexus 导出诊断包失败", &err),
+                    },
+                );
+            }
+            "retry_bootstrap" => {
+                tasks::spawn(
+                    app,
+                    "tray:retry-bootstrap",
+                    tasks::Scope::Shell,
+                    |app, _| {
+                        // 启动失败时主窗口可能停在启动页或已关闭，统一用原生对话框提示
+                        if let Err(err) = retry_bootstrap(app.clone()) {
+                            show_native_error(&app, "PT Nexus", &err.message);
+                        }
+                    },
+                );
+            }
+            safemode::EXIT_MENU_ID => {
+                tasks::spawn(app, "tray:exit-safe-mode", tasks::Scope::Shell, |app, _| {
+                    if let Err(err) = relaunch_without_safe_mode(&app) {
+                        show_native_error(&app, "PT Nexus", &err);
+                    }
+                });
+            }
+            "toggle_service_mode" => {
+                if let Err(err) = servicemode::toggle_and_restart(app) {
+                    show_native_error(app, "PT Nexus", &err);
+                }
+            }
+            "run_setup" => {
+                if let Err(err) = onboarding::open_window(app, false) {
+                    show_native_error(app, "PT Nexus", &err);
+                }
+            }
+            "edit_runtime_env" => {
+                if let Err(err) = open_runtime_env_in_editor(app.clone()) {
+                    show_native_error(app, "PT Nexus", &err.message);
+                }
+            }
+            "quit" => {
+                stop_runtime(app);
+                app.exit(0);
+            }
+            id => {
+                let Some(name) = id.strip_prefix(PROFILE_MENU_PREFIX) else {
+                    return;
+                };
+                let name = name.to_string();
+                tasks::spawn(
+                    app,
+                    "tray:switch-profile",
+                    tasks::Scope::Shell,
+                    move |app, _| {
+                        // 切换期间主窗口显示启动页，改用原生对话框提示失败
+                        if let Err(err) = switch_profile(app.clone(), name) {
+                            show_native_error(&app, "PT Nexus 切换配置失败", &err.message);
+                        }
+                    },
+                );
+            }
+        })
+        .on_tray_icon_event(move |tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                let app = tray.app_handle();
+                if service_mode {
+                    open_runtime_in_browser(app);
+                } else {
+                    show_main_window(app);
+                }
+            }
+        })
+        .build(app)?;
+    Ok(())
+}
+
+/// “切换配置”子菜单，当前配置档处于勾选状态。
+fn profile_submenu(app: &AppHandle) -> tauri::Result<Submenu<Wry>> {
+    let submenu = Submenu::with_id(app, "profiles", "切换配置", true)?;
+    for profile in datadir::list_profiles(app).unwrap_or_default() {
+        submenu.append(&profile_menu_item(app, &profile)?)?;
+    }
+    app.manage(ProfileMenu(submenu.clone()));
+    Ok(submenu)
+}
+
+fn profile_menu_item(
+    app: &AppHandle,
+    profile: &datadir::ProfileInfo,
+) -> tauri::Result<CheckMenuItem<Wry>> {
+    CheckMenuItem::with_id(
+        app,
+        format!("{PROFILE_MENU_PREFIX}{}", profile.name),
+        &profile.name,
+        true,
+        profile.active,
+        None::<&str>,
+    )
+}
+
+/// 切换（无论成功与否）后按实际的当前配置档更新勾选状态，并补上新建的配置档。
+fn sync_profile_menu(app: &AppHandle) {
+    let (Some(menu), Ok(profiles)) = (app.try_state::<ProfileMenu>(), datadir::list_profiles(app))
+    else {
+        return;
+    };
+    for profile in profiles {
+        let id = format!("{PROFILE_MENU_PREFIX}{}", profile.name);
+        match menu.0.get(id.as_str()) {
+            Some(item) => {
+                if let Some(check) = item.as_check_menuitem() {
+                    let _ = check.set_checked(profile.active);
+                }
+            }
+            None => {
+                if let Ok(item) = profile_menu_item(app, &profile) {
+                    let _ = menu.0.append(&item);
+                }
+            }
+        }
+    }
+}
+
+fn create_main_window(app: &AppHandle) -> tauri::Result<()> {
+    let Some(mut config) = app
+        .config()
+        .app
+        .windows
+        .iter()
+        .find(|w| w.label == "main")
+        .cloned()
+    else {
+        return Ok(());
+    };
+    // 有与当前 WebUI 版本一致的缓存时先显示缓存的界面，服务就绪后再导航到运行时页面
+    if let Some(url) = webuicache::start_url(app) {
+        config.url = tauri::WebviewUrl::CustomProtocol(url);
+    }
+
+    let mut builder = WebviewWindowBuilder::from_config(app, &config)?
+        .on_page_load(|window, payload| runtime::on_main_page_load(&window, &payload));
+    if !safemode::is_active(app) {
+        builder = builder
+            .initialization_script(desktopinfo::init_script(&desktopinfo::current(app)))
+            .initialization_script(firstpaint::FIRST_PAINT_JS)
+            .initialization_script(webuierrors::ERROR_HOOK_JS)
+            .initialization_script(devtools::SHORTCUT_JS);
+    }
+    let window = webviewprofile::apply_shared(app, builder).build()?;
+    display::apply(&window);
+    watchdog::attach(&window);
+    Ok(())
+}
+
+/// 显示并聚焦主窗口；窗口已被销毁时重新创建，并在服务运行时直接加载 WebUI。
+fn show_main_window(app_handle: &AppHandle) {
+    power::wake(app_handle);
+    ondemand::wake(app_handle, "打开窗口", |_| {});
+    if app_handle.get_webview_window("main").is_none() {
+        if let Err(err) = create_main_window(app_handle) {
+            runtime::shell_log(app_handle, &format!("[ERROR] 重新创建主窗口失败: {err}"));
+            return;
+        }
+        if let Some(w) = app_handle.get_webview_window("main") {
+            if app_handle.try_state::<RuntimeManager>().is_some() {
+                runtime::reload_runtime_page(&w);
+            }
+        }
+    }
+    firstpaint::mark_shown(app_handle);
+    if let Some(w) = app_handle.get_webview_window("main") {
+        // 启动期间窗口被关到托盘时导航可能没有生效，显示前补上
+        if status::is_running(app_handle) {
+            let _ = runtime::ensure_runtime_page(&w, &runtime::runtime_url(app_handle), false);
+        }
+        let _ = w.show();
+        let _ = w.unminimize();
+        let _ = w.set_focus();
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn build_macos_menu(app_handle: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    use tauri::menu::{AboutMetadata, PredefinedMenuItem, Submenu};
+
+    // 退出需要先停止后端服务，不使用系统预置的 Quit
+    let quit = MenuItem::with_id(
+        app_handle,
+        "macos_quit",
+        "退出 PT Nexus",
+        true,
+        Some("Cmd+Q"),
+    )?;
+    let app_menu = Submenu::with_items(
+        app_handle,
+        "PT Nexus",
+        true,
+        &[
+            &PredefinedMenuItem::about(
+                app_handle,
+                Some("关于 PT Nexus"),
+                Some(AboutMetadata::default()),
+            )?,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &PredefinedMenuItem::hide(app_handle, Some("隐藏 PT Nexus"))?,
+            &PredefinedMenuItem::hide_others(app_handle, Some("隐藏其他"))?,
+            &PredefinedMenuItem::show_all(app_handle, Some("全部显示"))?,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &quit,
+        ],
+    )?;
+    let edit_menu = Submenu::with_items(
+        app_handle,
+        "编辑",
+        true,
+        &[
+            &PredefinedMenuItem::undo(app_handle, Some("撤销"))?,
+            &PredefinedMenuItem::redo(app_handle, Some("重做"))?,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &PredefinedMenuItem::cut(app_handle, Some("剪切"))?,
+            &PredefinedMenuItem::copy(app_handle, Some("拷贝"))?,
+            &PredefinedMenuItem::paste(app_handle, Some("粘贴"))?,
+            &PredefinedMenuItem::select_all(app_handle, Some("全选"))?,
+        ],
+    )?;
+    // “关闭窗口”(Cmd+W) 触发 CloseRequested，按 close_to_tray 隐藏或退出
+    let window_menu = Submenu::with_items(
+        app_handle,
+        "窗口",
+        true,
+        &[
+            &PredefinedMenuItem::minimize(app_handle, Some("最小化"))?,
+            &PredefinedMenuItem::close_window(app_handle, Some("关闭窗口"))?,
+        ],
+    )?;
+    Menu::with_items(app_handle, &[&app_menu, &edit_menu, &window_menu])
+}
+
 /// 用系统默认浏览器打开 URL
 fn open_url_in_browser(url: &str) -> std::io::Result<()> {
     #[cfg(target_os = "windows")]
@@ -143,9 +1676,7 @@ fn open_url_in_browser(url: &str) -> std::io::Result<()> {
 fn open_path_in_file_manager(path: &std::path::Path) -> std::io::Result<()> {
     #[cfg(target_os = "windows")]
     {
-        std::process::Command::new("explorer")
-            .arg(path)
-            .spawn()?;
+        std::process::Command::new("explorer").arg(path).spawn()?;
     }
     #[cfg(target_os = "macos")]
     {
@@ -158,50 +1689,591 @@ fn open_path_in_file_manager(path: &std::path::Path) -> std::io::Result<()> {
     Ok(())
 }
 
+/// 用系统默认文本编辑器打开文件；没有关联程序时退而在文件管理器中定位该文件。
+/// 只启动编辑器而不等待它退出，关联程序在启动前检查。
+fn open_file_in_editor(path: &std::path::Path) -> std::io::Result<()> {
+    if !has_editor_for(path) {
+        return reveal_path_in_file_manager(path);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+        std::process::Command::new("cmd")
+            .args(["/C", "start", ""])
+            .arg(path)
+            .creation_flags(CREATE_NO_WINDOW)
+            .spawn()?;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg("-t")
+            .arg(path)
+            .spawn()?;
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        std::process::Command::new("xdg-open").arg(path).spawn()?;
+    }
+    Ok(())
+}
+
+/// 该类型的文件是否有关联的打开程序。按扩展名查询，忽略“打开方式”对话框这类兜底关联。
+#[cfg(target_os = "windows")]
+fn has_editor_for(path: &std::path::Path) -> bool {
+    use windows::core::{w, HSTRING};
+    use windows::Win32::UI::Shell::{
+        AssocQueryStringW, ASSOCF_INIT_IGNOREUNKNOWN, ASSOCSTR_EXECUTABLE,
+    };
+
+    let Some(extension) = path.extension() else {
+        return false;
+    };
+    let assoc = HSTRING::from(format!(".{}", extension.to_string_lossy()));
+    let mut len = 0u32;
+    // 只查询长度：有关联程序时给出可执行文件路径的长度，否则返回 ERROR_NO_ASSOCIATION
+    let result = unsafe {
+        AssocQueryStringW(
+            ASSOCF_INIT_IGNOREUNKNOWN,
+            ASSOCSTR_EXECUTABLE,
+            &assoc,
+            w!("open"),
+            None,
+            &mut len,
+        )
+    };
+    result.is_ok() && len > 0
+}
+
+/// `open -t` 总是交给默认文本编辑器（至少有系统自带的文本编辑），不需要检查。
+#[cfg(target_os = "macos")]
+fn has_editor_for(_path: &std::path::Path) -> bool {
+    true
+}
+
+/// 先查出文件的 MIME 类型，再查该类型的默认程序；没有 xdg-mime 时同样视为没有关联。
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn has_editor_for(path: &std::path::Path) -> bool {
+    fn xdg_mime(args: &[&std::ffi::OsStr]) -> Option<String> {
+        let output = std::process::Command::new("xdg-mime")
+            .args(args)
+            .output()
+            .ok()?;
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !value.is_empty()).then_some(value)
+    }
+
+    xdg_mime(&["query".as_ref(), "filetype".as_ref(), path.as_os_str()])
+        .and_then(|mime| xdg_mime(&["query".as_ref(), "default".as_ref(), mime.as_ref()]))
+        .is_some()
+}
+
+/// 在文件管理器中打开文件所在目录，并尽量选中该文件。
+fn reveal_path_in_file_manager(path: &std::path::Path) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+
+        std::process::Command::new("explorer")
+            .raw_arg(format!("/select,\"{}\"", path.display()))
+            .spawn()?;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg("-R")
+            .arg(path)
+            .spawn()?;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let dir = path.parent().unwrap_or(path);
+        std::process::Command::new("xdg-open").arg(dir).spawn()?;
+    }
+    Ok(())
+}
+
+/// 执行启动流程，成功后托管运行时并开启各项监测；失败时在启动页给出错误与可选操作。
+/// 启动页的“重试”“暂时使用 SQLite 启动”也经由这里重新执行。
+fn start_runtime(app_handle: &AppHandle) {
+    // 上次移动数据目录中途退出时，先在后台完成移动，再按移动后的 runtime.env 启动
+    if datamove::has_pending(app_handle) {
+        tasks::spawn(
+            app_handle,
+            "data-move-resume",
+            tasks::Scope::Shell,
+            |handle, _| {
+                datamove::resume_pending(&handle);
+                bootstrap_runtime(&handle);
+            },
+        );
+        return;
+    }
+    bootstrap_runtime(app_handle);
+}
+
+fn bootstrap_runtime(app_handle: &AppHandle) {
+    let runtime = match RuntimeManager::bootstrap(app_handle) {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            report_bootstrap_failure(app_handle, err);
+            return;
+        }
+    };
+
+    app_handle.manage(runtime);
+    status::mark_running(app_handle);
+    safemode::finish_startup(app_handle);
+    if let Some(offline) = database::sqlite_fallback(app_handle) {
+        journal::record(
+            app_handle,
+            journal::Severity::Warn,
+            None,
+            format!("数据库 {offline} 不可用，本次临时使用 SQLite 运行"),
+        );
+        events::emit(
+            app_handle,
+            "database-fallback",
+            events::Replay::Latest,
+            &offline,
+        );
+    }
+    health::start(app_handle);
+    memwatch::start(app_handle);
+    recycle::start(app_handle);
+    if !safemode::is_active(app_handle) {
+        traystats::start(app_handle);
+        clock::start(app_handle);
+        dataexport::start(app_handle);
+        legacylayout::check(app_handle);
+    }
+    updatecache::clean_at_bootstrap(app_handle);
+    inbox::clean_stale(app_handle);
+
+    tasks::spawn(
+        app_handle,
+        "startup-self-test",
+        tasks::Scope::Runtime,
+        |app, _| {
+            let items = selftest::run_at_startup(&app);
+            selftest::log_results(&app, &items);
+        },
+    );
+    if !safemode::is_active(app_handle) {
+        onboarding::offer(app_handle);
+    }
+}
+
+/// 记录启动失败并在主窗口中提示原因。
+fn report_bootstrap_failure(app_handle: &AppHandle, err: runtime::BootstrapError) {
+    let mut message = err.to_string();
+    if let Some(changes) = snapshot::changes_since_last_good(app_handle) {
+        message = format!("{message}\n\n{changes}");
+    }
+    let service = match &err {
+        runtime::BootstrapError::ServiceFailed { service, .. } => Some(service.as_str()),
+        _ => None,
+    };
+    journal::record(
+        app_handle,
+        journal::Severity::Error,
+        service,
+        format!("启动失败: {}", journal::first_line(&message)),
+    );
+    status::mark_failed(app_handle, &err);
+    safemode::finish_startup(app_handle);
+    write_bootstrap_error_log(app_handle, &message);
+    // 错误提示显示在主窗口中，不等首屏渲染
+    firstpaint::reveal(app_handle);
+    match err {
+        runtime::BootstrapError::DatabaseUnreachable { address, .. } => {
+            show_database_unreachable_dialog(app_handle, &address, &message)
+        }
+        _ => show_bootstrap_error_dialog(app_handle, &message, &err.actions()),
+    }
+}
+
+/// 启动页“重试”：启动失败后重新执行启动流程。
+#[tauri::command(async)]
+fn retry_bootstrap(app_handle: AppHandle) -> Result<(), CommandError> {
+    if app_handle
+        .try_state::<RuntimeManager>()
+        .is_some_and(|runtime| !runtime.service_pids().is_empty())
+    {
+        return Err(CommandError::new(
+            commanderror::ALREADY_RUNNING,
+            "后端服务已在运行",
+        ));
+    }
+    journal::record(&app_handle, journal::Severity::Info, None, "重试启动");
+    launch_runtime(&app_handle);
+    Ok(())
+}
+
+/// 启动后端服务：首次启动走完整流程；服务已停止（重启失败、按需启动进入休眠）时在原有运行时上重新启动。
+fn launch_runtime(app_handle: &AppHandle) {
+    match app_handle.try_state::<RuntimeManager>() {
+        Some(runtime) => {
+            if let Err(err) = runtime.relaunch(app_handle) {
+                report_bootstrap_failure(app_handle, err);
+            }
+        }
+        None => {
+            status::mark_starting(app_handle);
+            start_runtime(app_handle);
+        }
+    }
+}
+
+/// 启动页“暂时使用 SQLite 启动”：本次运行以 DB_TYPE=sqlite 启动服务，不修改 runtime.env。
+#[tauri::command(async)]
+fn start_with_sqlite_fallback(app_handle: AppHandle, address: String) -> Result<(), CommandError> {
+    database::enable_sqlite_fallback(&app_handle, address);
+    retry_bootstrap(app_handle)
+}
+
 fn stop_runtime(app_handle: &AppHandle) {
+    // 先停下监测循环，避免它们把正在停止的服务当成掉线处理
+    tasks::shutdown(app_handle, Some(tasks::Scope::Shell));
+    tasks::shutdown(app_handle, Some(tasks::Scope::Runtime));
     if let Some(runtime) = app_handle.try_state::<RuntimeManager>() {
+        journal::record(app_handle, journal::Severity::Info, None, "停止全部服务");
         runtime.shutdown_all();
+        status::mark_stopped(app_handle);
+        configguard::backup_on_shutdown(app_handle);
+        crashdumps::disable();
+    }
+    // 服务退出后读取线程写完剩余输出即结束
+    tasks::shutdown(app_handle, Some(tasks::Scope::Output));
+    safemode::finish_startup(app_handle);
+}
+
+/// 与 `restart_app` 相同，但新进程不带 `--safe-mode`；启动失败时重新启动本进程的服务。
+fn relaunch_without_safe_mode(app_handle: &AppHandle) -> Result<(), String> {
+    stop_runtime(app_handle);
+    if let Err(err) = safemode::relaunch_normally() {
+        runtime::shell_log(app_handle, &format!("[ERROR] 退出安全模式失败: {err}"));
+        launch_runtime(app_handle);
+        return Err(err);
+    }
+    app_handle.exit(0);
+    Ok(())
+}
+
+/// 停止后端服务后重启整个应用，用于只能在启动时生效的设置。
+fn restart_app(app_handle: &AppHandle) -> ! {
+    stop_runtime(app_handle);
+    app_handle.restart()
+}
+
+fn open_runtime_in_browser(app_handle: &AppHandle) {
+    // 休眠中的服务启动完成后再打开
+    if ondemand::wake(app_handle, "在浏览器中打开", open_runtime_in_browser) {
+        return;
+    }
+    let url = runtime::runtime_url(app_handle);
+    if let Err(err) = open_url_in_browser(url.as_str()) {
+        show_native_error(app_handle, "PT Nexus", &format!("打开浏览器失败: {err}"));
     }
 }
 
+/// 托盘“运行自检”与错误提示上的“检查运行文件”：结果用原生对话框显示，不依赖主窗口与后端服务。
+fn show_self_test_results(app_handle: &AppHandle) {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+    let items = selftest::run(app_handle);
+    selftest::log_results(app_handle, &items);
+    let passed = items.iter().all(|item| item.ok);
+    let lines: Vec<String> = items
+        .iter()
+        .map(|item| {
+            format!(
+                "{} {}：{}",
+                if item.ok { "✓" } else { "✗" },
+                item.name,
+                item.detail
+            )
+        })
+        .collect();
+    app_handle
+        .dialog()
+        .message(lines.join("\n"))
+        .title(if passed {
+            "PT Nexus 自检通过"
+        } else {
+            "PT Nexus 自检发现问题"
+        })
+        .kind(if passed {
+            MessageDialogKind::Info
+        } else {
+            MessageDialogKind::Warning
+        })
+        .show(|_| {});
+}
+
+/// 托盘等没有调用方可以接收错误的入口用原生对话框提示，页面中的脚本被阻塞时也不受影响。
+fn show_native_error(app_handle: &AppHandle, title: &str, message: &str) {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+    app_handle
+        .dialog()
+        .message(message)
+        .title(title)
+        .kind(MessageDialogKind::Error)
+        .show(|_| {});
+}
+
+/// 写在当前配置档目录中，日志查看器与诊断包都从这里读取。
 fn write_bootstrap_error_log(app_handle: &AppHandle, error: &str) {
-    let path = match app_handle.path().app_data_dir() {
-        Ok(dir) => dir.join("bootstrap-error.log"),
-        Err(_) => return,
+    let Ok(profile) = datadir::active_profile(app_handle) else {
+        return;
     };
 
-    let _ = std::fs::create_dir_all(
-        path.parent()
-            .unwrap_or_else(|| std::path::Path::new(".")),
-    );
-    let _ = std::fs::write(&path, error);
+    let _ = std::fs::create_dir_all(&profile.root);
+    let _ = fsutil::atomic_write(&profile.root.join("bootstrap-error.log"), error);
 }
 
-fn show_bootstrap_error_dialog(app_handle: &AppHandle, error: &str) {
+/// 启动失败时在启动页展示错误详情与操作按钮，`msg`、`actions` 由 [`script::call_with_args`] 传入。
+const BOOTSTRAP_ERROR_JS: &str = r#"
+  const invoke = window.__TAURI_INTERNALS__ && window.__TAURI_INTERNALS__.invoke;
+  const box = document.querySelector('.box');
+  const title = document.querySelector('.title');
+  const desc = document.querySelector('.desc');
+  if (title) title.innerText = 'PT Nexus 启动自检失败';
+  if (desc) {
+    desc.style.whiteSpace = 'pre-wrap';
+    desc.style.textAlign = 'left';
+    desc.innerText = msg;
+  }
+  const old = document.getElementById('error-actions');
+  if (old) old.remove();
+  if (box && invoke) {
+    const bar = document.createElement('div');
+    bar.id = 'error-actions';
+    bar.style.marginTop = '16px';
+    actions.forEach(function (action) {
+      const button = document.createElement('button');
+      button.type = 'button';
+      button.textContent = action.label;
+      button.style.margin = '0 6px';
+      button.addEventListener('click', function () {
+        if (action.id === 'retry') {
+          bar.remove();
+          if (title) title.innerText = 'PT Nexus 启动中…';
+          if (desc) desc.innerText = '正在重新启动…';
+        }
+        invoke('invoke_error_action', { id: action.id, context: action.context || null }).catch(function (err) {
+          if (desc) desc.innerText = (err && err.message) || String(err);
+        });
+      });
+      bar.appendChild(button);
+    });
+    box.appendChild(bar);
+  }
+"#;
+
+/// 数据库无法连接时在启动页提供“重试 / 暂时使用 SQLite 启动 / 编辑配置”，
+/// `address`、`msg` 由 [`script::call_with_args`] 传入。
+const DATABASE_UNREACHABLE_JS: &str = r#"
+  const invoke = window.__TAURI_INTERNALS__ && window.__TAURI_INTERNALS__.invoke;
+  const box = document.querySelector('.box');
+  const title = document.querySelector('.title');
+  const desc = document.querySelector('.desc');
+  if (title) title.innerText = '无法连接数据库 ' + address;
+  if (desc) {
+    desc.style.whiteSpace = 'pre-wrap';
+    desc.innerText = msg;
+  }
+  const old = document.getElementById('db-actions');
+  if (old) old.remove();
+  if (box && invoke) {
+    const actions = document.createElement('div');
+    actions.id = 'db-actions';
+    actions.style.marginTop = '16px';
+    const starting = function (text) {
+      actions.remove();
+      if (title) title.innerText = 'PT Nexus 启动中…';
+      if (desc) desc.innerText = text;
+    };
+    const fail = function (err) {
+      if (desc) desc.innerText = (err && err.message) || String(err);
+    };
+    [
+      ['重试', function () {
+        starting('正在重新连接数据库…');
+        invoke('retry_bootstrap').catch(fail);
+      }],
+      ['暂时使用 SQLite 启动', function () {
+        starting('正在使用 SQLite 启动，runtime.env 不会被修改…');
+        invoke('start_with_sqlite_fallback', { address: address }).catch(fail);
+      }],
+      ['编辑配置', function () {
+        invoke('open_runtime_env_in_editor').catch(fail);
+      }],
+    ].forEach(function (item) {
+      const button = document.createElement('button');
+      button.type = 'button';
+      button.textContent = item[0];
+      button.style.margin = '0 6px';
+      button.addEventListener('click', item[1]);
+      actions.appendChild(button);
+    });
+    box.appendChild(actions);
+  }
+"#;
+
+fn show_database_unreachable_dialog(app_handle: &AppHandle, address: &str, error: &str) {
     let Some(window) = app_handle.get_webview_window("main") else {
+        // 服务模式下没有启动页，用原生对话框提供可以立即执行的选项
+        use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+        let app = app_handle.clone();
+        let offline = address.to_string();
+        app_handle
+            .dialog()
+            .message(format!(
+                "{error}\n\n修改连接配置可从托盘菜单编辑 runtime.env 后重启应用。"
+            ))
+            .title("PT Nexus 无法连接数据库")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "暂时使用 SQLite 启动".to_string(),
+                "稍后处理".to_string(),
+            ))
+            .show(move |confirmed| {
+                if confirmed {
+                    tasks::spawn(
+                        &app,
+                        "sqlite-fallback",
+                        tasks::Scope::Shell,
+                        move |app, _| {
+                            let _ = start_with_sqlite_fallback(app, offline);
+                        },
+                    );
+                }
+            });
         return;
     };
 
+    let script = script::call_with_args(
+        DATABASE_UNREACHABLE_JS,
+        &[
+            ("address", serde_json::Value::from(address)),
+            ("msg", serde_json::Value::from(error)),
+        ],
+    );
+    let _ = window.eval(&script);
+}
+
+fn show_bootstrap_error_dialog(app_handle: &AppHandle, error: &str, actions: &[ErrorAction]) {
     let message = build_bootstrap_user_message(app_handle, error);
-    let js_message = serde_json::to_string(&message).unwrap_or_else(|_| {
-        "\"启动失败，请查看 bootstrap-error.log 和 logs/*.stderr.log\"".to_string()
-    });
+    let Some(window) = app_handle.get_webview_window("main") else {
+        // 服务模式下没有窗口，改用系统原生对话框
+        show_native_error_with_action(
+            app_handle,
+            "PT Nexus 启动自检失败",
+            &message,
+            actions.first(),
+        );
+        return;
+    };
 
-    let script = format!(
-        "(function() {{\n  const msg = {js_message};\n  alert(msg);\n  const title = document.querySelector('.title');\n  const desc = document.querySelector('.desc');\n  if (title) title.innerText = 'PT Nexus 启动自检失败';\n  if (desc) {{\n    desc.style.whiteSpace = 'pre-wrap';\n    desc.style.textAlign = 'left';\n    desc.innerText = msg;\n  }}\n}})();"
+    let script = script::call_with_args(
+        BOOTSTRAP_ERROR_JS,
+        &[
+            ("msg", serde_json::Value::from(message)),
+            ("actions", serde_json::to_value(actions).unwrap_or_default()),
+        ],
     );
-
     let _ = window.eval(&script);
 }
 
+/// 原生对话框只能有两个按钮：提供最重要的一个操作，另一个按钮关闭对话框。
+fn show_native_error_with_action(
+    app_handle: &AppHandle,
+    title: &str,
+    message: &str,
+    action: Option<&ErrorAction>,
+) {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let Some(action) = action.cloned() else {
+        show_native_error(app_handle, title, message);
+        return;
+    };
+    let app = app_handle.clone();
+    app_handle
+        .dialog()
+        .message(message)
+        .title(title)
+        .kind(MessageDialogKind::Error)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            action.label(),
+            "关闭".to_string(),
+        ))
+        .show(move |confirmed| {
+            if confirmed {
+                tasks::spawn(&app, "error-action", tasks::Scope::Shell, move |app, _| {
+                    if let Err(err) = run_error_action(&app, action) {
+                        show_native_error(&app, "PT Nexus", &err.message);
+                    }
+                });
+            }
+        });
+}
+
+/// 执行错误提示上的操作（见 erroraction.rs）。
+fn run_error_action(app_handle: &AppHandle, action: ErrorAction) -> Result<(), CommandError> {
+    match action {
+        ErrorAction::OpenLog(service) => {
+            let path = logs::stderr_log_path(app_handle, &service)?;
+            open_file_in_editor(&path)
+                .map_err(|e| CommandError::io(format_args!("打开日志失败 ({})", path.display()), e))
+        }
+        ErrorAction::OpenLogsDir => open_logs_dir(app_handle.clone()).map(|_| ()),
+        // 运行时已托管（重启全部服务失败）时重新执行重启，否则重新执行启动流程
+        ErrorAction::Retry => match app_handle.try_state::<RuntimeManager>() {
+            Some(manager) => manager.restart(app_handle).map_err(CommandError::from),
+            None => retry_bootstrap(app_handle.clone()),
+        },
+        ErrorAction::CopyDetails(details) => copy_text_to_clipboard(details),
+        ErrorAction::RemoveQuarantine => {
+            if quarantine::remove_with_confirmation(app_handle)? {
+                run_error_action(app_handle, ErrorAction::Retry)?;
+            }
+            Ok(())
+        }
+        ErrorAction::VerifyRuntime => {
+            show_self_test_results(app_handle);
+            Ok(())
+        }
+        ErrorAction::OpenDataDir => open_app_data_dir(app_handle.clone()).map(|_| ()),
+        ErrorAction::ReinstallPython => {
+            runtime::invalidate_python_runtime(app_handle)?;
+            run_error_action(app_handle, ErrorAction::Retry)
+        }
+    }
+}
+
 fn build_bootstrap_user_message(app_handle: &AppHandle, error: &str) -> String {
-    let data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|_| "<无法解析应用数据目录>".to_string());
+    let (error_log, logs_dir) = match datadir::active_profile(app_handle) {
+        Ok(profile) => (
+            profile
+                .root
+                .join("bootstrap-error.log")
+                .display()
+                .to_string(),
+            profile.logs_dir.display().to_string(),
+        ),
+        Err(_) => (
+            "<无法解析应用数据目录>".to_string(),
+            "<无法解析应用数据目录>".to_string(),
+        ),
+    };
 
     format!(
-        "启动失败，请按以下路径自检：\n\n1) 主错误日志：{data_dir}\\bootstrap-error.log\n2) 服务日志目录：{data_dir}\\logs\\\n   - background_runner.stderr.log\n   - server.stderr.log\n   - batch.stderr.log\n   - updater.stderr.log\n\n错误详情：\n{error}"
+        "启动失败，请按以下路径自检：\n\n1) 主错误日志：{error_log}\n2) 服务日志目录：{logs_dir}\n   - background_runner.stderr.log\n   - server.stderr.log\n   - batch.stderr.log\n   - updater.stderr.log\n\n错误详情：\n{error}"
     )
 }
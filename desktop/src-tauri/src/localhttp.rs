@@ -0,0 +1,175 @@
+//! 访问本机后端服务的简易 HTTP 客户端。
+//!
+//! 桌面壳只需要读取本机服务的少量 JSON 接口，不值得为此引入 HTTP 客户端依赖。
+//! 在共用异步运行时上（见 asyncrt.rs）使用 [`get_async`]，不阻塞工作线程。
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 最简单的 HTTP/1.0 GET：服务端返回后即关闭连接，不需要处理分块编码。
+pub fn get(host: &str, port: u16, path: &str) -> Result<String, String> {
+    ok_body(path, fetch(host, port, path, REQUEST_TIMEOUT)?)
+}
+
+/// 与 `get` 相同，但使用异步 socket；连接、发送与读取合计不超过 REQUEST_TIMEOUT。
+pub async fn get_async(host: &str, port: u16, path: &str) -> Result<String, String> {
+    ok_body(path, fetch_async(host, port, path).await?)
+}
+
+/// 与 `get_async` 相同，但保留状态码，供区分接口不存在（404）使用。
+pub async fn fetch_async(host: &str, port: u16, path: &str) -> Result<Response, String> {
+    let exchange = async {
+        let mut stream = tokio::net::TcpStream::connect((host, port))
+            .await
+            .map_err(|e| format!("连接 {host}:{port} 失败: {e}"))?;
+        stream
+            .write_all(message(host, port, "GET", path, "application/json", None).as_bytes())
+            .await
+            .map_err(|e| format!("发送请求失败: {e}"))?;
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .await
+            .map_err(|e| format!("读取响应失败: {e}"))?;
+        Ok::<_, String>(response)
+    };
+    let response = tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .map_err(|_| format!("请求 {path} 超时"))??;
+    parse(&response)
+}
+
+fn ok_body(path: &str, response: Response) -> Result<String, String> {
+    if response.status != 200 {
+        return Err(format!("请求 {path} 失败: {}", response.status_line));
+    }
+    Ok(String::from_utf8_lossy(&response.body).to_string())
+}
+
+pub struct Response {
+    pub status: u16,
+    pub status_line: String,
+    /// Content-Type 头（小写），没有时为空。
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+/// 与 `get` 相同，但保留状态码与原始字节，供下载较大的二进制内容、区分接口不存在（404）使用。
+pub fn fetch(host: &str, port: u16, path: &str, timeout: Duration) -> Result<Response, String> {
+    request(host, port, "GET", path, "application/json", None, timeout)
+}
+
+/// 以浏览器的方式请求页面，用于确认 WebUI 首页已可访问。
+pub fn fetch_page(
+    host: &str,
+    port: u16,
+    path: &str,
+    timeout: Duration,
+) -> Result<Response, String> {
+    request(host, port, "GET", path, "text/html", None, timeout)
+}
+
+/// 以 JSON 请求体调用本机服务的接口（POST），用于少数需要修改状态的管理接口。
+pub fn post_json(
+    host: &str,
+    port: u16,
+    path: &str,
+    body: &str,
+    timeout: Duration,
+) -> Result<Response, String> {
+    request(
+        host,
+        port,
+        "POST",
+        path,
+        "application/json",
+        Some(body),
+        timeout,
+    )
+}
+
+fn request(
+    host: &str,
+    port: u16,
+    method: &str,
+    path: &str,
+    accept: &str,
+    body: Option<&str>,
+    timeout: Duration,
+) -> Result<Response, String> {
+    let addr: SocketAddr = (host, port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| format!("无法解析地址 {host}:{port}"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, REQUEST_TIMEOUT)
+        .map_err(|e| format!("连接 {host}:{port} 失败: {e}"))?;
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+
+    stream
+        .write_all(message(host, port, method, path, accept, body).as_bytes())
+        .map_err(|e| format!("发送请求失败: {e}"))?;
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .map_err(|e| format!("读取响应失败: {e}"))?;
+    parse(&response)
+}
+
+/// 一次写出整个请求，避免服务端只读到一部分就回复并断开。
+fn message(
+    host: &str,
+    port: u16,
+    method: &str,
+    path: &str,
+    accept: &str,
+    body: Option<&str>,
+) -> String {
+    let mut message =
+        format!("{method} {path} HTTP/1.0\r\nHost: {host}:{port}\r\nAccept: {accept}\r\n");
+    if let Some(body) = body {
+        message.push_str(&format!(
+            "Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        ));
+    } else {
+        message.push_str("\r\n");
+    }
+    message
+}
+
+fn parse(response: &[u8]) -> Result<Response, String> {
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| "响应格式错误".to_string())?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let status_line = head.lines().next().unwrap_or_default().to_string();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .unwrap_or(0);
+    let content_type = head
+        .lines()
+        .skip(1)
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-type")
+                .then(|| value.trim().to_ascii_lowercase())
+        })
+        .unwrap_or_default();
+    Ok(Response {
+        status,
+        status_line,
+        content_type,
+        body: response[split + 4..].to_vec(),
+    })
+}
@@ -0,0 +1,304 @@
+//! 临时调整后端服务的日志级别，复现问题时不必改文件、重启两次。
+//!
+//! `set_backend_log_level(service, level)` 先调用服务的管理接口 `POST /api/admin/log-level`
+//! （请求体 `{"level": "DEBUG"}`），接口不存在或失败时改为只重启该服务，本次启动的环境变量中带上
+//! `LOG_LEVEL`（不写入 runtime.env，不影响其他服务）。调整后的级别在设定时长（默认 1 小时）后自动恢复为
+//! runtime.env 中配置的级别，避免调试日志一直开着写满磁盘。服务因其他原因重启（崩溃、内存回收、
+//! 重启全部服务）后按配置的级别运行，调整随之失效。
+//!
+//! 只有 Python 服务（server、background_runner）按级别输出日志；batch、updater 的日志没有级别之分。
+//! 不设置 FLASK_DEBUG：它会开启 Flask 的自动重载，服务进程会再派生子进程，桌面壳无法正确托管。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::journal::{self, Severity};
+use crate::runtime::RuntimeManager;
+use crate::tasks::{self, Scope};
+use crate::{health, localhttp};
+
+pub const LEVEL_KEY: &str = "LOG_LEVEL";
+/// Python 服务未配置 LOG_LEVEL 时的级别（与后端 logging.basicConfig 的默认值一致）。
+const BACKEND_DEFAULT_LEVEL: &str = "DEBUG";
+const SERVICES: &[&str] = &["server", "background_runner"];
+const LEVELS: &[&str] = &["DEBUG", "INFO", "WARNING", "ERROR"];
+const ADMIN_PATH: &str = "/api/admin/log-level";
+const ADMIN_TIMEOUT: Duration = Duration::from_secs(3);
+pub const DEFAULT_MINUTES: u32 = 60;
+const MAX_MINUTES: u32 = 24 * 60;
+
+/// 调整的序号，到期恢复时据此判断是否已被更新的调整取代。
+static GENERATION: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppliedVia {
+    /// 通过管理接口即时生效。
+    Admin,
+    /// 重启了该服务。
+    Restart,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackendLogLevel {
+    pub service: &'static str,
+    /// 当前生效的级别。
+    pub level: String,
+    /// runtime.env 中配置的级别，到期后恢复为它。
+    pub configured: String,
+    /// 临时级别到期的 Unix 毫秒时间戳；未调整时为 None。
+    pub revert_at_ms: Option<u64>,
+    pub via: Option<AppliedVia>,
+}
+
+#[derive(Default)]
+pub struct LogLevels(Mutex<HashMap<&'static str, Override>>);
+
+#[derive(Clone)]
+struct Override {
+    level: String,
+    revert_at_ms: u64,
+    via: AppliedVia,
+    /// 调整后服务的进程号；进程号变化说明服务已被重启，调整已失效。
+    pid: Option<u32>,
+    generation: u64,
+}
+
+/// 调整日志级别，`minutes` 后自动恢复；级别与配置相同时直接恢复。
+pub fn set(
+    app: &AppHandle,
+    service: &str,
+    level: &str,
+    minutes: Option<u32>,
+) -> Result<BackendLogLevel, String> {
+    let service = known_service(service)?;
+    let level = normalize_level(level)?;
+    let minutes = minutes.unwrap_or(DEFAULT_MINUTES);
+    if minutes == 0 || minutes > MAX_MINUTES {
+        return Err(format!("持续时间需要 1 到 {MAX_MINUTES} 分钟"));
+    }
+    let configured = configured_level(app);
+    if level == configured {
+        revert(app, service, None)?;
+        return current(app, service);
+    }
+
+    let via = apply(app, service, &level)?;
+    let revert_at_ms = now_ms() + u64::from(minutes) * 60_000;
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst);
+    {
+        let state = app
+            .try_state::<LogLevels>()
+            .ok_or_else(|| "日志级别状态尚未初始化".to_string())?;
+        let mut overrides = state
+            .0
+            .lock()
+            .map_err(|_| "日志级别状态不可用".to_string())?;
+        overrides.insert(
+            service,
+            Override {
+                level: level.clone(),
+                revert_at_ms,
+                via,
+                pid: service_pid(app, service),
+                generation,
+            },
+        );
+    }
+    journal::record(
+        app,
+        Severity::Info,
+        Some(service),
+        format!("日志级别临时调整为 {level}，{minutes} 分钟后恢复为 {configured}"),
+    );
+
+    // 再次调整同一服务时取消上一次的恢复任务，由新的任务按新的时长恢复
+    tasks::spawn(
+        app,
+        &format!("log-level-revert:{service}"),
+        Scope::Shell,
+        move |handle, token| {
+            if !token.sleep(Duration::from_secs(u64::from(minutes) * 60)) {
+                return;
+            }
+            if let Err(err) = revert(&handle, service, Some(generation)) {
+                journal::record(
+                    &handle,
+                    Severity::Warn,
+                    Some(service),
+                    format!("恢复日志级别失败: {err}"),
+                );
+            }
+        },
+    );
+    current(app, service)
+}
+
+/// 各 Python 服务当前的日志级别。
+pub fn list(app: &AppHandle) -> Vec<BackendLogLevel> {
+    SERVICES
+        .iter()
+        .filter_map(|service| current(app, service).ok())
+        .collect()
+}
+
+fn current(app: &AppHandle, service: &'static str) -> Result<BackendLogLevel, String> {
+    let configured = configured_level(app);
+    let active = active_override(app, service);
+    Ok(BackendLogLevel {
+        service,
+        level: active
+            .as_ref()
+            .map_or_else(|| configured.clone(), |o| o.level.clone()),
+        configured,
+        revert_at_ms: active.as_ref().map(|o| o.revert_at_ms),
+        via: active.map(|o| o.via),
+    })
+}
+
+/// 恢复为配置的级别。`generation` 不为空时只恢复该次调整，已被新的调整取代时什么也不做。
+fn revert(app: &AppHandle, service: &'static str, generation: Option<u64>) -> Result<(), String> {
+    let Some(active) = active_override(app, service) else {
+        clear(app, service);
+        return Ok(());
+    };
+    if generation.is_some_and(|generation| generation != active.generation) {
+        return Ok(());
+    }
+    clear(app, service);
+    let configured = configured_level(app);
+    match active.via {
+        AppliedVia::Admin if call_admin(app, service, &configured).is_ok() => {}
+        // 按配置的环境变量重新启动即可恢复
+        _ => restart_with(app, service, None)?,
+    }
+    journal::record(
+        app,
+        Severity::Info,
+        Some(service),
+        format!("日志级别已恢复为 {configured}"),
+    );
+    Ok(())
+}
+
+/// 先尝试管理接口，不可用时重启该服务。
+fn apply(app: &AppHandle, service: &'static str, level: &str) -> Result<AppliedVia, String> {
+    if call_admin(app, service, level).is_ok() {
+        return Ok(AppliedVia::Admin);
+    }
+    restart_with(app, service, Some(level))?;
+    Ok(AppliedVia::Restart)
+}
+
+fn call_admin(app: &AppHandle, service: &str, level: &str) -> Result<(), String> {
+    let port = health::service_ports(app)
+        .into_iter()
+        .find(|(name, _)| *name == service)
+        .map(|(_, port)| port)
+        .ok_or_else(|| format!("{service} 没有管理接口"))?;
+    let body = serde_json::json!({ "level": level }).to_string();
+    let response = localhttp::post_json("127.0.0.1", port, ADMIN_PATH, &body, ADMIN_TIMEOUT)?;
+    if response.status / 100 != 2 {
+        return Err(format!("{ADMIN_PATH} 返回 {}", response.status_line));
+    }
+    Ok(())
+}
+
+fn restart_with(app: &AppHandle, service: &str, level: Option<&str>) -> Result<(), String> {
+    let runtime = app
+        .try_state::<RuntimeManager>()
+        .ok_or_else(|| "运行时尚未启动".to_string())?;
+    let overrides: HashMap<String, String> = level
+        .map(|level| (LEVEL_KEY.to_string(), level.to_string()))
+        .into_iter()
+        .collect();
+    runtime.restart_service_with_env(app, service, &overrides)
+}
+
+/// 仍然有效的调整：服务进程没有换过。
+fn active_override(app: &AppHandle, service: &str) -> Option<Override> {
+    let state = app.try_state::<LogLevels>()?;
+    let active = state.0.lock().ok()?.get(service).cloned()?;
+    (active.pid == service_pid(app, service)).then_some(active)
+}
+
+fn clear(app: &AppHandle, service: &str) {
+    if let Some(state) = app.try_state::<LogLevels>() {
+        if let Ok(mut overrides) = state.0.lock() {
+            overrides.remove(service);
+        }
+    }
+}
+
+fn service_pid(app: &AppHandle, service: &str) -> Option<u32> {
+    app.try_state::<RuntimeManager>()?
+        .service_pids()
+        .into_iter()
+        .find(|(name, _)| name == service)
+        .map(|(_, pid)| pid)
+}
+
+fn configured_level(app: &AppHandle) -> String {
+    app.try_state::<RuntimeManager>()
+        .and_then(|runtime| runtime.context())
+        .and_then(|context| context.common_env.get(LEVEL_KEY).cloned())
+        .and_then(|level| normalize_level(&level).ok())
+        .unwrap_or_else(|| BACKEND_DEFAULT_LEVEL.to_string())
+}
+
+fn known_service(service: &str) -> Result<&'static str, String> {
+    SERVICES
+        .iter()
+        .copied()
+        .find(|known| *known == service)
+        .ok_or_else(|| {
+            format!(
+                "{service} 不支持调整日志级别，可调整的服务: {}",
+                SERVICES.join(", ")
+            )
+        })
+}
+
+/// 接受大小写与 WARN 简写，返回 Python logging 的级别名。
+fn normalize_level(level: &str) -> Result<String, String> {
+    let upper = level.trim().to_ascii_uppercase();
+    let upper = if upper == "WARN" {
+        "WARNING".to_string()
+    } else {
+        upper
+    };
+    if LEVELS.contains(&upper.as_str()) {
+        Ok(upper)
+    } else {
+        Err(format!(
+            "未知的日志级别 {level}，可选: {}",
+            LEVELS.join(", ")
+        ))
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_and_services_are_validated() {
+        assert_eq!(normalize_level("debug"), Ok("DEBUG".to_string()));
+        assert_eq!(normalize_level(" warn "), Ok("WARNING".to_string()));
+        assert!(normalize_level("TRACE").is_err());
+        assert_eq!(known_service("server"), Ok("server"));
+        assert!(known_service("updater").is_err());
+    }
+}
@@ -0,0 +1,297 @@
+//! 日志查看器：列出日志文件、读取末尾内容、实时跟踪新增内容。
+//!
+//! 只读取本地日志文件，不依赖后端服务，后端启动失败时也能使用。
+//! 跟踪由查看器窗口开启，窗口关闭时全部停止。服务的 stdout/stderr 由读取线程（见 capture.rs）写入时
+//! 直接推送；其余日志文件由跟踪线程轮询，空闲节能期间暂停。
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::capture::Stream;
+use crate::power::{self, PowerProfile};
+use crate::tasks::{self, Scope};
+use crate::{datadir, diagnostics, webuierrors, webviewprofile};
+
+pub const LOG_VIEWER_LABEL: &str = "log-viewer";
+const SERVICES: &[&str] = &["background_runner", "server", "batch", "updater"];
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// 读取末尾内容时最多读取的字节数，避免一次载入过大的日志文件。
+const TAIL_MAX_BYTES: u64 = 512 * 1024;
+
+#[derive(Serialize)]
+pub struct LogSource {
+    pub id: String,
+    pub label: String,
+    pub path: String,
+    pub exists: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct LogStreamChunk {
+    stream_id: u64,
+    source: String,
+    lines: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct LogStreams {
+    next_id: AtomicU64,
+    active: Mutex<HashMap<u64, Arc<AtomicBool>>>,
+    /// 跟踪服务输出的 stream_id 及其日志 id。
+    live: Mutex<HashMap<u64, String>>,
+}
+
+pub fn list_sources(app: &AppHandle) -> Result<Vec<LogSource>, String> {
+    let data_dir = data_dir(app)?;
+    let logs_dir = data_dir.join("logs");
+
+    let mut sources = vec![
+        (
+            "shell".to_string(),
+            "桌面壳 shell.log".to_string(),
+            logs_dir.join("shell.log"),
+        ),
+        (
+            "bootstrap-error".to_string(),
+            "启动失败 bootstrap-error.log".to_string(),
+            data_dir.join("bootstrap-error.log"),
+        ),
+        (
+            "webui-errors".to_string(),
+            "WebUI 脚本错误 webui-errors.log".to_string(),
+            logs_dir.join(webuierrors::LOG_FILE),
+        ),
+    ];
+    for service in SERVICES {
+        for stream in ["stderr", "stdout"] {
+            sources.push((
+                format!("{service}.{stream}"),
+                format!("{service} {stream}"),
+                logs_dir.join(format!("{service}.{stream}.log")),
+            ));
+        }
+    }
+
+    Ok(sources
+        .into_iter()
+        .map(|(id, label, path)| LogSource {
+            id,
+            label,
+            exists: path.exists(),
+            path: path.to_string_lossy().to_string(),
+        })
+        .collect())
+}
+
+/// 只接受 list_sources 中的 id，避免前端借此读取任意文件。
+fn resolve_source(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    list_sources(app)?
+        .into_iter()
+        .find(|source| source.id == id)
+        .map(|source| PathBuf::from(source.path))
+        .ok_or_else(|| format!("未知的日志: {id}"))
+}
+
+/// 服务的 stderr 日志路径；只接受已知的服务名。
+pub fn stderr_log_path(app: &AppHandle, service: &str) -> Result<PathBuf, String> {
+    resolve_source(app, &format!("{service}.stderr"))
+}
+
+pub fn tail(app: &AppHandle, id: &str, max_lines: usize) -> Result<Vec<String>, String> {
+    let path = resolve_source(app, id)?;
+    let Ok(mut file) = File::open(&path) else {
+        return Ok(Vec::new());
+    };
+
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let start = len.saturating_sub(TAIL_MAX_BYTES);
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| format!("读取日志失败 ({}): {e}", path.display()))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| format!("读取日志失败 ({}): {e}", path.display()))?;
+
+    let content = String::from_utf8_lossy(&bytes);
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    // 从文件中间开始读时第一行可能不完整
+    if start > 0 && !lines.is_empty() {
+        lines.remove(0);
+    }
+    let skip = lines.len().saturating_sub(max_lines);
+    Ok(lines.split_off(skip))
+}
+
+/// 从当前文件末尾开始跟踪新增内容，通过 `log-stream` 事件发给查看器窗口。
+pub fn start_stream(app: &AppHandle, id: &str) -> Result<u64, String> {
+    let path = resolve_source(app, id)?;
+    let streams = app
+        .try_state::<LogStreams>()
+        .ok_or_else(|| "日志跟踪不可用".to_string())?;
+    let stream_id = streams.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+    if is_service_output(id) {
+        if let Ok(mut live) = streams.live.lock() {
+            live.insert(stream_id, id.to_string());
+        }
+        return Ok(stream_id);
+    }
+    let running = Arc::new(AtomicBool::new(true));
+    if let Ok(mut active) = streams.active.lock() {
+        active.insert(stream_id, running.clone());
+    }
+
+    let source = id.to_string();
+    tasks::spawn(
+        app,
+        &format!("log-stream:{stream_id}"),
+        Scope::Shell,
+        move |app, token| {
+            let mut offset = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            let mut pending = String::new();
+            while running.load(Ordering::SeqCst) && token.sleep(POLL_INTERVAL) {
+                // 节能模式下暂停跟踪，恢复后一次性补发期间新增的内容
+                if power::profile(&app) == PowerProfile::Saver {
+                    continue;
+                }
+
+                let len = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                if len < offset {
+                    // 文件被截断或轮转，从头开始
+                    offset = 0;
+                    pending.clear();
+                }
+                if len == offset {
+                    continue;
+                }
+
+                let Ok(mut file) = File::open(&path) else {
+                    continue;
+                };
+                let mut bytes = Vec::new();
+                if file.seek(SeekFrom::Start(offset)).is_err()
+                    || file.read_to_end(&mut bytes).is_err()
+                {
+                    continue;
+                }
+                offset += bytes.len() as u64;
+
+                pending.push_str(&String::from_utf8_lossy(&bytes));
+                let Some(last_newline) = pending.rfind('\n') else {
+                    continue;
+                };
+                let complete: String = pending.drain(..=last_newline).collect();
+                let lines: Vec<String> = complete.lines().map(str::to_string).collect();
+                let _ = app.emit_to(
+                    LOG_VIEWER_LABEL,
+                    "log-stream",
+                    LogStreamChunk {
+                        stream_id,
+                        source: source.clone(),
+                        lines,
+                    },
+                );
+            }
+        },
+    );
+
+    Ok(stream_id)
+}
+
+/// 服务新写入日志的行，发给正在跟踪该日志的查看器。
+pub fn publish(app: &AppHandle, service: &str, stream: Stream, lines: &[String]) {
+    let Some(streams) = app.try_state::<LogStreams>() else {
+        return;
+    };
+    let source = format!("{service}.{}", stream.name());
+    let targets: Vec<u64> = match streams.live.lock() {
+        Ok(live) => live
+            .iter()
+            .filter(|(_, id)| **id == source)
+            .map(|(stream_id, _)| *stream_id)
+            .collect(),
+        Err(_) => return,
+    };
+    for stream_id in targets {
+        let _ = app.emit_to(
+            LOG_VIEWER_LABEL,
+            "log-stream",
+            LogStreamChunk {
+                stream_id,
+                source: source.clone(),
+                lines: lines.to_vec(),
+            },
+        );
+    }
+}
+
+fn is_service_output(id: &str) -> bool {
+    id.rsplit_once('.').is_some_and(|(service, stream)| {
+        SERVICES.contains(&service) && matches!(stream, "stdout" | "stderr")
+    })
+}
+
+pub fn stop_stream(app: &AppHandle, stream_id: u64) {
+    let Some(streams) = app.try_state::<LogStreams>() else {
+        return;
+    };
+    if let Ok(mut live) = streams.live.lock() {
+        live.remove(&stream_id);
+    }
+    if let Some(running) = streams
+        .active
+        .lock()
+        .ok()
+        .and_then(|mut a| a.remove(&stream_id))
+    {
+        running.store(false, Ordering::SeqCst);
+    }
+}
+
+pub fn stop_all(app: &AppHandle) {
+    let Some(streams) = app.try_state::<LogStreams>() else {
+        return;
+    };
+    if let Ok(mut live) = streams.live.lock() {
+        live.clear();
+    }
+    if let Ok(mut active) = streams.active.lock() {
+        for (_, running) in active.drain() {
+            running.store(false, Ordering::SeqCst);
+        }
+    };
+}
+
+/// 打开（或聚焦已打开的）日志查看器窗口。
+pub fn open_viewer(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(LOG_VIEWER_LABEL) {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    let builder = WebviewWindowBuilder::new(
+        app,
+        LOG_VIEWER_LABEL,
+        WebviewUrl::App("log-viewer.html".into()),
+    )
+    .title("PT Nexus 日志查看器")
+    .inner_size(1000.0, 680.0)
+    .min_inner_size(640.0, 400.0)
+    .on_navigation(diagnostics::is_local_page);
+    webviewprofile::apply_shared(app, builder)
+        .build()
+        .map_err(|e| format!("打开日志查看器失败: {e}"))?;
+    Ok(())
+}
+
+fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    datadir::active_profile(app).map(|profile| profile.root)
+}
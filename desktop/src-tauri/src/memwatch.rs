@@ -0,0 +1,287 @@
+//! 后端服务的内存上限。
+//!
+//! 长时间运行后 server 等 Python 进程的内存可能持续增长。runtime.env 中设置
+//! `PTNEXUS_MEM_LIMIT_<服务名>_MB`（如 `PTNEXUS_MEM_LIMIT_SERVER_MB=1500`）后，每分钟采样一次该服务的
+//! 常驻内存（RSS），连续两次超过上限时平滑重启该服务，并发出 `service-recycled` 事件。
+//! 同一服务两次回收至少间隔 30 分钟，batch 服务有任务在执行时不回收任何服务。
+//! 回收次数单独计数，不计入崩溃重启。未设置任何上限时不做采样。
+
+use std::collections::HashMap;
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::tasks::{self, Scope};
+use crate::{batchstatus, runtime};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+/// 连续超过上限的采样次数达到该值才回收，避免瞬时峰值触发重启。
+const CONSECUTIVE_SAMPLES: u32 = 2;
+const MIN_RECYCLE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const LIMIT_KEY_PREFIX: &str = "PTNEXUS_MEM_LIMIT_";
+const LIMIT_KEY_SUFFIX: &str = "_MB";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ServiceRecycled {
+    pub service: String,
+    pub before_mb: u64,
+    /// 重启后新进程的内存；读取失败时为 None。
+    pub after_mb: Option<u64>,
+    pub limit_mb: u64,
+}
+
+/// 各服务因内存超限被回收的次数，托管在 Tauri state 中。
+#[derive(Default)]
+pub struct MemWatch {
+    recycles: Mutex<HashMap<String, u32>>,
+}
+
+pub fn recycle_count(app: &AppHandle, service: &str) -> u32 {
+    app.try_state::<MemWatch>()
+        .and_then(|state| {
+            state
+                .recycles
+                .lock()
+                .ok()
+                .and_then(|counts| counts.get(service).copied())
+        })
+        .unwrap_or(0)
+}
+
+pub fn start(app: &AppHandle) {
+    app.manage(MemWatch::default());
+    tasks::spawn(app, "memory-watch", Scope::Shell, move |app, token| {
+        let mut tracker = Tracker::default();
+        while token.sleep(SAMPLE_INTERVAL) {
+            sample(&app, &mut tracker);
+        }
+    });
+}
+
+fn sample(app: &AppHandle, tracker: &mut Tracker) {
+    let Some(manager) = app.try_state::<runtime::RuntimeManager>() else {
+        return;
+    };
+    let Some(context) = manager.context() else {
+        return;
+    };
+    let limits = configured_limits(&context.common_env);
+    if limits.is_empty() {
+        return;
+    }
+
+    for (service, pid) in manager.service_pids() {
+        let Some(&limit_mb) = limits.get(&service) else {
+            continue;
+        };
+        let Some(used_mb) = rss_mb(pid) else {
+            continue;
+        };
+        if !tracker.observe(&service, used_mb > limit_mb, Instant::now()) {
+            continue;
+        }
+        if batchstatus::is_busy(app) {
+            runtime::shell_log(
+                app,
+                &format!("[INFO] {service} 内存 {used_mb} MB 超过上限 {limit_mb} MB，batch 有任务在执行，暂不回收"),
+            );
+            continue;
+        }
+
+        tracker.recycled(&service, Instant::now());
+        runtime::shell_log(
+            app,
+            &format!(
+                "[WARN] {service} 内存 {used_mb} MB 连续超过上限 {limit_mb} MB，正在重启该服务"
+            ),
+        );
+        if let Err(err) = manager.restart_service(app, &service) {
+            runtime::shell_log(app, &format!("[ERROR] 回收 {service} 失败: {err}"));
+            continue;
+        }
+        let after_mb = manager
+            .service_pids()
+            .into_iter()
+            .find(|(name, _)| *name == service)
+            .and_then(|(_, pid)| rss_mb(pid));
+        report(
+            app,
+            ServiceRecycled {
+                service,
+                before_mb: used_mb,
+                after_mb,
+                limit_mb,
+            },
+        );
+    }
+}
+
+fn report(app: &AppHandle, recycled: ServiceRecycled) {
+    if let Some(state) = app.try_state::<MemWatch>() {
+        if let Ok(mut counts) = state.recycles.lock() {
+            *counts.entry(recycled.service.clone()).or_default() += 1;
+        }
+    }
+    let after = recycled
+        .after_mb
+        .map(|mb| format!("{mb} MB"))
+        .unwrap_or_else(|| "未知".to_string());
+    let message = format!(
+        "内存 {} MB 超过上限 {} MB，已重启（重启后 {after}）",
+        recycled.before_mb, recycled.limit_mb
+    );
+    runtime::shell_log(app, &format!("[INFO] {}: {message}", recycled.service));
+    journal::record(app, Severity::Warn, Some(&recycled.service), message);
+    events::emit(app, "service-recycled", Replay::All, &recycled);
+}
+
+/// 从环境变量中读取各服务的内存上限（MB），键中的服务名不区分大小写。
+fn configured_limits(envs: &HashMap<String, String>) -> HashMap<String, u64> {
+    envs.iter()
+        .filter_map(|(key, value)| {
+            let service = key
+                .strip_prefix(LIMIT_KEY_PREFIX)?
+                .strip_suffix(LIMIT_KEY_SUFFIX)?
+                .to_ascii_lowercase();
+            let limit = value.trim().parse::<u64>().ok().filter(|mb| *mb > 0)?;
+            (!service.is_empty()).then_some((service, limit))
+        })
+        .collect()
+}
+
+/// 连续超限计数与上次回收时间。
+#[derive(Default)]
+struct Tracker {
+    over: HashMap<String, u32>,
+    last_recycle: HashMap<String, Instant>,
+}
+
+impl Tracker {
+    /// 记录一次采样，返回是否应当回收该服务。
+    fn observe(&mut self, service: &str, over_limit: bool, now: Instant) -> bool {
+        if !over_limit {
+            self.over.remove(service);
+            return false;
+        }
+        let count = self.over.entry(service.to_string()).or_default();
+        *count += 1;
+        *count >= CONSECUTIVE_SAMPLES
+            && self
+                .last_recycle
+                .get(service)
+                .is_none_or(|at| now.duration_since(*at) >= MIN_RECYCLE_INTERVAL)
+    }
+
+    fn recycled(&mut self, service: &str, now: Instant) {
+        self.over.remove(service);
+        self.last_recycle.insert(service.to_string(), now);
+    }
+}
+
+/// 进程的常驻内存（MB）。
+#[cfg(target_os = "linux")]
+fn rss_mb(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb / 1024)
+}
+
+#[cfg(target_os = "macos")]
+fn rss_mb(pid: u32) -> Option<u64> {
+    let output = Command::new("ps")
+        .args(["-o", "rss=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let kb = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb / 1024)
+}
+
+#[cfg(target_os = "windows")]
+fn rss_mb(pid: u32) -> Option<u64> {
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    let output = Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/FO", "CSV", "/NH"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    // "python.exe","1234","Console","1","123,456 K"：千位分隔符随系统区域设置变化，只保留数字
+    let line = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()?
+        .to_string();
+    let kb: String = line
+        .rsplit("\",\"")
+        .next()?
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+    Some(kb.parse::<u64>().ok()? / 1024)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn rss_mb(_pid: u32) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_are_read_per_service() {
+        let envs = HashMap::from([
+            (
+                "PTNEXUS_MEM_LIMIT_SERVER_MB".to_string(),
+                "1500".to_string(),
+            ),
+            (
+                "PTNEXUS_MEM_LIMIT_BACKGROUND_RUNNER_MB".to_string(),
+                " 800 ".to_string(),
+            ),
+            ("PTNEXUS_MEM_LIMIT_BATCH_MB".to_string(), "abc".to_string()),
+            ("PTNEXUS_MEM_LIMIT_UPDATER_MB".to_string(), "0".to_string()),
+            ("SERVER_PORT".to_string(), "5275".to_string()),
+        ]);
+        let limits = configured_limits(&envs);
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits["server"], 1500);
+        assert_eq!(limits["background_runner"], 800);
+        assert!(configured_limits(&HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn recycle_requires_consecutive_samples_and_interval() {
+        let mut tracker = Tracker::default();
+        let start = Instant::now();
+        assert!(!tracker.observe("server", true, start));
+        assert!(!tracker.observe("server", false, start));
+        assert!(!tracker.observe("server", true, start));
+        assert!(tracker.observe("server", true, start));
+        tracker.recycled("server", start);
+
+        let soon = start + Duration::from_secs(5 * 60);
+        assert!(!tracker.observe("server", true, soon));
+        assert!(!tracker.observe("server", true, soon));
+        let later = start + MIN_RECYCLE_INTERVAL;
+        assert!(tracker.observe("server", true, later));
+        assert!(!tracker.observe("batch", true, later));
+    }
+}
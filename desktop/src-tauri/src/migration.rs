@@ -0,0 +1,448 @@
+//! SQLite → MySQL / PostgreSQL 数据迁移流程。
+//!
+//! 由桌面壳编排：以 `--dry-run` 运行迁移脚本校验目标库（真正连接并登录，目标表已有数据时拒绝）→
+//! 主窗口切换到自带的启动页 → 停止全部服务 → 以子进程运行 server 自带的 `migrate_sqlite.py`
+//! （两套连接配置经环境变量传入），进度行转发到启动页 →
+//! 成功后写入 runtime.env 再重启；失败时不改 runtime.env，按原 sqlite 配置重启，
+//! 保证用户不会停在一个无法启动的状态。发起迁移的页面在迁移期间已被替换，结果用原生对话框告知。
+
+use std::io::{BufRead, BufReader};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use crate::events::{self, Replay};
+use crate::runtime::{self, RuntimeContext, RuntimeManager};
+use crate::{database, paths, pyruntime};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const DRY_RUN_FLAG: &str = "--dry-run";
+
+/// 目标数据库配置，字段与 runtime.env 中的 MYSQL_* / POSTGRES_* 一一对应。
+#[derive(Clone, Deserialize)]
+pub struct DatabaseTarget {
+    pub db_type: String,
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub database: String,
+}
+
+#[derive(Clone, Serialize)]
+struct MigrationProgress {
+    stage: &'static str,
+    message: String,
+}
+
+/// `--dry-run` 的结果。
+#[derive(Debug, Default, PartialEq)]
+pub struct DryRun {
+    /// 目标库中已有数据的表，迁移要求这些表为空。
+    pub non_empty_tables: Vec<String>,
+}
+
+impl DatabaseTarget {
+    fn env_prefix(&self) -> Result<&'static str, String> {
+        match self.db_type.trim().to_ascii_lowercase().as_str() {
+            "mysql" => Ok("MYSQL"),
+            "postgresql" => Ok("POSTGRES"),
+            other => Err(format!(
+                "不支持的目标数据库类型: {other}（可选 mysql / postgresql）"
+            )),
+        }
+    }
+
+    /// 写入 runtime.env / 传给子进程的键值对。
+    pub(crate) fn env_pairs(&self) -> Result<Vec<(String, String)>, String> {
+        let prefix = self.env_prefix()?;
+        Ok(vec![
+            (
+                "DB_TYPE".to_string(),
+                self.db_type.trim().to_ascii_lowercase(),
+            ),
+            (format!("{prefix}_HOST"), self.host.trim().to_string()),
+            (format!("{prefix}_PORT"), self.port.to_string()),
+            (format!("{prefix}_USER"), self.user.clone()),
+            (format!("{prefix}_PASSWORD"), self.password.clone()),
+            (
+                format!("{prefix}_DATABASE"),
+                self.database.trim().to_string(),
+            ),
+        ])
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.env_prefix()?;
+        if self.host.trim().is_empty() || self.database.trim().is_empty() {
+            return Err("数据库主机和库名不能为空".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 校验目标库：先确认 host:port 可达（连不上时尽快给出明确的原因），再以 `--dry-run` 运行迁移脚本，
+/// 用与 server 相同的驱动和配置真正登录目标库，不做任何写入。
+pub fn test_connection(app: &AppHandle, target: &DatabaseTarget) -> Result<DryRun, String> {
+    target.validate()?;
+    check_reachable(target)?;
+    let context = app
+        .try_state::<RuntimeManager>()
+        .and_then(|runtime| runtime.context())
+        .ok_or_else(|| "后端运行时尚未就绪，暂时无法校验数据库连接".to_string())?;
+    let (program, args) = migration_launcher(app, &context)?;
+    let source = database::sqlite_path(&context.common_env).filter(|path| path.exists());
+    dry_run(&program, &args, &context, source.as_deref(), target)
+}
+
+fn check_reachable(target: &DatabaseTarget) -> Result<(), String> {
+    let address = format!("{}:{}", target.host.trim(), target.port);
+    let addrs = address
+        .to_socket_addrs()
+        .map_err(|e| format!("无法解析数据库地址 {address}: {e}"))?;
+
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(_) => return Ok(()),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(match last_error {
+        Some(e) => format!("无法连接数据库 {address}: {e}"),
+        None => format!("无法解析数据库地址 {address}"),
+    })
+}
+
+pub fn migrate(
+    app: &AppHandle,
+    runtime: &RuntimeManager,
+    target: &DatabaseTarget,
+) -> Result<(), String> {
+    target.validate()?;
+    let context = runtime
+        .context()
+        .ok_or_else(|| "运行时状态不可用".to_string())?;
+    let source = database::sqlite_path(&context.common_env)
+        .ok_or_else(|| "当前数据库不是 SQLite，无需迁移".to_string())?;
+    if !source.exists() {
+        return Err(format!("未找到 SQLite 数据库: {}", source.display()));
+    }
+    let (program, args) = migration_launcher(app, &context)?;
+
+    // 校验失败时服务照常运行，错误直接返回给发起迁移的页面
+    emit(app, "validating", "正在检查目标数据库连接");
+    check_reachable(target)?;
+    let checked = dry_run(&program, &args, &context, Some(&source), target)?;
+    if let Some(table) = checked.non_empty_tables.first() {
+        return Err(format!("目标数据库的表 {table} 已有数据，请使用空数据库"));
+    }
+
+    // 停止服务后 WebUI 不可用，迁移进度显示在桌面壳自带的启动页
+    runtime::show_startup_page(app);
+    emit(app, "stopping", "正在停止后端服务");
+    runtime.shutdown_all();
+    runtime::append_shell_log(
+        &context.logs_dir,
+        &format!("[INFO] 开始迁移 SQLite 数据到 {}", target.db_type),
+    );
+
+    emit(app, "migrating", "正在迁移数据");
+    let result = run_script(&program, &args, &context, Some(&source), target, |line| {
+        emit(app, "migrating", line);
+    })
+    .and_then(|_| {
+        let env_file = context.data_dir.join("runtime.env");
+        let pairs = target.env_pairs()?;
+        let updates: Vec<(&str, Option<&str>)> = pairs
+            .iter()
+            .map(|(k, v)| (k.as_str(), Some(v.as_str())))
+            .collect();
+        runtime::update_env_file(&env_file, &updates)
+    });
+
+    match &result {
+        Ok(()) => {
+            runtime::append_shell_log(&context.logs_dir, "[INFO] 数据迁移完成，已写入 runtime.env");
+            emit(app, "restarting", "迁移完成，正在按新数据库配置重启服务");
+        }
+        Err(err) => {
+            runtime::append_shell_log(&context.logs_dir, &format!("[ERROR] 数据迁移失败: {err}"));
+            emit(app, "restarting", "迁移失败，正在按原 SQLite 配置重启服务");
+        }
+    }
+
+    let outcome = match (result, runtime.restart(app)) {
+        (Ok(()), Ok(())) => Ok(()),
+        (Ok(()), Err(err)) => Err(format!("数据已迁移，但按新配置重启失败：{err}")),
+        (Err(migrate_err), Ok(())) => Err(migrate_err),
+        (Err(migrate_err), Err(err)) => Err(format!("{migrate_err}\n\n且按原配置重启失败：{err}")),
+    };
+    match &outcome {
+        Ok(()) => emit(app, "done", "迁移完成"),
+        Err(err) => emit(app, "failed", err),
+    }
+    show_outcome(app, &outcome, &target.db_type);
+    outcome
+}
+
+fn show_outcome(app: &AppHandle, outcome: &Result<(), String>, db_type: &str) {
+    let (title, message, kind) = match outcome {
+        Ok(()) => (
+            "PT Nexus 数据迁移完成",
+            format!("数据已迁移到 {db_type}，服务已按新的数据库配置重新启动。"),
+            MessageDialogKind::Info,
+        ),
+        Err(err) => (
+            "PT Nexus 数据迁移失败",
+            err.clone(),
+            MessageDialogKind::Error,
+        ),
+    };
+    app.dialog()
+        .message(message)
+        .title(title)
+        .kind(kind)
+        .show(|_| {});
+}
+
+fn dry_run(
+    program: &Path,
+    args: &[String],
+    context: &RuntimeContext,
+    source: Option<&Path>,
+    target: &DatabaseTarget,
+) -> Result<DryRun, String> {
+    let mut args = args.to_vec();
+    args.push(DRY_RUN_FLAG.to_string());
+    let mut lines = Vec::new();
+    run_script(program, &args, context, source, target, |line| {
+        lines.push(line.to_string())
+    })?;
+    Ok(parse_dry_run(&lines))
+}
+
+/// 运行迁移脚本，逐行回调 stdout；退出码非 0 时返回脚本的 `ERROR` 行与 stderr 末尾。
+fn run_script(
+    program: &Path,
+    args: &[String],
+    context: &RuntimeContext,
+    source: Option<&Path>,
+    target: &DatabaseTarget,
+    mut on_line: impl FnMut(&str),
+) -> Result<(), String> {
+    let mut cmd = Command::new(program);
+    cmd.args(args)
+        .current_dir(&context.server_dir)
+        .envs(&context.common_env)
+        .envs(target.env_pairs()?)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(source) = source {
+        cmd.env("PTNEXUS_MIGRATE_SOURCE_SQLITE", source);
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("启动迁移进程失败 {}: {e}", program.display()))?;
+
+    // stderr 单独收集，失败时附在错误信息里
+    let stderr = child.stderr.take();
+    let stderr_reader = std::thread::spawn(move || {
+        let mut lines = Vec::new();
+        if let Some(stderr) = stderr {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                lines.push(line);
+            }
+        }
+        lines
+    });
+
+    let mut last_error = None;
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some(message) = line.strip_prefix("ERROR ") {
+                last_error = Some(message.to_string());
+            }
+            on_line(&line);
+        }
+    }
+
+    let status = child.wait().map_err(|e| format!("等待迁移进程失败: {e}"))?;
+    let stderr_lines = stderr_reader.join().unwrap_or_default();
+
+    if status.success() {
+        return Ok(());
+    }
+
+    let tail: Vec<&str> = stderr_lines
+        .iter()
+        .rev()
+        .take(20)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    Err(format!(
+        "迁移脚本执行失败（状态: {status}）：{}\n\n{}",
+        last_error.unwrap_or_else(|| "未知错误".to_string()),
+        tail.join("\n")
+    ))
+}
+
+/// 脚本在 `--dry-run` 下为每个已有数据的表输出一行 `NOTEMPTY <表名>`。
+fn parse_dry_run(lines: &[String]) -> DryRun {
+    DryRun {
+        non_empty_tables: lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("NOTEMPTY "))
+            .map(|table| table.trim().to_string())
+            .collect(),
+    }
+}
+
+/// 迁移入口，Python 运行时与 server 一样按 [`pyruntime::resolve_python_home`] 定位。
+fn migration_launcher(
+    app: &AppHandle,
+    context: &RuntimeContext,
+) -> Result<(PathBuf, Vec<String>), String> {
+    let runtime_root = runtime::resolve_runtime_root(app)?;
+    let python_home =
+        pyruntime::resolve_python_home(app, &runtime_root, &context.server_dir, &context.data_dir)?;
+    resolve_migration_launcher(&context.server_dir, &python_home)
+}
+
+/// 优先使用独立打包的 migrate_sqlite 可执行文件，其次是内置 Python + 脚本。
+fn resolve_migration_launcher(
+    server_dir: &Path,
+    python_home: &Path,
+) -> Result<(PathBuf, Vec<String>), String> {
+    let migrate_exe = server_dir.join(runtime::exe_name("migrate_sqlite"));
+    if paths::extended(&migrate_exe).exists() {
+        return Ok((migrate_exe, vec![]));
+    }
+
+    let python_exe = python_home.join(runtime::exe_name("python"));
+    let entry = server_dir.join("migrate_sqlite.py");
+    if paths::extended(&python_exe).exists() && paths::extended(&entry).exists() {
+        return Ok((
+            python_exe,
+            vec!["-u".to_string(), entry.to_string_lossy().to_string()],
+        ));
+    }
+
+    Err(format!(
+        "当前运行时不包含数据迁移入口：{} 或 {} + {}",
+        migrate_exe.display(),
+        python_exe.display(),
+        entry.display()
+    ))
+}
+
+/// 进度同时作为启动阶段发出，由启动页显示；`database-migration-progress` 保留给需要区分阶段的页面。
+fn emit(app: &AppHandle, stage: &'static str, message: &str) {
+    runtime::emit_stage(app, "migration", message.to_string());
+    events::emit(
+        app,
+        "database-migration-progress",
+        Replay::Latest,
+        MigrationProgress {
+            stage,
+            message: message.to_string(),
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    fn target(db_type: &str) -> DatabaseTarget {
+        DatabaseTarget {
+            db_type: db_type.to_string(),
+            host: " 192.168.1.10 ".to_string(),
+            port: 5432,
+            user: "pt".to_string(),
+            password: "secret".to_string(),
+            database: " ptnexus ".to_string(),
+        }
+    }
+
+    #[test]
+    fn env_pairs_follow_the_target_type() {
+        let pairs = target("PostgreSQL").env_pairs().unwrap();
+        assert_eq!(pairs[0], ("DB_TYPE".to_string(), "postgresql".to_string()));
+        assert!(pairs.contains(&("POSTGRES_HOST".to_string(), "192.168.1.10".to_string())));
+        assert!(pairs.contains(&("POSTGRES_DATABASE".to_string(), "ptnexus".to_string())));
+        assert!(target("sqlite").env_pairs().is_err());
+    }
+
+    #[test]
+    fn incomplete_targets_are_rejected_before_connecting() {
+        assert!(target("mysql").validate().is_ok());
+        let mut missing_host = target("mysql");
+        missing_host.host = "  ".to_string();
+        assert!(missing_host.validate().is_err());
+        assert!(target("oracle").validate().is_err());
+    }
+
+    #[test]
+    fn dry_run_reports_non_empty_tables() {
+        let lines: Vec<String> = [
+            "CHECK 已连接",
+            "NOTEMPTY sites ",
+            "NOTEMPTY torrents",
+            "DONE",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        assert_eq!(
+            parse_dry_run(&lines).non_empty_tables,
+            ["sites".to_string(), "torrents".to_string()]
+        );
+        assert_eq!(parse_dry_run(&["DONE".to_string()]), DryRun::default());
+    }
+
+    #[test]
+    fn launcher_uses_the_resolved_python_home() {
+        let dir = temp_dir("launcher");
+        let server_dir = dir.join("server");
+        let python_home = dir.join("python-runtime");
+        fs::create_dir_all(&server_dir).unwrap();
+        fs::create_dir_all(&python_home).unwrap();
+        assert!(resolve_migration_launcher(&server_dir, &python_home).is_err());
+
+        fs::write(server_dir.join("migrate_sqlite.py"), "").unwrap();
+        fs::write(python_home.join(runtime::exe_name("python")), "").unwrap();
+        // 解压后的运行时不在 server/python 下
+        let (program, args) = resolve_migration_launcher(&server_dir, &python_home).unwrap();
+        assert_eq!(program, python_home.join(runtime::exe_name("python")));
+        assert_eq!(args[0], "-u");
+
+        let packaged = server_dir.join(runtime::exe_name("migrate_sqlite"));
+        fs::write(&packaged, "").unwrap();
+        assert_eq!(
+            resolve_migration_launcher(&server_dir, &python_home).unwrap(),
+            (packaged, vec![])
+        );
+    }
+}
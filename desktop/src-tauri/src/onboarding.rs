@@ -0,0 +1,618 @@
+//! 初始设置向导：数据位置、数据库与 Webhook 通知。
+//!
+//! 首次启动（启动前还没有 runtime.env）时，服务就绪后打开设置窗口（setup.html）；跳过的用户可以从托盘
+//! “重新运行初始设置”再次打开，窗口预填当前取值。保存沿用已有的流程：数据库先做连通性检查再写入
+//! runtime.env，数据目录由 datamove.rs 移动，需要重启服务的改动在保存前列出。
+//!
+//! 完成情况记录在应用数据目录的 `onboarding.json`：哪些步骤完成、哪些跳过，以及记录时的应用版本。
+//! 之后版本在 [`STEPS`] 中新增的步骤（`since` 晚于记录版本且未记录）只以“有新的设置项”提示单独补充。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::runtime::{self, RuntimeManager};
+use crate::{
+    database, datadir, datamove, diagnostics, fsutil, injections, migration, servicemode, webhook,
+    webviewprofile,
+};
+
+pub const SETUP_LABEL: &str = "setup";
+const RECORD_FILE: &str = "onboarding.json";
+
+pub struct Step {
+    pub id: &'static str,
+    pub title: &'static str,
+    /// 引入该步骤的应用版本。
+    pub since: &'static str,
+}
+
+/// 向导步骤，按显示顺序排列；新增步骤时填写当时的版本号。
+pub const STEPS: &[Step] = &[
+    Step {
+        id: "data_dir",
+        title: "数据位置",
+        since: "0.1.0",
+    },
+    Step {
+        id: "database",
+        title: "数据库",
+        since: "0.1.0",
+    },
+    Step {
+        id: "webhook",
+        title: "Webhook 通知",
+        since: "0.1.0",
+    },
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Record {
+    version: String,
+    completed: Vec<String>,
+    skipped: Vec<String>,
+    updated_at: String,
+}
+
+/// 启动时判断是否首次运行；向导在每次运行中最多自动弹出一次。
+#[derive(Default)]
+pub struct OnboardingState {
+    first_launch: bool,
+    offered: AtomicBool,
+    /// 当前窗口只显示新增步骤。
+    incremental: AtomicBool,
+}
+
+/// 向导中的可编辑取值。
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SetupValues {
+    pub data_dir: String,
+    pub db_type: String,
+    pub db_host: String,
+    pub db_port: u16,
+    pub db_user: String,
+    /// 读取当前值时总为空；提交时为空表示保留原密码。
+    pub db_password: String,
+    pub db_name: String,
+    pub webhook_url: String,
+    pub webhook_events: String,
+}
+
+#[derive(Serialize)]
+pub struct StepState {
+    id: &'static str,
+    title: &'static str,
+    /// completed / skipped / new / pending（从未记录）
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct SetupState {
+    steps: Vec<StepState>,
+    incremental: bool,
+    values: SetupValues,
+    password_set: bool,
+}
+
+#[derive(Deserialize)]
+pub struct SetupSubmission {
+    pub values: SetupValues,
+    /// 本次保存的步骤，未列出的步骤不做改动。
+    pub steps: Vec<String>,
+    #[serde(default)]
+    pub skipped: Vec<String>,
+}
+
+/// 保存时将要发生的改动。
+#[derive(Debug, Serialize)]
+pub struct SetupChange {
+    step: &'static str,
+    summary: String,
+    /// 需要重启服务（或移动数据时暂停服务）才能生效。
+    restart: bool,
+}
+
+/// 在启动服务之前调用：此时还没有 runtime.env 且没有完成记录，才算首次启动。
+pub fn detect(app: &AppHandle) -> OnboardingState {
+    let first_launch = record_path(app).is_some_and(|path| !path.exists())
+        && datadir::active_profile(app)
+            .is_ok_and(|profile| !profile.root.join("runtime.env").exists());
+    OnboardingState {
+        first_launch,
+        ..OnboardingState::default()
+    }
+}
+
+/// 服务启动成功后调用：首次启动打开向导；已有记录但版本新增了步骤时询问是否补充设置。
+pub fn offer(app: &AppHandle) {
+    let Some(state) = app.try_state::<OnboardingState>() else {
+        return;
+    };
+    if state.offered.swap(true, Ordering::SeqCst) || servicemode::is_enabled(app) {
+        return;
+    }
+
+    let Some(record) = load(app) else {
+        if state.first_launch {
+            let _ = open_window(app, false);
+        } else {
+            // 引入向导之前就已配置过的安装，不再弹出完整流程，可从托盘重新运行
+            let all: Vec<String> = STEPS.iter().map(|step| step.id.to_string()).collect();
+            let _ = save_record(app, &[], &all);
+        }
+        return;
+    };
+    let steps = new_steps(&record);
+    if steps.is_empty() {
+        return;
+    }
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let titles: Vec<&str> = steps.iter().map(|step| step.title).collect();
+        let confirmed = app
+            .dialog()
+            .message(format!(
+                "新版本增加了以下设置项：\n\n{}\n\n现在设置吗？之后也可以从托盘“重新运行初始设置”进入。",
+                titles.join("、")
+            ))
+            .title("有新的设置项")
+            .kind(MessageDialogKind::Info)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "现在设置".to_string(),
+                "跳过".to_string(),
+            ))
+            .blocking_show();
+        if confirmed {
+            let _ = open_window(&app, true);
+        } else {
+            let ids: Vec<String> = steps.iter().map(|step| step.id.to_string()).collect();
+            let _ = save_record(&app, &[], &ids);
+        }
+    });
+}
+
+/// 打开设置窗口；`incremental` 时只显示新增步骤。窗口已打开时切到前台。
+pub fn open_window(app: &AppHandle, incremental: bool) -> Result<(), String> {
+    if let Some(state) = app.try_state::<OnboardingState>() {
+        state.incremental.store(incremental, Ordering::SeqCst);
+    }
+    if let Some(window) = app.get_webview_window(SETUP_LABEL) {
+        let _ = window.eval("window.location.reload()");
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    let builder = WebviewWindowBuilder::new(app, SETUP_LABEL, WebviewUrl::App("setup.html".into()))
+        .title("PT Nexus 初始设置")
+        .inner_size(640.0, 620.0)
+        .min_inner_size(480.0, 420.0)
+        .on_navigation(diagnostics::is_local_page);
+    webviewprofile::apply_shared(app, builder)
+        .build()
+        .map_err(|e| format!("打开初始设置失败: {e}"))?;
+    Ok(())
+}
+
+pub fn state(app: &AppHandle) -> SetupState {
+    let record = load(app);
+    let new_ids: Vec<&str> = record
+        .as_ref()
+        .map(|record| new_steps(record).iter().map(|step| step.id).collect())
+        .unwrap_or_default();
+    let steps = STEPS
+        .iter()
+        .map(|step| StepState {
+            id: step.id,
+            title: step.title,
+            status: match &record {
+                _ if new_ids.contains(&step.id) => "new",
+                Some(record) if record.completed.iter().any(|id| id == step.id) => "completed",
+                Some(record) if record.skipped.iter().any(|id| id == step.id) => "skipped",
+                _ => "pending",
+            },
+        })
+        .collect();
+    let values = current_values(app);
+    let password_set = db_prefix(&values.db_type).is_some_and(|prefix| {
+        runtime::read_runtime_setting(app, &format!("{prefix}_PASSWORD"))
+            .is_some_and(|password| !password.is_empty())
+    });
+    SetupState {
+        steps,
+        incremental: app
+            .try_state::<OnboardingState>()
+            .is_some_and(|state| state.incremental.load(Ordering::SeqCst)),
+        values,
+        password_set,
+    }
+}
+
+/// 列出保存后会发生的改动，不写入任何内容。
+pub fn preview(app: &AppHandle, submission: &SetupSubmission) -> Vec<SetupChange> {
+    changes(&current_values(app), &submission.values, &submission.steps)
+}
+
+/// 校验并保存：写入 runtime.env、记录完成情况，然后移动数据目录或重启服务。返回已应用的改动。
+pub fn apply(app: &AppHandle, submission: &SetupSubmission) -> Result<Vec<SetupChange>, String> {
+    let current = current_values(app);
+    let next = &submission.values;
+    let changes = changes(&current, next, &submission.steps);
+    let moving = changes.iter().any(|change| change.step == "data_dir");
+    let runtime = app.try_state::<RuntimeManager>();
+    if moving {
+        if current.db_type != "sqlite" {
+            return Err("当前数据库不是 SQLite，没有需要移动的数据文件".to_string());
+        }
+        if runtime.is_none() {
+            return Err("服务尚未启动，暂时无法移动数据目录".to_string());
+        }
+        if datamove::in_progress() {
+            return Err("数据目录正在移动中".to_string());
+        }
+    }
+
+    let mut updates: Vec<(String, Option<String>)> = Vec::new();
+    for change in &changes {
+        match change.step {
+            "database" => updates.extend(database_updates(app, next)?),
+            "webhook" => {
+                let url = next.webhook_url.trim();
+                if !url.is_empty() {
+                    webhook::validate_url(url)?;
+                }
+                let events = next.webhook_events.trim();
+                updates.push((
+                    webhook::URL_KEY.to_string(),
+                    (!url.is_empty()).then(|| url.to_string()),
+                ));
+                updates.push((
+                    webhook::EVENTS_KEY.to_string(),
+                    (!events.is_empty()).then(|| events.to_string()),
+                ));
+            }
+            _ => {}
+        }
+    }
+    if !updates.is_empty() {
+        let env_file = datadir::active_profile(app)?.root.join("runtime.env");
+        let updates: Vec<(&str, Option<&str>)> = updates
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_deref()))
+            .collect();
+        runtime::update_env_file(&env_file, &updates)?;
+    }
+    save_record(app, &submission.steps, &submission.skipped)?;
+    if !changes.is_empty() {
+        let summaries: Vec<&str> = changes
+            .iter()
+            .map(|change| change.summary.as_str())
+            .collect();
+        runtime::shell_log(
+            app,
+            &format!("[INFO] 初始设置已保存：{}", summaries.join("；")),
+        );
+    }
+
+    // 移动数据目录时会停止并重启服务，同时带上刚写入的数据库设置
+    if let Some(runtime) = runtime {
+        if moving {
+            datamove::start(app, &runtime, next.data_dir.trim())?;
+        } else if changes.iter().any(|change| change.restart) {
+            runtime
+                .restart(app)
+                .map_err(|e| format!("设置已保存，但重启服务失败：{e}"))?;
+        }
+    }
+    Ok(changes)
+}
+
+/// 只记录跳过的步骤，用于直接关闭向导。
+pub fn skip(app: &AppHandle, steps: &[String]) -> Result<(), String> {
+    save_record(app, &[], steps)
+}
+
+fn database_updates(
+    app: &AppHandle,
+    next: &SetupValues,
+) -> Result<Vec<(String, Option<String>)>, String> {
+    if next.db_type == "sqlite" {
+        return Ok(vec![("DB_TYPE".to_string(), Some("sqlite".to_string()))]);
+    }
+    let prefix =
+        db_prefix(&next.db_type).ok_or_else(|| format!("不支持的数据库类型: {}", next.db_type))?;
+    let password = if next.db_password.is_empty() {
+        runtime::read_runtime_setting(app, &format!("{prefix}_PASSWORD")).unwrap_or_default()
+    } else {
+        next.db_password.clone()
+    };
+    let target = migration::DatabaseTarget {
+        db_type: next.db_type.clone(),
+        host: next.db_host.clone(),
+        port: next.db_port,
+        user: next.db_user.clone(),
+        password,
+        database: next.db_name.clone(),
+    };
+    // 向导只切换连接配置，目标库已有数据（如重装后连回原库）不影响
+    migration::test_connection(app, &target)?;
+    Ok(target
+        .env_pairs()?
+        .into_iter()
+        .map(|(key, value)| (key, Some(value)))
+        .collect())
+}
+
+fn current_values(app: &AppHandle) -> SetupValues {
+    let setting = |key: &str| runtime::read_runtime_setting(app, key).unwrap_or_default();
+    let data_dir = app
+        .try_state::<RuntimeManager>()
+        .and_then(|runtime| runtime.context())
+        .and_then(|context| database::sqlite_path(&context.common_env))
+        .and_then(|path| path.parent().map(|dir| dir.display().to_string()))
+        .or_else(|| runtime::read_runtime_setting(app, database::SQLITE_DIR_KEY))
+        .or_else(|| {
+            datadir::active_profile(app)
+                .ok()
+                .map(|profile| profile.root.display().to_string())
+        })
+        .unwrap_or_default();
+    let db_type = runtime::read_runtime_setting(app, "DB_TYPE")
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_else(|| "sqlite".to_string());
+
+    let mut values = SetupValues {
+        data_dir,
+        db_type,
+        webhook_url: setting(webhook::URL_KEY),
+        webhook_events: setting(webhook::EVENTS_KEY),
+        ..SetupValues::default()
+    };
+    if let Some(prefix) = db_prefix(&values.db_type) {
+        values.db_host = setting(&format!("{prefix}_HOST"));
+        values.db_port = setting(&format!("{prefix}_PORT"))
+            .trim()
+            .parse()
+            .unwrap_or(default_port(&values.db_type));
+        values.db_user = setting(&format!("{prefix}_USER"));
+        values.db_name = setting(&format!("{prefix}_DATABASE"));
+    }
+    values
+}
+
+fn db_prefix(db_type: &str) -> Option<&'static str> {
+    match db_type {
+        "mysql" => Some("MYSQL"),
+        "postgresql" => Some("POSTGRES"),
+        _ => None,
+    }
+}
+
+fn default_port(db_type: &str) -> u16 {
+    if db_type == "postgresql" {
+        5432
+    } else {
+        3306
+    }
+}
+
+fn db_label(db_type: &str) -> &str {
+    match db_type {
+        "mysql" => "MySQL",
+        "postgresql" => "PostgreSQL",
+        "sqlite" => "SQLite",
+        other => other,
+    }
+}
+
+/// 对比当前取值与提交的取值，只看本次保存的步骤。
+fn changes(current: &SetupValues, next: &SetupValues, steps: &[String]) -> Vec<SetupChange> {
+    let saving = |id: &str| steps.iter().any(|step| step == id);
+    let mut changes = Vec::new();
+
+    let data_dir = next.data_dir.trim();
+    if saving("data_dir") && !data_dir.is_empty() && data_dir != current.data_dir.trim() {
+        changes.push(SetupChange {
+            step: "data_dir",
+            summary: format!("数据目录移动到 {data_dir}，移动期间服务暂停"),
+            restart: true,
+        });
+    }
+
+    let database_changed = next.db_type != current.db_type
+        || (next.db_type != "sqlite"
+            && (next.db_host.trim() != current.db_host.trim()
+                || next.db_port != current.db_port
+                || next.db_user != current.db_user
+                || next.db_name.trim() != current.db_name.trim()
+                || !next.db_password.is_empty()));
+    if saving("database") && database_changed {
+        let mut summary = if next.db_type == "sqlite" {
+            "数据库改为 SQLite".to_string()
+        } else {
+            format!(
+                "数据库改为 {} {}:{}/{}",
+                db_label(&next.db_type),
+                next.db_host.trim(),
+                next.db_port,
+                next.db_name.trim()
+            )
+        };
+        if next.db_type != current.db_type {
+            summary.push_str("，现有数据不会自动迁移，如需保留请使用数据库迁移");
+        }
+        changes.push(SetupChange {
+            step: "database",
+            summary,
+            restart: true,
+        });
+    }
+
+    let url = next.webhook_url.trim();
+    if saving("webhook")
+        && (url != current.webhook_url.trim()
+            || next.webhook_events.trim() != current.webhook_events.trim())
+    {
+        let summary = if url.is_empty() {
+            "关闭 Webhook 通知".to_string()
+        } else {
+            format!("Webhook 通知发送到 {url}")
+        };
+        changes.push(SetupChange {
+            step: "webhook",
+            summary,
+            restart: false,
+        });
+    }
+    changes
+}
+
+/// 记录版本之后新增、且尚未完成或跳过的步骤。
+fn new_steps(record: &Record) -> Vec<&'static Step> {
+    STEPS
+        .iter()
+        .filter(|step| {
+            injections::is_newer(step.since, &record.version)
+                && !record.completed.iter().any(|id| id == step.id)
+                && !record.skipped.iter().any(|id| id == step.id)
+        })
+        .collect()
+}
+
+fn merge(record: &mut Record, completed: &[String], skipped: &[String]) {
+    for id in completed {
+        record.skipped.retain(|other| other != id);
+        if !record.completed.contains(id) {
+            record.completed.push(id.clone());
+        }
+    }
+    for id in skipped {
+        if !record.completed.contains(id) && !record.skipped.contains(id) {
+            record.skipped.push(id.clone());
+        }
+    }
+}
+
+fn record_path(app: &AppHandle) -> Option<PathBuf> {
+    Some(app.path().app_data_dir().ok()?.join(RECORD_FILE))
+}
+
+fn load(app: &AppHandle) -> Option<Record> {
+    read_record(&record_path(app)?)
+}
+
+fn read_record(path: &Path) -> Option<Record> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn save_record(app: &AppHandle, completed: &[String], skipped: &[String]) -> Result<(), String> {
+    let path = record_path(app).ok_or_else(|| "无法解析应用数据目录".to_string())?;
+    write_record(
+        &path,
+        completed,
+        skipped,
+        &app.package_info().version.to_string(),
+    )
+}
+
+fn write_record(
+    path: &Path,
+    completed: &[String],
+    skipped: &[String],
+    version: &str,
+) -> Result<(), String> {
+    let mut record = read_record(path).unwrap_or_default();
+    merge(&mut record, completed, skipped);
+    record.version = version.to_string();
+    record.updated_at = runtime::format_utc_timestamp(SystemTime::now());
+    let json = serde_json::to_string_pretty(&record).map_err(|e| e.to_string())?;
+    fsutil::atomic_write(path, json).map_err(|e| format!("写入 {} 失败: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn record_round_trips_and_merges() {
+        let dir = temp_dir("record");
+        let path = dir.join(RECORD_FILE);
+
+        write_record(
+            &path,
+            &ids(&["database"]),
+            &ids(&["data_dir", "webhook"]),
+            "0.1.0",
+        )
+        .unwrap();
+        write_record(&path, &ids(&["webhook"]), &ids(&["database"]), "0.2.0").unwrap();
+        let record = read_record(&path).unwrap();
+        assert_eq!(record.version, "0.2.0");
+        assert_eq!(record.completed, ids(&["database", "webhook"]));
+        assert_eq!(record.skipped, ids(&["data_dir"]));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn only_steps_after_recorded_version_are_new() {
+        let record = Record {
+            version: "0.0.9".to_string(),
+            skipped: ids(&["webhook"]),
+            ..Record::default()
+        };
+        let new: Vec<&str> = new_steps(&record).iter().map(|step| step.id).collect();
+        assert_eq!(new, ["data_dir", "database"]);
+
+        let current = Record {
+            version: "0.1.0".to_string(),
+            ..Record::default()
+        };
+        assert!(new_steps(&current).is_empty());
+    }
+
+    #[test]
+    fn changes_cover_only_saved_steps() {
+        let current = SetupValues {
+            data_dir: "/data".to_string(),
+            db_type: "sqlite".to_string(),
+            ..SetupValues::default()
+        };
+        let next = SetupValues {
+            data_dir: "/mnt/data".to_string(),
+            db_type: "mysql".to_string(),
+            db_host: "127.0.0.1".to_string(),
+            db_port: 3306,
+            db_name: "pt_nexus".to_string(),
+            webhook_url: "https://example.com/hook".to_string(),
+            ..SetupValues::default()
+        };
+        let all = changes(&current, &next, &ids(&["data_dir", "database", "webhook"]));
+        let steps: Vec<(&str, bool)> = all.iter().map(|c| (c.step, c.restart)).collect();
+        assert_eq!(
+            steps,
+            [("data_dir", true), ("database", true), ("webhook", false)]
+        );
+        assert!(all[1].summary.contains("MySQL 127.0.0.1:3306/pt_nexus"));
+
+        assert!(changes(&current, &next, &ids(&[])).is_empty());
+        assert!(changes(
+            &current,
+            &current,
+            &ids(&["data_dir", "database", "webhook"])
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+//! 按需启动：每周只打开一两次的用户不必在开机时就拉起全部后端服务。
+//!
+//! 桌面设置 `start_on_demand` 开启后（修改后下次启动生效），启动应用时只创建托盘和隐藏的主窗口（显示打包的
+//! 启动页），服务处于休眠状态；用户第一次打开主窗口、在浏览器中打开 WebUI 或点击托盘「启动服务」时，
+//! 才按正常的启动流程启动服务。`dormant_after_minutes` 大于 0 时，主窗口隐藏超过该时长后平滑停止服务、
+//! 回到休眠状态；batch 有任务在执行时推迟。服务模式没有主窗口，无从判断是否还在使用，不会自动停止。
+//! 没有托盘时无法唤醒，按普通方式启动。状态变化都经过 status.rs 的状态机并写入运行记录。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use tauri::menu::MenuItem;
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::journal::{self, Severity};
+use crate::runtime::{self, RuntimeManager};
+use crate::tasks::{self, Scope};
+use crate::{
+    batchstatus, configguard, crashdumps, firstpaint, servicemode, settings, status, trayhost,
+    traystats,
+};
+
+pub const START_MENU_ID: &str = "start_services";
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const DORMANT_TOOLTIP: &str = "PT Nexus（服务未启动）";
+const PLAIN_TOOLTIP: &str = "PT Nexus";
+
+/// 正在唤醒时拒绝重复唤醒（托盘连点、显示窗口与菜单同时触发）。
+static WAKING: AtomicBool = AtomicBool::new(false);
+
+/// 托盘中的「启动服务」菜单项，仅在休眠时可用。
+pub struct StartMenuItem(pub MenuItem<Wry>);
+
+/// 开启了按需启动且有托盘可用于唤醒。
+pub fn is_enabled(app: &AppHandle) -> bool {
+    settings::current(app).start_on_demand
+        && app
+            .try_state::<trayhost::TrayState>()
+            .is_some_and(|state| state.0 == trayhost::TrayAvailability::Available)
+}
+
+/// 启动应用时代替 `start_runtime` 调用：进入休眠并开始监测窗口隐藏时长。
+pub fn enter_at_launch(app: &AppHandle) {
+    // 主窗口保持隐藏，直到用户主动打开
+    firstpaint::mark_shown(app);
+    status::mark_dormant(app);
+    journal::record(
+        app,
+        Severity::Info,
+        None,
+        "按需启动：服务将在打开窗口时启动",
+    );
+    sync_tray(app);
+    start_monitor(app);
+}
+
+/// 休眠时启动服务，成功后执行 `then`；不在休眠或正在唤醒时什么也不做，返回是否开始了唤醒。
+pub fn wake(app: &AppHandle, reason: &str, then: impl FnOnce(&AppHandle) + Send + 'static) -> bool {
+    if !status::is_dormant(app) || WAKING.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    journal::record(
+        app,
+        Severity::Info,
+        None,
+        format!("按需启动服务（{reason}）"),
+    );
+    status::mark_starting(app);
+    sync_tray(app);
+    tasks::spawn(app, "wake", Scope::Shell, move |app, _| {
+        crate::launch_runtime(&app);
+        WAKING.store(false, Ordering::SeqCst);
+        sync_tray(&app);
+        if status::is_running(&app) {
+            then(&app);
+        } else if let Some(window) = app.get_webview_window("main") {
+            // 从托盘唤醒时窗口仍隐藏着，失败提示显示在主窗口中
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    });
+    true
+}
+
+/// 平滑停止全部服务并回到休眠状态，主窗口换回打包的启动页。
+fn enter_dormant(app: &AppHandle, hidden_for: Duration) {
+    let Some(runtime) = app.try_state::<RuntimeManager>() else {
+        return;
+    };
+    journal::record(
+        app,
+        Severity::Info,
+        None,
+        format!(
+            "主窗口已隐藏 {} 分钟，停止服务进入休眠",
+            hidden_for.as_secs() / 60
+        ),
+    );
+    status::mark_dormant(app);
+    if let (Some(window), Some(url)) = (
+        app.get_webview_window("main"),
+        runtime::local_page_url("index.html"),
+    ) {
+        let _ = window.navigate(url);
+    }
+    runtime.shutdown_gracefully();
+    configguard::backup_on_shutdown(app);
+    crashdumps::disable();
+    sync_tray(app);
+}
+
+/// 按当前状态更新托盘：休眠时「启动服务」可用，提示中注明服务未启动。
+pub fn sync_tray(app: &AppHandle) {
+    let dormant = status::is_dormant(app);
+    if let Some(item) = app.try_state::<StartMenuItem>() {
+        let _ = item
+            .0
+            .set_enabled(dormant && !WAKING.load(Ordering::SeqCst));
+    }
+    if let Some(tray) = app.tray_by_id(traystats::TRAY_ID) {
+        let _ = tray.set_tooltip(Some(if dormant {
+            DORMANT_TOOLTIP
+        } else {
+            PLAIN_TOOLTIP
+        }));
+    }
+}
+
+fn start_monitor(app: &AppHandle) {
+    if servicemode::is_enabled(app) {
+        return;
+    }
+    tasks::spawn(app, "dormant-monitor", Scope::Shell, move |app, token| {
+        let mut hidden_since: Option<Instant> = None;
+        let mut deferred = false;
+        while token.sleep(CHECK_INTERVAL) {
+            let visible = app
+                .get_webview_window("main")
+                .is_some_and(|window| window.is_visible().unwrap_or(false));
+            if visible || !status::is_running(&app) {
+                hidden_since = None;
+                deferred = false;
+                continue;
+            }
+            let since = *hidden_since.get_or_insert_with(Instant::now);
+            let minutes = settings::current(&app).dormant_after_minutes;
+            if !should_sleep(since.elapsed(), minutes) {
+                continue;
+            }
+            if batchstatus::is_busy(&app) {
+                if !deferred {
+                    runtime::shell_log(&app, "[INFO] batch 有任务在执行，暂不停止服务");
+                    deferred = true;
+                }
+                continue;
+            }
+            enter_dormant(&app, since.elapsed());
+            hidden_since = None;
+            deferred = false;
+        }
+    });
+}
+
+/// 主窗口已隐藏 `hidden_for`，是否应停止服务；`after_minutes` 为 0 表示不停止。
+fn should_sleep(hidden_for: Duration, after_minutes: u32) -> bool {
+    after_minutes > 0 && hidden_for >= Duration::from_secs(u64::from(after_minutes) * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn services_sleep_only_after_the_configured_hidden_period() {
+        assert!(!should_sleep(Duration::from_secs(24 * 3600), 0));
+        assert!(!should_sleep(Duration::from_secs(119 * 60), 120));
+        assert!(should_sleep(Duration::from_secs(120 * 60), 120));
+    }
+}
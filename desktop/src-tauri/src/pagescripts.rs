@@ -0,0 +1,306 @@
+//! 注入运行时页面的脚本。
+//!
+//! 脚本源码放在 `pagescripts/` 目录下的 .js 文件中，编译时嵌入。每个脚本都是一个立即执行的函数，
+//! 唯一的参数写作占位符 [`PLACEHOLDER`]；[`render_script`] 把 [`ScriptConfig`] 序列化为 JSON
+//! （经 [`script::js_literal`] 编码）替换它。允许的来源、界面文案、WebUI 版本门槛与功能开关
+//! 都集中在这一个配置对象中，脚本本身不拼接任何动态内容。新增注入也应放在这里，而不是写成字符串常量。
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::injections::Injection;
+use crate::script;
+
+/// 脚本中代表配置对象的占位符，每个脚本恰好出现一次。
+pub const PLACEHOLDER: &str = "__PTNEXUS_SCRIPT_CONFIG__";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageScript {
+    /// 把外部链接交给系统浏览器打开。
+    ExternalLinks,
+    /// 设置页中的“打开数据库配置目录”按钮。
+    DbConfigButton,
+    /// 启动遮罩及其渲染超时提示。
+    StartupOverlay,
+    /// 页面心跳，见 watchdog.rs。
+    WebviewHeartbeat,
+}
+
+impl PageScript {
+    pub fn name(self) -> &'static str {
+        match self {
+            PageScript::ExternalLinks => "external-links",
+            PageScript::DbConfigButton => "db-config-button",
+            PageScript::StartupOverlay => "startup-overlay",
+            PageScript::WebviewHeartbeat => "webview-heartbeat",
+        }
+    }
+
+    fn source(self) -> &'static str {
+        match self {
+            PageScript::ExternalLinks => include_str!("pagescripts/external-links.js"),
+            PageScript::DbConfigButton => include_str!("pagescripts/db-config-button.js"),
+            PageScript::StartupOverlay => include_str!("pagescripts/startup-overlay.js"),
+            PageScript::WebviewHeartbeat => include_str!("pagescripts/webview-heartbeat.js"),
+        }
+    }
+
+    /// 脚本使用的界面文案，键与脚本中的 `config.strings` 对应。
+    fn strings(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            PageScript::DbConfigButton => &[
+                // 按 WebUI 卡片标题定位插入位置，须与 WebUI 的文案一致
+                ("cardTitle", "其他设置"),
+                ("label", "数据库配置文件"),
+                ("openButton", "打开数据库配置目录"),
+                ("openLabel", "打开数据库配置目录（包含 runtime.env）"),
+                ("openFailed", "打开目录失败，请手动前往应用数据目录修改 runtime.env。"),
+                ("editButton", "编辑 runtime.env"),
+                ("editLabel", "用系统编辑器打开 runtime.env"),
+                ("editFailed", "打开 runtime.env 失败。"),
+                ("hint", "默认 sqlite；如需 MySQL/PostgreSQL，请编辑 runtime.env 后重启应用。"),
+            ],
+            PageScript::StartupOverlay => &[
+                ("title", "PT Nexus 启动中"),
+                ("desc", "正在初始化页面，请稍候。"),
+                ("failureTitle", "界面加载失败"),
+                (
+                    "failureDesc",
+                    "后台服务已启动，但界面在 {seconds} 秒内没有显示出来，可能是缓存了旧版本的页面。可以尝试强制刷新，或查看日志了解原因。",
+                ),
+                ("reload", "强制刷新"),
+                ("logs", "查看日志"),
+            ],
+            PageScript::ExternalLinks | PageScript::WebviewHeartbeat => &[],
+        }
+    }
+}
+
+/// 外部链接拦截视为内部地址的来源。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowedOrigins {
+    /// WebUI 的 origin，如 `http://127.0.0.1:5274`。
+    pub runtime: String,
+    /// 主机名列表，`127.*` 形式的条目表示 IPv4 网段。
+    pub internal_hosts: Vec<String>,
+}
+
+/// 依赖页面结构的脚本核对选择器时的 WebUI 版本，脚本把它记到 `window.__PTNEXUS_INJECTIONS__`。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionGate {
+    pub id: &'static str,
+    pub verified_with: &'static str,
+}
+
+impl From<&Injection> for VersionGate {
+    fn from(injection: &Injection) -> Self {
+        Self {
+            id: injection.id,
+            verified_with: injection.verified_with,
+        }
+    }
+}
+
+/// 传给脚本的配置对象，字段不适用时为 null 或空对象，结构固定。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptConfig {
+    pub origins: Option<AllowedOrigins>,
+    pub strings: BTreeMap<&'static str, &'static str>,
+    pub version_gate: Option<VersionGate>,
+    pub features: BTreeMap<&'static str, serde_json::Value>,
+}
+
+impl ScriptConfig {
+    /// 带上脚本自己的文案，其余字段为空。
+    pub fn new(script: PageScript) -> Self {
+        Self {
+            origins: None,
+            strings: script.strings().iter().copied().collect(),
+            version_gate: None,
+            features: BTreeMap::new(),
+        }
+    }
+
+    pub fn origins(mut self, origins: AllowedOrigins) -> Self {
+        self.origins = Some(origins);
+        self
+    }
+
+    pub fn version_gate(mut self, injection: &Injection) -> Self {
+        self.version_gate = Some(injection.into());
+        self
+    }
+
+    pub fn feature(mut self, name: &'static str, value: impl Into<serde_json::Value>) -> Self {
+        self.features.insert(name, value.into());
+        self
+    }
+}
+
+/// 生成可直接交给 `window.eval` 的脚本。
+pub fn render_script(script: PageScript, config: &ScriptConfig) -> String {
+    script
+        .source()
+        .replacen(PLACEHOLDER, &script::js_literal(config), 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::injections;
+
+    const ALL: [PageScript; 4] = [
+        PageScript::ExternalLinks,
+        PageScript::DbConfigButton,
+        PageScript::StartupOverlay,
+        PageScript::WebviewHeartbeat,
+    ];
+
+    fn sample(script: PageScript) -> ScriptConfig {
+        ScriptConfig::new(script)
+            .origins(AllowedOrigins {
+                runtime: "http://127.0.0.1:5274".to_string(),
+                internal_hosts: vec!["127.*".to_string(), "</script>".to_string()],
+            })
+            .version_gate(&injections::STARTUP_OVERLAY)
+            .feature("intervalMs", 5000)
+    }
+
+    #[test]
+    fn every_script_has_exactly_one_placeholder() {
+        for script in ALL {
+            assert_eq!(
+                script.source().matches(PLACEHOLDER).count(),
+                1,
+                "{}",
+                script.name()
+            );
+        }
+    }
+
+    #[test]
+    fn rendered_scripts_embed_the_config_as_json() {
+        for script in ALL {
+            let config = sample(script);
+            let rendered = render_script(script, &config);
+            assert!(!rendered.contains(PLACEHOLDER), "{}", script.name());
+            let literal = script::js_literal(&config);
+            assert!(rendered.contains(&literal), "{}", script.name());
+            let parsed: serde_json::Value = serde_json::from_str(&literal).unwrap();
+            assert_eq!(parsed["versionGate"]["id"], "startup-overlay");
+            assert_eq!(parsed["origins"]["internalHosts"][1], "</script>");
+            assert!(!rendered.contains("</script>"));
+        }
+    }
+
+    #[test]
+    fn strings_used_by_scripts_are_provided() {
+        for script in ALL {
+            let source = script.source();
+            let strings = ScriptConfig::new(script).strings;
+            let mut rest = source;
+            while let Some(index) = rest.find("text.") {
+                rest = &rest[index + "text.".len()..];
+                let key: String = rest
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric())
+                    .collect();
+                assert!(
+                    strings.contains_key(key.as_str()),
+                    "{}: {key}",
+                    script.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rendered_scripts_are_balanced() {
+        for script in ALL {
+            let rendered = render_script(script, &sample(script));
+            assert_eq!(check_balance(&rendered), Ok(()), "{}", script.name());
+        }
+        assert!(check_balance("(function() { if (a) { })();").is_err());
+        assert!(check_balance("var s = '}'; var r = /[(]/; // )").is_ok());
+        assert!(check_balance("var s = 'unterminated;").is_err());
+    }
+
+    /// 不依赖 JS 引擎的语法粗检：跳过字符串、注释与正则字面量后，括号必须成对且正确嵌套。
+    fn check_balance(source: &str) -> Result<(), String> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut stack = Vec::new();
+        // 上一个非空白字符，用来区分正则字面量与除号
+        let mut previous = '(';
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            match c {
+                '\'' | '"' | '`' => {
+                    i += 1;
+                    while i < chars.len() && chars[i] != c {
+                        if chars[i] == '\n' && c != '`' {
+                            return Err(format!("第 {i} 个字符处字符串未结束"));
+                        }
+                        i += if chars[i] == '\\' { 2 } else { 1 };
+                    }
+                    if i >= chars.len() {
+                        return Err("字符串未结束".to_string());
+                    }
+                }
+                '/' if next == Some('/') => {
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                    continue;
+                }
+                '/' if next == Some('*') => {
+                    i += 2;
+                    while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                        i += 1;
+                    }
+                    i += 2;
+                    continue;
+                }
+                '/' if "(,=:[!&|?{};".contains(previous) => {
+                    let mut in_class = false;
+                    i += 1;
+                    while i < chars.len() && (chars[i] != '/' || in_class) {
+                        match chars[i] {
+                            '\\' => i += 1,
+                            '[' => in_class = true,
+                            ']' => in_class = false,
+                            '\n' => return Err(format!("第 {i} 个字符处正则未结束")),
+                            _ => {}
+                        }
+                        i += 1;
+                    }
+                }
+                '(' | '[' | '{' => stack.push(c),
+                ')' | ']' | '}' => {
+                    let open = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    if stack.pop() != Some(open) {
+                        return Err(format!("第 {i} 个字符处的 {c} 没有配对"));
+                    }
+                }
+                _ => {}
+            }
+            if !c.is_whitespace() {
+                previous = c;
+            }
+            i += 1;
+        }
+        match stack.last() {
+            Some(open) => Err(format!("{open} 没有闭合")),
+            None => Ok(()),
+        }
+    }
+}
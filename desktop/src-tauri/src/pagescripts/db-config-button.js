@@ -0,0 +1,199 @@
+// 在设置页的“其他设置”卡片中插入“打开数据库配置目录”按钮，仅桌面端运行时注入，不修改 webui 源码。
+// 只在设置页期间用 MutationObserver 观察 #app，路由变化通过一次性包装的 history 方法感知；
+// 页面出现 data-ptnexus-shell-settings 标记（WebUI 自带桌面设置卡片）后永久停用。
+// 文案见 config.strings。
+(function(config) {
+  var gate = config.versionGate;
+  window.__PTNEXUS_INJECTIONS__ = window.__PTNEXUS_INJECTIONS__ || {};
+  window.__PTNEXUS_INJECTIONS__[gate.id] = gate.verifiedWith;
+
+  if (window.__PTNEXUS_DB_BUTTON_WATCHER__) return;
+  window.__PTNEXUS_DB_BUTTON_WATCHER__ = true;
+
+  var text = config.strings;
+
+  // 失败原因写在按钮下方的提示中，不用 alert 阻塞页面脚本
+  function showResult(message) {
+    var hint = document.querySelector('#ptnexus-db-config-hint span');
+    if (!hint) return;
+    hint.textContent = message || text.hint;
+    hint.className = message ? 'el-text el-text--small is-danger' : 'el-text el-text--small is-info';
+    hint.setAttribute('role', message ? 'alert' : 'status');
+  }
+
+  function invokeOrReport(cmd, failMessage) {
+    showResult(null);
+    try {
+      var pending = window.__TAURI_INTERNALS__.invoke(cmd);
+      if (pending && typeof pending.catch === 'function') {
+        pending.catch(function(err) { showResult(failMessage + ' ' + ((err && err.message) || err)); });
+      }
+    } catch (e) {
+      showResult(failMessage);
+    }
+  }
+
+  // 注入的按钮沿用 Element Plus 样式，这里补上键盘焦点的可见轮廓
+  function ensureFocusStyle() {
+    if (document.getElementById('ptnexus-db-config-style')) return;
+    var style = document.createElement('style');
+    style.id = 'ptnexus-db-config-style';
+    style.textContent =
+      '.ptnexus-db-config-item .el-button:focus-visible{outline:2px solid var(--el-color-primary,#409eff);outline-offset:2px;}' +
+      '@media (forced-colors: active){.ptnexus-db-config-item .el-button:focus-visible{outline-color:Highlight;}}';
+    (document.head || document.documentElement).appendChild(style);
+  }
+
+  function createButton(className, caption, ariaLabel, cmd, failMessage) {
+    var btn = document.createElement('button');
+    btn.type = 'button';
+    btn.className = className;
+    var span = document.createElement('span');
+    span.textContent = caption;
+    btn.appendChild(span);
+    btn.setAttribute('aria-label', ariaLabel);
+    btn.setAttribute('aria-describedby', 'ptnexus-db-config-hint');
+    btn.addEventListener('click', function() {
+      invokeOrReport(cmd, failMessage);
+    });
+    return btn;
+  }
+
+  function ensureButton() {
+    var headers = Array.from(document.querySelectorAll('.card-header .header-content h3'));
+    var target = headers.find(function(h) {
+      var t = (h.textContent || '').trim();
+      return t === text.cardTitle;
+    });
+    if (!target) return false;
+
+    var card = target.closest('.settings-card');
+    if (!card) return false;
+
+    if (card.querySelector('.ptnexus-open-db-config-btn')) return true;
+
+    var form = card.querySelector('.settings-form');
+    if (!form) return false;
+
+    var formItem = document.createElement('div');
+    formItem.className = 'el-form-item form-item ptnexus-db-config-item';
+
+    var label = document.createElement('label');
+    label.className = 'el-form-item__label';
+    label.textContent = text.label;
+
+    var content = document.createElement('div');
+    content.className = 'el-form-item__content';
+
+    ensureFocusStyle();
+    formItem.setAttribute('role', 'group');
+    label.id = 'ptnexus-db-config-label';
+    formItem.setAttribute('aria-labelledby', label.id);
+
+    var btn = createButton(
+      'el-button el-button--success is-plain ptnexus-open-db-config-btn',
+      text.openButton,
+      text.openLabel,
+      'open_app_data_dir',
+      text.openFailed
+    );
+    var editBtn = createButton(
+      'el-button el-button--primary is-plain ptnexus-edit-runtime-env-btn',
+      text.editButton,
+      text.editLabel,
+      'open_runtime_env_in_editor',
+      text.editFailed
+    );
+
+    var hint = document.createElement('div');
+    hint.className = 'password-hint';
+    hint.id = 'ptnexus-db-config-hint';
+    var hintText = document.createElement('span');
+    hintText.className = 'el-text el-text--small is-info';
+    hintText.textContent = text.hint;
+    hint.appendChild(hintText);
+
+    content.appendChild(btn);
+    content.appendChild(editBtn);
+    content.appendChild(hint);
+    formItem.appendChild(label);
+    formItem.appendChild(content);
+
+    var spacer = form.querySelector('.form-spacer');
+    if (spacer && spacer.parentNode) {
+      spacer.parentNode.insertBefore(formItem, spacer);
+    } else {
+      form.appendChild(formItem);
+    }
+
+    return true;
+  }
+
+  function isSettingsRoute() {
+    return /(^|\/)settings(\/|$)/.test(location.pathname);
+  }
+
+  // WebUI 自带桌面设置卡片（shell-settings bridge）后不再需要注入
+  function hasShellSettingsBridge() {
+    return !!document.querySelector('[data-ptnexus-shell-settings]');
+  }
+
+  var observer = null;
+  var pending = false;
+  var retired = false;
+
+  function uninstall() {
+    if (observer) observer.disconnect();
+    observer = null;
+  }
+
+  function retire() {
+    retired = true;
+    uninstall();
+    var injected = document.querySelector('.ptnexus-db-config-item');
+    if (injected && injected.parentNode) injected.parentNode.removeChild(injected);
+  }
+
+  function scheduleEnsure() {
+    if (pending) return;
+    pending = true;
+    requestAnimationFrame(function() {
+      pending = false;
+      if (retired) return;
+      if (hasShellSettingsBridge()) {
+        retire();
+        return;
+      }
+      ensureButton();
+    });
+  }
+
+  // 只在设置页期间观察 DOM，离开设置页即停止
+  function sync() {
+    if (retired) return;
+    if (!isSettingsRoute()) {
+      uninstall();
+      return;
+    }
+    if (!observer) {
+      observer = new MutationObserver(scheduleEnsure);
+      observer.observe(document.getElementById('app') || document.body || document.documentElement, {
+        childList: true,
+        subtree: true,
+      });
+    }
+    scheduleEnsure();
+  }
+
+  ['pushState', 'replaceState'].forEach(function(name) {
+    var original = history[name];
+    history[name] = function() {
+      var ret = original.apply(this, arguments);
+      setTimeout(sync, 0);
+      return ret;
+    };
+  });
+  window.addEventListener('popstate', sync, true);
+
+  sync();
+})(__PTNEXUS_SCRIPT_CONFIG__);
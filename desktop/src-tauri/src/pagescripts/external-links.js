@@ -0,0 +1,62 @@
+// 拦截 window.open 和 <a target="_blank"> 等外部链接，交给 Rust 端的 open_external 命令在系统浏览器中打开。
+// 允许的来源见 config.origins；主机名列表存在 window 上，重新注入时即使拦截器已安装也会更新。
+(function(config) {
+  window.__PTNEXUS_RUNTIME_ORIGIN__ = config.origins.runtime;
+  window.__PTNEXUS_INTERNAL_HOSTS__ = config.origins.internalHosts;
+  if (window.__PTNEXUS_LINK_INTERCEPTOR__) return;
+  window.__PTNEXUS_LINK_INTERCEPTOR__ = true;
+
+  function isInternalHost(hostname) {
+    var hosts = window.__PTNEXUS_INTERNAL_HOSTS__ || [];
+    var host = String(hostname).toLowerCase();
+    for (var i = 0; i < hosts.length; i++) {
+      var entry = hosts[i];
+      if (entry === host) return true;
+      // 以 .* 结尾的条目表示 IPv4 网段，如 127.* 为整个回环网段
+      if (entry.slice(-2) === '.*' && /^\d+\.\d+\.\d+\.\d+$/.test(host) &&
+          host.indexOf(entry.slice(0, -1)) === 0) return true;
+    }
+    return false;
+  }
+
+  function isExternal(url) {
+    try {
+      var u = new URL(url, location.href);
+      if (u.origin === window.__PTNEXUS_RUNTIME_ORIGIN__) return false;
+      return !isInternalHost(u.hostname);
+    } catch(e) {
+      // 无法解析的地址按内部处理，交给 WebView 自行导航，不能让拦截器把点击吞掉
+      return false;
+    }
+  }
+
+  // 拦截 window.open
+  var origOpen = window.open;
+  window.open = function(url) {
+    if (url && isExternal(String(url))) {
+      try {
+        var u = new URL(String(url), location.href);
+        window.__TAURI_INTERNALS__.invoke('open_external', { url: u.href });
+      } catch(e) {}
+      return null;
+    }
+    return origOpen.apply(this, arguments);
+  };
+
+  // 拦截 <a> 元素点击（含 target="_blank" 和普通外部链接）
+  document.addEventListener('click', function(e) {
+    var el = e.target;
+    while (el && el.tagName !== 'A') el = el.parentElement;
+    if (!el) return;
+    var href = el.getAttribute('href') || el.href;
+    if (!href) return;
+    if (isExternal(href)) {
+      e.preventDefault();
+      e.stopPropagation();
+      try {
+        var u = new URL(href, location.href);
+        window.__TAURI_INTERNALS__.invoke('open_external', { url: u.href });
+      } catch(ex) {}
+    }
+  }, true);
+})(__PTNEXUS_SCRIPT_CONFIG__);
@@ -0,0 +1,152 @@
+// 启动遮罩，尽可能覆盖 WebUI 初始化阶段，减少白屏观感。
+// 页面渲染完成后通知桌面壳并移除遮罩；桌面壳判定渲染超时（webui-render-timeout）时，
+// 遮罩改为错误提示，提供强制刷新与查看日志两个操作；事件监听需要 capabilities/webui-remote.json
+// 授权，未收到事件时由本地计时兜底。文案见 config.strings。
+(function(config) {
+  var gate = config.versionGate;
+  window.__PTNEXUS_INJECTIONS__ = window.__PTNEXUS_INJECTIONS__ || {};
+  window.__PTNEXUS_INJECTIONS__[gate.id] = gate.verifiedWith;
+
+  if (window.__PTNEXUS_STARTUP_OVERLAY__) return;
+  window.__PTNEXUS_STARTUP_OVERLAY__ = true;
+
+  var internals = window.__TAURI_INTERNALS__;
+  var text = config.strings;
+  var failed = false;
+
+  function invoke(cmd, args) {
+    try {
+      var pending = internals.invoke(cmd, args || {});
+      if (pending && typeof pending.catch === 'function') pending.catch(function() {});
+      return pending;
+    } catch (e) {}
+  }
+
+  function removeOverlay() {
+    var overlay = document.getElementById('ptnexus-startup-overlay');
+    if (overlay && overlay.parentNode) overlay.parentNode.removeChild(overlay);
+    var style = document.getElementById('ptnexus-startup-style');
+    if (style && style.parentNode) style.parentNode.removeChild(style);
+  }
+
+  // 判断逻辑由 firstpaint.rs 的初始化脚本提供
+  function appReady() {
+    var ready = window.__PTNEXUS_APP_READY__;
+    return typeof ready === 'function' && ready();
+  }
+
+  function element(tag, className, content) {
+    var el = document.createElement(tag);
+    if (className) el.className = className;
+    if (content) el.textContent = content;
+    return el;
+  }
+
+  // 重新填充遮罩内容，文案一律以 textContent 写入
+  function fillBox(overlay, title, desc) {
+    var box = element('div', 'box');
+    var heading = element('div', 'title', title);
+    box.appendChild(heading);
+    box.appendChild(element('div', 'desc', desc));
+    while (overlay.firstChild) overlay.removeChild(overlay.firstChild);
+    overlay.appendChild(box);
+    return { box: box, heading: heading };
+  }
+
+  function createOverlay() {
+    if (document.getElementById('ptnexus-startup-overlay')) return;
+    // 如果 Vue 应用已经渲染了有效内容，不再创建遮罩
+    if (appReady()) return;
+
+    var style = document.createElement('style');
+    style.id = 'ptnexus-startup-style';
+    style.textContent = [
+      '#ptnexus-startup-overlay{position:fixed;inset:0;z-index:2147483647;display:flex;align-items:center;justify-content:center;background:#f5f7fa;color:#303133;font-family:-apple-system,BlinkMacSystemFont,"Segoe UI",sans-serif;}',
+      '#ptnexus-startup-overlay .box{text-align:center;padding:28px 32px;border-radius:14px;box-shadow:0 8px 32px rgba(0,0,0,.08);background:#fff;min-width:320px;max-width:480px;}',
+      '#ptnexus-startup-overlay .title{font-size:22px;font-weight:600;margin-bottom:10px;}',
+      '#ptnexus-startup-overlay .desc{font-size:14px;color:#606266;line-height:1.6;}',
+      '#ptnexus-startup-overlay .actions{margin-top:18px;display:flex;gap:10px;justify-content:center;}',
+      '#ptnexus-startup-overlay button{font-size:14px;padding:8px 16px;border-radius:6px;border:1px solid #dcdfe6;background:#fff;color:#606266;cursor:pointer;}',
+      '#ptnexus-startup-overlay button.primary{background:#409eff;border-color:#409eff;color:#fff;}',
+      '#ptnexus-startup-overlay button:focus-visible{outline:2px solid #409eff;outline-offset:2px;}',
+      '#ptnexus-startup-overlay .dot::after{content:"";display:inline-block;animation:ptnexus-dot 1.2s steps(3,end) infinite;}',
+      '@keyframes ptnexus-dot{0%{content:""}33%{content:"."}66%{content:".."}100%{content:"..."}}',
+      '@media (prefers-color-scheme: dark){#ptnexus-startup-overlay{background:#141414;color:#e5eaf3;}#ptnexus-startup-overlay .box{background:#1d1e1f;box-shadow:none;}#ptnexus-startup-overlay .desc{color:#a3a6ad;}#ptnexus-startup-overlay button{background:#1d1e1f;border-color:#363637;color:#cfd3dc;}}',
+      '@media (prefers-contrast: more){#ptnexus-startup-overlay .box{border:2px solid currentColor;box-shadow:none;}#ptnexus-startup-overlay .desc{color:inherit;}}',
+      '@media (forced-colors: active){#ptnexus-startup-overlay{background:Canvas;color:CanvasText;}#ptnexus-startup-overlay .box{border:2px solid CanvasText;}#ptnexus-startup-overlay button{border:1px solid ButtonText;}}',
+      '@media (prefers-reduced-motion: reduce){#ptnexus-startup-overlay .dot::after{animation:none;content:"...";}}'
+    ].join('');
+    document.head && document.head.appendChild(style);
+
+    var overlay = document.createElement('div');
+    overlay.id = 'ptnexus-startup-overlay';
+    overlay.setAttribute('role', 'status');
+    overlay.setAttribute('aria-live', 'polite');
+    overlay.setAttribute('aria-busy', 'true');
+    var dot = element('span', 'dot');
+    dot.setAttribute('aria-hidden', 'true');
+    fillBox(overlay, text.title, text.desc).heading.appendChild(dot);
+    (document.body || document.documentElement).appendChild(overlay);
+  }
+
+  function actionButton(caption, primary, cmd) {
+    var btn = element('button', primary ? 'primary' : '', caption);
+    btn.type = 'button';
+    btn.addEventListener('click', function() {
+      invoke(cmd);
+    });
+    return btn;
+  }
+
+  function showFailure(seconds) {
+    if (appReady()) return;
+    failed = true;
+    createOverlay();
+    var overlay = document.getElementById('ptnexus-startup-overlay');
+    if (!overlay) return;
+    overlay.setAttribute('role', 'alert');
+    overlay.setAttribute('aria-busy', 'false');
+    var desc = text.failureDesc.replace('{seconds}', String(seconds || config.features.renderTimeoutSeconds));
+    var box = fillBox(overlay, text.failureTitle, desc).box;
+    var actions = element('div', 'actions');
+    var reload = actionButton(text.reload, true, 'hard_refresh');
+    actions.appendChild(reload);
+    actions.appendChild(actionButton(text.logs, false, 'open_log_viewer'));
+    box.appendChild(actions);
+    reload.focus();
+  }
+
+  function tick() {
+    if (appReady()) {
+      clearInterval(timer);
+      removeOverlay();
+      invoke('webui_render_ready');
+    } else if (!failed) {
+      createOverlay();
+    }
+  }
+
+  try {
+    invoke('plugin:event|listen', {
+      event: 'webui-render-timeout',
+      target: { kind: 'Any' },
+      handler: internals.transformCallback(function(event) {
+        showFailure(event && event.payload);
+      }),
+    });
+  } catch (e) {}
+
+  // 监听被拒绝或事件丢失时的兜底：比桌面壳的计时多等几秒，页面不可见时顺延
+  function fallback() {
+    if (failed || appReady()) return;
+    if (document.hidden) {
+      setTimeout(fallback, 5000);
+      return;
+    }
+    showFailure();
+  }
+  setTimeout(fallback, (config.features.renderTimeoutSeconds + 5) * 1000);
+
+  var timer = setInterval(tick, 250);
+  tick();
+})(__PTNEXUS_SCRIPT_CONFIG__);
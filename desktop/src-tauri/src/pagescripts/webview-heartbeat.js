@@ -0,0 +1,15 @@
+// 页面心跳：供渲染进程看门狗判断 WebView 是否仍然存活，间隔见 config.features.intervalMs。
+(function(config) {
+  if (window.__PTNEXUS_HEARTBEAT__) return;
+  window.__PTNEXUS_HEARTBEAT__ = true;
+
+  function beat() {
+    try {
+      var pending = window.__TAURI_INTERNALS__.invoke('webview_heartbeat');
+      if (pending && typeof pending.catch === 'function') pending.catch(function() {});
+    } catch (e) {}
+  }
+
+  beat();
+  setInterval(beat, config.features.intervalMs);
+})(__PTNEXUS_SCRIPT_CONFIG__);
@@ -0,0 +1,191 @@
+//! 后端读取数据目录以外目录的授权。
+//!
+//! 后端需要读取用户的种子下载目录，但此前只是隐式继承了用户的全部权限，用户无从知晓。WebUI 通过
+//! `request_path_access(path, purpose)` 申请，桌面壳弹出系统确认框，用户允许后把目录记入桌面设置
+//! `allowed_paths`，并在启动服务时以 `PTNEXUS_ALLOWED_PATHS`（按系统的 PATH 分隔符拼接）传给子进程，
+//! 后端可据此限制读取范围。新的授权在服务下次启动时进入环境变量。撤销授权时发出 `path-access-revoked`
+//! 事件（含被撤销的目录），WebUI 据此通知后端停止对该目录的监视。
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::{datadir, runtime, settings};
+
+pub const ENV_KEY: &str = "PTNEXUS_ALLOWED_PATHS";
+
+/// 一条已授权的目录。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PathGrant {
+    pub path: PathBuf,
+    /// 申请时说明的用途。
+    pub purpose: String,
+    /// 授权时间（Unix 秒）。
+    pub granted_at: u64,
+}
+
+/// 申请读取目录：已被授权（含上级目录已授权）时直接返回 true，否则弹窗询问，返回用户是否允许。
+pub fn request(app: &AppHandle, path: &str, purpose: &str) -> Result<bool, String> {
+    let purpose = purpose.trim();
+    if purpose.is_empty() {
+        return Err("请说明需要读取该目录的用途".to_string());
+    }
+    let path = normalize(path)?;
+    let grants = settings::current(app).allowed_paths;
+    if covers(&grants, &path) {
+        return Ok(true);
+    }
+    if datadir::active_profile(app).is_ok_and(|profile| path.starts_with(&profile.root)) {
+        return Ok(true);
+    }
+
+    let allowed = app
+        .dialog()
+        .message(format!(
+            "PT Nexus 后端请求读取以下目录及其子目录：\n\n{}\n\n用途：{purpose}\n\n可以随时在设置的「已授权目录」中撤销。",
+            path.display()
+        ))
+        .title("PT Nexus 目录访问授权")
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::OkCancelCustom("允许".to_string(), "拒绝".to_string()))
+        .blocking_show();
+    if !allowed {
+        runtime::shell_log(app, &format!("[INFO] 用户拒绝后端读取 {}", path.display()));
+        return Ok(false);
+    }
+
+    let grant = PathGrant {
+        path: path.clone(),
+        purpose: purpose.to_string(),
+        granted_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+    };
+    settings::update(app, |settings| add(&mut settings.allowed_paths, grant))?;
+    runtime::shell_log(
+        app,
+        &format!("[INFO] 已授权后端读取 {}（{purpose}）", path.display()),
+    );
+    journal::record(
+        app,
+        Severity::Info,
+        None,
+        format!("已授权后端读取 {}", path.display()),
+    );
+    Ok(true)
+}
+
+pub fn list(app: &AppHandle) -> Vec<PathGrant> {
+    settings::current(app).allowed_paths
+}
+
+/// 撤销一个目录的授权并发出 `path-access-revoked` 事件；目录未被授权时报错。
+pub fn revoke(app: &AppHandle, path: &str) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    let mut removed = false;
+    settings::update(app, |settings| {
+        let before = settings.allowed_paths.len();
+        settings.allowed_paths.retain(|grant| grant.path != path);
+        removed = settings.allowed_paths.len() != before;
+    })?;
+    if !removed {
+        return Err(format!("{} 未被授权", path.display()));
+    }
+    runtime::shell_log(
+        app,
+        &format!("[INFO] 已撤销后端对 {} 的读取授权", path.display()),
+    );
+    journal::record(
+        app,
+        Severity::Info,
+        None,
+        format!("已撤销对 {} 的读取授权", path.display()),
+    );
+    events::emit(app, "path-access-revoked", Replay::All, &path);
+    Ok(())
+}
+
+/// 传给子进程的 `PTNEXUS_ALLOWED_PATHS`；没有授权时为空字符串。
+pub fn env_value(grants: &[PathGrant]) -> String {
+    std::env::join_paths(grants.iter().map(|grant| &grant.path))
+        .map(|joined| joined.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// 只接受已存在目录的绝对路径，解析符号链接后保存，避免借链接扩大范围。
+fn normalize(path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(path.trim());
+    if !path.is_absolute() {
+        return Err(format!("需要绝对路径: {}", path.display()));
+    }
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| format!("无法访问 {}: {e}", path.display()))?;
+    if !canonical.is_dir() {
+        return Err(format!("{} 不是目录", path.display()));
+    }
+    Ok(strip_verbatim(canonical))
+}
+
+/// Windows 上 canonicalize 返回 `\\?\C:\...` 形式，后端与界面都不认识，去掉前缀。
+pub(crate) fn strip_verbatim(path: PathBuf) -> PathBuf {
+    let text = path.to_string_lossy();
+    match text.strip_prefix(r"\\?\") {
+        Some(rest) if !rest.starts_with("UNC\\") => PathBuf::from(rest),
+        _ => path,
+    }
+}
+
+fn covers(grants: &[PathGrant], path: &Path) -> bool {
+    grants.iter().any(|grant| path.starts_with(&grant.path))
+}
+
+/// 加入新的授权，已被新目录包含的子目录授权随之合并。
+fn add(grants: &mut Vec<PathGrant>, grant: PathGrant) {
+    grants.retain(|existing| !existing.path.starts_with(&grant.path));
+    grants.push(grant);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    fn grant(path: &Path) -> PathGrant {
+        PathGrant {
+            path: path.to_path_buf(),
+            purpose: "读取下载目录".to_string(),
+            granted_at: 0,
+        }
+    }
+
+    #[test]
+    fn grants_cover_subdirectories_and_merge() {
+        let root = temp_dir("grants");
+        let movies = root.join("movies");
+        std::fs::create_dir_all(&movies).unwrap();
+
+        let movies = normalize(&movies.to_string_lossy()).unwrap();
+        let root = normalize(&root.to_string_lossy()).unwrap();
+        assert!(normalize("relative/dir").is_err());
+        assert!(normalize(&root.join("missing").to_string_lossy()).is_err());
+
+        let mut grants = vec![grant(&movies)];
+        assert!(covers(&grants, &movies.join("2024")));
+        assert!(!covers(&grants, &root));
+        // 名称前缀相同的兄弟目录不在授权范围内
+        assert!(!covers(&grants, &root.join("movies-old")));
+
+        add(&mut grants, grant(&root));
+        assert_eq!(grants, vec![grant(&root)]);
+        assert_eq!(env_value(&grants), root.to_string_lossy());
+        assert_eq!(env_value(&[]), "");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
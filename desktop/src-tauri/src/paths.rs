@@ -0,0 +1,153 @@
+//! 安装目录与数据目录的路径兼容性。
+//!
+//! Windows 上用户名含中文（`C:\Users\张三\...`）或安装目录层级很深时，部分 ANSI 程序与未开启长路径的
+//! Python 会出错。桌面壳自身通过清单声明 longPathAware，并在检查文件是否存在时使用 `\\?\` 前缀；
+//! 传给子进程的路径保持原样，由 PYTHONUTF8 等环境变量保证按 UTF-8 解读。
+//! 同时提供风险检查，供启动自检提前提示。
+
+use std::path::{Component, Path, PathBuf};
+
+/// 传统 Win32 API 的路径长度上限（含结尾的 NUL）。
+pub const MAX_PATH: usize = 260;
+/// 目录路径超过该长度时，其下较深的文件很容易触及 MAX_PATH。
+const DEEP_DIR_WARN_LEN: usize = 180;
+
+/// Windows 上为超长的绝对路径加 `\\?\` 前缀，其他平台或普通长度的路径原样返回。
+pub fn extended(path: &Path) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        if let Some(prefixed) = with_verbatim_prefix(&path.to_string_lossy()) {
+            return PathBuf::from(prefixed);
+        }
+    }
+    path.to_path_buf()
+}
+
+/// 按字符串处理 `\\?\` 前缀，便于在任意平台上测试。
+///
+/// `\\?\` 路径不再经过规范化，所以只处理不含 `.`、`..` 的盘符或 UNC 绝对路径，并统一分隔符。
+fn with_verbatim_prefix(path: &str) -> Option<String> {
+    if path.encode_utf16().count() < MAX_PATH - 12 || path.starts_with(r"\\?\") {
+        return None;
+    }
+    let normalized = path.replace('/', "\\");
+    let has_relative_parts = normalized
+        .split('\\')
+        .skip(1)
+        .any(|part| part == "." || part == "..");
+    if has_relative_parts {
+        return None;
+    }
+
+    if let Some(unc) = normalized.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{unc}"));
+    }
+    let bytes = normalized.as_bytes();
+    if bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\' {
+        return Some(format!(r"\\?\{normalized}"));
+    }
+    None
+}
+
+/// 检查路径是否可能导致子进程或第三方工具出错，返回面向用户的说明。
+pub fn risks(path: &Path) -> Vec<String> {
+    let text = path.to_string_lossy();
+    let mut risks = Vec::new();
+
+    if text.contains('\u{FFFD}') {
+        risks.push("包含无法按 Unicode 解读的字符".to_string());
+    }
+    if cfg!(target_os = "windows") && !text.is_ascii() && !ansi_code_page_is_utf8() {
+        risks.push(
+            "包含中文等非 ASCII 字符，部分第三方工具（如 BDInfo、mpv）可能无法读取".to_string(),
+        );
+    }
+    let len = text.encode_utf16().count();
+    if len >= DEEP_DIR_WARN_LEN {
+        risks.push(format!(
+            "路径过长（{len} 个字符），其下的文件可能超过 {MAX_PATH} 个字符的限制"
+        ));
+    }
+    let separator = if cfg!(target_os = "windows") {
+        ';'
+    } else {
+        ':'
+    };
+    let has_separator = path.components().any(|component| {
+        matches!(component, Component::Normal(part) if part.to_string_lossy().contains(separator))
+    });
+    if has_separator {
+        risks.push(format!(
+            "目录名包含 `{separator}`，会被 PYTHONPATH 当作分隔符"
+        ));
+    }
+    risks
+}
+
+/// 系统 ANSI 代码页是否为 UTF-8（Windows “使用 Unicode UTF-8 提供全球语言支持”）。
+#[cfg(target_os = "windows")]
+fn ansi_code_page_is_utf8() -> bool {
+    use windows::Win32::Globalization::{GetACP, CP_UTF8};
+
+    unsafe { GetACP() == CP_UTF8 }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn ansi_code_page_is_utf8() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_paths_are_left_alone() {
+        assert_eq!(
+            with_verbatim_prefix(r"C:\Program Files\PT Nexus\server"),
+            None
+        );
+    }
+
+    #[test]
+    fn long_disk_and_unc_paths_get_prefixed() {
+        let deep = "子目录\\".repeat(60);
+        let disk = format!(r"C:\Users\张三\{deep}app.py");
+        assert_eq!(with_verbatim_prefix(&disk), Some(format!(r"\\?\{disk}")));
+
+        let unc = format!(r"\\nas\share\{deep}app.py");
+        assert_eq!(
+            with_verbatim_prefix(&unc),
+            Some(format!(r"\\?\UNC\nas\share\{deep}app.py"))
+        );
+
+        let mixed = format!("C:/Users/{}app.py", "dir/".repeat(70));
+        assert!(with_verbatim_prefix(&mixed)
+            .unwrap()
+            .starts_with(r"\\?\C:\Users\dir\"));
+    }
+
+    #[test]
+    fn relative_or_already_prefixed_paths_are_not_prefixed() {
+        let deep = "dir\\".repeat(70);
+        assert_eq!(with_verbatim_prefix(&format!(r"C:\{deep}..\app.py")), None);
+        assert_eq!(with_verbatim_prefix(&format!(r"{deep}app.py")), None);
+        assert_eq!(with_verbatim_prefix(&format!(r"\\?\C:\{deep}app.py")), None);
+    }
+
+    #[test]
+    fn risky_paths_are_reported() {
+        assert!(risks(Path::new("/opt/ptnexus/server")).is_empty());
+        let deep = PathBuf::from("/opt").join("长".repeat(200));
+        assert!(risks(&deep).iter().any(|risk| risk.contains("路径过长")));
+
+        let separator = if cfg!(target_os = "windows") {
+            "a;b"
+        } else {
+            "a:b"
+        };
+        let with_separator = std::env::temp_dir().join(separator).join("server");
+        assert!(risks(&with_separator)
+            .iter()
+            .any(|risk| risk.contains("PYTHONPATH")));
+    }
+}
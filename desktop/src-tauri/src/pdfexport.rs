@@ -0,0 +1,230 @@
+//! 导出页面为 PDF。
+//!
+//! Windows 上直接调用 WebView2 的 PrintToPdf 写入用户选择的文件；macOS / Linux 的 WebView
+//! 没有可用的无界面导出接口，退回到系统打印对话框（用户可在其中选择“存储为 PDF”）。
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+use crate::{runtime, webviewprofile};
+
+const PRINT_WINDOW_LABEL: &str = "print-export";
+/// 导出窗口加载完成后等待 SPA 渲染数据的时间。
+const RENDER_SETTLE: Duration = Duration::from_millis(1500);
+const LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+impl Orientation {
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("") | Some("portrait") => Ok(Self::Portrait),
+            Some("landscape") => Ok(Self::Landscape),
+            Some(other) => Err(format!(
+                "不支持的纸张方向: {other}（可选 portrait / landscape）"
+            )),
+        }
+    }
+}
+
+/// 纸张尺寸（英寸，纵向）。
+pub fn paper_size(value: Option<&str>) -> Result<(f64, f64), String> {
+    match value.map(|v| v.trim().to_ascii_uppercase()).as_deref() {
+        None | Some("") | Some("A4") => Ok((8.27, 11.69)),
+        Some("A3") => Ok((11.69, 16.54)),
+        Some("LETTER") => Ok((8.5, 11.0)),
+        Some("LEGAL") => Ok((8.5, 14.0)),
+        Some(other) => Err(format!(
+            "不支持的纸张大小: {other}（可选 A4 / A3 / Letter / Legal）"
+        )),
+    }
+}
+
+#[derive(Serialize)]
+pub struct PrintResult {
+    /// 实际使用的方式："pdf" 已直接导出；"print_dialog" 已打开系统打印对话框。
+    pub method: &'static str,
+    /// 导出的文件路径；用户取消保存或使用打印对话框时为空。
+    pub path: Option<String>,
+}
+
+pub fn print_to_pdf(
+    app: &AppHandle,
+    route: Option<String>,
+    orientation: Orientation,
+    paper: (f64, f64),
+) -> Result<PrintResult, String> {
+    let route = route.filter(|r| !r.trim().is_empty());
+    let (window, offscreen) = match &route {
+        Some(route) => (open_print_window(app, route)?, true),
+        None => (
+            app.get_webview_window("main")
+                .ok_or_else(|| "主窗口不存在，请指定要导出的页面路径".to_string())?,
+            false,
+        ),
+    };
+
+    let result = export(app, &window, orientation, paper);
+    // 打印对话框依附在导出窗口上，只有直接导出时才能立即关闭
+    if offscreen && !matches!(&result, Ok(r) if r.method == "print_dialog") {
+        let _ = window.close();
+    }
+    result
+}
+
+#[cfg(target_os = "windows")]
+fn export(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    orientation: Orientation,
+    paper: (f64, f64),
+) -> Result<PrintResult, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let Some(path) = app
+        .dialog()
+        .file()
+        .add_filter("PDF", &["pdf"])
+        .set_file_name("ptnexus.pdf")
+        .blocking_save_file()
+    else {
+        return Ok(PrintResult {
+            method: "pdf",
+            path: None,
+        });
+    };
+    let path = path
+        .into_path()
+        .map_err(|e| format!("无法使用所选路径: {e}"))?;
+
+    webview2::print_to_pdf(window, &path, orientation, paper)?;
+    Ok(PrintResult {
+        method: "pdf",
+        path: Some(path.to_string_lossy().to_string()),
+    })
+}
+
+#[cfg(not(target_os = "windows"))]
+fn export(
+    _app: &AppHandle,
+    window: &WebviewWindow,
+    _orientation: Orientation,
+    _paper: (f64, f64),
+) -> Result<PrintResult, String> {
+    let _ = window.show();
+    window
+        .print()
+        .map_err(|e| format!("打开打印对话框失败: {e}"))?;
+    Ok(PrintResult {
+        method: "print_dialog",
+        path: None,
+    })
+}
+
+/// 在隐藏窗口中加载指定路由，等待页面加载与渲染完成。
+fn open_print_window(app: &AppHandle, route: &str) -> Result<WebviewWindow, String> {
+    if let Some(existing) = app.get_webview_window(PRINT_WINDOW_LABEL) {
+        let _ = existing.close();
+    }
+
+    let url = runtime::runtime_url(app)
+        .join(route.trim_start_matches('/'))
+        .map_err(|e| format!("页面路径无效 ({route}): {e}"))?;
+    let (tx, rx) = mpsc::channel();
+    let builder = WebviewWindowBuilder::new(app, PRINT_WINDOW_LABEL, WebviewUrl::External(url))
+        .title("PT Nexus 打印预览")
+        .visible(false)
+        .inner_size(1200.0, 900.0)
+        .on_page_load(move |_, payload| {
+            if matches!(payload.event(), tauri::webview::PageLoadEvent::Finished) {
+                let _ = tx.send(());
+            }
+        });
+    // 与主窗口共用 WebView 数据，导出的页面带着相同的登录状态
+    let window = webviewprofile::apply_shared(app, builder)
+        .build()
+        .map_err(|e| format!("创建导出窗口失败: {e}"))?;
+
+    if rx.recv_timeout(LOAD_TIMEOUT).is_err() {
+        let _ = window.close();
+        return Err(format!("加载页面超时: {route}"));
+    }
+    std::thread::sleep(RENDER_SETTLE);
+    Ok(window)
+}
+
+#[cfg(target_os = "windows")]
+mod webview2 {
+    use std::path::Path;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use tauri::WebviewWindow;
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        ICoreWebView2Environment6, ICoreWebView2_7, COREWEBVIEW2_PRINT_ORIENTATION_LANDSCAPE,
+        COREWEBVIEW2_PRINT_ORIENTATION_PORTRAIT,
+    };
+    use webview2_com::PrintToPdfCompletedHandler;
+    use windows::core::{Interface, HSTRING};
+
+    use super::Orientation;
+
+    const PRINT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    pub fn print_to_pdf(
+        window: &WebviewWindow,
+        path: &Path,
+        orientation: Orientation,
+        (width, height): (f64, f64),
+    ) -> Result<(), String> {
+        let (tx, rx) = mpsc::channel::<Result<(), String>>();
+        let target = HSTRING::from(path.as_os_str());
+
+        window
+            .with_webview(move |webview| {
+                let started = unsafe {
+                    (|| -> windows::core::Result<()> {
+                        let core = webview.controller().CoreWebView2()?;
+                        let core7: ICoreWebView2_7 = core.cast()?;
+                        let environment: ICoreWebView2Environment6 =
+                            webview.environment().cast()?;
+                        let settings = environment.CreatePrintSettings()?;
+                        settings.SetOrientation(match orientation {
+                            Orientation::Portrait => COREWEBVIEW2_PRINT_ORIENTATION_PORTRAIT,
+                            Orientation::Landscape => COREWEBVIEW2_PRINT_ORIENTATION_LANDSCAPE,
+                        })?;
+                        settings.SetPageWidth(width)?;
+                        settings.SetPageHeight(height)?;
+
+                        let done = tx.clone();
+                        let handler = PrintToPdfCompletedHandler::create(Box::new(
+                            move |result: windows::core::Result<()>, succeeded: bool| {
+                                let outcome = match (result, succeeded) {
+                                    (Ok(()), true) => Ok(()),
+                                    (Err(e), _) => Err(format!("WebView2 导出 PDF 失败: {e}")),
+                                    (Ok(()), false) => Err("WebView2 导出 PDF 失败".to_string()),
+                                };
+                                let _ = done.send(outcome);
+                                Ok(())
+                            },
+                        ));
+                        core7.PrintToPdf(&target, &settings, &handler)
+                    })()
+                };
+                if let Err(err) = started {
+                    let _ = tx.send(Err(format!("调用 WebView2 PrintToPdf 失败: {err}")));
+                }
+            })
+            .map_err(|e| format!("访问 WebView 失败: {e}"))?;
+
+        rx.recv_timeout(PRINT_TIMEOUT)
+            .map_err(|_| "导出 PDF 超时".to_string())?
+    }
+}
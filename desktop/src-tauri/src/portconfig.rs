@@ -0,0 +1,323 @@
+//! 端口设置：server、batch、updater 三个服务的监听端口。
+//!
+//! 端口写入 runtime.env 的 SERVER_PORT / BATCH_PORT / UPDATER_PORT，重启服务后生效。
+//! GO_SERVICE_URL、CORE_API_URL 未显式设置时启动时会自动推导；显式设置且端口与旧端口一致时，
+//! 这里同步改写其中的端口，BATCH_ENHANCER_PORT 同理，避免跨服务调用仍指向旧端口。
+
+use std::collections::BTreeMap;
+use std::net::TcpListener;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{bindings, health, runtime};
+
+const MIN_PORT: u32 = 1024;
+const MAX_PORT: u32 = 65535;
+const DEFAULT_SERVER_PORT: u16 = 5275;
+const DEFAULT_BATCH_PORT: u16 = 5276;
+/// 端口被占用时给出的备选数量。
+const SUGGESTION_COUNT: usize = 3;
+/// 常见服务的默认端口，即使当前空闲也不建议使用，以免日后与这些服务冲突。
+const COMMON_SERVICE_PORTS: &[u16] = &[
+    1433, 1521, 3000, 3306, 3389, 5000, 5432, 5900, 6379, 8000, 8080, 8081, 8443, 8888, 9000, 9090,
+    27017,
+];
+
+/// 使用 u32 接收前端输入，超出 u16 的值也能给出范围提示，而不是反序列化失败。
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PortConfig {
+    pub server: u32,
+    pub batch: u32,
+    pub updater: u32,
+}
+
+impl PortConfig {
+    fn fields(&self) -> [(&'static str, &'static str, u32); 3] {
+        [
+            ("server", "SERVER_PORT", self.server),
+            ("batch", "BATCH_PORT", self.batch),
+            ("updater", "UPDATER_PORT", self.updater),
+        ]
+    }
+}
+
+/// 校验或保存失败的原因：`字段 -> 错误信息`，端口被占用时附带建议改用的空闲端口。
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct PortProblems {
+    /// 汇总的错误信息，可直接显示给用户。
+    pub message: String,
+    pub errors: BTreeMap<&'static str, String>,
+    pub suggestions: BTreeMap<&'static str, Vec<u16>>,
+}
+
+impl PortProblems {
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl From<String> for PortProblems {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            ..Self::default()
+        }
+    }
+}
+
+/// runtime.env（其次是宿主环境变量）中配置的端口，即下次启动使用的端口。
+pub fn current(app: &AppHandle) -> PortConfig {
+    let port = |key: &str, default: u16| {
+        runtime::read_runtime_setting(app, key)
+            .and_then(|value| value.trim().parse::<u16>().ok())
+            .unwrap_or(default)
+    };
+    PortConfig {
+        server: u32::from(port("SERVER_PORT", DEFAULT_SERVER_PORT)),
+        batch: u32::from(port("BATCH_PORT", DEFAULT_BATCH_PORT)),
+        updater: u32::from(port("UPDATER_PORT", runtime::UPDATER_PORT)),
+    }
+}
+
+/// 校验端口设置；结果为空表示可以保存。
+pub fn validate(app: &AppHandle, config: &PortConfig) -> PortProblems {
+    let ours = running_ports(app);
+    let requested: Vec<u32> = config.fields().iter().map(|(_, _, port)| *port).collect();
+    check(config, |port| {
+        // 本应用的服务正在使用的端口，重启时会先释放
+        if ours.contains(&port) || is_free(port) {
+            return None;
+        }
+        let suggestions = suggest(port, SUGGESTION_COUNT, |candidate| {
+            !requested.contains(&u32::from(candidate)) && is_free(candidate)
+        });
+        let owner = match bindings::port_owner(port) {
+            Some(owner) => format!("端口 {port} 已被 {owner} 占用"),
+            None => format!("端口 {port} 已被其他程序占用"),
+        };
+        Some((with_suggestions(owner, &suggestions), suggestions))
+    })
+}
+
+/// 从 `near` 起向上查找 `count` 个当前可以监听的端口，跳过 1024 以下的知名端口与常见服务的端口。
+pub fn suggest_free_ports(near: u16, count: usize) -> Vec<u16> {
+    suggest(near, count, is_free)
+}
+
+/// 在错误信息后附上备选端口，如 “端口 5275 已被占用，可改用 5277、5278、5279”。
+pub fn with_suggestions(message: String, suggestions: &[u16]) -> String {
+    if suggestions.is_empty() {
+        return message;
+    }
+    let ports: Vec<String> = suggestions.iter().map(u16::to_string).collect();
+    format!("{message}，可改用 {}", ports.join("、"))
+}
+
+fn suggest(near: u16, count: usize, is_free: impl Fn(u16) -> bool) -> Vec<u16> {
+    (near.max(MIN_PORT as u16)..=u16::MAX)
+        .filter(|port| !COMMON_SERVICE_PORTS.contains(port))
+        .filter(|port| is_free(*port))
+        .take(count)
+        .collect()
+}
+
+fn is_free(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+/// 校验后写入 runtime.env，返回是否需要重启服务才能生效。
+pub fn save(app: &AppHandle, config: &PortConfig) -> Result<bool, PortProblems> {
+    let problems = validate(app, config);
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    let previous = current(app);
+    let mut updates: Vec<(&str, Option<String>)> = config
+        .fields()
+        .iter()
+        .map(|(_, key, port)| (*key, Some(port.to_string())))
+        .collect();
+    for (key, old, new) in [
+        ("GO_SERVICE_URL", previous.batch, config.batch),
+        ("CORE_API_URL", previous.server, config.server),
+    ] {
+        if let Some(url) = runtime::read_runtime_setting(app, key) {
+            if let Some(rewritten) = rewrite_url_port(&url, old, new) {
+                updates.push((key, Some(rewritten)));
+            }
+        }
+    }
+    if runtime::read_runtime_setting(app, "BATCH_ENHANCER_PORT")
+        .is_some_and(|port| port.trim() == previous.batch.to_string())
+    {
+        updates.push(("BATCH_ENHANCER_PORT", Some(config.batch.to_string())));
+    }
+
+    let env_file = runtime::ensure_runtime_env_file(app)?;
+    let updates: Vec<(&str, Option<&str>)> = updates
+        .iter()
+        .map(|(key, value)| (*key, value.as_deref()))
+        .collect();
+    runtime::update_env_file(&env_file, &updates)?;
+    runtime::shell_log(
+        app,
+        &format!(
+            "[INFO] 端口设置已更新: server={} batch={} updater={}",
+            config.server, config.batch, config.updater
+        ),
+    );
+
+    // 已通过范围校验，转换不会截断
+    let running = running_ports(app);
+    Ok(config
+        .fields()
+        .iter()
+        .any(|(_, _, port)| !running.contains(&(*port as u16))))
+}
+
+/// 当前运行中的服务占用的端口；运行时未启动时为空。
+fn running_ports(app: &AppHandle) -> Vec<u16> {
+    if app.try_state::<runtime::RuntimeManager>().is_none() {
+        return Vec::new();
+    }
+    let webui = runtime::runtime_url(app);
+    let mut ports: Vec<u16> = health::service_ports(app)
+        .into_iter()
+        .map(|(_, port)| port)
+        .collect();
+    ports.push(
+        webui
+            .port_or_known_default()
+            .unwrap_or(runtime::UPDATER_PORT),
+    );
+    ports
+}
+
+/// `in_use` 对被占用的端口返回错误信息与备选端口。
+fn check(config: &PortConfig, in_use: impl Fn(u16) -> Option<(String, Vec<u16>)>) -> PortProblems {
+    let mut problems = PortProblems::default();
+    let fields = config.fields();
+    for (index, (field, _, port)) in fields.iter().enumerate() {
+        if !(MIN_PORT..=MAX_PORT).contains(port) {
+            problems
+                .errors
+                .insert(*field, format!("端口需在 {MIN_PORT}–{MAX_PORT} 之间"));
+            continue;
+        }
+        if let Some((other, _, _)) = fields[..index].iter().find(|(_, _, other)| other == port) {
+            problems
+                .errors
+                .insert(*field, format!("与 {other} 的端口相同"));
+            continue;
+        }
+        if let Some((message, suggestions)) = in_use(*port as u16) {
+            problems.errors.insert(*field, message);
+            problems.suggestions.insert(*field, suggestions);
+        }
+    }
+    problems.message = problems
+        .errors
+        .values()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    problems
+}
+
+/// URL 中的端口等于 `old` 时改为 `new`，其余部分保持不变；端口不一致（指向其他服务）时返回 None。
+fn rewrite_url_port(url: &str, old: u32, new: u32) -> Option<String> {
+    let trimmed = url.trim();
+    let (scheme, rest) = trimmed.split_once("://")?;
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, tail) = rest.split_at(authority_end);
+    let host_end = authority.rfind(']').map_or(0, |index| index + 1);
+    let (host, port) = authority[host_end..]
+        .rsplit_once(':')
+        .map(|(host_tail, port)| (&authority[..host_end + host_tail.len()], port))?;
+    (port.parse::<u32>().ok()? == old && old != new)
+        .then(|| format!("{scheme}://{host}:{new}{tail}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(server: u32, batch: u32, updater: u32) -> PortConfig {
+        PortConfig {
+            server,
+            batch,
+            updater,
+        }
+    }
+
+    #[test]
+    fn ports_are_checked_for_range_and_uniqueness() {
+        let free = |_: u16| None;
+        assert!(check(&config(5275, 5276, 5274), free).is_empty());
+
+        let errors = check(&config(80, 70000, 5274), free).errors;
+        assert!(errors["server"].contains("1024"));
+        assert!(errors["batch"].contains("65535"));
+
+        let errors = check(&config(6000, 6000, 6001), free).errors;
+        assert_eq!(errors.len(), 1);
+        assert!(errors["batch"].contains("server"));
+    }
+
+    #[test]
+    fn occupied_ports_report_the_owner() {
+        let problems = check(&config(5275, 5276, 8080), |port| {
+            (port == 8080).then(|| ("端口 8080 已被 nginx (PID 88) 占用".to_string(), vec![8082]))
+        });
+        assert_eq!(problems.errors.len(), 1);
+        assert!(problems.errors["updater"].contains("nginx"));
+        assert_eq!(problems.suggestions["updater"], vec![8082]);
+        assert_eq!(problems.message, problems.errors["updater"]);
+    }
+
+    #[test]
+    fn suggestions_skip_occupied_and_common_ports() {
+        // 占用一个临时端口及其后一个端口，建议应从更高的端口开始
+        let first = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = first.local_addr().unwrap().port();
+        let second = base
+            .checked_add(1)
+            .and_then(|port| TcpListener::bind(("127.0.0.1", port)).ok());
+        let suggestions = suggest_free_ports(base, SUGGESTION_COUNT);
+        assert!(suggestions.len() <= SUGGESTION_COUNT);
+        assert!(!suggestions.contains(&base));
+        if let Some(second) = &second {
+            assert!(!suggestions.contains(&second.local_addr().unwrap().port()));
+        }
+        assert!(suggestions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(suggestions
+            .iter()
+            .all(|port| *port > base && is_free(*port)));
+
+        assert_eq!(suggest(8080, 2, |_| true), vec![8082, 8083]);
+        assert_eq!(suggest(80, 1, |_| true), vec![1024]);
+        assert!(suggest(u16::MAX, 3, |_| false).is_empty());
+        assert_eq!(
+            with_suggestions("端口 5275 已被占用".to_string(), &[5277, 5278]),
+            "端口 5275 已被占用，可改用 5277、5278"
+        );
+    }
+
+    #[test]
+    fn explicit_urls_follow_the_new_port() {
+        assert_eq!(
+            rewrite_url_port("http://127.0.0.1:5276", 5276, 6276).as_deref(),
+            Some("http://127.0.0.1:6276")
+        );
+        assert_eq!(
+            rewrite_url_port("http://[::1]:5275/api", 5275, 6275).as_deref(),
+            Some("http://[::1]:6275/api")
+        );
+        // 指向其他端口的地址是用户有意设置的，不改写
+        assert_eq!(rewrite_url_port("http://batch.lan:9000", 5276, 6276), None);
+        assert_eq!(rewrite_url_port("http://127.0.0.1", 80, 6276), None);
+        assert_eq!(rewrite_url_port("http://127.0.0.1:5276", 5276, 5276), None);
+    }
+}
@@ -0,0 +1,144 @@
+//! 空闲节能：所有窗口隐藏或失去焦点超过一定时间后，降低后台轮询频率。
+//!
+//! 空闲时长由桌面设置 `idle_after_minutes` 控制（0 表示不进入节能）。窗口重新显示/获得焦点，
+//! 或出现需要提醒用户的事件时立即恢复。
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::settings;
+use crate::tasks::CancelToken;
+
+/// 节能模式下健康检查的间隔。
+pub const SAVER_HEALTH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// 等待期间检查是否需要提前恢复的粒度。
+const WAKE_CHECK: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerProfile {
+    Normal,
+    Saver,
+}
+
+pub struct PowerState {
+    last_active: Mutex<Instant>,
+}
+
+impl Default for PowerState {
+    fn default() -> Self {
+        Self {
+            last_active: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PowerStatus {
+    pub profile: PowerProfile,
+    pub idle_seconds: u64,
+    pub idle_after_minutes: u32,
+}
+
+/// 按“当前是否活跃、已空闲多久、空闲阈值”决定节能档位。
+pub fn decide(active_now: bool, idle_for: Duration, idle_after_minutes: u32) -> PowerProfile {
+    if active_now || idle_after_minutes == 0 {
+        return PowerProfile::Normal;
+    }
+    if idle_for >= Duration::from_secs(u64::from(idle_after_minutes) * 60) {
+        PowerProfile::Saver
+    } else {
+        PowerProfile::Normal
+    }
+}
+
+/// 标记为活跃：窗口获得焦点、显示，或有需要提醒的事件。
+pub fn wake(app: &AppHandle) {
+    if let Some(state) = app.try_state::<PowerState>() {
+        if let Ok(mut last_active) = state.last_active.lock() {
+            *last_active = Instant::now();
+        }
+    }
+}
+
+pub fn status(app: &AppHandle) -> PowerStatus {
+    // 日志查看器等辅助窗口处于前台同样算活跃
+    let active_now = app
+        .webview_windows()
+        .values()
+        .any(|w| w.is_visible().unwrap_or(false) && w.is_focused().unwrap_or(false));
+    if active_now {
+        wake(app);
+    }
+
+    let idle_for = app
+        .try_state::<PowerState>()
+        .and_then(|state| state.last_active.lock().ok().map(|t| t.elapsed()))
+        .unwrap_or_default();
+    let idle_after_minutes = settings::current(app).idle_after_minutes;
+
+    PowerStatus {
+        profile: decide(active_now, idle_for, idle_after_minutes),
+        idle_seconds: idle_for.as_secs(),
+        idle_after_minutes,
+    }
+}
+
+pub fn profile(app: &AppHandle) -> PowerProfile {
+    status(app).profile
+}
+
+/// 按当前档位等待下一轮轮询：正常档位等待 `normal`，节能档位等待更久，但恢复活跃时立即返回。
+/// 任务被取消时返回 false。
+pub fn sleep(app: &AppHandle, token: &CancelToken, normal: Duration, saver: Duration) -> bool {
+    let started = Instant::now();
+    loop {
+        let target = match profile(app) {
+            PowerProfile::Normal => normal,
+            PowerProfile::Saver => saver,
+        };
+        let elapsed = started.elapsed();
+        if elapsed >= target {
+            return true;
+        }
+        if !token.sleep((target - elapsed).min(WAKE_CHECK)) {
+            return false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_window_is_always_normal() {
+        assert_eq!(
+            decide(true, Duration::from_secs(3600), 10),
+            PowerProfile::Normal
+        );
+    }
+
+    #[test]
+    fn saver_after_idle_threshold() {
+        assert_eq!(
+            decide(false, Duration::from_secs(9 * 60), 10),
+            PowerProfile::Normal
+        );
+        assert_eq!(
+            decide(false, Duration::from_secs(10 * 60), 10),
+            PowerProfile::Saver
+        );
+    }
+
+    #[test]
+    fn zero_threshold_disables_saver() {
+        assert_eq!(
+            decide(false, Duration::from_secs(86400), 0),
+            PowerProfile::Normal
+        );
+    }
+}
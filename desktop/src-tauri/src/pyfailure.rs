@@ -0,0 +1,165 @@
+//! 从服务的 stderr 输出识别常见的 Python 启动失败。
+//!
+//! 用户提交的日志里反复出现同几类错误：安全软件删除了某个 .pyd、更新中断留下损坏的 .pyc、
+//! 内置运行时不完整、数据目录不可写。服务启动失败时用 [`analyze`] 匹配错误信息中的 stderr 片段，
+//! 命中后启动失败提示给出专门的错误码、处理建议与对应的操作按钮。
+//! 新增特征只需在 [`SIGNATURES`] 中追加一项，并在测试中补充一段实际的 stderr 输出。
+
+use crate::erroraction::ErrorAction;
+
+/// 一类失败的特征。
+#[derive(Debug, PartialEq)]
+pub struct Signature {
+    /// 命令错误码，同时列入 commanderror.rs 的 CODES。
+    pub code: &'static str,
+    /// 任一片段出现在某行 stderr 中即视为命中（区分大小写）。
+    pub patterns: &'static [&'static str],
+    pub summary: &'static str,
+    pub remedy: &'static str,
+    /// 启动失败提示上优先显示的操作。
+    pub action: ErrorAction,
+}
+
+/// 按优先级排列：同一行命中多项时取靠前的一项，如 `ImportError: DLL load failed` 归为 DLL 加载失败。
+pub const SIGNATURES: &[Signature] = &[
+    Signature {
+        code: "python_bytecode_corrupt",
+        patterns: &["bad magic number", "bad marshal data", "marshal data too short"],
+        summary: "Python 字节码文件（.pyc）已损坏",
+        remedy: "通常是更新中断或磁盘错误所致，重新安装 Python 运行时即可恢复；仍失败时请重新安装应用。",
+        action: ErrorAction::ReinstallPython,
+    },
+    Signature {
+        code: "python_runtime_broken",
+        patterns: &[
+            "No module named 'encodings'",
+            "Could not find platform independent libraries",
+            "init_fs_encoding",
+        ],
+        summary: "内置 Python 运行时不完整",
+        remedy: "标准库文件缺失，通常是解压中断或被安全软件删除，重新安装 Python 运行时即可恢复。",
+        action: ErrorAction::ReinstallPython,
+    },
+    Signature {
+        code: "python_dll_load_failed",
+        patterns: &["DLL load failed", "cannot open shared object file", "Library not loaded"],
+        summary: "Python 扩展模块无法加载",
+        remedy: "运行目录中的 .pyd / .dll 可能被安全软件删除或拦截，也可能缺少 VC++ 运行库。\
+                 请先检查运行文件，并将安装目录加入安全软件白名单。",
+        action: ErrorAction::VerifyRuntime,
+    },
+    Signature {
+        code: "database_open_failed",
+        patterns: &["sqlite3.OperationalError: unable to open database file"],
+        summary: "无法打开 SQLite 数据库文件",
+        remedy: "数据目录不存在、没有写入权限，或所在磁盘已断开。请检查数据目录及其权限后重试。",
+        action: ErrorAction::OpenDataDir,
+    },
+    Signature {
+        code: "python_module_missing",
+        patterns: &["ModuleNotFoundError:"],
+        summary: "Python 模块缺失",
+        remedy: "运行目录中的文件可能被安全软件删除，或上次更新没有完成。重新安装 Python 运行时后重试，仍失败时请重新安装应用。",
+        action: ErrorAction::ReinstallPython,
+    },
+    Signature {
+        code: "python_import_failed",
+        patterns: &["ImportError:"],
+        summary: "Python 模块导入失败",
+        remedy: "运行文件可能不完整或版本不一致，请先检查运行文件，仍失败时重新安装应用。",
+        action: ErrorAction::VerifyRuntime,
+    },
+];
+
+/// 从最后一行向前查找，返回第一处命中的特征：traceback 的最后一行就是最终抛出的异常。
+pub fn analyze(stderr: &str) -> Option<&'static Signature> {
+    stderr.lines().rev().find_map(|line| {
+        SIGNATURES.iter().find(|signature| {
+            signature
+                .patterns
+                .iter()
+                .any(|pattern| line.contains(pattern))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DLL_LOAD_FAILED: &str = r#"Traceback (most recent call last):
+  File "C:\Program Files\PT Nexus\server\app.py", line 12, in <module>
+    from database import init_db
+  File "C:\Program Files\PT Nexus\server\database.py", line 3, in <module>
+    import sqlite3
+  File "C:\Program Files\PT Nexus\server\python\Lib\sqlite3\__init__.py", line 57, in <module>
+    from sqlite3.dbapi2 import *
+  File "C:\Program Files\PT Nexus\server\python\Lib\sqlite3\dbapi2.py", line 27, in <module>
+    from _sqlite3 import *
+ImportError: DLL load failed while importing _sqlite3: 找不到指定的模块。"#;
+
+    const MODULE_NOT_FOUND: &str = r#"Traceback (most recent call last):
+  File "/Applications/PT Nexus.app/Contents/Resources/server/background_runner.py", line 8, in <module>
+    from apscheduler.schedulers.blocking import BlockingScheduler
+ModuleNotFoundError: No module named 'apscheduler'"#;
+
+    const SQLITE_OPEN: &str = r#"[2026-03-02 09:14:07] INFO 正在初始化数据库
+Traceback (most recent call last):
+  File "D:\PTNexus\server\app.py", line 40, in create_app
+    conn = sqlite3.connect(db_path)
+sqlite3.OperationalError: unable to open database file"#;
+
+    const BAD_MAGIC: &str = r#"Traceback (most recent call last):
+  File "<frozen importlib._bootstrap>", line 1178, in _find_and_load
+  File "<frozen importlib._bootstrap_external>", line 1006, in get_code
+ImportError: bad magic number in 'core.services': b'\x03\xf3\r\n'"#;
+
+    const ENCODINGS_MISSING: &str = r#"Python path configuration:
+  PYTHONHOME = 'C:\Users\me\AppData\Roaming\com.ptnexus.desktop\python-runtime'
+  PYTHONPATH = (not set)
+Fatal Python error: init_fs_encoding: failed to get the Python codec of the filesystem encoding
+Python runtime state: core initialized
+ModuleNotFoundError: No module named 'encodings'"#;
+
+    const LINUX_SHARED_OBJECT: &str = r#"Traceback (most recent call last):
+  File "/opt/ptnexus/server/app.py", line 5, in <module>
+    import psutil
+ImportError: libpython3.11.so.1.0: cannot open shared object file: No such file or directory"#;
+
+    fn code(stderr: &str) -> Option<&'static str> {
+        analyze(stderr).map(|signature| signature.code)
+    }
+
+    #[test]
+    fn captured_stderr_maps_to_codes() {
+        assert_eq!(code(DLL_LOAD_FAILED), Some("python_dll_load_failed"));
+        assert_eq!(code(MODULE_NOT_FOUND), Some("python_module_missing"));
+        assert_eq!(code(SQLITE_OPEN), Some("database_open_failed"));
+        assert_eq!(code(BAD_MAGIC), Some("python_bytecode_corrupt"));
+        assert_eq!(code(ENCODINGS_MISSING), Some("python_runtime_broken"));
+        assert_eq!(code(LINUX_SHARED_OBJECT), Some("python_dll_load_failed"));
+        assert_eq!(
+            code("ImportError: cannot import name 'x' from 'y'"),
+            Some("python_import_failed")
+        );
+        assert_eq!(code("OSError: [Errno 98] Address already in use"), None);
+        assert_eq!(
+            analyze(SQLITE_OPEN).unwrap().action,
+            ErrorAction::OpenDataDir
+        );
+    }
+
+    #[test]
+    fn signatures_are_well_formed() {
+        for (index, signature) in SIGNATURES.iter().enumerate() {
+            assert!(signature
+                .code
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c == '_'));
+            assert!(!signature.patterns.is_empty() && !signature.remedy.is_empty());
+            assert!(SIGNATURES[index + 1..]
+                .iter()
+                .all(|other| other.code != signature.code));
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! 内置 Python 运行时的压缩包布局。
+//!
+//! `server/python/` 由数千个小文件组成，杀毒软件每次启动都会重新扫描，拖慢冷启动。
+//! 运行目录根部存在 `python.zip` 时，首次启动按 `python.zip.sha256` 校验后把它解压到
+//! 数据目录的 `python-runtime/` 并写入版本标记，之后压缩包没变就直接复用，不再读取压缩包。
+//! 安装包总会附带 `python.zip.sha256`（见 build-windows-installer-linux.sh），缺少时视为安装不完整。
+//! 没有压缩包的旧安装仍然使用 `server/python/` 原地布局。
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+
+use crate::runtime;
+
+const ARCHIVE_NAME: &str = "python.zip";
+const HASH_SIDECAR_NAME: &str = "python.zip.sha256";
+const EXTRACT_DIR_NAME: &str = "python-runtime";
+const MARKER_NAME: &str = ".ptnexus-python-version";
+/// 每解压多少个文件上报一次进度
+const PROGRESS_EVERY: usize = 200;
+
+/// 不校验、不解压时 Python 运行时所在的目录，供启动干跑计划使用。
+pub fn expected_python_home(runtime_root: &Path, server_dir: &Path, data_dir: &Path) -> PathBuf {
+    if runtime_root.join(ARCHIVE_NAME).exists() {
+        data_dir.join(EXTRACT_DIR_NAME)
+    } else {
+        server_dir.join("python")
+    }
+}
+
+/// 返回 Python 运行时目录：有压缩包时为解压后的目录，否则为 `server/python`。
+///
+/// 标记记录解压时压缩包的大小、修改时间与 SHA-256。大小与修改时间都没变就直接复用，不读压缩包；
+/// 变了（升级、重装）先比较 `python.zip.sha256` 与标记中的哈希，一致说明内容相同，只更新标记；
+/// 不一致才计算压缩包的哈希并与 sidecar 核对，通过后重新解压。
+pub fn resolve_python_home(
+    app: &AppHandle,
+    runtime_root: &Path,
+    server_dir: &Path,
+    data_dir: &Path,
+) -> Result<PathBuf, String> {
+    let archive = runtime_root.join(ARCHIVE_NAME);
+    if !archive.exists() {
+        return Ok(server_dir.join("python"));
+    }
+
+    let target = data_dir.join(EXTRACT_DIR_NAME);
+    let marker_path = target.join(MARKER_NAME);
+    let installed = fs::read_to_string(&marker_path)
+        .ok()
+        .map(|content| Marker::parse(&content));
+    let fingerprint = Fingerprint::of(&archive)?;
+    let extracted = target.join(runtime::exe_name("python")).exists();
+    if extracted
+        && installed
+            .as_ref()
+            .is_some_and(|marker| marker.fingerprint == Some(fingerprint))
+    {
+        return Ok(target);
+    }
+
+    // 没有 sidecar 就无从校验：用压缩包自身的哈希去比较等于没有校验
+    let sidecar = runtime_root.join(HASH_SIDECAR_NAME);
+    let Some(expected_hash) = read_hash_sidecar(&sidecar) else {
+        return Err(format!(
+            "缺少 Python 运行时压缩包的校验文件：{}\n无法确认 {} 完整，请重新安装应用。",
+            sidecar.display(),
+            archive.display()
+        ));
+    };
+    let marker = Marker {
+        sha256: expected_hash.clone(),
+        fingerprint: Some(fingerprint),
+    };
+    if extracted && installed.is_some_and(|installed| installed.sha256 == expected_hash) {
+        // 压缩包内容未变（如重装同一版本），解压目录仍可用
+        fs::write(&marker_path, marker.render())
+            .map_err(|e| format!("写入 {} 失败: {e}", marker_path.display()))?;
+        return Ok(target);
+    }
+
+    runtime::emit_stage(
+        app,
+        "extract-python",
+        "首次启动，正在校验 Python 运行时".to_string(),
+    );
+    let actual_hash = sha256_file(&archive)?;
+    if actual_hash != expected_hash {
+        return Err(format!(
+            "Python 运行时压缩包校验失败：{}\n期望 SHA-256 {expected_hash}，实际 {actual_hash}。请重新安装应用。",
+            archive.display()
+        ));
+    }
+
+    // 先解压到临时目录，全部成功后再替换，避免接受解压了一半的运行时
+    let partial = data_dir.join(format!("{EXTRACT_DIR_NAME}.partial"));
+    let _ = fs::remove_dir_all(&partial);
+    let extracted = extract(&archive, &partial, |done, total| {
+        if done % PROGRESS_EVERY == 0 || done == total {
+            runtime::emit_stage(
+                app,
+                "extract-python",
+                format!("正在解压 Python 运行时 {done}/{total}"),
+            );
+        }
+    });
+    if let Err(err) = extracted {
+        let _ = fs::remove_dir_all(&partial);
+        return Err(err);
+    }
+
+    let finish = || -> io::Result<()> {
+        fs::write(partial.join(MARKER_NAME), marker.render())?;
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(&partial, &target)
+    };
+    if let Err(e) = finish() {
+        let _ = fs::remove_dir_all(&partial);
+        return Err(format!(
+            "安装 Python 运行时失败 ({}): {e}",
+            target.display()
+        ));
+    }
+
+    runtime::emit_stage(app, "extract-python", "Python 运行时已就绪".to_string());
+    Ok(target)
+}
+
+/// 压缩包的大小与修改时间，用来在不读内容的情况下判断压缩包是否变过。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Fingerprint {
+    size: u64,
+    /// 修改时间，距 UNIX 纪元的纳秒数。
+    modified: u128,
+}
+
+impl Fingerprint {
+    fn of(path: &Path) -> Result<Self, String> {
+        let metadata =
+            fs::metadata(path).map_err(|e| format!("读取 {} 失败: {e}", path.display()))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_nanos());
+        Ok(Self {
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// 解压目录中的版本标记。
+#[derive(Debug, PartialEq, Eq)]
+struct Marker {
+    sha256: String,
+    /// 旧版本的标记只有哈希，没有指纹。
+    fingerprint: Option<Fingerprint>,
+}
+
+impl Marker {
+    fn parse(content: &str) -> Self {
+        let mut sha256 = String::new();
+        let (mut size, mut modified) = (None, None);
+        for line in content.lines() {
+            match line.trim().split_once('=') {
+                Some(("sha256", value)) => sha256 = value.trim().to_string(),
+                Some(("size", value)) => size = value.trim().parse().ok(),
+                Some(("mtime", value)) => modified = value.trim().parse().ok(),
+                Some(_) => {}
+                // 旧版本的标记只有一行哈希
+                None if sha256.is_empty() => sha256 = line.trim().to_string(),
+                None => {}
+            }
+        }
+        let fingerprint = size
+            .zip(modified)
+            .map(|(size, modified)| Fingerprint { size, modified });
+        Self {
+            sha256,
+            fingerprint,
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut content = format!("sha256={}\n", self.sha256);
+        if let Some(fingerprint) = self.fingerprint {
+            content.push_str(&format!(
+                "size={}\nmtime={}\n",
+                fingerprint.size, fingerprint.modified
+            ));
+        }
+        content
+    }
+}
+
+/// 不解压即可确定的 Python 运行时目录；压缩包尚未解压时为 None。
+pub fn installed_python_home(
+    runtime_root: &Path,
+    server_dir: &Path,
+    data_dir: &Path,
+) -> Option<PathBuf> {
+    if !runtime_root.join(ARCHIVE_NAME).exists() {
+        return Some(server_dir.join("python"));
+    }
+    let target = data_dir.join(EXTRACT_DIR_NAME);
+    target
+        .join(runtime::exe_name("python"))
+        .exists()
+        .then_some(target)
+}
+
+/// 删除解压目录的版本标记，下次启动时重新校验压缩包并完整解压；没有压缩包时返回 false。
+/// 只删标记不删目录：服务可能仍占用其中的文件，旧目录在重新解压成功后才被替换。
+pub fn invalidate_extracted(runtime_root: &Path, data_dir: &Path) -> Result<bool, String> {
+    if !runtime_root.join(ARCHIVE_NAME).exists() {
+        return Ok(false);
+    }
+    let marker = data_dir.join(EXTRACT_DIR_NAME).join(MARKER_NAME);
+    match fs::remove_file(&marker) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(format!("删除 {} 失败: {e}", marker.display())),
+    }
+}
+
+/// 解压到 `dest`，每解压一个条目调用一次 `progress(已完成, 总数)`。
+/// 保留压缩包中记录的 Unix 权限位，Linux/macOS 上的 `bin/python3` 等才能执行。
+fn extract(
+    archive_path: &Path,
+    dest: &Path,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<(), String> {
+    let file = File::open(archive_path)
+        .map_err(|e| format!("打开 {} 失败: {e}", archive_path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("读取 {} 失败: {e}", archive_path.display()))?;
+
+    let total = archive.len();
+    for index in 0..total {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| format!("读取压缩包条目失败: {e}"))?;
+        let Some(relative) = entry.enclosed_name() else {
+            return Err(format!("压缩包包含非法路径: {}", entry.name()));
+        };
+        let out_path = dest.join(relative);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path)
+                .map_err(|e| format!("创建目录失败 ({}): {e}", out_path.display()))?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("创建目录失败 ({}): {e}", parent.display()))?;
+            }
+            let mut out_file = File::create(&out_path)
+                .map_err(|e| format!("写入文件失败 ({}): {e}", out_path.display()))?;
+            io::copy(&mut entry, &mut out_file)
+                .map_err(|e| format!("解压文件失败 ({}): {e}", out_path.display()))?;
+        }
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&out_path, fs::Permissions::from_mode(mode & 0o7777))
+                .map_err(|e| format!("设置文件权限失败 ({}): {e}", out_path.display()))?;
+        }
+
+        progress(index + 1, total);
+    }
+
+    Ok(())
+}
+
+fn read_hash_sidecar(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let hash = content.split_whitespace().next()?.to_ascii_lowercase();
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())).then_some(hash)
+}
+
+pub(crate) fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("打开 {} 失败: {e}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| format!("读取 {} 失败: {e}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn marker_round_trips_and_reads_legacy_hash_only_markers() {
+        let marker = Marker {
+            sha256: "ab".repeat(32),
+            fingerprint: Some(Fingerprint {
+                size: 42,
+                modified: 1_700_000_000_123_456_789,
+            }),
+        };
+        assert_eq!(Marker::parse(&marker.render()), marker);
+
+        let legacy = Marker::parse(&format!("{}\n", "cd".repeat(32)));
+        assert_eq!(legacy.sha256, "cd".repeat(32));
+        assert_eq!(legacy.fingerprint, None);
+    }
+
+    #[test]
+    fn fingerprint_changes_when_the_archive_is_replaced() {
+        let dir = temp_dir("fingerprint");
+        let archive = dir.join(ARCHIVE_NAME);
+        fs::write(&archive, b"first").unwrap();
+        let first = Fingerprint::of(&archive).unwrap();
+        assert_eq!(Fingerprint::of(&archive).unwrap(), first);
+        fs::write(&archive, b"second build").unwrap();
+        assert_ne!(Fingerprint::of(&archive).unwrap(), first);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_keeps_unix_permissions() {
+        use std::io::Write;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("extract");
+        let archive = dir.join(ARCHIVE_NAME);
+        let mut writer = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let executable = zip::write::SimpleFileOptions::default().unix_permissions(0o755);
+        writer.start_file("bin/python3", executable).unwrap();
+        writer.write_all(b"#!/bin/sh\n").unwrap();
+        let plain = zip::write::SimpleFileOptions::default().unix_permissions(0o644);
+        writer.start_file("lib/os.py", plain).unwrap();
+        writer.write_all(b"").unwrap();
+        writer.finish().unwrap();
+
+        let dest = dir.join("out");
+        let mut reported = Vec::new();
+        extract(&archive, &dest, |done, total| reported.push((done, total))).unwrap();
+        assert_eq!(reported, vec![(1, 2), (2, 2)]);
+        let mode = |path: &str| fs::metadata(dest.join(path)).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode("bin/python3"), 0o755);
+        assert_eq!(mode("lib/os.py"), 0o644);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,204 @@
+//! 系统对下载文件的隔离标记。
+//!
+//! macOS 上从网络下载、解压的运行文件带有 `com.apple.quarantine` 扩展属性，Gatekeeper 会拒绝运行未公证的
+//! 子进程，启动时只得到含糊的“Operation not permitted”，或进程刚启动就被结束；Windows 上带有
+//! Zone.Identifier 备用数据流（Mark of the Web）的程序可能被 SmartScreen 拦截。
+//! 服务启动失败或启动后立即退出时检查其程序是否带有标记，有则在错误信息中说明原因，并提供
+//! `remove_quarantine` 操作：用户确认后清除运行目录下全部文件的标记。启动自检同样会检查，
+//! 在第一次失败之前给出提示。
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::runtime;
+
+/// 出现在错误信息中，用于判断错误是否由隔离标记引起。
+pub const BLOCKED_HINT: &str = "运行文件被系统安全机制拦截";
+/// 启动后在这段时间内退出，视为可能被系统结束。
+pub const INSTANT_EXIT: Duration = Duration::from_secs(3);
+
+#[cfg(target_os = "macos")]
+const MARK: &str = "com.apple.quarantine";
+#[cfg(target_os = "windows")]
+const ZONE_STREAM: &str = ":Zone.Identifier";
+
+/// 程序带有隔离标记时在错误信息后附上原因与处理方式，否则原样返回。
+pub fn annotate(program: &Path, error: String) -> String {
+    if !is_quarantined(program) {
+        return error;
+    }
+    format!(
+        "{error}\n\n{BLOCKED_HINT}：{} 带有{}。\n可以在错误提示中选择「解除系统拦截」，确认后清除运行目录下文件的隔离标记再重试。",
+        program.display(),
+        mark_description()
+    )
+}
+
+/// 服务程序中带有隔离标记的文件。
+pub fn scan(app: &AppHandle) -> Vec<PathBuf> {
+    let Ok(root) = runtime::resolve_runtime_root(app) else {
+        return Vec::new();
+    };
+    programs(&root)
+        .into_iter()
+        .filter(|program| is_quarantined(program))
+        .collect()
+}
+
+/// 弹窗确认后清除运行目录下全部文件的隔离标记，返回是否已清除（用户取消时为 false）。
+pub fn remove_with_confirmation(app: &AppHandle) -> Result<bool, String> {
+    let root = runtime::resolve_runtime_root(app)?;
+    let confirmed = app
+        .dialog()
+        .message(format!(
+            "将清除以下目录中全部文件的{}，之后系统不再拦截其中的程序：\n\n{}\n\n请确认这些文件来自 PT Nexus 官方发布。",
+            mark_description(),
+            root.display()
+        ))
+        .title("PT Nexus 解除系统拦截")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom("解除拦截".to_string(), "取消".to_string()))
+        .blocking_show();
+    if !confirmed {
+        return Ok(false);
+    }
+    remove(&root)?;
+    runtime::shell_log(
+        app,
+        &format!("[INFO] 已清除 {} 下文件的隔离标记", root.display()),
+    );
+    Ok(true)
+}
+
+/// 各服务可能直接运行的程序；以 Python 源码运行的服务由运行目录中的 Python 启动，不在此列。
+fn programs(root: &Path) -> Vec<PathBuf> {
+    [
+        ("server", "server"),
+        ("server", "background_runner"),
+        ("batch", "batch"),
+        ("updater", "updater"),
+    ]
+    .iter()
+    .map(|(dir, name)| root.join(dir).join(runtime::exe_name(name)))
+    .filter(|path| path.is_file())
+    .collect()
+}
+
+fn mark_description() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "“来自 Internet”的标记（Zone.Identifier），可能被 SmartScreen 拦截"
+    } else {
+        "隔离属性（com.apple.quarantine），未经公证的程序会被 Gatekeeper 拒绝运行"
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn is_quarantined(path: &Path) -> bool {
+    std::process::Command::new("xattr")
+        .arg("-p")
+        .arg(MARK)
+        .arg(path)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[cfg(target_os = "macos")]
+fn remove(root: &Path) -> Result<(), String> {
+    let output = std::process::Command::new("xattr")
+        .args(["-d", "-r", MARK])
+        .arg(root)
+        .output()
+        .map_err(|e| format!("执行 xattr 失败: {e}"))?;
+    // 没有该属性的文件也会报错，清除后再检查一遍各服务程序
+    match programs(root)
+        .into_iter()
+        .find(|program| is_quarantined(program))
+    {
+        Some(program) => Err(format!(
+            "清除 {} 的隔离属性失败: {}",
+            program.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Internet（3）与受限站点（4）区域的文件才会被拦截。
+#[cfg(target_os = "windows")]
+fn is_quarantined(path: &Path) -> bool {
+    std::fs::read_to_string(zone_stream(path))
+        .ok()
+        .and_then(|content| parse_zone_id(&content))
+        .is_some_and(|zone| zone >= 3)
+}
+
+#[cfg(target_os = "windows")]
+fn remove(root: &Path) -> Result<(), String> {
+    let mut failed = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                pending.push(path);
+                continue;
+            }
+            match std::fs::remove_file(zone_stream(&path)) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    failed.push(format!("{}: {err}", path.display()))
+                }
+                _ => {}
+            }
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("部分文件的标记清除失败：\n{}", failed.join("\n")))
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn zone_stream(path: &Path) -> PathBuf {
+    let mut stream = path.as_os_str().to_os_string();
+    stream.push(ZONE_STREAM);
+    PathBuf::from(stream)
+}
+
+#[cfg(any(target_os = "windows", test))]
+fn parse_zone_id(content: &str) -> Option<u32> {
+    content
+        .lines()
+        .filter_map(|line| line.trim().split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("ZoneId"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn is_quarantined(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn remove(_root: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zone_identifier_is_parsed() {
+        let downloaded = "[ZoneTransfer]\r\nZoneId=3\r\nReferrerUrl=https://github.com/\r\n";
+        assert_eq!(parse_zone_id(downloaded), Some(3));
+        assert_eq!(parse_zone_id("[ZoneTransfer]\nzoneid = 1\n"), Some(1));
+        assert_eq!(parse_zone_id("[ZoneTransfer]\n"), None);
+    }
+}
@@ -0,0 +1,319 @@
+//! 更新器替换后端程序后的滚动重启。
+//!
+//! 桌面端的服务进程由桌面壳托管（子进程环境中 `PTNEXUS_SUPERVISOR=desktop`），更新器不能自行停止、
+//! 启动它们。更新器同步完文件后在 UPDATE_DIR 写入 `recycle-request.json`：
+//!
+//! ```json
+//! {"id": "1700000000", "components": ["server", "batch"], "files": {"<程序路径>": "<SHA-256>"}}
+//! ```
+//!
+//! 桌面壳定时检查该文件，取走后先按清单校验磁盘上的新程序，再等到 batch 没有进行中的任务，
+//! 按启动顺序逐个平滑重启列出的服务。请求中的 `previous` 给出被替换程序的备份，重启前保存为回滚副本
+//! （见 rollback.rs）。进度与结果写入同目录的 `recycle-result.json`
+//! （`state` 依次为 waiting / restarting / succeeded / failed，`components` 为重启顺序），同时以 `runtime-recycle`
+//! 事件通知界面；更新器读到 failed 时回滚文件并再次请求重启。updater 自身不能通过这种方式重启。
+//! 开始重启时主窗口切换到更新进度页，成功后返回原来的页面（见 updatepage.rs）。
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::tasks::{self, Scope};
+use crate::webhook::{self, Trigger};
+use crate::{batchstatus, datadir, fsutil, rollback, runtime, updatecache, updatepage};
+
+pub const SUPERVISOR_KEY: &str = "PTNEXUS_SUPERVISOR";
+pub const SUPERVISOR_DESKTOP: &str = "desktop";
+pub(crate) const REQUEST_FILE: &str = "recycle-request.json";
+pub(crate) const RESULT_FILE: &str = "recycle-result.json";
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// 等待 batch 任务结束时的检查间隔。
+const BUSY_POLL_INTERVAL: Duration = Duration::from_secs(15);
+/// 更新器自己发出请求并等待结果，不能在此期间被重启。
+const REFUSED_COMPONENTS: &[&str] = &["updater"];
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct RecycleRequest {
+    id: String,
+    components: Vec<String>,
+    #[serde(default)]
+    files: BTreeMap<PathBuf, String>,
+    /// 服务名 -> 被替换的程序 -> 更新器备份的旧版本，重启前保存为回滚副本。
+    #[serde(default)]
+    previous: BTreeMap<String, BTreeMap<PathBuf, PathBuf>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RecycleState {
+    Waiting,
+    Restarting,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+struct RecycleReport<'a> {
+    id: &'a str,
+    state: RecycleState,
+    /// 需要重启的服务，按重启顺序排列；请求被拒绝时为请求中的原始列表。
+    components: &'a [String],
+    /// 正在重启的服务，仅 restarting 时有值；failed 时为重启失败的服务。
+    component: Option<&'a str>,
+    message: String,
+}
+
+pub fn start(app: &AppHandle) {
+    tasks::spawn(app, "recycle", Scope::Shell, move |app, token| {
+        while token.sleep(POLL_INTERVAL) {
+            let Some(update_dir) = update_dir(&app) else {
+                continue;
+            };
+            let request_path = update_dir.join(REQUEST_FILE);
+            if !request_path.is_file() {
+                continue;
+            }
+            // 先取走请求，处理期间更新器写入的新请求留到下一轮
+            let content = fs::read_to_string(&request_path);
+            let _ = fs::remove_file(&request_path);
+            match content
+                .map_err(|e| e.to_string())
+                .and_then(|content| parse_request(&content))
+            {
+                Ok(request) => handle(&app, &update_dir, &request),
+                Err(err) => runtime::shell_log(
+                    &app,
+                    &format!("[WARN] 忽略无效的重启请求 {REQUEST_FILE}: {err}"),
+                ),
+            }
+        }
+    });
+}
+
+fn update_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.try_state::<runtime::RuntimeManager>()?
+        .context()?
+        .common_env
+        .get("UPDATE_DIR")
+        .map(PathBuf::from)
+}
+
+fn handle(app: &AppHandle, update_dir: &Path, request: &RecycleRequest) {
+    let report = |components: &[String], state, component: Option<&str>, message: String| {
+        let report = RecycleReport {
+            id: &request.id,
+            state,
+            components,
+            component,
+            message,
+        };
+        if let Err(err) = write_report(update_dir, &report) {
+            runtime::shell_log(app, &format!("[WARN] 写入 {RESULT_FILE} 失败: {err}"));
+        }
+        events::emit(app, "runtime-recycle", Replay::Latest, &report);
+    };
+
+    let Some(manager) = app.try_state::<runtime::RuntimeManager>() else {
+        return;
+    };
+    let running: Vec<String> = manager
+        .service_pids()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let order = match restart_order(&request.components, &running)
+        .and_then(|order| verify_files(&request.files).map(|_| order))
+    {
+        Ok(order) => order,
+        Err(err) => {
+            runtime::shell_log(
+                app,
+                &format!("[ERROR] 拒绝更新器的重启请求 {}: {err}", request.id),
+            );
+            journal::record(
+                app,
+                Severity::Error,
+                None,
+                format!("更新后重启被拒绝: {err}"),
+            );
+            report(&request.components, RecycleState::Failed, None, err);
+            return;
+        }
+    };
+
+    if let Ok(profile) = datadir::active_profile(app) {
+        for (name, files) in request
+            .previous
+            .iter()
+            .filter(|(name, _)| order.contains(name))
+        {
+            if let Err(err) = rollback::store(&profile.rollback_dir, name, files) {
+                runtime::shell_log(app, &format!("[WARN] 保存 {name} 的回滚副本失败: {err}"));
+            }
+        }
+    }
+
+    if batchstatus::is_busy(app) {
+        report(
+            &order,
+            RecycleState::Waiting,
+            None,
+            "等待 batch 进行中的任务结束".to_string(),
+        );
+        runtime::shell_log(app, "[INFO] batch 有任务在执行，更新后的重启推迟到任务结束");
+        while batchstatus::is_busy(app) {
+            thread::sleep(BUSY_POLL_INTERVAL);
+        }
+    }
+
+    rollback::mark_updated(app, &order);
+    // 等待 batch 期间 WebUI 照常可用，真正开始重启时才切换到更新进度页
+    updatepage::show(app, &request.id);
+    for name in &order {
+        report(
+            &order,
+            RecycleState::Restarting,
+            Some(name.as_str()),
+            format!("正在重启 {name}"),
+        );
+        if let Err(err) = manager.restart_service(app, name) {
+            let message = format!("重启 {name} 失败: {}", journal::first_line(&err));
+            runtime::shell_log(app, &format!("[ERROR] 更新后{message}"));
+            report(&order, RecycleState::Failed, Some(name.as_str()), message);
+            return;
+        }
+    }
+    let message = format!("已重启 {}", order.join(", "));
+    runtime::shell_log(app, &format!("[INFO] 更新后{message}"));
+    journal::record(app, Severity::Info, None, format!("更新后{message}"));
+    webhook::notify(app, Trigger::Update, format!("更新后{message}"));
+    report(&order, RecycleState::Succeeded, None, message);
+    updatepage::finish(app);
+    updatecache::clean_after_update(app);
+}
+
+fn parse_request(content: &str) -> Result<RecycleRequest, String> {
+    let request: RecycleRequest = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    if request.components.is_empty() {
+        return Err("未指定需要重启的服务".to_string());
+    }
+    Ok(request)
+}
+
+/// 按服务的启动顺序排列需要重启的服务，被依赖的先重启。
+fn restart_order(components: &[String], running: &[String]) -> Result<Vec<String>, String> {
+    if let Some(refused) = components
+        .iter()
+        .find(|name| REFUSED_COMPONENTS.contains(&name.as_str()))
+    {
+        return Err(format!("{refused} 不能通过重启请求重启"));
+    }
+    if let Some(unknown) = components.iter().find(|name| !running.contains(name)) {
+        return Err(format!("服务 {unknown} 未在运行"));
+    }
+    Ok(running
+        .iter()
+        .filter(|name| components.contains(name))
+        .cloned()
+        .collect())
+}
+
+/// 磁盘上的程序必须与清单中的 SHA-256 一致，否则说明文件没有替换完整。
+fn verify_files(files: &BTreeMap<PathBuf, String>) -> Result<(), String> {
+    for (path, expected) in files {
+        let actual = sha256_file(path).map_err(|e| format!("读取 {} 失败: {e}", path.display()))?;
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(format!("{} 与更新清单不一致", path.display()));
+        }
+    }
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn write_report(update_dir: &Path, report: &RecycleReport) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(report).map_err(|e| e.to_string())?;
+    fsutil::atomic_write(&update_dir.join(RESULT_FILE), json).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn components_restart_in_launch_order() {
+        let running = names(&["background_runner", "server", "batch", "updater"]);
+        assert_eq!(
+            restart_order(&names(&["batch", "server"]), &running),
+            Ok(names(&["server", "batch"]))
+        );
+        assert!(restart_order(&names(&["updater"]), &running)
+            .unwrap_err()
+            .contains("updater"));
+        assert!(restart_order(&names(&["proxy"]), &running)
+            .unwrap_err()
+            .contains("proxy"));
+    }
+
+    #[test]
+    fn requests_are_parsed_and_files_verified() {
+        assert!(parse_request(r#"{"id": "1", "components": []}"#).is_err());
+        assert!(parse_request("not json").is_err());
+
+        let dir = temp_dir("verify");
+        let program = dir.join("batch");
+        fs::write(&program, b"new build").unwrap();
+        let hash = sha256_file(&program).unwrap();
+        let request = parse_request(&format!(
+            r#"{{"id": "1", "components": ["batch"], "files": {{{}: "{}"}}}}"#,
+            serde_json::to_string(&program).unwrap(),
+            hash.to_uppercase()
+        ))
+        .unwrap();
+        assert_eq!(verify_files(&request.files), Ok(()));
+
+        fs::write(&program, b"half").unwrap();
+        assert!(verify_files(&request.files).unwrap_err().contains("不一致"));
+        fs::remove_file(&program).unwrap();
+        assert!(verify_files(&request.files).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn report_is_written_atomically() {
+        let dir = temp_dir("report");
+        let report = RecycleReport {
+            id: "7",
+            state: RecycleState::Restarting,
+            components: &names(&["server", "batch"]),
+            component: Some("server"),
+            message: "正在重启 server".to_string(),
+        };
+        write_report(&dir, &report).unwrap();
+        let value: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join(RESULT_FILE)).unwrap()).unwrap();
+        assert_eq!(value["state"], "restarting");
+        assert_eq!(value["component"], "server");
+        assert_eq!(value["components"], serde_json::json!(["server", "batch"]));
+        assert!(!fsutil::tmp_path(&dir.join(RESULT_FILE)).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
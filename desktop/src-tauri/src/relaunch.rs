@@ -0,0 +1,161 @@
+//! WebUI 请求重启整个桌面应用。
+//!
+//! 后端的部分设置（语言包、插件安装）需要重启整个应用才能生效。`relaunch_app(reason)` 不会立即重启：
+//! 桌面壳记录请求并发出 `relaunch-requested` 事件（含一次性的 token 与原因），WebUI 弹出自己的确认框，
+//! 用户确认后以该 token 调用 `confirm_relaunch`，桌面壳才平滑停止服务并启动新进程。
+//! 没有界面响应的请求 2 分钟后失效，误调用不会在用户不知情时重启应用。
+//! 新进程沿用当前进程的命令行参数（如 `--service-mode`）。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::runtime;
+
+const REQUEST_TTL: Duration = Duration::from_secs(120);
+
+/// 等待确认的重启请求，同一时间只有一个，新的请求覆盖旧的。
+#[derive(Default)]
+pub struct RelaunchState {
+    pending: Mutex<Option<Pending>>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    token: String,
+    reason: String,
+    requested_at: Instant,
+}
+
+/// `relaunch-requested` 事件的内容。
+#[derive(Clone, Serialize)]
+struct RelaunchRequest {
+    token: String,
+    reason: String,
+}
+
+pub fn request(app: &AppHandle, reason: &str) -> Result<(), String> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err("请说明需要重启应用的原因".to_string());
+    }
+    let state = app
+        .try_state::<RelaunchState>()
+        .ok_or_else(|| "重启请求尚未初始化".to_string())?;
+    let token = new_token();
+    if let Ok(mut pending) = state.pending.lock() {
+        *pending = Some(Pending {
+            token: token.clone(),
+            reason: reason.to_string(),
+            requested_at: Instant::now(),
+        });
+    }
+    runtime::shell_log(
+        app,
+        &format!("[INFO] WebUI 请求重启应用，等待确认: {reason}"),
+    );
+    events::emit(
+        app,
+        "relaunch-requested",
+        Replay::Never,
+        RelaunchRequest {
+            token,
+            reason: reason.to_string(),
+        },
+    );
+    Ok(())
+}
+
+/// 用户在 WebUI 的确认框中作出选择后调用；确认时停止服务并重启应用，不会返回。
+pub fn confirm(app: &AppHandle, token: &str, confirmed: bool) -> Result<(), String> {
+    let state = app
+        .try_state::<RelaunchState>()
+        .ok_or_else(|| "重启请求尚未初始化".to_string())?;
+    let pending = {
+        let mut slot = state
+            .pending
+            .lock()
+            .map_err(|_| "重启请求不可用".to_string())?;
+        take_matching(&mut slot, token, Instant::now())?
+    };
+    if !confirmed {
+        runtime::shell_log(app, &format!("[INFO] 已取消重启应用: {}", pending.reason));
+        return Ok(());
+    }
+    runtime::shell_log(app, &format!("[INFO] 重启应用: {}", pending.reason));
+    journal::record(
+        app,
+        Severity::Info,
+        None,
+        format!("重启应用: {}", pending.reason),
+    );
+    crate::restart_app(app)
+}
+
+/// token 不匹配时保留原请求（可能是过期页面的回应），过期的请求直接丢弃。
+fn take_matching(slot: &mut Option<Pending>, token: &str, now: Instant) -> Result<Pending, String> {
+    if slot.as_ref().is_none_or(|pending| pending.token != token) {
+        return Err("重启请求不存在或已被新的请求取代".to_string());
+    }
+    let pending = slot.take().ok_or_else(|| "重启请求不存在".to_string())?;
+    if now.duration_since(pending.requested_at) > REQUEST_TTL {
+        return Err("重启请求已过期，请重新操作".to_string());
+    }
+    Ok(pending)
+}
+
+fn new_token() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(nanos.to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    hasher.update(COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    format!("{:x}", hasher.finalize())[..32].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(token: &str, requested_at: Instant) -> Option<Pending> {
+        Some(Pending {
+            token: token.to_string(),
+            reason: "安装语言包".to_string(),
+            requested_at,
+        })
+    }
+
+    #[test]
+    fn only_the_current_unexpired_request_can_be_confirmed() {
+        let now = Instant::now();
+        let mut slot = pending("abc", now);
+        assert!(take_matching(&mut slot, "stale", now).is_err());
+        assert!(slot.is_some());
+        assert_eq!(
+            take_matching(&mut slot, "abc", now).unwrap().reason,
+            "安装语言包"
+        );
+        assert!(slot.is_none());
+        assert!(take_matching(&mut slot, "abc", now).is_err());
+
+        let mut slot = pending("abc", now);
+        let late = now + REQUEST_TTL + Duration::from_secs(1);
+        assert!(take_matching(&mut slot, "abc", late)
+            .unwrap_err()
+            .contains("过期"));
+        assert!(slot.is_none());
+
+        assert_ne!(new_token(), new_token());
+        assert_eq!(new_token().len(), 32);
+    }
+}
@@ -0,0 +1,110 @@
+//! WebUI 首屏渲染检测。
+//!
+//! 后端启动成功但 SPA 没有渲染出来（如缓存了旧的 index.html）时，启动遮罩会一直停留。
+//! 每次导航到运行时页面都开始一轮计时，注入的遮罩脚本在登录页或主界面出现后调用
+//! `webui_render_ready`；超时仍未收到时向主窗口发出 `webui-render-timeout`，由遮罩切换为错误提示。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+use crate::journal::{self, Severity};
+use crate::runtime;
+use crate::tasks::{self, Scope};
+
+pub const RENDER_TIMEOUT: Duration = Duration::from_secs(20);
+/// 窗口不可见时 WebView 会节流脚本，等窗口重新可见后再判断。
+const HIDDEN_POLL: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+pub struct RenderWatch {
+    /// 每次导航递增。
+    generation: AtomicU64,
+    /// 已确认渲染完成的导航序号。
+    ready: AtomicU64,
+}
+
+impl RenderWatch {
+    fn arm(&self) -> u64 {
+        self.generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    pub fn mark_ready(&self) {
+        self.ready
+            .store(self.generation.load(Ordering::SeqCst), Ordering::SeqCst);
+    }
+
+    /// 本轮计时是否已被新的导航取代或已收到渲染完成通知。
+    fn settled(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::SeqCst) != generation
+            || self.ready.load(Ordering::SeqCst) >= generation
+    }
+}
+
+/// 导航到运行时页面后调用，开始一轮渲染计时。
+pub fn arm(window: &WebviewWindow) {
+    let Some(watch) = window.try_state::<RenderWatch>() else {
+        return;
+    };
+    let generation = watch.arm();
+    let (app, window) = (window.app_handle().clone(), window.clone());
+    // 同名任务再次启动时取消上一轮，上一轮的计时已被新的导航取代
+    tasks::spawn(&app, "render-watch", Scope::Runtime, move |app, token| {
+        if !token.sleep(RENDER_TIMEOUT) {
+            return;
+        }
+        let Some(watch) = app.try_state::<RenderWatch>() else {
+            return;
+        };
+        while !watch.settled(generation) && !is_visible(&window) {
+            if !token.sleep(HIDDEN_POLL) {
+                return;
+            }
+        }
+        if watch.settled(generation) {
+            return;
+        }
+        report_timeout(&app, &window);
+    });
+}
+
+fn is_visible(window: &WebviewWindow) -> bool {
+    window.is_visible().unwrap_or(false) && !window.is_minimized().unwrap_or(true)
+}
+
+fn report_timeout(app: &AppHandle, window: &WebviewWindow) {
+    let on_runtime_page = window
+        .url()
+        .map(|url| runtime::is_runtime_url(app, &url))
+        .unwrap_or(false);
+    if !on_runtime_page {
+        return;
+    }
+
+    let seconds = RENDER_TIMEOUT.as_secs();
+    runtime::shell_log(
+        app,
+        &format!("[WARN] WebUI 在 {seconds} 秒内未完成渲染，可能是页面缓存与后端版本不一致"),
+    );
+    journal::record(
+        app,
+        Severity::Warn,
+        None,
+        format!("界面 {seconds} 秒内未完成渲染"),
+    );
+    let _ = app.emit_to(window.label(), "webui-render-timeout", seconds);
+}
+
+/// 绕过缓存重新加载运行时页面：在地址上附加时间戳参数，使 index.html 重新请求。
+pub fn force_reload(window: &WebviewWindow) -> Result<(), String> {
+    let mut url = runtime::runtime_url(window.app_handle());
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    url.query_pairs_mut()
+        .append_pair("_ptnexus_reload", &stamp.to_string());
+    runtime::shell_log(window.app_handle(), "[INFO] 强制刷新 WebUI");
+    runtime::navigate_runtime_page(window, url)
+}
@@ -0,0 +1,232 @@
+//! 后端组件的上一个版本。
+//!
+//! 更新器的重启请求（见 recycle.rs）中带有被替换程序的备份位置，桌面壳在重启前把它们复制到
+//! `<data_dir>/updates/rollback/<服务名>/`，每个服务只保留一代。回滚时停止服务，把当前程序与保存的版本互换，
+//! 再启动并等待就绪；互换后目录中保存的是回滚前的版本，需要时可以再换回去。
+//! 更新后一段时间内服务连续三次健康检查失败，会弹窗询问是否回滚。回滚副本计入数据目录占用，可一键清理。
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::commanderror::CommandError;
+use crate::journal::{self, Severity};
+use crate::tasks::{self, Scope};
+use crate::{datadir, fsutil, runtime};
+
+const MANIFEST_FILE: &str = "manifest.json";
+/// 更新后多久内的故障视为可能由更新引起。
+const RECENT_UPDATE_WINDOW: Duration = Duration::from_secs(30 * 60);
+/// 健康检查连续失败多少次后询问是否回滚。
+pub const FAILURES_BEFORE_PROMPT: u32 = 3;
+
+/// 回滚目录中保存的文件：目标程序路径 -> 目录中的文件名。
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Manifest {
+    files: BTreeMap<PathBuf, String>,
+}
+
+/// 最近通过更新器重启过的服务，用于判断故障是否可能由更新引起。
+#[derive(Default)]
+pub struct RecentUpdates {
+    updated_at: Mutex<HashMap<String, Instant>>,
+}
+
+pub fn mark_updated(app: &AppHandle, services: &[String]) {
+    if let Some(recent) = app.try_state::<RecentUpdates>() {
+        if let Ok(mut updated_at) = recent.updated_at.lock() {
+            for service in services {
+                updated_at.insert(service.clone(), Instant::now());
+            }
+        }
+    }
+}
+
+/// 健康检查连续失败时调用：服务刚更新过且有回滚副本时弹窗询问，每次更新只询问一次。
+pub fn offer(app: &AppHandle, service: &str) {
+    let Ok(profile) = datadir::active_profile(app) else {
+        return;
+    };
+    let updated_at = app.try_state::<RecentUpdates>().and_then(|recent| {
+        let mut updated_at = recent.updated_at.lock().ok()?;
+        updated_at.remove(service)
+    });
+    let recently_updated = updated_at.is_some_and(|at| at.elapsed() <= RECENT_UPDATE_WINDOW);
+    if !recently_updated || !has_copy(&profile.rollback_dir, service) {
+        return;
+    }
+    runtime::shell_log(
+        app,
+        &format!("[WARN] {service} 更新后连续 {FAILURES_BEFORE_PROMPT} 次健康检查失败"),
+    );
+
+    let app = app.clone();
+    let service = service.to_string();
+    app.dialog()
+        .message(format!(
+            "{service} 更新后连续 {FAILURES_BEFORE_PROMPT} 次健康检查失败。\n\n可以回滚到更新前的版本，回滚后仍可再次更新。"
+        ))
+        .title("PT Nexus 服务异常")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom("回滚".to_string(), "暂不处理".to_string()))
+        .show({
+            let app = app.clone();
+            move |confirmed| {
+                if confirmed {
+                    tasks::spawn(&app, &format!("rollback:{service}"), Scope::Shell, move |app, _| {
+                        if let Err(err) = rollback_component(&app, &service) {
+                            runtime::shell_log(&app, &format!("[ERROR] 回滚 {service} 失败: {err}"));
+                        }
+                    });
+                }
+            }
+        });
+}
+
+/// 停止服务，与保存的版本互换程序后重新启动并等待就绪。
+pub fn rollback_component(app: &AppHandle, name: &str) -> Result<(), String> {
+    let root = datadir::active_profile(app)?.rollback_dir;
+    if !has_copy(&root, name) {
+        return Err(format!("{name} 没有可回滚的版本"));
+    }
+    let manager = app
+        .try_state::<runtime::RuntimeManager>()
+        .ok_or_else(|| "运行时尚未启动".to_string())?;
+    manager.restart_service_with(app, name, || swap(&root, name).map(|_| ()))?;
+    runtime::shell_log(app, &format!("[INFO] 已将 {name} 回滚到上一个版本"));
+    journal::record(app, Severity::Warn, Some(name), "已回滚到上一个版本");
+    Ok(())
+}
+
+/// 删除全部回滚副本，返回释放的字节数。
+pub fn clear(root: &Path) -> Result<u64, CommandError> {
+    let size = datadir::dir_size(root);
+    match fs::remove_dir_all(root) {
+        Ok(()) => Ok(size),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(CommandError::io(
+            format_args!("删除 {} 失败", root.display()),
+            err,
+        )),
+    }
+}
+
+pub fn has_copy(root: &Path, component: &str) -> bool {
+    root.join(component).join(MANIFEST_FILE).is_file()
+}
+
+/// 保存一个服务被替换前的程序（`目标路径 -> 备份路径`），替换原有的副本。
+/// 先写入同级的临时目录，完整后再改名，中途失败时保留原有的副本。
+pub fn store(
+    root: &Path,
+    component: &str,
+    previous: &BTreeMap<PathBuf, PathBuf>,
+) -> Result<(), String> {
+    let dir = root.join(component);
+    let staging = fsutil::tmp_path(&dir);
+    let _ = fs::remove_dir_all(&staging);
+    let stored = write_copy(&staging, previous).and_then(|_| {
+        let _ = fs::remove_dir_all(&dir);
+        fs::rename(&staging, &dir).map_err(|e| format!("保存 {} 失败: {e}", dir.display()))
+    });
+    if stored.is_err() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    stored
+}
+
+fn write_copy(dir: &Path, previous: &BTreeMap<PathBuf, PathBuf>) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("创建目录失败 ({}): {e}", dir.display()))?;
+    let mut manifest = Manifest::default();
+    for (index, (target, backup)) in previous.iter().enumerate() {
+        let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+        let stored_name = format!("{index}-{file_name}");
+        fs::copy(backup, dir.join(&stored_name))
+            .map_err(|e| format!("复制 {} 失败: {e}", backup.display()))?;
+        manifest.files.insert(target.clone(), stored_name);
+    }
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    fsutil::atomic_write(&dir.join(MANIFEST_FILE), json)
+        .map_err(|e| format!("写入回滚清单失败: {e}"))
+}
+
+/// 把保存的程序与当前程序互换，返回互换的文件数。服务需已停止。
+fn swap(root: &Path, component: &str) -> Result<usize, String> {
+    let dir = root.join(component);
+    let manifest: Manifest = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        .map_err(|e| format!("读取回滚清单失败: {e}"))?;
+    for (target, stored_name) in &manifest.files {
+        let stored = dir.join(stored_name);
+        let current = fsutil::tmp_path(&stored);
+        fs::copy(target, &current).map_err(|e| format!("备份 {} 失败: {e}", target.display()))?;
+        if let Err(err) = fsutil::atomic_copy(&stored, target) {
+            let _ = fs::remove_file(&current);
+            return Err(format!("还原 {} 失败: {err}", target.display()));
+        }
+        fs::rename(&current, &stored)
+            .map_err(|e| format!("保存 {} 失败: {e}", stored.display()))?;
+    }
+    Ok(manifest.files.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn swap_exchanges_current_and_previous_versions() {
+        let dir = temp_dir("swap");
+        let root = dir.join("rollback");
+        let target = dir.join("runtime").join("batch");
+        let backup = dir.join("backup").join("batch");
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        fs::create_dir_all(backup.parent().unwrap()).unwrap();
+        fs::write(&target, b"v2").unwrap();
+        fs::write(&backup, b"v1").unwrap();
+
+        assert!(!has_copy(&root, "batch"));
+        store(
+            &root,
+            "batch",
+            &BTreeMap::from([(target.clone(), backup.clone())]),
+        )
+        .unwrap();
+        assert!(has_copy(&root, "batch"));
+
+        assert_eq!(swap(&root, "batch"), Ok(1));
+        assert_eq!(fs::read(&target).unwrap(), b"v1");
+        // 再换一次回到更新后的版本
+        assert_eq!(swap(&root, "batch"), Ok(1));
+        assert_eq!(fs::read(&target).unwrap(), b"v2");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_store_keeps_the_existing_copy() {
+        let dir = temp_dir("store");
+        let root = dir.join("rollback");
+        let target = dir.join("server");
+        let backup = dir.join("server.bak");
+        fs::write(&backup, b"v1").unwrap();
+        store(&root, "server", &BTreeMap::from([(target.clone(), backup)])).unwrap();
+
+        let missing = BTreeMap::from([(target, dir.join("missing"))]);
+        assert!(store(&root, "server", &missing).is_err());
+        assert!(has_copy(&root, "server"));
+        assert!(!fsutil::tmp_path(&root.join("server")).exists());
+
+        assert!(clear(&root).unwrap() > 0);
+        assert!(!has_copy(&root, "server"));
+        assert_eq!(clear(&root).unwrap(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,413 +1,746 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs::{self, OpenOptions};
-use std::net::TcpListener;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command, Stdio};
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use serde::Serialize;
 use tauri::path::BaseDirectory;
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
 use tauri::WebviewWindow;
+use tauri::{AppHandle, Manager};
+
+use crate::bindings::{self, ServiceBinding};
+use crate::datadir::ProfilePaths;
+use crate::erroraction::ErrorAction;
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::pagescripts::{self, AllowedOrigins, PageScript, ScriptConfig};
+use crate::services::{self, Progress, Readiness, RunningService, ServiceSpec};
+use crate::tasks::{self, Scope};
+use crate::timings::{self, StartupTimer};
+use crate::{
+    bdinfo, cabundle, configguard, crashdumps, database, datadir, datamove, fsutil, health,
+    injections, launchargs, localhttp, pathgrant, paths, pyfailure, pyruntime, quarantine, recycle,
+    renderwatch, safemode, settings, snapshot, status, updatepage, watchdog, webhook, webuicache,
+};
+
+/// 外部链接拦截视为内部地址的主机名，写法与 JS 中 `URL.hostname` 一致（IPv6 带方括号）。
+/// 固定包含整个 IPv4 回环网段（`127.*`）、`localhost` 与 `[::1]`，再加上 WebUI 地址的主机名与
+/// SERVER_HOST / UPDATER_HOST 中配置的主机名（如 hosts 文件中的别名）；通配监听地址不加入。
+const LOOPBACK_HOSTS: [&str; 3] = ["127.*", "localhost", "[::1]"];
+
+fn internal_hosts(runtime_url: &tauri::Url, configured: &[String]) -> Vec<String> {
+    let mut hosts: Vec<String> = LOOPBACK_HOSTS.iter().map(|host| host.to_string()).collect();
+    let candidates = runtime_url
+        .host_str()
+        .into_iter()
+        .chain(configured.iter().map(String::as_str));
+    for candidate in candidates {
+        let bare = candidate
+            .trim()
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let host = if let Ok(ip) = bare.parse::<std::net::Ipv4Addr>() {
+            if ip.is_loopback() || ip.is_unspecified() {
+                continue;
+            }
+            ip.to_string()
+        } else if let Ok(ip) = bare.parse::<std::net::Ipv6Addr>() {
+            if ip.is_unspecified() {
+                continue;
+            }
+            format!("[{ip}]")
+        } else {
+            bare.trim_end_matches('.').to_ascii_lowercase()
+        };
+        if !host.is_empty() && !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+    hosts
+}
 
-/// 注入到前端页面的 JS 脚本，用于拦截 window.open 和 <a target="_blank"> 等外部链接，
-/// 将它们路由到 Rust 端的 open_external 命令，在系统浏览器中打开。
-const EXTERNAL_LINK_INTERCEPT_JS: &str = r#"
-(function() {
-  if (window.__PTNEXUS_LINK_INTERCEPTOR__) return;
-  window.__PTNEXUS_LINK_INTERCEPTOR__ = true;
-
-  function isExternal(url) {
-    try {
-      var u = new URL(url, location.href);
-      return u.hostname !== '127.0.0.1' && u.hostname !== 'localhost';
-    } catch(e) { return false; }
-  }
-
-  // 拦截 window.open
-  var origOpen = window.open;
-  window.open = function(url) {
-    if (url && isExternal(String(url))) {
-      try {
-        var u = new URL(String(url), location.href);
-        window.__TAURI_INTERNALS__.invoke('open_external', { url: u.href });
-      } catch(e) {}
-      return null;
-    }
-    return origOpen.apply(this, arguments);
-  };
-
-  // 拦截 <a> 元素点击（含 target="_blank" 和普通外部链接）
-  document.addEventListener('click', function(e) {
-    var el = e.target;
-    while (el && el.tagName !== 'A') el = el.parentElement;
-    if (!el) return;
-    var href = el.getAttribute('href') || el.href;
-    if (!href) return;
-    if (isExternal(href)) {
-      e.preventDefault();
-      e.stopPropagation();
-      try {
-        var u = new URL(href, location.href);
-        window.__TAURI_INTERNALS__.invoke('open_external', { url: u.href });
-      } catch(ex) {}
-    }
-  }, true);
-})();
-"#;
-
-/// 注入“打开数据库配置目录”按钮到设置页的“背景设置/其他设置”卡片。
-/// 仅桌面端运行时注入，不修改 webui 源码。
-const INJECT_DB_CONFIG_BUTTON_JS: &str = r#"
-(function() {
-  if (window.__PTNEXUS_DB_BUTTON_WATCHER__) return;
-  window.__PTNEXUS_DB_BUTTON_WATCHER__ = true;
-
-  function ensureButton() {
-    var headers = Array.from(document.querySelectorAll('.card-header .header-content h3'));
-    var target = headers.find(function(h) {
-      var t = (h.textContent || '').trim();
-      return t === '其他设置';
+/// 生成外部链接拦截脚本，`configured` 为 SERVER_HOST、UPDATER_HOST 的取值。
+fn external_link_script(runtime_url: &tauri::Url, configured: &[String]) -> String {
+    let config = ScriptConfig::new(PageScript::ExternalLinks).origins(AllowedOrigins {
+        runtime: runtime_url.origin().ascii_serialization(),
+        internal_hosts: internal_hosts(runtime_url, configured),
     });
-    if (!target) return false;
-
-    var card = target.closest('.settings-card');
-    if (!card) return false;
-
-    if (card.querySelector('.ptnexus-open-db-config-btn')) return true;
-
-    var form = card.querySelector('.settings-form');
-    if (!form) return false;
-
-    var formItem = document.createElement('div');
-    formItem.className = 'el-form-item form-item ptnexus-db-config-item';
+    pagescripts::render_script(PageScript::ExternalLinks, &config)
+}
 
-    var label = document.createElement('label');
-    label.className = 'el-form-item__label';
-    label.textContent = '数据库配置文件';
+/// 主窗口加载的运行时页面地址（由 updater 提供 WebUI）。
+/// 默认的 WebUI 地址；实际地址见 [`runtime_url`]，会跟随 runtime.env 中的 UPDATER_PORT 等设置。
+pub const RUNTIME_URL: &str = "http://127.0.0.1:5274";
+pub const UPDATER_PORT: u16 = 5274;
 
-    var content = document.createElement('div');
-    content.className = 'el-form-item__content';
+/// 启动阶段事件，前端启动页据此展示进度。
+#[derive(Clone, Serialize)]
+pub struct BootstrapStage {
+    pub stage: &'static str,
+    pub message: String,
+}
 
-    var btn = document.createElement('button');
-    btn.type = 'button';
-    btn.className = 'el-button el-button--success is-plain ptnexus-open-db-config-btn';
-    btn.innerHTML = '<span>打开数据库配置目录</span>';
-    btn.addEventListener('click', function() {
-      try {
-        window.__TAURI_INTERNALS__.invoke('open_app_data_dir');
-      } catch (e) {
-        alert('打开目录失败，请手动前往应用数据目录修改 runtime.env。');
-      }
-    });
+pub fn emit_stage(app: &AppHandle, stage: &'static str, message: String) {
+    events::emit(
+        app,
+        "bootstrap-stage",
+        Replay::Latest,
+        BootstrapStage { stage, message },
+    );
+}
 
-    var hint = document.createElement('div');
-    hint.className = 'password-hint';
-    hint.innerHTML = '<span class="el-text el-text--small is-info">默认 sqlite；如需 MySQL/PostgreSQL，请编辑 runtime.env 后重启应用。</span>';
+/// 启动失败的原因。大多数失败只有一段说明文字，需要界面区别处理的情况单独成为变体。
+#[derive(Debug)]
+pub enum BootstrapError {
+    Failed(String),
+    /// 某个服务启动或等待就绪失败；`diagnosis` 为从 stderr 识别出的 Python 失败类型。
+    ServiceFailed {
+        service: String,
+        message: String,
+        diagnosis: Option<&'static pyfailure::Signature>,
+    },
+    DatabaseCorrupt {
+        path: PathBuf,
+        detail: String,
+        latest_backup: Option<PathBuf>,
+    },
+    /// 配置的 MySQL / PostgreSQL 无法建立连接。
+    DatabaseUnreachable {
+        db_type: String,
+        address: String,
+    },
+    /// 运行目录不完整，一次列出全部缺失的文件。
+    MissingFiles {
+        runtime_root: PathBuf,
+        missing: Vec<MissingFile>,
+    },
+}
 
-    content.appendChild(btn);
-    content.appendChild(hint);
-    formItem.appendChild(label);
-    formItem.appendChild(content);
+/// 启动所需但不存在的文件。
+#[derive(Clone, Debug, PartialEq)]
+pub struct MissingFile {
+    /// 所属组件，错误信息按它分组。
+    pub component: &'static str,
+    pub path: PathBuf,
+    /// 存在时可以代替该文件的另一种启动方式（打包的可执行文件）。
+    pub alternative: Option<PathBuf>,
+}
 
-    var spacer = form.querySelector('.form-spacer');
-    if (spacer && spacer.parentNode) {
-      spacer.parentNode.insertBefore(formItem, spacer);
-    } else {
-      form.appendChild(formItem);
+impl fmt::Display for BootstrapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Failed(message) => f.write_str(message),
+            Self::ServiceFailed {
+                message,
+                diagnosis,
+                ..
+            } => {
+                if let Some(signature) = diagnosis {
+                    write!(f, "{}。{}\n\n", signature.summary, signature.remedy)?;
+                }
+                f.write_str(message)
+            }
+            Self::DatabaseCorrupt {
+                path,
+                detail,
+                latest_backup,
+            } => {
+                write!(
+                    f,
+                    "SQLite 数据库完整性检查失败：{}\n{detail}\n\n",
+                    path.display()
+                )?;
+                match latest_backup {
+                    Some(backup) => write!(
+                        f,
+                        "最近的自动备份：{}\n可退出应用后用该备份覆盖数据库文件再启动。",
+                        backup.display()
+                    )?,
+                    None => f.write_str("未找到自动备份（backups/auto/）。")?,
+                }
+                f.write_str("\n如确认要跳过检查，可在 runtime.env 中设置 PTNEXUS_SKIP_DB_INTEGRITY_CHECK=true。")
+            }
+            Self::DatabaseUnreachable { db_type, address } => write!(
+                f,
+                "无法连接数据库 {address}（DB_TYPE={db_type}）。\n请确认数据库服务已启动、网络可达，或在 runtime.env 中修改连接配置。"
+            ),
+            Self::MissingFiles {
+                runtime_root,
+                missing,
+            } => {
+                writeln!(
+                    f,
+                    "运行目录不完整，缺少 {} 个文件：{}",
+                    missing.len(),
+                    runtime_root.display()
+                )?;
+                let relative = |path: &Path| {
+                    path.strip_prefix(runtime_root)
+                        .unwrap_or(path)
+                        .display()
+                        .to_string()
+                };
+                let mut components: Vec<&str> = Vec::new();
+                for file in missing {
+                    if !components.contains(&file.component) {
+                        components.push(file.component);
+                    }
+                }
+                for component in components {
+                    writeln!(f, "{component}：")?;
+                    for file in missing.iter().filter(|file| file.component == component) {
+                        match &file.alternative {
+                            Some(alternative) => writeln!(
+                                f,
+                                "  - {}（或改用 {}）",
+                                relative(&file.path),
+                                relative(alternative)
+                            )?,
+                            None => writeln!(f, "  - {}", relative(&file.path))?,
+                        }
+                    }
+                }
+                f.write_str("安装可能未完整解压或被安全软件删除了部分文件，请重新安装应用后再启动。")
+            }
+        }
     }
+}
 
-    return true;
-  }
-
-  function startObserver() {
-    var observer = new MutationObserver(function() {
-      ensureButton();
-    });
-    observer.observe(document.documentElement || document.body, {
-      childList: true,
-      subtree: true,
-    });
-
-    window.addEventListener('hashchange', ensureButton, true);
-    window.addEventListener('popstate', ensureButton, true);
-
-    var originalPushState = history.pushState;
-    var originalReplaceState = history.replaceState;
-    history.pushState = function() {
-      var ret = originalPushState.apply(this, arguments);
-      setTimeout(ensureButton, 0);
-      return ret;
-    };
-    history.replaceState = function() {
-      var ret = originalReplaceState.apply(this, arguments);
-      setTimeout(ensureButton, 0);
-      return ret;
-    };
+impl BootstrapError {
+    /// 错误提示上提供的操作，按重要程度排列（原生对话框只能显示第一个）。
+    pub fn actions(&self) -> Vec<ErrorAction> {
+        let mut actions = Vec::new();
+        if self.to_string().contains(quarantine::BLOCKED_HINT) {
+            actions.push(ErrorAction::RemoveQuarantine);
+        }
+        if let Self::ServiceFailed {
+            service, diagnosis, ..
+        } = self
+        {
+            if let Some(signature) = diagnosis {
+                actions.push(signature.action.clone());
+            }
+            actions.push(ErrorAction::OpenLog(service.clone()));
+        }
+        actions.extend([
+            ErrorAction::OpenLogsDir,
+            ErrorAction::Retry,
+            ErrorAction::CopyDetails(self.to_string()),
+        ]);
+        actions
+    }
+}
 
-    setInterval(ensureButton, 1500);
-  }
-
-  var warmupTries = 0;
-  var warmupTimer = setInterval(function() {
-    warmupTries += 1;
-    var ok = ensureButton();
-    if (ok || warmupTries > 80) {
-      clearInterval(warmupTimer);
-      startObserver();
-      ensureButton();
-    }
-  }, 250);
-})();
-"#;
-
-/// 注入启动遮罩，尽可能覆盖 WebUI 初始化阶段，减少白屏观感。
-const STARTUP_OVERLAY_JS: &str = r#"
-(function() {
-  if (window.__PTNEXUS_STARTUP_OVERLAY__) return;
-  window.__PTNEXUS_STARTUP_OVERLAY__ = true;
-
-  function removeOverlay() {
-    var overlay = document.getElementById('ptnexus-startup-overlay');
-    if (overlay && overlay.parentNode) overlay.parentNode.removeChild(overlay);
-    var style = document.getElementById('ptnexus-startup-style');
-    if (style && style.parentNode) style.parentNode.removeChild(style);
-  }
-
-  function appReady() {
-    var app = document.getElementById('app');
-    if (!app) return false;
-    // 登录页已渲染
-    if (app.querySelector('.login-page, .login-card')) return true;
-    // 主应用页已渲染
-    if (app.querySelector('.app-container, .layout-container, .main-container, .home-container, .main-nav')) return true;
-    return false;
-  }
-
-  function createOverlay() {
-    if (document.getElementById('ptnexus-startup-overlay')) return;
-    // 如果 Vue 应用已经渲染了有效内容，不再创建遮罩
-    if (appReady()) return;
-
-    var style = document.createElement('style');
-    style.id = 'ptnexus-startup-style';
-    style.textContent = [
-      '#ptnexus-startup-overlay{position:fixed;inset:0;z-index:2147483647;display:flex;align-items:center;justify-content:center;background:#f5f7fa;color:#303133;font-family:-apple-system,BlinkMacSystemFont,"Segoe UI",sans-serif;}',
-      '#ptnexus-startup-overlay .box{text-align:center;padding:28px 32px;border-radius:14px;box-shadow:0 8px 32px rgba(0,0,0,.08);background:#fff;min-width:320px;}',
-      '#ptnexus-startup-overlay .title{font-size:22px;font-weight:600;margin-bottom:10px;}',
-      '#ptnexus-startup-overlay .desc{font-size:14px;color:#606266;}',
-      '#ptnexus-startup-overlay .dot::after{content:"";display:inline-block;animation:ptnexus-dot 1.2s steps(3,end) infinite;}',
-      '@keyframes ptnexus-dot{0%{content:""}33%{content:"."}66%{content:".."}100%{content:"..."}}'
-    ].join('');
-    document.head && document.head.appendChild(style);
-
-    var overlay = document.createElement('div');
-    overlay.id = 'ptnexus-startup-overlay';
-    overlay.innerHTML = '<div class="box"><div class="title">PT Nexus 启动中<span class="dot"></span></div><div class="desc">正在初始化页面，请稍候。</div></div>';
-    (document.body || document.documentElement).appendChild(overlay);
-  }
-
-  function tick() {
-    if (appReady()) {
-      removeOverlay();
-      clearInterval(timer);
-    } else {
-      createOverlay();
+impl From<String> for BootstrapError {
+    fn from(message: String) -> Self {
+        Self::Failed(message)
     }
-  }
+}
 
-  var timer = setInterval(tick, 250);
-  tick();
-  setTimeout(function(){ clearInterval(timer); removeOverlay(); }, 20000);
-})();
-"#;
+/// 单独重启服务时等待其自行退出的时间。
+const SERVICE_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+/// 等待 server、batch、updater 就绪的默认时长，可由 runtime.env 的 PTNEXUS_READY_TIMEOUT_SECS 调整。
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+/// 服务就绪后等待 WebUI 首页可以打开的最长时间，超时则显示离线提示页。
+const PAGE_READY_TIMEOUT: Duration = Duration::from_secs(10);
+const PAGE_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+const PAGE_RETRY_MIN: Duration = Duration::from_millis(200);
+const PAGE_RETRY_MAX: Duration = Duration::from_secs(2);
+/// 设为 true 时，依赖关系允许的服务（batch 与 updater）同时启动。
+const PARALLEL_START_KEY: &str = "PTNEXUS_PARALLEL_START";
+/// 切换配置档期间拒绝再次切换。
+static SWITCHING_PROFILE: AtomicBool = AtomicBool::new(false);
+/// 上次解析到的运行目录；文件仍在时直接使用，不再按全部候选布局逐个探测。
+static RESOLVED_RUNTIME_ROOT: Mutex<Option<PathBuf>> = Mutex::new(None);
 
 pub struct RuntimeManager {
-    processes: Arc<Mutex<Vec<Child>>>,
+    /// 运行中的服务及其启动方式，单独重启某个服务时复用。
+    services: Arc<Mutex<Vec<RunningService>>>,
+    context: Mutex<RuntimeContext>,
 }
 
-impl RuntimeManager {
-    pub fn bootstrap(app: &AppHandle) -> Result<Self, String> {
-        ensure_ports_available(&[5274, 5275, 5276])?;
+/// 最近一次启动解析出的目录与环境，供迁移、重启等后续操作复用。
+#[derive(Clone)]
+pub struct RuntimeContext {
+    pub data_dir: PathBuf,
+    pub logs_dir: PathBuf,
+    pub server_dir: PathBuf,
+    pub common_env: HashMap<String, String>,
+    /// 主窗口加载的 WebUI 地址，由最终环境变量计算得出。
+    pub runtime_url: tauri::Url,
+    /// 启动时观察到的各服务监听地址。
+    pub bindings: Vec<ServiceBinding>,
+    /// 本次启动耗时（毫秒）。
+    pub bootstrap_ms: u64,
+}
 
+impl RuntimeManager {
+    pub fn bootstrap(app: &AppHandle) -> Result<Self, BootstrapError> {
+        let mut timer = StartupTimer::start();
         let runtime_root = resolve_runtime_root(app)?;
         let changelog_path = resolve_changelog_path(app, &runtime_root);
 
-        let data_dir = app
-            .path()
-            .app_data_dir()
-            .map_err(|e| format!("解析应用数据目录失败: {e}"))?;
-        fs::create_dir_all(data_dir.join("tmp")).map_err(|e| format!("创建应用数据目录失败: {e}"))?;
-        let logs_dir = data_dir.join("logs");
+        let profile = datadir::active_profile(app)?;
+        let data_dir = profile.root.clone();
+        fs::create_dir_all(&profile.temp_dir).map_err(|e| format!("创建应用数据目录失败: {e}"))?;
+        let logs_dir = profile.logs_dir.clone();
         fs::create_dir_all(&logs_dir).map_err(|e| format!("创建日志目录失败: {e}"))?;
+        datadir::warn_if_risky(app, &data_dir);
 
-        // 首次运行时，把模板配置复制到用户可写目录，方便后续修改 DB/端口等运行参数。
-        let bundled_env_example = if runtime_root.join("data").join("runtime.env.example").exists() {
-            runtime_root.join("data").join("runtime.env.example")
-        } else {
-            runtime_root
-                .join("_up_")
-                .join("runtime")
-                .join("data")
-                .join("runtime.env.example")
-        };
-        let local_env_example = data_dir.join("runtime.env.example");
-        let local_runtime_env = data_dir.join("runtime.env");
-
-        if bundled_env_example.exists() && !local_env_example.exists() {
-            let _ = fs::copy(&bundled_env_example, &local_env_example);
-        }
-
-        // 用户目录若不存在 runtime.env，则直接从模板创建一份可编辑配置。
-        if bundled_env_example.exists() && !local_runtime_env.exists() {
-            let _ = fs::copy(&bundled_env_example, &local_runtime_env);
-        }
+        seed_runtime_env(&runtime_root, &data_dir);
 
-        let updater_dir = runtime_root.join("updater");
-        let batch_dir = runtime_root.join("batch");
         let server_dir = runtime_root.join("server");
 
-        let updater_exe = updater_dir.join(exe_name("updater"));
-        let batch_exe = batch_dir.join(exe_name("batch"));
-
-        ensure_exists(&updater_exe)?;
-        ensure_exists(&batch_exe)?;
-        ensure_exists(&server_dir.join("dist").join("index.html"))?;
-
-        let (background_runner_program, background_runner_args, background_runner_workdir) =
-            resolve_background_runner_launcher(&server_dir)?;
-        let (server_program, server_args, server_workdir) = resolve_server_launcher(&server_dir)?;
-
-        let mut processes = Vec::new();
-        let mut common_env = build_runtime_env(&data_dir, &server_dir, &changelog_path);
-
-        apply_host_env_overrides(
-            &mut common_env,
-            &[
-                "DB_TYPE",
-                "MYSQL_HOST",
-                "MYSQL_PORT",
-                "MYSQL_USER",
-                "MYSQL_PASSWORD",
-                "MYSQL_DATABASE",
-                "POSTGRES_HOST",
-                "POSTGRES_PORT",
-                "POSTGRES_USER",
-                "POSTGRES_PASSWORD",
-                "POSTGRES_DATABASE",
-                "SERVER_HOST",
-                "SERVER_PORT",
-                "BATCH_PORT",
-                "UPDATER_PORT",
-                "GO_SERVICE_URL",
-                "CORE_API_URL",
-                "PTNEXUS_EMBED_BG_IN_APP",
-            ],
+        let (mut common_env, env_warnings, passthrough) =
+            assemble_common_env(&profile, &server_dir, &changelog_path)?;
+        for warning in env_warnings {
+            append_shell_log(&logs_dir, &format!("[WARN] {warning}"));
+        }
+        database::apply_sqlite_fallback(app, &mut common_env, &logs_dir);
+        common_env.insert(
+            pathgrant::ENV_KEY.to_string(),
+            pathgrant::env_value(&settings::current(app).allowed_paths),
         );
-        merge_env_file(&mut common_env, &data_dir.join("runtime.env"))?;
-
-        let background_runner = spawn_process(
-            &background_runner_program,
-            &background_runner_workdir,
-            &common_env,
-            &background_runner_args,
-            "background_runner",
-            &logs_dir,
-        )?;
-        let mut background_runner = background_runner;
-        wait_for_process_running(
-            "background_runner",
-            &mut background_runner,
-            Duration::from_secs(10),
-            &logs_dir,
-        )?;
-        processes.push(background_runner);
-
-        let server = spawn_process(
-            &server_program,
-            &server_workdir,
-            &common_env,
-            &server_args,
-            "server",
-            &logs_dir,
-        )?;
-        let mut server = server;
-        wait_for_http_with_process_state(
-            "server",
-            &mut server,
-            "127.0.0.1",
-            5275,
-            Duration::from_secs(30),
-            &logs_dir,
-        )?;
-        processes.push(server);
-
-        let batch = spawn_process(
-            &batch_exe,
-            &batch_dir,
-            &common_env,
-            &[],
-            "batch",
-            &logs_dir,
-        )?;
-        let mut batch = batch;
-        wait_for_http_with_process_state(
-            "batch",
-            &mut batch,
-            "127.0.0.1",
-            5276,
-            Duration::from_secs(30),
-            &logs_dir,
-        )?;
-        processes.push(batch);
-
-        let updater = spawn_process(
-            &updater_exe,
-            &updater_dir,
-            &common_env,
-            &[],
-            "updater",
-            &logs_dir,
+        if !passthrough.found.is_empty() {
+            append_shell_log(
+                &logs_dir,
+                &format!(
+                    "[INFO] 已透传宿主环境变量: {}",
+                    passthrough.found.join(", ")
+                ),
+            );
+        }
+        let runtime_url = runtime_url_from_env(&common_env)?;
+        let server_port = env_port(&common_env, "SERVER_PORT", 5275)?;
+        let batch_port = env_port(&common_env, "BATCH_PORT", 5276)?;
+        let updater_port = runtime_url.port_or_known_default().unwrap_or(UPDATER_PORT);
+        let ready_timeout = env_secs(&common_env, services::READY_TIMEOUT_KEY, READY_TIMEOUT)?;
+        timer.mark("env merge");
+
+        // 退出后立即重新打开时，上次运行的连接可能还未释放，稍等片刻而不是直接报端口被占用
+        let port_grace = services::port_grace(&common_env);
+        let mut lingering = Vec::new();
+        services::wait_for_ports(
+            &[updater_port, server_port, batch_port],
+            port_grace,
+            |port, remaining| {
+                if !lingering.contains(&port) {
+                    lingering.push(port);
+                    append_shell_log(
+                        &logs_dir,
+                        &format!("[INFO] 端口 {port} 尚未释放，等待上次运行的连接关闭"),
+                    );
+                }
+                emit_stage(
+                    app,
+                    "ports",
+                    format!(
+                        "端口 {port} 尚未释放，最多再等待 {} 秒…",
+                        remaining.as_secs_f32().ceil()
+                    ),
+                );
+            },
         )?;
-        let mut updater = updater;
-        wait_for_http_with_process_state(
-            "updater",
-            &mut updater,
-            "127.0.0.1",
-            5274,
-            Duration::from_secs(30),
-            &logs_dir,
+        timer.mark("port check");
+
+        let python_home =
+            pyruntime::resolve_python_home(app, &runtime_root, &server_dir, &data_dir)?;
+        check_required_files(&runtime_root, Some(&python_home))?;
+        for message in cabundle::apply(&mut common_env, &data_dir, &python_home)? {
+            append_shell_log(&logs_dir, &message);
+        }
+        let mut specs = service_specs(
+            &runtime_root,
+            &python_home,
+            ServicePorts {
+                server: server_port,
+                batch: batch_port,
+                updater_host: runtime_url.host_str().unwrap_or("127.0.0.1"),
+                updater: updater_port,
+            },
+            ready_timeout,
         )?;
-        processes.push(updater);
+        for spec in &mut specs {
+            launchargs::apply(spec, &common_env)?;
+        }
+        timer.mark("prepare");
+
+        let mut observed_bindings = Vec::new();
+        // 各服务配置的监听地址，用于核对实际监听范围
+        let configured_host = |key: &str| {
+            common_env
+                .get(key)
+                .map(|h| h.trim().to_string())
+                .filter(|h| !h.is_empty())
+                .unwrap_or_else(|| "127.0.0.1".to_string())
+        };
+        database::preflight(&common_env, &data_dir, &logs_dir)?;
+        timer.mark("db preflight");
+        if let Some(config_file) = common_env.get("CONFIG_FILE") {
+            configguard::preflight(app, Path::new(config_file), &logs_dir)?;
+        }
+        for message in crashdumps::prepare(&logs_dir) {
+            append_shell_log(&logs_dir, &message);
+        }
 
-        if let Some(window) = app.get_webview_window("main") {
-            let _ = window.eval("window.location.replace('http://127.0.0.1:5274')");
-            let _ = app.emit("runtime-ready", true);
+        let parallel = common_env
+            .get(PARALLEL_START_KEY)
+            .is_some_and(|value| is_truthy(value));
+        // 已开始启动但尚未就绪的服务，启动失败时据此判断是哪个服务出了问题
+        let mut pending: Vec<String> = Vec::new();
+        let launched =
+            services::launch(
+                &specs,
+                &common_env,
+                &logs_dir,
+                parallel,
+                |progress| match progress {
+                    Progress::Spawning(spec) => {
+                        append_shell_log(
+                            &logs_dir,
+                            &format!("[INFO] 启动 {}: {}", spec.name, launchargs::render(spec)),
+                        );
+                        pending.push(spec.name.clone());
+                        emit_stage(app, "spawn", format!("正在启动 {}", spec.name));
+                    }
+                    Progress::Spawned(spec) => timer.mark(&format!("{} spawn", spec.name)),
+                    Progress::Ready(spec, child) => {
+                        pending.retain(|name| name != &spec.name);
+                        timer.mark(&format!("{} wait", spec.name));
+                        if let Some(port) = spec.readiness.port() {
+                            let host_key = format!("{}_HOST", spec.name.to_ascii_uppercase());
+                            observed_bindings.extend(bindings::check(
+                                app,
+                                &spec.name,
+                                child.id(),
+                                port,
+                                &configured_host(&host_key),
+                            ));
+                        }
+                    }
+                    Progress::Skipped(spec, reason) => {
+                        pending.retain(|name| name != &spec.name);
+                        append_shell_log(
+                            &logs_dir,
+                            &format!("[WARN] 已跳过 {}: {reason}", spec.name),
+                        );
+                    }
+                },
+            );
+        let running = launched.map_err(|message| match pending.into_iter().next() {
+            Some(service) => BootstrapError::ServiceFailed {
+                service,
+                diagnosis: pyfailure::analyze(&message),
+                message,
+            },
+            None => BootstrapError::Failed(message),
+        })?;
 
-            // 页面导航后注入外部链接拦截脚本
-            inject_external_link_interceptor(&window);
-            inject_startup_overlay(&window);
-            inject_db_config_button(&window);
+        if let Some(window) = app.get_webview_window("main") {
+            // updater 端口就绪后，HTTP 路由偶尔还要片刻才可用，过早导航会停在 404 页面直到手动刷新
+            match wait_for_runtime_page(&runtime_url) {
+                Ok(()) => {
+                    // 窗口此时可能隐藏或正在销毁，导航没有完成时由重新显示窗口时补上
+                    if let Err(err) = ensure_runtime_page(&window, &runtime_url, true) {
+                        append_shell_log(&logs_dir, &format!("[WARN] {err}"));
+                    }
+                    events::emit(app, "runtime-ready", Replay::Latest, true);
+                }
+                Err(err) => {
+                    append_shell_log(
+                        &logs_dir,
+                        &format!(
+                            "[WARN] WebUI 首页在 {} 秒内未就绪: {err}",
+                            PAGE_READY_TIMEOUT.as_secs()
+                        ),
+                    );
+                    // 健康监测会在首页可用后自动返回运行时页面
+                    health::show_offline_page(
+                        &window,
+                        &format!("服务已启动，但 WebUI 首页暂时无法打开：{err}\n首页可用后会自动返回，也可以点击重试。"),
+                    );
+                    events::emit(app, "runtime-ready", Replay::Latest, true);
+                }
+            }
         }
+        timer.mark("navigation");
+        let total_ms = timer.finish(app, &data_dir, &logs_dir);
+        journal::record(
+            app,
+            Severity::Info,
+            None,
+            format!("启动完成 ({})", timings::format_seconds(total_ms)),
+        );
+        snapshot::record_last_good(app);
 
         Ok(Self {
-            processes: Arc::new(Mutex::new(processes)),
+            services: Arc::new(Mutex::new(running)),
+            context: Mutex::new(RuntimeContext {
+                data_dir,
+                logs_dir,
+                server_dir,
+                common_env,
+                runtime_url,
+                bindings: observed_bindings,
+                bootstrap_ms: total_ms,
+            }),
         })
     }
 
-    pub fn shutdown_all(&self) {
-        let mut children = match self.processes.lock() {
-            Ok(guard) => guard,
-            Err(_) => return,
+    pub fn context(&self) -> Option<RuntimeContext> {
+        self.context.lock().ok().map(|ctx| ctx.clone())
+    }
+
+    /// 停止全部服务后按当前 runtime.env 重新执行启动流程。
+    pub fn restart(&self, app: &AppHandle) -> Result<(), BootstrapError> {
+        self.restart_after(app, || {})
+    }
+
+    /// 同 [`Self::restart`]，在全部服务停止后、重新启动前执行 `prepare`（如迁移安装目录）。
+    /// `prepare` 失败时仍会重新启动服务，随后返回该错误。
+    pub fn restart_with(
+        &self,
+        app: &AppHandle,
+        prepare: impl FnOnce() -> Result<(), String>,
+    ) -> Result<(), String> {
+        let mut prepared = Ok(());
+        self.restart_after(app, || prepared = prepare())
+            .map_err(|err| err.to_string())?;
+        prepared
+    }
+
+    fn restart_after(&self, app: &AppHandle, between: impl FnOnce()) -> Result<(), BootstrapError> {
+        datamove::ensure_idle()?;
+        journal::record(app, Severity::Info, None, "重启全部服务");
+        webhook::note_restart(app, "all");
+        self.relaunch_after(app, between).inspect_err(|err| {
+            let message = err.to_string();
+            journal::record(
+                app,
+                Severity::Error,
+                None,
+                format!("重启失败: {}", journal::first_line(&message)),
+            );
+            status::mark_failed(app, err);
+        })
+    }
+
+    /// 同 [`Self::restart`]，但不写运行记录、失败时不更新状态，由调用方处理（按需启动从休眠中恢复时使用）。
+    pub fn relaunch(&self, app: &AppHandle) -> Result<(), BootstrapError> {
+        self.relaunch_after(app, || {})
+    }
+
+    fn relaunch_after(
+        &self,
+        app: &AppHandle,
+        between: impl FnOnce(),
+    ) -> Result<(), BootstrapError> {
+        datamove::ensure_idle()?;
+        status::mark_starting(app);
+        // 旧页面上尚未执行的注入不再需要，先停下来再停止服务
+        tasks::shutdown(app, Some(Scope::Runtime));
+        self.shutdown_all();
+        tasks::shutdown(app, Some(Scope::Output));
+        between();
+
+        let fresh = Self::bootstrap(app)?;
+        if let (Ok(mut ours), Ok(mut theirs)) = (self.services.lock(), fresh.services.lock()) {
+            std::mem::swap(&mut *ours, &mut *theirs);
+        }
+        if let (Ok(mut ours), Some(theirs)) = (self.context.lock(), fresh.context()) {
+            *ours = theirs;
+        }
+        status::mark_running(app);
+        Ok(())
+    }
+
+    /// 不重启应用切换到另一个配置档：停止当前服务，改写当前配置档后按新配置档的数据目录与端口重新启动，
+    /// 主窗口随后导航到新的运行时地址并重新注入脚本（与启动流程相同）。切换期间主窗口显示启动页，
+    /// 进度通过 `bootstrap-stage` 事件展示。新配置档启动失败时切回原配置档重新启动；
+    /// 仍然失败时停留在失败状态，可以重试，不会出现服务与当前配置档不一致的情况。
+    pub fn switch_profile(&self, app: &AppHandle, name: &str) -> Result<(), String> {
+        datadir::validate_profile_name(name)?;
+        let previous = datadir::active_profile_name(app)?;
+        if previous == name {
+            return Ok(());
+        }
+        if SWITCHING_PROFILE.swap(true, Ordering::SeqCst) {
+            return Err("正在切换配置档，请稍候".to_string());
+        }
+        let switched = self.switch_profile_from(app, &previous, name);
+        SWITCHING_PROFILE.store(false, Ordering::SeqCst);
+        switched
+    }
+
+    fn switch_profile_from(
+        &self,
+        app: &AppHandle,
+        previous: &str,
+        name: &str,
+    ) -> Result<(), String> {
+        journal::record(
+            app,
+            Severity::Info,
+            None,
+            format!("切换配置档: {previous} → {name}"),
+        );
+        show_startup_page(app);
+        emit_stage(app, "profile", format!("正在切换到配置档 {name}"));
+        datadir::set_active_profile(app, name)?;
+        let Err(err) = self.restart(app) else {
+            events::emit(app, "profile-switched", Replay::Latest, name);
+            return Ok(());
+        };
+
+        emit_stage(
+            app,
+            "profile",
+            format!("配置档 {name} 启动失败，正在切回 {previous}"),
+        );
+        datadir::set_active_profile(app, previous).map_err(|e| {
+            format!("配置档 {name} 启动失败: {err}\n\n无法切回配置档 {previous}: {e}")
+        })?;
+        self.restart(app).map_err(|rollback| {
+            format!("配置档 {name} 启动失败: {err}\n\n切回配置档 {previous} 也失败: {rollback}")
+        })?;
+        journal::record(
+            app,
+            Severity::Warn,
+            None,
+            format!(
+                "配置档 {name} 启动失败，已切回 {previous}: {}",
+                journal::first_line(&err.to_string())
+            ),
+        );
+        events::emit(app, "profile-switched", Replay::Latest, previous);
+        Err(format!(
+            "配置档 {name} 启动失败，已切回配置档 {previous}。\n\n{err}"
+        ))
+    }
+
+    /// 各服务当前的进程号。
+    pub fn service_pids(&self) -> Vec<(String, u32)> {
+        let Ok(running) = self.services.lock() else {
+            return Vec::new();
         };
+        running
+            .iter()
+            .map(|service| (service.spec.name.clone(), service.child.id()))
+            .collect()
+    }
+
+    /// 服务进程是否仍在运行；服务列表正被占用（如正在重启）或没有该服务时为 None。
+    pub fn is_service_alive(&self, name: &str) -> Option<bool> {
+        let mut running = self.services.try_lock().ok()?;
+        let service = running
+            .iter_mut()
+            .find(|service| service.spec.name == name)?;
+        Some(matches!(service.child.try_wait(), Ok(None)))
+    }
+
+    /// 平滑停止单个服务后按原来的启动方式重新启动，其他服务不受影响。
+    pub fn restart_service(&self, app: &AppHandle, name: &str) -> Result<(), String> {
+        self.restart_service_with(app, name, || Ok(()))
+    }
+
+    /// 同 [`Self::restart_service`]，在服务停止后、重新启动前执行 `prepare`（如替换程序文件）。
+    /// `prepare` 失败时仍会重新启动服务，随后返回该错误。
+    pub fn restart_service_with(
+        &self,
+        app: &AppHandle,
+        name: &str,
+        prepare: impl FnOnce() -> Result<(), String>,
+    ) -> Result<(), String> {
+        self.restart_service_inner(app, name, prepare, &HashMap::new())
+    }
 
-        for child in children.iter_mut() {
-            let _ = child.kill();
+    /// 同 [`Self::restart_service`]，本次启动的环境变量额外加上 `overrides`（如临时调整日志级别），
+    /// 不影响其他服务，也不写入 runtime.env。
+    pub fn restart_service_with_env(
+        &self,
+        app: &AppHandle,
+        name: &str,
+        overrides: &HashMap<String, String>,
+    ) -> Result<(), String> {
+        self.restart_service_inner(app, name, || Ok(()), overrides)
+    }
+
+    fn restart_service_inner(
+        &self,
+        app: &AppHandle,
+        name: &str,
+        prepare: impl FnOnce() -> Result<(), String>,
+        overrides: &HashMap<String, String>,
+    ) -> Result<(), String> {
+        datamove::ensure_idle()?;
+        let mut context = self.context().ok_or_else(|| "运行时尚未启动".to_string())?;
+        context.common_env.extend(
+            overrides
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        let mut running = self
+            .services
+            .lock()
+            .map_err(|_| "服务列表不可用".to_string())?;
+        let service = running
+            .iter_mut()
+            .find(|service| service.spec.name == name)
+            .ok_or_else(|| format!("服务 {name} 未在运行"))?;
+        services::stop_gracefully(&mut service.child, SERVICE_STOP_TIMEOUT);
+        webhook::note_restart(app, name);
+        let prepared = prepare();
+        // 依赖的服务仍在运行，只按该服务自己的启动方式重新拉起
+        let mut spec = service.spec.clone();
+        spec.depends_on.clear();
+        let fresh = services::launch(
+            std::slice::from_ref(&spec),
+            &context.common_env,
+            &context.logs_dir,
+            false,
+            |progress| {
+                if let Progress::Spawning(spec) = progress {
+                    append_shell_log(
+                        &context.logs_dir,
+                        &format!("[INFO] 启动 {}: {}", spec.name, launchargs::render(spec)),
+                    );
+                }
+            },
+        )
+        .inspect_err(|err| {
+            journal::record(
+                app,
+                Severity::Error,
+                Some(name),
+                format!("重启失败: {}", journal::first_line(err)),
+            );
+        })?;
+        if let Some(started) = fresh.into_iter().next() {
+            service.child = started.child;
+        }
+        prepared
+    }
+
+    pub fn shutdown_all(&self) {
+        if let Ok(mut running) = self.services.lock() {
+            services::stop_all(&mut running);
+        }
+    }
+
+    /// 按启动顺序的逆序逐个平滑停止全部服务，用于按需启动进入休眠。
+    pub fn shutdown_gracefully(&self) {
+        if let Ok(mut running) = self.services.lock() {
+            for service in running.iter_mut().rev() {
+                services::stop_gracefully(&mut service.child, SERVICE_STOP_TIMEOUT);
+            }
+            running.clear();
         }
-        children.clear();
     }
 }
 
@@ -417,10 +750,267 @@ impl Drop for RuntimeManager {
     }
 }
 
-fn resolve_runtime_root(app: &AppHandle) -> Result<PathBuf, String> {
+/// 宿主环境中允许覆盖默认值的变量（runtime.env 的优先级更高）。
+const HOST_ENV_OVERRIDE_KEYS: &[&str] = &[
+    "DB_TYPE",
+    "MYSQL_HOST",
+    "MYSQL_PORT",
+    "MYSQL_USER",
+    "MYSQL_PASSWORD",
+    "MYSQL_DATABASE",
+    "POSTGRES_HOST",
+    "POSTGRES_PORT",
+    "POSTGRES_USER",
+    "POSTGRES_PASSWORD",
+    "POSTGRES_DATABASE",
+    "SERVER_HOST",
+    "SERVER_PORT",
+    "BATCH_PORT",
+    "UPDATER_PORT",
+    "UPDATER_HOST",
+    "PTNEXUS_BASE_PATH",
+    "GO_SERVICE_URL",
+    "CORE_API_URL",
+    "PTNEXUS_EMBED_BG_IN_APP",
+    "TZ",
+    "PTNEXUS_TZ",
+    "LANG",
+    "LC_ALL",
+];
+
+/// runtime.env 中列出的额外宿主环境变量（逗号分隔），如证书路径、站点 API 密钥。
+const ENV_PASSTHROUGH_KEY: &str = "PTNEXUS_ENV_PASSTHROUGH";
+
+/// `PTNEXUS_ENV_PASSTHROUGH` 的生效结果。
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EnvPassthrough {
+    /// 宿主环境中存在并已传给子进程的键。
+    pub found: Vec<String>,
+    /// 宿主环境中不存在（或为空）的键。
+    pub missing: Vec<String>,
+    /// 含 `=` 或空白等、不能作为环境变量名的项。
+    pub invalid: Vec<String>,
+}
+
+/// 子进程共用的环境变量、需要记录的告警与透传结果。
+type CommonEnv = (HashMap<String, String>, Vec<String>, EnvPassthrough);
+
+/// 组装子进程共用的环境变量：默认值 → 宿主环境覆盖 → PTNEXUS_ENV_PASSTHROUGH → runtime.env → 派生值。
+/// 返回需要记录的告警与透传结果，启动流程与干跑计划共用。
+fn assemble_common_env(
+    profile: &ProfilePaths,
+    server_dir: &Path,
+    changelog_path: &Path,
+) -> Result<CommonEnv, String> {
+    let mut common_env = build_runtime_env(profile, server_dir, changelog_path);
+    apply_host_env_overrides(&mut common_env, HOST_ENV_OVERRIDE_KEYS);
+
+    // 透传列表写在 runtime.env 中，但要先于 runtime.env 应用，保证文件中的取值优先
+    let mut file_env = HashMap::new();
+    merge_env_file(&mut file_env, &profile.root.join("runtime.env"))?;
+    let passthrough = file_env
+        .get(ENV_PASSTHROUGH_KEY)
+        .map(|list| {
+            apply_env_passthrough(&mut common_env, list, |key| {
+                std::env::var(key)
+                    .ok()
+                    .filter(|value| !value.trim().is_empty())
+            })
+        })
+        .unwrap_or_default();
+    common_env.extend(file_env);
+
+    let mut warnings = apply_derived_service_urls(&mut common_env);
+    warnings.extend(align_timezone(&mut common_env, cfg!(target_os = "windows")));
+    if !passthrough.missing.is_empty() {
+        warnings.push(format!(
+            "{ENV_PASSTHROUGH_KEY} 中的 {} 在宿主环境中不存在",
+            passthrough.missing.join(", ")
+        ));
+    }
+    if !passthrough.invalid.is_empty() {
+        warnings.push(format!(
+            "{ENV_PASSTHROUGH_KEY} 中的 {} 不是合法的环境变量名，已忽略",
+            passthrough.invalid.join(", ")
+        ));
+    }
+    bdinfo::align_bdinfo_dir(&mut common_env, &server_dir.join("bdinfo"));
+    Ok((common_env, warnings, passthrough))
+}
+
+fn apply_env_passthrough(
+    envs: &mut HashMap<String, String>,
+    list: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> EnvPassthrough {
+    let mut result = EnvPassthrough::default();
+    for key in list.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+        if key.contains('=') || key.contains('\0') || key.chars().any(char::is_whitespace) {
+            result.invalid.push(key.to_string());
+            continue;
+        }
+        match lookup(key) {
+            Some(value) => {
+                envs.insert(key.to_string(), value);
+                result.found.push(key.to_string());
+            }
+            None => result.missing.push(key.to_string()),
+        }
+    }
+    result
+}
+
+/// 启动干跑计划：解析运行目录与最终环境变量，但不启动任何进程，便于排查配置问题。
+#[derive(Serialize)]
+pub struct BootstrapPlan {
+    pub runtime_root: String,
+    pub data_dir: String,
+    pub env: BTreeMap<String, String>,
+    pub env_passthrough: EnvPassthrough,
+    /// 各服务的完整启动命令，含 runtime.env 中的附加参数与包装命令（见 launchargs.rs）。
+    pub launch: Vec<launchargs::LaunchCommand>,
+    pub warnings: Vec<String>,
+}
+
+pub fn bootstrap_plan(app: &AppHandle) -> Result<BootstrapPlan, String> {
+    let runtime_root = resolve_runtime_root(app)?;
+    let changelog_path = resolve_changelog_path(app, &runtime_root);
+    let profile = datadir::active_profile(app)?;
+    let server_dir = runtime_root.join("server");
+
+    let (common_env, mut warnings, env_passthrough) =
+        assemble_common_env(&profile, &server_dir, &changelog_path)?;
+    let python_home = pyruntime::expected_python_home(&runtime_root, &server_dir, &profile.root);
+    let launch = match planned_specs(&runtime_root, &python_home, &common_env) {
+        Ok(specs) => specs
+            .iter()
+            .map(|spec| launchargs::describe(spec, &common_env))
+            .collect(),
+        Err(err) => {
+            warnings.push(format!("无法列出启动命令：{err}"));
+            Vec::new()
+        }
+    };
+    let env = common_env
+        .into_iter()
+        .map(|(key, value)| {
+            let value = if is_secret_key(&key) {
+                "******".to_string()
+            } else {
+                value
+            };
+            (key, value)
+        })
+        .collect();
+
+    Ok(BootstrapPlan {
+        runtime_root: runtime_root.to_string_lossy().to_string(),
+        data_dir: profile.root.to_string_lossy().to_string(),
+        env,
+        env_passthrough,
+        launch,
+        warnings,
+    })
+}
+
+/// 与启动时相同的服务表，端口等取自最终环境变量。
+fn planned_specs(
+    runtime_root: &Path,
+    python_home: &Path,
+    envs: &HashMap<String, String>,
+) -> Result<Vec<ServiceSpec>, String> {
+    let runtime_url = runtime_url_from_env(envs)?;
+    let mut specs = service_specs(
+        runtime_root,
+        python_home,
+        ServicePorts {
+            server: env_port(envs, "SERVER_PORT", 5275)?,
+            batch: env_port(envs, "BATCH_PORT", 5276)?,
+            updater_host: runtime_url.host_str().unwrap_or("127.0.0.1"),
+            updater: runtime_url.port_or_known_default().unwrap_or(UPDATER_PORT),
+        },
+        env_secs(envs, services::READY_TIMEOUT_KEY, READY_TIMEOUT)?,
+    )?;
+    for spec in &mut specs {
+        launchargs::apply(spec, envs)?;
+    }
+    Ok(specs)
+}
+
+/// 展示给用户的计划、诊断信息中需要隐藏取值的键。
+pub(crate) fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    ["PASSWORD", "TOKEN", "SECRET", "API_KEY", "COOKIE"]
+        .iter()
+        .any(|marker| key.contains(marker))
+}
+
+/// 确保用户目录下存在 runtime.env 并返回其路径。
+/// 与启动流程共用模板复制逻辑；找不到模板时创建一个仅含说明的空配置。
+pub fn ensure_runtime_env_file(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = datadir::active_profile(app)?.root;
+    fs::create_dir_all(&data_dir).map_err(|e| format!("创建应用数据目录失败: {e}"))?;
+
+    if let Ok(runtime_root) = resolve_runtime_root(app) {
+        seed_runtime_env(&runtime_root, &data_dir);
+    }
+
+    let env_path = data_dir.join("runtime.env");
+    if !env_path.exists() {
+        fsutil::atomic_write(
+            &env_path,
+            "# PT Nexus Desktop 运行时环境变量（KEY=VALUE）\n",
+        )
+        .map_err(|e| format!("创建 runtime.env 失败 ({}): {e}", env_path.display()))?;
+    }
+
+    Ok(env_path)
+}
+
+pub fn resolve_server_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(resolve_runtime_root(app)?.join("server"))
+}
+
+/// 首次运行时，把模板配置复制到用户可写目录，方便后续修改 DB/端口等运行参数。
+fn seed_runtime_env(runtime_root: &Path, data_dir: &Path) {
+    let bundled_env_example = if runtime_root
+        .join("data")
+        .join("runtime.env.example")
+        .exists()
+    {
+        runtime_root.join("data").join("runtime.env.example")
+    } else {
+        runtime_root
+            .join("_up_")
+            .join("runtime")
+            .join("data")
+            .join("runtime.env.example")
+    };
+    let local_env_example = data_dir.join("runtime.env.example");
+    let local_runtime_env = data_dir.join("runtime.env");
+
+    if bundled_env_example.exists() && !local_env_example.exists() {
+        let _ = fsutil::atomic_copy(&bundled_env_example, &local_env_example);
+    }
+
+    // 用户目录若不存在 runtime.env，则直接从模板创建一份可编辑配置。
+    if bundled_env_example.exists() && !local_runtime_env.exists() {
+        let _ = fsutil::atomic_copy(&bundled_env_example, &local_runtime_env);
+    }
+}
+
+pub(crate) fn resolve_runtime_root(app: &AppHandle) -> Result<PathBuf, String> {
+    let cached = RESOLVED_RUNTIME_ROOT
+        .lock()
+        .ok()
+        .and_then(|root| root.clone());
+    if let Some(root) = cached.filter(|root| is_runtime_root(root)) {
+        return Ok(root);
+    }
     let candidates = candidate_runtime_roots(app);
     for candidate in &candidates {
         if is_runtime_root(candidate) {
+            remember_runtime_root(candidate);
             return Ok(candidate.clone());
         }
     }
@@ -435,6 +1025,13 @@ fn resolve_runtime_root(app: &AppHandle) -> Result<PathBuf, String> {
     ))
 }
 
+/// 运行目录换了位置（见 legacylayout.rs）后调用，之后的解析直接使用新位置。
+pub(crate) fn remember_runtime_root(root: &Path) {
+    if let Ok(mut cached) = RESOLVED_RUNTIME_ROOT.lock() {
+        *cached = Some(root.to_path_buf());
+    }
+}
+
 fn candidate_runtime_roots(app: &AppHandle) -> Vec<PathBuf> {
     let mut candidates = Vec::new();
 
@@ -470,6 +1067,20 @@ fn candidate_runtime_roots(app: &AppHandle) -> Vec<PathBuf> {
     dedup_paths(candidates)
 }
 
+/// updater 维护的本地 CHANGELOG.json（LOCAL_CONFIG_FILE），第一条记录即当前安装的版本。
+pub(crate) fn local_changelog_path(app: &AppHandle) -> Option<PathBuf> {
+    let configured = app
+        .try_state::<RuntimeManager>()
+        .and_then(|runtime| runtime.context())
+        .and_then(|context| context.common_env.get("LOCAL_CONFIG_FILE").cloned());
+    if let Some(path) = configured {
+        return Some(PathBuf::from(path));
+    }
+    resolve_runtime_root(app)
+        .ok()
+        .map(|root| resolve_changelog_path(app, &root))
+}
+
 fn resolve_changelog_path(app: &AppHandle, runtime_root: &Path) -> PathBuf {
     let mut candidates = Vec::new();
     candidates.push(runtime_root.join("CHANGELOG.json"));
@@ -497,7 +1108,10 @@ fn resolve_changelog_path(app: &AppHandle, runtime_root: &Path) -> PathBuf {
         }
     }
 
-    if let Ok(path) = app.path().resolve("CHANGELOG.json", BaseDirectory::Resource) {
+    if let Ok(path) = app
+        .path()
+        .resolve("CHANGELOG.json", BaseDirectory::Resource)
+    {
         candidates.push(path);
     }
 
@@ -537,25 +1151,105 @@ fn push_layout_candidates(candidates: &mut Vec<PathBuf>, base_dir: &Path) {
     candidates.push(base_dir.join("_up_").join("runtime"));
 }
 
-
 fn is_runtime_root(root: &Path) -> bool {
-    root.join("updater").join(exe_name("updater")).exists()
-        && root.join("batch").join(exe_name("batch")).exists()
-        && root.join("server").join("dist").join("index.html").exists()
+    [
+        root.join("updater").join(exe_name("updater")),
+        root.join("batch").join(exe_name("batch")),
+        root.join("server").join("dist").join("index.html"),
+    ]
+    .iter()
+    .all(|path| paths::extended(path).exists())
+}
+
+/// 各服务的监听端口，由最终环境变量解析得出。
+struct ServicePorts<'a> {
+    server: u16,
+    batch: u16,
+    updater_host: &'a str,
+    updater: u16,
+}
+
+/// 后端服务表。启动顺序由依赖关系决定：background_runner 先完成数据库迁移，
+/// server 随后启动，batch 通过 CORE_API_URL、updater 通过反向代理依赖 server。
+fn service_specs(
+    runtime_root: &Path,
+    python_home: &Path,
+    ports: ServicePorts<'_>,
+    ready_timeout: Duration,
+) -> Result<Vec<ServiceSpec>, String> {
+    let server_dir = runtime_root.join("server");
+    let batch_dir = runtime_root.join("batch");
+    let updater_dir = runtime_root.join("updater");
+    let (runner_program, runner_args, runner_workdir) =
+        resolve_background_runner_launcher(&server_dir, python_home)?;
+    let (server_program, server_args, server_workdir) =
+        resolve_server_launcher(&server_dir, python_home)?;
+    let http = |host: &str, port: u16| Readiness::Http {
+        host: host.to_string(),
+        port,
+        timeout: ready_timeout,
+    };
+    let depends_on = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+    Ok(vec![
+        ServiceSpec {
+            name: "background_runner".to_string(),
+            program: runner_program,
+            args: runner_args,
+            workdir: runner_workdir,
+            readiness: Readiness::StaysRunning(Duration::from_secs(10)),
+            depends_on: Vec::new(),
+            required: true,
+        },
+        ServiceSpec {
+            name: "server".to_string(),
+            program: server_program,
+            args: server_args,
+            workdir: server_workdir,
+            // /health 在 Flask 应用初始化完成后才会应答，比端口可连接更能说明服务已可用
+            readiness: Readiness::Health {
+                host: "127.0.0.1".to_string(),
+                port: ports.server,
+                path: "/health".to_string(),
+                timeout: ready_timeout,
+            },
+            depends_on: depends_on(&["background_runner"]),
+            required: true,
+        },
+        ServiceSpec {
+            name: "batch".to_string(),
+            program: batch_dir.join(exe_name("batch")),
+            args: Vec::new(),
+            workdir: batch_dir,
+            readiness: http("127.0.0.1", ports.batch),
+            depends_on: depends_on(&["server"]),
+            required: true,
+        },
+        ServiceSpec {
+            name: "updater".to_string(),
+            program: updater_dir.join(exe_name("updater")),
+            args: Vec::new(),
+            workdir: updater_dir,
+            readiness: http(ports.updater_host, ports.updater),
+            depends_on: depends_on(&["server"]),
+            required: true,
+        },
+    ])
 }
 
 fn resolve_background_runner_launcher(
     server_dir: &Path,
+    python_home: &Path,
 ) -> Result<(PathBuf, Vec<String>, PathBuf), String> {
     let runner_exe = server_dir.join(exe_name("background_runner"));
-    if runner_exe.exists() {
+    if paths::extended(&runner_exe).exists() {
         return Ok((runner_exe, vec![], server_dir.to_path_buf()));
     }
 
-    let python_exe = server_dir.join("python").join(exe_name("python"));
+    let python_exe = python_home.join(exe_name("python"));
     let entry = server_dir.join("background_runner.py");
 
-    if python_exe.exists() && entry.exists() {
+    if paths::extended(&python_exe).exists() && paths::extended(&entry).exists() {
         return Ok((
             python_exe,
             vec!["-u".to_string(), entry.to_string_lossy().to_string()],
@@ -571,16 +1265,19 @@ fn resolve_background_runner_launcher(
     ))
 }
 
-fn resolve_server_launcher(server_dir: &Path) -> Result<(PathBuf, Vec<String>, PathBuf), String> {
+fn resolve_server_launcher(
+    server_dir: &Path,
+    python_home: &Path,
+) -> Result<(PathBuf, Vec<String>, PathBuf), String> {
     let server_exe = server_dir.join(exe_name("server"));
-    if server_exe.exists() {
+    if paths::extended(&server_exe).exists() {
         return Ok((server_exe, vec![], server_dir.to_path_buf()));
     }
 
-    let python_exe = server_dir.join("python").join(exe_name("python"));
+    let python_exe = python_home.join(exe_name("python"));
     let app_entry = server_dir.join("app.py");
 
-    if python_exe.exists() && app_entry.exists() {
+    if paths::extended(&python_exe).exists() && paths::extended(&app_entry).exists() {
         return Ok((
             python_exe,
             vec!["-u".to_string(), app_entry.to_string_lossy().to_string()],
@@ -596,8 +1293,9 @@ fn resolve_server_launcher(server_dir: &Path) -> Result<(PathBuf, Vec<String>, P
     ))
 }
 
+/// 子进程环境的默认值。数据相关的路径全部取自配置档，不同配置档的中间文件互不干扰。
 fn build_runtime_env(
-    data_dir: &Path,
+    profile: &ProfilePaths,
     server_dir: &Path,
     changelog_path: &Path,
 ) -> HashMap<String, String> {
@@ -622,7 +1320,14 @@ fn build_runtime_env(
     envs.insert("DEV_ENV".to_string(), "false".to_string());
     envs.insert("FLASK_DEBUG".to_string(), "false".to_string());
     envs.insert("PTNEXUS_EMBED_BG_IN_APP".to_string(), "false".to_string());
+    // 服务进程由桌面壳托管，更新器通过 UPDATE_DIR 中的文件请求重启（见 recycle.rs）
+    envs.insert(
+        recycle::SUPERVISOR_KEY.to_string(),
+        recycle::SUPERVISOR_DESKTOP.to_string(),
+    );
+    // 路径等取值一律按 UTF-8 传给 Python，避免中文用户名的数据目录在 GBK 代码页下被读乱
     envs.insert("PYTHONUTF8".to_string(), "1".to_string());
+    envs.insert("PYTHONIOENCODING".to_string(), "utf-8".to_string());
     envs.insert(
         "PYTHONPATH".to_string(),
         server_dir.to_string_lossy().to_string(),
@@ -632,16 +1337,8 @@ fn build_runtime_env(
 
     envs.insert("UPDATER_PORT".to_string(), updater_port.to_string());
     envs.insert("BATCH_PORT".to_string(), batch_port.to_string());
-    envs.insert("BATCH_ENHANCER_PORT".to_string(), batch_port.to_string());
-
-    envs.insert(
-        "GO_SERVICE_URL".to_string(),
-        format!("http://{server_host}:{batch_port}"),
-    );
-    envs.insert(
-        "CORE_API_URL".to_string(),
-        format!("http://{server_host}:{server_port}"),
-    );
+    // BATCH_ENHANCER_PORT / GO_SERVICE_URL / CORE_API_URL 由 apply_derived_service_urls
+    // 在合并完 runtime.env 之后统一推导，保证只改端口时跨服务地址也随之更新。
 
     envs.insert(
         "PTNEXUS_BASE_DIR".to_string(),
@@ -649,7 +1346,7 @@ fn build_runtime_env(
     );
     envs.insert(
         "PTNEXUS_DATA_DIR".to_string(),
-        data_dir.to_string_lossy().to_string(),
+        profile.root.to_string_lossy().to_string(),
     );
     envs.insert(
         "PTNEXUS_STATIC_DIR".to_string(),
@@ -669,7 +1366,10 @@ fn build_runtime_env(
     );
     envs.insert(
         "PTNEXUS_BDINFO_PATH".to_string(),
-        bdinfo_dir.join(exe_name("BDInfo")).to_string_lossy().to_string(),
+        bdinfo_dir
+            .join(exe_name("BDInfo"))
+            .to_string_lossy()
+            .to_string(),
     );
     envs.insert(
         "PTNEXUS_MPV_PATH".to_string(),
@@ -686,31 +1386,40 @@ fn build_runtime_env(
 
     envs.insert(
         "TEMP_DIR".to_string(),
-        data_dir.join("tmp").to_string_lossy().to_string(),
+        profile.temp_dir.to_string_lossy().to_string(),
     );
     envs.insert(
         "CONFIG_FILE".to_string(),
-        data_dir.join("config.json").to_string_lossy().to_string(),
+        profile.config_file.to_string_lossy().to_string(),
     );
 
     envs.insert(
         "UPDATE_DIR".to_string(),
-        data_dir.join("updates").to_string_lossy().to_string(),
+        profile.update_dir.to_string_lossy().to_string(),
     );
     envs.insert(
         "REPO_DIR".to_string(),
-        data_dir
-            .join("updates")
-            .join("repo")
-            .to_string_lossy()
-            .to_string(),
+        profile.repo_dir.to_string_lossy().to_string(),
     );
     envs.insert(
         "LOCAL_CONFIG_FILE".to_string(),
         changelog_path.to_string_lossy().to_string(),
     );
 
-    let path_separator = if cfg!(target_os = "windows") { ";" } else { ":" };
+    // 子进程的环境被精简过，Python 未必能自行识别系统时区与语言，这里显式传入。
+    // IANA 名称放在 PTNEXUS_TZ，TZ 由 align_timezone 按平台补上。
+    if let Some(timezone) = detect_system_timezone() {
+        envs.insert("PTNEXUS_TZ".to_string(), timezone);
+    }
+    let locale = detect_system_locale();
+    envs.insert("LANG".to_string(), locale.clone());
+    envs.insert("LC_ALL".to_string(), locale);
+
+    let path_separator = if cfg!(target_os = "windows") {
+        ";"
+    } else {
+        ":"
+    };
     let mut path_entries = vec![
         mpv_dir.to_string_lossy().to_string(),
         ffmpeg_dir.to_string_lossy().to_string(),
@@ -725,6 +1434,59 @@ fn build_runtime_env(
     envs
 }
 
+/// 让 `TZ` 与 `PTNEXUS_TZ` 一致：`PTNEXUS_TZ` 总是 IANA 名称，供服务显示与计算定时任务。
+/// Windows 的 C 运行库只认 POSIX 形式的 TZ（如 `CST-8`），遇到 `Asia/Shanghai` 会把本地时间当成 UTC，
+/// 因此 Windows 上不设置 TZ，runtime.env 中写成 IANA 名称的 TZ 改为 PTNEXUS_TZ 并给出告警；
+/// 其他平台上 TZ 未设置时取 PTNEXUS_TZ。
+fn align_timezone(envs: &mut HashMap<String, String>, windows: bool) -> Option<String> {
+    let mut warning = None;
+    if let Some(timezone) = envs.get("TZ").filter(|tz| tz.contains('/')).cloned() {
+        envs.insert("PTNEXUS_TZ".to_string(), timezone.clone());
+        if windows {
+            envs.remove("TZ");
+            warning = Some(format!(
+                "TZ={timezone} 是 IANA 时区名，Windows 的 C 运行库无法识别，已改为通过 PTNEXUS_TZ 传给服务"
+            ));
+        }
+    }
+    if !windows && !envs.contains_key("TZ") {
+        if let Some(timezone) = envs.get("PTNEXUS_TZ").cloned() {
+            envs.insert("TZ".to_string(), timezone);
+        }
+    }
+    warning
+}
+
+/// 系统时区的 IANA 名称。Windows 上 iana-time-zone 会把 Windows 时区 ID
+/// （如 "China Standard Time"）转换为 IANA 名称（如 "Asia/Shanghai"）。
+pub fn detect_system_timezone() -> Option<String> {
+    iana_time_zone::get_timezone()
+        .ok()
+        .filter(|tz| !tz.trim().is_empty())
+}
+
+/// 系统语言，转换为 POSIX 形式（zh-CN → zh_CN.UTF-8）；无法识别时回退到 C.UTF-8。
+fn detect_system_locale() -> String {
+    sys_locale::get_locale()
+        .and_then(|tag| posix_locale(&tag))
+        .unwrap_or_else(|| "C.UTF-8".to_string())
+}
+
+fn posix_locale(bcp47: &str) -> Option<String> {
+    let mut parts = bcp47.split(['-', '_']);
+    let language = parts.next()?.to_ascii_lowercase();
+    if language.is_empty() || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    // 跳过书写系统（如 zh-Hans-CN 中的 Hans），取两位地区码
+    let region = parts.find(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_alphabetic()));
+    Some(match region {
+        Some(region) => format!("{language}_{}.UTF-8", region.to_ascii_uppercase()),
+        None => format!("{language}.UTF-8"),
+    })
+}
+
 fn apply_host_env_overrides(envs: &mut HashMap<String, String>, keys: &[&str]) {
     for key in keys {
         if let Ok(value) = std::env::var(key) {
@@ -735,7 +1497,10 @@ fn apply_host_env_overrides(envs: &mut HashMap<String, String>, keys: &[&str]) {
     }
 }
 
-fn merge_env_file(envs: &mut HashMap<String, String>, env_file: &Path) -> Result<(), String> {
+pub(crate) fn merge_env_file(
+    envs: &mut HashMap<String, String>,
+    env_file: &Path,
+) -> Result<(), String> {
     if !env_file.exists() {
         return Ok(());
     }
@@ -762,12 +1527,11 @@ fn merge_env_file(envs: &mut HashMap<String, String>, env_file: &Path) -> Result
         }
 
         let mut value = raw_value.trim().to_string();
-        if (value.starts_with('"') && value.ends_with('"'))
-            || (value.starts_with('\'') && value.ends_with('\''))
+        if ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')))
+            && value.len() >= 2
         {
-            if value.len() >= 2 {
-                value = value[1..value.len() - 1].to_string();
-            }
+            value = value[1..value.len() - 1].to_string();
         }
 
         envs.insert(key.to_string(), value);
@@ -776,213 +1540,1172 @@ fn merge_env_file(envs: &mut HashMap<String, String>, env_file: &Path) -> Result
     Ok(())
 }
 
-fn spawn_process(
-    executable: &Path,
-    working_dir: &Path,
-    envs: &HashMap<String, String>,
-    args: &[String],
-    process_name: &str,
-    logs_dir: &Path,
-) -> Result<Child, String> {
-    let stdout_log = logs_dir.join(format!("{process_name}.stdout.log"));
-    let stderr_log = logs_dir.join(format!("{process_name}.stderr.log"));
-
-    let stdout_file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&stdout_log)
-        .map_err(|e| format!("打开日志文件失败 {}: {e}", stdout_log.display()))?;
-
-    let stderr_file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&stderr_log)
-        .map_err(|e| format!("打开日志文件失败 {}: {e}", stderr_log.display()))?;
-
-    let mut cmd = Command::new(executable);
-    cmd.args(args)
-        .current_dir(working_dir)
-        .stdin(Stdio::null())
-        .stdout(Stdio::from(stdout_file))
-        .stderr(Stdio::from(stderr_file));
-
-    // Windows 上隐藏子进程的终端窗口，避免弹出三个黑框
-    #[cfg(target_os = "windows")]
-    {
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        cmd.creation_flags(CREATE_NO_WINDOW);
-    }
+/// 根据最终的 SERVER_HOST / SERVER_PORT / BATCH_PORT 推导跨服务地址。
+/// 用户显式设置的 GO_SERVICE_URL / CORE_API_URL 原样保留，但会校验能否解析、
+/// 主机与端口是否与 SERVER_HOST 和端口配置一致；返回需要记录的告警。
+fn apply_derived_service_urls(envs: &mut HashMap<String, String>) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    let host = envs
+        .get("SERVER_HOST")
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "127.0.0.1".to_string());
+    // 监听全部地址时，子进程之间仍应通过回环地址互访。
+    let connect_host = match host.as_str() {
+        "0.0.0.0" | "::" | "[::]" => "127.0.0.1".to_string(),
+        _ => host,
+    };
 
-    for (key, value) in envs {
-        cmd.env(key, value);
+    let server_port = envs
+        .get("SERVER_PORT")
+        .cloned()
+        .unwrap_or_else(|| "5275".to_string());
+    let batch_port = envs
+        .get("BATCH_PORT")
+        .cloned()
+        .unwrap_or_else(|| "5276".to_string());
+
+    envs.entry("BATCH_ENHANCER_PORT".to_string())
+        .or_insert_with(|| batch_port.clone());
+
+    for (key, port) in [
+        ("GO_SERVICE_URL", &batch_port),
+        ("CORE_API_URL", &server_port),
+    ] {
+        match envs.get(key).cloned() {
+            Some(url) if !url.trim().is_empty() => {
+                let Some((url_host, url_port)) = parse_http_host_port(&url) else {
+                    warnings.push(format!("{key}={url} 无法解析为 http(s)://host:port 地址"));
+                    continue;
+                };
+                if !same_host(&url_host, &connect_host) {
+                    warnings.push(format!(
+                        "{key}={url} 的主机 {url_host} 与 SERVER_HOST 对应的 {connect_host} 不一致，跨服务调用可能失败"
+                    ));
+                }
+                if port.trim().parse::<u16>().ok() != Some(url_port) {
+                    warnings.push(format!(
+                        "{key}={url} 的端口 {url_port} 与端口配置 {port} 不一致，跨服务调用可能失败"
+                    ));
+                }
+            }
+            _ => {
+                envs.insert(key.to_string(), format!("http://{connect_host}:{port}"));
+            }
+        }
     }
 
-    cmd.spawn()
-        .map_err(|e| format!("启动进程失败 {}: {e}", executable.display()))
+    warnings
 }
 
+/// 主机名不区分大小写；localhost 与回环地址视为同一主机。
+fn same_host(a: &str, b: &str) -> bool {
+    let is_loopback = |host: &str| {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        host.eq_ignore_ascii_case("localhost") || host == "127.0.0.1" || host == "::1"
+    };
+    a.eq_ignore_ascii_case(b) || (is_loopback(a) && is_loopback(b))
+}
 
-fn ensure_ports_available(ports: &[u16]) -> Result<(), String> {
-    for port in ports {
-        if TcpListener::bind(("127.0.0.1", *port)).is_err() {
-            return Err(format!("端口 {port} 被占用，请先释放后再启动应用。"));
-        }
+/// 解析 http(s) URL 的 host 与端口（未写端口时按协议取默认值）。
+fn parse_http_host_port(url: &str) -> Option<(String, u16)> {
+    let url = url.trim();
+    let (rest, default_port) = if let Some(rest) = url.strip_prefix("http://") {
+        (rest, 80)
+    } else if let Some(rest) = url.strip_prefix("https://") {
+        (rest, 443)
+    } else {
+        return None;
+    };
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let authority = authority.rsplit('@').next().unwrap_or_default();
+    if authority.is_empty() {
+        return None;
+    }
+
+    if let Some(bracketed) = authority.strip_prefix('[') {
+        let (host, tail) = bracketed.split_once(']')?;
+        let port = match tail.strip_prefix(':') {
+            Some(port) => port.parse().ok()?,
+            None if tail.is_empty() => default_port,
+            None => return None,
+        };
+        return Some((host.to_string(), port));
+    }
+
+    match authority.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() => Some((host.to_string(), port.parse().ok()?)),
+        Some(_) => None,
+        None => Some((authority.to_string(), default_port)),
     }
-    Ok(())
 }
 
-fn wait_for_http_with_process_state(
-    process_name: &str,
-    child: &mut Child,
-    host: &str,
-    port: u16,
-    timeout: Duration,
-    logs_dir: &Path,
-) -> Result<(), String> {
-    let begin = Instant::now();
-    let stdout_log = logs_dir.join(format!("{process_name}.stdout.log"));
-    let stderr_log = logs_dir.join(format!("{process_name}.stderr.log"));
+/// 追加一行到 logs/shell.log，记录桌面壳自身的告警与诊断信息。
+pub(crate) fn append_shell_log(logs_dir: &Path, message: &str) {
+    let path = logs_dir.join("shell.log");
+    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) else {
+        return;
+    };
+    let _ = writeln!(
+        file,
+        "{} {message}",
+        format_utc_timestamp(SystemTime::now())
+    );
+}
 
-    loop {
-        if std::net::TcpStream::connect((host, port)).is_ok() {
-            return Ok(());
-        }
+/// 把时间格式化为 `YYYY-MM-DDTHH:MM:SSZ`（UTC），避免为此引入日期库。
+pub(crate) fn format_utc_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Howard Hinnant 的 civil_from_days 算法
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
 
-        match child.try_wait() {
-            Ok(Some(status)) => {
-                let stderr_tail = read_log_tail(&stderr_log, 40);
-                if stderr_tail.is_empty() {
-                    return Err(format!(
-                        "进程 {process_name} 已退出（状态: {status}），服务 {host}:{port} 未就绪。\n请查看日志：{}",
-                        stderr_log.display()
-                    ));
+/// 更新 runtime.env 中的键：已存在的行原地替换，不存在的追加到末尾，值为 None 时删除该键。
+/// 其余行（包括注释）保持不变。
+pub fn update_env_file(env_file: &Path, updates: &[(&str, Option<&str>)]) -> Result<(), String> {
+    let content = if env_file.exists() {
+        fs::read_to_string(env_file)
+            .map_err(|e| format!("读取 runtime.env 失败 ({}): {e}", env_file.display()))?
+    } else {
+        String::new()
+    };
+
+    let mut applied = HashSet::new();
+    let mut lines = Vec::new();
+    for raw_line in content.lines() {
+        let line = raw_line.trim();
+        let key = (!line.starts_with('#'))
+            .then(|| line.split_once('=').map(|(key, _)| key.trim()))
+            .flatten();
+
+        match key.and_then(|key| updates.iter().find(|(k, _)| *k == key)) {
+            Some((key, value)) => {
+                if applied.insert(*key) {
+                    if let Some(value) = value {
+                        lines.push(format!("{key}={value}"));
+                    }
                 }
+            }
+            None => lines.push(raw_line.to_string()),
+        }
+    }
 
-                return Err(format!(
-                    "进程 {process_name} 已退出（状态: {status}），服务 {host}:{port} 未就绪。\n日志：{}\n\n最近 stderr 输出：\n{stderr_tail}",
-                    stderr_log.display()
-                ));
+    for (key, value) in updates {
+        if let (false, Some(value)) = (applied.contains(key), value) {
+            lines.push(format!("{key}={value}"));
+        }
+    }
+
+    let mut output = lines.join("\n");
+    output.push('\n');
+    fsutil::atomic_write(env_file, output)
+        .map_err(|e| format!("写入 runtime.env 失败 ({}): {e}", env_file.display()))
+}
+
+/// 读取单个运行参数：runtime.env 优先（与启动时的合并顺序一致），其次是宿主环境变量。
+pub fn read_runtime_setting(app: &AppHandle, key: &str) -> Option<String> {
+    if let Ok(data_dir) = datadir::active_profile(app).map(|profile| profile.root) {
+        let mut values = HashMap::new();
+        if merge_env_file(&mut values, &data_dir.join("runtime.env")).is_ok() {
+            if let Some(value) = values.remove(key) {
+                return Some(value);
             }
-            Ok(None) => {}
-            Err(e) => {
-                return Err(format!(
-                    "检查进程 {process_name} 运行状态失败: {e}。\n请查看日志：{}, {}",
-                    stdout_log.display(),
-                    stderr_log.display()
-                ));
+        }
+    }
+
+    std::env::var(key)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+pub fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// 重新导航到运行时页面并重新注入全部脚本，用于渲染进程崩溃后的恢复。
+pub fn reload_runtime_page(window: &WebviewWindow) {
+    let url = runtime_url(window.app_handle());
+    let _ = ensure_runtime_page(window, &url, true);
+}
+
+/// 导航到运行时页面的指定地址（可带额外查询参数）并重新注入全部脚本。
+pub fn navigate_runtime_page(window: &WebviewWindow, url: tauri::Url) -> Result<(), String> {
+    ensure_runtime_page(window, &url, true)
+}
+
+/// 主窗口最近一次请求的运行时页面，及其是否已加载完成并注入脚本（由页面加载事件更新）。
+struct RuntimePage {
+    requested: Option<tauri::Url>,
+    loaded: bool,
+}
+
+static RUNTIME_PAGE: Mutex<RuntimePage> = Mutex::new(RuntimePage {
+    requested: None,
+    loaded: false,
+});
+
+/// 确保主窗口停在运行时页面 `url` 上并已注入全部脚本，可以重复调用。
+/// `reload` 为 false 时，上次导航已确认加载完成、或窗口正显示离线提示页（由健康监测负责返回）
+/// 或更新进度页（由 updatepage.rs 负责返回）就什么也不做；
+/// 否则（启动时窗口已关到托盘、正在销毁，导航没有生效等）重新导航。
+/// 页面加载完成后由 [`on_main_page_load`] 注入脚本，而不是导航后按固定延时注入，避免注入落到旧页面上。
+pub fn ensure_runtime_page(
+    window: &WebviewWindow,
+    url: &tauri::Url,
+    reload: bool,
+) -> Result<(), String> {
+    let current = window.url().ok();
+    {
+        let Ok(mut page) = RUNTIME_PAGE.lock() else {
+            return Err("运行时页面状态不可用".to_string());
+        };
+        if !reload {
+            let loaded = page.loaded
+                && page
+                    .requested
+                    .as_ref()
+                    .is_some_and(|requested| requested.origin() == url.origin());
+            let offline = current.as_ref().is_some_and(|current| {
+                health::is_offline_page(window.app_handle(), current)
+                    || updatepage::is_update_page(window.app_handle(), current)
+            });
+            if loaded || offline {
+                return Ok(());
             }
         }
+        page.requested = Some(url.clone());
+        page.loaded = false;
+    }
+    window
+        .navigate(url.clone())
+        .map_err(|e| format!("加载 WebUI 失败: {e}"))
+}
 
-        if begin.elapsed() > timeout {
-            return Err(format!(
-                "等待服务 {host}:{port} 超时。\n请查看日志：{}, {}",
-                stdout_log.display(),
-                stderr_log.display()
-            ));
+/// 主窗口的页面加载事件：请求的运行时页面加载完成后注入脚本并记为完成；任何页面开始加载时清除完成标记。
+pub fn on_main_page_load(window: &WebviewWindow, payload: &PageLoadPayload<'_>) {
+    let Ok(mut page) = RUNTIME_PAGE.lock() else {
+        return;
+    };
+    match payload.event() {
+        PageLoadEvent::Started => page.loaded = false,
+        PageLoadEvent::Finished => {
+            let Some(requested) = page
+                .requested
+                .clone()
+                .filter(|requested| requested.origin() == payload.url().origin())
+            else {
+                return;
+            };
+            page.loaded = true;
+            drop(page);
+            inject_runtime_hooks(window, &requested);
+            webuicache::refresh(window.app_handle());
         }
+    }
+}
+
+/// 当前运行时的 WebUI 地址；服务尚未启动时返回默认地址。
+pub fn runtime_url(app: &AppHandle) -> tauri::Url {
+    app.try_state::<RuntimeManager>()
+        .and_then(|rt| rt.context())
+        .map(|ctx| ctx.runtime_url)
+        .unwrap_or_else(default_runtime_url)
+}
 
-        thread::sleep(Duration::from_millis(250));
+fn default_runtime_url() -> tauri::Url {
+    tauri::Url::parse(RUNTIME_URL).expect("RUNTIME_URL 必须是合法地址")
+}
+
+/// 按 UPDATER_HOST / UPDATER_PORT / PTNEXUS_BASE_PATH 计算 WebUI 地址。
+/// 监听地址为 0.0.0.0 等通配地址时（局域网模式）仍通过回环地址访问。
+fn runtime_url_from_env(envs: &HashMap<String, String>) -> Result<tauri::Url, String> {
+    let host = envs
+        .get("UPDATER_HOST")
+        .map(|h| h.trim())
+        .filter(|h| !h.is_empty())
+        .unwrap_or("127.0.0.1");
+    let host = match host {
+        "0.0.0.0" | "::" | "[::]" => "127.0.0.1",
+        other => other,
+    };
+    let port = env_port(envs, "UPDATER_PORT", UPDATER_PORT)?;
+    let base_path = envs
+        .get("PTNEXUS_BASE_PATH")
+        .map(|p| p.trim().trim_matches('/'))
+        .unwrap_or("");
+    let path = if base_path.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{base_path}/")
+    };
+
+    let raw = format!("http://{host}:{port}{path}");
+    let url = tauri::Url::parse(&raw).map_err(|e| format!("WebUI 地址无效 ({raw}): {e}"))?;
+    if url.host_str().is_none() || url.query().is_some() || url.fragment().is_some() {
+        return Err(format!(
+            "WebUI 地址无效 ({raw})，请检查 runtime.env 中的 UPDATER_HOST 与 PTNEXUS_BASE_PATH"
+        ));
     }
+    Ok(url)
 }
 
-fn wait_for_process_running(
-    process_name: &str,
-    child: &mut Child,
-    timeout: Duration,
-    logs_dir: &Path,
-) -> Result<(), String> {
-    let begin = Instant::now();
-    let stdout_log = logs_dir.join(format!("{process_name}.stdout.log"));
-    let stderr_log = logs_dir.join(format!("{process_name}.stderr.log"));
+fn env_port(envs: &HashMap<String, String>, key: &str, default: u16) -> Result<u16, String> {
+    match envs.get(key).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        None => Ok(default),
+        Some(value) => value
+            .parse::<u16>()
+            .ok()
+            .filter(|port| *port != 0)
+            .ok_or_else(|| format!("runtime.env 中 {key}={value} 不是有效端口")),
+    }
+}
+
+fn env_secs(
+    envs: &HashMap<String, String>,
+    key: &str,
+    default: Duration,
+) -> Result<Duration, String> {
+    match envs.get(key).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        None => Ok(default),
+        Some(value) => value
+            .parse::<u64>()
+            .ok()
+            .filter(|secs| *secs != 0)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("runtime.env 中 {key}={value} 不是有效的秒数")),
+    }
+}
+
+/// 是否为运行时页面（同源且位于 base path 下）。
+pub fn is_runtime_url(app: &AppHandle, url: &tauri::Url) -> bool {
+    let runtime = runtime_url(app);
+    url.origin() == runtime.origin() && url.path().starts_with(runtime.path())
+}
+
+/// 运行时 WebUI 所在端口是否可连接。
+pub fn is_runtime_reachable(app: &AppHandle) -> bool {
+    let url = runtime_url(app);
+    let port = url.port_or_known_default().unwrap_or(UPDATER_PORT);
+    match url.host_str() {
+        Some("127.0.0.1") | Some("localhost") | None => is_port_open(port),
+        Some(host) => std::net::ToSocketAddrs::to_socket_addrs(&(host, port))
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .map(|addr| std::net::TcpStream::connect_timeout(&addr, Duration::from_secs(1)).is_ok())
+            .unwrap_or(false),
+    }
+}
+
+/// 请求一次 WebUI 首页：返回 200 且内容为 HTML 才算可以打开。
+pub fn probe_runtime_page(url: &tauri::Url) -> Result<(), String> {
+    let host = url
+        .host_str()
+        .unwrap_or("127.0.0.1")
+        .trim_matches(['[', ']']);
+    let port = url.port_or_known_default().unwrap_or(UPDATER_PORT);
+    let response = localhttp::fetch_page(host, port, url.path(), PAGE_PROBE_TIMEOUT)?;
+    if response.status != 200 {
+        return Err(format!("{url} 返回 {}", response.status_line));
+    }
+    if !response.content_type.starts_with("text/html") {
+        return Err(format!("{url} 返回的不是网页（{}）", response.content_type));
+    }
+    Ok(())
+}
 
+/// 按退避间隔重试 [`probe_runtime_page`]，最多等待 PAGE_READY_TIMEOUT，超时返回最后一次的原因。
+fn wait_for_runtime_page(url: &tauri::Url) -> Result<(), String> {
+    let started = Instant::now();
+    let mut backoff = services::Backoff::new(PAGE_RETRY_MIN, PAGE_RETRY_MAX);
     loop {
-        match child.try_wait() {
-            Ok(Some(status)) => {
-                let stderr_tail = read_log_tail(&stderr_log, 40);
-                if stderr_tail.is_empty() {
-                    return Err(format!(
-                        "进程 {process_name} 已退出（状态: {status}），未能保持运行。
-请查看日志：{}",
-                        stderr_log.display()
-                    ));
-                }
-                return Err(format!(
-                    "进程 {process_name} 已退出（状态: {status}），未能保持运行。
-日志：{}
-
-最近 stderr 输出：
-{stderr_tail}",
-                    stderr_log.display()
-                ));
-            }
-            Ok(None) => {
-                if begin.elapsed() >= timeout {
-                    return Ok(());
-                }
-            }
-            Err(e) => {
-                return Err(format!(
-                    "检查进程 {process_name} 运行状态失败: {e}。
-请查看日志：{}, {}",
-                    stdout_log.display(),
-                    stderr_log.display()
-                ));
-            }
+        let err = match probe_runtime_page(url) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        let remaining = PAGE_READY_TIMEOUT.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            return Err(err);
         }
+        thread::sleep(backoff.next_interval().min(remaining));
+    }
+}
 
-        thread::sleep(Duration::from_millis(250));
+/// 桌面壳自带静态页（frontendDist）的访问地址。
+/// 停止全部服务前把主窗口切换到桌面壳自带的启动页，期间的进度通过 `bootstrap-stage` 事件显示；
+/// 服务重新启动后由启动流程导航回运行时页面。
+pub fn show_startup_page(app: &AppHandle) {
+    if let (Some(window), Some(url)) =
+        (app.get_webview_window("main"), local_page_url("index.html"))
+    {
+        let _ = window.navigate(url);
     }
 }
 
-fn read_log_tail(path: &Path, max_lines: usize) -> String {
-    let Ok(content) = fs::read_to_string(path) else {
-        return String::new();
+pub fn local_page_url(page: &str) -> Option<tauri::Url> {
+    let base = if cfg!(target_os = "windows") {
+        "http://tauri.localhost/"
+    } else {
+        "tauri://localhost/"
     };
+    tauri::Url::parse(base).ok()?.join(page).ok()
+}
 
-    let mut lines: Vec<&str> = content.lines().rev().take(max_lines).collect();
-    lines.reverse();
-    lines.join("\n")
+pub fn is_port_open(port: u16) -> bool {
+    std::net::TcpStream::connect_timeout(
+        &std::net::SocketAddr::from(([127, 0, 0, 1], port)),
+        Duration::from_secs(1),
+    )
+    .is_ok()
+}
+
+/// 运行时页面加载后需要注入的全部脚本。
+fn inject_runtime_hooks(window: &WebviewWindow, runtime_url: &tauri::Url) {
+    if safemode::is_active(window.app_handle()) {
+        return;
+    }
+    // WebUI 版本比脚本核对时的新，依赖页面结构的注入就不再执行（见 injections.rs）
+    let active = injections::active(window.app_handle());
+    inject_external_link_interceptor(window, runtime_url);
+    publish_active_injections(window, &active);
+    if active.contains(&injections::STARTUP_OVERLAY.id) {
+        inject_startup_overlay(window);
+        renderwatch::arm(window);
+    }
+    if active.contains(&injections::DB_CONFIG_BUTTON.id) {
+        inject_db_config_button(window);
+    }
+    // 已订阅平台的渲染进程退出通知时不需要心跳（见 watchdog.rs）
+    if watchdog::uses_heartbeat(window.app_handle()) {
+        inject_webview_heartbeat(window);
+    }
+}
+
+/// 按当前配置档的数据目录定位 logs/shell.log 并追加一行。
+pub fn shell_log(app: &AppHandle, message: &str) {
+    let Ok(data_dir) = datadir::active_profile(app).map(|profile| profile.root) else {
+        return;
+    };
+    let logs_dir = data_dir.join("logs");
+    let _ = fs::create_dir_all(&logs_dir);
+    append_shell_log(&logs_dir, message);
 }
 
 /// 在新页面加载完成后注入外部链接拦截 JS。
-/// 因为 window.location.replace 会销毁当前页面上下文，所以需要等待新页面加载完成后再注入。
-fn inject_external_link_interceptor(window: &WebviewWindow) {
-    let window = window.clone();
-    thread::spawn(move || {
-        // 等待新页面加载完成（SPA 首次渲染通常需要几秒）
-        thread::sleep(Duration::from_secs(3));
-        let _ = window.eval(EXTERNAL_LINK_INTERCEPT_JS);
-    });
+fn inject_external_link_interceptor(window: &WebviewWindow, runtime_url: &tauri::Url) {
+    let configured: Vec<String> = ["SERVER_HOST", "UPDATER_HOST"]
+        .iter()
+        .filter_map(|key| read_runtime_setting(window.app_handle(), key))
+        .collect();
+    let script = external_link_script(runtime_url, &configured);
+    // 等待 SPA 首次渲染（通常需要几秒）
+    eval_after(
+        window,
+        PageScript::ExternalLinks.name(),
+        Duration::from_secs(3),
+        vec![script],
+    );
 }
 
 fn inject_db_config_button(window: &WebviewWindow) {
-    let window = window.clone();
-    thread::spawn(move || {
-        thread::sleep(Duration::from_secs(4));
-        let _ = window.eval(INJECT_DB_CONFIG_BUTTON_JS);
-    });
+    let config =
+        ScriptConfig::new(PageScript::DbConfigButton).version_gate(&injections::DB_CONFIG_BUTTON);
+    eval_after(
+        window,
+        PageScript::DbConfigButton.name(),
+        Duration::from_secs(4),
+        vec![pagescripts::render_script(
+            PageScript::DbConfigButton,
+            &config,
+        )],
+    );
 }
 
 fn inject_startup_overlay(window: &WebviewWindow) {
+    let config = ScriptConfig::new(PageScript::StartupOverlay)
+        .version_gate(&injections::STARTUP_OVERLAY)
+        .feature(
+            "renderTimeoutSeconds",
+            renderwatch::RENDER_TIMEOUT.as_secs(),
+        );
+    // 导航到业务页后立即尝试注入；若尚未就绪，脚本内部会自行重试。
+    eval_after(
+        window,
+        PageScript::StartupOverlay.name(),
+        Duration::from_millis(600),
+        vec![pagescripts::render_script(
+            PageScript::StartupOverlay,
+            &config,
+        )],
+    );
+}
+
+/// 导航到业务页后更新 `window.__PTNEXUS_DESKTOP__.injections`。
+fn publish_active_injections(window: &WebviewWindow, active: &[&str]) {
+    let script = injections::publish_script(active);
+    eval_after(
+        window,
+        "active-injections",
+        Duration::from_millis(600),
+        vec![script],
+    );
+}
+
+fn inject_webview_heartbeat(window: &WebviewWindow) {
+    let interval = watchdog::HEARTBEAT_INTERVAL.as_millis() as u64;
+    let config = ScriptConfig::new(PageScript::WebviewHeartbeat).feature("intervalMs", interval);
+    eval_after(
+        window,
+        PageScript::WebviewHeartbeat.name(),
+        Duration::from_secs(3),
+        vec![pagescripts::render_script(
+            PageScript::WebviewHeartbeat,
+            &config,
+        )],
+    );
+}
+
+/// 延时后依次执行脚本。作为运行时任务登记：页面再次加载时取代上一次尚未执行的同名注入，重启服务时取消。
+fn eval_after(window: &WebviewWindow, name: &str, delay: Duration, scripts: Vec<String>) {
     let window = window.clone();
-    thread::spawn(move || {
-        // 导航到业务页后立即尝试注入；若尚未就绪，脚本内部会自行重试。
-        thread::sleep(Duration::from_millis(600));
-        let _ = window.eval(STARTUP_OVERLAY_JS);
-    });
+    let app = window.app_handle().clone();
+    tasks::spawn(
+        &app,
+        &format!("inject:{name}"),
+        Scope::Runtime,
+        move |_, token| {
+            if token.sleep(delay) {
+                for script in &scripts {
+                    let _ = window.eval(script);
+                }
+            }
+        },
+    );
+}
+
+fn file_exists(path: &Path) -> bool {
+    paths::extended(path).exists()
+}
+
+/// 让下次启动重新解压内置 Python 运行时；没有压缩包（原地布局）时返回错误。
+pub fn invalidate_python_runtime(app: &AppHandle) -> Result<(), String> {
+    let runtime_root = resolve_runtime_root(app)?;
+    let data_dir = datadir::active_profile(app)?.root;
+    if pyruntime::invalidate_extracted(&runtime_root, &data_dir)? {
+        append_shell_log(
+            &data_dir.join("logs"),
+            "[INFO] 已标记 Python 运行时需要重新解压",
+        );
+        Ok(())
+    } else {
+        Err(
+            "当前安装直接使用 server/python，无法单独重新安装 Python 运行时，请重新安装应用"
+                .to_string(),
+        )
+    }
+}
+
+/// 启动自检使用：不解压 Python 运行时，检查当前能确定的必需文件。
+pub fn check_runtime_files(app: &AppHandle) -> Result<(), BootstrapError> {
+    let runtime_root = resolve_runtime_root(app)?;
+    let data_dir = datadir::active_profile(app)?.root;
+    let python_home =
+        pyruntime::installed_python_home(&runtime_root, &runtime_root.join("server"), &data_dir);
+    check_required_files(&runtime_root, python_home.as_deref())
 }
 
-fn ensure_exists(path: &Path) -> Result<(), String> {
-    if path.exists() {
+pub(crate) fn check_required_files(
+    runtime_root: &Path,
+    python_home: Option<&Path>,
+) -> Result<(), BootstrapError> {
+    let missing = missing_runtime_files(runtime_root, python_home);
+    if missing.is_empty() {
         return Ok(());
     }
-    Err(format!("缺少运行文件: {}", path.display()))
+    Err(BootstrapError::MissingFiles {
+        runtime_root: runtime_root.to_path_buf(),
+        missing,
+    })
+}
+
+/// 一次列出启动所需而缺失的全部文件，免得补齐一个、重启后才发现下一个。
+/// `python_home` 为 None 表示 Python 运行时尚未从压缩包解压，不检查解释器。
+fn missing_runtime_files(runtime_root: &Path, python_home: Option<&Path>) -> Vec<MissingFile> {
+    let server_dir = runtime_root.join("server");
+    let mut missing = Vec::new();
+    let mut require = |component: &'static str, path: PathBuf, alternative: Option<PathBuf>| {
+        if !file_exists(&path) {
+            missing.push(MissingFile {
+                component,
+                path,
+                alternative,
+            });
+        }
+    };
+
+    require(
+        "updater",
+        runtime_root.join("updater").join(exe_name("updater")),
+        None,
+    );
+    require(
+        "batch",
+        runtime_root.join("batch").join(exe_name("batch")),
+        None,
+    );
+    require("server", server_dir.join("dist").join("index.html"), None);
+    require(
+        "server",
+        server_dir.join("configs").join("global_mappings.yaml"),
+        None,
+    );
+    require("server", server_dir.join("sites_data.json"), None);
+
+    // Python 服务可以是打包的可执行文件，也可以是解释器加入口脚本（见 resolve_*_launcher）
+    let mut needs_python = false;
+    for (component, entry) in [
+        ("background_runner", "background_runner.py"),
+        ("server", "app.py"),
+    ] {
+        let packaged = server_dir.join(exe_name(component));
+        if file_exists(&packaged) {
+            continue;
+        }
+        needs_python = true;
+        require(component, server_dir.join(entry), Some(packaged));
+    }
+    if let (true, Some(python_home)) = (needs_python, python_home) {
+        require("python", python_home.join(exe_name("python")), None);
+    }
+    missing
 }
 
-fn exe_name(name: &str) -> String {
+pub(crate) fn exe_name(name: &str) -> String {
     if cfg!(target_os = "windows") {
         format!("{name}.exe")
     } else {
         name.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+    use crate::script;
+
+    /// 按 bootstrap 的顺序构建环境：默认值 → runtime.env → 推导跨服务地址。
+    fn env_with_file(name: &str, content: &str) -> (HashMap<String, String>, Vec<String>) {
+        let dir = temp_dir(name);
+        let env_file = dir.join("runtime.env");
+        fs::write(&env_file, content).unwrap();
+
+        let mut envs = build_runtime_env(
+            &ProfilePaths::for_root(&dir),
+            &dir.join("server"),
+            &dir.join("CHANGELOG.json"),
+        );
+        merge_env_file(&mut envs, &env_file).unwrap();
+        let warnings = apply_derived_service_urls(&mut envs);
+
+        let _ = fs::remove_dir_all(&dir);
+        (envs, warnings)
+    }
+
+    #[test]
+    fn profiles_get_disjoint_runtime_paths() {
+        let base = temp_dir("profiles");
+        let server_dir = base.join("server");
+        let changelog = base.join("CHANGELOG.json");
+        let profile_env = |name: &str| {
+            build_runtime_env(
+                &ProfilePaths::for_root(&base.join(name)),
+                &server_dir,
+                &changelog,
+            )
+        };
+        let (home, work) = (profile_env("home"), profile_env("work"));
+
+        let keys = [
+            "PTNEXUS_DATA_DIR",
+            "TEMP_DIR",
+            "CONFIG_FILE",
+            "UPDATE_DIR",
+            "REPO_DIR",
+        ];
+        for key in keys {
+            assert!(
+                PathBuf::from(&home[key]).starts_with(base.join("home")),
+                "{key}"
+            );
+        }
+        for ours in keys.map(|key| PathBuf::from(&home[key])) {
+            for theirs in keys.map(|key| PathBuf::from(&work[key])) {
+                assert!(!ours.starts_with(&theirs) && !theirs.starts_with(&ours));
+            }
+        }
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn derived_urls_follow_default_ports() {
+        let (envs, warnings) = env_with_file("defaults", "");
+        assert_eq!(envs["GO_SERVICE_URL"], "http://127.0.0.1:5276");
+        assert_eq!(envs["CORE_API_URL"], "http://127.0.0.1:5275");
+        assert_eq!(envs["BATCH_ENHANCER_PORT"], "5276");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn derived_urls_follow_overridden_ports() {
+        let (envs, warnings) = env_with_file("ports", "BATCH_PORT=6000\nSERVER_PORT=6001\n");
+        assert_eq!(envs["GO_SERVICE_URL"], "http://127.0.0.1:6000");
+        assert_eq!(envs["CORE_API_URL"], "http://127.0.0.1:6001");
+        assert_eq!(envs["BATCH_ENHANCER_PORT"], "6000");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn wildcard_server_host_connects_via_loopback() {
+        let (envs, _) = env_with_file("wildcard", "SERVER_HOST=0.0.0.0\n");
+        assert_eq!(envs["CORE_API_URL"], "http://127.0.0.1:5275");
+    }
+
+    #[test]
+    fn explicit_urls_are_kept_when_consistent() {
+        let (envs, warnings) = env_with_file(
+            "explicit-ok",
+            "BATCH_PORT=6000\nGO_SERVICE_URL=http://localhost:6000/api\n",
+        );
+        assert_eq!(envs["GO_SERVICE_URL"], "http://localhost:6000/api");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn explicit_urls_warn_on_port_mismatch() {
+        let (envs, warnings) = env_with_file(
+            "explicit-mismatch",
+            "BATCH_PORT=6000\nGO_SERVICE_URL=http://127.0.0.1:5276\n",
+        );
+        assert_eq!(envs["GO_SERVICE_URL"], "http://127.0.0.1:5276");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("GO_SERVICE_URL"));
+    }
+
+    #[test]
+    fn explicit_urls_warn_on_host_mismatch() {
+        let (_, warnings) = env_with_file(
+            "explicit-host",
+            "SERVER_HOST=192.168.1.10\nCORE_API_URL=http://10.0.0.2:5275\nGO_SERVICE_URL=http://192.168.1.10:5276\n",
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("CORE_API_URL") && warnings[0].contains("10.0.0.2"));
+
+        let (_, warnings) = env_with_file(
+            "explicit-host-remote",
+            "CORE_API_URL=http://nas.local:5275\n",
+        );
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn localhost_and_loopback_are_the_same_host() {
+        let (_, warnings) = env_with_file(
+            "explicit-loopback",
+            "SERVER_HOST=localhost\nCORE_API_URL=http://127.0.0.1:5275\nGO_SERVICE_URL=http://LOCALHOST:5276\n",
+        );
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert!(same_host("[::1]", "localhost"));
+        assert!(!same_host("127.0.0.1", "192.168.1.10"));
+    }
+
+    #[test]
+    fn explicit_urls_warn_when_unparseable() {
+        let (_, warnings) = env_with_file("explicit-bad", "CORE_API_URL='127.0.0.1:5275'\n");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("CORE_API_URL"));
+    }
+
+    #[test]
+    fn merge_env_file_strips_quotes_and_comments() {
+        let (envs, _) = env_with_file(
+            "quotes",
+            "# comment\n\nDB_TYPE=\"mysql\"\nMYSQL_PASSWORD='p=ss'\n",
+        );
+        assert_eq!(envs["DB_TYPE"], "mysql");
+        assert_eq!(envs["MYSQL_PASSWORD"], "p=ss");
+    }
+
+    #[test]
+    fn merge_env_file_rejects_malformed_lines() {
+        let dir = temp_dir("malformed");
+        let env_file = dir.join("runtime.env");
+        fs::write(&env_file, "DB_TYPE=sqlite\nnot a pair\n").unwrap();
+
+        let mut envs = HashMap::new();
+        let err = merge_env_file(&mut envs, &env_file).unwrap_err();
+        assert!(err.contains("第 2 行"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn env_passthrough_copies_listed_host_vars() {
+        let host = HashMap::from([
+            ("REQUESTS_CA_BUNDLE", "/etc/ssl/corp.pem"),
+            ("FOO_TOKEN", "abc"),
+            ("EMPTY", ""),
+        ]);
+        let lookup = |key: &str| {
+            host.get(key)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+        };
+
+        let mut envs = HashMap::new();
+        let result = apply_env_passthrough(
+            &mut envs,
+            " REQUESTS_CA_BUNDLE, FOO_TOKEN ,SSL_CERT_FILE,EMPTY,A=B,BAD KEY,,",
+            lookup,
+        );
+        assert_eq!(result.found, vec!["REQUESTS_CA_BUNDLE", "FOO_TOKEN"]);
+        assert_eq!(result.missing, vec!["SSL_CERT_FILE", "EMPTY"]);
+        assert_eq!(result.invalid, vec!["A=B", "BAD KEY"]);
+        assert_eq!(envs["REQUESTS_CA_BUNDLE"], "/etc/ssl/corp.pem");
+        assert_eq!(envs.len(), 2);
+    }
+
+    #[test]
+    fn parse_http_host_port_handles_common_shapes() {
+        assert_eq!(
+            parse_http_host_port("http://127.0.0.1:5275"),
+            Some(("127.0.0.1".into(), 5275))
+        );
+        assert_eq!(
+            parse_http_host_port("https://example.com/x"),
+            Some(("example.com".into(), 443))
+        );
+        assert_eq!(
+            parse_http_host_port("http://[::1]:8080/"),
+            Some(("::1".into(), 8080))
+        );
+        assert_eq!(parse_http_host_port("127.0.0.1:5275"), None);
+        assert_eq!(parse_http_host_port("http://host:notaport"), None);
+    }
+
+    #[test]
+    fn runtime_url_defaults_to_loopback_updater() {
+        let envs = HashMap::new();
+        let url = runtime_url_from_env(&envs).unwrap();
+        assert_eq!(url.as_str(), "http://127.0.0.1:5274/");
+    }
+
+    #[test]
+    fn runtime_url_follows_port_host_and_base_path() {
+        let mut envs = HashMap::new();
+        envs.insert("UPDATER_PORT".to_string(), "6000".to_string());
+        envs.insert("UPDATER_HOST".to_string(), "0.0.0.0".to_string());
+        envs.insert("PTNEXUS_BASE_PATH".to_string(), "/ptnexus/".to_string());
+        let url = runtime_url_from_env(&envs).unwrap();
+        assert_eq!(url.as_str(), "http://127.0.0.1:6000/ptnexus/");
+    }
+
+    #[test]
+    fn link_interceptor_allows_loopback_and_configured_hosts() {
+        let hosts = |url: &str, configured: &[&str]| {
+            let url = tauri::Url::parse(url).unwrap();
+            let configured: Vec<String> = configured.iter().map(|h| h.to_string()).collect();
+            let script = external_link_script(&url, &configured);
+            let hosts = internal_hosts(&url, &configured);
+            assert!(script.contains(&script::js_literal(&hosts)));
+            hosts
+        };
+
+        assert_eq!(
+            hosts("http://127.0.0.1:5274/", &[]),
+            ["127.*", "localhost", "[::1]"]
+        );
+        // 通配监听地址与回环地址不重复加入
+        assert_eq!(
+            hosts("http://127.0.0.2:5274/", &["0.0.0.0", "::", "127.0.0.1"]),
+            ["127.*", "localhost", "[::1]"]
+        );
+        assert_eq!(
+            hosts("http://[::1]:5274/", &["0:0:0:0:0:0:0:1", "[fe80::1]"]),
+            ["127.*", "localhost", "[::1]", "[fe80::1]"]
+        );
+        assert_eq!(
+            hosts(
+                "http://PTNexus.local:5274/",
+                &["ptnexus.local", "192.168.1.20", "nas.lan."]
+            ),
+            [
+                "127.*",
+                "localhost",
+                "[::1]",
+                "ptnexus.local",
+                "192.168.1.20",
+                "nas.lan"
+            ]
+        );
+        assert!(
+            external_link_script(&tauri::Url::parse("http://[::1]:5274/").unwrap(), &[])
+                .contains(r#""http://[::1]:5274""#)
+        );
+    }
+
+    #[test]
+    fn runtime_url_rejects_invalid_values() {
+        let mut envs = HashMap::new();
+        envs.insert("UPDATER_PORT".to_string(), "70000".to_string());
+        assert!(runtime_url_from_env(&envs).is_err());
+
+        let mut envs = HashMap::new();
+        envs.insert("PTNEXUS_BASE_PATH".to_string(), "a?b='x'".to_string());
+        assert!(runtime_url_from_env(&envs).is_err());
+    }
+
+    #[test]
+    fn runtime_page_waits_for_html_home_page() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let responses = [
+                "HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\nContent-Length: 0\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}",
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: 6\r\n\r\n<html>",
+            ];
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let url = tauri::Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap();
+        let err = probe_runtime_page(&url).unwrap_err();
+        assert!(err.contains("404"), "{err}");
+        // JSON 响应不算首页就绪，继续重试直到拿到网页
+        assert_eq!(wait_for_runtime_page(&url), Ok(()));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn timezone_is_only_passed_as_tz_outside_windows() {
+        let detected = || HashMap::from([("PTNEXUS_TZ".to_string(), "Asia/Shanghai".to_string())]);
+
+        let mut envs = detected();
+        assert_eq!(align_timezone(&mut envs, false), None);
+        assert_eq!(envs.get("TZ").map(String::as_str), Some("Asia/Shanghai"));
+
+        let mut envs = detected();
+        assert_eq!(align_timezone(&mut envs, true), None);
+        assert_eq!(envs.get("TZ"), None);
+        assert_eq!(
+            envs.get("PTNEXUS_TZ").map(String::as_str),
+            Some("Asia/Shanghai")
+        );
+
+        // runtime.env 覆盖的 IANA 时区在 Windows 上改为 PTNEXUS_TZ
+        let mut envs = detected();
+        envs.insert("TZ".to_string(), "Europe/Berlin".to_string());
+        assert!(align_timezone(&mut envs, true).is_some());
+        assert_eq!(envs.get("TZ"), None);
+        assert_eq!(
+            envs.get("PTNEXUS_TZ").map(String::as_str),
+            Some("Europe/Berlin")
+        );
+
+        // POSIX 形式的 TZ 原样保留
+        let mut envs = detected();
+        envs.insert("TZ".to_string(), "CST-8".to_string());
+        assert_eq!(align_timezone(&mut envs, true), None);
+        assert_eq!(envs.get("TZ").map(String::as_str), Some("CST-8"));
+        assert_eq!(
+            envs.get("PTNEXUS_TZ").map(String::as_str),
+            Some("Asia/Shanghai")
+        );
+    }
+
+    #[test]
+    fn posix_locale_converts_bcp47_tags() {
+        assert_eq!(posix_locale("zh-CN").as_deref(), Some("zh_CN.UTF-8"));
+        assert_eq!(posix_locale("zh-Hans-CN").as_deref(), Some("zh_CN.UTF-8"));
+        assert_eq!(posix_locale("en").as_deref(), Some("en.UTF-8"));
+        assert_eq!(posix_locale(""), None);
+    }
+
+    #[test]
+    fn bootstrap_paths_survive_non_ascii_and_spaces() {
+        let base = temp_dir("unicode")
+            .join("张三 的 文档")
+            .join("PT Nexus 运行时");
+        let root = base.join("runtime");
+        let server_dir = root.join("server");
+        let python_home = root.join("python 3");
+        let data_dir = base.join("AppData 数据");
+        for dir in [
+            root.join("updater"),
+            root.join("batch"),
+            server_dir.join("dist"),
+            python_home.clone(),
+            data_dir.clone(),
+        ] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        for file in [
+            root.join("updater").join(exe_name("updater")),
+            root.join("batch").join(exe_name("batch")),
+            server_dir.join("dist").join("index.html"),
+            server_dir.join("app.py"),
+            python_home.join(exe_name("python")),
+        ] {
+            fs::write(file, "").unwrap();
+        }
+        fs::write(data_dir.join("runtime.env"), "SITE_NOTE=\"中文 备注\"\n").unwrap();
+
+        assert!(is_runtime_root(&root));
+        assert!(file_exists(&server_dir.join("app.py")));
+
+        let (exe, args, cwd) = resolve_server_launcher(&server_dir, &python_home).unwrap();
+        assert_eq!(exe, python_home.join(exe_name("python")));
+        assert_eq!(PathBuf::from(&args[1]), server_dir.join("app.py"));
+        assert_eq!(cwd, server_dir);
+
+        let (envs, _, _) = assemble_common_env(
+            &ProfilePaths::for_root(&data_dir),
+            &server_dir,
+            &root.join("CHANGELOG.json"),
+        )
+        .unwrap();
+        assert_eq!(PathBuf::from(&envs["PYTHONPATH"]), server_dir);
+        assert_eq!(PathBuf::from(&envs["PTNEXUS_DATA_DIR"]), data_dir);
+        assert_eq!(envs["PYTHONUTF8"], "1");
+        assert_eq!(envs["SITE_NOTE"], "中文 备注");
+
+        let _ = fs::remove_dir_all(temp_dir("unicode"));
+    }
+
+    #[test]
+    fn missing_runtime_files_are_listed_together() {
+        let root = temp_dir("missing-files");
+        let server_dir = root.join("server");
+        let python_home = server_dir.join("python");
+        let files = [
+            root.join("updater").join(exe_name("updater")),
+            root.join("batch").join(exe_name("batch")),
+            server_dir.join("dist").join("index.html"),
+            server_dir.join("configs").join("global_mappings.yaml"),
+            server_dir.join("sites_data.json"),
+            server_dir.join("background_runner.py"),
+            server_dir.join("app.py"),
+            python_home.join(exe_name("python")),
+        ];
+        for file in &files {
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, "").unwrap();
+        }
+        assert!(check_required_files(&root, Some(&python_home)).is_ok());
+
+        for file in [&files[0], &files[2], &files[4], &files[6], &files[7]] {
+            fs::remove_file(file).unwrap();
+        }
+        let missing = missing_runtime_files(&root, Some(&python_home));
+        let listed: Vec<(&str, &Path)> = missing
+            .iter()
+            .map(|m| (m.component, m.path.as_path()))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("updater", files[0].as_path()),
+                ("server", files[2].as_path()),
+                ("server", files[4].as_path()),
+                ("server", files[6].as_path()),
+                ("python", files[7].as_path()),
+            ]
+        );
+        assert_eq!(
+            missing[3].alternative,
+            Some(server_dir.join(exe_name("server")))
+        );
+
+        let message = check_required_files(&root, Some(&python_home))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(message.matches(&*root.to_string_lossy()).count(), 1);
+        assert!(message.starts_with("运行目录不完整，缺少 5 个文件"));
+        assert_eq!(message.matches("server：").count(), 1);
+        assert!(message.contains(&format!(
+            "  - {}\n",
+            Path::new("server")
+                .join("dist")
+                .join("index.html")
+                .display()
+        )));
+
+        // 打包的可执行文件可以代替入口脚本与解释器；Python 尚未解压时不检查解释器
+        fs::write(server_dir.join(exe_name("server")), "").unwrap();
+        let missing = missing_runtime_files(&root, None);
+        let components: Vec<&str> = missing.iter().map(|m| m.component).collect();
+        assert_eq!(components, ["updater", "server", "server"]);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn file_check_handles_paths_beyond_max_path() {
+        let mut deep = temp_dir("long-path");
+        while deep.to_string_lossy().len() <= paths::MAX_PATH + 20 {
+            deep = deep.join("深层目录 segment");
+        }
+        fs::create_dir_all(&deep).unwrap();
+        let file = deep.join("index.html");
+        fs::write(&file, "").unwrap();
+
+        assert!(file_exists(&file));
+        assert!(!file_exists(&deep.join("missing.html")));
+
+        let _ = fs::remove_dir_all(temp_dir("long-path"));
+    }
+
+    #[test]
+    fn format_utc_timestamp_renders_known_instant() {
+        let instant = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(format_utc_timestamp(instant), "2023-11-14T22:13:20Z");
+    }
+}
@@ -0,0 +1,204 @@
+//! 安全模式：不注入任何脚本、不读桌面设置、不运行非必需功能，用于判断问题是否出在这些地方。
+//!
+//! 以下任一条件满足时进入安全模式：启动参数 `--safe-mode`；启动时按住 Shift（Windows 与 macOS 在 setup
+//! 开始时读取键盘状态，Linux 上没有窗口时读不到，只能用启动参数）；连续两次启动未完成。桌面壳没有自己的
+//! 崩溃日志，启动是否完成记在应用数据目录的 `startup-pending` 中：每次启动时计数加一，服务启动完成（或
+//! 失败并已提示）、按需启动进入休眠、正常退出时删除，计数达到 2 说明前两次启动都在中途崩溃。
+//!
+//! 安全模式下主窗口的初始化脚本与运行时页面的注入都跳过；desktop-settings.json 既不读取也不写入，使用
+//! 默认设置，修改设置会被拒绝；托盘统计、时钟检查、定时导出、Webhook 与首次运行引导不启动。进入时写入
+//! shell.log 与运行记录，`safe-mode` 事件与 `shell_status` 的 `safe_mode` 供界面显示提示横幅，诊断包
+//! summary.json 的 `status` 中也会带上。退出安全模式就是去掉启动参数正常重启（托盘菜单或 `exit_safe_mode`）。
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::SystemTime;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::runtime;
+
+pub const SAFE_MODE_FLAG: &str = "--safe-mode";
+pub const EXIT_MENU_ID: &str = "exit_safe_mode";
+const STARTUP_MARKER: &str = "startup-pending";
+/// 连续多少次启动未完成后自动进入安全模式。
+const UNFINISHED_STARTUPS_LIMIT: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    Flag,
+    ShiftKey,
+    StartupCrashes,
+}
+
+impl Reason {
+    fn describe(self) -> &'static str {
+        match self {
+            Reason::Flag => "启动参数 --safe-mode",
+            Reason::ShiftKey => "启动时按住了 Shift",
+            Reason::StartupCrashes => "连续两次启动未完成",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SafeModeStatus {
+    pub reason: Reason,
+    /// 本次启动前连续未完成的启动次数。
+    pub unfinished_startups: u32,
+    pub activated_at: String,
+}
+
+/// 本次运行是否处于安全模式；启动时确定，运行期间不变。
+pub struct SafeMode(Option<SafeModeStatus>);
+
+pub fn forced_by_flag() -> bool {
+    std::env::args().any(|arg| arg == SAFE_MODE_FLAG)
+}
+
+/// 在 setup 最开始调用：记一次启动并决定是否进入安全模式，须在读取桌面设置之前。
+pub fn init(app: &tauri::App) {
+    let unfinished = app
+        .path()
+        .app_data_dir()
+        .map(|dir| begin_startup(&dir))
+        .unwrap_or(0);
+    let status = decide(forced_by_flag(), shift_held(), unfinished).map(|reason| SafeModeStatus {
+        reason,
+        unfinished_startups: unfinished,
+        activated_at: runtime::format_utc_timestamp(SystemTime::now()),
+    });
+    app.manage(SafeMode(status));
+}
+
+fn decide(flag: bool, shift: bool, unfinished: u32) -> Option<Reason> {
+    if flag {
+        Some(Reason::Flag)
+    } else if shift {
+        Some(Reason::ShiftKey)
+    } else if unfinished >= UNFINISHED_STARTUPS_LIMIT {
+        Some(Reason::StartupCrashes)
+    } else {
+        None
+    }
+}
+
+pub fn status(app: &AppHandle) -> Option<SafeModeStatus> {
+    app.try_state::<SafeMode>()
+        .and_then(|state| state.0.clone())
+}
+
+pub fn is_active(app: &AppHandle) -> bool {
+    status(app).is_some()
+}
+
+/// 运行记录打开后调用：记录进入安全模式并通知界面。
+pub fn announce(app: &AppHandle) {
+    let Some(status) = status(app) else {
+        return;
+    };
+    let message = format!(
+        "已进入安全模式（{}），跳过注入脚本与桌面设置，正常重启即可退出",
+        status.reason.describe()
+    );
+    runtime::shell_log(app, &format!("[WARN] {message}"));
+    journal::record(app, Severity::Warn, None, message);
+    events::emit(app, "safe-mode", Replay::Latest, &status);
+}
+
+/// 本次启动已完成（或已正常结束），清除未完成标记。
+pub fn finish_startup(app: &AppHandle) {
+    if let Ok(dir) = app.path().app_data_dir() {
+        let _ = fs::remove_file(dir.join(STARTUP_MARKER));
+    }
+}
+
+/// 读出之前未完成的启动次数并加一写回；文件损坏时按一次计。
+fn begin_startup(data_dir: &Path) -> u32 {
+    let path = data_dir.join(STARTUP_MARKER);
+    let unfinished: u32 = match fs::read_to_string(&path) {
+        Ok(content) => content.trim().parse().unwrap_or(1),
+        Err(_) => 0,
+    };
+    let _ = fs::create_dir_all(data_dir);
+    let _ = fs::write(&path, unfinished.saturating_add(1).to_string());
+    unfinished
+}
+
+/// 去掉 `--safe-mode` 重新启动桌面壳；调用前须先停止服务，成功后由调用方退出当前进程。
+pub fn relaunch_normally() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("无法确定程序路径: {e}"))?;
+    Command::new(exe)
+        .args(normal_args(std::env::args().skip(1)))
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("重新启动失败: {e}"))
+}
+
+fn normal_args(args: impl Iterator<Item = String>) -> Vec<String> {
+    args.filter(|arg| arg != SAFE_MODE_FLAG).collect()
+}
+
+/// Shift 当前是否按下。
+#[cfg(target_os = "windows")]
+fn shift_held() -> bool {
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_SHIFT};
+
+    // 最高位表示按键当前处于按下状态
+    unsafe { GetAsyncKeyState(i32::from(VK_SHIFT.0)) < 0 }
+}
+
+#[cfg(target_os = "macos")]
+fn shift_held() -> bool {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceFlagsState(state_id: i32) -> u64;
+    }
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const FLAG_MASK_SHIFT: u64 = 0x0002_0000;
+
+    unsafe { CGEventSourceFlagsState(COMBINED_SESSION_STATE) & FLAG_MASK_SHIFT != 0 }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn shift_held() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn two_unfinished_startups_trigger_safe_mode() {
+        let dir = temp_dir("count");
+        assert_eq!(begin_startup(&dir), 0);
+        assert_eq!(begin_startup(&dir), 1);
+        assert_eq!(decide(false, false, 1), None);
+        let unfinished = begin_startup(&dir);
+        assert_eq!(
+            decide(false, false, unfinished),
+            Some(Reason::StartupCrashes)
+        );
+
+        fs::remove_file(dir.join(STARTUP_MARKER)).unwrap();
+        assert_eq!(begin_startup(&dir), 0);
+        fs::write(dir.join(STARTUP_MARKER), "garbage").unwrap();
+        assert_eq!(begin_startup(&dir), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn explicit_triggers_take_precedence() {
+        assert_eq!(decide(true, true, 5), Some(Reason::Flag));
+        assert_eq!(decide(false, true, 0), Some(Reason::ShiftKey));
+        let args = ["--service-mode", SAFE_MODE_FLAG, "x"].map(String::from);
+        assert_eq!(normal_args(args.into_iter()), ["--service-mode", "x"]);
+    }
+}
@@ -0,0 +1,117 @@
+//! 主窗口截图：保存到当前配置档的 `screenshots/` 并复制到剪贴板，供反馈问题时使用。
+//!
+//! Tauri 没有暴露 WebView 自身的截图接口，这里用 xcap 按窗口抓取系统合成后的画面。
+//! 窗口隐藏、最小化或横跨多个显示器时抓到的往往是黑图或残缺画面，直接返回错误说明。
+
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::{datadir, fsutil, runtime};
+
+/// 截图目录，位于当前配置档目录中。
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+pub fn capture_main_window(app: &AppHandle) -> Result<PathBuf, String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| "主窗口不存在（服务模式下无法截图）".to_string())?;
+    ensure_capturable(&window)?;
+
+    let image = find_native_window(&window)?
+        .capture_image()
+        .map_err(|e| format!("截取窗口失败: {e}"))?;
+    if image
+        .pixels()
+        .all(|pixel| pixel[0] == 0 && pixel[1] == 0 && pixel[2] == 0)
+    {
+        return Err(
+            "截图结果为全黑，可能是 WebView 硬件加速导致无法抓取，可尝试关闭 GPU 加速后重试"
+                .to_string(),
+        );
+    }
+
+    let dir = datadir::active_profile(app)?.root.join(SCREENSHOT_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("创建截图目录失败: {e}"))?;
+    let stamp = runtime::format_utc_timestamp(SystemTime::now()).replace(':', "-");
+    let path = dir.join(format!("ptnexus-{stamp}.png"));
+    let mut png = std::io::Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|e| format!("编码截图失败: {e}"))?;
+    fsutil::atomic_write(&path, png.into_inner())
+        .map_err(|e| format!("保存截图失败 ({}): {e}", path.display()))?;
+
+    // 剪贴板失败不影响截图文件本身
+    if let Err(err) = copy_to_clipboard(&image) {
+        runtime::shell_log(app, &format!("[WARN] 截图已保存但复制到剪贴板失败: {err}"));
+    }
+    Ok(path)
+}
+
+fn ensure_capturable(window: &WebviewWindow) -> Result<(), String> {
+    if !window.is_visible().unwrap_or(false) {
+        return Err("主窗口已隐藏到托盘，请先显示窗口再截图".to_string());
+    }
+    if window.is_minimized().unwrap_or(false) {
+        return Err("主窗口已最小化，请先还原窗口再截图".to_string());
+    }
+
+    let position = window
+        .outer_position()
+        .map_err(|e| format!("读取窗口位置失败: {e}"))?;
+    let size = window
+        .outer_size()
+        .map_err(|e| format!("读取窗口大小失败: {e}"))?;
+    let monitor = window
+        .current_monitor()
+        .ok()
+        .flatten()
+        .ok_or_else(|| "无法识别窗口所在的显示器".to_string())?;
+    let (mx, my) = (monitor.position().x, monitor.position().y);
+    let (mw, mh) = (monitor.size().width as i32, monitor.size().height as i32);
+    let inside = position.x >= mx
+        && position.y >= my
+        && position.x + size.width as i32 <= mx + mw
+        && position.y + size.height as i32 <= my + mh;
+    if !inside {
+        return Err(
+            "窗口横跨多个显示器或部分在屏幕外，请将窗口完整移到一个显示器内再截图".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// 按标题和位置在系统窗口列表中找到主窗口。
+fn find_native_window(window: &WebviewWindow) -> Result<xcap::Window, String> {
+    let title = window.title().unwrap_or_default();
+    let position = window.outer_position().ok();
+    let windows = xcap::Window::all().map_err(|e| format!("枚举系统窗口失败: {e}"))?;
+
+    let mut candidates = windows
+        .into_iter()
+        .filter(|w| w.title().is_ok_and(|t| t == title));
+    let first = candidates.next();
+    let matched = match position {
+        Some(position) => candidates
+            .chain(first.clone())
+            .find(|w| w.x().ok() == Some(position.x) && w.y().ok() == Some(position.y))
+            .or(first),
+        None => first,
+    };
+    matched.ok_or_else(|| "未找到主窗口对应的系统窗口".to_string())
+}
+
+fn copy_to_clipboard(image: &image::RgbaImage) -> Result<(), String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    clipboard
+        .set_image(arboard::ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: Cow::Borrowed(image.as_raw()),
+        })
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,106 @@
+//! 生成交给 `window.eval` 执行的脚本。
+//!
+//! 动态值一律先序列化为 JSON，再作为函数参数传入固定的脚本模板，不直接拼接到脚本文本中。
+//! JSON 本身是合法的 JS 表达式，额外转义 `</` 与 U+2028/U+2029，
+//! 避免脚本被嵌入 `<script>` 标签或在旧版引擎中解析时提前截断。
+
+use serde::Serialize;
+
+/// 把值编码为可以直接放进 JS 源码的字面量。
+pub fn js_literal<T: Serialize + ?Sized>(value: &T) -> String {
+    let json = serde_json::to_string(value).unwrap_or_else(|_| "null".to_string());
+    json.replace("</", "<\\/")
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
+}
+
+/// 生成立即执行的函数：`body` 为固定模板，`args` 按顺序绑定到同名参数。
+///
+/// 参数名只能是模板中写死的标识符，取值经 [`js_literal`] 编码后传入。
+pub fn call_with_args(body: &str, args: &[(&str, serde_json::Value)]) -> String {
+    let names = args
+        .iter()
+        .map(|(name, _)| {
+            debug_assert!(is_identifier(name), "非法的参数名: {name}");
+            *name
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let values = args
+        .iter()
+        .map(|(_, value)| js_literal(value))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("(function({names}) {{\n{body}\n}})({values});")
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTILE: &[&str] = &[
+        "it's \"quoted\"",
+        "back\\slash \\' \\\"",
+        "line1\nline2\r\n\ttab",
+        "</script><script>alert(1)</script>",
+        "');alert(1);//",
+        "emoji 😀 and 𠀀",
+        "separators \u{2028} \u{2029}",
+        "nul \u{0} bell \u{7}",
+    ];
+
+    #[test]
+    fn literals_round_trip_verbatim() {
+        for input in HOSTILE {
+            let literal = js_literal(*input);
+            // 反转义 `<\/` 后仍是同一个 JSON 字符串
+            let decoded: String = serde_json::from_str(&literal.replace("<\\/", "</")).unwrap();
+            assert_eq!(&decoded, input);
+            // JSON 中 `\/` 是合法转义，直接解析也应得到原值
+            let direct: String = serde_json::from_str(&literal).unwrap();
+            assert_eq!(&direct, input);
+        }
+    }
+
+    #[test]
+    fn literals_never_contain_breaking_sequences() {
+        for input in HOSTILE {
+            let literal = js_literal(*input);
+            assert!(!literal.to_ascii_lowercase().contains("</script"));
+            assert!(!literal.contains('\n'));
+            assert!(!literal.contains('\r'));
+            assert!(!literal.contains('\u{2028}'));
+            assert!(!literal.contains('\u{2029}'));
+            assert!(literal.starts_with('"') && literal.ends_with('"'));
+        }
+    }
+
+    #[test]
+    fn call_with_args_keeps_template_and_encodes_values() {
+        let script = call_with_args(
+            "alert(msg);",
+            &[("msg", serde_json::Value::from("');alert(1);//"))],
+        );
+        assert_eq!(
+            script,
+            "(function(msg) {\nalert(msg);\n})(\"');alert(1);//\");"
+        );
+    }
+
+    #[test]
+    fn identifiers_are_validated() {
+        assert!(is_identifier("msg"));
+        assert!(is_identifier("_url$1"));
+        assert!(!is_identifier("1abc"));
+        assert!(!is_identifier("a-b"));
+        assert!(!is_identifier(""));
+    }
+}
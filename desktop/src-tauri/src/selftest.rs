@@ -0,0 +1,200 @@
+//! 启动自检：汇总桌面壳能独立判断的运行环境问题，供自检命令与启动日志复用。
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::{bdinfo, clock, datadir, footprint, paths, quarantine, runtime, settings};
+
+#[derive(Clone, Serialize)]
+pub struct SelfTestItem {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+pub fn run(app: &AppHandle) -> Vec<SelfTestItem> {
+    run_with(app, true)
+}
+
+/// 每次启动后的自检：不试运行 BDInfo，只检查文件是否存在，完整检查在用户运行自检时进行。
+pub fn run_at_startup(app: &AppHandle) -> Vec<SelfTestItem> {
+    run_with(app, false)
+}
+
+fn run_with(app: &AppHandle, probe_bdinfo: bool) -> Vec<SelfTestItem> {
+    vec![
+        runtime_files_item(app),
+        bdinfo_item(app, probe_bdinfo),
+        data_dir_item(app),
+        paths_item(app),
+        clock_item(app),
+        quarantine_item(app),
+    ]
+}
+
+/// 与启动时相同的必需文件检查，缺失的文件一次列出。
+fn runtime_files_item(app: &AppHandle) -> SelfTestItem {
+    let result = runtime::check_runtime_files(app);
+    SelfTestItem {
+        name: "runtime_files",
+        ok: result.is_ok(),
+        detail: match result {
+            Ok(()) => "运行文件齐全".to_string(),
+            Err(err) => err.to_string(),
+        },
+    }
+}
+
+/// 服务程序是否带有会被 Gatekeeper / SmartScreen 拦截的隔离标记。
+fn quarantine_item(app: &AppHandle) -> SelfTestItem {
+    let blocked = quarantine::scan(app);
+    SelfTestItem {
+        name: "quarantine",
+        ok: blocked.is_empty(),
+        detail: if blocked.is_empty() {
+            "运行文件没有隔离标记".to_string()
+        } else {
+            let files: Vec<String> = blocked
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            format!(
+                "{} 带有隔离标记，启动时可能被系统拦截，可通过 remove_quarantine 清除",
+                files.join("、")
+            )
+        },
+    }
+}
+
+/// 只读取最近一次测得的时钟偏差，不在自检中发起网络请求。
+fn clock_item(app: &AppHandle) -> SelfTestItem {
+    let (ok, detail) = match clock::last_skew(app) {
+        Some(skew) => (!skew.exceeds_threshold(), skew.describe()),
+        None if settings::current(app).clock_check => (true, "尚未完成时钟校验".to_string()),
+        None => (true, "未开启时钟校验（桌面设置 clock_check）".to_string()),
+    };
+    SelfTestItem {
+        name: "clock",
+        ok,
+        detail,
+    }
+}
+
+/// 安装目录与数据目录中的中文、超长路径等可能让子进程出错的情况。
+fn paths_item(app: &AppHandle) -> SelfTestItem {
+    let mut checked = Vec::new();
+    if let Ok(server_dir) = runtime::resolve_server_dir(app) {
+        checked.push(("安装目录", server_dir));
+    }
+    if let Ok(data_dir) = app.path().app_data_dir() {
+        checked.push(("数据目录", data_dir));
+    }
+
+    let problems: Vec<String> = checked
+        .iter()
+        .flat_map(|(label, path)| {
+            paths::risks(path)
+                .into_iter()
+                .map(move |risk| format!("{label} {} {risk}", path.display()))
+        })
+        .collect();
+
+    SelfTestItem {
+        name: "paths",
+        ok: problems.is_empty(),
+        detail: if problems.is_empty() {
+            "路径检查通过".to_string()
+        } else {
+            problems.join("；")
+        },
+    }
+}
+
+fn data_dir_item(app: &AppHandle) -> SelfTestItem {
+    let Ok(data_dir) = app.path().app_data_dir() else {
+        return SelfTestItem {
+            name: "data_dir",
+            ok: false,
+            detail: "无法解析应用数据目录".to_string(),
+        };
+    };
+
+    match datadir::inspect(&data_dir) {
+        Some(risk) => SelfTestItem {
+            name: "data_dir",
+            ok: false,
+            detail: format!(
+                "{} {}，同步或网络中断可能损坏数据库，建议移到本地磁盘",
+                risk.path, risk.detail
+            ),
+        },
+        None => SelfTestItem {
+            name: "data_dir",
+            ok: true,
+            detail: data_dir.to_string_lossy().to_string(),
+        },
+    }
+}
+
+fn bdinfo_item(app: &AppHandle, probe: bool) -> SelfTestItem {
+    let status = if probe {
+        bdinfo::status(app)
+    } else {
+        bdinfo::located(app)
+    };
+    // 用户主动删除的打包版本不算安装损坏
+    if !status.exists && !status.overridden && footprint::is_removed(app, "bdinfo") {
+        return SelfTestItem {
+            name: "bdinfo",
+            ok: true,
+            detail: format!(
+                "打包的 BDInfo 已按需删除，原盘扫描不可用；需要时可在 runtime.env 中设置 {} 指向可用的 BDInfo",
+                bdinfo::BDINFO_PATH_KEY
+            ),
+        };
+    }
+    if !probe && status.exists {
+        return SelfTestItem {
+            name: "bdinfo",
+            ok: true,
+            detail: format!(
+                "{}（未试运行，可在托盘“运行自检”中检查能否运行）",
+                status.path
+            ),
+        };
+    }
+    let detail = match (&status.error, status.runnable) {
+        (_, true) => format!(
+            "{}{}",
+            status.path,
+            if status.overridden {
+                "（runtime.env 覆盖）"
+            } else {
+                ""
+            }
+        ),
+        (Some(err), false) => format!(
+            "{} 不可用: {err}。可在 runtime.env 中设置 {} 指向可用的 BDInfo",
+            status.path,
+            bdinfo::BDINFO_PATH_KEY
+        ),
+        (None, false) => format!("{} 不可用", status.path),
+    };
+
+    SelfTestItem {
+        name: "bdinfo",
+        ok: status.runnable,
+        detail,
+    }
+}
+
+/// 把自检结果写入 shell.log，便于用户反馈问题时一并提供。
+pub fn log_results(app: &AppHandle, items: &[SelfTestItem]) {
+    for item in items {
+        let level = if item.ok { "INFO" } else { "WARN" };
+        crate::runtime::shell_log(
+            app,
+            &format!("[{level}] 自检 {}: {}", item.name, item.detail),
+        );
+    }
+}
@@ -0,0 +1,30 @@
+//! 服务模式：不创建主窗口，只保留托盘，WebUI 通过系统浏览器访问。
+//!
+//! 由桌面设置 `service_mode` 或启动参数 `--service-mode` 开启；启动参数优先，此时托盘中无法关闭。
+
+use tauri::AppHandle;
+
+use crate::settings;
+
+pub const SERVICE_MODE_FLAG: &str = "--service-mode";
+
+pub fn forced_by_flag() -> bool {
+    std::env::args().any(|arg| arg == SERVICE_MODE_FLAG)
+}
+
+pub fn is_enabled(app: &AppHandle) -> bool {
+    forced_by_flag() || settings::current(app).service_mode
+}
+
+/// 切换服务模式并重启应用，使窗口的创建与否按新设置生效。
+pub fn toggle_and_restart(app: &AppHandle) -> Result<(), String> {
+    if forced_by_flag() {
+        return Err(format!(
+            "当前通过 {SERVICE_MODE_FLAG} 参数启动，需去掉该参数后才能退出服务模式。"
+        ));
+    }
+    settings::update(app, |settings| {
+        settings.service_mode = !settings.service_mode
+    })?;
+    crate::restart_app(app);
+}
@@ -0,0 +1,1390 @@
+//! 后端服务进程的启动、就绪等待与停止。
+//!
+//! 各服务以 [`ServiceSpec`] 表描述：启动入口、工作目录、就绪判断方式、依赖的服务以及是否必需。
+//! bootstrap 负责按运行目录组装这张表，本模块按依赖关系分层启动（同一层可以并行）并等待就绪，
+//! 因此测试可以换成假服务验证启动流程。就绪等待作为异步任务在共用运行时上进行（见 asyncrt.rs），
+//! 并行启动不再为每个服务占用一个线程；进度回调仍在调用 [`launch`] 的线程上执行。
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::future::Future;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::capture::{self, Output, Stream};
+use crate::tasks::CancelToken;
+use crate::{activity, asyncrt, localhttp, quarantine};
+
+/// runtime.env 中等待 server、batch、updater 就绪的秒数。
+pub const READY_TIMEOUT_KEY: &str = "PTNEXUS_READY_TIMEOUT_SECS";
+/// 就绪等待轮询间隔的下限与上限（毫秒），主要供测试调整。
+pub const READY_POLL_MIN_KEY: &str = "PTNEXUS_READY_POLL_MIN_MS";
+pub const READY_POLL_MAX_KEY: &str = "PTNEXUS_READY_POLL_MAX_MS";
+const READY_POLL_MIN: Duration = Duration::from_millis(100);
+const READY_POLL_MAX: Duration = Duration::from_secs(1);
+/// 开始等待后按下限轮询的时长，之后逐次翻倍。
+const FAST_POLL_PHASE: Duration = Duration::from_secs(1);
+/// runtime.env 中端口尚未释放时最多等待的秒数，设为 0 不等待。
+pub const PORT_GRACE_KEY: &str = "PTNEXUS_PORT_GRACE_SECS";
+const PORT_GRACE: Duration = Duration::from_secs(10);
+const PORT_RECHECK: Duration = Duration::from_millis(500);
+const PORT_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+/// 进程退出后等待读取线程把剩余输出写入日志的时长，随后才读取 stderr 末尾。
+const OUTPUT_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 轮询间隔：开始的一段时间按下限频繁探测，让很快就绪的服务不必多等；之后逐次翻倍直到上限，
+/// 避免长时间等待中反复连接端口（部分杀毒软件会为每次连接做检查）并不断唤醒 CPU。
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    floor: Duration,
+    ceiling: Duration,
+    started: Instant,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(floor: Duration, ceiling: Duration) -> Self {
+        let ceiling = ceiling.max(floor);
+        Self {
+            floor,
+            ceiling,
+            started: Instant::now(),
+            current: floor,
+        }
+    }
+
+    /// 就绪等待使用的间隔，可由 READY_POLL_MIN_KEY / READY_POLL_MAX_KEY 调整。
+    pub fn from_env(envs: &HashMap<String, String>) -> Self {
+        let millis = |key: &str| {
+            envs.get(key)
+                .cloned()
+                .or_else(|| std::env::var(key).ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis)
+        };
+        Self::new(
+            millis(READY_POLL_MIN_KEY).unwrap_or(READY_POLL_MIN),
+            millis(READY_POLL_MAX_KEY).unwrap_or(READY_POLL_MAX),
+        )
+    }
+
+    /// 重新从下限开始，用于状态发生变化之后。
+    pub fn reset(&mut self) {
+        self.started = Instant::now();
+        self.current = self.floor;
+    }
+
+    /// 下一次探测前等待的时间。
+    pub fn next_interval(&mut self) -> Duration {
+        let elapsed = self.started.elapsed();
+        self.interval_after(elapsed)
+    }
+
+    fn interval_after(&mut self, elapsed: Duration) -> Duration {
+        if elapsed < FAST_POLL_PHASE {
+            return self.floor;
+        }
+        self.current = (self.current * 2).min(self.ceiling);
+        self.current
+    }
+}
+
+/// 一个后端服务的启动方式。
+#[derive(Clone, Debug)]
+pub struct ServiceSpec {
+    pub name: String,
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    pub workdir: PathBuf,
+    pub readiness: Readiness,
+    /// 需要先就绪的服务名。
+    pub depends_on: Vec<String>,
+    /// 必需的服务启动失败时整体失败；可选服务失败时跳过它及依赖它的服务。
+    pub required: bool,
+}
+
+/// 判断服务启动成功的方式。
+#[derive(Clone, Debug)]
+pub enum Readiness {
+    /// 没有监听端口的进程：在给定时间内未退出即视为启动成功。
+    StaysRunning(Duration),
+    /// 端口可以连接即视为就绪。
+    Http {
+        host: String,
+        port: u16,
+        timeout: Duration,
+    },
+    /// 健康检查接口返回 200 即视为就绪。
+    Health {
+        host: String,
+        port: u16,
+        path: String,
+        timeout: Duration,
+    },
+}
+
+impl Readiness {
+    pub fn port(&self) -> Option<u16> {
+        match self {
+            Self::StaysRunning(_) => None,
+            Self::Http { port, .. } | Self::Health { port, .. } => Some(*port),
+        }
+    }
+
+    /// 就绪等待的超时时间乘以 `factor`；StaysRunning 只要求进程不退出，观察时长不变。
+    pub fn stretched(mut self, factor: u32) -> Self {
+        match &mut self {
+            Self::StaysRunning(_) => {}
+            Self::Http { timeout, .. } | Self::Health { timeout, .. } => {
+                *timeout = timeout.saturating_mul(factor)
+            }
+        }
+        self
+    }
+}
+
+/// 已启动的服务及其进程。
+#[derive(Debug)]
+pub struct RunningService {
+    pub spec: ServiceSpec,
+    pub child: Child,
+}
+
+/// 启动过程中的进度，供调用方发送启动阶段事件、记录耗时、检查监听地址。
+pub enum Progress<'a> {
+    Spawning(&'a ServiceSpec),
+    Spawned(&'a ServiceSpec),
+    Ready(&'a ServiceSpec, &'a Child),
+    /// 可选服务启动失败，或其依赖的服务未启动，已跳过。
+    Skipped(&'a ServiceSpec, &'a str),
+}
+
+/// 启动任务报告给调用线程的进度。
+enum Step {
+    Spawning,
+    Spawned,
+    Done(Result<Child, String>),
+}
+
+/// 按依赖关系把服务分层：每层只依赖前面各层，层内保持表中的顺序。
+pub fn plan_levels(specs: &[ServiceSpec]) -> Result<Vec<Vec<usize>>, String> {
+    for spec in specs {
+        if let Some(missing) = spec
+            .depends_on
+            .iter()
+            .find(|dep| !specs.iter().any(|other| &other.name == *dep))
+        {
+            return Err(format!("服务 {} 依赖的 {missing} 不存在", spec.name));
+        }
+    }
+
+    let mut placed: Vec<Option<usize>> = vec![None; specs.len()];
+    let mut levels: Vec<Vec<usize>> = Vec::new();
+    while placed.iter().any(Option::is_none) {
+        let level: Vec<usize> = (0..specs.len())
+            .filter(|index| placed[*index].is_none())
+            .filter(|index| {
+                specs[*index].depends_on.iter().all(|dep| {
+                    specs
+                        .iter()
+                        .position(|other| &other.name == dep)
+                        .is_some_and(|dep_index| placed[dep_index].is_some())
+                })
+            })
+            .collect();
+        if level.is_empty() {
+            let cycle: Vec<&str> = (0..specs.len())
+                .filter(|index| placed[*index].is_none())
+                .map(|index| specs[index].name.as_str())
+                .collect();
+            return Err(format!("服务之间存在循环依赖: {}", cycle.join(", ")));
+        }
+        for index in &level {
+            placed[*index] = Some(levels.len());
+        }
+        levels.push(level);
+    }
+    Ok(levels)
+}
+
+/// 按依赖顺序启动各服务并等待就绪；`parallel` 为 true 时同一层的服务同时启动。
+/// 必需的服务失败时先停止已启动的进程，再返回错误。返回的服务按启动顺序排列。
+pub fn launch(
+    specs: &[ServiceSpec],
+    envs: &HashMap<String, String>,
+    logs_dir: &Path,
+    parallel: bool,
+    mut observe: impl FnMut(Progress<'_>) + Send,
+) -> Result<Vec<RunningService>, String> {
+    let levels = plan_levels(specs)?;
+    let envs = Arc::new(envs.clone());
+
+    let mut running: Vec<RunningService> = Vec::new();
+    let mut unavailable: Vec<&str> = Vec::new();
+    for level in levels {
+        let mut pending = Vec::new();
+        for index in level {
+            let spec = &specs[index];
+            match spec
+                .depends_on
+                .iter()
+                .find(|dep| unavailable.contains(&dep.as_str()))
+            {
+                Some(dep) if spec.required => {
+                    stop_all(&mut running);
+                    return Err(format!("服务 {} 依赖的 {dep} 未能启动", spec.name));
+                }
+                Some(dep) => {
+                    observe(Progress::Skipped(spec, &format!("依赖的服务 {dep} 未启动")));
+                    unavailable.push(&spec.name);
+                }
+                None => pending.push(spec),
+            }
+        }
+
+        let batches: Vec<Vec<&ServiceSpec>> = if parallel {
+            vec![pending]
+        } else {
+            pending.into_iter().map(|spec| vec![spec]).collect()
+        };
+        let mut outcomes = Vec::new();
+        for batch in batches {
+            outcomes.extend(launch_batch(&batch, &envs, logs_dir, &mut observe));
+        }
+
+        let mut failure = None;
+        for (spec, outcome) in outcomes {
+            match outcome {
+                Ok(child) => running.push(RunningService {
+                    spec: spec.clone(),
+                    child,
+                }),
+                Err(err) if spec.required => {
+                    failure.get_or_insert(err);
+                }
+                Err(err) => {
+                    observe(Progress::Skipped(spec, &err));
+                    unavailable.push(&spec.name);
+                }
+            }
+        }
+        if let Some(err) = failure {
+            stop_all(&mut running);
+            return Err(err);
+        }
+    }
+    Ok(running)
+}
+
+/// 在共用异步运行时上同时启动一批服务并等待就绪，进度在调用线程上回调；结果与 `batch` 的顺序一致。
+fn launch_batch<'a>(
+    batch: &[&'a ServiceSpec],
+    envs: &Arc<HashMap<String, String>>,
+    logs_dir: &Path,
+    observe: &mut impl FnMut(Progress<'_>),
+) -> Vec<(&'a ServiceSpec, Result<Child, String>)> {
+    let (sender, receiver) = mpsc::channel();
+    for (index, spec) in batch.iter().enumerate() {
+        let spec = (*spec).clone();
+        let envs = envs.clone();
+        let logs_dir = logs_dir.to_path_buf();
+        let sender = sender.clone();
+        asyncrt::spawn(&format!("ready:{}", spec.name), move |token| async move {
+            let report = |step| {
+                let _ = sender.send((index, step));
+            };
+            let outcome = launch_one(&spec, &envs, &logs_dir, &token, &report).await;
+            report(Step::Done(outcome));
+        });
+    }
+    drop(sender);
+
+    let mut outcomes: Vec<Option<Result<Child, String>>> = batch.iter().map(|_| None).collect();
+    // 全部任务结束、发送端都释放后循环结束
+    for (index, step) in receiver {
+        let spec = batch[index];
+        match step {
+            Step::Spawning => observe(Progress::Spawning(spec)),
+            Step::Spawned => observe(Progress::Spawned(spec)),
+            Step::Done(outcome) => {
+                if let Ok(child) = &outcome {
+                    observe(Progress::Ready(spec, child));
+                }
+                outcomes[index] = Some(outcome);
+            }
+        }
+    }
+    batch
+        .iter()
+        .zip(outcomes)
+        .map(|(spec, outcome)| {
+            let outcome =
+                outcome.unwrap_or_else(|| Err(format!("启动 {} 的任务异常退出", spec.name)));
+            (*spec, outcome)
+        })
+        .collect()
+}
+
+async fn launch_one(
+    spec: &ServiceSpec,
+    envs: &HashMap<String, String>,
+    logs_dir: &Path,
+    token: &CancelToken,
+    report: &(impl Fn(Step) + Sync),
+) -> Result<Child, String> {
+    // 启动前面的服务需要时间，期间端口可能被其他程序占用；此时连接探测会误判为就绪，所以再查一次
+    if let Some(port) = spec.readiness.port() {
+        wait_for_port(&spec.name, port, port_grace(envs), token).await?;
+    }
+
+    report(Step::Spawning);
+    let (mut child, output) = spawn_process(
+        &spec.program,
+        &spec.workdir,
+        envs,
+        &spec.args,
+        &spec.name,
+        logs_dir,
+    )
+    .map_err(|err| quarantine::annotate(&spec.program, err))?;
+    let spawned_at = Instant::now();
+    report(Step::Spawned);
+    // 每个服务（包括重启时）都从下限重新开始
+    let poll = Backoff::from_env(envs);
+
+    let waited = match &spec.readiness {
+        Readiness::StaysRunning(duration) => {
+            wait_for_process_running(&mut child, &output, *duration, poll, token).await
+        }
+        Readiness::Http {
+            host,
+            port,
+            timeout,
+        } => {
+            let target = format!("服务 {host}:{port}");
+            wait_until(
+                &mut child,
+                &output,
+                *timeout,
+                poll,
+                &target,
+                token,
+                move || port_open(host, *port),
+            )
+            .await
+        }
+        Readiness::Health {
+            host,
+            port,
+            path,
+            timeout,
+        } => {
+            let target = format!("服务 {host}:{port}{path}");
+            wait_until(
+                &mut child,
+                &output,
+                *timeout,
+                poll,
+                &target,
+                token,
+                move || async move { localhttp::get_async(host, *port, path).await.is_ok() },
+            )
+            .await
+        }
+    };
+    if let Err(err) = waited {
+        let _ = child.kill();
+        let _ = child.wait();
+        // 被 Gatekeeper / SmartScreen 拦截的程序通常刚启动就被结束
+        if spawned_at.elapsed() < quarantine::INSTANT_EXIT {
+            return Err(quarantine::annotate(&spec.program, err));
+        }
+        return Err(err);
+    }
+    Ok(child)
+}
+
+async fn port_open(host: &str, port: u16) -> bool {
+    let connect = tokio::net::TcpStream::connect((host, port));
+    matches!(
+        tokio::time::timeout(PORT_PROBE_TIMEOUT, connect).await,
+        Ok(Ok(_))
+    )
+}
+
+fn cancelled(process_name: &str) -> String {
+    format!("启动 {process_name} 已取消")
+}
+
+/// 终止全部进程并等待其真正退出，保证端口在随后的重启中可用。
+pub fn stop_all(services: &mut Vec<RunningService>) {
+    for service in services.iter_mut() {
+        let _ = service.child.kill();
+        let _ = service.child.wait();
+    }
+    services.clear();
+}
+
+/// 先请求进程自行退出，超时后再强制结束。
+///
+/// Unix 上发送 SIGTERM；Windows 上隐藏窗口的控制台进程收不到关闭消息，直接结束。
+pub fn stop_gracefully(child: &mut Child, timeout: Duration) {
+    #[cfg(unix)]
+    {
+        let requested = Command::new("kill")
+            .arg("-TERM")
+            .arg(child.id().to_string())
+            .status()
+            .is_ok_and(|status| status.success());
+        if requested {
+            let begin = Instant::now();
+            while begin.elapsed() < timeout {
+                if !matches!(child.try_wait(), Ok(None)) {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(200));
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = timeout;
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn spawn_process(
+    executable: &Path,
+    working_dir: &Path,
+    envs: &HashMap<String, String>,
+    args: &[String],
+    process_name: &str,
+    logs_dir: &Path,
+) -> Result<(Child, Output), String> {
+    // 输出经由桌面壳的读取线程写入日志（见 capture.rs），先确认日志目录可写
+    for stream in [Stream::Stdout, Stream::Stderr] {
+        let log = capture::log_path(logs_dir, process_name, stream);
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log)
+            .map_err(|e| format!("打开日志文件失败 {}: {e}", log.display()))?;
+    }
+
+    let mut cmd = Command::new(executable);
+    cmd.args(args)
+        .current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // Windows 上隐藏子进程的终端窗口，避免弹出三个黑框
+    #[cfg(target_os = "windows")]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    // 宿主环境中的该变量会让 Python 退回 ANSI 代码页解码文件名，与 PYTHONUTF8 冲突
+    cmd.env_remove("PYTHONLEGACYWINDOWSFSENCODING");
+    for (key, value) in envs {
+        cmd.env(key, value);
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("启动进程失败 {}: {e}", executable.display()))?;
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(format!("获取进程 {process_name} 的输出管道失败"));
+    };
+    let output = Output::attach(process_name, stdout, stderr, logs_dir);
+    Ok((child, output))
+}
+
+/// 端口的占用情况。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PortState {
+    Free,
+    /// 有程序在监听，连接可以建立。
+    Listening,
+    /// 无法绑定但没有程序应答：通常是刚退出的进程留下的连接（TIME_WAIT 等），稍后会自行释放。
+    Lingering,
+}
+
+pub fn port_state(port: u16) -> PortState {
+    if TcpListener::bind(("127.0.0.1", port)).is_ok() {
+        return PortState::Free;
+    }
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    match TcpStream::connect_timeout(&addr, PORT_PROBE_TIMEOUT) {
+        Ok(_) => PortState::Listening,
+        Err(_) => PortState::Lingering,
+    }
+}
+
+/// runtime.env（或宿主环境变量）中的 PORT_GRACE_KEY，无效时使用默认的 10 秒。
+pub fn port_grace(envs: &HashMap<String, String>) -> Duration {
+    envs.get(PORT_GRACE_KEY)
+        .cloned()
+        .or_else(|| std::env::var(PORT_GRACE_KEY).ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map_or(PORT_GRACE, Duration::from_secs)
+}
+
+/// 检查端口均可绑定。有程序在监听时立即失败；端口只是尚未释放时最多等待 `grace`，
+/// 等待期间每次复查前调用 `waiting(端口, 剩余时间)`。退出后立即重新打开应用时，
+/// 上次运行的连接可能还要几秒才释放。
+pub fn wait_for_ports(
+    ports: &[u16],
+    grace: Duration,
+    mut waiting: impl FnMut(u16, Duration),
+) -> Result<(), String> {
+    let deadline = Instant::now() + grace;
+    for port in ports {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if port_free(*port, remaining, grace)? {
+                break;
+            }
+            waiting(*port, remaining);
+            thread::sleep(PORT_RECHECK.min(remaining));
+        }
+    }
+    Ok(())
+}
+
+/// 启动单个服务前的 [`wait_for_ports`]，在异步任务中等待。
+async fn wait_for_port(
+    process_name: &str,
+    port: u16,
+    grace: Duration,
+    token: &CancelToken,
+) -> Result<(), String> {
+    let deadline = Instant::now() + grace;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if port_free(port, remaining, grace)? {
+            return Ok(());
+        }
+        if !token.sleep_async(PORT_RECHECK.min(remaining)).await {
+            return Err(cancelled(process_name));
+        }
+    }
+}
+
+/// 端口可以绑定时返回 true；尚未释放且还有 `remaining` 可等时返回 false，稍后复查。
+fn port_free(port: u16, remaining: Duration, grace: Duration) -> Result<bool, String> {
+    match port_state(port) {
+        PortState::Free => Ok(true),
+        PortState::Lingering if !remaining.is_zero() => Ok(false),
+        PortState::Lingering if !grace.is_zero() => Err(port_error(
+            port,
+            format!(
+                "端口 {port} 在 {} 秒内仍未释放（没有程序应答，可能是刚退出的程序留下的连接）",
+                grace.as_secs()
+            ),
+            &format!("请稍后重试，或在 runtime.env 中调大 {PORT_GRACE_KEY}、修改端口"),
+        )),
+        PortState::Listening | PortState::Lingering => Err(port_error(
+            port,
+            format!("端口 {port} 被占用"),
+            "请先释放该端口，或在 runtime.env 中修改端口后再启动应用",
+        )),
+    }
+}
+
+fn port_error(port: u16, message: String, hint: &str) -> String {
+    let suggestions = crate::portconfig::suggest_free_ports(port, 3);
+    let message = crate::portconfig::with_suggestions(message, &suggestions);
+    format!("{message}。{hint}。")
+}
+
+/// 等待 `ready` 返回 true；期间进程退出、超时或被取消则返回错误，`target` 用于错误信息
+/// （如“服务 127.0.0.1:5275”）。
+async fn wait_until<Fut>(
+    child: &mut Child,
+    output: &Output,
+    timeout: Duration,
+    mut poll: Backoff,
+    target: &str,
+    token: &CancelToken,
+    mut ready: impl FnMut() -> Fut,
+) -> Result<(), String>
+where
+    Fut: Future<Output = bool>,
+{
+    let process_name = output.service();
+    let begin = Instant::now();
+    let stdout_log = output.log(Stream::Stdout);
+    let stderr_log = output.log(Stream::Stderr);
+    // 超时前一段时间采样一次，超时时与之比较
+    let window = activity::window(timeout);
+    let mut earlier = None;
+
+    loop {
+        if ready().await {
+            return Ok(());
+        }
+
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                output.wait_flushed(OUTPUT_FLUSH_TIMEOUT).await;
+                let stderr_tail = read_log_tail(&stderr_log, 40);
+                if stderr_tail.is_empty() {
+                    return Err(format!(
+                        "进程 {process_name} 已退出（状态: {status}），{target} 未就绪。\n请查看日志：{}",
+                        stderr_log.display()
+                    ));
+                }
+
+                return Err(format!(
+                    "进程 {process_name} 已退出（状态: {status}），{target} 未就绪。\n日志：{}\n\n最近 stderr 输出：\n{stderr_tail}",
+                    stderr_log.display()
+                ));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                return Err(format!(
+                    "检查进程 {process_name} 运行状态失败: {e}。\n请查看日志：{}, {}",
+                    stdout_log.display(),
+                    stderr_log.display()
+                ));
+            }
+        }
+
+        if begin.elapsed() > timeout {
+            let latest = activity::sample(child.id(), &stderr_log);
+            let observed = activity::classify(earlier.unwrap_or(latest), latest);
+            return Err(timeout_message(
+                process_name,
+                target,
+                timeout,
+                observed,
+                &stdout_log,
+                &stderr_log,
+            ));
+        }
+        if earlier.is_none() && begin.elapsed() + window >= timeout {
+            earlier = Some(activity::sample(child.id(), &stderr_log));
+        }
+
+        if !token.sleep_async(poll.next_interval()).await {
+            return Err(cancelled(process_name));
+        }
+    }
+}
+
+/// 按超时前的活动区分“仍在初始化”与“卡住”，两种情况用户接下来要做的事不同。
+fn timeout_message(
+    process_name: &str,
+    target: &str,
+    timeout: Duration,
+    observed: activity::Activity,
+    stdout_log: &Path,
+    stderr_log: &Path,
+) -> String {
+    let seconds = timeout.as_secs();
+    match observed {
+        activity::Activity::Busy { .. } => format!(
+            "等待{target} 超时（{seconds} 秒），但进程 {process_name} 仍在工作（{}）。\n\
+             服务仍在初始化，可能需要更长时间，可在 runtime.env 中调大 {READY_TIMEOUT_KEY}。\n请查看日志：{}, {}",
+            observed.describe(),
+            stdout_log.display(),
+            stderr_log.display()
+        ),
+        activity::Activity::Idle => {
+            let stderr_tail = read_log_tail(stderr_log, 40);
+            let tail = if stderr_tail.is_empty() {
+                String::new()
+            } else {
+                format!("\n\n最近 stderr 输出：\n{stderr_tail}")
+            };
+            format!(
+                "等待{target} 超时（{seconds} 秒），进程 {process_name} 仍在运行，但最近 {} 秒{}，可能已卡住。\n日志：{}{tail}",
+                activity::window(timeout).as_secs_f64().ceil(),
+                observed.describe(),
+                stderr_log.display()
+            )
+        }
+        activity::Activity::Unknown => format!(
+            "等待{target} 超时（{seconds} 秒）。\n请查看日志：{}, {}",
+            stdout_log.display(),
+            stderr_log.display()
+        ),
+    }
+}
+
+async fn wait_for_process_running(
+    child: &mut Child,
+    output: &Output,
+    timeout: Duration,
+    mut poll: Backoff,
+    token: &CancelToken,
+) -> Result<(), String> {
+    let process_name = output.service();
+    let begin = Instant::now();
+    let stdout_log = output.log(Stream::Stdout);
+    let stderr_log = output.log(Stream::Stderr);
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                output.wait_flushed(OUTPUT_FLUSH_TIMEOUT).await;
+                let stderr_tail = read_log_tail(&stderr_log, 40);
+                if stderr_tail.is_empty() {
+                    return Err(format!(
+                        "进程 {process_name} 已退出（状态: {status}），未能保持运行。
+请查看日志：{}",
+                        stderr_log.display()
+                    ));
+                }
+                return Err(format!(
+                    "进程 {process_name} 已退出（状态: {status}），未能保持运行。
+日志：{}
+
+最近 stderr 输出：
+{stderr_tail}",
+                    stderr_log.display()
+                ));
+            }
+            Ok(None) => {
+                if begin.elapsed() >= timeout {
+                    return Ok(());
+                }
+            }
+            Err(e) => {
+                return Err(format!(
+                    "检查进程 {process_name} 运行状态失败: {e}。
+请查看日志：{}, {}",
+                    stdout_log.display(),
+                    stderr_log.display()
+                ));
+            }
+        }
+
+        // 不要睡过头：到时间后立即判定成功
+        let interval = poll
+            .next_interval()
+            .min(timeout.saturating_sub(begin.elapsed()));
+        if !token.sleep_async(interval).await {
+            return Err(cancelled(process_name));
+        }
+    }
+}
+
+fn read_log_tail(path: &Path, max_lines: usize) -> String {
+    let Ok(content) = fs::read_to_string(path) else {
+        return String::new();
+    };
+
+    let mut lines: Vec<&str> = content.lines().rev().take(max_lines).collect();
+    lines.reverse();
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    /// 设置后测试二进制以假服务身份运行 [`fake_service_main`]。
+    const FAKE_SERVICE_ENV: &str = "PTNEXUS_FAKE_SERVICE";
+    /// 假服务从工作目录中的该文件读取行为配置（`key=value`）。
+    const FAKE_CONFIG_FILE: &str = "fake-service.conf";
+
+    /// 假服务：测试二进制重新运行自身的这一个用例。正常跑测试时没有设置环境变量，直接返回。
+    ///
+    /// 支持的配置：`port` 监听端口、`http` 为 1 时对每个请求返回 200、`ready_delay_ms` 开始监听前的延迟、
+    /// `exit_after_ms` 多久后退出、`exit_code` 退出码。
+    #[test]
+    fn fake_service_main() {
+        if std::env::var_os(FAKE_SERVICE_ENV).is_none() {
+            return;
+        }
+        let config: HashMap<String, u64> = fs::read_to_string(FAKE_CONFIG_FILE)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .filter_map(|(key, value)| Some((key.trim().to_string(), value.trim().parse().ok()?)))
+            .collect();
+
+        println!("fake service starting");
+        eprintln!("fake stderr: starting");
+        if let Some(ms) = config.get("ready_delay_ms") {
+            thread::sleep(Duration::from_millis(*ms));
+        }
+        let listener = config.get("port").map(|port| {
+            let listener = TcpListener::bind(("127.0.0.1", *port as u16)).expect("假服务监听失败");
+            println!("fake service listening on {port}");
+            listener
+        });
+        if config.contains_key("http") {
+            if let Some(listener) = listener
+                .as_ref()
+                .and_then(|listener| listener.try_clone().ok())
+            {
+                thread::spawn(move || {
+                    for mut stream in listener.incoming().flatten() {
+                        // 读完请求头再应答，否则客户端分段写入时会遇到连接已关闭
+                        let mut request = Vec::new();
+                        let mut chunk = [0u8; 1024];
+                        while !request.ends_with(b"\r\n\r\n") {
+                            match stream.read(&mut chunk) {
+                                Ok(0) | Err(_) => break,
+                                Ok(read) => request.extend_from_slice(&chunk[..read]),
+                            }
+                        }
+                        let _ = stream.write_all(b"HTTP/1.0 200 OK\r\n\r\nok");
+                    }
+                });
+            }
+        }
+        println!("fake service ready");
+
+        let started = Instant::now();
+        loop {
+            if let Some(ms) = config.get("exit_after_ms") {
+                if started.elapsed() >= Duration::from_millis(*ms) {
+                    let code = config.get("exit_code").copied().unwrap_or(0) as i32;
+                    eprintln!("fake stderr: exiting with {code}");
+                    std::process::exit(code);
+                }
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    struct Harness {
+        dir: PathBuf,
+        envs: HashMap<String, String>,
+    }
+
+    impl Harness {
+        fn new(name: &str) -> Self {
+            let dir = temp_dir(name);
+            fs::create_dir_all(dir.join("logs")).unwrap();
+            let envs = HashMap::from([(FAKE_SERVICE_ENV.to_string(), "1".to_string())]);
+            Self { dir, envs }
+        }
+
+        fn logs_dir(&self) -> PathBuf {
+            self.dir.join("logs")
+        }
+
+        fn spec(&self, name: &str, config: &[(&str, u64)], readiness: Readiness) -> ServiceSpec {
+            let workdir = self.dir.join(name);
+            fs::create_dir_all(&workdir).unwrap();
+            let content = config
+                .iter()
+                .map(|(key, value)| format!("{key}={value}\n"))
+                .collect::<String>();
+            fs::write(workdir.join(FAKE_CONFIG_FILE), content).unwrap();
+
+            ServiceSpec {
+                name: name.to_string(),
+                program: std::env::current_exe().unwrap(),
+                args: [
+                    "services::tests::fake_service_main",
+                    "--exact",
+                    "--nocapture",
+                    "--test-threads=1",
+                ]
+                .map(str::to_string)
+                .to_vec(),
+                workdir,
+                readiness,
+                depends_on: Vec::new(),
+                required: true,
+            }
+        }
+
+        fn launch(
+            &self,
+            specs: &[ServiceSpec],
+        ) -> (Result<Vec<RunningService>, String>, Vec<String>) {
+            self.launch_with(specs, false)
+        }
+
+        fn launch_with(
+            &self,
+            specs: &[ServiceSpec],
+            parallel: bool,
+        ) -> (Result<Vec<RunningService>, String>, Vec<String>) {
+            let mut events = Vec::new();
+            let result = launch(specs, &self.envs, &self.logs_dir(), parallel, |progress| {
+                events.push(match progress {
+                    Progress::Spawning(spec) => format!("spawning {}", spec.name),
+                    Progress::Spawned(spec) => format!("spawned {}", spec.name),
+                    Progress::Ready(spec, _) => format!("ready {}", spec.name),
+                    Progress::Skipped(spec, _) => format!("skipped {}", spec.name),
+                });
+            });
+            (result, events)
+        }
+    }
+
+    impl Drop for Harness {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// 同时占住再一起释放，保证拿到的端口互不相同。
+    fn free_ports<const N: usize>() -> [u16; N] {
+        let listeners: Vec<TcpListener> = (0..N)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        std::array::from_fn(|i| listeners[i].local_addr().unwrap().port())
+    }
+
+    fn http(port: u16, timeout: Duration) -> Readiness {
+        Readiness::Http {
+            host: "127.0.0.1".to_string(),
+            port,
+            timeout,
+        }
+    }
+
+    fn is_listening(port: u16) -> bool {
+        TcpStream::connect(("127.0.0.1", port)).is_ok()
+    }
+
+    fn depends_on(mut spec: ServiceSpec, deps: &[&str]) -> ServiceSpec {
+        spec.depends_on = deps.iter().map(|dep| dep.to_string()).collect();
+        spec
+    }
+
+    fn optional(mut spec: ServiceSpec) -> ServiceSpec {
+        spec.required = false;
+        spec
+    }
+
+    fn named(name: &str, deps: &[&str]) -> ServiceSpec {
+        depends_on(
+            ServiceSpec {
+                name: name.to_string(),
+                program: PathBuf::new(),
+                args: Vec::new(),
+                workdir: PathBuf::new(),
+                readiness: Readiness::StaysRunning(Duration::ZERO),
+                depends_on: Vec::new(),
+                required: true,
+            },
+            deps,
+        )
+    }
+
+    #[test]
+    fn services_are_planned_in_dependency_levels() {
+        let specs = [
+            named("updater", &["server"]),
+            named("background_runner", &[]),
+            named("batch", &["server"]),
+            named("server", &["background_runner"]),
+        ];
+        assert_eq!(
+            plan_levels(&specs).unwrap(),
+            vec![vec![1], vec![3], vec![0, 2]]
+        );
+
+        let err = plan_levels(&[named("server", &["runner"])]).unwrap_err();
+        assert!(err.contains("runner"), "{err}");
+        let err =
+            plan_levels(&[named("a", &["b"]), named("b", &["a"]), named("c", &[])]).unwrap_err();
+        assert!(err.contains("循环依赖: a, b"), "{err}");
+    }
+
+    #[test]
+    fn optional_failure_skips_dependents_only() {
+        let harness = Harness::new("optional");
+        let specs = [
+            harness.spec(
+                "runner",
+                &[],
+                Readiness::StaysRunning(Duration::from_millis(300)),
+            ),
+            optional(harness.spec(
+                "plugin",
+                &[("exit_after_ms", 50), ("exit_code", 2)],
+                Readiness::StaysRunning(Duration::from_secs(5)),
+            )),
+            optional(depends_on(
+                harness.spec(
+                    "plugin_ui",
+                    &[],
+                    Readiness::StaysRunning(Duration::from_millis(300)),
+                ),
+                &["plugin"],
+            )),
+            depends_on(
+                harness.spec(
+                    "worker",
+                    &[],
+                    Readiness::StaysRunning(Duration::from_millis(300)),
+                ),
+                &["runner"],
+            ),
+        ];
+
+        let (result, events) = harness.launch(&specs);
+        let mut services = result.unwrap();
+        let names: Vec<&str> = services
+            .iter()
+            .map(|service| service.spec.name.as_str())
+            .collect();
+        assert_eq!(names, ["runner", "worker"]);
+        assert!(events.contains(&"skipped plugin".to_string()));
+        assert!(events.contains(&"skipped plugin_ui".to_string()));
+        assert!(!events.contains(&"spawning plugin_ui".to_string()));
+        stop_all(&mut services);
+    }
+
+    #[test]
+    fn health_readiness_waits_for_the_endpoint() {
+        let harness = Harness::new("probes");
+        let [port] = free_ports();
+        let server = harness.spec(
+            "server",
+            &[
+                ("port", u64::from(port)),
+                ("http", 1),
+                ("ready_delay_ms", 300),
+            ],
+            Readiness::Health {
+                host: "127.0.0.1".to_string(),
+                port,
+                path: "/health".to_string(),
+                timeout: Duration::from_secs(20),
+            },
+        );
+
+        let begin = Instant::now();
+        let (result, events) = harness.launch(&[server]);
+        let mut services = result.unwrap();
+        assert!(begin.elapsed() >= Duration::from_millis(300));
+        assert!(events.contains(&"ready server".to_string()));
+        assert_eq!(localhttp::get("127.0.0.1", port, "/health").unwrap(), "ok");
+        stop_all(&mut services);
+    }
+
+    /// 已建立连接的客户端占着本地端口，但没有程序在该端口上监听，与刚退出的进程留下的连接相同。
+    fn lingering_connection() -> (TcpListener, TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (accepted, _) = listener.accept().unwrap();
+        (listener, client, accepted)
+    }
+
+    #[test]
+    fn listeners_fail_fast_and_lingering_ports_are_waited_for() {
+        let (listener, client, _accepted) = lingering_connection();
+        let listening = listener.local_addr().unwrap().port();
+        let lingering = client.local_addr().unwrap().port();
+        assert_eq!(port_state(listening), PortState::Listening);
+        assert_eq!(port_state(lingering), PortState::Lingering);
+
+        let begin = Instant::now();
+        let err = wait_for_ports(&[listening], Duration::from_secs(10), |_, _| {}).unwrap_err();
+        assert!(
+            begin.elapsed() < Duration::from_secs(3),
+            "{:?}",
+            begin.elapsed()
+        );
+        assert!(err.contains("被占用"), "{err}");
+        let mut waits = Vec::new();
+        let err = wait_for_ports(&[lingering], Duration::from_secs(1), |port, remaining| {
+            waits.push((port, remaining));
+        })
+        .unwrap_err();
+        assert!(err.contains("1 秒内仍未释放"), "{err}");
+        assert!(!waits.is_empty());
+        assert!(waits
+            .iter()
+            .all(|(port, remaining)| *port == lingering && *remaining <= Duration::from_secs(1)));
+        assert!(err.contains(PORT_GRACE_KEY), "{err}");
+
+        assert_eq!(port_grace(&HashMap::new()), PORT_GRACE);
+        let envs = HashMap::from([(PORT_GRACE_KEY.to_string(), "0".to_string())]);
+        assert_eq!(port_grace(&envs), Duration::ZERO);
+    }
+
+    #[test]
+    fn launch_waits_for_a_port_released_shortly_after_relaunch() {
+        let mut harness = Harness::new("lingering");
+        harness
+            .envs
+            .insert(PORT_GRACE_KEY.to_string(), "10".to_string());
+        let (_listener, client, accepted) = lingering_connection();
+        let port = client.local_addr().unwrap().port();
+        let spec = harness.spec(
+            "server",
+            &[("port", u64::from(port))],
+            http(port, Duration::from_secs(20)),
+        );
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            // 对端先关闭，客户端一侧随后关闭时不进入 TIME_WAIT，端口立即释放
+            drop(accepted);
+            thread::sleep(Duration::from_millis(50));
+            drop(client);
+        });
+        let begin = Instant::now();
+        let (result, events) = harness.launch(&[spec]);
+        releaser.join().unwrap();
+        let mut services = result.unwrap();
+        assert!(begin.elapsed() >= Duration::from_millis(300));
+        assert!(events.contains(&"ready server".to_string()));
+        assert!(is_listening(port));
+        stop_all(&mut services);
+    }
+
+    #[test]
+    fn readiness_polls_fast_first_then_backs_off() {
+        let mut poll = Backoff::new(Duration::from_millis(100), Duration::from_secs(1));
+        let intervals: Vec<u64> = [0, 900, 1_000, 1_200, 1_600, 2_400, 3_400, 9_000]
+            .map(|ms| poll.interval_after(Duration::from_millis(ms)).as_millis() as u64)
+            .to_vec();
+        assert_eq!(intervals, [100, 100, 200, 400, 800, 1_000, 1_000, 1_000]);
+        poll.reset();
+        assert_eq!(poll.next_interval(), Duration::from_millis(100));
+
+        let envs = HashMap::from([
+            (READY_POLL_MIN_KEY.to_string(), "20".to_string()),
+            (READY_POLL_MAX_KEY.to_string(), "oops".to_string()),
+        ]);
+        let mut tuned = Backoff::from_env(&envs);
+        assert_eq!(
+            tuned.interval_after(Duration::ZERO),
+            Duration::from_millis(20)
+        );
+        assert_eq!(
+            tuned.interval_after(Duration::from_secs(60)),
+            Duration::from_millis(40)
+        );
+        assert_eq!(tuned.ceiling, READY_POLL_MAX);
+    }
+
+    #[test]
+    fn service_ready_shortly_after_spawn_is_detected_promptly() {
+        let harness = Harness::new("prompt");
+        let spec = harness.spec("worker", &[], Readiness::StaysRunning(Duration::ZERO));
+        let (mut child, output) = spawn_process(
+            &spec.program,
+            &spec.workdir,
+            &harness.envs,
+            &spec.args,
+            &spec.name,
+            &harness.logs_dir(),
+        )
+        .unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let begin = Instant::now();
+        let waited = runtime.block_on(wait_until(
+            &mut child,
+            &output,
+            Duration::from_secs(20),
+            Backoff::from_env(&harness.envs),
+            "服务 worker",
+            &CancelToken::default(),
+            || std::future::ready(begin.elapsed() >= Duration::from_millis(300)),
+        ));
+        let elapsed = begin.elapsed();
+        let _ = child.kill();
+        let _ = child.wait();
+        waited.unwrap();
+        assert!(elapsed < Duration::from_millis(450), "{elapsed:?}");
+    }
+
+    #[test]
+    fn parallel_launch_starts_a_level_together() {
+        let harness = Harness::new("parallel");
+        let [first, second] = free_ports();
+        let specs = [
+            harness.spec(
+                "runner",
+                &[],
+                Readiness::StaysRunning(Duration::from_millis(100)),
+            ),
+            depends_on(
+                harness.spec(
+                    "server",
+                    &[("port", u64::from(first)), ("ready_delay_ms", 1_000)],
+                    http(first, Duration::from_secs(20)),
+                ),
+                &["runner"],
+            ),
+            depends_on(
+                harness.spec(
+                    "batch",
+                    &[("port", u64::from(second)), ("ready_delay_ms", 1_000)],
+                    http(second, Duration::from_secs(20)),
+                ),
+                &["runner"],
+            ),
+        ];
+
+        let begin = Instant::now();
+        let (result, events) = harness.launch_with(&specs, true);
+        let mut services = result.unwrap();
+        assert!(
+            begin.elapsed() < Duration::from_millis(1_900),
+            "{:?}",
+            begin.elapsed()
+        );
+        assert_eq!(services.len(), 3);
+        let position = |event: &str| events.iter().position(|e| e == event).unwrap();
+        assert!(position("ready runner") < position("spawning server"));
+        assert!(position("spawned batch") < position("ready server"));
+        assert!(position("spawned server") < position("ready batch"));
+        stop_all(&mut services);
+    }
+
+    #[test]
+    fn happy_path_starts_services_in_order() {
+        let harness = Harness::new("happy");
+        let [port] = free_ports();
+        let specs = [
+            harness.spec(
+                "runner",
+                &[],
+                Readiness::StaysRunning(Duration::from_millis(300)),
+            ),
+            harness.spec(
+                "server",
+                &[("port", u64::from(port)), ("ready_delay_ms", 300)],
+                http(port, Duration::from_secs(20)),
+            ),
+        ];
+
+        let (result, events) = harness.launch(&specs);
+        let mut children = result.unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(
+            events,
+            vec![
+                "spawning runner",
+                "spawned runner",
+                "ready runner",
+                "spawning server",
+                "spawned server",
+                "ready server",
+            ]
+        );
+        assert!(is_listening(port));
+        let stdout = fs::read_to_string(harness.logs_dir().join("server.stdout.log")).unwrap();
+        assert!(stdout.contains("fake service starting"));
+
+        stop_all(&mut children);
+    }
+
+    #[test]
+    fn service_exiting_before_ready_reports_stderr_tail() {
+        let harness = Harness::new("early-exit");
+        let [port] = free_ports();
+        let specs = [harness.spec(
+            "server",
+            &[
+                ("exit_after_ms", 100),
+                ("exit_code", 3),
+                ("ready_delay_ms", 0),
+            ],
+            http(port, Duration::from_secs(20)),
+        )];
+
+        let (result, events) = harness.launch(&specs);
+        let err = result.unwrap_err();
+        assert!(err.contains("进程 server 已退出"), "{err}");
+        assert!(err.contains("fake stderr: exiting with 3"), "{err}");
+        assert!(!events.contains(&"ready server".to_string()));
+    }
+
+    #[test]
+    fn readiness_timeout_stops_already_started_services() {
+        let harness = Harness::new("timeout");
+        let [first_port, slow_port] = free_ports();
+        let specs = [
+            harness.spec(
+                "batch",
+                &[("port", u64::from(first_port))],
+                http(first_port, Duration::from_secs(20)),
+            ),
+            harness.spec(
+                "updater",
+                &[("port", u64::from(slow_port)), ("ready_delay_ms", 30_000)],
+                http(slow_port, Duration::from_secs(1)),
+            ),
+        ];
+
+        let (result, events) = harness.launch(&specs);
+        let err = result.unwrap_err();
+        assert!(
+            err.contains(&format!("等待服务 127.0.0.1:{slow_port} 超时")),
+            "{err}"
+        );
+        // 假服务在 sleep，没有 CPU 与日志活动
+        assert!(err.contains("可能已卡住"), "{err}");
+        assert!(err.contains("fake stderr: starting"), "{err}");
+        assert!(events.contains(&"ready batch".to_string()));
+        // 已就绪的 batch 也被一并停止
+        assert!(!is_listening(first_port));
+    }
+
+    #[test]
+    fn port_conflict_fails_before_spawning() {
+        let harness = Harness::new("conflict");
+        let occupied = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = occupied.local_addr().unwrap().port();
+        let specs = [harness.spec(
+            "server",
+            &[("port", u64::from(port))],
+            http(port, Duration::from_secs(5)),
+        )];
+
+        let (result, events) = harness.launch(&specs);
+        let err = result.unwrap_err();
+        assert!(err.contains(&format!("端口 {port} 被占用")), "{err}");
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn stop_all_kills_every_service() {
+        let harness = Harness::new("shutdown");
+        let ports = free_ports::<2>();
+        let specs = [
+            harness.spec(
+                "server",
+                &[("port", u64::from(ports[0]))],
+                http(ports[0], Duration::from_secs(20)),
+            ),
+            harness.spec(
+                "batch",
+                &[("port", u64::from(ports[1]))],
+                http(ports[1], Duration::from_secs(20)),
+            ),
+        ];
+
+        let (result, _) = harness.launch(&specs);
+        let mut children = result.unwrap();
+        assert!(ports.iter().all(|port| is_listening(*port)));
+
+        stop_all(&mut children);
+        assert!(children.is_empty());
+        assert!(ports.iter().all(|port| !is_listening(*port)));
+    }
+}
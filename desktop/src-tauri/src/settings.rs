@@ -0,0 +1,528 @@
+//! 桌面壳自身的持久化设置（与传给后端的 runtime.env 分开）。
+//!
+//! 保存在 `<data_dir>/desktop-settings.json`，写入时先写临时文件再改名，避免断电留下半个文件。
+//! 文件带版本号，读取时逐级迁移到当前版本；无法解析的文件会备份后按默认值重建，不影响启动。
+//! 来自更新版本的文件（回退到旧版本后）不算损坏：只读取认识的设置项，其余字段与版本号在保存时原样写回。
+//! 安全模式下不读写该文件，使用默认值且拒绝修改（见 safemode.rs）。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::pathgrant::PathGrant;
+use crate::{display, fsutil, runtime, safemode};
+
+const SETTINGS_FILE: &str = "desktop-settings.json";
+pub const CURRENT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DesktopSettings {
+    pub version: u32,
+    /// WebView 硬件加速，修改后需重启生效。
+    pub gpu_acceleration: bool,
+    /// 服务模式：不创建主窗口，仅保留托盘，修改后需重启生效。
+    pub service_mode: bool,
+    /// 界面缩放（与 WebView 的 DPI 换算无关），1.0 为原始大小。
+    pub ui_scale: f64,
+    /// 按显示器名称覆盖 ui_scale。
+    pub monitor_scales: BTreeMap<String, f64>,
+    /// 不再提示数据目录位于同步盘或网络共享。
+    pub suppress_data_dir_warning: bool,
+    /// 主窗口隐藏或失去焦点多少分钟后进入节能模式，0 表示不进入。
+    pub idle_after_minutes: u32,
+    /// 关闭主窗口（含 macOS 的 Cmd+W）时隐藏到托盘；为 false 时直接退出应用。
+    pub close_to_tray: bool,
+    /// 托盘提示中显示实时上传/下载速度。
+    pub tray_stats: bool,
+    /// 托盘速度的刷新间隔（秒）。
+    pub tray_stats_interval_secs: u32,
+    /// 启动时及每天检查一次本机时钟偏差。
+    pub clock_check: bool,
+    /// 时钟检查使用的 NTP 服务器。
+    pub clock_check_server: String,
+    /// 用户授权后端读取的数据目录以外的目录，见 pathgrant.rs。
+    pub allowed_paths: Vec<PathGrant>,
+    /// 按需启动：启动应用时不启动服务，首次打开窗口时再启动，修改后需重启生效。
+    pub start_on_demand: bool,
+    /// 按需启动时，主窗口隐藏多少分钟后停止服务，0 表示不停止。
+    pub dormant_after_minutes: u32,
+}
+
+impl Default for DesktopSettings {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            gpu_acceleration: true,
+            service_mode: false,
+            ui_scale: 1.0,
+            monitor_scales: BTreeMap::new(),
+            suppress_data_dir_warning: false,
+            idle_after_minutes: 10,
+            close_to_tray: true,
+            tray_stats: false,
+            tray_stats_interval_secs: 15,
+            clock_check: false,
+            clock_check_server: "pool.ntp.org".to_string(),
+            allowed_paths: Vec::new(),
+            start_on_demand: false,
+            dormant_after_minutes: 0,
+        }
+    }
+}
+
+/// 托管在 Tauri state 中的设置存储。
+pub struct SettingsStore {
+    /// 安全模式下为 None：只用默认值，不落盘。
+    path: Option<PathBuf>,
+    current: Mutex<DesktopSettings>,
+    /// 更新版本写入、当前版本不认识的字段，保存时原样写回。
+    unknown: Map<String, Value>,
+}
+
+/// 从文件读取的设置。
+struct Loaded {
+    settings: DesktopSettings,
+    unknown: Map<String, Value>,
+}
+
+impl SettingsStore {
+    pub fn open(data_dir: &Path) -> (Self, Option<String>) {
+        let path = data_dir.join(SETTINGS_FILE);
+        let (loaded, warning) = load(&path);
+        (
+            Self {
+                path: Some(path),
+                current: Mutex::new(loaded.settings),
+                unknown: loaded.unknown,
+            },
+            warning,
+        )
+    }
+
+    fn defaults() -> Self {
+        Self {
+            path: None,
+            current: Mutex::new(DesktopSettings::default()),
+            unknown: Map::new(),
+        }
+    }
+
+    pub fn get(&self) -> DesktopSettings {
+        self.current
+            .lock()
+            .map(|settings| settings.clone())
+            .unwrap_or_default()
+    }
+
+    /// 修改并立即落盘；写入失败时内存中的值保持不变。
+    pub fn update(
+        &self,
+        change: impl FnOnce(&mut DesktopSettings),
+    ) -> Result<DesktopSettings, String> {
+        self.try_update(|settings| {
+            change(settings);
+            Ok(())
+        })
+    }
+
+    fn try_update(
+        &self,
+        change: impl FnOnce(&mut DesktopSettings) -> Result<(), String>,
+    ) -> Result<DesktopSettings, String> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| "安全模式下使用默认设置，不能修改；正常重启后再修改".to_string())?;
+        let mut current = self
+            .current
+            .lock()
+            .map_err(|_| "设置存储不可用".to_string())?;
+        let mut next = current.clone();
+        change(&mut next)?;
+        save(path, &next, &self.unknown)?;
+        *current = next.clone();
+        Ok(next)
+    }
+
+    pub fn get_value(&self, key: &str) -> Result<Value, String> {
+        let settings =
+            serde_json::to_value(self.get()).map_err(|e| format!("读取设置失败: {e}"))?;
+        settings
+            .get(key)
+            .filter(|_| key != "version")
+            .cloned()
+            .ok_or_else(|| format!("未知的设置项: {key}"))
+    }
+
+    pub fn set_value(&self, key: &str, value: Value) -> Result<(), String> {
+        validate(key, &value)?;
+        self.try_update(|settings| apply_value(settings, key, value))
+            .map(|_| ())
+    }
+}
+
+/// 在 setup 中尽早调用，以便创建窗口前就能读取设置。
+pub fn init(app: &tauri::App) -> Result<(), String> {
+    if safemode::is_active(app.handle()) {
+        app.manage(SettingsStore::defaults());
+        return Ok(());
+    }
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("解析应用数据目录失败: {e}"))?;
+    let (store, warning) = SettingsStore::open(&data_dir);
+    if let Some(warning) = warning {
+        runtime::shell_log(app.handle(), &format!("[WARN] {warning}"));
+    }
+    app.manage(store);
+    Ok(())
+}
+
+/// 创建 Tauri 运行时之前只读地读取 `data_dir` 中的设置，文件缺失或损坏时返回默认值（由之后的 [`init`] 处理）。
+pub fn read_in(data_dir: &Path) -> DesktopSettings {
+    fs::read_to_string(data_dir.join(SETTINGS_FILE))
+        .ok()
+        .and_then(|content| parse(&content).ok())
+        .map(|loaded| loaded.settings)
+        .unwrap_or_default()
+}
+
+/// 当前设置；store 尚未初始化时返回默认值。
+pub fn current(app: &AppHandle) -> DesktopSettings {
+    app.try_state::<SettingsStore>()
+        .map(|store| store.get())
+        .unwrap_or_default()
+}
+
+pub fn update(
+    app: &AppHandle,
+    change: impl FnOnce(&mut DesktopSettings),
+) -> Result<DesktopSettings, String> {
+    let store = app
+        .try_state::<SettingsStore>()
+        .ok_or_else(|| "设置存储尚未初始化".to_string())?;
+    store.update(change)
+}
+
+/// 逐项校验前端传入的值；新增设置项时在这里补充规则。
+fn validate(key: &str, value: &Value) -> Result<(), String> {
+    match key {
+        "version" => Err("version 不可修改".to_string()),
+        "gpu_acceleration"
+        | "service_mode"
+        | "suppress_data_dir_warning"
+        | "close_to_tray"
+        | "tray_stats"
+        | "clock_check"
+        | "start_on_demand"
+            if value.is_boolean() =>
+        {
+            Ok(())
+        }
+        "gpu_acceleration"
+        | "service_mode"
+        | "suppress_data_dir_warning"
+        | "close_to_tray"
+        | "tray_stats"
+        | "clock_check"
+        | "start_on_demand" => Err(format!("{key} 需要布尔值")),
+        "clock_check_server" if value.as_str().is_some_and(is_valid_host) => Ok(()),
+        "clock_check_server" => Err(format!("{key} 需要主机名或 IP 地址")),
+        "tray_stats_interval_secs"
+            if value.as_u64().is_some_and(|secs| (5..=600).contains(&secs)) =>
+        {
+            Ok(())
+        }
+        "tray_stats_interval_secs" => Err(format!("{key} 需要 5 到 600 之间的整数")),
+        "idle_after_minutes" if value.as_u64().is_some_and(|minutes| minutes <= 24 * 60) => Ok(()),
+        "idle_after_minutes" => Err(format!("{key} 需要 0 到 1440 之间的整数")),
+        "dormant_after_minutes" if value.as_u64().is_some_and(|minutes| minutes <= 7 * 24 * 60) => {
+            Ok(())
+        }
+        "dormant_after_minutes" => Err(format!("{key} 需要 0 到 10080 之间的整数")),
+        "ui_scale" if value.as_f64().is_some_and(display::is_valid_scale) => Ok(()),
+        "ui_scale" => Err(format!(
+            "{key} 需要 {} 到 {} 之间的数字",
+            display::MIN_UI_SCALE,
+            display::MAX_UI_SCALE
+        )),
+        "allowed_paths" => Err(format!("{key} 只能通过授权确认框修改")),
+        "monitor_scales" => {
+            let valid = value.as_object().is_some_and(|scales| {
+                scales
+                    .values()
+                    .all(|scale| scale.as_f64().is_some_and(display::is_valid_scale))
+            });
+            if valid {
+                Ok(())
+            } else {
+                Err(format!("{key} 需要“显示器名称 → 缩放比例”的对象"))
+            }
+        }
+        _ => Err(format!("未知的设置项: {key}")),
+    }
+}
+
+fn is_valid_host(host: &str) -> bool {
+    !host.is_empty()
+        && host.len() <= 253
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':'))
+}
+
+fn apply_value(settings: &mut DesktopSettings, key: &str, value: Value) -> Result<(), String> {
+    let mut raw = serde_json::to_value(&*settings).map_err(|e| format!("写入设置失败: {e}"))?;
+    if let Some(object) = raw.as_object_mut() {
+        object.insert(key.to_string(), value);
+    }
+    *settings = serde_json::from_value(raw).map_err(|e| format!("{key} 的取值无效: {e}"))?;
+    Ok(())
+}
+
+fn load(path: &Path) -> (Loaded, Option<String>) {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return (Loaded::defaults(), None),
+    };
+
+    match parse(&content) {
+        Ok(loaded) if loaded.settings.version > CURRENT_VERSION => {
+            let warning = format!(
+                "桌面设置文件来自更新的版本（{} 高于当前支持的 {CURRENT_VERSION}），只读取当前版本认识的设置项，其余内容保留",
+                loaded.settings.version
+            );
+            (loaded, Some(warning))
+        }
+        Ok(loaded) => (loaded, None),
+        Err(err) => {
+            let backup = backup_corrupt(path);
+            let defaults = Loaded::defaults();
+            let _ = save(path, &defaults.settings, &defaults.unknown);
+            let warning = match backup {
+                Some(backup) => format!(
+                    "桌面设置文件损坏（{err}），已备份到 {} 并恢复默认设置",
+                    backup.display()
+                ),
+                None => format!("桌面设置文件损坏（{err}），已恢复默认设置"),
+            };
+            (defaults, Some(warning))
+        }
+    }
+}
+
+impl Loaded {
+    fn defaults() -> Self {
+        Self {
+            settings: DesktopSettings::default(),
+            unknown: Map::new(),
+        }
+    }
+}
+
+fn parse(content: &str) -> Result<Loaded, String> {
+    let raw: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let Value::Object(object) = raw else {
+        return Err("顶层不是 JSON 对象".to_string());
+    };
+    let version = object.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version > CURRENT_VERSION {
+        return Ok(load_known_fields(object, version));
+    }
+
+    let migrated = migrate(Value::Object(object), version);
+    let settings = serde_json::from_value(migrated).map_err(|e| e.to_string())?;
+    Ok(Loaded {
+        settings,
+        unknown: Map::new(),
+    })
+}
+
+/// 逐项读取更新版本文件中认识的设置项，取值无法识别的项使用默认值。
+fn load_known_fields(object: Map<String, Value>, version: u32) -> Loaded {
+    let known = match serde_json::to_value(DesktopSettings::default()) {
+        Ok(Value::Object(known)) => known,
+        _ => Map::new(),
+    };
+    let mut settings = DesktopSettings::default();
+    let mut unknown = Map::new();
+    for (key, value) in object {
+        if key == "version" {
+            continue;
+        }
+        if known.contains_key(&key) {
+            let _ = apply_value(&mut settings, &key, value);
+        } else {
+            unknown.insert(key, value);
+        }
+    }
+    settings.version = version;
+    Loaded { settings, unknown }
+}
+
+/// 从旧版本逐级迁移到 CURRENT_VERSION。
+fn migrate(mut raw: Value, from: u32) -> Value {
+    if from < 1 {
+        // v0：没有 version 字段的早期文件，字段含义与 v1 相同
+        if let Some(object) = raw.as_object_mut() {
+            object.insert("version".to_string(), Value::from(1));
+        }
+    }
+    raw
+}
+
+fn save(
+    path: &Path,
+    settings: &DesktopSettings,
+    unknown: &Map<String, Value>,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建设置目录失败: {e}"))?;
+    }
+    let mut raw = serde_json::to_value(settings).map_err(|e| format!("序列化设置失败: {e}"))?;
+    if let Some(object) = raw.as_object_mut() {
+        for (key, value) in unknown {
+            object.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+    let content = serde_json::to_string_pretty(&raw).map_err(|e| format!("序列化设置失败: {e}"))?;
+    fsutil::atomic_write(path, content)
+        .map_err(|e| format!("保存设置失败 ({}): {e}", path.display()))
+}
+
+fn backup_corrupt(path: &Path) -> Option<PathBuf> {
+    let stamp = runtime::format_utc_timestamp(SystemTime::now()).replace(':', "-");
+    let backup = path.with_file_name(format!("{SETTINGS_FILE}.corrupt-{stamp}"));
+    fs::rename(path, &backup).ok().map(|_| backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn missing_file_yields_defaults() {
+        let dir = temp_dir("missing");
+        let (store, warning) = SettingsStore::open(&dir);
+        assert!(warning.is_none());
+        assert_eq!(store.get(), DesktopSettings::default());
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = temp_dir("round-trip");
+        let (store, _) = SettingsStore::open(&dir);
+        store
+            .set_value("gpu_acceleration", Value::Bool(false))
+            .unwrap();
+
+        let (reopened, warning) = SettingsStore::open(&dir);
+        assert!(warning.is_none());
+        assert!(!reopened.get().gpu_acceleration);
+        assert_eq!(
+            reopened.get_value("gpu_acceleration").unwrap(),
+            Value::Bool(false)
+        );
+        assert!(!dir.join("desktop-settings.json.tmp").exists());
+    }
+
+    #[test]
+    fn unversioned_file_is_migrated() {
+        let dir = temp_dir("migrate");
+        fs::write(dir.join(SETTINGS_FILE), r#"{"gpu_acceleration": false}"#).unwrap();
+
+        let (store, warning) = SettingsStore::open(&dir);
+        assert!(warning.is_none());
+        let settings = store.get();
+        assert_eq!(settings.version, CURRENT_VERSION);
+        assert!(!settings.gpu_acceleration);
+    }
+
+    #[test]
+    fn corrupt_file_is_backed_up_and_regenerated() {
+        let dir = temp_dir("corrupt");
+        fs::write(dir.join(SETTINGS_FILE), "{not json").unwrap();
+
+        let (store, warning) = SettingsStore::open(&dir);
+        assert!(warning.is_some());
+        assert_eq!(store.get(), DesktopSettings::default());
+
+        let names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        assert!(names
+            .iter()
+            .any(|name| name.starts_with("desktop-settings.json.corrupt-")));
+        assert!(parse(&fs::read_to_string(dir.join(SETTINGS_FILE)).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn newer_version_keeps_known_fields_and_the_file() {
+        let dir = temp_dir("newer");
+        fs::write(
+            dir.join(SETTINGS_FILE),
+            r#"{"version": 99, "gpu_acceleration": false, "ui_scale": "large", "future_option": {"on": true}}"#,
+        )
+        .unwrap();
+
+        let (store, warning) = SettingsStore::open(&dir);
+        assert!(warning.is_some());
+        let settings = store.get();
+        assert!(!settings.gpu_acceleration);
+        assert_eq!(settings.ui_scale, DesktopSettings::default().ui_scale);
+        let names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        assert!(!names.iter().any(|name| name.contains(".corrupt-")));
+
+        // 保存时保留版本号与不认识的字段
+        store.set_value("tray_stats", Value::Bool(true)).unwrap();
+        let saved: Value =
+            serde_json::from_str(&fs::read_to_string(dir.join(SETTINGS_FILE)).unwrap()).unwrap();
+        assert_eq!(saved["version"], 99);
+        assert_eq!(saved["future_option"], serde_json::json!({ "on": true }));
+        assert_eq!(saved["tray_stats"], true);
+        assert_eq!(saved["gpu_acceleration"], false);
+    }
+
+    #[test]
+    fn set_value_validates_keys_and_types() {
+        let dir = temp_dir("validate");
+        let (store, _) = SettingsStore::open(&dir);
+        assert!(store
+            .set_value("gpu_acceleration", Value::from("yes"))
+            .is_err());
+        assert!(store.set_value("version", Value::from(2)).is_err());
+        assert!(store.set_value("no_such_key", Value::Bool(true)).is_err());
+        assert!(store
+            .set_value("allowed_paths", serde_json::json!([]))
+            .is_err());
+        assert!(store.get_value("version").is_err());
+        assert!(store.set_value("ui_scale", Value::from(10.0)).is_err());
+        assert!(store
+            .set_value("monitor_scales", serde_json::json!({ "DELL U2720Q": 0.1 }))
+            .is_err());
+        assert!(store
+            .set_value("clock_check_server", Value::from("ntp.example.com/x"))
+            .is_err());
+        store
+            .set_value("clock_check_server", Value::from("ntp.aliyun.com"))
+            .unwrap();
+        store.set_value("ui_scale", Value::from(1.25)).unwrap();
+        assert_eq!(store.get().ui_scale, 1.25);
+        assert!(store.get().gpu_acceleration);
+    }
+}
@@ -0,0 +1,279 @@
+//! 导出/导入桌面壳自身的配置，便于迁移到新电脑。
+//!
+//! 导出文件是一个带版本号的 JSON：桌面设置、窗口状态（若有）以及 runtime.env 中的非敏感项。
+//! 密码、令牌等敏感键一律不导出，只在 `excluded_secrets` 中列出键名提醒用户手动填写。
+//! 导入时逐项校验，返回已应用与已跳过（附原因）的键。
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::commanderror::CommandError;
+use crate::settings::SettingsStore;
+use crate::{fsutil, runtime};
+
+pub const BUNDLE_FILE_NAME: &str = "ptnexus-desktop-settings.json";
+const SCHEMA_VERSION: u32 = 1;
+const WINDOW_STATE_FILE: &str = "window-state.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub schema_version: u32,
+    pub app_version: String,
+    pub exported_at: String,
+    pub desktop_settings: serde_json::Map<String, Value>,
+    #[serde(default)]
+    pub window_state: Option<Value>,
+    #[serde(default)]
+    pub runtime_env: BTreeMap<String, String>,
+    /// 未导出的敏感键，仅作提示。
+    #[serde(default)]
+    pub excluded_secrets: Vec<String>,
+    #[serde(default)]
+    pub note: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub applied: Vec<String>,
+    pub skipped: Vec<SkippedKey>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedKey {
+    pub key: String,
+    pub reason: String,
+}
+
+impl ImportReport {
+    fn skip(&mut self, key: impl Into<String>, reason: impl Into<String>) {
+        self.skipped.push(SkippedKey {
+            key: key.into(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// 按当前配置生成导出内容。
+pub fn build(app: &AppHandle) -> Result<SettingsBundle, String> {
+    let data_dir = data_dir(app)?;
+    let store = app
+        .try_state::<SettingsStore>()
+        .ok_or_else(|| "设置存储尚未初始化".to_string())?;
+
+    let desktop_settings = match serde_json::to_value(store.get()) {
+        Ok(Value::Object(map)) => map,
+        Ok(_) => serde_json::Map::new(),
+        Err(e) => return Err(format!("读取设置失败: {e}")),
+    };
+    let window_state = fs::read_to_string(data_dir.join(WINDOW_STATE_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let (runtime_env, excluded_secrets) = split_secrets(read_runtime_env(&data_dir)?);
+
+    Ok(SettingsBundle {
+        schema_version: SCHEMA_VERSION,
+        app_version: app.package_info().version.to_string(),
+        exported_at: runtime::format_utc_timestamp(SystemTime::now()),
+        desktop_settings,
+        window_state,
+        runtime_env,
+        note: if excluded_secrets.is_empty() {
+            String::new()
+        } else {
+            "excluded_secrets 中的键包含密码等敏感信息，未导出，请在新电脑的 runtime.env 中重新填写。"
+                .to_string()
+        },
+        excluded_secrets,
+    })
+}
+
+/// 通过保存对话框写出导出文件，返回保存路径；用户取消时返回 None。
+pub fn export(app: &AppHandle) -> Result<Option<PathBuf>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let bundle = build(app)?;
+    let Some(path) = app
+        .dialog()
+        .file()
+        .set_file_name(BUNDLE_FILE_NAME)
+        .add_filter("JSON", &["json"])
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = path
+        .into_path()
+        .map_err(|e| format!("无法使用所选路径: {e}"))?;
+    write(&bundle, &path)?;
+    Ok(Some(path))
+}
+
+pub fn write(bundle: &SettingsBundle, path: &Path) -> Result<(), String> {
+    let content =
+        serde_json::to_string_pretty(bundle).map_err(|e| format!("序列化设置失败: {e}"))?;
+    fsutil::atomic_write(path, content).map_err(|e| format!("导出失败 ({}): {e}", path.display()))
+}
+
+pub fn import(app: &AppHandle, path: &Path) -> Result<ImportReport, CommandError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| CommandError::io(format_args!("读取 {} 失败", path.display()), e))?;
+    let bundle = parse(&content).map_err(CommandError::invalid)?;
+    let data_dir = data_dir(app)?;
+    let store = app
+        .try_state::<SettingsStore>()
+        .ok_or_else(|| CommandError::not_ready("设置存储尚未初始化"))?;
+
+    let mut report = ImportReport::default();
+    for (key, value) in bundle.desktop_settings {
+        if key == "version" {
+            continue;
+        }
+        match store.set_value(&key, value) {
+            Ok(()) => report.applied.push(format!("desktop_settings.{key}")),
+            Err(err) => report.skip(format!("desktop_settings.{key}"), err),
+        }
+    }
+
+    if let Some(window_state) = bundle.window_state {
+        let path = data_dir.join(WINDOW_STATE_FILE);
+        let written = serde_json::to_string_pretty(&window_state)
+            .map_err(|e| e.to_string())
+            .and_then(|content| fsutil::atomic_write(&path, content).map_err(|e| e.to_string()));
+        match written {
+            Ok(()) => report.applied.push("window_state".to_string()),
+            Err(err) => report.skip("window_state", format!("写入失败: {err}")),
+        }
+    }
+
+    let mut env_updates = Vec::new();
+    for (key, value) in &bundle.runtime_env {
+        let name = format!("runtime_env.{key}");
+        if let Err(reason) = check_env_entry(key, value) {
+            report.skip(name, reason);
+        } else {
+            env_updates.push((key.as_str(), Some(value.as_str())));
+            report.applied.push(name);
+        }
+    }
+    if !env_updates.is_empty() {
+        runtime::update_env_file(&data_dir.join("runtime.env"), &env_updates)?;
+    }
+
+    runtime::shell_log(
+        app,
+        &format!(
+            "[INFO] 已从 {} 导入桌面配置：应用 {} 项，跳过 {} 项",
+            path.display(),
+            report.applied.len(),
+            report.skipped.len()
+        ),
+    );
+    Ok(report)
+}
+
+fn parse(content: &str) -> Result<SettingsBundle, String> {
+    let raw: Value =
+        serde_json::from_str(content).map_err(|e| format!("不是有效的 JSON 文件: {e}"))?;
+    let version = raw
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| "不是 PT Nexus 桌面配置导出文件（缺少 schema_version）".to_string())?;
+    if version > u64::from(SCHEMA_VERSION) {
+        return Err(format!(
+            "导出文件版本 {version} 高于当前支持的 {SCHEMA_VERSION}，请先升级应用"
+        ));
+    }
+    serde_json::from_value(raw).map_err(|e| format!("导出文件格式错误: {e}"))
+}
+
+/// 导入的 runtime.env 项：敏感键、非法键名、在本机不存在的绝对路径都跳过。
+fn check_env_entry(key: &str, value: &str) -> Result<(), String> {
+    if runtime::is_secret_key(key) {
+        return Err("敏感项不通过导出文件导入".to_string());
+    }
+    let valid_key = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_key {
+        return Err("键名不合法".to_string());
+    }
+    if value.contains('\n') || value.contains('\r') {
+        return Err("取值不能包含换行".to_string());
+    }
+    let path = Path::new(value);
+    if path.is_absolute() && !path.exists() {
+        return Err(format!("路径 {value} 在本机不存在"));
+    }
+    Ok(())
+}
+
+fn split_secrets(env: HashMap<String, String>) -> (BTreeMap<String, String>, Vec<String>) {
+    let mut kept = BTreeMap::new();
+    let mut secrets = Vec::new();
+    for (key, value) in env {
+        if runtime::is_secret_key(&key) {
+            secrets.push(key);
+        } else {
+            kept.insert(key, value);
+        }
+    }
+    secrets.sort();
+    (kept, secrets)
+}
+
+fn read_runtime_env(data_dir: &Path) -> Result<HashMap<String, String>, String> {
+    let mut env = HashMap::new();
+    runtime::merge_env_file(&mut env, &data_dir.join("runtime.env"))?;
+    Ok(env)
+}
+
+fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("解析应用数据目录失败: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_split_out() {
+        let env = HashMap::from([
+            ("DB_TYPE".to_string(), "mysql".to_string()),
+            ("MYSQL_PASSWORD".to_string(), "hunter2".to_string()),
+            ("API_TOKEN".to_string(), "abc".to_string()),
+        ]);
+        let (kept, secrets) = split_secrets(env);
+        assert_eq!(kept.keys().collect::<Vec<_>>(), vec!["DB_TYPE"]);
+        assert_eq!(secrets, vec!["API_TOKEN", "MYSQL_PASSWORD"]);
+    }
+
+    #[test]
+    fn env_entries_are_validated() {
+        assert!(check_env_entry("DB_TYPE", "mysql").is_ok());
+        assert!(check_env_entry("MYSQL_PASSWORD", "x").is_err());
+        assert!(check_env_entry("BAD KEY", "x").is_err());
+        assert!(check_env_entry("NOTE", "a\nb").is_err());
+        let missing = std::env::temp_dir()
+            .join("ptnexus-settingsexport-missing")
+            .join("bdinfo");
+        assert!(check_env_entry("PTNEXUS_BDINFO_PATH", &missing.to_string_lossy()).is_err());
+    }
+
+    #[test]
+    fn parse_rejects_foreign_or_newer_files() {
+        assert!(parse("{}").is_err());
+        assert!(parse(r#"{"schema_version": 99}"#).is_err());
+        let bundle = parse(
+            r#"{"schema_version":1,"app_version":"0.1.0","exported_at":"2024-01-01T00:00:00Z","desktop_settings":{"ui_scale":1.25}}"#,
+        )
+        .unwrap();
+        assert_eq!(bundle.desktop_settings["ui_scale"], 1.25);
+        assert!(bundle.runtime_env.is_empty());
+    }
+}
@@ -0,0 +1,341 @@
+//! 最近一次成功启动时的环境快照，启动失败时据此列出“自上次成功启动以来的变化”。
+//!
+//! 用了几个月突然启动失败时，最先要问的是“改了什么”。每次启动成功后把运行目录、各组件文件的哈希、
+//! runtime.env 内容的哈希、端口、应用版本与系统版本记入配置档目录下的 `last-good-startup.json`；
+//! 启动失败时重新观察一遍并与快照比较，在错误报告末尾附上变化列表，例如组件被更新或篡改、
+//! runtime.env 被修改、系统升级。只在本机记录，不上传。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::portconfig::{self, PortConfig};
+use crate::tasks::{self, Scope};
+use crate::{datadir, fsutil, paths, pyruntime, runtime};
+
+const SNAPSHOT_FILE: &str = "last-good-startup.json";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// 记录时间（Unix 秒）。
+    pub taken_at: u64,
+    pub app_version: String,
+    pub os_version: String,
+    /// 未找到运行目录时为 None。
+    pub runtime_root: Option<String>,
+    /// 组件文件（相对运行目录）→ SHA-256；不存在的文件不记录。
+    pub components: BTreeMap<String, String>,
+    /// runtime.env 内容的 SHA-256；文件不存在时为 None。
+    pub runtime_env_hash: Option<String>,
+    pub ports: PortConfig,
+}
+
+/// 启动成功后调用：在后台记录快照，组件较大时哈希需要一些时间。
+pub fn record_last_good(app: &AppHandle) {
+    tasks::spawn(app, "last-good-snapshot", Scope::Shell, |app, _| {
+        let Ok(profile) = datadir::active_profile(&app) else {
+            return;
+        };
+        let snapshot = observe(&app);
+        let written = serde_json::to_vec_pretty(&snapshot)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                fsutil::atomic_write(&profile.root.join(SNAPSHOT_FILE), json)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(err) = written {
+            runtime::shell_log(&app, &format!("[WARN] 记录启动快照失败: {err}"));
+        }
+    });
+}
+
+/// 启动失败时调用：与上次成功启动的快照比较，返回附加到错误报告的段落；没有快照时返回 None。
+pub fn changes_since_last_good(app: &AppHandle) -> Option<String> {
+    let profile = datadir::active_profile(app).ok()?;
+    let last: Snapshot = fs::read_to_string(profile.root.join(SNAPSHOT_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())?;
+    Some(render(&last, &observe(app)))
+}
+
+fn observe(app: &AppHandle) -> Snapshot {
+    let runtime_root = runtime::resolve_runtime_root(app).ok();
+    let env_file = datadir::active_profile(app)
+        .ok()
+        .map(|profile| profile.root.join("runtime.env"));
+    Snapshot {
+        taken_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        app_version: app.package_info().version.to_string(),
+        os_version: os_version(),
+        runtime_root: runtime_root
+            .as_ref()
+            .map(|root| root.to_string_lossy().to_string()),
+        components: runtime_root
+            .as_deref()
+            .map(hash_components)
+            .unwrap_or_default(),
+        runtime_env_hash: env_file.and_then(|path| pyruntime::sha256_file(&path).ok()),
+        ports: portconfig::current(app),
+    }
+}
+
+/// 各服务的入口文件；server 视打包方式是可执行文件或 Python 源码。
+fn component_paths() -> Vec<String> {
+    vec![
+        format!("server/{}", runtime::exe_name("server")),
+        "server/app.py".to_string(),
+        "server/background_runner.py".to_string(),
+        "server/dist/index.html".to_string(),
+        format!("batch/{}", runtime::exe_name("batch")),
+        format!("updater/{}", runtime::exe_name("updater")),
+    ]
+}
+
+pub(crate) fn hash_components(runtime_root: &Path) -> BTreeMap<String, String> {
+    component_paths()
+        .into_iter()
+        .filter_map(|relative| {
+            let hash =
+                pyruntime::sha256_file(&paths::extended(&runtime_root.join(&relative))).ok()?;
+            Some((relative, hash))
+        })
+        .collect()
+}
+
+/// 变化逐条列出；没有变化时也说明，提示问题可能出在外部（数据库、网络、杀毒软件等）。
+fn render(last: &Snapshot, current: &Snapshot) -> String {
+    let taken_at = runtime::format_utc_timestamp(UNIX_EPOCH + Duration::from_secs(last.taken_at));
+    let changes = diff(last, current);
+    let body = if changes.is_empty() {
+        "- 未发现变化：组件文件、runtime.env、端口、应用与系统版本均与当时相同，问题可能来自数据库、网络或安全软件等外部因素"
+            .to_string()
+    } else {
+        changes
+            .iter()
+            .map(|change| format!("- {change}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    format!("自上次成功启动以来的变化（上次成功启动于 {taken_at}）：\n{body}")
+}
+
+fn diff(last: &Snapshot, current: &Snapshot) -> Vec<String> {
+    let mut changes = Vec::new();
+    if last.app_version != current.app_version {
+        changes.push(format!(
+            "PT Nexus 已从 {} 变为 {}",
+            last.app_version, current.app_version
+        ));
+    }
+    if last.os_version != current.os_version {
+        changes.push(format!(
+            "系统已从 {} 变为 {}",
+            last.os_version, current.os_version
+        ));
+    }
+    match (&last.runtime_root, &current.runtime_root) {
+        (Some(before), Some(now)) if before != now => {
+            changes.push(format!("运行目录已从 {before} 变为 {now}"))
+        }
+        (Some(before), None) => changes.push(format!("找不到运行目录（上次为 {before}）")),
+        _ => {}
+    }
+    // 找不到运行目录时组件无从比较，上一条已经说明
+    if current.runtime_root.is_some() {
+        for relative in component_paths() {
+            match (
+                last.components.get(&relative),
+                current.components.get(&relative),
+            ) {
+                (Some(before), Some(now)) if before != now => {
+                    changes.push(format!("{relative} 哈希已变化（可能已更新或被篡改）"))
+                }
+                (Some(_), None) => changes.push(format!("{relative} 已不存在")),
+                (None, Some(_)) => changes.push(format!("{relative} 为新出现的文件")),
+                _ => {}
+            }
+        }
+    }
+    match (&last.runtime_env_hash, &current.runtime_env_hash) {
+        (Some(before), Some(now)) if before != now => {
+            changes.push("runtime.env 发生修改".to_string())
+        }
+        (Some(_), None) => changes.push("runtime.env 已被删除".to_string()),
+        (None, Some(_)) => changes.push("runtime.env 为新建的文件".to_string()),
+        _ => {}
+    }
+    let ports = [
+        ("SERVER_PORT", last.ports.server, current.ports.server),
+        ("BATCH_PORT", last.ports.batch, current.ports.batch),
+        ("UPDATER_PORT", last.ports.updater, current.ports.updater),
+    ];
+    for (key, before, now) in ports {
+        if before != now {
+            changes.push(format!("{key} 已从 {before} 改为 {now}"));
+        }
+    }
+    changes
+}
+
+#[cfg(target_os = "windows")]
+fn os_version() -> String {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    // 输出形如 “Microsoft Windows [版本 10.0.22631.3880]”，不同语言的系统措辞不同，只取版本号
+    let output = Command::new("cmd")
+        .args(["/c", "ver"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output();
+    let text = output
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default();
+    let Some(version) = text
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .find(|part| part.matches('.').count() >= 2)
+    else {
+        return "Windows".to_string();
+    };
+    let build = version
+        .split('.')
+        .nth(2)
+        .and_then(|build| build.parse::<u32>().ok());
+    // Windows 11 仍报告 10.0，以内部版本号 22000 区分
+    let name = match build {
+        Some(build) if build >= 22000 => "Win11",
+        Some(_) if version.starts_with("10.") => "Win10",
+        _ => "Windows",
+    };
+    format!("{name} ({version})")
+}
+
+#[cfg(target_os = "macos")]
+fn os_version() -> String {
+    let version = std::process::Command::new("sw_vers")
+        .arg("-productVersion")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+    format!("macOS {version}").trim().to_string()
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn os_version() -> String {
+    let name = fs::read_to_string("/etc/os-release")
+        .ok()
+        .and_then(|content| {
+            content.lines().find_map(|line| {
+                let value = line.strip_prefix("PRETTY_NAME=")?;
+                Some(value.trim_matches('"').to_string())
+            })
+        })
+        .unwrap_or_else(|| std::env::consts::OS.to_string());
+    match fs::read_to_string("/proc/sys/kernel/osrelease") {
+        Ok(kernel) => format!("{name} (内核 {})", kernel.trim()),
+        Err(_) => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            taken_at: 1_700_000_000,
+            app_version: "1.4.0".to_string(),
+            os_version: "Win10 (10.0.19045.4651)".to_string(),
+            runtime_root: Some(r"C:\Program Files\PT Nexus".to_string()),
+            components: component_paths()
+                .into_iter()
+                .filter(|path| !path.ends_with(".py"))
+                .map(|path| (path, "a".repeat(64)))
+                .collect(),
+            runtime_env_hash: Some("b".repeat(64)),
+            ports: PortConfig {
+                server: 5275,
+                batch: 5276,
+                updater: 5274,
+            },
+        }
+    }
+
+    #[test]
+    fn unchanged_environment_points_outside() {
+        let report = render(&snapshot(), &snapshot());
+        assert!(report
+            .starts_with("自上次成功启动以来的变化（上次成功启动于 2023-11-14T22:13:20Z）：\n"));
+        assert!(report.contains("未发现变化"));
+    }
+
+    #[test]
+    fn changes_are_listed_one_per_line() {
+        let last = snapshot();
+        let mut current = snapshot();
+        let server = format!("server/{}", runtime::exe_name("server"));
+        let batch = format!("batch/{}", runtime::exe_name("batch"));
+        current.os_version = "Win11 (10.0.22631.3880)".to_string();
+        current.components.insert(server.clone(), "c".repeat(64));
+        current.components.remove(&batch);
+        current
+            .components
+            .insert("server/app.py".to_string(), "d".repeat(64));
+        current.runtime_env_hash = Some("e".repeat(64));
+        current.ports.server = 5300;
+
+        let report = render(&last, &current);
+        let lines: Vec<&str> = report.lines().skip(1).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- 系统已从 Win10 (10.0.19045.4651) 变为 Win11 (10.0.22631.3880)".to_string(),
+                format!("- {server} 哈希已变化（可能已更新或被篡改）"),
+                "- server/app.py 为新出现的文件".to_string(),
+                format!("- {batch} 已不存在"),
+                "- runtime.env 发生修改".to_string(),
+                "- SERVER_PORT 已从 5275 改为 5300".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_runtime_root_is_reported_without_listing_components() {
+        let mut current = snapshot();
+        current.runtime_root = None;
+        current.components.clear();
+        current.runtime_env_hash = None;
+        assert_eq!(
+            diff(&snapshot(), &current),
+            vec![
+                r"找不到运行目录（上次为 C:\Program Files\PT Nexus）".to_string(),
+                "runtime.env 已被删除".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn components_are_hashed_relative_to_the_runtime_root() {
+        let root = temp_dir("components");
+        fs::create_dir_all(root.join("server").join("dist")).unwrap();
+        fs::write(
+            root.join("server").join("dist").join("index.html"),
+            b"<html></html>",
+        )
+        .unwrap();
+
+        let components = hash_components(&root);
+        assert_eq!(components.len(), 1);
+        assert_eq!(components["server/dist/index.html"].len(), 64);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,220 @@
+//! 桌面壳与 WebUI 之间的状态握手。
+//!
+//! WebUI 的连接状态指示器应每隔几秒调用一次 `shell_status` 命令，并据此决定是否显示
+//! “桌面端后台服务异常”横幅：`runtime_state` 为 `running` 时不显示；`degraded`、`failed`、`stopped`
+//! 时显示，`services` 中 `healthy` 为 false 的项即为异常的服务；`starting` 表示正在启动或重启，应显示加载状态。
+//! 异常服务的 `hung` 为 true 时进程仍在运行、只是端口无响应，桌面壳会自动重启一次（见 hangwatch.rs）。
+//! 该命令只读取内存中的状态（服务健康情况由健康监测线程定期更新），不做网络探测，可以频繁调用。
+//! `actions` 为可以直接执行的操作（打开日志、重试等），渲染为按钮并通过 `invoke_error_action` 执行。
+//! `dormant` 表示按需启动模式下服务尚未启动或已因长时间不用而停止（见 ondemand.rs），此时 WebUI 本身不会加载。
+//! `database_fallback` 不为空时表示配置的数据库不可用、本次运行临时使用 SQLite，WebUI 应常驻显示提醒横幅。
+//! `disk_space` 不为空时表示数据目录或已授权目录所在磁盘空间不足（见 diskwatch.rs），`level` 为 `critical`
+//! 时 WebUI 应常驻显示错误横幅，空间释放后自动消失；`batch_pause` 为 `paused` 表示批量任务确已暂停，
+//! `unsupported` / `failed` 表示任务仍在运行。
+//! `safe_mode` 不为空时表示本次以安全模式启动（见 safemode.rs），WebUI 应显示不显眼的提示横幅。
+//! 旧版 WebUI 使用的 `ping` 保留兼容，由本状态推导：`failed` / `stopped` 时返回状态名，否则返回 `pong`。
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::erroraction::ErrorAction;
+use crate::runtime::BootstrapError;
+use crate::webhook::{self, Trigger};
+use crate::{database, diskwatch, hangwatch, health, memwatch, runtime, safemode};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeState {
+    Starting,
+    Running,
+    /// 已启动，但有服务不可用。
+    Degraded,
+    Failed,
+    Stopped,
+    /// 按需启动：服务未运行，等待用户打开窗口。
+    Dormant,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ServiceStatus {
+    pub name: &'static str,
+    pub port: u16,
+    pub healthy: bool,
+    /// 因内存超限被回收（平滑重启）的次数，不含崩溃重启。
+    pub recycles: u32,
+    /// 进程仍在运行、端口却连续不可用（见 hangwatch.rs）。
+    pub hung: bool,
+    /// 因端口无响应被自动重启的次数。
+    pub hang_recoveries: u32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ShellStatus {
+    pub shell_version: String,
+    pub runtime_state: RuntimeState,
+    pub webui_url: String,
+    /// 最近一次启动耗时；尚未启动成功时为 None。
+    pub bootstrap_elapsed_ms: Option<u64>,
+    pub services: Vec<ServiceStatus>,
+    /// 启动失败时的原因（第一行）。
+    pub error: Option<String>,
+    /// 临时使用 SQLite 启动时，不可用的原数据库地址。
+    pub database_fallback: Option<String>,
+    /// 磁盘空间不足时的告警。
+    pub disk_space: Option<diskwatch::DiskStatus>,
+    /// 以安全模式启动时的原因。
+    pub safe_mode: Option<safemode::SafeModeStatus>,
+    /// 启动失败或有服务不可用时可以直接执行的操作，通过 `invoke_error_action` 执行（见 erroraction.rs）。
+    pub actions: Vec<ErrorAction>,
+}
+
+#[derive(Default)]
+pub struct ShellState {
+    phase: Mutex<Phase>,
+    down: Mutex<HashSet<&'static str>>,
+}
+
+#[derive(Clone, Default)]
+enum Phase {
+    #[default]
+    Starting,
+    Running,
+    Failed(String, Vec<ErrorAction>),
+    Stopped,
+    Dormant,
+}
+
+fn update(app: &AppHandle, phase: Phase) {
+    if let Some(state) = app.try_state::<ShellState>() {
+        if let Ok(mut current) = state.phase.lock() {
+            *current = phase;
+        }
+    }
+}
+
+pub fn mark_starting(app: &AppHandle) {
+    update(app, Phase::Starting);
+}
+
+pub fn mark_running(app: &AppHandle) {
+    update(app, Phase::Running);
+}
+
+pub fn mark_failed(app: &AppHandle, error: &BootstrapError) {
+    let message = error.to_string();
+    let reason = crate::journal::first_line(&message).to_string();
+    webhook::notify(app, Trigger::Failure, reason.clone());
+    update(app, Phase::Failed(reason, error.actions()));
+}
+
+pub fn mark_stopped(app: &AppHandle) {
+    update(app, Phase::Stopped);
+}
+
+pub fn mark_dormant(app: &AppHandle) {
+    update(app, Phase::Dormant);
+}
+
+pub fn is_dormant(app: &AppHandle) -> bool {
+    app.try_state::<ShellState>()
+        .and_then(|state| {
+            state
+                .phase
+                .lock()
+                .ok()
+                .map(|phase| matches!(*phase, Phase::Dormant))
+        })
+        .unwrap_or(false)
+}
+
+/// 服务已启动（含部分服务不可用），不包括启动中与失败。
+pub fn is_running(app: &AppHandle) -> bool {
+    app.try_state::<ShellState>()
+        .and_then(|state| {
+            state
+                .phase
+                .lock()
+                .ok()
+                .map(|phase| matches!(*phase, Phase::Running))
+        })
+        .unwrap_or(false)
+}
+
+/// 由健康监测线程在每轮探测后调用。
+pub fn set_down_services(app: &AppHandle, down: &HashSet<&'static str>) {
+    if let Some(state) = app.try_state::<ShellState>() {
+        if let Ok(mut current) = state.down.lock() {
+            current.clone_from(down);
+        }
+    }
+}
+
+pub fn snapshot(app: &AppHandle) -> ShellStatus {
+    let (phase, down) = app
+        .try_state::<ShellState>()
+        .map(|state| {
+            (
+                state.phase.lock().map(|p| p.clone()).unwrap_or_default(),
+                state.down.lock().map(|d| d.clone()).unwrap_or_default(),
+            )
+        })
+        .unwrap_or_default();
+    let context = app
+        .try_state::<runtime::RuntimeManager>()
+        .and_then(|rt| rt.context());
+    let webui_url = runtime::runtime_url(app);
+
+    let mut ports = vec![(
+        "updater",
+        webui_url
+            .port_or_known_default()
+            .unwrap_or(runtime::UPDATER_PORT),
+    )];
+    ports.extend(health::service_ports(app));
+    let running = matches!(phase, Phase::Running);
+    let services: Vec<ServiceStatus> = ports
+        .into_iter()
+        .map(|(name, port)| ServiceStatus {
+            name,
+            port,
+            healthy: running && !down.contains(name),
+            recycles: memwatch::recycle_count(app, name),
+            hung: running && hangwatch::is_hung(app, name),
+            hang_recoveries: hangwatch::recovery_count(app, name),
+        })
+        .collect();
+
+    let (runtime_state, error, actions) = match phase {
+        Phase::Starting => (RuntimeState::Starting, None, Vec::new()),
+        Phase::Running if down.is_empty() => (RuntimeState::Running, None, Vec::new()),
+        Phase::Running => {
+            let mut down: Vec<&str> = down.into_iter().collect();
+            down.sort_unstable();
+            let mut actions: Vec<ErrorAction> = down
+                .into_iter()
+                .map(|name| ErrorAction::OpenLog(name.to_string()))
+                .collect();
+            actions.push(ErrorAction::OpenLogsDir);
+            (RuntimeState::Degraded, None, actions)
+        }
+        Phase::Failed(error, actions) => (RuntimeState::Failed, Some(error), actions),
+        Phase::Stopped => (RuntimeState::Stopped, None, Vec::new()),
+        Phase::Dormant => (RuntimeState::Dormant, None, Vec::new()),
+    };
+
+    ShellStatus {
+        shell_version: app.package_info().version.to_string(),
+        runtime_state,
+        webui_url: webui_url.to_string(),
+        bootstrap_elapsed_ms: context.map(|ctx| ctx.bootstrap_ms),
+        services,
+        error,
+        database_fallback: database::sqlite_fallback(app),
+        disk_space: diskwatch::status(app),
+        safe_mode: safemode::status(app),
+        actions,
+    }
+}
@@ -0,0 +1,359 @@
+//! 桌面壳后台线程的登记与停止。
+//!
+//! 注入延时、健康监测、托盘统计等后台线程都通过 [`spawn`] 启动：每个线程有名称和取消标记，循环中用
+//! [`CancelToken::sleep`] 代替 `thread::sleep`，取消后在当前一轮结束时退出。同名任务再次启动时先取消旧的，
+//! 页面反复加载不会留下越来越多的注入线程。
+//!
+//! [`Scope::Runtime`] 的任务随运行时页面与服务存在，重启服务时取消并等待退出；[`Scope::Shell`] 的监测
+//! 跨重启运行，退出应用时与前者一起取消。[`Scope::Output`] 是服务输出的读取线程，在服务停止之后等待。
+//! 等待有时间上限，超时仍未退出的线程记录到 shell.log 后不再等待。
+//! 共用异步运行时上的任务（见 asyncrt.rs）同样登记在这里，等待中用 [`CancelToken::sleep_async`]。
+//! `get_background_tasks()` 返回当前仍在运行的任务，供诊断页查看。
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tokio::runtime::Handle;
+
+use crate::runtime;
+
+/// 取消后等待线程退出的总时长上限。
+pub const JOIN_TIMEOUT: Duration = Duration::from_secs(2);
+/// 可取消的等待每隔这么久检查一次取消标记。
+const CANCEL_CHECK: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// 跨服务重启运行，退出应用时停止。
+    Shell,
+    /// 重启服务、退出应用时停止。
+    Runtime,
+    /// 服务输出的读取线程（见 capture.rs），服务进程退出、管道关闭后自行结束，在停止服务之后等待。
+    Output,
+}
+
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// 等待 `duration`，期间被取消时提前返回 false；返回 true 表示可以继续。
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        loop {
+            if self.is_cancelled() {
+                return false;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            thread::sleep((deadline - now).min(CANCEL_CHECK));
+        }
+    }
+
+    /// 异步任务中的 [`sleep`](Self::sleep)，不占用运行时的工作线程。
+    pub async fn sleep_async(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        loop {
+            if self.is_cancelled() {
+                return false;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            tokio::time::sleep((deadline - now).min(CANCEL_CHECK)).await;
+        }
+    }
+}
+
+/// 任务的执行者：独立线程，或共用异步运行时上的任务。
+enum Worker {
+    Thread(JoinHandle<()>),
+    Async(tokio::task::JoinHandle<()>),
+}
+
+impl Worker {
+    fn is_finished(&self) -> bool {
+        match self {
+            Worker::Thread(handle) => handle.is_finished(),
+            Worker::Async(handle) => handle.is_finished(),
+        }
+    }
+
+    fn join(self) {
+        // 已结束的异步任务没有需要回收的东西
+        if let Worker::Thread(handle) = self {
+            let _ = handle.join();
+        }
+    }
+}
+
+struct Task {
+    id: u64,
+    name: String,
+    scope: Scope,
+    token: CancelToken,
+    started: Instant,
+    worker: Worker,
+}
+
+#[derive(Serialize)]
+pub struct TaskInfo {
+    pub name: String,
+    pub scope: Scope,
+    pub running_secs: u64,
+}
+
+#[derive(Default)]
+pub struct TaskRegistry {
+    tasks: Mutex<Vec<Task>>,
+    next_id: AtomicU64,
+}
+
+impl TaskRegistry {
+    /// 启动并登记后台线程；同名任务仍在运行时先取消它（不等待）。
+    pub fn spawn<F>(&self, name: &str, scope: Scope, run: F) -> CancelToken
+    where
+        F: FnOnce(CancelToken) + Send + 'static,
+    {
+        let token = CancelToken::default();
+        let thread_token = token.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("ptnexus-{name}"))
+            .spawn(move || run(thread_token));
+        if let Ok(handle) = spawned {
+            self.register(name, scope, &token, Worker::Thread(handle));
+        }
+        token
+    }
+
+    /// 与 [`spawn`](Self::spawn) 相同，但在 `runtime` 上运行异步任务。
+    pub fn spawn_async<F, Fut>(
+        &self,
+        runtime: &Handle,
+        name: &str,
+        scope: Scope,
+        run: F,
+    ) -> CancelToken
+    where
+        F: FnOnce(CancelToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let token = CancelToken::default();
+        let handle = runtime.spawn(run(token.clone()));
+        self.register(name, scope, &token, Worker::Async(handle));
+        token
+    }
+
+    fn register(&self, name: &str, scope: Scope, token: &CancelToken, worker: Worker) {
+        let Ok(mut tasks) = self.tasks.lock() else {
+            return;
+        };
+        tasks.retain(|task| !task.worker.is_finished());
+        for task in tasks.iter().filter(|task| task.name == name) {
+            task.token.cancel();
+        }
+        tasks.push(Task {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            name: name.to_string(),
+            scope,
+            token: token.clone(),
+            started: Instant::now(),
+            worker,
+        });
+    }
+
+    /// 取消 `scope` 的任务（None 为全部），在 `timeout` 内等待它们退出，返回超时仍在运行的任务名。
+    pub fn cancel(&self, scope: Option<Scope>, timeout: Duration) -> Vec<String> {
+        let cancelled: Vec<Task> = match self.tasks.lock() {
+            Ok(mut tasks) => {
+                let (cancelled, kept): (Vec<Task>, Vec<Task>) = tasks
+                    .drain(..)
+                    .partition(|task| scope.is_none_or(|scope| task.scope == scope));
+                *tasks = kept;
+                cancelled
+            }
+            Err(_) => return Vec::new(),
+        };
+        for task in &cancelled {
+            task.token.cancel();
+        }
+
+        let deadline = Instant::now() + timeout;
+        let mut pending = cancelled;
+        while !pending.is_empty() && Instant::now() < deadline {
+            let (finished, running): (Vec<Task>, Vec<Task>) = pending
+                .into_iter()
+                .partition(|task| task.worker.is_finished());
+            for task in finished {
+                task.worker.join();
+            }
+            pending = running;
+            if !pending.is_empty() {
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+        pending.into_iter().map(|task| task.name).collect()
+    }
+
+    /// 仍在运行的任务，按启动顺序排列。
+    pub fn snapshot(&self) -> Vec<TaskInfo> {
+        let Ok(mut tasks) = self.tasks.lock() else {
+            return Vec::new();
+        };
+        tasks.retain(|task| !task.worker.is_finished());
+        tasks.sort_by_key(|task| task.id);
+        tasks
+            .iter()
+            .map(|task| TaskInfo {
+                name: task.name.clone(),
+                scope: task.scope,
+                running_secs: task.started.elapsed().as_secs(),
+            })
+            .collect()
+    }
+}
+
+/// 通过应用中的 [`TaskRegistry`] 启动后台线程，线程拿到一份 AppHandle；
+/// 尚未创建登记表时（启动早期）直接启动，不登记。
+pub fn spawn<F>(app: &AppHandle, name: &str, scope: Scope, run: F) -> CancelToken
+where
+    F: FnOnce(AppHandle, CancelToken) + Send + 'static,
+{
+    let handle = app.clone();
+    let run = move |token| run(handle, token);
+    match app.try_state::<TaskRegistry>() {
+        Some(registry) => registry.spawn(name, scope, run),
+        None => {
+            let token = CancelToken::default();
+            let thread_token = token.clone();
+            thread::spawn(move || run(thread_token));
+            token
+        }
+    }
+}
+
+/// 取消并等待任务退出，超时仍在运行的记录到 shell.log。
+pub fn shutdown(app: &AppHandle, scope: Option<Scope>) {
+    let Some(registry) = app.try_state::<TaskRegistry>() else {
+        return;
+    };
+    let lingering = registry.cancel(scope, JOIN_TIMEOUT);
+    if !lingering.is_empty() {
+        runtime::shell_log(
+            app,
+            &format!(
+                "[WARN] 以下后台任务在 {} 秒内未退出: {}",
+                JOIN_TIMEOUT.as_secs(),
+                lingering.join(", ")
+            ),
+        );
+    }
+}
+
+pub fn list(app: &AppHandle) -> Vec<TaskInfo> {
+    app.try_state::<TaskRegistry>()
+        .map(|registry| registry.snapshot())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(token: CancelToken) {
+        while token.sleep(Duration::from_secs(5)) {}
+    }
+
+    /// 模拟启动 → 重启服务 → 退出：重启后任务数回到重启前，退出后为零。
+    #[test]
+    fn restart_cycle_returns_to_baseline() {
+        let registry = TaskRegistry::default();
+        registry.spawn("health", Scope::Shell, monitor);
+        registry.spawn("inject:overlay", Scope::Runtime, monitor);
+        registry.spawn("inject:heartbeat", Scope::Runtime, monitor);
+        let baseline = registry.snapshot().len();
+        assert_eq!(baseline, 3);
+
+        for _ in 0..3 {
+            assert!(registry
+                .cancel(Some(Scope::Runtime), JOIN_TIMEOUT)
+                .is_empty());
+            assert_eq!(registry.snapshot().len(), 1);
+            registry.spawn("inject:overlay", Scope::Runtime, monitor);
+            registry.spawn("inject:heartbeat", Scope::Runtime, monitor);
+            assert_eq!(registry.snapshot().len(), baseline);
+        }
+
+        assert!(registry.cancel(None, JOIN_TIMEOUT).is_empty());
+        assert!(registry.snapshot().is_empty());
+    }
+
+    #[test]
+    fn same_name_replaces_running_task() {
+        let registry = TaskRegistry::default();
+        let first = registry.spawn("inject:links", Scope::Runtime, monitor);
+        let second = registry.spawn("inject:links", Scope::Runtime, monitor);
+        assert!(first.is_cancelled() && !second.is_cancelled());
+        thread::sleep(CANCEL_CHECK * 3);
+        let names: Vec<String> = registry
+            .snapshot()
+            .into_iter()
+            .map(|task| task.name)
+            .collect();
+        assert_eq!(names, ["inject:links"]);
+        registry.cancel(None, JOIN_TIMEOUT);
+    }
+
+    #[test]
+    fn async_tasks_are_cancelled_with_their_scope() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_time()
+            .build()
+            .unwrap();
+        let registry = TaskRegistry::default();
+        registry.spawn("health", Scope::Shell, monitor);
+        let token = registry.spawn_async(
+            runtime.handle(),
+            "ready:server",
+            Scope::Runtime,
+            |token| async move { while token.sleep_async(Duration::from_secs(5)).await {} },
+        );
+        assert_eq!(registry.snapshot().len(), 2);
+
+        assert!(registry
+            .cancel(Some(Scope::Runtime), JOIN_TIMEOUT)
+            .is_empty());
+        assert!(token.is_cancelled());
+        assert_eq!(registry.snapshot().len(), 1);
+        registry.cancel(None, JOIN_TIMEOUT);
+    }
+
+    #[test]
+    fn stuck_task_is_reported_after_timeout() {
+        let registry = TaskRegistry::default();
+        registry.spawn("stuck", Scope::Shell, |_| {
+            thread::sleep(Duration::from_millis(500))
+        });
+        assert_eq!(
+            registry.cancel(None, Duration::from_millis(50)),
+            ["stuck".to_string()]
+        );
+    }
+}
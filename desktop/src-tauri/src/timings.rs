@@ -0,0 +1,120 @@
+//! 启动耗时统计：记录 bootstrap 各阶段耗时，写入 shell.log，并在数据目录保留最近若干次记录。
+//! 仅本地保存，不做任何上报。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::fsutil;
+use crate::runtime::{append_shell_log, format_utc_timestamp};
+
+const TIMINGS_FILE: &str = "startup-times.json";
+const MAX_RECORDS: usize = 20;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PhaseTiming {
+    pub name: String,
+    pub ms: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StartupRecord {
+    pub recorded_at: String,
+    pub app_version: String,
+    pub total_ms: u64,
+    pub phases: Vec<PhaseTiming>,
+}
+
+/// 按顺序累计各阶段耗时，每次 `mark` 结束上一个阶段。
+pub struct StartupTimer {
+    started: Instant,
+    phase_started: Instant,
+    phases: Vec<PhaseTiming>,
+}
+
+impl StartupTimer {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            phase_started: now,
+            phases: Vec::new(),
+        }
+    }
+
+    pub fn mark(&mut self, name: &str) {
+        let now = Instant::now();
+        self.phases.push(PhaseTiming {
+            name: name.to_string(),
+            ms: duration_ms(now - self.phase_started),
+        });
+        self.phase_started = now;
+    }
+
+    /// 写入汇总日志并追加到 startup-times.json，返回总耗时（毫秒）。
+    pub fn finish(self, app: &AppHandle, data_dir: &Path, logs_dir: &Path) -> u64 {
+        let record = StartupRecord {
+            recorded_at: format_utc_timestamp(SystemTime::now()),
+            app_version: app.package_info().version.to_string(),
+            total_ms: duration_ms(self.started.elapsed()),
+            phases: self.phases,
+        };
+        append_shell_log(logs_dir, &summary_line(&record));
+        let total_ms = record.total_ms;
+
+        let path = timings_path(data_dir);
+        let mut records = read_records(&path);
+        records.push(record);
+        if records.len() > MAX_RECORDS {
+            records.drain(..records.len() - MAX_RECORDS);
+        }
+        if let Ok(content) = serde_json::to_string_pretty(&records) {
+            let _ = fsutil::atomic_write(&path, content);
+        }
+        total_ms
+    }
+}
+
+/// 最近的启动耗时记录，按时间先后排列。
+pub fn last_startup_timings(app: &AppHandle) -> Result<Vec<StartupRecord>, String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("解析应用数据目录失败: {e}"))?;
+    Ok(read_records(&timings_path(&data_dir)))
+}
+
+fn timings_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(TIMINGS_FILE)
+}
+
+fn read_records(path: &Path) -> Vec<StartupRecord> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn summary_line(record: &StartupRecord) -> String {
+    let phases = record
+        .phases
+        .iter()
+        .map(|phase| format!("{} {}", phase.name, format_seconds(phase.ms)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "bootstrap done in {} ({phases})",
+        format_seconds(record.total_ms)
+    )
+}
+
+pub fn format_seconds(ms: u64) -> String {
+    format!("{:.1}s", ms as f64 / 1000.0)
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
@@ -0,0 +1,208 @@
+//! 系统托盘是否可用。
+//!
+//! Linux 上托盘图标通过 StatusNotifierItem 注册到会话总线上的托盘宿主。GNOME 等 Wayland 桌面默认没有宿主
+//! （需安装 AppIndicator 扩展），此时图标不会显示，“关闭到托盘”后窗口将无从找回。启动时向
+//! `org.kde.StatusNotifierWatcher` 查询是否有宿主注册；Wayland 下确认没有宿主时不创建托盘，
+//! 关闭主窗口改为退出应用。X11 下没有宿主时 libappindicator 会退回 XEmbed 托盘，仍然创建。
+//! 查询工具（gdbus / dbus-send）不存在或结果无法识别时按可用处理，保持原有行为。
+//!
+//! 在 runtime.env 或环境变量中设置 `PTNEXUS_DISABLE_TRAY=1` 可在任何平台上跳过托盘，关闭行为同样改为退出。
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::{runtime, settings};
+
+pub const DISABLE_TRAY_KEY: &str = "PTNEXUS_DISABLE_TRAY";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayAvailability {
+    Available,
+    /// 被 PTNEXUS_DISABLE_TRAY 关闭。
+    Disabled,
+    /// 会话中没有 StatusNotifier 托盘宿主。
+    NoHost,
+}
+
+impl TrayAvailability {
+    pub fn describe(self) -> String {
+        match self {
+            Self::Available => "系统托盘可用".to_string(),
+            Self::Disabled => {
+                format!("已通过 {DISABLE_TRAY_KEY} 关闭系统托盘，关闭主窗口将退出应用")
+            }
+            Self::NoHost => {
+                "未检测到 StatusNotifier 托盘宿主（Wayland 下需安装 AppIndicator 扩展），\
+                             不创建托盘，关闭主窗口将退出应用"
+                    .to_string()
+            }
+        }
+    }
+}
+
+/// 启动时检测的结果，整个进程期间不变。
+pub struct TrayState(pub TrayAvailability);
+
+pub fn detect(app: &AppHandle) -> TrayAvailability {
+    detect_with(
+        |key| runtime::read_runtime_setting(app, key),
+        probe_platform,
+    )
+}
+
+/// `setting` 读取 runtime.env 或环境变量中的设置；已关闭托盘时不再调用 `probe` 查询宿主。
+fn detect_with(
+    setting: impl Fn(&str) -> Option<String>,
+    probe: impl FnOnce() -> TrayAvailability,
+) -> TrayAvailability {
+    if setting(DISABLE_TRAY_KEY).is_some_and(|value| runtime::is_truthy(&value)) {
+        return TrayAvailability::Disabled;
+    }
+    probe()
+}
+
+fn probe_platform() -> TrayAvailability {
+    #[cfg(target_os = "linux")]
+    {
+        decide(query_host(), is_wayland())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        TrayAvailability::Available
+    }
+}
+
+pub fn availability(app: &AppHandle) -> TrayAvailability {
+    app.try_state::<TrayState>()
+        .map(|state| state.0)
+        .unwrap_or(TrayAvailability::Available)
+}
+
+/// 关闭主窗口时是否隐藏到托盘：设置开启且托盘确实存在。
+pub fn close_to_tray(app: &AppHandle) -> bool {
+    settings::current(app).close_to_tray && availability(app) == TrayAvailability::Available
+}
+
+/// `host` 为是否有宿主注册，None 表示无法查询。
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn decide(host: Option<bool>, wayland: bool) -> TrayAvailability {
+    if host == Some(false) && wayland {
+        TrayAvailability::NoHost
+    } else {
+        TrayAvailability::Available
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_wayland() -> bool {
+    std::env::var("XDG_SESSION_TYPE").is_ok_and(|kind| kind.eq_ignore_ascii_case("wayland"))
+        || std::env::var_os("WAYLAND_DISPLAY").is_some_and(|display| !display.is_empty())
+}
+
+/// 查询 IsStatusNotifierHostRegistered。Watcher 不存在时调用失败，视为没有宿主。
+#[cfg(target_os = "linux")]
+fn query_host() -> Option<bool> {
+    use std::process::Command;
+
+    const DEST: &str = "org.kde.StatusNotifierWatcher";
+    const PATH: &str = "/StatusNotifierWatcher";
+    const PROPERTY: &str = "IsStatusNotifierHostRegistered";
+
+    let output = Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--timeout",
+            "2",
+            "--dest",
+            DEST,
+            "--object-path",
+            PATH,
+        ])
+        .args([
+            "--method",
+            "org.freedesktop.DBus.Properties.Get",
+            DEST,
+            PROPERTY,
+        ])
+        .output()
+        .or_else(|_| {
+            Command::new("dbus-send")
+                .args(["--session", "--print-reply", "--reply-timeout=2000"])
+                .arg(format!("--dest={DEST}"))
+                .args([PATH, "org.freedesktop.DBus.Properties.Get"])
+                .arg(format!("string:{DEST}"))
+                .arg(format!("string:{PROPERTY}"))
+                .output()
+        })
+        .ok()?;
+    if !output.status.success() {
+        return Some(false);
+    }
+    parse_reply(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// 解析 gdbus（`(<true>,)`）或 dbus-send（`variant boolean true`）的输出。
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn parse_reply(stdout: &str) -> Option<bool> {
+    let words: Vec<&str> = stdout
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    match (words.contains(&"true"), words.contains(&"false")) {
+        (true, false) => Some(true),
+        (false, true) => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tray_is_skipped_only_without_host_on_wayland() {
+        assert_eq!(decide(Some(false), true), TrayAvailability::NoHost);
+        // X11 退回 XEmbed 托盘
+        assert_eq!(decide(Some(false), false), TrayAvailability::Available);
+        // 无法查询时保持原有行为
+        assert_eq!(decide(None, true), TrayAvailability::Available);
+        assert_eq!(decide(Some(true), true), TrayAvailability::Available);
+    }
+
+    #[test]
+    fn disable_setting_skips_the_host_query() {
+        let setting = |value: &'static str| {
+            move |key: &str| (key == DISABLE_TRAY_KEY).then(|| value.to_string())
+        };
+        let unreachable = || -> TrayAvailability { panic!("已关闭托盘时不应查询宿主") };
+        assert_eq!(
+            detect_with(setting("1"), unreachable),
+            TrayAvailability::Disabled
+        );
+        assert_eq!(
+            detect_with(setting(" TRUE "), unreachable),
+            TrayAvailability::Disabled
+        );
+        assert_eq!(
+            detect_with(setting("0"), || TrayAvailability::NoHost),
+            TrayAvailability::NoHost
+        );
+        assert_eq!(
+            detect_with(|_| None, || TrayAvailability::Available),
+            TrayAvailability::Available
+        );
+    }
+
+    #[test]
+    fn dbus_replies_are_parsed() {
+        assert_eq!(parse_reply("(<true>,)\n"), Some(true));
+        assert_eq!(parse_reply("(<false>,)\n"), Some(false));
+        assert_eq!(
+            parse_reply("method return time=1.0 sender=:1.5 -> destination=:1.9 serial=7 reply_serial=2\n   variant       boolean true\n"),
+            Some(true)
+        );
+        assert_eq!(parse_reply(""), None);
+    }
+}
@@ -0,0 +1,52 @@
+//! 托盘图标。
+//!
+//! 打包损坏（如 AppImage 解压不完整）时默认窗口图标可能缺失，托盘图标只是外观问题，不应阻止后端启动。
+//! 依次尝试：默认窗口图标 → 编译进程序的 32x32 PNG → 不设图标（仍保留提示文字与菜单），
+//! 每次退而求其次都记录一条警告。
+
+use tauri::image::Image;
+use tauri::AppHandle;
+
+use crate::runtime;
+
+/// 编译时嵌入的备用图标，不依赖打包后的资源文件。
+const EMBEDDED_ICON: &[u8] = include_bytes!("../icons/32x32.png");
+
+/// 托盘使用的图标；全部来源都不可用时为 None。
+pub fn resolve(app: &AppHandle) -> Option<Image<'static>> {
+    if let Some(icon) = app.default_window_icon() {
+        return Some(icon.clone().to_owned());
+    }
+    runtime::shell_log(app, "[WARN] 默认窗口图标缺失，托盘改用内置图标");
+    match decode_png(EMBEDDED_ICON) {
+        Ok(icon) => Some(icon),
+        Err(err) => {
+            runtime::shell_log(
+                app,
+                &format!("[WARN] 内置托盘图标解码失败，托盘将不显示图标: {err}"),
+            );
+            None
+        }
+    }
+}
+
+fn decode_png(bytes: &[u8]) -> Result<Image<'static>, String> {
+    let decoded = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?
+        .into_rgba8();
+    let (width, height) = decoded.dimensions();
+    Ok(Image::new_owned(decoded.into_raw(), width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_icon_decodes() {
+        let icon = decode_png(EMBEDDED_ICON).unwrap();
+        assert_eq!((icon.width(), icon.height()), (32, 32));
+        assert_eq!(icon.rgba().len(), 32 * 32 * 4);
+        assert!(decode_png(b"not a png").is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! 托盘提示显示实时传输速度，如 “PT Nexus · ↑ 12.3 MB/s ↓ 1.1 MB/s”。
+//!
+//! 由桌面设置 `tray_stats` 开启，每隔 `tray_stats_interval_secs` 秒向 server 的 `/api/speed_data`
+//! 查询一次（本机请求无需登录）。空闲节能期间暂停查询；后端不可用时恢复为 “PT Nexus”。
+//! batch 有任务在执行时，无论是否开启都在末尾附上任务摘要（见 batchstatus.rs）。
+
+use std::time::Duration;
+
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::power::{self, PowerProfile};
+use crate::tasks::{self, Scope};
+use crate::{batchstatus, localhttp, runtime, settings, status};
+
+pub const TRAY_ID: &str = "main";
+const PLAIN_TOOLTIP: &str = "PT Nexus";
+/// 关闭时检查设置是否被打开的间隔。
+const DISABLED_POLL: Duration = Duration::from_secs(15);
+/// Windows 托盘提示最多 127 个 UTF-16 字符（NOTIFYICONDATAW::szTip）。
+const MAX_TOOLTIP_UNITS: usize = 127;
+
+pub fn start(app: &AppHandle) {
+    tasks::spawn(app, "tray-stats", Scope::Shell, move |app, token| {
+        let mut shown = PLAIN_TOOLTIP.to_string();
+        let _batch = batchstatus::subscribe(&app);
+        loop {
+            let current = settings::current(&app);
+            // 休眠时托盘提示由 ondemand.rs 维护
+            if status::is_dormant(&app) {
+                shown.clear();
+                if !token.sleep(DISABLED_POLL) {
+                    return;
+                }
+                continue;
+            }
+            let tooltip = if !current.tray_stats {
+                if !token.sleep(DISABLED_POLL) {
+                    return;
+                }
+                PLAIN_TOOLTIP.to_string()
+            } else {
+                let interval = Duration::from_secs(u64::from(current.tray_stats_interval_secs));
+                if !power::sleep(&app, &token, interval, interval) {
+                    return;
+                }
+                if power::profile(&app) == PowerProfile::Saver {
+                    PLAIN_TOOLTIP.to_string()
+                } else {
+                    fetch_totals(&app)
+                        .map(|(up, down)| format_tooltip(up, down))
+                        .unwrap_or_else(|_| PLAIN_TOOLTIP.to_string())
+                }
+            };
+            let tooltip = with_batch(tooltip, &batchstatus::latest(&app));
+
+            if tooltip != shown {
+                if let Some(tray) = app.tray_by_id(TRAY_ID) {
+                    let _ = tray.set_tooltip(Some(&tooltip));
+                }
+                shown = tooltip;
+            }
+        }
+    });
+}
+
+/// 所有下载器的上传、下载速度之和（字节/秒）。
+fn fetch_totals(app: &AppHandle) -> Result<(u64, u64), String> {
+    let (host, port) = server_address(app);
+    let body = localhttp::get(&host, port, "/api/speed_data")?;
+    let speeds: Value =
+        serde_json::from_str(&body).map_err(|e| format!("解析速度数据失败: {e}"))?;
+    Ok(sum_speeds(&speeds))
+}
+
+/// server 的本机访问地址：监听全部地址时改用回环地址。
+pub fn server_address(app: &AppHandle) -> (String, u16) {
+    let context = app
+        .try_state::<runtime::RuntimeManager>()
+        .and_then(|rt| rt.context());
+    let env = |key: &str| {
+        context
+            .as_ref()
+            .and_then(|ctx| ctx.common_env.get(key))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let host = env("SERVER_HOST")
+        .filter(|host| host != "0.0.0.0" && host != "::")
+        .unwrap_or_else(|| "127.0.0.1".to_string());
+    let port = env("SERVER_PORT")
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(5275);
+    (host, port)
+}
+
+/// `/api/speed_data` 的格式为 `{下载器 ID: {upload_speed, download_speed, ...}}`。
+fn sum_speeds(speeds: &Value) -> (u64, u64) {
+    let Some(clients) = speeds.as_object() else {
+        return (0, 0);
+    };
+    clients.values().fold((0, 0), |(up, down), client| {
+        let speed = |key: &str| client.get(key).and_then(Value::as_u64).unwrap_or(0);
+        (
+            up.saturating_add(speed("upload_speed")),
+            down.saturating_add(speed("download_speed")),
+        )
+    })
+}
+
+fn format_tooltip(up: u64, down: u64) -> String {
+    truncate(
+        &format!(
+            "{PLAIN_TOOLTIP} · ↑ {} ↓ {}",
+            format_speed(up),
+            format_speed(down)
+        ),
+        MAX_TOOLTIP_UNITS,
+    )
+}
+
+fn with_batch(tooltip: String, batch: &batchstatus::BatchSummary) -> String {
+    match batch.tooltip_suffix() {
+        Some(suffix) => truncate(&format!("{tooltip} · {suffix}"), MAX_TOOLTIP_UNITS),
+        None => tooltip,
+    }
+}
+
+fn format_speed(bytes_per_sec: u64) -> String {
+    const UNITS: [&str; 4] = ["B/s", "KB/s", "MB/s", "GB/s"];
+    let mut value = bytes_per_sec as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes_per_sec} B/s")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// 按 UTF-16 长度截断，超出时以省略号结尾。
+fn truncate(text: &str, max_units: usize) -> String {
+    if text.encode_utf16().count() <= max_units {
+        return text.to_string();
+    }
+    let mut result = String::new();
+    let mut units = 0;
+    for c in text.chars() {
+        if units + c.len_utf16() > max_units - 1 {
+            break;
+        }
+        units += c.len_utf16();
+        result.push(c);
+    }
+    result.push('…');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speeds_are_summed_across_clients() {
+        let speeds = serde_json::json!({
+            "qb1": {"name": "qB", "upload_speed": 1000, "download_speed": 20},
+            "tr1": {"name": "TR", "upload_speed": 24, "download_speed": 4},
+            "broken": {"name": "x"}
+        });
+        assert_eq!(sum_speeds(&speeds), (1024, 24));
+        assert_eq!(sum_speeds(&Value::Null), (0, 0));
+    }
+
+    #[test]
+    fn speeds_are_formatted_with_binary_units() {
+        assert_eq!(format_speed(512), "512 B/s");
+        assert_eq!(format_speed(1536), "1.5 KB/s");
+        assert_eq!(format_speed(12_897_485), "12.3 MB/s");
+        assert_eq!(
+            format_tooltip(12_897_485, 1_153_434),
+            "PT Nexus · ↑ 12.3 MB/s ↓ 1.1 MB/s"
+        );
+    }
+
+    #[test]
+    fn busy_batch_is_appended_to_tooltip() {
+        let busy = batchstatus::BatchSummary {
+            busy: true,
+            active_jobs: 1,
+            progress: Some(45.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            with_batch(PLAIN_TOOLTIP.to_string(), &busy),
+            "PT Nexus · batch 1 个任务 45%"
+        );
+        let idle = batchstatus::BatchSummary::default();
+        assert_eq!(with_batch(PLAIN_TOOLTIP.to_string(), &idle), "PT Nexus");
+    }
+
+    #[test]
+    fn long_tooltips_are_truncated() {
+        let text = "种".repeat(200);
+        let truncated = truncate(&text, MAX_TOOLTIP_UNITS);
+        assert_eq!(truncated.encode_utf16().count(), MAX_TOOLTIP_UNITS);
+        assert!(truncated.ends_with('…'));
+        assert_eq!(truncate("PT Nexus", MAX_TOOLTIP_UNITS), "PT Nexus");
+    }
+}
@@ -0,0 +1,245 @@
+//! 清理更新器的工作目录 `<data_dir>/updates`。
+//!
+//! 更新器每次更新都在这里留下文件：`repo/` 是更新源的 git 检出，拉取后重置到最新版本，即当前安装的版本，
+//! 但旧提交的对象一直留在 `.git` 中；`backup/` 是被替换的旧程序，更新成功后已由桌面壳保存为回滚副本
+//! （见 rollback.rs）；此外还有中断的克隆、下载留下的残余文件。清理保留检出、唯一一代回滚副本以及与
+//! 桌面壳通信的请求/结果文件，删除其余内容，并回收检出中未跟踪的文件与不可达的 git 对象。
+//!
+//! 更新器工作期间持有 `update.lock`，此时不清理：协调更新成功后等更新器释放锁再清理，启动完成后
+//! 更新器空闲时清理一次，也可以通过 `clean_update_cache` 手动清理。释放的空间记入运行事件日志。
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+use tauri::AppHandle;
+
+use crate::journal::{self, Severity};
+use crate::tasks::{self, Scope};
+use crate::{datadir, recycle, runtime};
+
+/// 更新器拉取、安装期间创建，结束后删除；启动时删除上次异常退出留下的锁。
+pub const LOCK_FILE: &str = "update.lock";
+const LOCK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// 更新成功后最多等待更新器释放锁的时间。
+const LOCK_WAIT: Duration = Duration::from_secs(30 * 60);
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+static CLEANING: AtomicBool = AtomicBool::new(false);
+
+/// 清理更新缓存，返回释放的字节数；更新器正在工作时拒绝。
+pub fn clean(app: &AppHandle) -> Result<u64, String> {
+    let profile = datadir::active_profile(app)?;
+    if updater_busy(&profile.update_dir) {
+        return Err("更新器正在工作，请在更新完成后再清理".to_string());
+    }
+    if CLEANING.swap(true, Ordering::SeqCst) {
+        return Err("正在清理更新缓存".to_string());
+    }
+    let result = prune(
+        &profile.update_dir,
+        &profile.repo_dir,
+        &profile.rollback_dir,
+    );
+    CLEANING.store(false, Ordering::SeqCst);
+
+    let freed = result?;
+    if freed > 0 {
+        let message = format!(
+            "已清理更新缓存，释放 {:.1} MB",
+            freed as f64 / 1024.0 / 1024.0
+        );
+        runtime::shell_log(app, &format!("[INFO] {message}"));
+        journal::record(app, Severity::Info, None, message);
+    }
+    Ok(freed)
+}
+
+/// 协调更新成功后调用：更新器还在等待结果、随后才释放锁，等锁释放后再清理。
+pub fn clean_after_update(app: &AppHandle) {
+    tasks::spawn(
+        app,
+        "update-cache-clean",
+        Scope::Shell,
+        move |app, token| {
+            let Ok(profile) = datadir::active_profile(&app) else {
+                return;
+            };
+            let started = Instant::now();
+            while updater_busy(&profile.update_dir) {
+                if started.elapsed() >= LOCK_WAIT {
+                    runtime::shell_log(
+                        &app,
+                        "[WARN] 更新器长时间未释放 update.lock，跳过本次更新缓存清理",
+                    );
+                    return;
+                }
+                if !token.sleep(LOCK_POLL_INTERVAL) {
+                    return;
+                }
+            }
+            if let Err(err) = clean(&app) {
+                runtime::shell_log(&app, &format!("[WARN] 清理更新缓存失败: {err}"));
+            }
+        },
+    );
+}
+
+/// 启动完成后调用：更新器空闲时清理一次，正在更新时留给更新完成后的清理。
+pub fn clean_at_bootstrap(app: &AppHandle) {
+    tasks::spawn(app, "update-cache-clean", Scope::Shell, move |app, _| {
+        if let Err(err) = clean(&app) {
+            runtime::shell_log(&app, &format!("[INFO] 跳过启动时的更新缓存清理: {err}"));
+        }
+    });
+}
+
+/// 更新器持有锁，或有尚未处理的重启请求。
+fn updater_busy(update_dir: &Path) -> bool {
+    update_dir.join(LOCK_FILE).exists() || update_dir.join(recycle::REQUEST_FILE).exists()
+}
+
+/// 删除检出与回滚副本以外的内容，返回释放的字节数。
+fn prune(update_dir: &Path, repo_dir: &Path, rollback_dir: &Path) -> Result<u64, String> {
+    let entries = match fs::read_dir(update_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(format!("读取 {} 失败: {err}", update_dir.display())),
+    };
+    let keep_files = [LOCK_FILE, recycle::REQUEST_FILE, recycle::RESULT_FILE];
+    let mut freed = 0;
+    let mut failed = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let name = entry.file_name();
+        if path == rollback_dir || keep_files.iter().any(|keep| name == *keep) {
+            continue;
+        }
+        // 没有 .git 的检出是中断的克隆，更新器下次会重新克隆
+        if path == repo_dir && path.join(".git").exists() {
+            freed += prune_checkout(&path);
+            continue;
+        }
+        match remove(&path) {
+            Ok(bytes) => freed += bytes,
+            Err(err) => failed.push(err),
+        }
+    }
+    if failed.is_empty() {
+        Ok(freed)
+    } else {
+        Err(failed.join("\n"))
+    }
+}
+
+/// 删除检出中未被跟踪的文件，回收旧版本的 git 对象；找不到 git 时跳过。
+fn prune_checkout(repo: &Path) -> u64 {
+    let mut freed = 0;
+    if let Some(output) = git(repo, &["clean", "-ndx"]) {
+        for line in output.lines() {
+            let Some(relative) = line.strip_prefix("Would remove ") else {
+                continue;
+            };
+            freed += remove(&repo.join(relative.trim_end_matches(['/', '\\']))).unwrap_or(0);
+        }
+    }
+    let git_dir = repo.join(".git");
+    let before = datadir::dir_size(&git_dir);
+    if git(repo, &["reflog", "expire", "--expire=now", "--all"]).is_some()
+        && git(repo, &["gc", "--prune=now", "--quiet"]).is_some()
+    {
+        freed += before.saturating_sub(datadir::dir_size(&git_dir));
+    }
+    freed
+}
+
+fn git(repo: &Path, args: &[&str]) -> Option<String> {
+    let mut command = Command::new("git");
+    // 固定 git 目录，检出损坏时不会沿上级目录找到别的仓库
+    command
+        .arg("-C")
+        .arg(repo)
+        .args(["--git-dir=.git", "--work-tree=."])
+        .args(args);
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW);
+    let output = command.output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn remove(path: &Path) -> Result<u64, String> {
+    let bytes = datadir::dir_size(path);
+    let removed = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match removed {
+        Ok(()) => Ok(bytes),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(format!("删除 {} 失败: {err}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn prune_keeps_checkout_rollback_and_handshake_files() {
+        let update_dir = temp_dir("prune");
+        let repo = update_dir.join("repo");
+        let rollback = update_dir.join("rollback");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::write(repo.join("CHANGELOG.json"), b"{}").unwrap();
+        fs::create_dir_all(rollback.join("batch")).unwrap();
+        fs::write(rollback.join("batch").join("0-batch"), vec![0u8; 50]).unwrap();
+        fs::create_dir_all(update_dir.join("backup").join("server")).unwrap();
+        fs::write(
+            update_dir.join("backup").join("server").join("server"),
+            vec![0u8; 300],
+        )
+        .unwrap();
+        fs::write(update_dir.join("release.zip"), vec![0u8; 200]).unwrap();
+        fs::write(update_dir.join(recycle::RESULT_FILE), b"{}").unwrap();
+
+        assert_eq!(prune(&update_dir, &repo, &rollback), Ok(500));
+        assert!(repo.join("CHANGELOG.json").exists());
+        assert!(rollback.join("batch").join("0-batch").exists());
+        assert!(update_dir.join(recycle::RESULT_FILE).exists());
+        assert!(!update_dir.join("backup").exists());
+        assert!(!update_dir.join("release.zip").exists());
+        assert_eq!(prune(&update_dir, &repo, &rollback), Ok(0));
+        let _ = fs::remove_dir_all(&update_dir);
+    }
+
+    #[test]
+    fn interrupted_clone_is_removed_and_lock_means_busy() {
+        let update_dir = temp_dir("busy");
+        let repo = update_dir.join("repo");
+        fs::create_dir_all(&repo).unwrap();
+        fs::write(repo.join("partial.pack"), vec![0u8; 120]).unwrap();
+        assert!(!updater_busy(&update_dir));
+        fs::write(update_dir.join(LOCK_FILE), b"4242").unwrap();
+        assert!(updater_busy(&update_dir));
+
+        assert_eq!(
+            prune(&update_dir, &repo, &update_dir.join("rollback")),
+            Ok(120)
+        );
+        assert!(!repo.exists());
+        assert!(update_dir.join(LOCK_FILE).exists());
+        let _ = fs::remove_dir_all(&update_dir);
+    }
+}
@@ -0,0 +1,79 @@
+//! 协调更新期间主窗口显示的“正在更新组件…”页面。
+//!
+//! 滚动重启（见 recycle.rs）期间后端组件逐个停止，WebUI 无法正常使用。开始重启前，主窗口若停在运行时页面，
+//! 先记下当前地址，再切换到桌面壳自带的 updating.html；页面订阅 `runtime-recycle` 事件显示各组件的进度。
+//! 全部重启成功后返回记下的地址；失败时页面停留，显示原因并提供回滚与诊断操作，由用户选择后返回（`reconnect`）。
+//! 更新器在失败后回滚文件并再次请求重启时，页面仍在显示，直接切换到新请求的进度，结束后返回最初记下的地址。
+//! 窗口停在其他页面（离线提示页、启动页等）时不切换，结束后也不导航。
+
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::runtime;
+
+const PAGE: &str = "updating.html";
+
+/// 切换到更新页之前的运行时页面地址，返回时使用。
+static RETURN_TO: Mutex<Option<tauri::Url>> = Mutex::new(None);
+
+/// 开始重启前调用，`id` 为重启请求的 ID，页面只显示该请求的进度。
+pub fn show(app: &AppHandle, id: &str) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let Ok(current) = window.url() else {
+        return;
+    };
+    let Ok(mut return_to) = RETURN_TO.lock() else {
+        return;
+    };
+    if runtime::is_runtime_url(app, &current) {
+        *return_to = Some(current);
+    } else if !is_update_page(app, &current) {
+        return;
+    }
+    let Some(mut url) = runtime::local_page_url(PAGE) else {
+        return;
+    };
+    url.query_pairs_mut().append_pair("id", id);
+    if window.navigate(url).is_ok() {
+        runtime::shell_log(app, "[INFO] 更新期间主窗口切换到更新进度页");
+    }
+}
+
+/// 全部组件重启成功后调用：主窗口仍停在更新页时返回切换前的地址。
+pub fn finish(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let Some(return_to) = RETURN_TO
+        .lock()
+        .ok()
+        .and_then(|mut return_to| return_to.take())
+    else {
+        return;
+    };
+    if window
+        .url()
+        .is_ok_and(|current| is_update_page(app, &current))
+    {
+        return_to_runtime(app, &window, return_to);
+    }
+}
+
+fn return_to_runtime(app: &AppHandle, window: &WebviewWindow, return_to: tauri::Url) {
+    // 重启期间运行时地址不会变化，保险起见只在同源时返回原来的路由
+    let url = if return_to.origin() == runtime::runtime_url(app).origin() {
+        return_to
+    } else {
+        runtime::runtime_url(app)
+    };
+    if let Err(err) = runtime::navigate_runtime_page(window, url) {
+        runtime::shell_log(app, &format!("[WARN] 更新完成后返回运行时页面失败: {err}"));
+    }
+}
+
+pub fn is_update_page(app: &AppHandle, url: &tauri::Url) -> bool {
+    !runtime::is_runtime_url(app, url) && url.path().trim_start_matches('/') == PAGE
+}
@@ -0,0 +1,389 @@
+//! 渲染进程看门狗。
+//!
+//! WebView 渲染进程崩溃（常见于显卡驱动更新）后窗口会变成永久白屏，而后端服务仍然正常。
+//! 主窗口创建后 [`attach`] 订阅平台 WebView 的渲染进程退出通知：WebView2 的 `ProcessFailed`、
+//! WebKitGTK 的 `web-process-terminated`。收到通知即视为一次崩溃，记录日志、发出事件并自动重新加载页面；
+//! 1 分钟内多次崩溃则停止自动重新加载，提示关闭 GPU 加速。
+//!
+//! Tauri 没有暴露 WKWebView 的渲染进程退出回调，macOS（以及订阅失败时）改用注入脚本的心跳兜底：
+//! 窗口可见时心跳超时视为一次崩溃。心跳无法区分崩溃与被阻塞的脚本，只在没有平台通知时启用。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, WebviewWindow};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::events::{self, Replay};
+use crate::journal::{self, Severity};
+use crate::tasks::{self, Scope};
+use crate::{gpu, runtime};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// 注入脚本发送心跳的间隔（见 pagescripts/webview-heartbeat.js）。
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(20);
+const RELOAD_DELAY: Duration = Duration::from_secs(2);
+const CRASH_WINDOW: Duration = Duration::from_secs(60);
+const MAX_CRASHES_IN_WINDOW: usize = 3;
+
+#[derive(Default)]
+pub struct RendererWatchdog {
+    state: Mutex<WatchdogState>,
+    /// 已订阅平台的渲染进程退出通知，心跳不再参与判断。
+    native: AtomicBool,
+}
+
+#[derive(Default)]
+struct WatchdogState {
+    last_heartbeat: Option<Instant>,
+    crashes: Vec<Instant>,
+    auto_reload_disabled: bool,
+}
+
+impl WatchdogState {
+    fn record_crash(&mut self, now: Instant) -> RendererCrashPayload {
+        // 清空心跳，等待重新加载后的页面再次上报
+        self.last_heartbeat = None;
+        self.crashes
+            .retain(|t| now.duration_since(*t) < CRASH_WINDOW);
+        self.crashes.push(now);
+        if self.crashes.len() >= MAX_CRASHES_IN_WINDOW {
+            self.auto_reload_disabled = true;
+        }
+        RendererCrashPayload {
+            crashes_in_last_minute: self.crashes.len(),
+            auto_reload: !self.auto_reload_disabled,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct RendererCrashPayload {
+    crashes_in_last_minute: usize,
+    auto_reload: bool,
+}
+
+impl RendererWatchdog {
+    pub fn heartbeat(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.last_heartbeat = Some(Instant::now());
+        }
+    }
+
+    /// 窗口隐藏或最小化时 WebView 会节流定时器，此时刷新心跳时间，避免重新显示时误判。
+    fn grace(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.last_heartbeat.is_some() {
+                state.last_heartbeat = Some(Instant::now());
+            }
+        }
+    }
+
+    /// 心跳超时则记为一次崩溃并返回事件内容；页面正常或尚未上报过心跳时返回 None。
+    fn check(&self) -> Option<RendererCrashPayload> {
+        let mut state = self.state.lock().ok()?;
+        let last = state.last_heartbeat?;
+        if last.elapsed() < HEARTBEAT_TIMEOUT {
+            return None;
+        }
+        Some(state.record_crash(Instant::now()))
+    }
+
+    /// 平台通知渲染进程已退出，记为一次崩溃。
+    fn crashed(&self) -> Option<RendererCrashPayload> {
+        let mut state = self.state.lock().ok()?;
+        Some(state.record_crash(Instant::now()))
+    }
+
+    fn is_native(&self) -> bool {
+        self.native.load(Ordering::SeqCst)
+    }
+}
+
+/// 是否需要向运行时页面注入心跳脚本：已订阅平台通知时不再注入。
+pub fn uses_heartbeat(app: &AppHandle) -> bool {
+    app.try_state::<RendererWatchdog>()
+        .is_none_or(|watchdog| !watchdog.is_native())
+}
+
+/// 主窗口创建后调用，订阅平台 WebView 的渲染进程退出通知。订阅在主线程回调中完成，
+/// 成功后才关闭心跳判断；失败时记录日志，继续使用心跳。
+pub fn attach(window: &WebviewWindow) {
+    if let Err(err) = platform::subscribe(window) {
+        runtime::shell_log(
+            window.app_handle(),
+            &format!("[WARN] 订阅渲染进程退出通知失败，改用页面心跳判断: {err}"),
+        );
+    }
+}
+
+/// 平台订阅回调的结果。
+#[cfg_attr(not(any(target_os = "windows", target_os = "linux")), allow(dead_code))]
+fn subscribed(window: &WebviewWindow, result: Result<(), String>) {
+    let app = window.app_handle();
+    match result {
+        Ok(()) => {
+            if let Some(watchdog) = app.try_state::<RendererWatchdog>() {
+                watchdog.native.store(true, Ordering::SeqCst);
+            }
+        }
+        Err(err) => runtime::shell_log(
+            app,
+            &format!("[WARN] 订阅渲染进程退出通知失败，改用页面心跳判断: {err}"),
+        ),
+    }
+}
+
+/// 平台通知渲染进程退出（在主线程上调用，不能阻塞）。
+#[cfg_attr(not(any(target_os = "windows", target_os = "linux")), allow(dead_code))]
+fn renderer_gone(window: &WebviewWindow, reason: &str) {
+    let app = window.app_handle();
+    let Some(payload) = app
+        .try_state::<RendererWatchdog>()
+        .and_then(|watchdog| watchdog.crashed())
+    else {
+        return;
+    };
+    report(app, &payload, reason);
+    if payload.auto_reload {
+        let window = window.clone();
+        tasks::spawn(app, "renderer-reload", Scope::Shell, move |app, token| {
+            if !token.sleep(RELOAD_DELAY) {
+                return;
+            }
+            let on_runtime_page = window
+                .url()
+                .is_ok_and(|url| runtime::is_runtime_url(&app, &url));
+            if on_runtime_page {
+                runtime::reload_runtime_page(&window);
+            } else if let Err(err) = window.reload() {
+                runtime::shell_log(
+                    &app,
+                    &format!("[WARN] 渲染进程退出后重新加载页面失败: {err}"),
+                );
+            }
+        });
+    } else {
+        show_repeated_crash_dialog(app);
+    }
+}
+
+fn report(app: &AppHandle, payload: &RendererCrashPayload, reason: &str) {
+    runtime::shell_log(
+        app,
+        &format!(
+            "[WARN] WebView 渲染进程{reason}（1 分钟内第 {} 次），{}",
+            payload.crashes_in_last_minute,
+            if payload.auto_reload {
+                "即将自动重新加载"
+            } else {
+                "已停止自动重新加载"
+            }
+        ),
+    );
+    journal::record(
+        app,
+        Severity::Warn,
+        None,
+        format!(
+            "界面渲染进程{reason}（1 分钟内第 {} 次）",
+            payload.crashes_in_last_minute
+        ),
+    );
+    events::emit(
+        app,
+        "webview-renderer-crashed",
+        Replay::Never,
+        payload.clone(),
+    );
+}
+
+/// 心跳兜底，只在没有平台通知时判断。
+pub fn start(app: &AppHandle) {
+    tasks::spawn(app, "renderer-watchdog", Scope::Shell, move |app, token| {
+        while token.sleep(CHECK_INTERVAL) {
+            let Some(watchdog) = app.try_state::<RendererWatchdog>() else {
+                continue;
+            };
+            if watchdog.is_native() {
+                continue;
+            }
+            let Some(window) = app.get_webview_window("main") else {
+                continue;
+            };
+
+            // 只有运行时页面会注入心跳脚本，离线提示页等本地页面不参与判断
+            let on_runtime_page = window
+                .url()
+                .map(|url| runtime::is_runtime_url(&app, &url))
+                .unwrap_or(false);
+            let visible =
+                window.is_visible().unwrap_or(false) && !window.is_minimized().unwrap_or(true);
+            if !visible || !on_runtime_page {
+                watchdog.grace();
+                continue;
+            }
+
+            let Some(payload) = watchdog.check() else {
+                continue;
+            };
+            report(&app, &payload, "无响应");
+
+            if payload.auto_reload {
+                if !token.sleep(RELOAD_DELAY) {
+                    return;
+                }
+                runtime::reload_runtime_page(&window);
+            } else {
+                show_repeated_crash_dialog(&app);
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use tauri::WebviewWindow;
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        ICoreWebView2, ICoreWebView2ProcessFailedEventArgs, COREWEBVIEW2_PROCESS_FAILED_KIND,
+        COREWEBVIEW2_PROCESS_FAILED_KIND_RENDER_PROCESS_EXITED,
+        COREWEBVIEW2_PROCESS_FAILED_KIND_RENDER_PROCESS_UNRESPONSIVE,
+    };
+    use webview2_com::ProcessFailedEventHandler;
+
+    use super::{renderer_gone, subscribed};
+
+    pub fn subscribe(window: &WebviewWindow) -> tauri::Result<()> {
+        let target = window.clone();
+        window.with_webview(move |webview| {
+            let result = unsafe {
+                (|| -> windows::core::Result<()> {
+                    let core = webview.controller().CoreWebView2()?;
+                    let failed = target.clone();
+                    let handler = ProcessFailedEventHandler::create(Box::new(
+                        move |_sender: Option<ICoreWebView2>,
+                              args: Option<ICoreWebView2ProcessFailedEventArgs>| {
+                            let mut kind = COREWEBVIEW2_PROCESS_FAILED_KIND::default();
+                            if let Some(args) = args {
+                                args.ProcessFailedKind(&mut kind)?;
+                            }
+                            // 浏览器进程、GPU 进程等退出时 WebView2 会自行处理，这里只关心渲染进程
+                            if kind == COREWEBVIEW2_PROCESS_FAILED_KIND_RENDER_PROCESS_EXITED {
+                                renderer_gone(&failed, "已退出");
+                            } else if kind == COREWEBVIEW2_PROCESS_FAILED_KIND_RENDER_PROCESS_UNRESPONSIVE {
+                                renderer_gone(&failed, "无响应");
+                            }
+                            Ok(())
+                        },
+                    ));
+                    let mut token = 0i64;
+                    core.add_ProcessFailed(&handler, &mut token)
+                })()
+            };
+            subscribed(&target, result.map_err(|e| e.to_string()));
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use tauri::WebviewWindow;
+    use webkit2gtk::{WebProcessTerminationReason, WebViewExt};
+
+    use super::{renderer_gone, subscribed};
+
+    pub fn subscribe(window: &WebviewWindow) -> tauri::Result<()> {
+        let target = window.clone();
+        window.with_webview(move |webview| {
+            let failed = target.clone();
+            webview
+                .inner()
+                .connect_web_process_terminated(move |_, reason| match reason {
+                    // 应用自己终止的进程（如重新加载）不算崩溃
+                    WebProcessTerminationReason::TerminatedByApi => {}
+                    WebProcessTerminationReason::ExceededMemoryLimit => {
+                        renderer_gone(&failed, "因内存超限退出")
+                    }
+                    _ => renderer_gone(&failed, "已退出"),
+                });
+            subscribed(&target, Ok(()));
+        })
+    }
+}
+
+/// WKWebView 的渲染进程退出回调未经 Tauri 暴露，使用心跳兜底。
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+mod platform {
+    use tauri::WebviewWindow;
+
+    pub fn subscribe(_window: &WebviewWindow) -> tauri::Result<()> {
+        Ok(())
+    }
+}
+
+fn show_repeated_crash_dialog(app: &AppHandle) {
+    let message = if gpu::is_gpu_disabled(app) {
+        "界面渲染进程在 1 分钟内多次崩溃，已停止自动重新加载。\n\nGPU 硬件加速已关闭，请查看 logs/shell.log 并尝试更新显卡驱动或 WebView 运行时。"
+    } else {
+        "界面渲染进程在 1 分钟内多次崩溃，已停止自动重新加载。\n\n这通常与显卡驱动有关，建议关闭 GPU 硬件加速后重启应用。"
+    };
+    let buttons = if gpu::is_gpu_disabled(app) {
+        MessageDialogButtons::Ok
+    } else {
+        MessageDialogButtons::OkCancelCustom(
+            "关闭 GPU 加速并重启".to_string(),
+            "暂不处理".to_string(),
+        )
+    };
+
+    let app = app.clone();
+    app.dialog()
+        .message(message)
+        .title("PT Nexus 界面渲染异常")
+        .kind(MessageDialogKind::Warning)
+        .buttons(buttons)
+        .show({
+            let app = app.clone();
+            move |confirmed| {
+                if confirmed && !gpu::is_gpu_disabled(&app) {
+                    if let Err(err) = gpu::disable_and_restart(&app) {
+                        runtime::shell_log(&app, &format!("[ERROR] 关闭 GPU 加速失败: {err}"));
+                    }
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_crashes_disable_auto_reload() {
+        let mut state = WatchdogState::default();
+        let start = Instant::now();
+        assert!(state.record_crash(start).auto_reload);
+        // 超出统计窗口的崩溃不计入
+        let later = start + CRASH_WINDOW + Duration::from_secs(1);
+        let payload = state.record_crash(later);
+        assert_eq!(payload.crashes_in_last_minute, 1);
+        assert!(state.record_crash(later).auto_reload);
+        let payload = state.record_crash(later);
+        assert_eq!(payload.crashes_in_last_minute, 3);
+        assert!(!payload.auto_reload);
+    }
+
+    #[test]
+    fn native_crash_clears_the_heartbeat() {
+        let watchdog = RendererWatchdog::default();
+        watchdog.heartbeat();
+        assert!(watchdog.check().is_none());
+        assert!(watchdog
+            .crashed()
+            .is_some_and(|payload| payload.auto_reload));
+        assert!(watchdog.check().is_none());
+    }
+}
@@ -0,0 +1,425 @@
+//! 定时与出错时推送的 Webhook 报告，供无人值守的机器确认应用仍在运行。
+//!
+//! runtime.env 中设置 `PTNEXUS_WEBHOOK_URL` 后，按 `PTNEXUS_WEBHOOK_EVENTS`（逗号分隔，默认 `daily,failure`）
+//! 选择的时机向该地址 POST 一段 JSON：`daily` 每 24 小时一次；`failure` 启动或重启失败、服务不可用时；
+//! `update` 更新器更新并重启服务后。请求体带有 `title` 与 `message`，ntfy、gotify 等推送服务可以直接显示，
+//! 其余字段为运行状态、运行时长、重启次数、版本与数据目录所在磁盘的剩余空间，不含 URL、路径、密码或 Cookie。
+//!
+//! 请求经系统 curl 发送，代理与根证书同站点图标下载（见 assetcache.rs）。发送失败按 10 秒、1 分钟、5 分钟重试，
+//! 连续 5 次报告都没有送达后暂停 1 小时（熔断），期间的报告直接丢弃。日志中只记录目标主机，不记录完整地址。
+//! 设置页可以调用 `test_webhook(url?)` 立即发送一次测试报告，不传地址时使用 runtime.env 中的配置。
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::status::{self, RuntimeState};
+use crate::tasks::{self, Scope};
+use crate::{assetcache, datadir, injections, runtime};
+
+pub const URL_KEY: &str = "PTNEXUS_WEBHOOK_URL";
+pub const EVENTS_KEY: &str = "PTNEXUS_WEBHOOK_EVENTS";
+const DEFAULT_TRIGGERS: [Trigger; 2] = [Trigger::Daily, Trigger::Failure];
+const DAILY_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const DAILY_POLL: Duration = Duration::from_secs(60);
+/// 记录上次每日报告的时间（Unix 秒），应用重启后不会提前或重复发送。
+const DAILY_STAMP_FILE: &str = "webhook-daily";
+const RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_secs(10),
+    Duration::from_secs(60),
+    Duration::from_secs(300),
+];
+const BREAKER_THRESHOLD: u32 = 5;
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+const REQUEST_TIMEOUT_SECS: &str = "15";
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+#[cfg(target_os = "windows")]
+const NULL_DEVICE: &str = "NUL";
+#[cfg(not(target_os = "windows"))]
+const NULL_DEVICE: &str = "/dev/null";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger {
+    Daily,
+    Failure,
+    Update,
+    Test,
+}
+
+impl Trigger {
+    fn name(self) -> &'static str {
+        match self {
+            Trigger::Daily => "daily",
+            Trigger::Failure => "failure",
+            Trigger::Update => "update",
+            Trigger::Test => "test",
+        }
+    }
+}
+
+pub struct WebhookState {
+    started: Instant,
+    /// 本次运行中各服务的重启次数，`all` 为重启全部服务。
+    restarts: Mutex<BTreeMap<String, u32>>,
+    breaker: Mutex<Breaker>,
+}
+
+impl Default for WebhookState {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            restarts: Mutex::default(),
+            breaker: Mutex::default(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    event: &'static str,
+    title: String,
+    message: String,
+    runtime_state: RuntimeState,
+    uptime_secs: u64,
+    restarts: BTreeMap<String, u32>,
+    shell_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    webui_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    free_disk_bytes: Option<u64>,
+    timestamp: String,
+}
+
+/// 连续送达失败的次数与熔断截止时间。
+#[derive(Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Breaker {
+    fn allows(&mut self, now: Instant) -> bool {
+        match self.open_until {
+            Some(until) if now < until => false,
+            Some(_) => {
+                // 冷却结束后先放行一次，失败则重新熔断
+                self.open_until = None;
+                self.failures = BREAKER_THRESHOLD - 1;
+                true
+            }
+            None => true,
+        }
+    }
+
+    /// 返回本次是否触发了熔断。
+    fn record(&mut self, delivered: bool, now: Instant) -> bool {
+        if delivered {
+            *self = Self::default();
+            return false;
+        }
+        self.failures += 1;
+        if self.failures >= BREAKER_THRESHOLD && self.open_until.is_none() {
+            self.open_until = Some(now + BREAKER_COOLDOWN);
+            return true;
+        }
+        false
+    }
+}
+
+/// 记一次服务重启，计入之后报告中的重启次数。
+pub fn note_restart(app: &AppHandle, service: &str) {
+    if let Some(state) = app.try_state::<WebhookState>() {
+        if let Ok(mut restarts) = state.restarts.lock() {
+            *restarts.entry(service.to_string()).or_default() += 1;
+        }
+    }
+}
+
+/// 按配置在后台发送一次报告；未配置地址或未选择该时机时什么也不做。
+pub fn notify(app: &AppHandle, trigger: Trigger, message: impl Into<String>) {
+    let Some(url) = configured_url(app) else {
+        return;
+    };
+    if !configured_triggers(app).contains(&trigger) {
+        return;
+    }
+    let message = message.into();
+    // 同一类报告再次触发时取消上一份尚在重试的，只发送较新的那份
+    tasks::spawn(
+        app,
+        &format!("webhook:{}", trigger.name()),
+        Scope::Shell,
+        move |app, token| deliver(&app, &url, trigger, message, &token),
+    );
+}
+
+/// 启动每日报告的计时线程，在 setup 中调用一次。
+pub fn start(app: &AppHandle) {
+    tasks::spawn(app, "webhook-daily", Scope::Shell, move |app, token| {
+        while token.sleep(DAILY_POLL) {
+            if configured_url(&app).is_none()
+                || !configured_triggers(&app).contains(&Trigger::Daily)
+            {
+                continue;
+            }
+            let Some(stamp_path) = daily_stamp_path(&app) else {
+                continue;
+            };
+            let now = unix_secs(SystemTime::now());
+            match read_stamp(&stamp_path) {
+                // 首次启用时从现在开始计时
+                None => {
+                    let _ = fs::write(&stamp_path, now.to_string());
+                }
+                Some(last) if now.saturating_sub(last) >= DAILY_INTERVAL.as_secs() => {
+                    let _ = fs::write(&stamp_path, now.to_string());
+                    notify(&app, Trigger::Daily, "PT Nexus 运行中");
+                }
+                Some(_) => {}
+            }
+        }
+    });
+}
+
+/// 立即发送一次测试报告，不重试、不受熔断限制；`url` 为空时使用 runtime.env 中的地址。
+pub fn test(app: &AppHandle, url: Option<&str>) -> Result<(), String> {
+    let url = match url.map(str::trim).filter(|url| !url.is_empty()) {
+        Some(url) => validate_url(url)?,
+        None => configured_url(app).ok_or_else(|| format!("未设置 {URL_KEY}"))?,
+    };
+    let report = build_report(app, Trigger::Test, "这是一条测试消息".to_string());
+    post(&url, &report, &assetcache::network_env(app))?;
+    if let Some(state) = app.try_state::<WebhookState>() {
+        if let Ok(mut breaker) = state.breaker.lock() {
+            breaker.record(true, Instant::now());
+        }
+    }
+    Ok(())
+}
+
+fn deliver(
+    app: &AppHandle,
+    url: &tauri::Url,
+    trigger: Trigger,
+    message: String,
+    token: &tasks::CancelToken,
+) {
+    let Some(state) = app.try_state::<WebhookState>() else {
+        return;
+    };
+    if !state
+        .breaker
+        .lock()
+        .map(|mut b| b.allows(Instant::now()))
+        .unwrap_or(false)
+    {
+        return;
+    }
+    let report = build_report(app, trigger, message);
+    let env = assetcache::network_env(app);
+    let mut result = post(url, &report, &env);
+    for delay in RETRY_DELAYS {
+        if result.is_ok() {
+            break;
+        }
+        if !token.sleep(delay) {
+            return;
+        }
+        result = post(url, &report, &env);
+    }
+
+    let host = url.host_str().unwrap_or("");
+    let tripped = state
+        .breaker
+        .lock()
+        .map(|mut b| b.record(result.is_ok(), Instant::now()))
+        .unwrap_or(false);
+    if let Err(err) = result {
+        runtime::shell_log(
+            app,
+            &format!(
+                "[WARN] Webhook（{host}）发送 {} 报告失败: {err}",
+                trigger.name()
+            ),
+        );
+    }
+    if tripped {
+        runtime::shell_log(
+            app,
+            &format!(
+                "[WARN] Webhook（{host}）连续 {BREAKER_THRESHOLD} 次发送失败，暂停 {} 分钟",
+                BREAKER_COOLDOWN.as_secs() / 60
+            ),
+        );
+    }
+}
+
+fn build_report(app: &AppHandle, trigger: Trigger, message: String) -> Report {
+    let snapshot = status::snapshot(app);
+    let (uptime_secs, restarts) = app
+        .try_state::<WebhookState>()
+        .map(|state| {
+            let restarts = state.restarts.lock().map(|r| r.clone()).unwrap_or_default();
+            (state.started.elapsed().as_secs(), restarts)
+        })
+        .unwrap_or_default();
+    let title = match trigger {
+        Trigger::Daily => "PT Nexus 每日报告",
+        Trigger::Failure => "PT Nexus 运行异常",
+        Trigger::Update => "PT Nexus 已更新",
+        Trigger::Test => "PT Nexus 测试消息",
+    };
+    Report {
+        event: trigger.name(),
+        title: title.to_string(),
+        message,
+        runtime_state: snapshot.runtime_state,
+        uptime_secs,
+        restarts,
+        shell_version: snapshot.shell_version,
+        webui_version: injections::webui_version(app),
+        free_disk_bytes: datadir::active_profile(app)
+            .ok()
+            .and_then(|profile| datadir::free_space(&profile.root)),
+        timestamp: runtime::format_utc_timestamp(SystemTime::now()),
+    }
+}
+
+fn post(url: &tauri::Url, report: &Report, env: &HashMap<String, String>) -> Result<(), String> {
+    let body = serde_json::to_string(report).map_err(|e| e.to_string())?;
+    let mut command = Command::new("curl");
+    command
+        .args(["--silent", "--show-error", "--request", "POST"])
+        .args(["--proto", "=http,https"])
+        .args(["--max-time", REQUEST_TIMEOUT_SECS])
+        .args([
+            "--user-agent",
+            concat!("PT-Nexus-Desktop/", env!("CARGO_PKG_VERSION")),
+        ])
+        .args(["--header", "Content-Type: application/json"])
+        .args(["--data-binary", &body])
+        .args(["--output", NULL_DEVICE])
+        .args(["--write-out", "%{http_code}"])
+        .arg("--")
+        .arg(url.as_str())
+        .envs(env);
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW);
+    let output = command.output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => "未找到 curl，无法发送 Webhook".to_string(),
+        _ => format!("启动 curl 失败: {e}"),
+    })?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    let status = String::from_utf8_lossy(&output.stdout);
+    match status.trim().parse::<u16>() {
+        Ok(code) if (200..300).contains(&code) => Ok(()),
+        Ok(code) => Err(format!("HTTP {code}")),
+        Err(_) => Err("无法识别的响应".to_string()),
+    }
+}
+
+fn configured_url(app: &AppHandle) -> Option<tauri::Url> {
+    let url = runtime::read_runtime_setting(app, URL_KEY)?;
+    if url.trim().is_empty() {
+        return None;
+    }
+    match validate_url(&url) {
+        Ok(url) => Some(url),
+        Err(err) => {
+            runtime::shell_log(app, &format!("[WARN] {err}"));
+            None
+        }
+    }
+}
+
+fn configured_triggers(app: &AppHandle) -> Vec<Trigger> {
+    runtime::read_runtime_setting(app, EVENTS_KEY)
+        .map(|list| parse_triggers(&list))
+        .unwrap_or_else(|| DEFAULT_TRIGGERS.to_vec())
+}
+
+fn parse_triggers(list: &str) -> Vec<Trigger> {
+    list.split(',')
+        .filter_map(|name| match name.trim().to_ascii_lowercase().as_str() {
+            "daily" => Some(Trigger::Daily),
+            "failure" => Some(Trigger::Failure),
+            "update" => Some(Trigger::Update),
+            _ => None,
+        })
+        .collect()
+}
+
+pub(crate) fn validate_url(url: &str) -> Result<tauri::Url, String> {
+    let parsed = tauri::Url::parse(url.trim()).map_err(|_| format!("{URL_KEY} 不是有效的地址"))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!("{URL_KEY} 只支持 http:// 或 https:// 地址"));
+    }
+    Ok(parsed)
+}
+
+fn daily_stamp_path(app: &AppHandle) -> Option<PathBuf> {
+    let dir = app.path().app_data_dir().ok()?;
+    Some(dir.join(DAILY_STAMP_FILE))
+}
+
+fn read_stamp(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triggers_parse_known_names_only() {
+        assert_eq!(
+            parse_triggers(" Daily, update ,bogus"),
+            [Trigger::Daily, Trigger::Update]
+        );
+        assert!(parse_triggers("").is_empty());
+        assert!(validate_url("https://ntfy.sh/ptnexus").is_ok());
+        assert!(validate_url("file:///etc/passwd").is_err());
+        assert!(validate_url("ntfy.sh/ptnexus").is_err());
+    }
+
+    #[test]
+    fn breaker_opens_after_repeated_failures_and_half_opens_after_cooldown() {
+        let mut breaker = Breaker::default();
+        let now = Instant::now();
+        for _ in 0..BREAKER_THRESHOLD - 1 {
+            assert!(breaker.allows(now));
+            assert!(!breaker.record(false, now));
+        }
+        assert!(breaker.record(false, now));
+        assert!(!breaker.allows(now + Duration::from_secs(60)));
+
+        let later = now + BREAKER_COOLDOWN;
+        assert!(breaker.allows(later));
+        assert!(breaker.record(false, later));
+        assert!(!breaker.allows(later));
+
+        breaker.open_until = None;
+        assert!(breaker.allows(later));
+        assert!(!breaker.record(true, later));
+        assert_eq!(breaker.failures, 0);
+    }
+}
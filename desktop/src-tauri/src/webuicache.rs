@@ -0,0 +1,308 @@
+//! 冷启动时先显示上次缓存的 WebUI，不必等后端就绪才看到界面。
+//!
+//! 运行时页面每次加载完成后，把 updater 提供的静态文件（`PTNEXUS_STATIC_DIR`：index.html 与带哈希的
+//! assets）连同当时的 WebUI 版本复制到 `<应用数据目录>/webui-cache/`。下次创建主窗口时，若本地
+//! CHANGELOG.json 中的版本与缓存一致，主窗口直接打开 `ptnexus-cache://` 上的缓存页面：SPA 渲染出界面，
+//! 通过 `__PTNEXUS_DESKTOP__.coldStart` 得知后端尚未就绪并显示“正在连接后端服务”；`/api/` 请求一律返回 503。
+//! 服务就绪后仍按原流程导航到运行时页面。
+//!
+//! 版本不一致（更新过 WebUI）、读不到版本、缓存不完整或 WebUI 部署在子路径下时不使用缓存，仍显示启动页。
+
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, Runtime, UriSchemeContext};
+
+use crate::tasks::{self, Scope};
+use crate::{injections, runtime};
+
+/// 自定义协议名，注册见 lib.rs。
+pub const SCHEME: &str = "ptnexus-cache";
+const CACHE_DIR: &str = "webui-cache";
+/// 缓存目录中记录 WebUI 版本的文件，最后写入，存在即表示复制完整。
+const VERSION_FILE: &str = ".version";
+const INDEX_FILE: &str = "index.html";
+const OFFLINE_API_BODY: &str = r#"{"success":false,"message":"正在连接后端服务"}"#;
+
+static REFRESHING: AtomicBool = AtomicBool::new(false);
+
+/// 主窗口的初始地址：缓存可用时返回缓存页面，否则返回 `None`，使用配置中的启动页。
+pub fn start_url(app: &AppHandle) -> Option<tauri::Url> {
+    if runtime::read_runtime_setting(app, "PTNEXUS_BASE_PATH")
+        .is_some_and(|base| !base.trim().is_empty())
+    {
+        return None;
+    }
+    let dir = cache_dir(app)?;
+    let version = injections::webui_version(app)?;
+    if !is_valid(&dir, &version) {
+        return None;
+    }
+    tauri::Url::parse(&index_url()).ok()
+}
+
+/// 运行时页面加载完成后调用：WebUI 版本变化时在后台重新复制静态文件。
+pub fn refresh(app: &AppHandle) {
+    if REFRESHING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tasks::spawn(app, "webui-cache-refresh", Scope::Shell, |app, _| {
+        if let Err(err) = refresh_now(&app) {
+            runtime::shell_log(&app, &format!("[WARN] 更新 WebUI 缓存失败: {err}"));
+        }
+        REFRESHING.store(false, Ordering::SeqCst);
+    });
+}
+
+fn refresh_now(app: &AppHandle) -> Result<(), String> {
+    let Some(dir) = cache_dir(app) else {
+        return Ok(());
+    };
+    let Some(version) = injections::webui_version(app) else {
+        return Ok(());
+    };
+    let Some(static_dir) = app
+        .try_state::<runtime::RuntimeManager>()
+        .and_then(|runtime| runtime.context())
+        .and_then(|context| context.common_env.get("PTNEXUS_STATIC_DIR").cloned())
+    else {
+        return Ok(());
+    };
+    if update(&dir, Path::new(&static_dir), &version)? {
+        runtime::shell_log(
+            app,
+            &format!("[INFO] 已缓存 WebUI {version}，下次启动时先显示缓存的界面"),
+        );
+    }
+    Ok(())
+}
+
+/// 缓存与 `version` 不一致时用 `static_dir` 替换整个缓存目录，返回是否替换。
+fn update(dir: &Path, static_dir: &Path, version: &str) -> Result<bool, String> {
+    if is_valid(dir, version) {
+        return Ok(false);
+    }
+    if !static_dir.join(INDEX_FILE).is_file() {
+        return Err(format!("{} 中没有 {INDEX_FILE}", static_dir.display()));
+    }
+    let staging = sibling(dir, "tmp");
+    let previous = sibling(dir, "old");
+    let _ = fs::remove_dir_all(&staging);
+    let _ = fs::remove_dir_all(&previous);
+    let copied =
+        copy_dir(static_dir, &staging).and_then(|_| fs::write(staging.join(VERSION_FILE), version));
+    if let Err(err) = copied {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(format!("复制 {} 失败: {err}", static_dir.display()));
+    }
+    if dir.exists() {
+        fs::rename(dir, &previous).map_err(|e| format!("替换 {} 失败: {e}", dir.display()))?;
+    }
+    fs::rename(&staging, dir).map_err(|e| format!("替换 {} 失败: {e}", dir.display()))?;
+    let _ = fs::remove_dir_all(&previous);
+    Ok(true)
+}
+
+fn is_valid(dir: &Path, version: &str) -> bool {
+    dir.join(INDEX_FILE).is_file()
+        && fs::read_to_string(dir.join(VERSION_FILE)).is_ok_and(|cached| cached.trim() == version)
+}
+
+/// 自定义协议的处理函数：返回缓存中的文件，SPA 路由返回 index.html。
+pub fn handle<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+) -> Response<Cow<'static, [u8]>> {
+    let Some(dir) = ctx
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(CACHE_DIR))
+    else {
+        return respond(StatusCode::NOT_FOUND, "text/plain", Vec::new());
+    };
+    match resolve(&dir, request.uri().path()) {
+        Resolved::Api => respond(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "application/json",
+            OFFLINE_API_BODY.as_bytes().to_vec(),
+        ),
+        Resolved::File(path) => match fs::read(&path) {
+            Ok(bytes) => respond(StatusCode::OK, mime_type(&path), bytes),
+            Err(_) => respond(StatusCode::NOT_FOUND, "text/plain", Vec::new()),
+        },
+        Resolved::NotFound => respond(StatusCode::NOT_FOUND, "text/plain", Vec::new()),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Resolved {
+    Api,
+    File(PathBuf),
+    NotFound,
+}
+
+/// 把请求路径映射到缓存中的文件；拒绝 `..` 与以点开头的路径段（含版本文件）。
+fn resolve(dir: &Path, path: &str) -> Resolved {
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if segments.first() == Some(&"api") {
+        return Resolved::Api;
+    }
+    if segments
+        .iter()
+        .any(|segment| segment.starts_with('.') || segment.contains('\\'))
+    {
+        return Resolved::NotFound;
+    }
+    let file = segments
+        .iter()
+        .fold(dir.to_path_buf(), |path, segment| path.join(segment));
+    if !segments.is_empty() && file.is_file() {
+        return Resolved::File(file);
+    }
+    // 带扩展名的是静态资源，缺失时不能用 index.html 代替
+    if segments.last().is_some_and(|last| last.contains('.')) {
+        return Resolved::NotFound;
+    }
+    Resolved::File(dir.join(INDEX_FILE))
+}
+
+fn respond(status: StatusCode, mime: &str, body: Vec<u8>) -> Response<Cow<'static, [u8]>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, mime)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Cow::Owned(body))
+        .unwrap_or_else(|_| Response::new(Cow::Borrowed(&[][..])))
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "html" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "wasm" => "application/wasm",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Windows 上自定义协议经 `http://<scheme>.localhost/` 访问，其余平台为 `<scheme>://localhost/`。
+fn index_url() -> String {
+    if cfg!(target_os = "windows") {
+        format!("http://{SCHEME}.localhost/")
+    } else {
+        format!("{SCHEME}://localhost/")
+    }
+}
+
+fn cache_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(CACHE_DIR))
+}
+
+fn sibling(dir: &Path, suffix: &str) -> PathBuf {
+    let mut name = dir.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{suffix}"));
+    dir.with_file_name(name)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn paths_map_to_cached_files() {
+        let dir = temp_dir("resolve");
+        fs::create_dir_all(dir.join("assets")).unwrap();
+        fs::write(dir.join(INDEX_FILE), "<html>").unwrap();
+        fs::write(dir.join("assets/index-abc123.js"), "").unwrap();
+        fs::write(dir.join(VERSION_FILE), "v1").unwrap();
+
+        assert_eq!(resolve(&dir, "/api/auth/status"), Resolved::Api);
+        assert_eq!(
+            resolve(&dir, "/assets/index-abc123.js"),
+            Resolved::File(dir.join("assets/index-abc123.js"))
+        );
+        assert_eq!(resolve(&dir, "/"), Resolved::File(dir.join(INDEX_FILE)));
+        assert_eq!(
+            resolve(&dir, "/settings/sites"),
+            Resolved::File(dir.join(INDEX_FILE))
+        );
+        assert_eq!(resolve(&dir, "/assets/missing-1.js"), Resolved::NotFound);
+        assert_eq!(resolve(&dir, "/../secret"), Resolved::NotFound);
+        assert_eq!(resolve(&dir, "/.version"), Resolved::NotFound);
+        assert_eq!(
+            mime_type(Path::new("a/b.JS")),
+            "text/javascript; charset=utf-8"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn update_replaces_cache_only_when_version_changes() {
+        let root = temp_dir("update");
+        let dist = root.join("dist");
+        let cache = root.join(CACHE_DIR);
+        fs::create_dir_all(dist.join("assets")).unwrap();
+        fs::write(dist.join(INDEX_FILE), "v1").unwrap();
+        fs::write(dist.join("assets/app-1.js"), "").unwrap();
+
+        assert!(!is_valid(&cache, "v1"));
+        assert!(update(&cache, &dist, "v1").unwrap());
+        assert!(is_valid(&cache, "v1"));
+        assert!(!update(&cache, &dist, "v1").unwrap());
+
+        fs::remove_file(dist.join("assets/app-1.js")).unwrap();
+        fs::write(dist.join("assets/app-2.js"), "").unwrap();
+        assert!(update(&cache, &dist, "v2").unwrap());
+        assert!(cache.join("assets/app-2.js").is_file());
+        assert!(!cache.join("assets/app-1.js").exists());
+        assert!(!is_valid(&cache, "v1"));
+        assert!(!sibling(&cache, "tmp").exists() && !sibling(&cache, "old").exists());
+
+        assert!(update(&cache, &root.join("missing"), "v3").is_err());
+        assert!(is_valid(&cache, "v2"));
+        let _ = fs::remove_dir_all(&root);
+    }
+}
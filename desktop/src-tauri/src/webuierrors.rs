@@ -0,0 +1,328 @@
+//! WebUI 脚本错误：收集主窗口页面中未捕获的异常与 Promise 拒绝，便于排查“桌面版打不开/点了没反应”。
+//!
+//! 初始化脚本监听 `error` 与 `unhandledrejection`，通过 `report_webui_error` 交给桌面壳，追加写入
+//! `logs/webui-errors.log`（每行一条 JSON，超过上限后轮转为 `webui-errors.1.log`），并在运行记录中计数。
+//! 渲染循环里的错误可能每秒触发成百上千次，所以每分钟最多写入 50 条，其余只计数，下一分钟第一次
+//! 上报时补写一条汇总。诊断页与日志查看器读取最近 50 条。
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::journal::{self, Severity};
+use crate::runtime::format_utc_timestamp;
+use crate::{datadir, logs};
+
+pub const LOG_FILE: &str = "webui-errors.log";
+const ROTATED_FILE: &str = "webui-errors.1.log";
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const RATE_LIMIT: u32 = 50;
+/// 诊断页与 `get_webui_errors` 返回的条数。
+pub const RECENT_LIMIT: usize = 50;
+/// 单个字段的最大字符数，压缩后的脚本错误堆栈可能很长。
+const MAX_FIELD_CHARS: usize = 4000;
+
+/// 作为初始化脚本注入主窗口。用 addEventListener 而不是覆盖 window.onerror，不影响页面自己的处理。
+pub const ERROR_HOOK_JS: &str = r#"
+(function() {
+  if (window.__PTNEXUS_ERROR_HOOK__) return;
+  window.__PTNEXUS_ERROR_HOOK__ = true;
+
+  function report(error) {
+    try {
+      var pending = window.__TAURI_INTERNALS__.invoke('report_webui_error', { error: error });
+      if (pending && typeof pending.catch === 'function') pending.catch(function() {});
+    } catch (e) {}
+  }
+  function stackOf(value) {
+    return value && value.stack ? String(value.stack) : null;
+  }
+
+  window.addEventListener('error', function(event) {
+    // 图片、脚本等资源加载失败也会触发 error 事件，但没有 message
+    if (!event.message) return;
+    report({
+      message: String(event.message),
+      source: event.filename || null,
+      line: event.lineno || null,
+      stack: stackOf(event.error)
+    });
+  });
+  window.addEventListener('unhandledrejection', function(event) {
+    var reason = event.reason;
+    report({
+      message: 'Unhandled rejection: ' + (reason && reason.message ? reason.message : String(reason)),
+      source: null,
+      line: null,
+      stack: stackOf(reason)
+    });
+  });
+})();
+"#;
+
+/// 页面上报的内容。
+#[derive(Debug, Deserialize)]
+pub struct WebuiError {
+    pub message: String,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub line: Option<u32>,
+    #[serde(default)]
+    pub stack: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WebuiErrorEntry {
+    /// UTC 时间，如 2024-01-01T09:02:00Z。
+    pub timestamp: String,
+    pub unix_ms: u64,
+    pub message: String,
+    pub source: Option<String>,
+    pub line: Option<u32>,
+    pub stack: Option<String>,
+    /// 汇总条目：上一分钟因超过限额而未写入的错误数。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suppressed: Option<u32>,
+}
+
+#[derive(Default)]
+pub struct ErrorLimiter(Mutex<RateWindow>);
+
+#[derive(Default)]
+struct RateWindow {
+    started: Option<Instant>,
+    logged: u32,
+    suppressed: u32,
+}
+
+#[derive(Debug, PartialEq)]
+enum Admit {
+    /// 写入日志。`first` 表示本分钟的第一条；`previous` 为上一分钟的（总数, 未写入数）。
+    Log {
+        first: bool,
+        previous: Option<(u32, u32)>,
+    },
+    Drop,
+}
+
+impl RateWindow {
+    fn admit(&mut self, now: Instant) -> Admit {
+        let expired = self
+            .started
+            .is_none_or(|started| now.duration_since(started) >= RATE_WINDOW);
+        if expired {
+            let total = self.logged + self.suppressed;
+            let previous = (total > 1).then_some((total, self.suppressed));
+            *self = RateWindow {
+                started: Some(now),
+                logged: 1,
+                suppressed: 0,
+            };
+            return Admit::Log {
+                first: true,
+                previous,
+            };
+        }
+        if self.logged >= RATE_LIMIT {
+            self.suppressed += 1;
+            return Admit::Drop;
+        }
+        self.logged += 1;
+        Admit::Log {
+            first: false,
+            previous: None,
+        }
+    }
+}
+
+/// 记录一条页面上报的错误；超过每分钟限额时只计数。
+pub fn report(app: &AppHandle, error: WebuiError) -> Result<(), String> {
+    let admit = match app.try_state::<ErrorLimiter>() {
+        Some(limiter) => limiter
+            .0
+            .lock()
+            .map_err(|_| "错误记录状态异常".to_string())?
+            .admit(Instant::now()),
+        None => return Ok(()),
+    };
+    let Admit::Log { first, previous } = admit else {
+        return Ok(());
+    };
+    let logs_dir = logs_dir(app)?;
+
+    if let Some((total, suppressed)) = previous {
+        journal::record(
+            app,
+            Severity::Warn,
+            None,
+            format!("上一分钟 WebUI 共出现 {total} 个脚本错误，详见 {LOG_FILE}"),
+        );
+        if suppressed > 0 {
+            let mut summary = entry(
+                format!("上一分钟另有 {suppressed} 个脚本错误超过记录上限，未写入日志"),
+                None,
+                None,
+                None,
+            );
+            summary.suppressed = Some(suppressed);
+            append(&logs_dir, &summary);
+        }
+    }
+
+    let entry = entry(error.message, error.source, error.line, error.stack);
+    if first {
+        journal::record(
+            app,
+            Severity::Warn,
+            None,
+            format!("WebUI 脚本错误: {}", journal::first_line(&entry.message)),
+        );
+    }
+    append(&logs_dir, &entry);
+    Ok(())
+}
+
+/// 最近的 RECENT_LIMIT 条，按时间先后排列。
+pub fn recent(app: &AppHandle) -> Result<Vec<WebuiErrorEntry>, String> {
+    let lines = logs::tail(app, "webui-errors", RECENT_LIMIT)?;
+    Ok(parse_lines(&lines))
+}
+
+fn parse_lines(lines: &[String]) -> Vec<WebuiErrorEntry> {
+    lines
+        .iter()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn entry(
+    message: String,
+    source: Option<String>,
+    line: Option<u32>,
+    stack: Option<String>,
+) -> WebuiErrorEntry {
+    let now = SystemTime::now();
+    WebuiErrorEntry {
+        timestamp: format_utc_timestamp(now),
+        unix_ms: now
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        message: truncate(&message),
+        source: source.map(|source| truncate(&source)),
+        line,
+        stack: stack.map(|stack| truncate(&stack)),
+        suppressed: None,
+    }
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_FIELD_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+fn logs_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    datadir::active_profile(app).map(|profile| profile.root.join("logs"))
+}
+
+fn append(dir: &Path, entry: &WebuiErrorEntry) {
+    let path = dir.join(LOG_FILE);
+    if fs::metadata(&path)
+        .map(|m| m.len() >= MAX_FILE_BYTES)
+        .unwrap_or(false)
+    {
+        let _ = fs::rename(&path, dir.join(ROTATED_FILE));
+    }
+    let Ok(line) = serde_json::to_string(entry) else {
+        return;
+    };
+    let _ = fs::create_dir_all(dir);
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "{line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn rate_window_caps_entries_and_summarizes_the_rest() {
+        let mut window = RateWindow::default();
+        let start = Instant::now();
+        assert_eq!(
+            window.admit(start),
+            Admit::Log {
+                first: true,
+                previous: None
+            }
+        );
+        for _ in 1..RATE_LIMIT {
+            assert_eq!(
+                window.admit(start),
+                Admit::Log {
+                    first: false,
+                    previous: None
+                }
+            );
+        }
+        assert_eq!(window.admit(start), Admit::Drop);
+        assert_eq!(window.admit(start + Duration::from_secs(59)), Admit::Drop);
+
+        assert_eq!(
+            window.admit(start + RATE_WINDOW),
+            Admit::Log {
+                first: true,
+                previous: Some((RATE_LIMIT + 2, 2))
+            }
+        );
+        // 一分钟内只有一条时不再汇总
+        assert_eq!(
+            window.admit(start + RATE_WINDOW * 3),
+            Admit::Log {
+                first: true,
+                previous: None
+            }
+        );
+    }
+
+    #[test]
+    fn entries_round_trip_through_the_log_file() {
+        let dir = temp_dir("log");
+
+        let long_stack = "at f (app.js:1:1)\n".repeat(MAX_FIELD_CHARS);
+        let first = entry(
+            "TypeError: x is undefined".to_string(),
+            Some("http://127.0.0.1:5274/assets/index.js".to_string()),
+            Some(12),
+            Some(long_stack),
+        );
+        append(&dir, &first);
+        let mut summary = entry("汇总".to_string(), None, None, None);
+        summary.suppressed = Some(3);
+        append(&dir, &summary);
+
+        let content = fs::read_to_string(dir.join(LOG_FILE)).unwrap();
+        let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+        lines.insert(1, "not json".to_string());
+        let parsed = parse_lines(&lines);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0], first);
+        assert_eq!(
+            parsed[0].stack.as_ref().unwrap().chars().count(),
+            MAX_FIELD_CHARS + 1
+        );
+        assert_eq!(parsed[1].suppressed, Some(3));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
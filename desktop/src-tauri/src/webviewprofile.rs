@@ -0,0 +1,218 @@
+//! WebView 的用户数据目录（localStorage、Cookie、缓存）。
+//!
+//! Tauri 默认把 WebView2 的数据放在 `%LOCALAPPDATA%\<identifier>\EBWebView`，CCleaner 一类的清理工具
+//! 会把它当作浏览器缓存清掉，用户随之丢失 WebUI 的登录状态与界面偏好。Windows 上主窗口改用
+//! `<data_dir>/webview-profile`，与数据库一起备份，也不会被清理工具当作缓存。
+//!
+//! 迁移只在新目录尚不存在时进行一次：旧目录中除缓存外的内容先复制到 `webview-profile.tmp`，
+//! 完整复制后再改名为 `webview-profile`，旧目录保留不删。复制失败（文件被占用、磁盘空间不足等）时
+//! 删除复制了一半的内容，以空目录重新开始并通知用户需要重新登录；新目录此后已存在，不会再次迁移或提示。
+//! Linux 上的默认位置本就在数据目录中，macOS 的 WKWebView 不支持自定义目录，这两个平台保持不变。
+//!
+//! 所有窗口都通过 [`apply_shared`] 创建：打印导出窗口加载运行时页面，需要与主窗口相同的登录状态；
+//! 同一数据目录下的 WebView2 还要求启动参数一致，否则拒绝创建，所以一并带上主窗口的 GPU 参数。
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager, Runtime, WebviewWindowBuilder};
+
+use crate::gpu;
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+const PROFILE_DIR_NAME: &str = "webview-profile";
+/// WebView2 在数据目录下创建的子目录。
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+const WEBVIEW2_DIR_NAME: &str = "EBWebView";
+/// 可以重新生成的缓存目录，迁移时不复制。
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+const SKIPPED_DIRS: &[&str] = &[
+    "Cache",
+    "Code Cache",
+    "GPUCache",
+    "GrShaderCache",
+    "ShaderCache",
+    "Crashpad",
+];
+
+/// 主窗口 WebView 数据所在的目录；无法解析时为 None。
+pub fn profile_path(app: &AppHandle) -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    {
+        app.path()
+            .app_data_dir()
+            .ok()
+            .map(|dir| dir.join(PROFILE_DIR_NAME).join(WEBVIEW2_DIR_NAME))
+    }
+    #[cfg(target_os = "linux")]
+    {
+        app.path().app_local_data_dir().ok()
+    }
+    #[cfg(target_os = "macos")]
+    {
+        app.path().home_dir().ok().map(|home| {
+            home.join("Library")
+                .join("WebKit")
+                .join(&app.config().identifier)
+        })
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+    {
+        let _ = app;
+        None
+    }
+}
+
+/// 创建窗口前调用：使用与主窗口相同的 WebView 数据目录与启动参数。
+pub fn apply_shared<'a, R: Runtime, M: Manager<R>>(
+    app: &AppHandle,
+    builder: WebviewWindowBuilder<'a, R, M>,
+) -> WebviewWindowBuilder<'a, R, M> {
+    gpu::apply_to_builder(
+        apply_to_builder(app, builder),
+        gpu::disabled_at_startup(app),
+    )
+}
+
+/// 需要时迁移旧的 WebView2 数据，并让 WebView 使用数据目录中的位置。
+fn apply_to_builder<'a, R: Runtime, M: Manager<R>>(
+    app: &AppHandle,
+    builder: WebviewWindowBuilder<'a, R, M>,
+) -> WebviewWindowBuilder<'a, R, M> {
+    #[cfg(target_os = "windows")]
+    {
+        let (Ok(data_dir), Ok(legacy)) =
+            (app.path().app_data_dir(), app.path().app_local_data_dir())
+        else {
+            return builder;
+        };
+        let profile = data_dir.join(PROFILE_DIR_NAME);
+        if !profile.exists() {
+            migrate(app, &legacy, &profile);
+        }
+        builder.data_directory(profile)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = app;
+        builder
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn migrate(app: &AppHandle, legacy: &Path, profile: &Path) {
+    use tauri_plugin_notification::NotificationExt;
+
+    use crate::runtime;
+
+    let source = legacy.join(WEBVIEW2_DIR_NAME);
+    match copy_profile(&source, profile) {
+        Ok(true) => runtime::shell_log(
+            app,
+            &format!(
+                "[INFO] 已将 WebView 数据从 {} 迁移到 {}",
+                source.display(),
+                profile.display()
+            ),
+        ),
+        Ok(false) => {}
+        Err(err) => {
+            let _ = fs::create_dir_all(profile);
+            runtime::shell_log(
+                app,
+                &format!("[WARN] 迁移 WebView 数据失败，将使用新的空白数据: {err}"),
+            );
+            let _ = app
+                .notification()
+                .builder()
+                .title("PT Nexus 界面数据已重置")
+                .body("未能迁移原有的界面数据，需要重新登录 WebUI，界面偏好也已恢复默认。")
+                .show();
+        }
+    }
+}
+
+/// 把旧的 WebView2 数据复制到 `profile/EBWebView`，返回是否有数据被迁移。
+/// 先复制到同级的临时目录，成功后再改名，中途失败不会留下半个 profile。
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+fn copy_profile(source: &Path, profile: &Path) -> Result<bool, String> {
+    if !source.is_dir() {
+        return Ok(false);
+    }
+    let staging = crate::fsutil::tmp_path(profile);
+    let _ = fs::remove_dir_all(&staging);
+    let copied = copy_dir(source, &staging.join(WEBVIEW2_DIR_NAME))
+        .and_then(|_| fs::rename(&staging, profile))
+        .map_err(|e| format!("复制 {} 失败: {e}", source.display()));
+    if copied.is_err() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    copied.map(|_| true)
+}
+
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            if !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                copy_dir(&entry.path(), &target)?;
+            }
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsutil::temp_dir;
+
+    #[test]
+    fn profile_is_copied_without_caches() {
+        let dir = temp_dir("copy");
+        let source = dir.join("legacy").join(WEBVIEW2_DIR_NAME);
+        let storage = source.join("Default").join("Local Storage").join("leveldb");
+        fs::create_dir_all(&storage).unwrap();
+        fs::write(storage.join("000003.log"), b"token").unwrap();
+        fs::create_dir_all(source.join("Default").join("Cache")).unwrap();
+        fs::write(
+            source.join("Default").join("Cache").join("data_0"),
+            b"cache",
+        )
+        .unwrap();
+
+        let profile = dir.join("webview-profile");
+        assert_eq!(copy_profile(&source, &profile), Ok(true));
+        let copied = profile.join(WEBVIEW2_DIR_NAME).join("Default");
+        assert_eq!(
+            fs::read(
+                copied
+                    .join("Local Storage")
+                    .join("leveldb")
+                    .join("000003.log")
+            )
+            .unwrap(),
+            b"token"
+        );
+        assert!(!copied.join("Cache").exists());
+        assert!(!crate::fsutil::tmp_path(&profile).exists());
+        // 旧目录保留
+        assert!(source.join("Default").join("Cache").join("data_0").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn missing_legacy_profile_is_not_an_error() {
+        let dir = temp_dir("missing");
+        let profile = dir.join("webview-profile");
+        assert_eq!(copy_profile(&dir.join("legacy"), &profile), Ok(false));
+        assert!(!profile.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
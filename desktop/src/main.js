@@ -1 +1,42 @@
-// Tauri 桌面壳不承载业务前端，这里无需额外逻辑。
+// Tauri 桌面壳不承载业务前端，这里只把启动阶段（bootstrap-stage 事件）同步到页面的 aria-live 区域，
+// 让读屏软件能播报当前进度。
+(function () {
+  var stage = document.getElementById('stage');
+  var internals = window.__TAURI_INTERNALS__;
+  if (!stage || !internals) return;
+
+  try {
+    // 桌面壳的事件带递增的 seq，补取的旧阶段不能覆盖已收到的新阶段
+    var lastSeq = 0;
+    var show = function (payload) {
+      if (!payload || !payload.message) return;
+      if (typeof payload.seq === 'number') {
+        if (payload.seq <= lastSeq) return;
+        lastSeq = payload.seq;
+      }
+      stage.textContent = payload.message;
+    };
+    var handler = internals.transformCallback(function (event) {
+      show(event && event.payload);
+    });
+    var pending = internals.invoke('plugin:event|listen', {
+      event: 'bootstrap-stage',
+      target: { kind: 'Any' },
+      handler: handler,
+    });
+    if (pending && typeof pending.catch === 'function') pending.catch(function () {});
+
+    // 页面重新加载前发出的阶段不会再发，向桌面壳补取最近的一次
+    var missed = internals.invoke('sync_runtime_events', { lastSeenSeq: 0 });
+    if (missed && typeof missed.then === 'function') {
+      missed
+        .then(function (result) {
+          var events = (result && result.events) || [];
+          for (var i = 0; i < events.length; i++) {
+            if (events[i].event === 'bootstrap-stage') show(events[i].payload);
+          }
+        })
+        .catch(function () {});
+    }
+  } catch (e) {}
+})();
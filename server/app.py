@@ -24,7 +24,8 @@ from core.ratio_speed_limiter import start_ratio_speed_limiter, stop_ratio_speed
 
 # --- 日志基础配置 ---
 logging.basicConfig(
-    level=logging.DEBUG, format="%(asctime)s - [PID:%(process)d] - %(levelname)s - %(message)s"
+    level=os.getenv("LOG_LEVEL", "DEBUG").upper(),
+    format="%(asctime)s - [PID:%(process)d] - %(levelname)s - %(message)s",
 )
 logging.info("=== Flask 应用日志系统已初始化 ===")
 
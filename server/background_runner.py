@@ -22,7 +22,8 @@ from app import (
 )
 
 logging.basicConfig(
-    level=logging.DEBUG, format="%(asctime)s - [PID:%(process)d] - %(levelname)s - %(message)s"
+    level=os.getenv("LOG_LEVEL", "DEBUG").upper(),
+    format="%(asctime)s - [PID:%(process)d] - %(levelname)s - %(message)s",
 )
 
 shutdown_event = threading.Event()
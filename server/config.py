@@ -53,6 +53,9 @@ os.makedirs(TEMP_DIR, exist_ok=True)
 
 CONFIG_FILE = os.path.join(DATA_DIR, "config.json")
 
+# 桌面端移动数据库（datamove.rs）后只改数据库所在目录，config.json 等仍在 DATA_DIR
+SQLITE_PATH = os.path.join(os.getenv("PTNEXUS_DB_DIR", "").strip() or DATA_DIR, "pt_stats.db")
+
 
 class ConfigManager:
     """管理应用的配置信息，处理加载和保存操作。"""
@@ -354,12 +357,12 @@ def get_db_config():
 
     elif db_choice == "sqlite":
         logging.info("数据库类型选择为 SQLite。")
-        db_path = os.path.join(DATA_DIR, "pt_stats.db")
+        db_path = SQLITE_PATH
         return {"db_type": "sqlite", "path": db_path}
 
     else:
         logging.warning(f"无效的 DB_TYPE 值: '{db_choice}'。将回退到使用 SQLite。")
-        db_path = os.path.join(DATA_DIR, "pt_stats.db")
+        db_path = SQLITE_PATH
         return {"db_type": "sqlite", "path": db_path}
 
 
@@ -0,0 +1,239 @@
+#!/usr/bin/env python3
+"""
+SQLite → MySQL / PostgreSQL 数据迁移脚本
+
+由桌面端在停止 server / batch 之后调用，目标库配置沿用 server 的环境变量：
+- PTNEXUS_MIGRATE_SOURCE_SQLITE: 源 sqlite 文件路径
+- DB_TYPE 以及 MYSQL_* / POSTGRES_*: 目标数据库配置
+
+带 --dry-run 时只连接并登录目标库、检查其中的表是否为空，不做任何写入，
+服务运行期间即可调用（桌面端“测试连接”与迁移前的校验）；此时源数据库可以不存在。
+
+输出协议（逐行写入 stdout）：
+- PROGRESS <表名> <已迁移>/<总数>
+- SKIP <表名> <原因>
+- CHECK <说明>（仅 --dry-run）
+- NOTEMPTY <表名>（仅 --dry-run，目标库中该表已有数据）
+- ERROR <说明>
+- DONE
+
+目标库中已有数据的表会导致迁移中止，避免覆盖用户已有的数据。
+"""
+
+import os
+import sqlite3
+import sys
+
+import mysql.connector
+import psycopg2
+
+from config import get_db_config
+from database import DatabaseManager
+
+BATCH_SIZE = 500
+CONNECT_TIMEOUT = 10
+
+
+def emit(line):
+    print(line, flush=True)
+
+
+def quote(db_type, name):
+    return f"`{name}`" if db_type == "mysql" else f'"{name}"'
+
+
+def target_columns(cursor, db_type, table):
+    """返回目标库中该表的列名集合，表不存在时返回 None。"""
+    try:
+        cursor.execute(f"SELECT * FROM {quote(db_type, table)} WHERE 1=0")
+        columns = {d[0] for d in cursor.description}
+        cursor.fetchall()
+        return columns
+    except Exception:
+        return None
+
+
+def count_rows(cursor, db_type, table):
+    cursor.execute(f"SELECT COUNT(*) FROM {quote(db_type, table)}")
+    return cursor.fetchone()[0]
+
+
+def connect_target(config):
+    """按 server 的数据库配置连接目标库，字符集与 server 一致。"""
+    if config["db_type"] == "mysql":
+        return mysql.connector.connect(
+            **config["mysql"],
+            charset="utf8mb4",
+            collation="utf8mb4_unicode_ci",
+            autocommit=False,
+            connection_timeout=CONNECT_TIMEOUT,
+        )
+    return psycopg2.connect(**config["postgresql"], connect_timeout=CONNECT_TIMEOUT)
+
+
+def connect_server(config):
+    """不指定库名连接数据库服务，用于确认目标库不存在时账号仍可登录。"""
+    if config["db_type"] == "mysql":
+        server_config = {k: v for k, v in config["mysql"].items() if k != "database"}
+        return mysql.connector.connect(**server_config, connection_timeout=CONNECT_TIMEOUT)
+    server_config = dict(config["postgresql"], database="postgres")
+    return psycopg2.connect(**server_config, connect_timeout=CONNECT_TIMEOUT)
+
+
+def non_empty_tables(conn, db_type, tables):
+    """返回目标库中已有数据的表，目标库中不存在的表忽略。"""
+    found = []
+    cursor = conn.cursor()
+    try:
+        for table in tables:
+            if target_columns(cursor, db_type, table) is None:
+                conn.rollback()
+                continue
+            if count_rows(cursor, db_type, table) > 0:
+                found.append(table)
+    finally:
+        cursor.close()
+    return found
+
+
+def source_tables(src):
+    return [
+        row[0] for row in src.execute(
+            "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+        )
+    ]
+
+
+def dry_run(target_config, source):
+    """只读地检查目标库：能否登录、迁移涉及的表是否为空。"""
+    tables = []
+    if source and os.path.exists(source):
+        src = sqlite3.connect(f"file:{source}?mode=ro", uri=True)
+        try:
+            tables = source_tables(src)
+        finally:
+            src.close()
+
+    db_type = target_config["db_type"]
+    database_name = target_config[db_type]["database"]
+    try:
+        conn = connect_target(target_config)
+    except Exception as e:
+        # 目标库不存在时迁移会自动创建，只要账号能登录数据库服务即可
+        try:
+            connect_server(target_config).close()
+        except Exception:
+            emit(f"ERROR 无法登录目标数据库: {e}")
+            return 4
+        emit(f"CHECK 目标数据库 {database_name} 不存在，迁移时将自动创建")
+        emit("DONE")
+        return 0
+
+    try:
+        emit(f"CHECK 已登录目标数据库 {database_name}")
+        for table in non_empty_tables(conn, db_type, tables):
+            emit(f"NOTEMPTY {table}")
+    except Exception as e:
+        emit(f"ERROR 检查目标数据库失败: {e}")
+        return 4
+    finally:
+        conn.close()
+
+    emit("DONE")
+    return 0
+
+
+def main():
+    check_only = "--dry-run" in sys.argv[1:]
+    source = os.getenv("PTNEXUS_MIGRATE_SOURCE_SQLITE")
+    if not check_only and (not source or not os.path.exists(source)):
+        emit(f"ERROR 源数据库不存在: {source}")
+        return 2
+
+    try:
+        target_config = get_db_config()
+    except SystemExit:
+        emit("ERROR 目标数据库配置不完整，请填写主机、端口、用户名、密码与库名")
+        return 2
+    db_type = target_config["db_type"]
+    if db_type == "sqlite":
+        emit("ERROR 目标数据库类型必须是 mysql 或 postgresql")
+        return 2
+
+    if check_only:
+        return dry_run(target_config, source)
+
+    src = sqlite3.connect(source)
+    tables = source_tables(src)
+
+    target = DatabaseManager(target_config)
+
+    # 建表前先确认目标库里没有用户数据
+    conn = connect_target(target_config)
+    try:
+        for table in non_empty_tables(conn, db_type, tables):
+            emit(f"ERROR 目标数据库的表 {table} 已有数据，请使用空数据库")
+            return 3
+    finally:
+        conn.close()
+
+    # 由 server 自身的初始化逻辑创建表结构（会写入站点预置数据，下面迁移时整体替换）
+    target.init_db()
+
+    placeholder = target.get_placeholder()
+    conn = connect_target(target_config)
+    cursor = conn.cursor()
+    try:
+        for table in tables:
+            columns_in_target = target_columns(cursor, db_type, table)
+            if columns_in_target is None:
+                conn.rollback()
+                emit(f"SKIP {table} 目标数据库中不存在该表")
+                continue
+
+            src_cursor = src.execute(f'SELECT * FROM "{table}"')
+            indexes = [i for i, d in enumerate(src_cursor.description) if d[0] in columns_in_target]
+            columns = [src_cursor.description[i][0] for i in indexes]
+            total = src.execute(f'SELECT COUNT(*) FROM "{table}"').fetchone()[0]
+
+            cursor.execute(f"DELETE FROM {quote(db_type, table)}")
+            insert_sql = "INSERT INTO {} ({}) VALUES ({})".format(
+                quote(db_type, table),
+                ", ".join(quote(db_type, c) for c in columns),
+                ", ".join([placeholder] * len(columns)),
+            )
+
+            done = 0
+            emit(f"PROGRESS {table} {done}/{total}")
+            while True:
+                rows = src_cursor.fetchmany(BATCH_SIZE)
+                if not rows:
+                    break
+                cursor.executemany(insert_sql, [tuple(row[i] for i in indexes) for row in rows])
+                done += len(rows)
+                emit(f"PROGRESS {table} {done}/{total}")
+
+            # 显式写入 id 后需要把 PostgreSQL 的自增序列推进到当前最大值
+            if db_type == "postgresql" and "id" in columns:
+                cursor.execute(
+                    f"SELECT setval(pg_get_serial_sequence('{table}', 'id'), "
+                    f"COALESCE((SELECT MAX(id) FROM {quote(db_type, table)}), 1))"
+                )
+                cursor.fetchall()
+
+        conn.commit()
+    except Exception as e:
+        conn.rollback()
+        emit(f"ERROR {e}")
+        return 1
+    finally:
+        cursor.close()
+        conn.close()
+        src.close()
+
+    emit("DONE")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
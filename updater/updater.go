@@ -2,6 +2,8 @@ package main
 
 import (
 	"context"
+	"crypto/sha256"
+	"encoding/hex"
 	"encoding/json"
 	"fmt"
 	"io"
@@ -44,6 +46,9 @@ var (
 	// 新增：互斥锁防止重复触发更新
 	updateMutex      sync.Mutex
 	isSystemUpdating bool
+
+	updateLockMutex   sync.Mutex
+	updateLockHolders int
 )
 
 func init() {
@@ -298,11 +303,13 @@ func runAutoUpdate() {
 	}
 
 	log.Printf("执行更新流程 (强制更新: %v)...", shouldForce)
+	acquireUpdateLock()
+	defer releaseUpdateLock()
 
 	// 5. 删除updates目录强制重新拉取
 	// 这符合你的要求：如果有更新，先清理旧目录确保干净
 	log.Println("清理 updates 目录以强制重新拉取...")
-	if err := os.RemoveAll(updateDir); err != nil {
+	if err := cleanUpdateDir(); err != nil {
 		log.Printf("删除updates目录失败: %v", err)
 		// 如果删除失败，可能影响后续流程，但尝试继续
 	}
@@ -353,10 +360,15 @@ func runAutoUpdate() {
 
 	// 10. 更新本地配置文件
 	srcConfig := filepath.Join(repoDir, "CHANGELOG.json")
+	previousConfig, _ := os.ReadFile(localConfigFile)
 	copyFile(srcConfig, localConfigFile)
 
 	log.Println("重启服务...")
-	restartServices()
+	if err := finishUpdate(remoteConfig.Mappings, backupDir); err != nil {
+		os.WriteFile(localConfigFile, previousConfig, 0644)
+		log.Printf("自动更新失败，已回滚: %v", err)
+		return
+	}
 
 	log.Printf("自动更新完成: %s", remoteVersion)
 }
@@ -572,6 +584,8 @@ func pullUpdateHandler(w http.ResponseWriter, r *http.Request) {
 
 	// 确保更新目录存在
 	os.MkdirAll(updateDir, 0755)
+	acquireUpdateLock()
+	defer releaseUpdateLock()
 
 	if _, err := os.Stat(repoDir); os.IsNotExist(err) {
 		// 首次克隆 - 先尝试 Gitee，超时则切换到 GitHub
@@ -786,6 +800,17 @@ func installUpdateHandler(w http.ResponseWriter, r *http.Request) {
 		return
 	}
 
+	if current := currentInstallStatus(); current.State == "running" {
+		json.NewEncoder(w).Encode(map[string]interface{}{
+			"success": false,
+			"error":   fmt.Sprintf("正在安装 %s，请等待完成", current.Version),
+		})
+		return
+	}
+
+	acquireUpdateLock()
+	defer releaseUpdateLock()
+
 	// 读取更新配置
 	configFile := filepath.Join(repoDir, "CHANGELOG.json")
 	data, err := os.ReadFile(configFile)
@@ -917,10 +942,40 @@ func installUpdateHandler(w http.ResponseWriter, r *http.Request) {
 
 	// 更新本地配置文件
 	srcConfig := filepath.Join(repoDir, "CHANGELOG.json")
+	previousConfig, _ := os.ReadFile(localConfigFile)
 	copyFile(srcConfig, localConfigFile)
 
 	log.Println("重启服务...")
-	restartServices()
+	if supervisedByDesktop() {
+		// 桌面壳会等进行中的批量任务结束才重启（最长 recycleTimeout），请求不能一直挂着：
+		// 立即返回，重启结果通过 /update/install/status 查询
+		setInstallStatus("running", remoteVersion, "文件已更新，正在等待桌面端重启服务")
+		acquireUpdateLock()
+		go func() {
+			defer releaseUpdateLock()
+			if err := finishUpdate(config.Mappings, backupDir); err != nil {
+				os.WriteFile(localConfigFile, previousConfig, 0644)
+				setInstallStatus("failed", remoteVersion, fmt.Sprintf("重启服务失败，已回滚: %v", err))
+				return
+			}
+			log.Printf("更新完成: %s", remoteVersion)
+			setInstallStatus("succeeded", remoteVersion, fmt.Sprintf("成功更新到 %s", remoteVersion))
+		}()
+		json.NewEncoder(w).Encode(map[string]interface{}{
+			"success": true,
+			"pending": true,
+			"message": "文件已更新，正在重启服务",
+		})
+		return
+	}
+	if err := finishUpdate(config.Mappings, backupDir); err != nil {
+		os.WriteFile(localConfigFile, previousConfig, 0644)
+		json.NewEncoder(w).Encode(map[string]interface{}{
+			"success": false,
+			"error":   fmt.Sprintf("重启服务失败，已回滚: %v", err),
+		})
+		return
+	}
 
 	log.Printf("更新完成: %s", config.History[0].Version)
 	json.NewEncoder(w).Encode(map[string]interface{}{
@@ -1097,6 +1152,11 @@ func stopServices() {
 		return
 	}
 
+	if supervisedByDesktop() {
+		log.Println("服务由桌面端托管，同步完成后再请求重启")
+		return
+	}
+
 	log.Println("正在停止Python服务...")
 	exec.Command("pkill", "-TERM", "-f", "python.*app.py").Run()
 	time.Sleep(2 * time.Second)
@@ -1121,10 +1181,255 @@ func restartServices() {
 		return
 	}
 
+	if supervisedByDesktop() {
+		// 桌面端未停止服务，回滚后的文件由 finishUpdate 请求桌面壳重新加载
+		return
+	}
+
 	cmd := exec.Command("/app/start-services.sh")
 	cmd.Start()
 }
 
+// 桌面端的服务进程由桌面壳托管（见 desktop/src-tauri/src/recycle.rs），
+// 更新器通过 UPDATE_DIR 中的请求/结果文件让桌面壳滚动重启服务。
+const (
+	recycleRequestFile = "recycle-request.json"
+	recycleResultFile  = "recycle-result.json"
+	updateLockFile     = "update.lock"
+
+	// 桌面壳会等进行中的批量任务结束后才重启
+	recycleTimeout = 6 * time.Hour
+)
+
+type recycleRequest struct {
+	ID         string            `json:"id"`
+	Components []string          `json:"components"`
+	Files      map[string]string `json:"files"`
+	// 服务名 -> 被替换的程序 -> 备份的旧版本，桌面壳据此保存回滚副本
+	Previous map[string]map[string]string `json:"previous"`
+}
+
+type recycleResult struct {
+	ID        string `json:"id"`
+	State     string `json:"state"`
+	Component string `json:"component"`
+	Message   string `json:"message"`
+}
+
+// 最近一次通过 /update/install 安装的状态，桌面端在后台等待重启结果时由 /update/install/status 查询
+type installStatus struct {
+	State   string `json:"state"`
+	Version string `json:"version"`
+	Message string `json:"message"`
+}
+
+var (
+	installStatusMutex sync.Mutex
+	lastInstallStatus  = installStatus{State: "idle"}
+)
+
+func setInstallStatus(state, version, message string) {
+	installStatusMutex.Lock()
+	defer installStatusMutex.Unlock()
+	lastInstallStatus = installStatus{State: state, Version: version, Message: message}
+}
+
+func currentInstallStatus() installStatus {
+	installStatusMutex.Lock()
+	defer installStatusMutex.Unlock()
+	return lastInstallStatus
+}
+
+// 查询安装状态：state 为 idle、running、succeeded 或 failed
+func installStatusHandler(w http.ResponseWriter, r *http.Request) {
+	w.Header().Set("Content-Type", "application/json")
+	w.Header().Set("Access-Control-Allow-Origin", "*")
+	w.Header().Set("Access-Control-Allow-Methods", "GET, OPTIONS")
+	w.Header().Set("Access-Control-Allow-Headers", "Content-Type")
+
+	if r.Method == "OPTIONS" {
+		w.WriteHeader(http.StatusOK)
+		return
+	}
+
+	json.NewEncoder(w).Encode(currentInstallStatus())
+}
+
+func supervisedByDesktop() bool {
+	return os.Getenv("PTNEXUS_SUPERVISOR") == "desktop"
+}
+
+// 同步文件后重启服务。桌面端请求桌面壳重启，失败时回滚文件并让桌面壳重新加载旧版本。
+func finishUpdate(mappings []DirMapping, backupDir string) error {
+	if !supervisedByDesktop() {
+		restartServices()
+		return nil
+	}
+	req := buildRecycleRequest(mappings, backupDir)
+	if len(req.Components) == 0 {
+		return nil
+	}
+	err := requestRecycle(req)
+	if err == nil {
+		return nil
+	}
+	log.Printf("桌面端重启服务失败: %v", err)
+	rollback(backupDir)
+	retry := recycleRequest{ID: newRecycleID(), Components: req.Components}
+	if retryErr := requestRecycle(retry); retryErr != nil {
+		log.Printf("回滚后重启服务失败: %v", retryErr)
+	}
+	return err
+}
+
+// 根据映射的来源目录确定需要重启的服务，记录可执行文件的 SHA-256 供桌面壳校验，
+// 以及这些文件在 backupDir 中的旧版本
+func buildRecycleRequest(mappings []DirMapping, backupDir string) recycleRequest {
+	req := recycleRequest{
+		ID:         newRecycleID(),
+		Components: []string{},
+		Files:      map[string]string{},
+		Previous:   map[string]map[string]string{},
+	}
+	seen := map[string]bool{}
+	add := func(names ...string) {
+		for _, name := range names {
+			if !seen[name] {
+				seen[name] = true
+				req.Components = append(req.Components, name)
+			}
+		}
+	}
+	for _, mapping := range mappings {
+		var owner string
+		switch strings.SplitN(filepath.ToSlash(mapping.Source), "/", 2)[0] {
+		case "server":
+			add("background_runner", "server")
+			owner = "server"
+		case "batch":
+			add("batch")
+			owner = "batch"
+		default:
+			continue
+		}
+		if !mapping.Executable {
+			continue
+		}
+		if hash, err := sha256File(filepath.Join(repoDir, mapping.Source)); err == nil {
+			req.Files[mapping.Target] = hash
+		}
+		backupPath := filepath.Join(backupDir, strings.TrimPrefix(mapping.Target, "/"))
+		if _, err := os.Stat(backupPath); err == nil {
+			if req.Previous[owner] == nil {
+				req.Previous[owner] = map[string]string{}
+			}
+			req.Previous[owner][mapping.Target] = backupPath
+		}
+	}
+	return req
+}
+
+// 清空 updates 目录，保留桌面端保存的上一版本（rollback）与更新锁
+func cleanUpdateDir() error {
+	entries, err := os.ReadDir(updateDir)
+	if os.IsNotExist(err) {
+		return nil
+	}
+	if err != nil {
+		return err
+	}
+	for _, entry := range entries {
+		if entry.Name() == "rollback" || entry.Name() == updateLockFile {
+			continue
+		}
+		if err := os.RemoveAll(filepath.Join(updateDir, entry.Name())); err != nil {
+			return err
+		}
+	}
+	return nil
+}
+
+// 拉取、安装期间持有更新锁，桌面壳见到锁文件时不清理 updates 目录（见 desktop/src-tauri/src/updatecache.rs）。
+// 拉取与安装可能同时进行，最后一个结束时才删除锁文件
+func acquireUpdateLock() {
+	updateLockMutex.Lock()
+	defer updateLockMutex.Unlock()
+	if updateLockHolders == 0 {
+		os.MkdirAll(updateDir, 0755)
+		os.WriteFile(filepath.Join(updateDir, updateLockFile), []byte(strconv.Itoa(os.Getpid())), 0644)
+	}
+	updateLockHolders++
+}
+
+func releaseUpdateLock() {
+	updateLockMutex.Lock()
+	defer updateLockMutex.Unlock()
+	updateLockHolders--
+	if updateLockHolders == 0 {
+		os.Remove(filepath.Join(updateDir, updateLockFile))
+	}
+}
+
+func newRecycleID() string {
+	return strconv.FormatInt(time.Now().UnixNano(), 10)
+}
+
+// 写入重启请求并等待桌面壳给出最终结果
+func requestRecycle(req recycleRequest) error {
+	resultPath := filepath.Join(updateDir, recycleResultFile)
+	os.Remove(resultPath)
+	data, err := json.Marshal(req)
+	if err != nil {
+		return err
+	}
+	requestPath := filepath.Join(updateDir, recycleRequestFile)
+	tmpPath := requestPath + ".tmp"
+	if err := os.WriteFile(tmpPath, data, 0644); err != nil {
+		return err
+	}
+	if err := os.Rename(tmpPath, requestPath); err != nil {
+		return err
+	}
+
+	lastState := ""
+	deadline := time.Now().Add(recycleTimeout)
+	for time.Now().Before(deadline) {
+		time.Sleep(2 * time.Second)
+		data, err := os.ReadFile(resultPath)
+		if err != nil {
+			continue
+		}
+		var result recycleResult
+		if json.Unmarshal(data, &result) != nil || result.ID != req.ID {
+			continue
+		}
+		if result.State != lastState {
+			log.Printf("桌面端重启服务: %s %s", result.State, result.Message)
+			lastState = result.State
+		}
+		switch result.State {
+		case "succeeded":
+			return nil
+		case "failed":
+			return fmt.Errorf("%s", result.Message)
+		}
+	}
+	return fmt.Errorf("等待桌面端重启服务超时")
+}
+
+func sha256File(path string) (string, error) {
+	file, err := os.Open(path)
+	if err != nil {
+		return "", err
+	}
+	defer file.Close()
+	hasher := sha256.New()
+	if _, err := io.Copy(hasher, file); err != nil {
+		return "", err
+	}
+	return hex.EncodeToString(hasher.Sum(nil)), nil
+}
+
 // 回滚
 func rollback(backupDir string) {
 	log.Println("回滚更新...")
@@ -1407,6 +1712,9 @@ func main() {
 	log.Println("监听端口:", updaterPort)
 	log.Printf("配置的更新源: %s", getUpdateSource())
 
+	// 上次异常退出时可能留下更新锁，启动时没有进行中的更新
+	os.Remove(filepath.Join(updateDir, updateLockFile))
+
 	// 检查定时配置
 	schedule := loadScheduleConfig()
 	if schedule.Enabled {
@@ -1424,6 +1732,7 @@ func main() {
 	http.HandleFunc("/update/check", checkUpdateHandler)
 	http.HandleFunc("/update/pull", pullUpdateHandler)
 	http.HandleFunc("/update/install", installUpdateHandler)
+	http.HandleFunc("/update/install/status", installStatusHandler)
 	http.HandleFunc("/update/changelog", getChangelogHandler)
 
 	// 代理路由
@@ -0,0 +1,133 @@
+package main
+
+import (
+	"encoding/json"
+	"os"
+	"path/filepath"
+	"reflect"
+	"testing"
+	"time"
+)
+
+func writeTestFile(t *testing.T, path, content string) {
+	t.Helper()
+	if err := os.MkdirAll(filepath.Dir(path), 0755); err != nil {
+		t.Fatal(err)
+	}
+	if err := os.WriteFile(path, []byte(content), 0644); err != nil {
+		t.Fatal(err)
+	}
+}
+
+// 模拟桌面壳：依次读取重启请求，并按 states 给出每个请求的最终结果
+func fakeDesktop(states ...string) <-chan recycleRequest {
+	requests := make(chan recycleRequest, len(states))
+	requestPath := filepath.Join(updateDir, recycleRequestFile)
+	resultPath := filepath.Join(updateDir, recycleResultFile)
+	go func() {
+		for _, state := range states {
+			var req recycleRequest
+			for {
+				data, err := os.ReadFile(requestPath)
+				if err == nil && json.Unmarshal(data, &req) == nil {
+					break
+				}
+				time.Sleep(50 * time.Millisecond)
+			}
+			os.Remove(requestPath)
+			requests <- req
+			data, _ := json.Marshal(recycleResult{ID: req.ID, State: state, Message: state})
+			os.WriteFile(resultPath, data, 0644)
+		}
+	}()
+	return requests
+}
+
+func useTestDirs(t *testing.T) (root string) {
+	root = t.TempDir()
+	oldUpdate, oldRepo := updateDir, repoDir
+	updateDir = filepath.Join(root, "updates")
+	repoDir = filepath.Join(root, "repo")
+	t.Cleanup(func() { updateDir, repoDir = oldUpdate, oldRepo })
+	if err := os.MkdirAll(updateDir, 0755); err != nil {
+		t.Fatal(err)
+	}
+	return root
+}
+
+func TestBuildRecycleRequestMapsSourcesToServices(t *testing.T) {
+	root := useTestDirs(t)
+	backupDir := filepath.Join(root, "backup")
+	serverTarget := filepath.Join(root, "app", "server", "pt-nexus-server")
+	batchTarget := filepath.Join(root, "app", "batch", "batch")
+	writeTestFile(t, filepath.Join(repoDir, "server", "pt-nexus-server"), "new server")
+	writeTestFile(t, filepath.Join(repoDir, "batch", "batch"), "new batch")
+	// 只有 server 的旧版本被备份过
+	writeTestFile(t, filepath.Join(backupDir, serverTarget), "old server")
+
+	req := buildRecycleRequest([]DirMapping{
+		{Source: "server/pt-nexus-server", Target: serverTarget, Executable: true},
+		{Source: "server/templates", Target: filepath.Join(root, "app", "server", "templates")},
+		{Source: "batch/batch", Target: batchTarget, Executable: true},
+		{Source: "webui/dist", Target: filepath.Join(root, "app", "dist")},
+	}, backupDir)
+
+	if want := []string{"background_runner", "server", "batch"}; !reflect.DeepEqual(req.Components, want) {
+		t.Fatalf("components = %v, want %v", req.Components, want)
+	}
+	serverHash, _ := sha256File(filepath.Join(repoDir, "server", "pt-nexus-server"))
+	batchHash, _ := sha256File(filepath.Join(repoDir, "batch", "batch"))
+	if want := map[string]string{serverTarget: serverHash, batchTarget: batchHash}; !reflect.DeepEqual(req.Files, want) {
+		t.Fatalf("files = %v, want %v", req.Files, want)
+	}
+	wantPrevious := map[string]map[string]string{
+		"server": {serverTarget: filepath.Join(backupDir, serverTarget)},
+	}
+	if !reflect.DeepEqual(req.Previous, wantPrevious) {
+		t.Fatalf("previous = %v, want %v", req.Previous, wantPrevious)
+	}
+}
+
+func TestFinishUpdateSkipsRecycleWithoutServiceChanges(t *testing.T) {
+	root := useTestDirs(t)
+	t.Setenv("PTNEXUS_SUPERVISOR", "desktop")
+
+	err := finishUpdate([]DirMapping{{Source: "webui/dist", Target: filepath.Join(root, "app", "dist")}}, filepath.Join(root, "backup"))
+	if err != nil {
+		t.Fatalf("finishUpdate: %v", err)
+	}
+	if _, err := os.Stat(filepath.Join(updateDir, recycleRequestFile)); !os.IsNotExist(err) {
+		t.Fatalf("recycle request should not be written, stat err = %v", err)
+	}
+}
+
+func TestFinishUpdateRollsBackAndReloadsWhenRecycleFails(t *testing.T) {
+	root := useTestDirs(t)
+	t.Setenv("PTNEXUS_SUPERVISOR", "desktop")
+	backupDir := filepath.Join(root, "backup")
+	target := filepath.Join(root, "app", "batch", "batch")
+	writeTestFile(t, filepath.Join(repoDir, "batch", "batch"), "new batch")
+	writeTestFile(t, target, "new batch")
+	writeTestFile(t, filepath.Join(backupDir, target), "old batch")
+
+	requests := fakeDesktop("failed", "succeeded")
+	err := finishUpdate([]DirMapping{{Source: "batch/batch", Target: target, Executable: true}}, backupDir)
+	if err == nil || err.Error() != "failed" {
+		t.Fatalf("finishUpdate error = %v, want the desktop failure", err)
+	}
+
+	first, retry := <-requests, <-requests
+	if first.ID == retry.ID {
+		t.Fatalf("retry should use a new request id, both are %s", first.ID)
+	}
+	if !reflect.DeepEqual(retry.Components, first.Components) {
+		t.Fatalf("retry components = %v, want %v", retry.Components, first.Components)
+	}
+	// 回滚后的重启只重新加载旧版本，不再携带新文件的校验值
+	if len(retry.Files) != 0 || len(retry.Previous) != 0 {
+		t.Fatalf("retry should not carry files or previous versions: %+v", retry)
+	}
+	if data, _ := os.ReadFile(target); string(data) != "old batch" {
+		t.Fatalf("target = %q, want the backed up version", data)
+	}
+}
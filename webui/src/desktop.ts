@@ -0,0 +1,21 @@
+import type { DesktopCommandError, DesktopInfo } from '@/types'
+
+// 桌面壳在页面脚本执行前注入 __PTNEXUS_DESKTOP__，浏览器中访问时不存在
+export const desktopInfo = (window as any).__PTNEXUS_DESKTOP__ as DesktopInfo | undefined
+
+export const desktopInvoke = desktopInfo
+  ? ((window as any).__TAURI_INTERNALS__?.invoke as
+      | ((cmd: string, args?: Record<string, unknown>) => Promise<any>)
+      | undefined)
+  : undefined
+
+export const isDesktopCommandError = (error: unknown): error is DesktopCommandError =>
+  typeof (error as DesktopCommandError | null)?.code === 'string' &&
+  typeof (error as DesktopCommandError | null)?.message === 'string'
+
+/** 命令失败时桌面壳返回 { code, message, details }，取其中的说明；其他错误原样转成文字 */
+export const desktopErrorMessage = (error: unknown, fallback = '操作失败') => {
+  if (isDesktopCommandError(error)) return error.message || fallback
+  if (typeof error === 'string') return error || fallback
+  return (error as Error | null)?.message || fallback
+}
@@ -56,6 +56,9 @@ window.fetch = async (input: RequestInfo | URL, init?: RequestInit): Promise<Res
 
 // Axios 全局拦截：为所有请求附加 Bearer Token，并处理 401
 axios.interceptors.request.use((config) => {
+  // 桌面端冷启动时后端尚未就绪，请求一直挂起，避免各页面报错；服务就绪后桌面壳会导航到运行时页面
+  if ((window as any).__PTNEXUS_DESKTOP__?.coldStart) return new Promise<never>(() => {})
+
   const token = localStorage.getItem('token')
   if (token) {
     config.headers = config.headers || {}
@@ -66,6 +66,8 @@ const router = createRouter({
 router.beforeEach(async (to, _from, next) => {
   const token = localStorage.getItem('token')
   if (whiteList.includes(to.path)) return next()
+  // 桌面端冷启动时显示的是缓存的界面，与运行时页面不同源、读不到 token；服务就绪后会重新加载真正的页面
+  if ((window as any).__PTNEXUS_DESKTOP__?.coldStart) return next()
   if (!token) {
     // 未登录：直接去 login
     return next({ path: '/login', query: { redirect: to.fullPath } })
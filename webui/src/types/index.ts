@@ -33,4 +33,37 @@ export interface Downloader {
   id: string
   name: string
   enabled: boolean
-}
\ No newline at end of file
+}
+
+/** 桌面壳注入的 window.__PTNEXUS_DESKTOP__，字段见 desktop/src-tauri/src/desktopinfo.rs */
+export interface DesktopInfo {
+  readonly version: string
+  readonly platform: 'windows' | 'macos' | 'linux'
+  readonly features: readonly ('notifications' | 'file-dialogs' | 'lan-mode')[]
+  /** 桌面壳仍在注入的页面脚本 id */
+  readonly injections: readonly string[]
+  /** 当前页面是桌面壳缓存的界面，后端尚未就绪 */
+  readonly coldStart?: boolean
+}
+
+/** 桌面壳命令失败时的错误，code 见 desktop/src-tauri/src/commanderror.rs */
+export interface DesktopCommandError {
+  code: string
+  message: string
+  details?: unknown
+}
+
+/** 桌面壳错误提示上的操作，通过 `invoke_error_action` 执行 */
+export interface DesktopErrorAction {
+  id:
+    | 'open_log'
+    | 'open_logs_dir'
+    | 'retry'
+    | 'copy_details'
+    | 'remove_quarantine'
+    | 'verify_runtime'
+    | 'open_data_dir'
+    | 'reinstall_python'
+  label: string
+  context?: string
+}